@@ -0,0 +1,8 @@
+#include <stdio.h>
+
+int main(int argc, char *argv[]) {
+    for (int i = 1; i < argc; i++) {
+        printf("argv[%d] = %s\n", i, argv[i]);
+    }
+    return argc;
+}
@@ -0,0 +1,21 @@
+#include <stdio.h>
+
+/* Written in assembly, so that it has a symbol but no debug info. Stores 0 at p and returns 0. */
+int store_zero(int *p);
+asm(".text\n"
+    ".globl store_zero\n"
+    ".type store_zero, @function\n"
+    "store_zero:\n"
+    "    push %rbp\n"
+    "    mov %rsp, %rbp\n"
+    "    movl $0, (%rdi)\n"
+    "    xor %eax, %eax\n"
+    "    pop %rbp\n"
+    "    ret\n"
+    ".size store_zero, .-store_zero\n");
+
+int main() {
+    printf("about to fault\n");
+    int result = store_zero(NULL);
+    return result;
+}
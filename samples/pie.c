@@ -0,0 +1,13 @@
+#include <stdio.h>
+
+int counter = 0;
+
+void tick(void) {
+    counter++;
+}
+
+int main() {
+    tick();
+    printf("counter = %d\n", counter);
+    return 0;
+}
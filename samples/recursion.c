@@ -0,0 +1,13 @@
+#include <stdio.h>
+
+int factorial(int n) {
+    if (n <= 1) {
+        return 1;
+    }
+    return n * factorial(n - 1);
+}
+
+int main() {
+    printf("%d\n", factorial(4));
+    return 0;
+}
@@ -0,0 +1,22 @@
+#include <stdio.h>
+
+typedef unsigned int counter_t;
+typedef counter_t ticks_t;
+
+struct point {
+    int x;
+    int y;
+};
+
+typedef struct point point_t;
+
+struct point origin = {0, 0};
+struct point *cursor = &origin;
+int scores[4] = {1, 2, 3, 4};
+point_t corner = {3, 4};
+ticks_t elapsed = 7;
+
+int main() {
+    printf("%d\n", cursor->x + scores[2] + corner.y + (int) elapsed);
+    return 0;
+}
@@ -0,0 +1,219 @@
+//! Lookups by address that backtraces and stepping make over and over: an index of address
+//! ranges (built up front or on first use), and a small cache of recent answers.
+
+use std::cell::RefCell;
+use std::cmp::Ordering;
+
+/// The address ranges of a list of functions, sorted by start address so that the function
+/// containing an address is found with a binary search rather than a scan. Each range comes with
+/// a key saying where to find its function.
+pub struct RangeIndex<K> {
+    /// (start, end, position in the original list, key), by start address
+    ranges: Vec<(usize, usize, usize, K)>,
+    /// The highest end of ranges[..=i], which says when a search backwards can stop
+    max_end: Vec<usize>,
+}
+
+impl<K: Copy> RangeIndex<K> {
+    /// Indexes [start, end) ranges, given in their original order. Empty ranges are left out.
+    pub fn new<I: IntoIterator<Item = (usize, usize, K)>>(ranges: I) -> RangeIndex<K> {
+        let mut ranges: Vec<(usize, usize, usize, K)> = ranges
+            .into_iter()
+            .enumerate()
+            .filter(|(_, (start, end, _))| start < end)
+            .map(|(idx, (start, end, key))| (start, end, idx, key))
+            .collect();
+        ranges.sort_by_key(|&(start, end, idx, _)| (start, end, idx));
+        let mut highest = 0;
+        let max_end = ranges
+            .iter()
+            .map(|&(_, end, _, _)| {
+                highest = highest.max(end);
+                highest
+            })
+            .collect();
+        RangeIndex { ranges, max_end }
+    }
+
+    /// The key of the first range (in the original order) containing `addr`, which is what a
+    /// linear scan would find even where ranges overlap.
+    pub fn find(&self, addr: usize) -> Option<K> {
+        // The number of ranges starting at or before addr
+        let mut idx = match self.ranges.binary_search_by(|&(start, _, _, _)| {
+            if start <= addr {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            }
+        }) {
+            Ok(idx) | Err(idx) => idx,
+        };
+        let mut found: Option<(usize, K)> = None;
+        while idx > 0 && self.max_end[idx - 1] > addr {
+            idx -= 1;
+            let (_, end, position, key) = self.ranges[idx];
+            if addr < end && found.map_or(true, |(found, _)| position < found) {
+                found = Some((position, key));
+            }
+        }
+        found.map(|(_, key)| key)
+    }
+}
+
+/// A RangeIndex built the first time it is searched, for lookups by address that a session
+/// which only sets breakpoints by name never makes.
+pub struct LazyRangeIndex<K> {
+    index: RefCell<Option<RangeIndex<K>>>,
+}
+
+impl<K: Copy> LazyRangeIndex<K> {
+    pub fn new() -> LazyRangeIndex<K> {
+        LazyRangeIndex {
+            index: RefCell::new(None),
+        }
+    }
+
+    /// Like RangeIndex::find, indexing what `ranges` gives on the first search.
+    pub fn find_with<I, F>(&self, addr: usize, ranges: F) -> Option<K>
+    where
+        I: IntoIterator<Item = (usize, usize, K)>,
+        F: FnOnce() -> I,
+    {
+        self.index
+            .borrow_mut()
+            .get_or_insert_with(|| RangeIndex::new(ranges()))
+            .find(addr)
+    }
+
+    /// Whether a search has built the index yet.
+    pub fn is_built(&self) -> bool {
+        self.index.borrow().is_some()
+    }
+}
+
+/// How many addresses an AddrCache remembers.
+const CACHE_SIZE: usize = 64;
+
+/// Remembers the answers for the last few addresses looked up, most recent first.
+pub struct AddrCache<T> {
+    entries: RefCell<Vec<(usize, T)>>,
+}
+
+impl<T: Clone> AddrCache<T> {
+    pub fn new() -> AddrCache<T> {
+        AddrCache {
+            entries: RefCell::new(Vec::with_capacity(CACHE_SIZE)),
+        }
+    }
+
+    /// The answer for `addr`: the remembered one, or else what `lookup` says, which is then
+    /// remembered in place of the least recently used address.
+    pub fn get_or_insert_with<F: FnOnce() -> T>(&self, addr: usize, lookup: F) -> T {
+        let mut entries = self.entries.borrow_mut();
+        if let Some(idx) = entries.iter().position(|(cached, _)| *cached == addr) {
+            let entry = entries.remove(idx);
+            let value = entry.1.clone();
+            entries.insert(0, entry);
+            return value;
+        }
+        let value = lookup();
+        entries.truncate(CACHE_SIZE - 1);
+        entries.insert(0, (addr, value.clone()));
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    /// What RangeIndex::find stands in for: the first range in the list containing `addr`.
+    fn scan(ranges: &[(usize, usize, usize)], addr: usize) -> Option<usize> {
+        ranges
+            .iter()
+            .find(|&&(start, end, _)| start <= addr && addr < end)
+            .map(|&(_, _, key)| key)
+    }
+
+    #[test]
+    fn finds_what_a_scan_would() {
+        // Out of order, overlapping (an inlined copy inside its caller, a duplicate listed
+        // later), nested, empty and adjacent ranges
+        let ranges = [
+            (0x1100, 0x1200, 0),
+            (0x1000, 0x1400, 1),
+            (0x1100, 0x1200, 2),
+            (0x1180, 0x1190, 3),
+            (0x1400, 0x1400, 4),
+            (0x1400, 0x1480, 5),
+            (0x2000, 0x2100, 6),
+        ];
+        let index = RangeIndex::new(ranges.iter().cloned());
+        for addr in 0xff0..0x2110 {
+            assert_eq!(index.find(addr), scan(&ranges, addr), "at {:#x}", addr);
+        }
+    }
+
+    #[test]
+    fn lazy_index_is_built_on_first_search() {
+        let index = LazyRangeIndex::new();
+        assert!(!index.is_built());
+        assert_eq!(index.find_with(0x1010, || vec![(0x1000, 0x1100, 7)]), Some(7));
+        assert!(index.is_built());
+        // Later searches use what was indexed the first time
+        assert_eq!(index.find_with(0x2010, || vec![(0x2000, 0x2100, 8)]), None);
+    }
+
+    #[test]
+    fn lookups_in_a_large_program_are_quick() {
+        // A program the size of a large C++ binary: 100,000 functions of 64 bytes, with every
+        // 10th one followed by an inlined copy inside it. A scan for each of 100,000 addresses
+        // would make billions of comparisons; the index should take a fraction of a second even
+        // in a debug build.
+        let count = 100_000;
+        let ranges: Vec<(usize, usize, usize)> = (0..count)
+            .flat_map(|idx| {
+                let start = 0x400000 + idx * 64;
+                let mut ranges = vec![(start, start + 64, idx)];
+                if idx % 10 == 0 {
+                    ranges.push((start + 16, start + 32, count + idx));
+                }
+                ranges
+            })
+            .collect();
+        let started = Instant::now();
+        let index = RangeIndex::new(ranges.iter().cloned());
+        let found = (0..count)
+            .filter(|idx| index.find(0x400000 + idx * 64 + 20).is_some())
+            .count();
+        assert_eq!(found, count);
+        assert!(started.elapsed() < Duration::from_secs(2), "took {:?}", started.elapsed());
+        for &idx in [0, 1, 10, count / 2, count - 1].iter() {
+            let addr = 0x400000 + idx * 64 + 20;
+            assert_eq!(index.find(addr), scan(&ranges, addr));
+        }
+    }
+
+    #[test]
+    fn cache_forgets_the_least_recently_used_address() {
+        let cache = AddrCache::new();
+        let lookups = RefCell::new(0);
+        let lookup = |addr: usize| {
+            *lookups.borrow_mut() += 1;
+            addr * 2
+        };
+        for addr in 0..CACHE_SIZE {
+            assert_eq!(cache.get_or_insert_with(addr, || lookup(addr)), addr * 2);
+        }
+        // Using address 0 again makes 1 the least recently used, which the next new address
+        // then pushes out
+        assert_eq!(cache.get_or_insert_with(0, || lookup(0)), 0);
+        cache.get_or_insert_with(CACHE_SIZE, || lookup(CACHE_SIZE));
+        assert_eq!(*lookups.borrow(), CACHE_SIZE + 1);
+        cache.get_or_insert_with(0, || lookup(0));
+        assert_eq!(*lookups.borrow(), CACHE_SIZE + 1);
+        cache.get_or_insert_with(1, || lookup(1));
+        assert_eq!(*lookups.borrow(), CACHE_SIZE + 2);
+    }
+}
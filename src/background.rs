@@ -0,0 +1,126 @@
+//! `run &` and `continue &`: a thread that waits for the inferior to stop while the prompt stays
+//! usable, and announces the stop above the line being typed.
+
+use nix::sys::signal::Signal;
+use nix::unistd::Pid;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// The line being typed at the prompt, kept up to date by the prompt's helper so that a notice
+/// can draw it again below itself. None while there is no prompt up.
+#[derive(Clone, Default)]
+pub struct TypedLine(Arc<Mutex<Option<String>>>);
+
+impl TypedLine {
+    /// Notes that the prompt has come up, with nothing typed yet, or has gone.
+    pub fn set_prompting(&self, prompting: bool) {
+        if let Ok(mut line) = self.0.lock() {
+            *line = if prompting { Some(String::new()) } else { None };
+        }
+    }
+
+    /// Records the line as it stands after an edit.
+    pub fn update(&self, typed: &str) {
+        if let Ok(mut line) = self.0.lock() {
+            if let Some(line) = line.as_mut() {
+                line.clear();
+                line.push_str(typed);
+            }
+        }
+    }
+
+    fn get(&self) -> Option<String> {
+        self.0.lock().ok().and_then(|line| line.clone())
+    }
+}
+
+pub struct Watcher {
+    /// Receives once the inferior has stopped or exited
+    stopped: Receiver<()>,
+    /// Set when the watcher is dropped, so that a stop we bring about ourselves (by killing the
+    /// inferior, say) isn't announced
+    quiet: Arc<AtomicBool>,
+}
+
+impl Watcher {
+    /// Starts waiting for `pid` to stop. The stop is only observed, not reaped: only the thread
+    /// that traces the inferior may ptrace it, so collecting the stop with waitpid and reading
+    /// its registers is left to the main thread. With `prompt`, the stop is announced above it.
+    pub fn start(pid: Pid, prompt: Option<(&'static str, TypedLine)>) -> Watcher {
+        let (sender, stopped) = mpsc::channel();
+        let quiet = Arc::new(AtomicBool::new(false));
+        let watcher_quiet = quiet.clone();
+        thread::spawn(move || {
+            let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+            let result = loop {
+                let result = unsafe {
+                    libc::waitid(
+                        libc::P_PID,
+                        pid.as_raw() as libc::id_t,
+                        &mut info,
+                        libc::WEXITED | libc::WSTOPPED | libc::WNOWAIT,
+                    )
+                };
+                if result < 0 && io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                break result;
+            };
+            if result == 0 && !watcher_quiet.load(Ordering::SeqCst) {
+                if let Some((prompt, typed)) = prompt {
+                    announce(&describe(pid, &info), prompt, &typed);
+                }
+            }
+            let _ = sender.send(());
+        });
+        Watcher { stopped, quiet }
+    }
+
+    /// True once the inferior has stopped or exited.
+    pub fn has_stopped(&self) -> bool {
+        match self.stopped.try_recv() {
+            Ok(()) | Err(TryRecvError::Disconnected) => true,
+            Err(TryRecvError::Empty) => false,
+        }
+    }
+
+    /// Blocks until the inferior has stopped or exited, without announcing it: the caller
+    /// reports the stop itself.
+    pub fn wait(self) {
+        self.quiet.store(true, Ordering::SeqCst);
+        let _ = self.stopped.recv();
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.quiet.store(true, Ordering::SeqCst);
+    }
+}
+
+/// One line about a stop, from what waitid says about it. The full stop report comes once the
+/// main thread has collected it.
+fn describe(pid: Pid, info: &libc::siginfo_t) -> String {
+    let status = unsafe { info.si_status() };
+    let signal = Signal::from_c_int(status)
+        .map(|signal| signal.to_string())
+        .unwrap_or_else(|_| format!("signal {}", status));
+    match info.si_code {
+        libc::CLD_EXITED => format!("[Process {} exited with code {}]", pid, status),
+        libc::CLD_KILLED | libc::CLD_DUMPED => format!("[Process {} killed by {}]", pid, signal),
+        _ => format!("[Process {} stopped by {}]", pid, signal),
+    }
+}
+
+/// Prints `text` over the prompt's line, then the prompt and what had been typed below it. The
+/// terminal is in raw mode while the prompt is up, so lines end in \r\n.
+fn announce(text: &str, prompt: &str, typed: &TypedLine) {
+    match typed.get() {
+        Some(typed) => print!("\r\x1b[K{}\r\n{}{}", text, prompt, typed),
+        None => println!("{}", text),
+    }
+    let _ = io::stdout().flush();
+}
@@ -0,0 +1,180 @@
+//! The user's breakpoints: what each one was set on, where that resolved to, and the state that
+//! decides whether a hit stops the program. They live in the Session and outlast each inferior;
+//! the int3 bytes themselves are the Inferior's business (`arm` and `disarm`).
+
+use crate::session::Location;
+
+/// A breakpoint set by the user. One breakpoint can have several addresses, e.g. when a function
+/// name matches static functions in several files.
+#[derive(Debug, Clone)]
+pub struct Breakpoint {
+    pub id: BreakpointId,
+    /// What the user asked for, kept so that pending breakpoints can be resolved later
+    pub location: Location,
+    /// Where the breakpoint is, in address order. Empty while it is pending (the location doesn't
+    /// resolve yet).
+    pub addresses: Vec<usize>,
+    /// Disabled breakpoints are kept, but not written into the program
+    pub enabled: bool,
+    /// Whether the breakpoint disables itself after stopping the program
+    pub mode: EnableMode,
+    /// `condition`: an expression that must be nonzero for a hit to count
+    pub condition: Option<String>,
+    /// How many more hits to go past without stopping (`ignore`)
+    pub ignore_count: usize,
+    /// How many times the program has reached the breakpoint with its condition true, ignored
+    /// hits included
+    pub hits: usize,
+    /// `dprintf`: the format string and arguments to print at each hit, instead of stopping
+    pub dprintf: Option<String>,
+}
+
+/// What becomes of an enabled breakpoint when it stops the program.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EnableMode {
+    /// It stays enabled
+    Keep,
+    /// `enable once`: it disables itself after the next stop
+    Once,
+    /// `enable count`: it disables itself after this many more stops
+    Count(usize),
+}
+
+/// Identifies a breakpoint. Ids start at 1, in the order breakpoints were set, and aren't reused
+/// when breakpoints are deleted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BreakpointId(pub usize);
+
+/// Every breakpoint the user has set, in id order. This is the source of truth across kills and
+/// restarts; each new inferior has the enabled, resolved ones armed.
+#[derive(Debug)]
+pub struct BreakpointTable {
+    breakpoints: Vec<Breakpoint>,
+    next_id: usize,
+}
+
+impl Default for BreakpointTable {
+    fn default() -> BreakpointTable {
+        BreakpointTable {
+            breakpoints: Vec::new(),
+            next_id: 1,
+        }
+    }
+}
+
+impl BreakpointTable {
+    /// Adds an enabled breakpoint on `location`, which resolved to `addresses` (none if it is
+    /// pending), and returns its id.
+    pub fn add(&mut self, location: Location, addresses: Vec<usize>) -> BreakpointId {
+        let id = BreakpointId(self.next_id);
+        self.next_id += 1;
+        self.breakpoints.push(Breakpoint {
+            id,
+            location,
+            addresses,
+            enabled: true,
+            mode: EnableMode::Keep,
+            condition: None,
+            ignore_count: 0,
+            hits: 0,
+            dprintf: None,
+        });
+        id
+    }
+
+    /// Removes the breakpoints with these ids, returning the addresses they were at.
+    pub fn remove(&mut self, ids: &[BreakpointId]) -> Vec<usize> {
+        let addresses = self
+            .breakpoints
+            .iter()
+            .filter(|bp| ids.contains(&bp.id))
+            .flat_map(|bp| bp.addresses.iter().cloned())
+            .collect();
+        self.breakpoints.retain(|bp| !ids.contains(&bp.id));
+        addresses
+    }
+
+    pub fn all(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    pub fn all_mut(&mut self) -> &mut [Breakpoint] {
+        &mut self.breakpoints
+    }
+
+    pub fn get(&self, id: BreakpointId) -> Option<&Breakpoint> {
+        self.breakpoints.iter().find(|bp| bp.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: BreakpointId) -> Option<&mut Breakpoint> {
+        self.breakpoints.iter_mut().find(|bp| bp.id == id)
+    }
+
+    /// The first breakpoint with `addr` among its addresses, enabled or not.
+    pub fn at(&self, addr: usize) -> Option<&Breakpoint> {
+        self.breakpoints.iter().find(|bp| bp.addresses.contains(&addr))
+    }
+
+    /// The first enabled breakpoint with `addr` among its addresses: the one a hit there counts
+    /// for.
+    pub fn enabled_at(&self, addr: usize) -> Option<&Breakpoint> {
+        self.breakpoints
+            .iter()
+            .find(|bp| bp.enabled && bp.addresses.contains(&addr))
+    }
+
+    /// The id of the breakpoint added most recently, even if it has since been removed.
+    pub fn last_id(&self) -> Option<BreakpointId> {
+        match self.next_id {
+            1 => None,
+            next => Some(BreakpointId(next - 1)),
+        }
+    }
+
+    /// The addresses the inferior should have armed for the user: those of enabled breakpoints.
+    pub fn armed_addresses(&self) -> impl Iterator<Item = usize> + '_ {
+        self.breakpoints
+            .iter()
+            .filter(|bp| bp.enabled)
+            .flat_map(|bp| bp.addresses.iter().cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn function(name: &str) -> Location {
+        Location::Function(name.to_string())
+    }
+
+    #[test]
+    fn ids_are_not_reused() {
+        let mut table = BreakpointTable::default();
+        assert_eq!(table.last_id(), None);
+        let first = table.add(function("main"), vec![0x1000]);
+        let second = table.add(function("func1"), vec![0x2000]);
+        assert_eq!((first, second), (BreakpointId(1), BreakpointId(2)));
+        assert_eq!(table.remove(&[second]), vec![0x2000]);
+        assert_eq!(table.last_id(), Some(second));
+        assert_eq!(table.add(function("func2"), vec![]), BreakpointId(3));
+        let ids: Vec<usize> = table.all().iter().map(|bp| bp.id.0).collect();
+        assert_eq!(ids, vec![1, 3]);
+    }
+
+    #[test]
+    fn disabled_breakpoints_are_not_armed() {
+        let mut table = BreakpointTable::default();
+        let static_func = table.add(function("helper"), vec![0x1000, 0x3000]);
+        let pending = table.add(function("in_a_library"), vec![]);
+        let line = table.add(Location::Line(None, 12), vec![0x2000]);
+        assert_eq!(table.armed_addresses().collect::<Vec<_>>(), vec![0x1000, 0x3000, 0x2000]);
+        table.get_mut(static_func).unwrap().enabled = false;
+        assert_eq!(table.armed_addresses().collect::<Vec<_>>(), vec![0x2000]);
+        // A hit at a disabled breakpoint's address counts for none
+        assert_eq!(table.at(0x3000).map(|bp| bp.id), Some(static_func));
+        assert!(table.enabled_at(0x3000).is_none());
+        assert_eq!(table.enabled_at(0x2000).map(|bp| bp.id), Some(line));
+        assert!(table.get(pending).unwrap().addresses.is_empty());
+    }
+}
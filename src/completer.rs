@@ -0,0 +1,169 @@
+//! Tab completion for the (deet) prompt.
+
+use crate::background::TypedLine;
+use crate::debugger_command::{command_names, FUNCTION_COMMANDS, LIST_COMMANDS, SUBCOMMANDS};
+use crate::dwarf_data::DwarfData;
+use rustyline::completion::{Completer, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Helper};
+use std::rc::Rc;
+
+pub struct DeetHelper {
+    /// Debug info of the target, used to complete function and file names
+    debug_data: Rc<DwarfData>,
+    /// Updated as the line is edited, for notices from a program running in the background
+    typed_line: TypedLine,
+}
+
+impl DeetHelper {
+    pub fn new(debug_data: Rc<DwarfData>, typed_line: TypedLine) -> DeetHelper {
+        DeetHelper {
+            debug_data,
+            typed_line,
+        }
+    }
+
+    /// Returns the index in `line` where the word being completed starts, along with the possible
+    /// completions of that word.
+    pub fn candidates(&self, line: &str) -> (usize, Vec<String>) {
+        let word_start = line
+            .rfind(char::is_whitespace)
+            .map(|idx| idx + 1)
+            .unwrap_or(0);
+        let prefix = &line[word_start..];
+        let previous: Vec<&str> = line[..word_start].split_whitespace().collect();
+        if let [command] = previous.as_slice() {
+            let list = LIST_COMMANDS.contains(command);
+            if list || FUNCTION_COMMANDS.contains(command) {
+                return (word_start, self.location_candidates(prefix, list));
+            }
+        }
+        let words: Vec<&str> = match previous.as_slice() {
+            [] => command_names().collect(),
+            [command] => SUBCOMMANDS
+                .iter()
+                .find(|(name, _)| name == command)
+                .map(|(_, subcommands)| subcommands.to_vec())
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        let matches = words
+            .iter()
+            .filter(|word| word.starts_with(prefix))
+            .map(|word| word.to_string())
+            .collect();
+        (word_start, matches)
+    }
+
+    /// Completes a location: a word that looks like a path (it has a `.` or `/`) is a source
+    /// file, and anything else a function; `list` offers both either way. After `file:` only a
+    /// line number can follow, so nothing is offered.
+    fn location_candidates(&self, prefix: &str, list: bool) -> Vec<String> {
+        if prefix.contains(':') {
+            return Vec::new();
+        }
+        if list {
+            let mut names = self.debug_data.files_with_prefix(prefix);
+            names.extend(self.debug_data.functions_with_prefix(prefix));
+            names.sort();
+            names.dedup();
+            names
+        } else if prefix.contains('.') || prefix.contains('/') {
+            self.debug_data.files_with_prefix(prefix)
+        } else {
+            self.debug_data.functions_with_prefix(prefix)
+        }
+    }
+}
+
+impl Completer for DeetHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let (start, matches) = self.candidates(&line[..pos]);
+        Ok((
+            start,
+            matches
+                .into_iter()
+                .map(|word| Pair {
+                    display: word.clone(),
+                    replacement: word,
+                })
+                .collect(),
+        ))
+    }
+}
+
+impl Hinter for DeetHelper {
+    /// Offers no hints; called after every edit, it is where the typed line is recorded.
+    fn hint(&self, line: &str, _pos: usize, _ctx: &Context<'_>) -> Option<String> {
+        self.typed_line.update(line);
+        None
+    }
+}
+
+impl Highlighter for DeetHelper {}
+
+impl Validator for DeetHelper {}
+
+impl Helper for DeetHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Completion over the debug info of samples/function_calls.c, which defines func1, func2,
+    /// func3 and main.
+    fn helper() -> DeetHelper {
+        let path = format!("{}/samples/function_calls", env!("CARGO_MANIFEST_DIR"));
+        let debug_data = DwarfData::from_file(&path).unwrap();
+        DeetHelper::new(Rc::new(debug_data), TypedLine::default())
+    }
+
+    #[test]
+    fn command_names_and_subcommands() {
+        let helper = helper();
+        let (start, all) = helper.candidates("");
+        assert_eq!(start, 0);
+        assert_eq!(all.len(), command_names().count());
+        assert_eq!(helper.candidates("cont"), (0, vec!["continue".to_string()]));
+        assert_eq!(helper.candidates("info br"), (5, vec!["breakpoints".to_string()]));
+        assert_eq!(helper.candidates("enable  o"), (8, vec!["once".to_string()]));
+        // Nothing past the subcommand, and nothing for commands without subcommands
+        assert!(helper.candidates("info breakpoints ").1.is_empty());
+        assert!(helper.candidates("print g").1.is_empty());
+    }
+
+    #[test]
+    fn break_locations() {
+        let helper = helper();
+        let (start, names) = helper.candidates("break func");
+        assert_eq!(start, 6);
+        assert_eq!(names, vec!["func1", "func2", "func3"]);
+        assert_eq!(helper.candidates("b ma").1, vec!["main"]);
+        // A word with a dot or slash in it is a file
+        assert_eq!(helper.candidates("break function_calls.").1, vec!["function_calls.c"]);
+        assert_eq!(helper.candidates("break samples/").1, vec!["samples/function_calls.c"]);
+        assert!(helper.candidates("break nothing.").1.is_empty());
+        // After `file:` only a line number can follow
+        assert!(helper.candidates("break function_calls.c:").1.is_empty());
+        assert!(helper.candidates("break function_calls.c:1").1.is_empty());
+    }
+
+    #[test]
+    fn list_locations() {
+        let helper = helper();
+        // Both files and functions, whatever the word looks like
+        let names = helper.candidates("list func").1;
+        assert_eq!(names, vec!["func1", "func2", "func3", "function_calls.c"]);
+        assert_eq!(helper.candidates("l function_calls.").1, vec!["function_calls.c"]);
+        assert!(helper.candidates("list function_calls.c:").1.is_empty());
+    }
+}
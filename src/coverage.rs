@@ -0,0 +1,119 @@
+//! `coverage`: which source lines of the program have run, found with a breakpoint of our own on
+//! every line that is taken out the first time it is hit.
+
+use std::collections::{BTreeMap, HashMap};
+
+/// A line with code, and the addresses its statements start at.
+struct CoveredLine {
+    addresses: Vec<usize>,
+    executed: bool,
+}
+
+/// How many of a file's lines have run, for `coverage report`.
+pub struct FileCoverage {
+    pub file: String,
+    pub executed: usize,
+    pub total: usize,
+}
+
+/// Addresses are as in the debug info, without the load bias.
+pub struct Coverage {
+    lines: BTreeMap<String, BTreeMap<usize, CoveredLine>>,
+    /// The addresses with a breakpoint still to be hit, and the file and line each belongs to
+    pending: HashMap<usize, (String, usize)>,
+    /// An address that `run_to` is running to, so the stop there is not coverage's own
+    held: Option<usize>,
+}
+
+impl Coverage {
+    /// Starts with none of the lines run, from the statement rows of the line table as
+    /// (file, line, address).
+    pub fn new<'a, I>(rows: I) -> Coverage
+    where
+        I: IntoIterator<Item = (&'a str, usize, usize)>,
+    {
+        let mut lines: BTreeMap<String, BTreeMap<usize, CoveredLine>> = BTreeMap::new();
+        let mut pending = HashMap::new();
+        for (file, number, address) in rows {
+            let line = lines
+                .entry(file.to_string())
+                .or_default()
+                .entry(number)
+                .or_insert_with(|| CoveredLine {
+                    addresses: Vec::new(),
+                    executed: false,
+                });
+            if !line.addresses.contains(&address) {
+                line.addresses.push(address);
+                pending.insert(address, (file.to_string(), number));
+            }
+        }
+        Coverage {
+            lines,
+            pending,
+            held: None,
+        }
+    }
+
+    /// The addresses that still need a breakpoint.
+    pub fn pending(&self) -> impl Iterator<Item = usize> + '_ {
+        self.pending.keys().copied()
+    }
+
+    pub fn is_pending(&self, addr: usize) -> bool {
+        self.pending.contains_key(&addr)
+    }
+
+    pub fn hold(&mut self, addr: Option<usize>) {
+        self.held = addr;
+    }
+
+    pub fn is_held(&self, addr: usize) -> bool {
+        self.held == Some(addr)
+    }
+
+    /// Marks the line `addr` starts a statement of as run. Returns the addresses whose
+    /// breakpoints are no longer needed: all of the line's, since one hit is enough.
+    pub fn hit(&mut self, addr: usize) -> Vec<usize> {
+        let (file, number) = match self.pending.remove(&addr) {
+            Some(line) => line,
+            None => return Vec::new(),
+        };
+        let line = match self.lines.get_mut(&file).and_then(|lines| lines.get_mut(&number)) {
+            Some(line) => line,
+            None => return vec![addr],
+        };
+        line.executed = true;
+        for other in &line.addresses {
+            self.pending.remove(other);
+        }
+        line.addresses.clone()
+    }
+
+    /// Executed and total lines of each file, in order of file name.
+    pub fn report(&self) -> Vec<FileCoverage> {
+        self.lines
+            .iter()
+            .map(|(file, lines)| FileCoverage {
+                file: file.clone(),
+                executed: lines.values().filter(|line| line.executed).count(),
+                total: lines.len(),
+            })
+            .collect()
+    }
+
+    /// The coverage in lcov's tracefile format: a record per file with a `DA` line for each of
+    /// its lines. A line's count is 1 if it ran, since only the first hit is seen.
+    pub fn lcov(&self) -> String {
+        let mut text = String::new();
+        for (file, lines) in &self.lines {
+            text.push_str(&format!("SF:{}\n", file));
+            for (number, line) in lines {
+                text.push_str(&format!("DA:{},{}\n", number, line.executed as usize));
+            }
+            let executed = lines.values().filter(|line| line.executed).count();
+            text.push_str(&format!("LF:{}\nLH:{}\nend_of_record\n", lines.len(), executed));
+        }
+        text
+    }
+}
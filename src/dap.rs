@@ -0,0 +1,569 @@
+//! `--dap`: the Debug Adapter Protocol over stdin and stdout, so that an editor such as VS Code
+//! can drive a Session. Each message is a JSON object after a `Content-Length` header. A minimal
+//! set of requests is handled: launching, line breakpoints, the stack, the innermost frame's
+//! variables, evaluating expressions, and continuing and stepping.
+
+use crate::expr::{EvalError, Format};
+use crate::inferior::StopReason;
+use crate::json::{self, Json};
+use crate::output::json_string;
+use crate::breakpoint::BreakpointId;
+use crate::session::{Location, Session, SessionError, StopEvent};
+use nix::unistd::{dup, dup2};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, Write};
+use std::os::unix::io::FromRawFd;
+use std::path::Path;
+
+/// There's one thread, as far as the client is concerned.
+const THREAD_ID: i64 = 1;
+/// The variablesReference of the innermost frame's locals. 0 means "no children" in DAP.
+const LOCALS_REFERENCE: i64 = 1;
+
+struct Adapter<W> {
+    session: Session,
+    /// Where messages to the client go: for `serve`, what stdout was when we started. stdout
+    /// itself is pointed at stderr, so that neither the inferior nor a stray message corrupts the
+    /// stream.
+    output: W,
+    /// The sequence number of the last message sent
+    seq: i64,
+    /// Events to send once the response to the current request has gone
+    events: Vec<String>,
+    /// The breakpoints the client has set, by source path. Breakpoints it has since taken away
+    /// stay in the session and are continued past.
+    requested: HashMap<String, Vec<BreakpointId>>,
+    stop_on_entry: bool,
+}
+
+/// Serves the client on stdin and stdout until it disconnects. The inferior is killed then.
+pub fn serve(session: Session) -> io::Result<()> {
+    let output = unsafe { File::from_raw_fd(dup(1).map_err(nix_to_io)?) };
+    dup2(2, 1).map_err(nix_to_io)?;
+    let mut adapter = Adapter::new(session, output);
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    while let Some(body) = read_message(&mut input)? {
+        let message = match json::parse(&body) {
+            Some(message) => message,
+            None => continue,
+        };
+        if message.get("type").and_then(Json::as_str) != Some("request") {
+            continue;
+        }
+        if !adapter.handle(&message)? {
+            break;
+        }
+    }
+    adapter.session.kill();
+    Ok(())
+}
+
+fn nix_to_io(err: nix::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, err.to_string())
+}
+
+/// The body of the next message. None at the end of the input.
+fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let mut body = vec![0u8; length.unwrap_or(0)];
+    input.read_exact(&mut body)?;
+    Ok(Some(String::from_utf8_lossy(&body).into_owned()))
+}
+
+impl<W: Write> Adapter<W> {
+    fn new(session: Session, output: W) -> Adapter<W> {
+        Adapter {
+            session,
+            output,
+            seq: 0,
+            events: Vec::new(),
+            requested: HashMap::new(),
+            stop_on_entry: false,
+        }
+    }
+
+    /// Answers one request, then sends the events it brought about. Returns false once the
+    /// client has disconnected.
+    fn handle(&mut self, request: &Json) -> io::Result<bool> {
+        let seq = request.get("seq").and_then(Json::as_i64).unwrap_or(0);
+        let command = request.get("command").and_then(Json::as_str).unwrap_or("");
+        let null = Json::Null;
+        let arguments = request.get("arguments").unwrap_or(&null);
+        let result = match command {
+            "initialize" => {
+                self.events.push(event("initialized", None));
+                Ok(Some("{\"supportsConfigurationDoneRequest\":true}".to_string()))
+            }
+            "launch" => self.launch(arguments),
+            "setBreakpoints" => self.set_breakpoints(arguments),
+            "configurationDone" => self.configuration_done(),
+            "threads" => Ok(Some(format!(
+                "{{\"threads\":[{{\"id\":{},\"name\":{}}}]}}",
+                THREAD_ID,
+                json_string(self.session.target())
+            ))),
+            "stackTrace" => self.stack_trace(),
+            "scopes" => Ok(Some(scopes(arguments))),
+            "variables" => self.variables(arguments),
+            "evaluate" => self.evaluate(arguments),
+            "continue" => {
+                let stop = self.session.cont();
+                self.stopped(stop, "pause")
+                    .map(|_| Some("{\"allThreadsContinued\":true}".to_string()))
+            }
+            "next" => {
+                let stop = self.session.next();
+                self.stopped(stop, "step").map(|_| None)
+            }
+            "stepIn" => {
+                let stop = self.session.step();
+                self.stopped(stop, "step").map(|_| None)
+            }
+            "stepOut" => {
+                let stop = self.session.finish().map(|(stop, _)| stop);
+                self.stopped(stop, "step").map(|_| None)
+            }
+            "disconnect" | "terminate" => {
+                self.session.kill();
+                self.respond(seq, command, Ok(None))?;
+                return Ok(false);
+            }
+            _ => Err(format!("{} is not supported", command)),
+        };
+        self.respond(seq, command, result)?;
+        for event in std::mem::replace(&mut self.events, Vec::new()) {
+            self.send(&event)?;
+        }
+        Ok(true)
+    }
+
+    fn launch(&mut self, arguments: &Json) -> Result<Option<String>, String> {
+        if let Some(args) = arguments.get("args").and_then(Json::as_array) {
+            self.session.args = args
+                .iter()
+                .filter_map(|arg| arg.as_str().map(str::to_string))
+                .collect();
+        }
+        self.stop_on_entry = arguments
+            .get("stopOnEntry")
+            .and_then(Json::as_bool)
+            .unwrap_or(false);
+        Ok(None)
+    }
+
+    /// Starts the program once the client has set its breakpoints: to the top of main with
+    /// stopOnEntry, otherwise until it stops.
+    fn configuration_done(&mut self) -> Result<Option<String>, String> {
+        let args = self.session.args.clone();
+        if self.stop_on_entry {
+            let stop = self.session.start(args);
+            self.stopped(stop, "entry")?;
+        } else {
+            let stop = self.session.run(args);
+            self.stopped(stop, "pause")?;
+        }
+        Ok(None)
+    }
+
+    /// Sets a breakpoint on each of the lines the client asks for in one source file. The path
+    /// is tried as given and then by its file name alone, since the debug info may name the file
+    /// relative to where it was compiled.
+    fn set_breakpoints(&mut self, arguments: &Json) -> Result<Option<String>, String> {
+        let path = arguments
+            .get("source")
+            .and_then(|source| source.get("path"))
+            .and_then(Json::as_str)
+            .ok_or_else(|| "no source path".to_string())?;
+        let file_name = Path::new(path)
+            .file_name()
+            .map_or(path.to_string(), |name| name.to_string_lossy().into_owned());
+        let lines: Vec<usize> = arguments
+            .get("breakpoints")
+            .and_then(Json::as_array)
+            .unwrap_or(&[])
+            .iter()
+            .filter_map(|bp| bp.get("line").and_then(Json::as_i64))
+            .map(|line| line as usize)
+            .collect();
+        let mut ids = Vec::new();
+        let mut breakpoints = Vec::new();
+        for line in lines {
+            let mut location = Location::Line(Some(path.to_string()), line);
+            if self.session.resolve(&location).is_none() {
+                location = Location::Line(Some(file_name.clone()), line);
+            }
+            let id = match self.session.set_breakpoint(location, false) {
+                Ok(id) | Err(SessionError::DuplicateBreakpoint(id, _)) => id,
+                Err(err) => {
+                    breakpoints.push(format!(
+                        "{{\"verified\":false,\"line\":{},\"message\":{}}}",
+                        line,
+                        json_string(&err.to_string())
+                    ));
+                    continue;
+                }
+            };
+            let verified = !self.session.breakpoint_addresses(id).is_empty();
+            let resolved_line = self
+                .session
+                .breakpoint_addresses(id)
+                .first()
+                .and_then(|&addr| self.session.line_at(addr))
+                .map_or(line, |line| line.number);
+            breakpoints.push(format!(
+                "{{\"id\":{},\"verified\":{},\"line\":{}}}",
+                id.0, verified, resolved_line
+            ));
+            ids.push(id);
+        }
+        self.requested.insert(path.to_string(), ids);
+        Ok(Some(format!("{{\"breakpoints\":[{}]}}", breakpoints.join(","))))
+    }
+
+    /// Whether the client still wants the breakpoint.
+    fn is_requested(&self, id: BreakpointId) -> bool {
+        self.requested.values().any(|ids| ids.contains(&id))
+    }
+
+    /// Queues the event that tells the client how the inferior stopped, continuing past the
+    /// breakpoints it has taken away. `reason` is what to call a stop that isn't at a breakpoint
+    /// or watchpoint, or by a signal.
+    fn stopped(
+        &mut self,
+        mut stop: Result<StopEvent, SessionError>,
+        reason: &str,
+    ) -> Result<(), String> {
+        loop {
+            match stop {
+                Ok(StopEvent::Stopped {
+                    breakpoint: Some(id),
+                    ..
+                }) if !self.is_requested(id) => stop = self.session.cont(),
+                _ => break,
+            }
+        }
+        let caught = self.session.take_caught();
+        match stop.map_err(|err| err.to_string())? {
+            StopEvent::Stopped {
+                signal,
+                reason: stop_reason,
+                breakpoint,
+                ..
+            } => {
+                let (reason, description, hit) = match (breakpoint, stop_reason, caught) {
+                    (_, _, Some(caught)) => {
+                        ("exception", Some(caught.catchpoint.name().to_string()), None)
+                    }
+                    (Some(id), _, _) => ("breakpoint", None, Some(id.0)),
+                    (None, StopReason::Signal(_), None) => {
+                        ("exception", Some(signal.to_string()), None)
+                    }
+                    (None, StopReason::Watchpoint(_), None) => ("data breakpoint", None, None),
+                    (None, _, None) => (reason, None, None),
+                };
+                let mut body = format!(
+                    "{{\"reason\":\"{}\",\"threadId\":{},\"allThreadsStopped\":true",
+                    reason, THREAD_ID
+                );
+                if let Some(description) = description {
+                    body.push_str(&format!(",\"description\":{}", json_string(&description)));
+                }
+                if let Some(id) = hit {
+                    body.push_str(&format!(",\"hitBreakpointIds\":[{}]", id));
+                }
+                body.push('}');
+                self.events.push(event("stopped", Some(&body)));
+            }
+            StopEvent::Exited(code) => self.exited(code),
+            StopEvent::Signaled(signal) => self.exited(128 + signal as i32),
+        }
+        Ok(())
+    }
+
+    fn exited(&mut self, code: i32) {
+        let body = format!("{{\"exitCode\":{}}}", code);
+        self.events.push(event("exited", Some(&body)));
+        self.events.push(event("terminated", None));
+    }
+
+    /// The frames of the backtrace, innermost first, each with its index as its id.
+    fn stack_trace(&mut self) -> Result<Option<String>, String> {
+        let frames = self.session.backtrace().map_err(|err| err.to_string())?;
+        let frames: Vec<String> = frames
+            .iter()
+            .enumerate()
+            .map(|(idx, frame)| {
+                let name = match &frame.function {
+                    Some(function) => function.clone(),
+                    None => format!("{:#x}", frame.address),
+                };
+                let source = match &frame.line {
+                    Some(line) => format!(
+                        ",\"source\":{{\"name\":{},\"path\":{}}},\"line\":{}",
+                        json_string(file_name(&line.file)),
+                        json_string(&line.file),
+                        line.number
+                    ),
+                    None => ",\"line\":0".to_string(),
+                };
+                format!(
+                    "{{\"id\":{},\"name\":{}{},\"column\":0,\
+                     \"instructionPointerReference\":\"{:#x}\"}}",
+                    idx,
+                    json_string(&name),
+                    source,
+                    frame.address
+                )
+            })
+            .collect();
+        Ok(Some(format!(
+            "{{\"stackFrames\":[{}],\"totalFrames\":{}}}",
+            frames.join(","),
+            frames.len()
+        )))
+    }
+
+    /// The innermost frame's variables, each as `print` would show it.
+    fn variables(&mut self, arguments: &Json) -> Result<Option<String>, String> {
+        let reference = arguments.get("variablesReference").and_then(Json::as_i64);
+        let names = match reference {
+            Some(LOCALS_REFERENCE) => {
+                self.session.local_variables().map_err(|err| err.to_string())?
+            }
+            _ => Vec::new(),
+        };
+        let variables: Vec<String> = names
+            .iter()
+            .map(|name| {
+                format!(
+                    "{{\"name\":{},\"value\":{},\"variablesReference\":0}}",
+                    json_string(name),
+                    json_string(&self.value_of(name))
+                )
+            })
+            .collect();
+        Ok(Some(format!("{{\"variables\":[{}]}}", variables.join(","))))
+    }
+
+    fn evaluate(&mut self, arguments: &Json) -> Result<Option<String>, String> {
+        let expression = arguments
+            .get("expression")
+            .and_then(Json::as_str)
+            .ok_or_else(|| "no expression".to_string())?;
+        let (value, _) = self
+            .session
+            .evaluate(expression)
+            .map_err(|err| err.to_string())?;
+        let value = self.session.format_value(&value, Format::Natural);
+        Ok(Some(format!(
+            "{{\"result\":{},\"variablesReference\":0}}",
+            json_string(&value)
+        )))
+    }
+
+    /// A variable's value, or why it couldn't be read.
+    fn value_of(&self, name: &str) -> String {
+        match self.session.evaluate(name) {
+            Ok((value, _)) => self.session.format_value(&value, Format::Natural),
+            // Already in angle brackets
+            Err(err @ SessionError::Eval(EvalError::OptimizedOut)) => err.to_string(),
+            Err(err) => format!("<{}>", err),
+        }
+    }
+
+    /// Sends the response to request `seq`: the body on success, or the message on failure.
+    fn respond(
+        &mut self,
+        seq: i64,
+        command: &str,
+        result: Result<Option<String>, String>,
+    ) -> io::Result<()> {
+        let outcome = match result {
+            Ok(Some(body)) => format!("\"success\":true,\"body\":{}", body),
+            Ok(None) => "\"success\":true".to_string(),
+            Err(message) => format!("\"success\":false,\"message\":{}", json_string(&message)),
+        };
+        let response = format!(
+            "\"type\":\"response\",\"request_seq\":{},\"command\":{},{}",
+            seq,
+            json_string(command),
+            outcome
+        );
+        self.send(&response)
+    }
+
+    /// Sends a message, given as the members after `seq`.
+    fn send(&mut self, members: &str) -> io::Result<()> {
+        self.seq += 1;
+        let message = format!("{{\"seq\":{},{}}}", self.seq, members);
+        write!(self.output, "Content-Length: {}\r\n\r\n{}", message.len(), message)?;
+        self.output.flush()
+    }
+}
+
+/// The members of an event message, for `send`.
+fn event(name: &str, body: Option<&str>) -> String {
+    match body {
+        Some(body) => format!("\"type\":\"event\",\"event\":\"{}\",\"body\":{}", name, body),
+        None => format!("\"type\":\"event\",\"event\":\"{}\"", name),
+    }
+}
+
+/// The innermost frame has its locals; the variables of the frames above it aren't found yet.
+fn scopes(arguments: &Json) -> String {
+    match arguments.get("frameId").and_then(Json::as_i64) {
+        Some(0) => format!(
+            "{{\"scopes\":[{{\"name\":\"Locals\",\"variablesReference\":{},\
+             \"expensive\":false}}]}}",
+            LOCALS_REFERENCE
+        ),
+        _ => "{\"scopes\":[]}".to_string(),
+    }
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn adapter() -> Adapter<Vec<u8>> {
+        let target = format!("{}/samples/function_calls", env!("CARGO_MANIFEST_DIR"));
+        Adapter::new(Session::new(&target).unwrap(), Vec::new())
+    }
+
+    /// Sends one framed request through the adapter, as `serve` would, and returns the messages
+    /// it wrote back.
+    fn request(
+        adapter: &mut Adapter<Vec<u8>>,
+        seq: i64,
+        command: &str,
+        arguments: &str,
+    ) -> Vec<Json> {
+        let body = format!(
+            "{{\"seq\":{},\"type\":\"request\",\"command\":\"{}\",\"arguments\":{}}}",
+            seq, command, arguments
+        );
+        let framed = format!("Content-Length: {}\r\n\r\n{}", body.len(), body);
+        let body = read_message(&mut Cursor::new(framed)).unwrap().unwrap();
+        let more = adapter.handle(&json::parse(&body).unwrap()).unwrap();
+        assert_eq!(more, command != "disconnect");
+        let mut output = Cursor::new(std::mem::replace(&mut adapter.output, Vec::new()));
+        let mut messages = Vec::new();
+        while let Some(message) = read_message(&mut output).unwrap() {
+            messages.push(json::parse(&message).unwrap());
+        }
+        messages
+    }
+
+    /// Each message as the response's command or the event's name, e.g. `response initialize`.
+    fn kinds(messages: &[Json]) -> Vec<String> {
+        messages
+            .iter()
+            .map(|message| {
+                let kind = message.get("type").and_then(Json::as_str).unwrap();
+                let name = message.get("command").or_else(|| message.get("event"));
+                format!("{} {}", kind, name.and_then(Json::as_str).unwrap())
+            })
+            .collect()
+    }
+
+    /// The body member `key` of the message `idx`.
+    fn body<'a>(messages: &'a [Json], idx: usize, key: &str) -> &'a Json {
+        messages[idx].get("body").and_then(|body| body.get(key)).unwrap()
+    }
+
+    #[test]
+    fn requests_get_responses_then_events() {
+        let mut adapter = adapter();
+        let messages = request(&mut adapter, 1, "initialize", "{\"adapterID\":\"deet\"}");
+        assert_eq!(kinds(&messages), ["response initialize", "event initialized"]);
+        assert_eq!(messages[0].get("request_seq").and_then(Json::as_i64), Some(1));
+        assert_eq!(messages[0].get("success").and_then(Json::as_bool), Some(true));
+        // Messages are numbered in the order they're sent, across requests
+        let seqs: Vec<i64> = messages
+            .iter()
+            .filter_map(|message| message.get("seq").and_then(Json::as_i64))
+            .collect();
+        assert_eq!(seqs, [1, 2]);
+
+        let messages = request(&mut adapter, 2, "launch", "{\"stopOnEntry\":false}");
+        assert_eq!(kinds(&messages), ["response launch"]);
+
+        let arguments = format!(
+            "{{\"source\":{{\"path\":\"{}/samples/function_calls.c\"}},\
+             \"breakpoints\":[{{\"line\":17}}]}}",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        let messages = request(&mut adapter, 3, "setBreakpoints", &arguments);
+        assert_eq!(kinds(&messages), ["response setBreakpoints"]);
+        let breakpoint = &body(&messages, 0, "breakpoints").as_array().unwrap()[0];
+        assert_eq!(breakpoint.get("verified").and_then(Json::as_bool), Some(true));
+        assert_eq!(breakpoint.get("line").and_then(Json::as_i64), Some(17));
+
+        let messages = request(&mut adapter, 4, "configurationDone", "{}");
+        assert_eq!(kinds(&messages), ["response configurationDone", "event stopped"]);
+        assert_eq!(body(&messages, 1, "reason").as_str(), Some("breakpoint"));
+        assert_eq!(messages[1].get("seq").and_then(Json::as_i64), Some(6));
+
+        let messages = request(&mut adapter, 5, "continue", "{\"threadId\":1}");
+        assert_eq!(
+            kinds(&messages),
+            ["response continue", "event exited", "event terminated"]
+        );
+        assert_eq!(body(&messages, 1, "exitCode").as_i64(), Some(0));
+
+        let messages = request(&mut adapter, 6, "disconnect", "{}");
+        assert_eq!(kinds(&messages), ["response disconnect"]);
+    }
+
+    #[test]
+    fn next_and_step_in_stop_with_reason_step() {
+        let mut adapter = adapter();
+        request(&mut adapter, 1, "initialize", "{}");
+        request(&mut adapter, 2, "launch", "{\"stopOnEntry\":true}");
+        let messages = request(&mut adapter, 3, "configurationDone", "{}");
+        assert_eq!(body(&messages, 1, "reason").as_str(), Some("entry"));
+
+        // Into func1 from main, over its printf, then into func2
+        let steps = [("stepIn", "func1", 17), ("next", "func1", 18), ("stepIn", "func2", 10)];
+        let mut seq = 4;
+        for &(command, function, line) in steps.iter() {
+            let messages = request(&mut adapter, seq, command, "{\"threadId\":1}");
+            assert_eq!(kinds(&messages), [format!("response {}", command), "event stopped".into()]);
+            assert_eq!(body(&messages, 1, "reason").as_str(), Some("step"));
+            assert!(messages[1].get("body").unwrap().get("hitBreakpointIds").is_none());
+            let messages = request(&mut adapter, seq + 1, "stackTrace", "{\"threadId\":1}");
+            let frame = &body(&messages, 0, "stackFrames").as_array().unwrap()[0];
+            assert_eq!(frame.get("name").and_then(Json::as_str), Some(function));
+            assert_eq!(frame.get("line").and_then(Json::as_i64), Some(line));
+            seq += 2;
+        }
+
+        let messages = request(&mut adapter, seq, "pause", "{\"threadId\":1}");
+        assert_eq!(kinds(&messages), ["response pause"]);
+        assert_eq!(messages[0].get("success").and_then(Json::as_bool), Some(false));
+        request(&mut adapter, seq + 1, "disconnect", "{}");
+    }
+}
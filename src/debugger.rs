@@ -1,202 +1,3382 @@
 use std::usize;
-use crate::debugger_command::DebuggerCommand;
-use crate::inferior::Inferior;
+use crate::background::{TypedLine, Watcher};
+use crate::completer::DeetHelper;
+use crate::debugger_command::{
+    command_info, resolve_name, suggest_commands, tokenize, Category, DebuggerCommand,
+    ListArgument, MemoryRegion, Resolution, COMMANDS,
+};
+use crate::expr::{self, Format};
+use crate::inferior;
+use crate::skip::SkipTarget;
+use crate::syscalls::SyscallTrace;
+use crate::tui::{SourceView, Tui, MIN_ROWS};
+use crate::dwarf_data::DebugInfo;
+use crate::breakpoint::{BreakpointId, EnableMode};
+use crate::session::{
+    AdvanceStop, CallOutcome, LibraryCall, Location, Profile, ProfileOutcome, Redirection,
+    ReturnValue, Session, SessionError, StopEvent, WatchpointEvent,
+};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
 use rustyline::error::ReadlineError;
-use rustyline::Editor;
-use crate::inferior::Status;
-use crate::dwarf_data::{DwarfData, Error as DwarfError};
+use rustyline::{Config, Editor};
+use nix::sys::signal::Signal;
+use crate::printf;
+use crate::output::{
+    self, AliasInfo, BreakpointInfo, BreakpointSite, CheckpointInfo, DisplayInfo, Event,
+    FrameInfo, FrameLocals, FunctionInfo, HeapInfo, HeapSiteInfo, InferiorInfo, ProcessState,
+    RecordedInstruction, ScopeVariable, SharedLibraryInfo, SourcesInfo, VariableInfo,
+};
+use regex::Regex;
 
+/// Settings that come from the debugger's own command line.
+#[derive(Default)]
+pub struct Options {
+    /// Don't execute ~/.deetrc and ./.deetrc at startup (`--nx`)
+    pub skip_init_files: bool,
+    /// Commands to run before reading from the prompt (`-ex`)
+    pub commands: Vec<String>,
+    /// Exit once `commands` have run instead of prompting (`--batch`)
+    pub batch: bool,
+    /// Where to keep command history (`--history-file`). Overrides $DEET_HISTFILE.
+    pub history_file: Option<String>,
+    /// Emit JSON events instead of text, and read commands from stdin without a prompt
+    /// (`--interpreter=json`)
+    pub json: bool,
+    /// How much to say about the target's symbols at startup
+    pub symbols_banner: SymbolsBanner,
+    /// Start with the source pane shown (`--tui`)
+    pub tui: bool,
+    /// Arguments for `run` to start the target with, until `set args` changes them (`--args`)
+    pub args: Vec<String>,
+}
+
+/// What to print about the target's debug info when the debugger starts.
+pub enum SymbolsBanner {
+    /// Nothing (`--quiet`)
+    Quiet,
+    /// A one-line summary (the default)
+    Summary,
+    /// The full DWARF dump (`--verbose-symbols`)
+    Full,
+}
+
+impl Default for SymbolsBanner {
+    fn default() -> Self {
+        SymbolsBanner::Summary
+    }
+}
+
+/// Why a debugger command failed. Reported to the user in place of the command's output; in
+/// batch mode any failure makes the debugger exit with INTERNAL_ERROR_EXIT_CODE.
+#[derive(Debug)]
+pub enum DebuggerError {
+    /// The line isn't a command we know
+    UnrecognizedCommand(String),
+    /// The line opens a quote it doesn't close
+    UnterminatedQuote(String),
+    /// The command word is a prefix of several commands, which are listed
+    AmbiguousCommand(String, Vec<&'static str>),
+    /// A word that isn't a command, with the commands it may be a typo of
+    UnknownCommand(String, Vec<String>),
+    /// A breakpoint location that is neither `*address`, a line number nor a function name
+    BadLocation(String),
+    /// A file or directory named in a command could not be used
+    Io(String, io::Error),
+    /// `cd` to something that isn't a directory
+    NotADirectory(PathBuf),
+    /// A command that can go without its argument only where there's a default, and there isn't
+    /// one: what the argument is for
+    ArgumentRequired(&'static str),
+    /// A regular expression given to a command doesn't parse
+    BadRegex(regex::Error),
+    /// `call` with something other than `function(args...)`
+    BadCall(String),
+    /// `undisplay` or `enable display` with a number that isn't in use
+    NoDisplay(usize),
+    /// `snapshot diff` with a name that wasn't snapshotted, or with none before any snapshot
+    NoSnapshot(Option<String>),
+    /// A `define`d command invoked itself (directly or not) too many times
+    UserCommandDepth(String),
+    /// A `define`d command used `$argN` without being given that many arguments
+    MissingArgument(String, usize),
+    /// `show user` with a name that isn't a `define`d command
+    NoUserCommand(String),
+    /// `list` in a source file that can't be read
+    NoSourceFile(String),
+    /// `list` past the end of a file: the line asked for, the file and its number of lines
+    LineOutOfRange(usize, String, usize),
+    /// `list -` when the start of the file has been listed already
+    ListAtStart(String),
+    /// A command that needs the program stopped, while it runs in the background
+    TargetRunning,
+    /// `interrupt` when the program isn't running in the background
+    NotRunning,
+    /// The session could not carry out the command
+    Session(SessionError),
+}
+
+impl fmt::Display for DebuggerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DebuggerError::UnrecognizedCommand(line) => {
+                write!(f, "Unrecognized command \"{}\".", line)
+            }
+            DebuggerError::UnterminatedQuote(line) => {
+                write!(f, "Unterminated quote in \"{}\".", line)
+            }
+            DebuggerError::AmbiguousCommand(word, candidates) => write!(
+                f,
+                "Ambiguous command \"{}\": {}.",
+                word,
+                candidates.join(", ")
+            ),
+            DebuggerError::UnknownCommand(word, suggestions) => {
+                write!(f, "Undefined command: \"{}\".", word)?;
+                let quoted: Vec<String> =
+                    suggestions.iter().map(|name| format!("\"{}\"", name)).collect();
+                match quoted.split_last() {
+                    None => write!(f, " Try \"help\"."),
+                    Some((last, [])) => write!(f, " Did you mean {}?", last),
+                    Some((last, others)) => {
+                        write!(f, " Did you mean {} or {}?", others.join(", "), last)
+                    }
+                }
+            }
+            DebuggerError::BadLocation(location) => {
+                write!(f, "Bad breakpoint location \"{}\".", location)
+            }
+            DebuggerError::Io(path, err) => write!(f, "{}: {}.", path, err),
+            DebuggerError::NotADirectory(path) => write!(f, "{}: Not a directory.", path.display()),
+            DebuggerError::ArgumentRequired(what) => write!(f, "Argument required ({}).", what),
+            DebuggerError::BadRegex(err) => write!(f, "Invalid regular expression: {}", err),
+            DebuggerError::BadCall(call) => {
+                write!(f, "Expected a call like \"function(arg, ...)\", not \"{}\".", call)
+            }
+            DebuggerError::NoDisplay(id) => write!(f, "No display number {}.", id),
+            DebuggerError::NoSnapshot(Some(name)) => write!(f, "No snapshot named \"{}\".", name),
+            DebuggerError::NoSnapshot(None) => write!(f, "No snapshot has been taken."),
+            DebuggerError::UserCommandDepth(name) => write!(
+                f,
+                "Max user call depth ({}) exceeded in \"{}\". Does it invoke itself?",
+                MAX_USER_COMMAND_DEPTH, name
+            ),
+            DebuggerError::MissingArgument(name, idx) => {
+                write!(f, "Missing argument {} in user command \"{}\".", idx, name)
+            }
+            DebuggerError::NoUserCommand(name) => {
+                write!(f, "Not a user command: \"{}\".", name)
+            }
+            DebuggerError::NoSourceFile(file) => write!(f, "Can't read source file \"{}\".", file),
+            DebuggerError::LineOutOfRange(line, file, count) => write!(
+                f,
+                "Line number {} out of range; \"{}\" has {} lines.",
+                line, file, count
+            ),
+            DebuggerError::ListAtStart(file) => write!(f, "Already at the start of {}.", file),
+            DebuggerError::TargetRunning => {
+                write!(f, "The program is running. Use \"interrupt\" to stop it first.")
+            }
+            DebuggerError::NotRunning => write!(f, "The program is not running."),
+            DebuggerError::Session(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<SessionError> for DebuggerError {
+    fn from(err: SessionError) -> Self {
+        DebuggerError::Session(err)
+    }
+}
+
+/// The interactive front end: reads commands from the prompt (or scripts) and reports what the
+/// Session does.
 pub struct Debugger {
-    target: String,
-    history_path: String,
-    readline: Editor<()>,
-    inferior: Option<Inferior>,
-    debug_data: DwarfData,
-    break_points: Vec<usize>
+    history_path: PathBuf,
+    /// The interactive prompt. None in batch and JSON modes, where we never read from the
+    /// terminal.
+    readline: Option<Editor<DeetHelper>>,
+    /// Commands queued from the command line, run before the first prompt
+    queued_commands: Vec<String>,
+    batch: bool,
+    /// How the last run of the program ended, as a shell would put it: its exit code, or 128 plus
+    /// the signal that killed it. Becomes the debugger's exit code in batch mode.
+    exit_status: Option<i32>,
+    /// How the inferior last stopped or ended, for `info inferior`
+    last_stop: Option<StopEvent>,
+    /// Whether to ask before doing something drastic (`set confirm`). Off in batch mode, where
+    /// there is nobody to ask.
+    confirm: bool,
+    /// Whether `run` reloads the target's symbols when the file changed (`set auto-reload`)
+    auto_reload: bool,
+    /// Whether stops show the text of the source line (`set print source`)
+    print_source: bool,
+    /// How many source lines to show above and below the line at each stop (`set context`).
+    /// 0 shows just the line itself.
+    context_lines: usize,
+    /// How many lines `list` shows (`set listsize`)
+    list_size: usize,
+    /// Where `list` carries on from
+    list_position: Option<ListPosition>,
+    /// The source pane, while `tui enable` is in effect
+    tui: Option<Tui>,
+    /// Waits for the program while it runs in the background (after `run &` or `continue &`)
+    background: Option<Watcher>,
+    /// What has been typed at the prompt so far, for redrawing it below a background stop notice
+    typed_line: TypedLine,
+    symbols_banner: SymbolsBanner,
+    /// Whether to report breakpoint bookkeeping time at each stop (`maint time on`)
+    maint_time: bool,
+    /// Expressions from `display`, shown at every stop
+    displays: Vec<AutoDisplay>,
+    /// Number for the next `display`; numbers aren't reused
+    next_display_id: usize,
+    /// Memory saved by `snapshot`, by the address expression it was taken at
+    snapshots: BTreeMap<String, Snapshot>,
+    /// The name of the last snapshot taken, which `snapshot diff` compares with by default
+    last_snapshot: Option<String>,
+    /// Command aliases from `alias`, by name, plus the built-in ones
+    aliases: BTreeMap<String, String>,
+    /// Commands from `define`, by name: the lines of their bodies
+    user_commands: BTreeMap<String, Vec<String>>,
+    /// The `define` whose body is being read, if any
+    defining: Option<Definition>,
+    /// How many `define`d commands are running, one inside another
+    user_command_depth: usize,
+    /// The last line typed at the prompt, if its command repeats when Enter is pressed on an
+    /// empty line
+    repeat_line: Option<String>,
+    /// Where `set logging on` copies output to
+    logging_file: String,
+    /// Whether `set logging on` truncates the file rather than appending to it
+    logging_overwrite: bool,
+    logging: bool,
+    session: Session,
 }
 
-impl Debugger {
-    /// Initializes the debugger.
-    pub fn new(target: &str) -> Debugger {
-        // TODO (milestone 3): initialize the DwarfData
-        let debug_data = match DwarfData::from_file(target) {
-            Ok(val) => val,
-            Err(DwarfError::ErrorOpeningFile) => {
-                println!("Could not open file {}", target);
-                std::process::exit(1);
+/// Where `list` carries on from.
+#[derive(Clone)]
+enum ListPosition {
+    /// Nothing has been listed since the program stopped at this line: list around it
+    Around(String, usize),
+    /// Lines `first` to `last` of the file were listed last
+    Listed {
+        file: String,
+        first: usize,
+        last: usize,
+    },
+}
+
+impl ListPosition {
+    fn file(&self) -> &str {
+        match self {
+            ListPosition::Around(file, _) | ListPosition::Listed { file, .. } => file,
+        }
+    }
+}
+
+/// An expression registered with `display`.
+struct AutoDisplay {
+    id: usize,
+    expression: String,
+    format: Format,
+    /// Disabled displays are kept, but not shown
+    enabled: bool,
+}
+
+/// Memory saved by `snapshot`, to compare with later.
+struct Snapshot {
+    address: usize,
+    bytes: Vec<u8>,
+}
+
+/// A `define` whose body is still being read.
+struct Definition {
+    name: String,
+    body: Vec<String>,
+    /// How many `define`s inside the body are still open, so their `end`s don't end this one
+    nesting: usize,
+}
+
+impl Debugger {
+    /// Initializes the debugger.
+    pub fn new(target: &str, options: Options) -> Result<Debugger, SessionError> {
+        let mut session = Session::new(target)?;
+        session.args = options.args;
+        session.set_solib_listener(Some(Box::new(|event| output::emit(&Event::Solib(event)))));
+        session.set_dprintf_printer(Some(Box::new(|id, text| match text {
+            // Like printf, the trailing newline is implied
+            Ok(text) => report!("{}", text.strip_suffix('\n').unwrap_or(&text)),
+            Err(err) => report!("Error in dprintf {}: {}", id.0, err),
+        })));
+
+        let history_path = history_path(options.history_file);
+        let typed_line = TypedLine::default();
+        // With no one at a prompt to press return, output is never paged
+        let readline = if options.batch || options.json {
+            output::set_pagination(false);
+            None
+        } else {
+            let config = Config::builder().max_history_size(history_size()).build();
+            let mut readline = Editor::<DeetHelper>::with_config(config);
+            readline.set_helper(Some(DeetHelper::new(
+                session.debug_data().clone(),
+                typed_line.clone(),
+            )));
+            // Attempt to load history from the history file if it exists
+            let _ = readline.load_history(&history_path);
+            Some(readline)
+        };
+
+        let mut debugger = Debugger {
+            history_path,
+            readline,
+            queued_commands: options.commands,
+            batch: options.batch,
+            symbols_banner: options.symbols_banner,
+            exit_status: None,
+            last_stop: None,
+            confirm: !options.batch,
+            auto_reload: true,
+            print_source: true,
+            context_lines: 0,
+            list_size: DEFAULT_LIST_SIZE,
+            list_position: None,
+            tui: None,
+            maint_time: false,
+            displays: Vec::new(),
+            next_display_id: 1,
+            snapshots: BTreeMap::new(),
+            last_snapshot: None,
+            aliases: DEFAULT_ALIASES
+                .iter()
+                .map(|(name, expansion)| (name.to_string(), expansion.to_string()))
+                .collect(),
+            user_commands: BTreeMap::new(),
+            defining: None,
+            user_command_depth: 0,
+            repeat_line: None,
+            background: None,
+            typed_line,
+            logging_file: DEFAULT_LOGGING_FILE.to_string(),
+            logging_overwrite: false,
+            logging: false,
+            session,
+        };
+        if !options.skip_init_files {
+            debugger.load_init_files();
+        }
+        if options.tui {
+            debugger.queued_commands.push("tui enable".to_string());
+        }
+        Ok(debugger)
+    }
+
+    /// Executes $HOME/.deetrc and then ./.deetrc, if they exist.
+    fn load_init_files(&mut self) {
+        let mut paths = Vec::new();
+        if let Ok(home) = std::env::var("HOME") {
+            paths.push(Path::new(&home).join(".deetrc"));
+        }
+        paths.push(PathBuf::from(".deetrc"));
+        let mut loaded: Vec<PathBuf> = Vec::new();
+        for path in paths {
+            // Don't run the same file twice when the debugger is started from $HOME
+            let canonical = match path.canonicalize() {
+                Ok(canonical) => canonical,
+                Err(_) => continue,
+            };
+            if loaded.contains(&canonical) {
+                continue;
+            }
+            report!("Loading init file {}", path.display());
+            match self.source_file(&path.to_string_lossy(), true) {
+                Ok(true) => {}
+                // The init file asked to quit
+                Ok(false) => std::process::exit(0),
+                Err(err) => report!("{}", err),
+            }
+            loaded.push(canonical);
+        }
+    }
+
+    /// Gathers what `info inferior` shows. A live process is described by where it is now; one
+    /// that has ended by the last stop event.
+    fn inferior_info(&self) -> InferiorInfo {
+        let inferior = self.session.inferior.as_ref();
+        let process = match (inferior, &self.last_stop) {
+            (None, _) => ProcessState::None,
+            (Some(inferior), Some(StopEvent::Exited(code))) if inferior.has_exited() => {
+                ProcessState::Exited {
+                    pid: inferior.pid().as_raw(),
+                    code: *code,
+                }
+            }
+            (Some(inferior), Some(StopEvent::Signaled(signal))) if inferior.has_exited() => {
+                ProcessState::Signaled {
+                    pid: inferior.pid().as_raw(),
+                    signal: *signal,
+                }
+            }
+            (Some(inferior), _) => {
+                let address = inferior.getregs().map(|regs| regs.rip as usize).unwrap_or(0);
+                ProcessState::Stopped {
+                    pid: inferior.pid().as_raw(),
+                    address,
+                    function: self.session.function_at(address),
+                    line: self.session.line_at(address),
+                }
+            }
+        };
+        InferiorInfo {
+            target: self.session.target().to_string(),
+            args: self.session.args.clone(),
+            process,
+            installed_breakpoints: inferior
+                .map_or(0, |inferior| inferior.installed_breakpoint_count()),
+        }
+    }
+
+    /// Says where the target's DWARF came from, when it is in a separate debug file.
+    fn report_debug_file(&self) {
+        if let Some(path) = self.session.debug_data().debug_file() {
+            report!("Reading symbols from {}...", path.display());
+        }
+    }
+
+    /// `maint info dwarf`: everything read from the debug info, one compilation unit at a time.
+    fn dump_dwarf(&self) {
+        let data = self.session.debug_data();
+        for unit in data.compilation_units() {
+            output::emit(&Event::DwarfUnit { data, unit });
+        }
+    }
+
+    /// Runs any commands queued with -ex, then (unless in batch mode) reads and executes commands
+    /// from the prompt until the user quits. Returns the exit code for the debugger process.
+    pub fn run(&mut self) -> i32 {
+        self.report_symbols();
+        let mut failed = false;
+        for line in std::mem::replace(&mut self.queued_commands, Vec::new()) {
+            output::start_paging();
+            match self.execute_line(&line) {
+                Ok(true) => {}
+                Ok(false) => {
+                    self.save_history();
+                    return if self.batch {
+                        self.batch_exit_code(failed)
+                    } else if failed {
+                        1
+                    } else {
+                        0
+                    };
+                }
+                Err(err) => {
+                    report!("{}", err);
+                    failed = true;
+                }
+            }
+        }
+        if self.batch {
+            self.kill_inferior();
+            return self.batch_exit_code(failed);
+        }
+        loop {
+            if let Err(err) = self.collect_background_stop() {
+                report!("{}", err);
+            }
+            self.draw_tui();
+            let cmd = self.get_next_command();
+            output::start_paging();
+            match self.execute_command(cmd) {
+                Ok(true) => {}
+                Ok(false) => {
+                    if let Some(tui) = self.tui.take() {
+                        tui.disable();
+                    }
+                    self.save_history();
+                    return 0;
+                }
+                Err(err) => report!("{}", err),
+            }
+        }
+    }
+
+    /// Describes the target's debug info, as much as `--quiet` or `--verbose-symbols` ask for.
+    fn report_symbols(&self) {
+        match self.symbols_banner {
+            SymbolsBanner::Quiet => {}
+            _ => self.report_debug_file(),
+        }
+        let debug_data = self.session.debug_data();
+        match (&self.symbols_banner, debug_data.debug_info()) {
+            (SymbolsBanner::Quiet, _) => {}
+            (_, DebugInfo::Nothing) => {
+                report!("Reading symbols from {}...", self.session.target());
+                report!("(no debugging symbols found)");
+            }
+            (_, DebugInfo::Symbols) => {
+                report!(
+                    "Loaded {} functions from the symbol table of {}.",
+                    debug_data.function_count(),
+                    self.session.target()
+                );
+                report!("(no debugging symbols found)");
+            }
+            (SymbolsBanner::Summary, DebugInfo::Dwarf) => report!(
+                "Loaded symbols for {}: {} functions, {} source files",
+                self.session.target(),
+                debug_data.function_count(),
+                debug_data.file_count()
+            ),
+            (SymbolsBanner::Full, DebugInfo::Dwarf) => self.dump_dwarf(),
+        }
+        let quiet = match self.symbols_banner {
+            SymbolsBanner::Quiet => true,
+            _ => false,
+        };
+        if !quiet && self.session.looks_like_rust() {
+            report!("This looks like a Rust program: \"catch panic\" stops it when it panics.");
+        }
+    }
+
+    /// The debugger's exit code at the end of batch mode: the program's exit status, so that
+    /// scripts can tell whether it failed, or 125 if one of our own commands failed.
+    fn batch_exit_code(&self, failed: bool) -> i32 {
+        if failed {
+            INTERNAL_ERROR_EXIT_CODE
+        } else {
+            self.exit_status.unwrap_or(0)
+        }
+    }
+
+    /// Parses and executes one line of input (or adds it to the command being defined). Returns
+    /// false if the debugger should exit.
+    fn execute_line(&mut self, line: &str) -> Result<bool, DebuggerError> {
+        if self.capture_definition_line(line) {
+            return Ok(true);
+        }
+        let cmd = self.parse_line(line).ok_or_else(|| self.unrecognized(line))?;
+        self.execute_command(cmd)
+    }
+
+    /// Parses a line after expanding aliases and `$bpnum` (the breakpoint set last, which is how
+    /// `save breakpoints` refers to them). A line starting with the name of a `define`d
+    /// command invokes it, with the rest of the line as its arguments.
+    fn parse_line(&self, line: &str) -> Option<DebuggerCommand> {
+        let mut line = expand_alias(&self.aliases, line);
+        if let Some(id) = self.session.last_breakpoint_id() {
+            line = line.replace("$bpnum", &id.0.to_string());
+        }
+        let mut words = line.split_whitespace();
+        if let Some(name) = words.next() {
+            if self.user_commands.contains_key(name) {
+                let args = words.map(|word| word.to_string()).collect();
+                return Some(DebuggerCommand::User(name.to_string(), args));
+            }
+        }
+        DebuggerCommand::from_line(&line)
+    }
+
+    /// While a `define` is in progress, adds a line to its body and returns true. The body ends
+    /// at an `end` that doesn't close a nested `define`.
+    fn capture_definition_line(&mut self, line: &str) -> bool {
+        let definition = match self.defining.as_mut() {
+            Some(definition) => definition,
+            None => return false,
+        };
+        let line = line.trim();
+        let first_word = line.split_whitespace().next();
+        if first_word == Some("define") {
+            definition.nesting += 1;
+        } else if line == "end" {
+            if definition.nesting == 0 {
+                let definition = self.defining.take().unwrap();
+                self.user_commands.insert(definition.name, definition.body);
+                return true;
+            }
+            definition.nesting -= 1;
+        }
+        definition.body.push(line.to_string());
+        true
+    }
+
+    /// The error for a line that doesn't parse: an ambiguous abbreviation, an unknown command
+    /// (with suggestions), a known command with a quote left open, or a known command with
+    /// arguments it doesn't accept.
+    fn unrecognized(&self, line: &str) -> DebuggerError {
+        let line = line.trim();
+        let first_word = line.split_whitespace().next().unwrap_or_default();
+        // `print/x` and the like are resolved without their format
+        let name = first_word.split('/').next().unwrap_or_default();
+        match resolve_name(name) {
+            Resolution::Ambiguous(candidates) => {
+                DebuggerError::AmbiguousCommand(name.to_string(), candidates)
+            }
+            Resolution::Unknown => self.unknown_command(name),
+            Resolution::Command(_) if tokenize(line).is_none() => {
+                DebuggerError::UnterminatedQuote(line.to_string())
+            }
+            Resolution::Command(_) => DebuggerError::UnrecognizedCommand(line.to_string()),
+        }
+    }
+
+    /// The error for a word that isn't a command, suggesting commands, aliases and user-defined
+    /// commands it may be a typo of.
+    fn unknown_command(&self, word: &str) -> DebuggerError {
+        let extra_names = self.aliases.keys().chain(self.user_commands.keys());
+        let suggestions = suggest_commands(word, extra_names.map(|name| name.as_str()));
+        DebuggerError::UnknownCommand(
+            word.to_string(),
+            suggestions.into_iter().map(|name| name.to_string()).collect(),
+        )
+    }
+
+    /// Runs the body of a `define`d command, with `$arg0`..`$arg9` and `$argc` replaced by its
+    /// arguments. Stops at the first line that fails.
+    fn run_user_command(&mut self, name: &str, args: &[String]) -> Result<bool, DebuggerError> {
+        if self.user_command_depth >= MAX_USER_COMMAND_DEPTH {
+            return Err(DebuggerError::UserCommandDepth(name.to_string()));
+        }
+        let body = self.user_commands.get(name).cloned().unwrap_or_default();
+        self.user_command_depth += 1;
+        let mut result = Ok(true);
+        for line in &body {
+            result =
+                substitute_arguments(name, line, args).and_then(|line| self.execute_line(&line));
+            if result.as_ref().map_or(true, |keep_going| !keep_going) {
+                break;
+            }
+        }
+        self.user_command_depth -= 1;
+        result
+    }
+
+    /// Writes the prompt's history to the history file.
+    fn save_history(&mut self) {
+        if let Some(readline) = &mut self.readline {
+            if let Err(err) = readline.save_history(&self.history_path) {
+                report!(
+                    "Warning: failed to save history file at {}: {}",
+                    self.history_path.display(),
+                    err
+                );
+            }
+        }
+    }
+
+    /// Writes the breakpoints to `path` as `break` commands, with their locations as they were
+    /// given (function names and lines, which survive recompiling). Returns how many there were.
+    fn save_breakpoints(&self, path: &str) -> Result<usize, DebuggerError> {
+        let breakpoints = self.session.breakpoints();
+        let mut script = String::new();
+        for bp in breakpoints {
+            match (&bp.dprintf, &bp.location) {
+                (Some(args), _) => {
+                    script.push_str(&format!("dprintf {}, {}\n", bp.location, args))
+                }
+                (None, Location::Address(_)) => {
+                    script.push_str("# Set by address, which may be stale once rebuilt\n");
+                    script.push_str(&format!("break -force {}\n", bp.location));
+                }
+                (None, _) => script.push_str(&format!("break {}\n", bp.location)),
+            }
+            // The breakpoint's number once the script runs isn't known now, hence $bpnum. The
+            // mode comes before disabling, since enabling in a mode would undo it.
+            match bp.mode {
+                EnableMode::Keep => {}
+                EnableMode::Once => script.push_str("enable once $bpnum\n"),
+                EnableMode::Count(count) => {
+                    script.push_str(&format!("enable count {} $bpnum\n", count))
+                }
+            }
+            if !bp.enabled {
+                script.push_str("disable $bpnum\n");
+            }
+            if let Some(condition) = &bp.condition {
+                script.push_str(&format!("condition $bpnum {}\n", condition));
+            }
+            if bp.ignore_count > 0 {
+                script.push_str(&format!("ignore $bpnum {}\n", bp.ignore_count));
+            }
+        }
+        fs::write(path, script).map_err(|err| DebuggerError::Io(path.to_string(), err))?;
+        Ok(breakpoints.len())
+    }
+
+    /// Evaluates an expression to an address, for the commands that take one.
+    fn evaluate_address(&self, expression: &str) -> Result<usize, DebuggerError> {
+        let (value, _) = self.session.evaluate(expression)?;
+        let address = expr::to_integer(self.session.debug_data(), &value)
+            .map_err(SessionError::from)?;
+        Ok(address as usize)
+    }
+
+    /// `help <topic>`: the usage and examples of a command, or what an alias or user-defined
+    /// command stands for.
+    fn help(&self, topic: &str) -> Result<(), DebuggerError> {
+        if let Some(expansion) = self.aliases.get(topic) {
+            report!("\"{}\" is an alias for \"{}\".", topic, expansion);
+            return Ok(());
+        }
+        if self.user_commands.contains_key(topic) {
+            report!(
+                "\"{}\" is a user-defined command. Use \"show user {}\" to see it.",
+                topic,
+                topic
+            );
+            return Ok(());
+        }
+        let command = match resolve_name(topic) {
+            Resolution::Command(name) => command_info(name).unwrap(),
+            Resolution::Ambiguous(candidates) => {
+                return Err(DebuggerError::AmbiguousCommand(topic.to_string(), candidates))
+            }
+            Resolution::Unknown => {
+                return Err(self.unknown_command(topic))
+            }
+        };
+        report!("{}", command.summary);
+        report!("Usage: {}", command.usage);
+        if !command.examples.is_empty() {
+            report!("Examples:");
+            for example in command.examples {
+                report!("  {}", example);
+            }
+        }
+        Ok(())
+    }
+
+    /// Prints the run time and stop counts of the current (or last) run.
+    fn show_run_stats(&self) {
+        if let Some(stats) = self.session.run_stats() {
+            output::emit(&Event::RunStats(stats));
+        }
+    }
+
+    /// Carries out a single debugger command. Returns false if the debugger should exit.
+    fn execute_command(&mut self, cmd: DebuggerCommand) -> Result<bool, DebuggerError> {
+        self.collect_background_stop()?;
+        // Ask before killing the program (which may go on running in the background meanwhile)
+        let question = match cmd {
+            DebuggerCommand::Quit if self.session.is_alive() => {
+                Some("A debugging session is active. Quit anyway?")
+            }
+            DebuggerCommand::Kill if self.session.is_alive() => {
+                Some("Kill the program being debugged?")
+            }
+            _ => None,
+        };
+        if let Some(question) = question {
+            if !self.confirm(question) {
+                report!("Not confirmed.");
+                return Ok(true);
+            }
+        }
+        if self.background.is_some() {
+            match cmd {
+                DebuggerCommand::Kill | DebuggerCommand::Quit => self.background = None,
+                DebuggerCommand::Interrupt
+                | DebuggerCommand::Help(_)
+                | DebuggerCommand::Echo(_)
+                | DebuggerCommand::Shell(_)
+                | DebuggerCommand::Pwd
+                | DebuggerCommand::Ignore(..)
+                | DebuggerCommand::Condition(..) => {}
+                // Breakpoints are written into the program's code, so stop it for a moment
+                DebuggerCommand::Break(..)
+                | DebuggerCommand::Dprintf(..)
+                | DebuggerCommand::RBreak(..)
+                | DebuggerCommand::Delete(..)
+                | DebuggerCommand::Clear(..)
+                | DebuggerCommand::EnableBreakpoints(..)
+                | DebuggerCommand::DisableBreakpoints(..) => {
+                    let resume = self.pause_background()?;
+                    let result = self.execute_command(cmd);
+                    if resume {
+                        let stop = self.session.cont_background()?;
+                        self.report_background(stop);
+                    }
+                    return result;
+                }
+                _ => return Err(DebuggerError::TargetRunning),
+            }
+        }
+        match cmd {
+            DebuggerCommand::Run(args, redirections) => {
+                if !self.confirm_restart() {
+                    return Ok(true);
+                }
+                self.reload_if_changed()?;
+                self.kill_inferior();
+                self.exit_status = None;
+                self.set_arguments(args, redirections);
+                let stop = self.session.run(Vec::new())?;
+                self.report_stop(stop);
+            }
+            DebuggerCommand::Start(args, redirections) => {
+                if !self.confirm_restart() {
+                    return Ok(true);
+                }
+                self.reload_if_changed()?;
+                self.kill_inferior();
+                self.exit_status = None;
+                self.set_arguments(args, redirections);
+                let stop = self.session.start(Vec::new())?;
+                self.report_stop(stop);
+            }
+            DebuggerCommand::Stress(count) => {
+                if !self.confirm_restart() {
+                    return Ok(true);
+                }
+                self.reload_if_changed()?;
+                self.kill_inferior();
+                self.exit_status = None;
+                inferior::take_interrupt();
+                for run in 1..=count {
+                    // ctrl+c between runs; one during a run stops the inferior, ending the loop
+                    if inferior::take_interrupt() {
+                        report!("Interrupted after {} clean runs.", run - 1);
+                        return Ok(true);
+                    }
+                    // Each run installs the breakpoints afresh in the new process
+                    match self.session.run(Vec::new())? {
+                        StopEvent::Exited(code) => self.exit_status = Some(code),
+                        stop => {
+                            report!(
+                                "Run {} of {} did not exit, after {} clean runs:",
+                                run,
+                                count,
+                                run - 1
+                            );
+                            self.report_stop(stop);
+                            return Ok(true);
+                        }
+                    }
+                }
+                report!("All {} runs exited.", count);
+            }
+            DebuggerCommand::Profile(seconds, callers) => {
+                match self.session.profile(seconds.map(Duration::from_secs_f64))? {
+                    ProfileOutcome::Finished(profile) => self.report_profile(&profile, callers),
+                    ProfileOutcome::Paused(stop, remaining) => {
+                        self.report_stop(stop);
+                        report!(
+                            "Profiling paused with {:.1} s to go. \"profile\" carries on.",
+                            remaining.as_secs_f64()
+                        );
+                    }
+                    ProfileOutcome::Ended(stop, profile) => {
+                        self.report_stop(stop);
+                        self.report_profile(&profile, callers);
+                    }
+                }
+            }
+            DebuggerCommand::Cont(count) => {
+                let mut stop = self.session.cont()?;
+                let mut skipped = 0;
+                // Only breakpoint hits count; any other stop ends the run early
+                while skipped + 1 < count.unwrap_or(1) {
+                    match stop {
+                        StopEvent::Stopped {
+                            breakpoint: Some(_),
+                            ..
+                        } => {}
+                        _ => break,
+                    }
+                    skipped += 1;
+                    stop = self.session.cont()?;
+                }
+                if skipped > 0 {
+                    report!(
+                        "Skipped {} breakpoint hit{}.",
+                        skipped,
+                        if skipped == 1 { "" } else { "s" }
+                    );
+                }
+                self.report_stop(stop);
+            }
+            DebuggerCommand::Background(cmd) => {
+                let stop = match *cmd {
+                    DebuggerCommand::Run(args, redirections) => {
+                        if !self.confirm_restart() {
+                            return Ok(true);
+                        }
+                        self.reload_if_changed()?;
+                        self.kill_inferior();
+                        self.exit_status = None;
+                        self.set_arguments(args, redirections);
+                        self.session.run_background(Vec::new())?
+                    }
+                    _ => self.session.cont_background()?,
+                };
+                self.report_background(stop);
+            }
+            DebuggerCommand::Interrupt => {
+                let watcher = self.background.take().ok_or(DebuggerError::NotRunning)?;
+                self.session.interrupt()?;
+                watcher.wait();
+                let stop = self.session.wait_background()?;
+                self.report_background(stop);
+            }
+            DebuggerCommand::Until(line) => {
+                let stop = self.session.until(line)?;
+                self.report_stop(stop);
+            }
+            DebuggerCommand::Next => {
+                let stop = self.session.next();
+                self.report_line_step(stop)?;
+            }
+            DebuggerCommand::Step => {
+                let stop = self.session.step();
+                self.report_line_step(stop)?;
+            }
+            DebuggerCommand::Skip(target) => {
+                match &target {
+                    SkipTarget::Function(pattern) => {
+                        report!("Function {} will be skipped when stepping.", pattern)
+                    }
+                    SkipTarget::File(file) => {
+                        report!("File {} will be skipped when stepping.", file)
+                    }
+                }
+                self.session.skips.add(target);
+            }
+            DebuggerCommand::SkipDelete(ids) if ids.is_empty() => {
+                if !self.session.skips.skips().is_empty()
+                    && self.confirm("Delete all skiplist entries?")
+                {
+                    self.session.skips.clear();
+                }
+            }
+            DebuggerCommand::SkipDelete(ids) => {
+                for id in ids {
+                    if !self.session.skips.delete(id) {
+                        report!("No skiplist entry numbered {}.", id);
+                    }
+                }
+            }
+            DebuggerCommand::Kill => {
+                let pid = match &self.session.inferior {
+                    Some(inferior) if self.session.is_alive() => inferior.pid(),
+                    _ => return Err(SessionError::NoProcess.into()),
+                };
+                self.session.kill();
+                report!("[Process {} killed]", pid);
+            }
+            DebuggerCommand::File(path) => {
+                if self.session.is_alive()
+                    && !self.confirm("A program is being debugged already. Kill it?")
+                {
+                    report!("Not confirmed.");
+                    return Ok(true);
+                }
+                self.switch_target(&path)?;
+            }
+            DebuggerCommand::Checkpoint => {
+                let checkpoint = self.session.checkpoint()?;
+                report!("Checkpoint {}: process {}.", checkpoint.id, checkpoint.pid());
+            }
+            DebuggerCommand::Restart(id) => {
+                let stop = self.session.restart(id)?;
+                report!("Switching to checkpoint {}.", id);
+                self.report_stop(stop);
+            }
+            DebuggerCommand::Record => {
+                if !self.session.is_alive() {
+                    return Err(SessionError::NoProcess.into());
+                }
+                self.session.start_recording();
+                report!(
+                    "Recording: `continue` now single-steps every instruction, which is much \
+                     slower. Use `record stop` to go back to full speed."
+                );
+            }
+            DebuggerCommand::RecordStop => {
+                self.session.stop_recording();
+                report!("Recording stopped.");
+            }
+            DebuggerCommand::InfoRecord(count) => {
+                let recorded = match self.session.recorded_instructions() {
+                    Some(recorded) => recorded,
+                    None => {
+                        report!("Not recording. Use `record` to start.");
+                        return Ok(true);
+                    }
+                };
+                let count = count.unwrap_or(DEFAULT_RECORD_HISTORY).min(recorded.len());
+                let first = recorded.len() - count;
+                let instructions: Vec<RecordedInstruction> = recorded
+                    .iter()
+                    .skip(first)
+                    .map(|&addr| RecordedInstruction {
+                        address: addr,
+                        function: self.session.function_at(addr),
+                        line: self.session.line_at(addr),
+                    })
+                    .collect();
+                output::emit(&Event::Record {
+                    first: first + 1,
+                    instructions: &instructions,
+                });
+            }
+            DebuggerCommand::Catch(catchpoint, on) => {
+                self.session.set_catchpoint(catchpoint, on)?;
+                report!("Catchpoint on {} {}.", catchpoint.name(), if on { "on" } else { "off" });
+            }
+            DebuggerCommand::InfoCatch => {
+                output::emit(&Event::Catchpoints(&self.session.catchpoints()));
+            }
+            DebuggerCommand::Coverage(true) => {
+                let lines = self.session.start_coverage()?;
+                report!(
+                    "Recording coverage of {} line{}.",
+                    lines,
+                    if lines == 1 { "" } else { "s" }
+                );
+            }
+            DebuggerCommand::Coverage(false) => self.session.stop_coverage()?,
+            DebuggerCommand::CoverageReport(path) => {
+                let coverage = match self.session.coverage() {
+                    Some(coverage) => coverage,
+                    None => {
+                        report!("Coverage is not being recorded. Use \"coverage on\".");
+                        return Ok(true);
+                    }
+                };
+                output::emit(&Event::Coverage(&coverage.report()));
+                if let Some(path) = path {
+                    fs::write(&path, coverage.lcov())
+                        .map_err(|err| DebuggerError::Io(path.clone(), err))?;
+                    report!("Wrote lcov data to {}.", path);
+                }
+            }
+            DebuggerCommand::InfoStats => match self.session.run_stats() {
+                Some(stats) => output::emit(&Event::RunStats(stats)),
+                None => report!("The program has not been run."),
+            },
+            DebuggerCommand::InfoCheckpoints => {
+                let checkpoints: Vec<CheckpointInfo> = self
+                    .session
+                    .checkpoints()
+                    .iter()
+                    .map(|checkpoint| CheckpointInfo {
+                        id: checkpoint.id,
+                        pid: checkpoint.pid().as_raw(),
+                        address: checkpoint.address,
+                        function: checkpoint.function.clone(),
+                        line: checkpoint.line.clone(),
+                    })
+                    .collect();
+                output::emit(&Event::Checkpoints(&checkpoints));
+            }
+            DebuggerCommand::Call(call) => {
+                let (function, args) =
+                    parse_call(&call).ok_or_else(|| DebuggerError::BadCall(call.clone()))?;
+                match self.session.call_function(&function, &args)? {
+                    CallOutcome::Returned(Some(value)) => output::emit(&Event::Value {
+                        expression: &call,
+                        value: &value,
+                    }),
+                    CallOutcome::Returned(None) => {}
+                    CallOutcome::Stopped(stop) => {
+                        report!(
+                            "The program stopped while in a function called with `call`. \
+                             Continuing will finish the call and return to where it was made."
+                        );
+                        self.report_stop(stop);
+                    }
+                }
+            }
+            DebuggerCommand::Return(value) => {
+                if !self.session.is_alive() {
+                    return Err(SessionError::NoProcess.into());
+                }
+                let pc = self.session.registers()?.rip as usize;
+                let question = match self.session.function_at(pc) {
+                    Some(function) => format!("Make {} return now?", function),
+                    None => "Make the selected frame return now?".to_string(),
+                };
+                if !self.confirm(&question) {
+                    report!("Not confirmed.");
+                    return Ok(true);
+                }
+                let frames = self.session.return_from_frame(value.as_deref())?;
+                output::emit(&Event::Backtrace(&frames));
+            }
+            DebuggerCommand::Jump(s) => {
+                if !self.session.is_alive() {
+                    return Err(SessionError::NoProcess.into());
+                }
+                let location = parse_location(&s)?;
+                let addr = self
+                    .session
+                    .resolve(&location)
+                    .ok_or(SessionError::UnknownLocation(location.clone()))?;
+                if !self.session.in_current_function(addr)? {
+                    let question = format!(
+                        "{} is not in the current function. Jump anyway?",
+                        location
+                    );
+                    if !self.confirm(&question) {
+                        report!("Not confirmed.");
+                        return Ok(true);
+                    }
+                }
+                report!("Continuing at {}.", output::address(addr));
+                let stop = self.session.jump(addr)?;
+                self.report_stop(stop);
+            }
+            DebuggerCommand::Finish => {
+                let pc = self.session.registers()?.rip as usize;
+                if let Some(function) = self.session.function_at(pc) {
+                    report!("Run till exit from {}", output::function(&function));
+                }
+                let (stop, returned) = self.session.finish()?;
+                self.report_stop(stop);
+                if let Some(ReturnValue::Value(value)) = returned {
+                    let text = self.session.format_value(&value, Format::Natural);
+                    let history = self.session.record_value(value);
+                    output::emit(&Event::ReturnValue {
+                        history,
+                        value: &text,
+                    });
+                }
+            }
+            DebuggerCommand::ShowValues => {
+                for (idx, value) in self.session.values().iter().enumerate() {
+                    let text = self.session.format_value(value, Format::Natural);
+                    output::emit(&Event::Value {
+                        expression: &format!("${}", idx + 1),
+                        value: &text,
+                    });
+                }
+            }
+            DebuggerCommand::Advance(s) => {
+                let location = parse_location(&s)?;
+                let (stop, outcome) = self.session.advance(&location)?;
+                match outcome {
+                    Some(AdvanceStop::Reached) => report!("Advanced to {}.", location),
+                    Some(AdvanceStop::Returned) => {
+                        report!("The current function returned before reaching {}.", location)
+                    }
+                    None => {}
+                }
+                self.report_stop(stop);
+            }
+            DebuggerCommand::SetArgs(args, redirections) => {
+                self.session.args = args;
+                self.session.redirections = redirections;
+            },
+            DebuggerCommand::ShowArgs => {
+                let words: Vec<String> = self
+                    .session
+                    .args
+                    .iter()
+                    .cloned()
+                    .chain(self.session.redirections.iter().map(|r| r.to_string()))
+                    .collect();
+                report!(
+                    "Argument list to give program being debugged when it is started is \"{}\".",
+                    words.join(" ")
+                );
+            },
+            DebuggerCommand::SetEnvironment(var, value) => {
+                self.session.env.vars.insert(var, Some(value));
+            },
+            DebuggerCommand::UnsetEnvironment(Some(var)) => {
+                self.session.env.vars.insert(var, None);
+            },
+            DebuggerCommand::UnsetEnvironment(None) => {
+                // With no variable name, start the inferior with an empty environment
+                if self.confirm("Delete all environment variables?") {
+                    self.session.env.clear = true;
+                    self.session.env.vars.clear();
+                }
+            },
+            DebuggerCommand::ShowEnvironment => {
+                if self.session.env.clear {
+                    report!("(environment cleared)");
+                }
+                for (var, value) in &self.session.env.vars {
+                    match value {
+                        Some(value) => report!("{}={}", var, value),
+                        None => report!("unset {}", var),
+                    }
+                }
+            },
+            DebuggerCommand::SetInferiorTty(use_tty) => {
+                self.session.use_tty = use_tty;
+            },
+            DebuggerCommand::ShowInferiorTty => {
+                report!("Running the inferior on its own terminal is {}.", if self.session.use_tty { "on" } else { "off" });
+            },
+            DebuggerCommand::SetCaptureOutput(capture) => {
+                self.session.capture_output = capture;
+            },
+            DebuggerCommand::ShowCaptureOutput => {
+                report!("Capturing the program's output is {}.", if self.session.capture_output { "on" } else { "off" });
+            },
+            DebuggerCommand::SetDisableRandomization(disable) => {
+                self.session.disable_randomization = disable;
+            },
+            DebuggerCommand::SetTraceSyscalls(on) => {
+                let tracer: Option<Box<dyn FnMut(&SyscallTrace)>> = if on {
+                    Some(Box::new(|trace| output::emit(&Event::Syscall(trace))))
+                } else {
+                    None
+                };
+                self.session.set_syscall_tracer(tracer)?;
+            }
+            DebuggerCommand::SetTraceCalls(on) => {
+                let tracer: Option<Box<dyn FnMut(&LibraryCall)>> = if on {
+                    Some(Box::new(|call| output::emit(&Event::LibraryCall(call))))
+                } else {
+                    None
+                };
+                let count = self.session.set_call_tracer(tracer)?;
+                if on {
+                    report!(
+                        "Tracing calls to {} library function{}.",
+                        count,
+                        if count == 1 { "" } else { "s" }
+                    );
+                }
+            }
+            DebuggerCommand::SetDebugFileDirectory(dir) => {
+                self.session.set_debug_file_directory(&dir)?;
+                self.report_debug_file();
+            },
+            DebuggerCommand::SetConfirm(enabled) => self.confirm = enabled,
+            DebuggerCommand::SetAutoReload(enabled) => self.auto_reload = enabled,
+            DebuggerCommand::SetPrintSource(enabled) => self.print_source = enabled,
+            DebuggerCommand::SetContext(lines) => self.context_lines = lines,
+            DebuggerCommand::SetListSize(lines) => self.list_size = lines,
+            DebuggerCommand::SetBacktraceLimit(limit) => self.session.backtrace_limit = limit,
+            DebuggerCommand::ShowBacktraceLimit => match self.session.backtrace_limit {
+                0 => report!("An upper bound on the number of backtrace levels is unlimited."),
+                limit => report!("An upper bound on the number of backtrace levels is {}.", limit),
+            },
+            DebuggerCommand::ShowListSize => {
+                report!("Number of source lines listed is {}.", self.list_size);
+            }
+            DebuggerCommand::List(argument) => self.list(argument)?,
+            DebuggerCommand::Tui(true) => {
+                if self.tui.is_none() {
+                    if self.readline.is_none() {
+                        report!("The TUI needs an interactive terminal.");
+                        return Ok(true);
+                    }
+                    match Tui::enable() {
+                        Some(tui) => self.tui = Some(tui),
+                        None => {
+                            report!("The TUI needs a terminal at least {} rows tall.", MIN_ROWS)
+                        }
+                    }
+                }
+            }
+            DebuggerCommand::Tui(false) => {
+                if let Some(tui) = self.tui.take() {
+                    tui.disable();
+                }
+            }
+            DebuggerCommand::ShowAutoReload => {
+                report!(
+                    "Reloading symbols when the program changes on disk is {}.",
+                    if self.auto_reload { "on" } else { "off" }
+                );
+            },
+            DebuggerCommand::ShowContext => {
+                report!(
+                    "Number of source lines shown around the line at each stop is {}.",
+                    self.context_lines
+                );
+            },
+            DebuggerCommand::ShowPrintSource => {
+                report!(
+                    "Printing of the source line at stops is {}.",
+                    if self.print_source { "on" } else { "off" }
+                );
+            },
+            DebuggerCommand::ShowConfirm => {
+                report!(
+                    "Whether to confirm potentially dangerous operations is {}.",
+                    if self.confirm { "on" } else { "off" }
+                );
+            },
+            DebuggerCommand::ShowDebugFileDirectory => {
+                report!(
+                    "The directory where separate debug symbols are searched for is \"{}\".",
+                    self.session.debug_file_directory()
+                );
+            },
+            DebuggerCommand::SetSubstitutePath(from, to) => {
+                self.session.substitute_paths.set(&from, &to);
+            },
+            DebuggerCommand::UnsetSubstitutePath(Some(from)) => {
+                if !self.session.substitute_paths.remove(&from) {
+                    report!("No substitution rule defined for `{}'.", from);
+                }
+            },
+            DebuggerCommand::UnsetSubstitutePath(None) => {
+                self.session.substitute_paths.clear();
+            },
+            DebuggerCommand::Directory(dirs) => {
+                if dirs.is_empty() {
+                    if self.confirm("Reinitialize source path to empty?") {
+                        self.session.source_directories.clear();
+                    }
+                } else {
+                    // Like GDB, the directories given go to the front, in the order given
+                    for dir in dirs.iter().rev() {
+                        let dir = PathBuf::from(dir);
+                        self.session.source_directories.retain(|existing| *existing != dir);
+                        self.session.source_directories.insert(0, dir);
+                    }
+                }
+                self.show_directories();
+            },
+            DebuggerCommand::ShowDirectories => self.show_directories(),
+            DebuggerCommand::ShowSubstitutePath => {
+                let rules = self.session.substitute_paths.rules();
+                if rules.is_empty() {
+                    report!("No source path substitution rules.");
+                } else {
+                    report!("List of all source path substitution rules:");
+                    for (from, to) in rules {
+                        report!("  `{}' -> `{}'.", from.display(), to.display());
+                    }
+                }
+            },
+            DebuggerCommand::ShowDisableRandomization => {
+                report!(
+                    "Disabling randomization of debuggee's virtual address space is {}.",
+                    if self.session.disable_randomization { "on" } else { "off" }
+                );
+            },
+            DebuggerCommand::ShowTraceSyscalls => {
+                report!(
+                    "Tracing of system calls is {}.",
+                    if self.session.is_tracing_syscalls() { "on" } else { "off" }
+                );
+            }
+            DebuggerCommand::ShowTraceCalls => {
+                report!(
+                    "Tracing of library calls is {}.",
+                    if self.session.is_tracing_calls() { "on" } else { "off" }
+                );
+            }
+            DebuggerCommand::InfoTraceCalls => {
+                if !self.session.is_tracing_calls() {
+                    report!("Library calls are not being traced. Use \"set trace-calls on\".");
+                    return Ok(true);
+                }
+                output::emit(&Event::CallCounts(&self.session.call_counts()));
+            }
+            DebuggerCommand::SetStepIntoNoDebug(on) => self.session.step_into_no_debug = on,
+            DebuggerCommand::ShowStepIntoNoDebug => {
+                report!(
+                    "Stepping into functions without line information is {}.",
+                    if self.session.step_into_no_debug { "on" } else { "off" }
+                );
+            }
+            DebuggerCommand::SetStopOnSolibEvents(on) => self.session.stop_on_solib_events = on,
+            DebuggerCommand::ShowStopOnSolibEvents => {
+                report!(
+                    "Stopping for shared library events is {}.",
+                    if self.session.stop_on_solib_events { "on" } else { "off" }
+                );
+            }
+            DebuggerCommand::SetHeapTrack(on) => self.session.set_heap_tracking(on)?,
+            DebuggerCommand::ShowHeapTrack => {
+                report!(
+                    "Tracking of heap allocations is {}.",
+                    if self.session.heap_tracker().is_some() { "on" } else { "off" }
+                );
+            }
+            DebuggerCommand::InfoHeap => {
+                let heap = match self.session.heap_tracker() {
+                    Some(heap) => heap,
+                    None => {
+                        report!("The heap is not being tracked. Use \"set heap-track on\".");
+                        return Ok(true);
+                    }
+                };
+                let (live_blocks, live_bytes) = heap.live();
+                let sites = heap
+                    .call_sites(HEAP_SITE_LIMIT)
+                    .into_iter()
+                    .map(|site| HeapSiteInfo {
+                        address: site.address,
+                        blocks: site.blocks,
+                        bytes: site.bytes,
+                        function: self.session.function_at(site.address),
+                        line: self.session.line_at(site.address),
+                    })
+                    .collect();
+                output::emit(&Event::Heap(&HeapInfo {
+                    live_blocks,
+                    live_bytes,
+                    allocations: heap.allocations,
+                    frees: heap.frees,
+                    sites,
+                }));
+            }
+            DebuggerCommand::Cd(dir) => {
+                // As in gdb, `cd` on its own goes to the home directory
+                let dir = match dir {
+                    Some(dir) => dir,
+                    None => std::env::var("HOME")
+                        .map_err(|_| DebuggerError::ArgumentRequired("new working directory"))?,
+                };
+                let new_cwd = self.session.cwd.join(&dir);
+                let path = new_cwd.canonicalize().map_err(|err| DebuggerError::Io(dir, err))?;
+                if !path.is_dir() {
+                    return Err(DebuggerError::NotADirectory(path));
+                }
+                report!("Working directory {}.", path.display());
+                self.session.cwd = path;
+            },
+            DebuggerCommand::Pwd => {
+                report!("Working directory {}.", self.session.cwd.display());
+            },
+            DebuggerCommand::Shell(cmdline) => {
+                // Runs with our stdio, so its output streams straight to the terminal
+                let status = Command::new("/bin/sh")
+                    .arg("-c")
+                    .arg(&cmdline)
+                    .status()
+                    .map_err(|err| DebuggerError::Io("/bin/sh".to_string(), err))?;
+                match status.code() {
+                    Some(0) => {}
+                    Some(code) => report!("Shell command exited with code {}", code),
+                    None => report!("Shell command terminated by a signal"),
+                }
+            },
+            DebuggerCommand::Source(path, keep_going) => {
+                return self.source_file(&path, keep_going);
+            },
+            DebuggerCommand::History => {
+                if let Some(readline) = &self.readline {
+                    for (idx, entry) in readline.history().iter().enumerate() {
+                        report!("{:5}  {}", idx + 1, entry);
+                    }
+                }
+            },
+            DebuggerCommand::HistorySave => {
+                self.save_history();
+            },
+            DebuggerCommand::SaveBreakpoints(path) => {
+                let count = self.save_breakpoints(&path)?;
+                report!("Saved {} breakpoints to {}.", count, path);
+            }
+            DebuggerCommand::SetStyle(enabled) => {
+                output::set_style(enabled);
+            },
+            DebuggerCommand::SetPagination(enabled) => output::set_pagination(enabled),
+            DebuggerCommand::SetLogging(true) => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .append(!self.logging_overwrite)
+                    .truncate(self.logging_overwrite)
+                    .open(&self.logging_file)
+                    .map_err(|err| DebuggerError::Io(self.logging_file.clone(), err))?;
+                output::set_log_file(Some(file));
+                self.logging = true;
+                report!("Copying output to {}.", self.logging_file);
+            }
+            DebuggerCommand::SetLogging(false) => {
+                if self.logging {
+                    report!("Done logging to {}.", self.logging_file);
+                }
+                output::set_log_file(None);
+                self.logging = false;
+            }
+            DebuggerCommand::SetLoggingFile(path) => {
+                if self.logging {
+                    report!("The new file takes effect at the next `set logging on`.");
+                }
+                self.logging_file = path;
+            }
+            DebuggerCommand::SetLoggingOverwrite(overwrite) => {
+                self.logging_overwrite = overwrite;
+            }
+            DebuggerCommand::ShowLogging => {
+                report!(
+                    "Logging is {}. The logging file is \"{}\", which is {} when logging starts.",
+                    if self.logging { "on" } else { "off" },
+                    self.logging_file,
+                    if self.logging_overwrite { "overwritten" } else { "appended to" }
+                );
+            }
+            DebuggerCommand::ShowStyle => {
+                report!("Styling is {}.", if output::style_enabled() { "on" } else { "off" });
+            },
+            DebuggerCommand::ShowPagination => {
+                let state = if output::pagination_enabled() { "on" } else { "off" };
+                report!("State of pagination is {}.", state);
+            }
+            DebuggerCommand::MaintInfoDwarf => self.dump_dwarf(),
+            DebuggerCommand::MaintInfoLineTable(file) => {
+                let rows = self.session.debug_data().line_table(file.as_deref());
+                match file {
+                    Some(file) if rows.is_empty() => {
+                        report!("No line table rows for \"{}\".", file)
+                    }
+                    _ => output::emit(&Event::LineTable(&rows)),
+                }
+            }
+            DebuggerCommand::MaintTime(enabled) => {
+                self.maint_time = enabled;
+            },
+            DebuggerCommand::Quit => {
+                self.kill_inferior();
+                return Ok(false);
+            },
+            DebuggerCommand::Backtrace(count, false) => {
+                let mut frames = self.session.backtrace()?;
+                frames.truncate(count.unwrap_or(frames.len()));
+                output::emit(&Event::Backtrace(&frames));
+            }
+            DebuggerCommand::Backtrace(count, true) => {
+                let mut frames = self.session.backtrace()?;
+                frames.truncate(count.unwrap_or(frames.len()));
+                let selected = self.session.selected_frame();
+                let mut full = Vec::new();
+                for (level, frame) in frames.into_iter().enumerate() {
+                    let locals = match self.session.select_frame(level) {
+                        Ok(_) => self.frame_locals(&frame),
+                        Err(_) => None,
+                    };
+                    full.push(FrameLocals { frame, locals });
+                }
+                self.session.select_frame(selected)?;
+                output::emit(&Event::BacktraceFull(&full));
+            }
+            DebuggerCommand::Frame(level) => {
+                let level = level.unwrap_or_else(|| self.session.selected_frame());
+                let frame = self.session.select_frame(level)?;
+                self.show_frame(level, &frame);
+            }
+            DebuggerCommand::Up(count) => {
+                let frame = self.session.frame_up(count)?;
+                self.show_frame(self.session.selected_frame(), &frame);
+            }
+            DebuggerCommand::Down(count) => {
+                let frame = self.session.frame_down(count)?;
+                self.show_frame(self.session.selected_frame(), &frame);
+            }
+            DebuggerCommand::Alias(name, expansion) => {
+                if command_info(&name).is_some() {
+                    report!(
+                        "Alias \"{}\" would shadow the command of the same name; not defined.",
+                        name
+                    );
+                } else {
+                    self.aliases.insert(name, expansion);
+                }
+            }
+            DebuggerCommand::Define(name) => {
+                if command_info(&name).is_some() {
+                    report!("\"{}\" is a built-in command and can not be redefined.", name);
+                    return Ok(true);
+                }
+                if self.user_commands.contains_key(&name) {
+                    report!("Redefining \"{}\".", name);
+                }
+                if self.readline.is_some() {
+                    report!("Type commands for definition of \"{}\".", name);
+                    report!("End with a line saying just \"end\".");
+                }
+                self.defining = Some(Definition {
+                    name,
+                    body: Vec::new(),
+                    nesting: 0,
+                });
+            }
+            DebuggerCommand::User(name, args) => return self.run_user_command(&name, &args),
+            DebuggerCommand::ShowUser(name) => {
+                let names: Vec<&String> = match &name {
+                    Some(name) if !self.user_commands.contains_key(name) => {
+                        return Err(DebuggerError::NoUserCommand(name.clone()))
+                    }
+                    Some(name) => vec![name],
+                    None => self.user_commands.keys().collect(),
+                };
+                for name in names {
+                    output::emit(&Event::UserCommand {
+                        name,
+                        body: &self.user_commands[name],
+                    });
+                }
+            }
+            DebuggerCommand::Help(None) => {
+                for category in Category::ALL {
+                    report!("{}:", category.title());
+                    for command in COMMANDS.iter().filter(|command| command.category == *category) {
+                        report!("  {:<12} {}", command.name, command.summary);
+                    }
+                }
+                report!("Type \"help\" followed by a command name for its usage and examples.");
+            }
+            DebuggerCommand::Help(Some(topic)) => self.help(&topic)?,
+            DebuggerCommand::Apropos(word) => {
+                let lowercase = word.to_lowercase();
+                let mentions = |text: &str| text.to_lowercase().contains(&lowercase);
+                let mut matches = Vec::new();
+                for command in COMMANDS {
+                    if mentions(command.name) || mentions(command.summary) {
+                        matches.push(format!("{} -- {}", command.name, command.summary));
+                    }
+                }
+                for (name, expansion) in &self.aliases {
+                    if mentions(name) || mentions(expansion) {
+                        matches.push(format!("{} -- Alias for \"{}\".", name, expansion));
+                    }
+                }
+                for (name, body) in &self.user_commands {
+                    if mentions(name) || body.iter().any(|line| mentions(line)) {
+                        matches.push(format!("{} -- User-defined command.", name));
+                    }
+                }
+                if matches.is_empty() {
+                    report!(
+                        "No commands match \"{}\". Try \"help\" for a list of all commands.",
+                        word
+                    );
+                }
+                for line in matches {
+                    report!("{}", line);
+                }
+            }
+            DebuggerCommand::InfoRegisters(names) => {
+                let values = self.session.register_values(&names)?;
+                output::emit(&Event::Registers(&values));
+            }
+            DebuggerCommand::InfoAllRegisters => {
+                let mut values = self.session.register_values(&[])?;
+                values.extend(self.session.debug_registers()?);
+                output::emit(&Event::Registers(&values));
+                let fpregs = self.session.float_registers()?;
+                output::emit(&Event::FloatRegisters(&fpregs));
+                output::emit(&Event::VectorRegisters(&fpregs));
+            }
+            DebuggerCommand::InfoFloat => {
+                let fpregs = self.session.float_registers()?;
+                output::emit(&Event::FloatRegisters(&fpregs));
+            }
+            DebuggerCommand::InfoVector => {
+                let fpregs = self.session.float_registers()?;
+                output::emit(&Event::VectorRegisters(&fpregs));
+            }
+            DebuggerCommand::InfoSiginfo => {
+                let info = self.session.siginfo()?;
+                output::emit(&Event::SignalInfo(&info));
+            }
+            DebuggerCommand::InfoSkip => {
+                let skips = self.session.skips.skips();
+                if skips.is_empty() {
+                    report!("Not skipping any files or functions.");
+                } else {
+                    report!("Num  Type      Pattern");
+                    for skip in skips {
+                        let (kind, pattern) = match &skip.target {
+                            SkipTarget::Function(pattern) => ("function", pattern),
+                            SkipTarget::File(file) => ("file", file),
+                        };
+                        report!("{:<4} {:<9} {}", skip.id, kind, pattern);
+                    }
+                }
+            }
+            DebuggerCommand::InfoFrame => {
+                let (frame, caller) = self.session.current_frame()?;
+                let caller_address = caller.map(|caller| caller.rip);
+                let info = FrameInfo {
+                    level: self.session.selected_frame(),
+                    frame,
+                    function: self.session.function_at(frame.rip),
+                    line: self.session.line_at(frame.rip),
+                    caller,
+                    caller_function: caller_address.and_then(|addr| self.session.function_at(addr)),
+                    caller_line: caller_address.and_then(|addr| self.session.line_at(addr)),
+                };
+                output::emit(&Event::FrameInfo(&info));
+            }
+            DebuggerCommand::Examine(expression, length) => {
+                let address = self.evaluate_address(&expression)?;
+                let bytes = self.session.read_memory(address, length)?;
+                output::emit(&Event::Memory {
+                    address,
+                    bytes: &bytes,
+                });
+            }
+            DebuggerCommand::Snapshot(expression, length) => {
+                let address = self.evaluate_address(&expression)?;
+                let bytes = self.session.read_memory(address, length)?;
+                report!("Snapshot \"{}\": {} bytes at {:#x}.", expression, bytes.len(), address);
+                self.snapshots.insert(expression.clone(), Snapshot { address, bytes });
+                self.last_snapshot = Some(expression);
+            }
+            DebuggerCommand::SnapshotDiff(name) => {
+                let snapshot = match name.as_ref().or_else(|| self.last_snapshot.as_ref()) {
+                    Some(name) => self
+                        .snapshots
+                        .get(name)
+                        .ok_or_else(|| DebuggerError::NoSnapshot(Some(name.clone())))?,
+                    None => return Err(DebuggerError::NoSnapshot(None)),
+                };
+                let bytes = self.session.read_memory(snapshot.address, snapshot.bytes.len())?;
+                output::emit(&Event::MemoryDiff {
+                    address: snapshot.address,
+                    old: &snapshot.bytes,
+                    new: &bytes,
+                });
+            }
+            DebuggerCommand::Snapshots => {
+                if self.snapshots.is_empty() {
+                    report!("No snapshots.");
+                }
+                for (name, snapshot) in &self.snapshots {
+                    report!("{}: {} bytes at {:#x}", name, snapshot.bytes.len(), snapshot.address);
+                }
+            }
+            DebuggerCommand::Find(start, end, pattern) => {
+                let start = self.evaluate_address(&start)?;
+                let end = if end.starts_with('+') {
+                    start.saturating_add(self.evaluate_address(&end[1..])?)
+                } else {
+                    self.evaluate_address(&end)?
+                };
+                if end <= start {
+                    report!("The end of the range is not after its start.");
+                    return Ok(true);
+                }
+                let search = self
+                    .session
+                    .find_in_memory(start, end, &pattern, MAX_FIND_MATCHES)?;
+                for (address, mapping) in &search.matches {
+                    output::emit(&Event::MemoryMatch {
+                        address: *address,
+                        mapping,
+                    });
+                }
+                for (from, to) in &search.skipped {
+                    report!("Skipped unreadable memory at {:#x}-{:#x}.", from, to);
+                }
+                match search.matches.len() {
+                    0 => report!("Pattern not found."),
+                    _ if search.truncated => {
+                        report!("Stopped after {} matches.", MAX_FIND_MATCHES)
+                    }
+                    1 => report!("1 pattern found."),
+                    count => report!("{} patterns found.", count),
+                }
+            }
+            DebuggerCommand::Strings(region, nul_terminated) => {
+                let (start, end) = match region {
+                    MemoryRegion::Range(expression, length) => {
+                        let start = self.evaluate_address(&expression)?;
+                        (start, start.saturating_add(length))
+                    }
+                    MemoryRegion::Mapping(name) => match self.session.mapping_range(&name)? {
+                        Some(range) => range,
+                        None => {
+                            report!("No mapping named \"{}\".", name);
+                            return Ok(true);
+                        }
+                    },
+                };
+                // Print each string as it is found, rather than after the whole range is read
+                let skipped = self.session.strings_in_memory(
+                    start,
+                    end,
+                    MIN_STRING_RUN,
+                    nul_terminated,
+                    |address, text| output::emit(&Event::MemoryString { address, text }),
+                )?;
+                for (from, to) in &skipped {
+                    report!("Skipped unreadable memory at {:#x}-{:#x}.", from, to);
+                }
+            }
+            DebuggerCommand::InfoInferior => {
+                let info = self.inferior_info();
+                output::emit(&Event::InferiorInfo(&info));
+            }
+            DebuggerCommand::InfoAddress(name) => match self.session.symbol_address(&name) {
+                Some(symbol) => output::emit(&Event::SymbolAddress {
+                    name: &name,
+                    is_function: symbol.is_function,
+                    address: symbol.address,
+                    runtime_address: symbol.runtime_address,
+                }),
+                None => report!("No symbol \"{}\" in the program.", name),
+            },
+            DebuggerCommand::InfoSymbol(addr) => {
+                let digits = addr.trim_start_matches("0x").trim_start_matches("0X");
+                let addr = usize::from_str_radix(digits, 16)
+                    .map_err(|_| DebuggerError::BadLocation(addr.clone()))?;
+                match self.session.symbol_at(addr) {
+                    Some((symbol, offset, section)) => output::emit(&Event::SymbolAt {
+                        address: addr,
+                        symbol: &symbol,
+                        offset,
+                        section: section.as_deref(),
+                    }),
+                    None => report!("No symbol matches {:#x}.", addr),
+                }
+            }
+            DebuggerCommand::InfoLine(location) => {
+                let (file, line_number) = match parse_location(&location)? {
+                    Location::Line(file, line_number) => (file, line_number),
+                    other => {
+                        let addr = match self.session.resolve(&other) {
+                            Some(addr) => addr,
+                            None => return Err(SessionError::UnknownLocation(other).into()),
+                        };
+                        match self.session.line_row_at(addr) {
+                            Some(row) => output::emit(&Event::LineRange {
+                                file: &row.file,
+                                line: row.number,
+                                start: row.address,
+                                end: row.end,
+                                address: if addr == row.address { None } else { Some(addr) },
+                            }),
+                            None => report!(
+                                "No line number information available for address {:#x}.",
+                                addr
+                            ),
+                        }
+                        return Ok(true);
+                    }
+                };
+                let ranges = self.session.line_ranges(file.as_deref(), line_number);
+                let file = file
+                    .as_deref()
+                    .or_else(|| self.session.default_source_file())
+                    .unwrap_or("")
+                    .to_string();
+                if ranges.is_empty() {
+                    report!("Line {} of \"{}\" contains no code.", line_number, file);
+                }
+                for (start, end) in ranges {
+                    output::emit(&Event::LineRange {
+                        file: &file,
+                        line: line_number,
+                        start,
+                        end,
+                        address: None,
+                    });
+                }
+            }
+            DebuggerCommand::InfoAliases => {
+                let aliases: Vec<AliasInfo> = self
+                    .aliases
+                    .iter()
+                    .map(|(name, expansion)| AliasInfo { name, expansion })
+                    .collect();
+                output::emit(&Event::Aliases(&aliases));
+            }
+            DebuggerCommand::Echo(text) => {
+                let text = printf::unescape(&text);
+                // Output is line-based, so a trailing newline is implied
+                report!("{}", text.strip_suffix('\n').unwrap_or(&text));
+            }
+            DebuggerCommand::Printf(args) => {
+                let text = self.session.format_printf(&args)?;
+                report!("{}", text.strip_suffix('\n').unwrap_or(&text));
+            }
+            DebuggerCommand::Print(expression, format) => {
+                let (value, warnings) = self.session.evaluate(&expression)?;
+                for warning in warnings {
+                    report!("Warning: {}", warning);
+                }
+                let text = self.session.format_value(&value, format);
+                self.session.record_value(value);
+                output::emit(&Event::Value {
+                    expression: &expression,
+                    value: &text,
+                });
+            }
+            DebuggerCommand::Ptype(text) => {
+                let type_id = self.session.type_of(&text)?;
+                output::emit(&Event::Type {
+                    data: self.session.debug_data(),
+                    type_id,
+                    expand: true,
+                });
+            }
+            DebuggerCommand::Whatis(text) => {
+                let type_id = self.session.whatis(&text)?;
+                output::emit(&Event::Type {
+                    data: self.session.debug_data(),
+                    type_id,
+                    expand: false,
+                });
+            }
+            DebuggerCommand::Watch(name) => {
+                let watchpoint = self.session.set_watchpoint(&name)?;
+                output::emit(&Event::WatchpointSet {
+                    id: watchpoint.id,
+                    expression: &watchpoint.expression,
+                });
+            }
+            DebuggerCommand::Display(Some(expression), format) => {
+                let id = self.next_display_id;
+                self.next_display_id += 1;
+                self.displays.push(AutoDisplay {
+                    id,
+                    expression,
+                    format,
+                    enabled: true,
+                });
+                if self.session.is_alive() {
+                    self.show_display(self.displays.len() - 1);
+                }
+            }
+            DebuggerCommand::Display(None, _) => self.show_displays(),
+            DebuggerCommand::Delete(ids) if ids.is_empty() => {
+                if !self.session.breakpoints().is_empty()
+                    && self.confirm("Delete all breakpoints?")
+                {
+                    self.session.delete_all_breakpoints()?;
+                }
+            }
+            DebuggerCommand::Delete(ids) => {
+                for id in ids {
+                    self.session.delete_breakpoint(BreakpointId(id))?;
+                }
+            }
+            DebuggerCommand::EnableBreakpoints(ids, mode) => {
+                for id in self.breakpoint_ids(ids) {
+                    self.session.enable_breakpoint(id, mode)?;
+                }
+            }
+            DebuggerCommand::DisableBreakpoints(ids) => {
+                for id in self.breakpoint_ids(ids) {
+                    self.session.disable_breakpoint(id)?;
+                }
+            }
+            DebuggerCommand::Ignore(id, count) => {
+                self.session.set_ignore_count(BreakpointId(id), count)?;
+                match count {
+                    0 => report!("Will stop next time breakpoint {} is reached.", id),
+                    1 => report!("Will ignore next crossing of breakpoint {}.", id),
+                    count => report!("Will ignore next {} crossings of breakpoint {}.", count, id),
+                }
+            }
+            DebuggerCommand::Condition(id, condition) => {
+                let unconditional = condition.is_none();
+                self.session.set_condition(BreakpointId(id), condition)?;
+                if unconditional {
+                    report!("Breakpoint {} now unconditional.", id);
+                }
+            }
+            DebuggerCommand::Clear(s) => {
+                let location = parse_location(&s)?;
+                let ids = self.session.clear_breakpoints(location)?;
+                let ids: Vec<usize> = ids.iter().map(|id| id.0).collect();
+                output::emit(&Event::BreakpointsDeleted(&ids));
+            }
+            DebuggerCommand::Undisplay(Some(id)) => {
+                let idx = self.display_index(id)?;
+                self.displays.remove(idx);
+            }
+            DebuggerCommand::Undisplay(None) => {
+                if !self.displays.is_empty() && self.confirm("Delete all auto-display expressions?")
+                {
+                    self.displays.clear();
+                }
+            }
+            DebuggerCommand::EnableDisplay(id, enabled) => {
+                let idx = self.display_index(id)?;
+                self.displays[idx].enabled = enabled;
+            }
+            DebuggerCommand::InfoDisplay => {
+                let displays: Vec<DisplayInfo> = self
+                    .displays
+                    .iter()
+                    .map(|display| DisplayInfo {
+                        id: display.id,
+                        expression: display.expression.clone(),
+                        format: display.format.letter(),
+                        enabled: display.enabled,
+                    })
+                    .collect();
+                output::emit(&Event::Displays(&displays));
+            }
+            DebuggerCommand::Break(s, force) => {
+                let location = parse_location(&s)?;
+                // A function name that matches nothing may be a typo
+                let location = match location {
+                    Location::Function(name) if self.session.functions_matching(&name).is_empty() => {
+                        self.suggest_function(&name)
+                    }
+                    location => location,
+                };
+                let bare_line = match location {
+                    Location::Line(None, _) => true,
+                    _ => false,
+                };
+                // A name that matches several functions: let the user pick
+                if let Location::Function(_) | Location::FunctionEntry(_) = location {
+                    let addrs = self.session.resolve_all(&location);
+                    if addrs.len() > 1 {
+                        let chosen = self.choose_sites(&location, &addrs);
+                        if chosen.is_empty() {
+                            report!("Cancelled.");
+                            return Ok(true);
+                        }
+                        if chosen.len() < addrs.len() {
+                            for addr in chosen {
+                                let sites = vec![addr];
+                                match self.session.set_breakpoint_sites(location.clone(), sites) {
+                                    Ok(id) => self.report_breakpoint(id),
+                                    Err(err @ SessionError::DuplicateBreakpoint(..)) => {
+                                        report!("{}", err)
+                                    }
+                                    Err(err) => return Err(err.into()),
+                                }
+                            }
+                            return Ok(true);
+                        }
+                    }
+                }
+                let id = match self.session.set_breakpoint(location, force) {
+                    Ok(id) => id,
+                    Err(err @ SessionError::DuplicateBreakpoint(..)) => {
+                        report!("{}", err);
+                        return Ok(true);
+                    }
+                    Err(err) => return Err(err.into()),
+                };
+                // Say which file a bare line number was taken from when there was a choice
+                if bare_line && self.session.debug_data().file_count() > 1 {
+                    let location = self.session.breakpoint(id).map(|bp| &bp.location);
+                    if let Some(Location::Line(Some(file), _)) = location {
+                        report!("No file given; using {}.", output::location(file));
+                    }
+                }
+                self.report_breakpoint(id);
+            }
+            DebuggerCommand::Dprintf(location, args) => {
+                let location = parse_location(&location)?;
+                match self.session.set_dprintf(location, &args) {
+                    Ok(id) => self.report_breakpoint(id),
+                    Err(err @ SessionError::DuplicateBreakpoint(..)) => report!("{}", err),
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            DebuggerCommand::RBreak(regex) => {
+                let regex = Regex::new(&regex).map_err(DebuggerError::BadRegex)?;
+                let names = self.session.function_names_where(|name| regex.is_match(name));
+                let mut count = 0;
+                for name in names {
+                    match self.session.set_breakpoint(Location::Function(name), false) {
+                        Ok(id) => {
+                            self.report_breakpoint(id);
+                            count += 1;
+                        }
+                        Err(err @ SessionError::DuplicateBreakpoint(..)) => report!("{}", err),
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+                match count {
+                    0 => report!("No new breakpoints set."),
+                    1 => report!("1 breakpoint set."),
+                    count => report!("{} breakpoints set.", count),
+                }
+            }
+            DebuggerCommand::InfoScope(text) => {
+                let location = parse_location(&text)?;
+                let addr = match self.session.resolve(&location) {
+                    Some(addr) => addr,
+                    None => return Err(SessionError::UnknownLocation(location).into()),
+                };
+                let (function, variables) = match self.session.variables_in_scope(addr) {
+                    Some((func, variables)) => {
+                        let debug_data = self.session.debug_data();
+                        let variables: Vec<ScopeVariable> = variables
+                            .into_iter()
+                            .map(|(var, location)| ScopeVariable {
+                                name: var.name.clone(),
+                                location,
+                                size: debug_data.type_size(var.type_id),
+                            })
+                            .collect();
+                        (func.display_name(), variables)
+                    }
+                    None => {
+                        report!("No function contains address {:#x}.", addr);
+                        return Ok(true);
+                    }
+                };
+                output::emit(&Event::Scope {
+                    location: &text,
+                    function: &function,
+                    variables: &variables,
+                });
+            }
+            DebuggerCommand::InfoOutput => match self.session.captured_output() {
+                Some((lines, dropped)) => {
+                    if dropped > 0 {
+                        report!("({} earlier line(s) dropped to stay under the limit)", dropped);
+                    }
+                    for (stream, text) in &lines {
+                        output::emit(&Event::InferiorOutput {
+                            stream: *stream,
+                            text,
+                        });
+                    }
+                }
+                None => report!("No output captured."),
+            },
+            DebuggerCommand::InfoSharedLibrary => {
+                let libraries: Vec<SharedLibraryInfo> = self
+                    .session
+                    .shared_libraries()
+                    .iter()
+                    .map(|lib| SharedLibraryInfo {
+                        path: lib.path.display().to_string(),
+                        base: lib.base,
+                        text: lib.text_range(),
+                        symbols: lib.debug_info(),
+                    })
+                    .collect();
+                output::emit(&Event::SharedLibraries(&libraries));
+            }
+            DebuggerCommand::SharedLibrary(regex) => {
+                let pattern = match &regex {
+                    Some(regex) => Some(Regex::new(regex).map_err(DebuggerError::BadRegex)?),
+                    None => None,
+                };
+                let loaded = self.session.load_library_symbols(|path| {
+                    let path = path.to_string_lossy();
+                    pattern.as_ref().map_or(true, |pattern| pattern.is_match(&path))
+                });
+                if loaded.is_empty() {
+                    match regex {
+                        Some(regex) => report!("No loaded shared libraries match \"{}\".", regex),
+                        None => report!("No shared libraries loaded at this time."),
+                    }
+                }
+                for (path, symbols) in loaded {
+                    report!("Reading symbols from {}...", path.display());
+                    if symbols != DebugInfo::Dwarf {
+                        report!("(no debugging symbols found)");
+                    }
+                }
+            }
+            DebuggerCommand::InfoFunctions(regex, mangled) => {
+                let regex = match regex {
+                    Some(regex) => Some(Regex::new(&regex).map_err(DebuggerError::BadRegex)?),
+                    None => None,
+                };
+                let mut functions: Vec<FunctionInfo> = self
+                    .session
+                    .functions()
+                    .into_iter()
+                    .map(|(file, func, address)| FunctionInfo {
+                        name: func.display_name(),
+                        linkage_name: if mangled { func.linkage_name.clone() } else { None },
+                        address,
+                        file: file.to_string(),
+                    })
+                    .filter(|func| regex.as_ref().map_or(true, |regex| regex.is_match(&func.name)))
+                    .collect();
+                functions.sort_by(|a, b| a.name.cmp(&b.name).then(a.address.cmp(&b.address)));
+                functions.dedup_by(|a, b| a.name == b.name && a.address == b.address);
+                output::emit(&Event::Functions(&functions));
+            }
+            DebuggerCommand::InfoVariables(regex) => {
+                let regex = match regex {
+                    Some(regex) => Some(Regex::new(&regex).map_err(DebuggerError::BadRegex)?),
+                    None => None,
+                };
+                let mut variables: Vec<VariableInfo> = self
+                    .session
+                    .global_variables()
+                    .into_iter()
+                    .filter(|(_, _, var, _)| {
+                        regex.as_ref().map_or(true, |regex| regex.is_match(&var.name))
+                    })
+                    .map(|(debug_data, file, var, address)| VariableInfo {
+                        name: var.name.clone(),
+                        declaration: output::declaration(debug_data, var.type_id, &var.name),
+                        address,
+                        file: file.to_string(),
+                    })
+                    .collect();
+                variables.sort_by(|a, b| a.name.cmp(&b.name).then(a.address.cmp(&b.address)));
+                variables.dedup_by(|a, b| a.name == b.name && a.address == b.address);
+                output::emit(&Event::Variables(&variables));
+            }
+            DebuggerCommand::InfoSources => {
+                let units: Vec<SourcesInfo> = self
+                    .session
+                    .debug_data()
+                    .compilation_units()
+                    .iter()
+                    .map(|unit| SourcesInfo {
+                        compilation_unit: unit.name.clone(),
+                        files: unit
+                            .source_files
+                            .iter()
+                            .map(|path| (path.clone(), Path::new(path).exists()))
+                            .collect(),
+                    })
+                    .collect();
+                output::emit(&Event::Sources(&units));
+            }
+            DebuggerCommand::InfoBreakpoints => {
+                output::emit(&Event::Breakpoints(&self.breakpoint_infos()));
+            }
+        }
+        Ok(true)
+    }
+
+    /// What `info breakpoints` shows of each breakpoint.
+    fn breakpoint_infos(&self) -> Vec<BreakpointInfo> {
+        let session = &self.session;
+        session
+            .breakpoints()
+            .iter()
+            .map(|bp| BreakpointInfo {
+                id: bp.id.0,
+                location: bp.location.to_string(),
+                enabled: bp.enabled,
+                mode: bp.mode,
+                condition: bp.condition.clone(),
+                ignore_count: bp.ignore_count,
+                hits: bp.hits,
+                dprintf: bp.dprintf.clone(),
+                sites: bp
+                    .addresses
+                    .iter()
+                    .map(|&addr| BreakpointSite {
+                        address: addr,
+                        function: session.function_at(addr),
+                        line: session.line_at(addr),
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Called when `break` names a function that doesn't exist (yet). Looks for functions whose
+    /// names contain `name`, ignoring case: a single one is offered with a "Did you mean" prompt,
+    /// several are listed. Without a prompt (batch and JSON mode) nobody can take a suggestion,
+    /// so even a single one is only listed. Returns the location to break on, which is `name`
+    /// itself (and so a pending breakpoint) unless the user took the suggestion.
+    fn suggest_function(&mut self, name: &str) -> Location {
+        const MAX_CANDIDATES: usize = 10;
+        let needle = name.to_lowercase();
+        let candidates = self
+            .session
+            .function_names_where(|candidate| candidate.to_lowercase().contains(&needle));
+        match candidates.len() {
+            0 => {}
+            1 if self.readline.is_some() => {
+                if self.ask(&format!("Did you mean `{}`?", candidates[0])) {
+                    return Location::Function(candidates[0].clone());
+                }
+            }
+            count => {
+                report!("Function \"{}\" not defined. Similar functions:", name);
+                for candidate in candidates.iter().take(MAX_CANDIDATES) {
+                    report!("  {}", output::function(candidate));
+                }
+                if count > MAX_CANDIDATES {
+                    report!("  ... and {} more", count - MAX_CANDIDATES);
+                }
             }
-            Err(DwarfError::DwarfFormatError(err)) => {
-                println!("Could not debugging symbols from {}: {:?}", target, err);
-                std::process::exit(1);
+        }
+        Location::Function(name.to_string())
+    }
+
+    /// The local variables of the selected frame (which is `frame`), each with its value as
+    /// `print` shows it or the reason it can't be read. None without debug info for the frame.
+    fn frame_locals(&self, frame: &inferior::Frame) -> Option<Vec<(String, String)>> {
+        frame.line.as_ref()?;
+        let names = self.session.local_variables().ok()?;
+        Some(
+            names
+                .into_iter()
+                .map(|name| {
+                    let value = match self.session.evaluate(&name) {
+                        Ok((value, _)) => self.session.format_value(&value, Format::Natural),
+                        Err(err) => format!("<{}>", err),
+                    };
+                    (name, value)
+                })
+                .collect(),
+        )
+    }
+
+    /// The breakpoints a command numbers, or all of them when it gives none.
+    fn breakpoint_ids(&self, ids: Vec<usize>) -> Vec<BreakpointId> {
+        if ids.is_empty() {
+            self.session.breakpoints().iter().map(|bp| bp.id).collect()
+        } else {
+            ids.into_iter().map(BreakpointId).collect()
+        }
+    }
+
+    /// Asks which of the addresses a location matches to break at, with a numbered menu: 0
+    /// cancels, 1 picks them all, and the others pick one each (several numbers may be given).
+    /// Returns the addresses picked, none if cancelled. Without a prompt (batch and JSON mode), or
+    /// with `set confirm off`, all of them are picked.
+    fn choose_sites(&mut self, location: &Location, addrs: &[usize]) -> Vec<usize> {
+        let readline = match self.readline.as_mut() {
+            Some(readline) if self.confirm => readline,
+            _ => {
+                report!(
+                    "Warning: \"{}\" matches {} locations; breaking at all of them.",
+                    location,
+                    addrs.len()
+                );
+                return addrs.to_vec();
+            }
+        };
+        let mut menu = vec!["[0] cancel".to_string(), "[1] all".to_string()];
+        for (idx, &addr) in addrs.iter().enumerate() {
+            let line = self.session.line_at(addr).map(|line| line.to_string());
+            let function_name = self.session.function_at(addr);
+            menu.push(format!(
+                "[{}] {}",
+                idx + 2,
+                output::code_location(addr, function_name.as_deref(), line.as_deref())
+            ));
+        }
+        for item in &menu {
+            report!("{}", item);
+        }
+        let answer = match readline.readline("> ") {
+            Ok(answer) => answer,
+            Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => return Vec::new(),
+            Err(err) => panic!("Unexpected I/O error: {:?}", err),
+        };
+        let mut chosen = Vec::new();
+        for word in answer.split_whitespace() {
+            match word.parse::<usize>() {
+                Ok(0) => return Vec::new(),
+                Ok(1) => return addrs.to_vec(),
+                Ok(choice) if choice - 2 < addrs.len() => {
+                    if !chosen.contains(&addrs[choice - 2]) {
+                        chosen.push(addrs[choice - 2]);
+                    }
+                }
+                _ => report!("No choice \"{}\".", word),
             }
+        }
+        chosen
+    }
+
+    /// Asks a yes/no question at the prompt, before doing something that can't be undone.
+    /// Ctrl+C, Ctrl+D and anything but "y" or "yes" mean no; unlike at the command prompt, Ctrl+D
+    /// doesn't quit. With `set confirm off`, and without a prompt (batch and JSON mode), the
+    /// answer is always yes.
+    fn confirm(&mut self, question: &str) -> bool {
+        if !self.confirm || self.readline.is_none() {
+            return true;
+        }
+        self.ask(question)
+    }
+
+    /// Asks a yes/no question at the prompt, whatever `set confirm` says. Without a prompt the
+    /// answer is no.
+    fn ask(&mut self, question: &str) -> bool {
+        let readline = match self.readline.as_mut() {
+            Some(readline) => readline,
+            None => return false,
         };
+        match readline.readline(&format!("{} (y or n) ", question)) {
+            Ok(answer) => {
+                let answer = answer.trim().to_lowercase();
+                answer == "y" || answer == "yes"
+            }
+            Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => false,
+            Err(err) => panic!("Unexpected I/O error: {:?}", err),
+        }
+    }
 
-        let history_path = format!("{}/.deet_history", std::env::var("HOME").unwrap());
-        let mut readline = Editor::<()>::new();
-        // Attempt to load history from ~/.deet_history if it exists
-        let _ = readline.load_history(&history_path);
+    /// Loads another target binary (or the same one, rebuilt) in place of the current one, and
+    /// says what became of the breakpoints. Kills the inferior without asking.
+    fn switch_target(&mut self, path: &str) -> Result<(), DebuggerError> {
+        let pid = self.session.inferior.as_ref().map(|inferior| inferior.pid());
+        let reload = self.session.load_target(path)?;
+        if let Some(pid) = pid {
+            report!("[Process {} killed]", pid);
+        }
+        self.exit_status = None;
+        self.report_symbols();
+        for id in reload.resolved.iter().chain(reload.pending.iter()) {
+            self.report_breakpoint(*id);
+        }
+        for id in reload.stale {
+            report!(
+                "Warning: breakpoint {} is at a raw address, which is probably not the same code \
+                 in {}.",
+                id.0,
+                path
+            );
+        }
+        Ok(())
+    }
 
-        Debugger {
-            target: target.to_string(),
-            history_path,
-            readline,
-            inferior: None,
-            debug_data,
-            break_points: vec![]
+    /// Before `run` or `start` starts the program, loads the target's symbols again if it was
+    /// rebuilt since they were read (unless `set auto-reload off`).
+    fn reload_if_changed(&mut self) -> Result<(), DebuggerError> {
+        if self.auto_reload && self.session.target_changed() {
+            let target = self.session.target().to_string();
+            report!("`{}' has changed; re-reading symbols.", target);
+            self.switch_target(&target)?;
         }
+        Ok(())
     }
 
-    pub fn run(&mut self) {
-        self.debug_data.print();
-        loop {
-            match self.get_next_command() {
-                DebuggerCommand::Run(args) => {
-                    self.inferior.take().map(|mut inferior| {
-                        self.break_points = inferior.kill();
-                    });
-                    if let Some(inferior) = Inferior::new(&self.target, &args, &mut self.break_points) {
-                        // Create the inferior
-                        self.inferior = Some(inferior);
-                        // TODO (milestone 1): make the inferior run
-                        // You may use self.inferior.as_mut().unwrap() to get a mutable reference
-                        // to the Inferior object
-                        if let Ok(status) = self.inferior.as_mut().unwrap().continue_running(&mut self.break_points) {
-                            match status {
-                                Status::Exited(code) => println!("Exited with code {}", code),
-                                Status::Signaled(sig) => println!("Signaled with signal {}", sig),
-                                Status::Stopped(sig, ins) => {
-                                    if let Some(line) = self.debug_data.get_line_from_addr(ins as usize) {
-                                        if let Some(function_name) = self.debug_data.get_function_from_addr(ins as usize) {
-                                            println!("Stoped by signal {}, at {} {}", sig, function_name, line);
-                                            println!("addr: {:#x}", ins);
-                                            continue;
-                                        }
-                                    }                                    
-                                    println!("Stoped by signal {}, at instruction 0x{:x}", sig, ins);
-                                }
-                            };
-                        } else {
-                            println!("failed to continue to run")
-                        }
-                    } else {
-                        println!("Error starting subprocess");
+    /// Reports the source search list, with the compilation and current directories that are
+    /// searched after it.
+    fn show_directories(&self) {
+        let mut dirs: Vec<String> = self
+            .session
+            .source_directories
+            .iter()
+            .map(|dir| dir.display().to_string())
+            .collect();
+        dirs.push("$cdir".to_string());
+        dirs.push("$cwd".to_string());
+        report!("Source directories searched: {}", dirs.join(":"));
+    }
+
+    /// Before `run` or `start` kills a live inferior, asks whether that's really what the user
+    /// wants.
+    fn confirm_restart(&mut self) -> bool {
+        if !self.session.is_alive() {
+            return true;
+        }
+        let confirmed = self.confirm(
+            "The program being debugged has been started already. Start it from the beginning?",
+        );
+        if !confirmed {
+            report!("Program not restarted.");
+        }
+        confirmed
+    }
+
+    /// Executes each line of a command file as if it had been typed at the prompt. Blank lines and
+    /// lines starting with `#` are skipped. A failing line aborts the rest of the file unless
+    /// `keep_going` is set. Returns false if the file quit the debugger.
+    fn source_file(&mut self, path: &str, keep_going: bool) -> Result<bool, DebuggerError> {
+        let contents =
+            fs::read_to_string(path).map_err(|err| DebuggerError::Io(path.to_string(), err))?;
+        for (idx, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            report!("{}{}", if self.defining.is_some() { ">" } else { "(deet) " }, line);
+            match self.execute_line(line) {
+                Ok(true) => {}
+                Ok(false) => return Ok(false),
+                Err(err) => {
+                    report!("{}:{}: {}", path, idx + 1, err);
+                    if !keep_going {
+                        return Ok(true);
                     }
                 }
-                DebuggerCommand::Cont => {
-                    if let Some(inferior) = &mut self.inferior {
-                        if inferior.continue_running(&mut self.break_points).is_err() {
-                            println!("Error continuing process");
+            }
+        }
+        Ok(true)
+    }
+
+    /// Reports a stop from `run &` or `continue &`, or if there was none yet, watches for it.
+    fn report_background(&mut self, stop: Option<StopEvent>) {
+        match stop {
+            Some(stop) => self.report_stop(stop),
+            None => {
+                if let Some(inferior) = &self.session.inferior {
+                    let prompt = self.readline.as_ref().map(|_| (PROMPT, self.typed_line.clone()));
+                    self.background = Some(Watcher::start(inferior.pid(), prompt));
+                }
+            }
+        }
+    }
+
+    /// Reports the stop of the program running in the background, if it has stopped.
+    fn collect_background_stop(&mut self) -> Result<(), DebuggerError> {
+        match &self.background {
+            Some(watcher) if watcher.has_stopped() => self.background = None,
+            _ => return Ok(()),
+        }
+        let stop = self.session.wait_background()?;
+        self.report_background(stop);
+        Ok(())
+    }
+
+    /// Interrupts the program running in the background so that a command can change it, and
+    /// returns whether to set it going again afterwards: not if it had stopped by itself in the
+    /// meantime, which is reported instead.
+    fn pause_background(&mut self) -> Result<bool, DebuggerError> {
+        let watcher = match self.background.take() {
+            Some(watcher) => watcher,
+            None => return Ok(false),
+        };
+        self.session.interrupt()?;
+        watcher.wait();
+        match self.session.wait_background()? {
+            Some(StopEvent::Stopped {
+                signal: Signal::SIGINT,
+                ..
+            }) => Ok(true),
+            stop => {
+                self.report_background(stop);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Shows a profile's histogram, by function or (with `callers`) by function and caller.
+    fn report_profile(&self, profile: &Profile, callers: bool) {
+        let mut rows: Vec<(String, usize)> = if callers {
+            profile
+                .callers
+                .iter()
+                .map(|((function, caller), count)| (format!("{} <- {}", function, caller), *count))
+                .collect()
+        } else {
+            profile
+                .functions
+                .iter()
+                .map(|(function, count)| (function.clone(), *count))
+                .collect()
+        };
+        rows.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        output::emit(&Event::Profile {
+            samples: profile.samples,
+            rows: &rows,
+        });
+    }
+
+    /// Kills the inferior, if there is one, telling the user about it.
+    fn kill_inferior(&mut self) {
+        match &self.session.inferior {
+            Some(inferior) if self.session.is_alive() => {
+                report!("Killing process {}", inferior.pid())
+            }
+            _ => {}
+        }
+        self.session.kill();
+    }
+
+    /// Arguments given to `run` or `start` replace the stored ones, redirections included, as a
+    /// new command line would; with neither, the program is run with the ones it had before.
+    fn set_arguments(&mut self, args: Vec<String>, redirections: Vec<Redirection>) {
+        if !args.is_empty() || !redirections.is_empty() {
+            self.session.args = args;
+            self.session.redirections = redirections;
+        }
+    }
+
+    /// Confirms a new breakpoint (or one that was pending and has now resolved), with one line per
+    /// location when it has several.
+    fn report_breakpoint(&self, id: BreakpointId) {
+        let addrs = self.session.breakpoint_addresses(id);
+        if addrs.is_empty() {
+            let location = match self.session.breakpoint(id) {
+                Some(bp) => bp.location.to_string(),
+                None => return,
+            };
+            output::emit(&Event::BreakpointPending {
+                id: id.0,
+                location: &location,
+            });
+            return;
+        }
+        for (idx, &addr) in addrs.iter().enumerate() {
+            let line = self.session.line_at(addr);
+            let function_name = self.session.function_at(addr);
+            output::emit(&Event::BreakpointSet {
+                id: id.0,
+                sub_location: if addrs.len() > 1 { Some(idx + 1) } else { None },
+                address: addr,
+                function: function_name.as_deref(),
+                line: line.as_ref(),
+            });
+        }
+    }
+
+    /// Reports where `next` or `step` stopped, after the functions without line information it
+    /// went through for the first time (even if it then failed).
+    fn report_line_step(
+        &mut self,
+        stop: Result<StopEvent, SessionError>,
+    ) -> Result<(), DebuggerError> {
+        for function in self.session.take_no_line_functions() {
+            report!(
+                "Stepped through {}, which has no line number information.",
+                output::function(&function)
+            );
+        }
+        self.report_stop(stop?);
+        Ok(())
+    }
+
+    /// Reports how the inferior stopped or exited after it was resumed, after any pending
+    /// breakpoints that resolved and any breakpoints that could not be inserted.
+    fn report_stop(&mut self, stop: StopEvent) {
+        self.last_stop = Some(stop.clone());
+        for id in self.session.take_resolved_breakpoints() {
+            self.report_breakpoint(id);
+        }
+        for err in self.session.take_breakpoint_errors() {
+            report!("Warning: {}", err);
+        }
+        for event in self.session.take_watchpoint_events() {
+            match event {
+                WatchpointEvent::Changed {
+                    id,
+                    expression,
+                    old,
+                    new,
+                } => output::emit(&Event::WatchpointChanged {
+                    id,
+                    expression: &expression,
+                    old: &old,
+                    new: &new,
+                }),
+                WatchpointEvent::OutOfScope(id) => output::emit(&Event::WatchpointScope(id)),
+            }
+        }
+        let breakpoint_time = self.session.take_breakpoint_time();
+        if self.maint_time {
+            report!(
+                "Breakpoint bookkeeping: {:.3} ms",
+                breakpoint_time.as_secs_f64() * 1000.0
+            );
+        }
+        match stop {
+            StopEvent::Exited(code) => {
+                self.exit_status = Some(code);
+                output::emit(&Event::Exited(code));
+                self.show_run_stats();
+            }
+            StopEvent::Signaled(sig) => {
+                self.exit_status = Some(128 + sig as i32);
+                output::emit(&Event::Signaled(sig));
+                self.show_run_stats();
+            }
+            StopEvent::Stopped {
+                signal,
+                address,
+                reason,
+                breakpoint,
+                function,
+                line,
+            } => {
+                let solib_stop = self.session.take_solib_stop();
+                match self.session.take_caught() {
+                    _ if solib_stop => report!("Stopped due to shared library event."),
+                    Some(caught) => {
+                        output::emit(&Event::Caught {
+                            catchpoint: caught.catchpoint,
+                            address,
+                            function: function.as_deref(),
+                            line: line.as_ref(),
+                            assertion: caught.assertion.as_ref(),
+                        });
+                        if let Ok(frames) = self.session.backtrace() {
+                            output::emit(&Event::Backtrace(&frames));
                         }
-                    } else {
-                        println!("Nothing running!");
                     }
-                },
-                DebuggerCommand::Quit => {
-                    self.inferior.take().map(|mut inferior| {
-                        inferior.kill();
-                    });
-                    return;
-                },
-                DebuggerCommand::Backtrace => {
-                    self.inferior.as_ref().map(|inf| inf.print_backtrace(&self.debug_data));
-                },
-                DebuggerCommand::Break(s) => {
-                    match parse_address(&s) {
-                        ParseAddressRes::Addr(addr) => {
-                            self.break_points.push(addr);
-                            println!("Set breakpoint at {:#x}", addr);
-                        },
-                        ParseAddressRes::FalseAddr => {
-                            println!("Bad breakpoint!");
-                        },
-                        ParseAddressRes::FunctionName(function_name) => {
-                            if let Some(addr) = self.debug_data.get_addr_for_function(None, function_name) {
-                                self.break_points.push(addr);
-                                println!("Set breakpoint at func: {}, at addr: {:#x}", function_name, addr);
-                            } else {
-                                println!("Bad breakpoint!");
-                            }
-                        },
-                        ParseAddressRes::LineNumber(line_number) => {
-                            if let Some(addr) = self.debug_data.get_addr_for_line(None, line_number) {
-                                self.break_points.push(addr);
-                                println!("Set breakpoint at line: {}, at addr: {:#x}", line_number, addr);
-                            } else {
-                                println!("Bad breakpoint!");
-                            }
-                        }
+                    None => output::emit(&Event::Stopped {
+                        signal,
+                        address,
+                        reason,
+                        breakpoint: breakpoint.map(|id| id.0),
+                        function: function.as_deref(),
+                        line: line.as_ref(),
+                    }),
+                }
+                if let Some(line) = &line {
+                    self.list_position = Some(ListPosition::Around(line.file.clone(), line.number));
+                }
+                if let Some(line) = line {
+                    if self.context_lines > 0 {
+                        self.show_source_context(&line.file, line.number);
+                    } else if self.print_source {
+                        self.show_source_line(&line.file, line.number);
                     }
                 }
+                self.show_displays();
+            }
+        }
+    }
+
+    /// Shows a newly selected frame and its source line, which `list` then lists around.
+    fn show_frame(&mut self, level: usize, frame: &inferior::Frame) {
+        output::emit(&Event::Frame { level, frame });
+        if let Some(line) = &frame.line {
+            self.list_position = Some(ListPosition::Around(line.file.clone(), line.number));
+            self.show_source_line(&line.file, line.number);
+        }
+    }
+
+    /// Shows the text of a source line, or nothing if its file can't be read.
+    fn show_source_line(&mut self, file: &str, number: usize) {
+        if let Some(text) = self.session.source_line(file, number) {
+            output::emit(&Event::SourceLine {
+                number,
+                text: &text,
+            });
+        }
+    }
+
+    /// Shows `set context` lines of source above and below a line, or nothing if its file
+    /// can't be read.
+    fn show_source_context(&mut self, file: &str, number: usize) {
+        let first = number.saturating_sub(self.context_lines);
+        let last = number + self.context_lines;
+        if let Some(lines) = self.session.source_lines(file, first, last) {
+            output::emit(&Event::SourceContext {
+                lines: &lines,
+                current: number,
+            });
+        }
+    }
+
+    /// Redraws the source pane, if `tui enable` is on, around the line the program last stopped
+    /// at. Done before every prompt, so new breakpoints (and a resized terminal) show at once.
+    fn draw_tui(&mut self) {
+        let height = match &self.tui {
+            Some(tui) => tui.pane_height(),
+            None => return,
+        };
+        let line = match &self.last_stop {
+            Some(StopEvent::Stopped { line: Some(line), .. }) => Some(line.clone()),
+            _ => None,
+        };
+        let lines = line.as_ref().and_then(|line| {
+            let first = line.number.saturating_sub(height / 2).max(1);
+            self.session.source_lines(&line.file, first, first + height - 1)
+        });
+        let view = match (&line, &lines) {
+            (Some(line), Some(lines)) => Some((line, lines, self.breakpoint_lines(&line.file))),
+            _ => None,
+        };
+        let view = view.as_ref().map(|(line, lines, breakpoints)| SourceView {
+            file: &line.file,
+            lines,
+            current: line.number,
+            breakpoints,
+        });
+        if let Some(tui) = self.tui.as_mut() {
+            tui.draw(view.as_ref());
+        }
+    }
+
+    /// The lines of a file that have a breakpoint.
+    fn breakpoint_lines(&self, file: &str) -> Vec<usize> {
+        self.session
+            .breakpoints()
+            .iter()
+            .flat_map(|breakpoint| breakpoint.addresses.iter())
+            .filter_map(|&addr| self.session.line_at(addr))
+            .filter(|line| line.file == file)
+            .map(|line| line.number)
+            .collect()
+    }
+
+    /// `list`: shows `set listsize` lines of source, clamped to the start and end of the file,
+    /// and remembers them so that the next `list` or `list -` carries on from there.
+    fn list(&mut self, argument: ListArgument) -> Result<(), DebuggerError> {
+        let size = self.list_size;
+        // The first line of the window centered on a line
+        let around = |line: usize| line.saturating_sub(size / 2).max(1);
+        let backward = match argument {
+            ListArgument::Backward => true,
+            _ => false,
+        };
+        let position = match (argument, &self.list_position) {
+            (ListArgument::Around(location), _) => {
+                let (file, line) = self.list_location(&location)?;
+                ListPosition::Around(file, line)
+            }
+            (_, Some(position)) => position.clone(),
+            (_, None) => {
+                let (file, line) = self.list_location("main")?;
+                ListPosition::Around(file, line)
+            }
+        };
+        let (file, first, last) = match (backward, position) {
+            (true, ListPosition::Around(file, line)) => {
+                let end = around(line);
+                (file, end.saturating_sub(size).max(1), end - 1)
+            }
+            (true, ListPosition::Listed { file, first, .. }) => {
+                (file, first.saturating_sub(size).max(1), first - 1)
+            }
+            (false, ListPosition::Around(file, line)) => {
+                (file, around(line), around(line) + size - 1)
+            }
+            (false, ListPosition::Listed { file, last, .. }) => (file, last + 1, last + size),
+        };
+        if last < first {
+            return Err(DebuggerError::ListAtStart(file));
+        }
+        let count = self
+            .session
+            .source_line_count(&file)
+            .ok_or_else(|| DebuggerError::NoSourceFile(file.clone()))?;
+        if first > count {
+            return Err(DebuggerError::LineOutOfRange(first, file, count));
+        }
+        let lines = self
+            .session
+            .source_lines(&file, first, last)
+            .ok_or_else(|| DebuggerError::NoSourceFile(file.clone()))?;
+        output::emit(&Event::SourceLines {
+            file: &file,
+            lines: &lines,
+        });
+        self.list_position = Some(ListPosition::Listed {
+            file,
+            first,
+            last: last.min(count),
+        });
+        Ok(())
+    }
+
+    /// The file and line a `list` location refers to: a line (in the file being listed, or the
+    /// one with `main`), `file:line`, a function's first line, or the line of an address.
+    fn list_location(&self, location: &str) -> Result<(String, usize), DebuggerError> {
+        let addr = match parse_location(location)? {
+            Location::Line(Some(file), line) => {
+                let file = self.session.debug_data().source_file_matching(&file).unwrap_or(&file);
+                return Ok((file.to_string(), line));
+            }
+            Location::Line(None, line) => {
+                let file = match &self.list_position {
+                    Some(position) => position.file(),
+                    None => self.session.default_source_file().ok_or(SessionError::NoLineInfo)?,
+                };
+                return Ok((file.to_string(), line));
+            }
+            // A function is listed around its opening line, not the line after its prologue
+            Location::Function(name) => {
+                let location = Location::FunctionEntry(name);
+                self.session
+                    .resolve(&location)
+                    .ok_or(SessionError::UnknownLocation(location))?
+            }
+            location => self
+                .session
+                .resolve(&location)
+                .ok_or(SessionError::UnknownLocation(location))?,
+        };
+        let line = self.session.line_at(addr).ok_or(SessionError::NoLineInfo)?;
+        Ok((line.file, line.number))
+    }
+
+    /// Shows every enabled `display` expression.
+    fn show_displays(&self) {
+        for (idx, display) in self.displays.iter().enumerate() {
+            if display.enabled {
+                self.show_display(idx);
             }
         }
     }
 
-    /// This function prompts the user to enter a command, and continues re-prompting until the user
-    /// enters a valid command. It uses DebuggerCommand::from_tokens to do the command parsing.
-    ///
-    /// You don't need to read, understand, or modify this function.
+    /// Finds a display by the number the user knows it by.
+    fn display_index(&self, id: usize) -> Result<usize, DebuggerError> {
+        self.displays
+            .iter()
+            .position(|display| display.id == id)
+            .ok_or(DebuggerError::NoDisplay(id))
+    }
+
+    /// Evaluates and shows one `display` expression. Failures are shown in its place; the
+    /// expression stays registered, since it may be in scope at the next stop.
+    fn show_display(&self, idx: usize) {
+        let display = &self.displays[idx];
+        let value = self
+            .session
+            .evaluate(&display.expression)
+            .map(|(value, _)| self.session.format_value(&value, display.format))
+            .map_err(|err| err.to_string());
+        output::emit(&Event::Display {
+            id: display.id,
+            expression: &display.expression,
+            format: display.format.letter(),
+            value: value.as_ref().map(|s| s.as_str()).map_err(|s| s.as_str()),
+        });
+    }
+
+    /// Reads lines until one parses as a command, and returns it: from the prompt, or straight
+    /// from stdin in JSON mode. Ctrl+d (or the end of stdin) quits. An empty line repeats the last
+    /// command that repeats, such as `continue` or `list`; lines typed while a `define` is open are
+    /// captured into the definition, and the rest go through parse_line, so aliases and
+    /// user-defined commands are expanded.
     fn get_next_command(&mut self) -> DebuggerCommand {
         loop {
-            // Print prompt and get next line of user input
-            match self.readline.readline("(deet) ") {
-                Err(ReadlineError::Interrupted) => {
-                    // User pressed ctrl+c. We're going to ignore it
-                    println!("Type \"quit\" to exit");
-                }
-                Err(ReadlineError::Eof) => {
-                    // User pressed ctrl+d, which is the equivalent of "quit" for our purposes
-                    return DebuggerCommand::Quit;
-                }
-                Err(err) => {
-                    panic!("Unexpected I/O error: {:?}", err);
-                }
-                Ok(line) => {
-                    if line.trim().len() == 0 {
-                        continue;
-                    }
-                    self.readline.add_history_entry(line.as_str());
-                    if let Err(err) = self.readline.save_history(&self.history_path) {
-                        println!(
-                            "Warning: failed to save history file at {}: {}",
-                            self.history_path, err
-                        );
-                    }
-                    let tokens: Vec<&str> = line.split_whitespace().collect();
-                    if let Some(cmd) = DebuggerCommand::from_tokens(&tokens) {
-                        return cmd;
-                    } else {
-                        println!("Unrecognized command.");
+            let prompt = if self.defining.is_some() { ">" } else { PROMPT };
+            let line = match self.readline.as_mut() {
+                // Without a prompt (JSON mode), read commands straight from stdin
+                None => match read_line_from_stdin() {
+                    Some(line) => line,
+                    None => return DebuggerCommand::Quit,
+                },
+                // Print prompt and get next line of user input
+                Some(readline) => {
+                    self.typed_line.set_prompting(true);
+                    let result = readline.readline(prompt);
+                    self.typed_line.set_prompting(false);
+                    match result {
+                        Err(ReadlineError::Interrupted) => {
+                            // User pressed ctrl+c. We're going to ignore it
+                            report!("Type \"quit\" to exit");
+                            continue;
+                        }
+                        Err(ReadlineError::Eof) => {
+                            // User pressed ctrl+d, which is the equivalent of "quit" for our
+                            // purposes
+                            return DebuggerCommand::Quit;
+                        }
+                        Err(err) => {
+                            panic!("Unexpected I/O error: {:?}", err);
+                        }
+                        Ok(line) => {
+                            if line.trim().len() == 0 {
+                                // Enter on its own repeats the last command, if that makes sense
+                                match &self.repeat_line {
+                                    Some(last) if self.defining.is_none() => last.clone(),
+                                    _ => continue,
+                                }
+                            } else {
+                                readline.add_history_entry(line.as_str());
+                                line
+                            }
+                        }
                     }
                 }
+            };
+            if line.trim().is_empty() || self.capture_definition_line(&line) {
+                continue;
+            }
+            match self.parse_line(&line) {
+                Some(cmd) => {
+                    self.repeat_line = line_to_repeat(&cmd, &line);
+                    return cmd;
+                }
+                None => report!("{}", self.unrecognized(&line)),
+            }
+        }
+    }
+}
+
+/// What Enter on its own runs after `line`, which parsed as `cmd`: the same line again, for a
+/// command that repeats.
+fn line_to_repeat(cmd: &DebuggerCommand, line: &str) -> Option<String> {
+    match cmd {
+        // Enter after `list 42` lists the lines after those, not the same ones
+        DebuggerCommand::List(ListArgument::Around(_)) => Some("list".to_string()),
+        _ if cmd.repeats_on_empty_line() => Some(line.to_string()),
+        _ => None,
+    }
+}
+
+/// Reads a line from stdin, or None at EOF.
+fn read_line_from_stdin() -> Option<String> {
+    let mut line = String::new();
+    match io::stdin().read_line(&mut line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line),
+    }
+}
+
+/// How deeply `define`d commands may invoke each other, which stops runaway recursion.
+const MAX_USER_COMMAND_DEPTH: usize = 64;
+
+/// Replaces `$argc` and `$arg0`..`$arg9` in a line of a user command's body.
+fn substitute_arguments(name: &str, line: &str, args: &[String]) -> Result<String, DebuggerError> {
+    let mut line = line.replace("$argc", &args.len().to_string());
+    for idx in 0..10 {
+        let placeholder = format!("$arg{}", idx);
+        if line.contains(&placeholder) {
+            let arg = args
+                .get(idx)
+                .ok_or_else(|| DebuggerError::MissingArgument(name.to_string(), idx))?;
+            line = line.replace(&placeholder, arg);
+        }
+    }
+    Ok(line)
+}
+
+/// Aliases that exist without being defined with `alias`.
+const DEFAULT_ALIASES: &[(&str, &str)] = &[
+    ("b", "break"),
+    ("bt", "backtrace"),
+    ("c", "continue"),
+    ("q", "quit"),
+    ("r", "run"),
+];
+
+/// Replaces an alias at the start of a line with what it stands for. Only the first word is
+/// expanded, and only once, so aliases can't loop.
+fn expand_alias(aliases: &BTreeMap<String, String>, line: &str) -> String {
+    let line = line.trim_start();
+    let first_word = match line.split_whitespace().next() {
+        Some(word) => word,
+        None => return line.to_string(),
+    };
+    match aliases.get(first_word) {
+        Some(expansion) => format!("{}{}", expansion, &line[first_word.len()..]),
+        None => line.to_string(),
+    }
+}
+
+/// The file `set logging on` writes to unless `set logging file` names another.
+const DEFAULT_LOGGING_FILE: &str = "deet.txt";
+
+const PROMPT: &str = "(deet) ";
+
+/// How many matches `find` reports before it stops searching.
+const MAX_FIND_MATCHES: usize = 100;
+
+/// The fewest printable characters in a row that `strings` reports.
+const MIN_STRING_RUN: usize = 4;
+
+/// Number of source lines `list` shows until `set listsize` changes it.
+const DEFAULT_LIST_SIZE: usize = 10;
+
+/// Number of instructions `info record` shows without an argument.
+const DEFAULT_RECORD_HISTORY: usize = 10;
+
+/// How many call sites `info heap` lists.
+const HEAP_SITE_LIMIT: usize = 10;
+
+/// The exit code of a batch run in which one of the debugger's commands failed, distinct from
+/// anything the program itself is likely to exit with.
+const INTERNAL_ERROR_EXIT_CODE: i32 = 125;
+
+/// Number of history entries kept when $DEET_HISTSIZE isn't set.
+const DEFAULT_HISTORY_SIZE: usize = 1000;
+
+/// Picks the history file: the --history-file flag, then $DEET_HISTFILE, then ~/.deet_history.
+/// Without $HOME, the history is kept in the current directory.
+fn history_path(flag: Option<String>) -> PathBuf {
+    if let Some(path) = flag.or_else(|| std::env::var("DEET_HISTFILE").ok()) {
+        return PathBuf::from(path);
+    }
+    match std::env::var("HOME") {
+        Ok(home) => Path::new(&home).join(".deet_history"),
+        Err(_) => PathBuf::from(".deet_history"),
+    }
+}
+
+/// Maximum number of history entries, from $DEET_HISTSIZE.
+fn history_size() -> usize {
+    std::env::var("DEET_HISTSIZE")
+        .ok()
+        .and_then(|size| size.parse().ok())
+        .unwrap_or(DEFAULT_HISTORY_SIZE)
+}
+
+/// Splits `function(arg, ...)` into the function name and the argument expressions.
+fn parse_call(call: &str) -> Option<(String, Vec<String>)> {
+    let open = call.find('(')?;
+    let inner = call[open + 1..].trim_end().strip_suffix(')')?;
+    let function = call[..open].trim();
+    if function.is_empty() {
+        return None;
+    }
+    let mut args = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+    for c in inner.chars() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                args.push(std::mem::replace(&mut current, String::new()));
+                continue;
             }
+            _ => {}
         }
+        current.push(c);
+    }
+    if !current.trim().is_empty() || !args.is_empty() {
+        args.push(current);
     }
+    Some((
+        function.to_string(),
+        args.iter().map(|arg| arg.trim().to_string()).collect(),
+    ))
+}
+
+/// Parses a breakpoint-style location: `*address`, `line`, `file:line` or a function name.
+fn parse_location(s: &str) -> Result<Location, DebuggerError> {
+    Ok(match parse_address(s) {
+        ParseAddressRes::Addr(addr) => Location::Address(addr),
+        ParseAddressRes::FunctionName(function_name) => {
+            Location::Function(function_name.to_string())
+        }
+        ParseAddressRes::FunctionEntry(function_name) => {
+            Location::FunctionEntry(function_name.to_string())
+        }
+        ParseAddressRes::LineNumber(line_number) => Location::Line(None, line_number),
+        ParseAddressRes::FileLine(file, line_number) => {
+            Location::Line(Some(file.to_string()), line_number)
+        }
+        ParseAddressRes::FalseAddr => return Err(DebuggerError::BadLocation(s.to_string())),
+    })
 }
 
 enum ParseAddressRes<'a> {
     Addr(usize),
     LineNumber(usize),
+    FileLine(&'a str, usize),
     FunctionName(&'a str),
+    /// `*function`
+    FunctionEntry(&'a str),
     FalseAddr
 }
 
 fn parse_address(addr: &str) -> ParseAddressRes {
     if addr.starts_with("*") {
         // addr
-        let addr = if (&addr[1..]).to_lowercase().starts_with("0x") {
-            &addr[3..]
-        } else {
-            &addr[1..]
-        };
-        match usize::from_str_radix(addr, 16).ok() {
+        let has_prefix = (&addr[1..]).to_lowercase().starts_with("0x");
+        let digits = if has_prefix { &addr[3..] } else { &addr[1..] };
+        match usize::from_str_radix(digits, 16).ok() {
             Some(addr) => ParseAddressRes::Addr(addr),
+            // Otherwise the entry of a function, as in `*main`
+            None if !has_prefix && digits.starts_with(|c: char| c.is_alphabetic() || c == '_') => {
+                ParseAddressRes::FunctionEntry(digits)
+            }
             None => ParseAddressRes::FalseAddr
         }
     } else {
         if let Ok(line_number) = addr.parse::<usize>() {
-            ParseAddressRes::LineNumber(line_number)
-        } else {
-            ParseAddressRes::FunctionName(&addr)
+            return ParseAddressRes::LineNumber(line_number);
+        }
+        // file:line, taking care not to split a C++ or Rust path like Foo::bar
+        if let Some(idx) = addr.rfind(':') {
+            let (file, line) = (&addr[..idx], &addr[idx + 1..]);
+            if let Ok(line_number) = line.parse::<usize>() {
+                if !file.is_empty() && !file.ends_with(':') {
+                    return ParseAddressRes::FileLine(file, line_number);
+                }
+            }
         }
+        ParseAddressRes::FunctionName(&addr)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn debugger() -> Debugger {
+        let target = format!("{}/samples/function_calls", env!("CARGO_MANIFEST_DIR"));
+        let options = Options {
+            skip_init_files: true,
+            batch: true,
+            symbols_banner: SymbolsBanner::Quiet,
+            ..Options::default()
+        };
+        Debugger::new(&target, options).unwrap()
+    }
+
+    /// What `info breakpoints` shows, without the numbers, which `save breakpoints` doesn't keep.
+    fn unnumbered_infos(debugger: &Debugger) -> Vec<BreakpointInfo> {
+        let mut infos = debugger.breakpoint_infos();
+        for info in infos.iter_mut() {
+            info.id = 0;
+        }
+        infos
+    }
+
+    #[test]
+    fn saved_breakpoints_keep_their_state() {
+        let mut debugger = debugger();
+        let lines = [
+            "break func1",
+            "break func2",
+            "break func3",
+            "enable once 1",
+            "disable 1",
+            "ignore 2 3",
+            "enable count 4 3",
+            "condition 3 a > 1 && a != 7",
+        ];
+        for line in lines.iter() {
+            debugger.execute_line(line).unwrap();
+        }
+        let path = std::env::temp_dir().join(format!("deet-save-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        assert_eq!(debugger.save_breakpoints(path).unwrap(), 3);
+
+        // Numbered differently once there's a breakpoint already
+        let mut restored = debugger();
+        restored.execute_line("break main").unwrap();
+        restored.execute_line(&format!("source {}", path)).unwrap();
+        let _ = fs::remove_file(path);
+        let saved = unnumbered_infos(&debugger);
+        assert_eq!(saved.len(), 3);
+        assert_eq!(unnumbered_infos(&restored)[1..], saved[..]);
+    }
+
+    #[test]
+    fn errors_are_worded_as_gdb_would() {
+        let not_found = io::Error::new(io::ErrorKind::NotFound, "No such file or directory");
+        let cases = vec![
+            (DebuggerError::UnrecognizedCommand("frob".into()), "Unrecognized command \"frob\"."),
+            (
+                DebuggerError::UnterminatedQuote("run 'a".into()),
+                "Unterminated quote in \"run 'a\".",
+            ),
+            (
+                DebuggerError::AmbiguousCommand("s".into(), vec!["set", "step"]),
+                "Ambiguous command \"s\": set, step.",
+            ),
+            (
+                DebuggerError::UnknownCommand("frob".into(), vec![]),
+                "Undefined command: \"frob\". Try \"help\".",
+            ),
+            (
+                DebuggerError::UnknownCommand("brek".into(), vec!["break".into()]),
+                "Undefined command: \"brek\". Did you mean \"break\"?",
+            ),
+            (
+                DebuggerError::UnknownCommand("stp".into(), vec!["step".into(), "stop".into()]),
+                "Undefined command: \"stp\". Did you mean \"step\" or \"stop\"?",
+            ),
+            (DebuggerError::BadLocation("1:2:3".into()), "Bad breakpoint location \"1:2:3\"."),
+            (
+                DebuggerError::Io("init.gdb".into(), not_found),
+                "init.gdb: No such file or directory.",
+            ),
+            (DebuggerError::NotADirectory("/etc/passwd".into()), "/etc/passwd: Not a directory."),
+            (
+                DebuggerError::ArgumentRequired("new working directory"),
+                "Argument required (new working directory).",
+            ),
+            (
+                DebuggerError::BadCall("func1".into()),
+                "Expected a call like \"function(arg, ...)\", not \"func1\".",
+            ),
+            (DebuggerError::NoDisplay(3), "No display number 3."),
+            (DebuggerError::NoSnapshot(Some("a".into())), "No snapshot named \"a\"."),
+            (DebuggerError::NoSnapshot(None), "No snapshot has been taken."),
+            (
+                DebuggerError::UserCommandDepth("loop".into()),
+                "Max user call depth (64) exceeded in \"loop\". Does it invoke itself?",
+            ),
+            (
+                DebuggerError::MissingArgument("show2".into(), 1),
+                "Missing argument 1 in user command \"show2\".",
+            ),
+            (DebuggerError::NoUserCommand("frob".into()), "Not a user command: \"frob\"."),
+            (DebuggerError::NoSourceFile("a.c".into()), "Can't read source file \"a.c\"."),
+            (
+                DebuggerError::LineOutOfRange(30, "a.c".into(), 25),
+                "Line number 30 out of range; \"a.c\" has 25 lines.",
+            ),
+            (DebuggerError::ListAtStart("a.c".into()), "Already at the start of a.c."),
+            (
+                DebuggerError::TargetRunning,
+                "The program is running. Use \"interrupt\" to stop it first.",
+            ),
+            (DebuggerError::NotRunning, "The program is not running."),
+            (
+                DebuggerError::Session(SessionError::NoProcess),
+                "The program is not being run.",
+            ),
+        ];
+        for (err, expected) in cases {
+            assert_eq!(err.to_string(), expected);
+        }
+        // The regex crate's own message follows, over several lines
+        let err = DebuggerError::BadRegex(Regex::new("func(").unwrap_err());
+        assert!(err.to_string().starts_with("Invalid regular expression: "));
+    }
+
+    #[test]
+    fn examining_unmapped_memory_is_an_error() {
+        let mut debugger = debugger();
+        debugger.execute_line("break main").unwrap();
+        debugger.execute_line("run").unwrap();
+        for line in ["x 0", "x/16 -8"].iter() {
+            match debugger.execute_line(line) {
+                Err(DebuggerError::Session(_)) => {}
+                other => panic!("{}: expected a session error, got {:?}", line, other),
+            }
+        }
+        // and the program can still be examined
+        debugger.execute_line("x/4 &global").unwrap();
+        debugger.session.kill();
+    }
+
+    /// The lines `list` showed last.
+    fn listed(debugger: &Debugger) -> (usize, usize) {
+        match &debugger.list_position {
+            Some(ListPosition::Listed { first, last, .. }) => (*first, *last),
+            _ => panic!("nothing has been listed"),
+        }
+    }
+
+    #[test]
+    fn list_stays_within_the_file() {
+        // function_calls.c has 25 lines
+        let mut debugger = debugger();
+        debugger.execute_line("list 3").unwrap();
+        assert_eq!(listed(&debugger), (1, 10));
+        match debugger.execute_line("list -") {
+            Err(DebuggerError::ListAtStart(file)) => assert!(file.ends_with("function_calls.c")),
+            other => panic!("expected to be at the start, got {:?}", other),
+        }
+        debugger.execute_line("list 24").unwrap();
+        assert_eq!(listed(&debugger), (19, 25));
+        match debugger.execute_line("list") {
+            Err(DebuggerError::LineOutOfRange(26, _, 25)) => {}
+            other => panic!("expected to be past the end, got {:?}", other),
+        }
+        // Backwards from the end, then from the start of the file
+        debugger.execute_line("list -").unwrap();
+        assert_eq!(listed(&debugger), (9, 18));
+        debugger.execute_line("list -").unwrap();
+        assert_eq!(listed(&debugger), (1, 8));
+        assert!(debugger.execute_line("list -").is_err());
+    }
+
+    #[test]
+    fn enter_after_list_lists_on() {
+        let mut debugger = debugger();
+        let line = "list 12";
+        let cmd = debugger.parse_line(line).unwrap();
+        let repeat = line_to_repeat(&cmd, line).unwrap();
+        debugger.execute_command(cmd).unwrap();
+        assert_eq!(listed(&debugger), (7, 16));
+        debugger.execute_line(&repeat).unwrap();
+        assert_eq!(listed(&debugger), (17, 25));
+        // `list -` and plain `list` repeat as they are
+        let cmd = debugger.parse_line("list -").unwrap();
+        assert_eq!(line_to_repeat(&cmd, "list -").as_deref(), Some("list -"));
+        let cmd = debugger.parse_line("list").unwrap();
+        assert_eq!(line_to_repeat(&cmd, "list").as_deref(), Some("list"));
+    }
+
+    #[test]
+    fn unterminated_quotes_are_reported() {
+        let mut debugger = debugger();
+        match debugger.execute_line("run 'a b") {
+            Err(DebuggerError::UnterminatedQuote(line)) => assert_eq!(line, "run 'a b"),
+            other => panic!("expected an unterminated quote, got {:?}", other),
+        }
+    }
+}
@@ -1,28 +1,2281 @@
+use crate::breakpoint::EnableMode;
+use crate::expr::Format;
+use crate::session::{Catchpoint, Redirection};
+use crate::skip::SkipTarget;
+
+/// The groups `help` lists commands in.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Category {
+    Running,
+    Breakpoints,
+    Data,
+    Stack,
+    Status,
+    Support,
+}
+
+impl Category {
+    pub const ALL: &'static [Category] = &[
+        Category::Running,
+        Category::Breakpoints,
+        Category::Data,
+        Category::Stack,
+        Category::Status,
+        Category::Support,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Category::Running => "Running the program",
+            Category::Breakpoints => "Breakpoints and watchpoints",
+            Category::Data => "Examining data",
+            Category::Stack => "Examining the stack",
+            Category::Status => "Status inquiries",
+            Category::Support => "Support facilities",
+        }
+    }
+}
+
+/// A top-level command, for parsing (the name is what abbreviations resolve to), tab completion
+/// and `help`.
+pub struct CommandInfo {
+    pub name: &'static str,
+    pub category: Category,
+    /// One line saying what the command does
+    pub summary: &'static str,
+    pub usage: &'static str,
+    pub examples: &'static [&'static str],
+}
+
+/// Number of bytes `x` shows without a count.
+const DEFAULT_EXAMINE_LENGTH: usize = 16;
+
+/// Every top-level command, in alphabetical order.
+pub const COMMANDS: &[CommandInfo] = &[
+    CommandInfo {
+        name: "advance",
+        category: Category::Running,
+        summary: "Run to a location, or until the current function returns.",
+        usage: "advance <line | file:line | function | *addr>",
+        examples: &["advance 42", "advance parse_args"],
+    },
+    CommandInfo {
+        name: "alias",
+        category: Category::Support,
+        summary: "Define a short name for a command, which may include arguments.",
+        usage: "alias <name> = <command>",
+        examples: &["alias bm = break main"],
+    },
+    CommandInfo {
+        name: "apropos",
+        category: Category::Support,
+        summary: "Search the commands' names and summaries for a word.",
+        usage: "apropos <word>",
+        examples: &["apropos breakpoint"],
+    },
+    CommandInfo {
+        name: "backtrace",
+        category: Category::Stack,
+        summary: "Show the stack of function calls that led to the current location: the innermost \
+                  `count` frames, if given, and with `full`, each frame's local variables.",
+        usage: "backtrace [full] [count]",
+        examples: &["bt", "bt full", "backtrace 5"],
+    },
+    CommandInfo {
+        name: "break",
+        category: Category::Breakpoints,
+        summary: "Set a breakpoint at a line, function (past its prologue) or address.",
+        usage: "break [-force] <*addr | *function | line | file:line | function>",
+        examples: &["break main", "break list.c:17", "break *0x401136", "break *main"],
+    },
+    CommandInfo {
+        name: "call",
+        category: Category::Data,
+        summary: "Call a function in the program and show what it returns.",
+        usage: "call <function>(<args>...)",
+        examples: &["call strlen(name)", "call dump_list(head, 1)"],
+    },
+    CommandInfo {
+        name: "catch",
+        category: Category::Breakpoints,
+        summary: "Stop when the program calls abort (on by default) or a Rust program panics.",
+        usage: "catch abort|panic [on|off]",
+        examples: &["catch panic", "catch abort off"],
+    },
+    CommandInfo {
+        name: "cd",
+        category: Category::Support,
+        summary: "Change the working directory the program is started in. With no directory, \
+                  go to the home directory.",
+        usage: "cd [directory]",
+        examples: &["cd /tmp", "cd"],
+    },
+    CommandInfo {
+        name: "checkpoint",
+        category: Category::Running,
+        summary: "Save the program's current state so it can be returned to with restart.",
+        usage: "checkpoint",
+        examples: &["checkpoint"],
+    },
+    CommandInfo {
+        name: "clear",
+        category: Category::Breakpoints,
+        summary: "Delete the breakpoints at a line, function or address.",
+        usage: "clear <*addr | line | file:line | function>",
+        examples: &["clear main", "clear list.c:30"],
+    },
+    CommandInfo {
+        name: "condition",
+        category: Category::Breakpoints,
+        summary: "Make a breakpoint stop the program only where an expression is nonzero. With no \
+                  expression, it stops wherever it is hit again.",
+        usage: "condition <number> [expression]",
+        examples: &["condition 1 n == 0", "condition 2 len > 100 && buf != 0", "condition 1"],
+    },
+    CommandInfo {
+        name: "continue",
+        category: Category::Running,
+        summary: "Resume the program until the next breakpoint (or the count'th), signal or exit.",
+        usage: "continue [count] | continue &",
+        examples: &["c", "continue 50", "c &"],
+    },
+    CommandInfo {
+        name: "coverage",
+        category: Category::Running,
+        summary: "Record which source lines run, and report executed vs. total lines per file.",
+        usage: "coverage on|off | coverage report [lcov-file]",
+        examples: &["coverage on", "coverage report", "coverage report run.info"],
+    },
+    CommandInfo {
+        name: "define",
+        category: Category::Support,
+        summary: "Define a new command from the lines that follow, up to \"end\".",
+        usage: "define <name>",
+        examples: &["define rerun"],
+    },
+    CommandInfo {
+        name: "delete",
+        category: Category::Breakpoints,
+        summary: "Delete breakpoints by number, or all of them (after asking).",
+        usage: "delete [number]...",
+        examples: &["delete 2", "delete 1 3", "delete"],
+    },
+    CommandInfo {
+        name: "directory",
+        category: Category::Support,
+        summary: "Add directories to the front of the list searched for source files that aren't \
+                  where the debug info says, or empty the list (after asking).",
+        usage: "directory [dir[:dir]...]...",
+        examples: &["directory /home/me/project/src", "directory src:include", "directory"],
+    },
+    CommandInfo {
+        name: "disable",
+        category: Category::Breakpoints,
+        summary: "Disable breakpoints (all of them if none are given), or stop showing an \
+                  auto-display expression, without deleting them.",
+        usage: "disable [number]... | disable display <number>",
+        examples: &["disable 2", "disable", "disable display 2"],
+    },
+    CommandInfo {
+        name: "display",
+        category: Category::Data,
+        summary: "Show an expression every time the program stops.",
+        usage: "display[/format] [expression]",
+        examples: &["display i", "display/x flags"],
+    },
+    CommandInfo {
+        name: "down",
+        category: Category::Stack,
+        summary: "Select the frame called by the selected one, or `count` frames further in.",
+        usage: "down [count]",
+        examples: &["down", "down 2"],
+    },
+    CommandInfo {
+        name: "dprintf",
+        category: Category::Breakpoints,
+        summary: "Set a breakpoint that prints a formatted line, like printf, each time it is hit, \
+                  and lets the program carry on.",
+        usage: "dprintf <location>, \"format\", args...",
+        examples: &["dprintf worker.c:88, \"n=%d state=%s\\n\", n, state_name"],
+    },
+    CommandInfo {
+        name: "echo",
+        category: Category::Support,
+        summary: "Print text, with C escapes such as \\n.",
+        usage: "echo <text>",
+        examples: &["echo hit the slow path\\n"],
+    },
+    CommandInfo {
+        name: "enable",
+        category: Category::Breakpoints,
+        summary: "Enable breakpoints (all of them if none are given), or show a disabled \
+                  auto-display expression again. `once` disables the breakpoint again after it \
+                  next stops the program, `count <n>` after n stops. Hits skipped by `ignore` \
+                  come first and don't count as stops.",
+        usage: "enable [once | count <n>] [number]... | enable display <number>",
+        examples: &["enable 2", "enable once 3", "enable count 5 3", "enable display 2"],
+    },
+    CommandInfo {
+        name: "file",
+        category: Category::Support,
+        summary: "Debug another program (or a rebuilt one), keeping the breakpoints.",
+        usage: "file <path>",
+        examples: &["file ./a.out"],
+    },
+    CommandInfo {
+        name: "find",
+        category: Category::Data,
+        summary: "Search memory for a byte sequence, a string or an integer.",
+        usage: "find <start>, <end | +length>, <0xNN 0xNN... | \"string\" | integer[/b|h|w|g]>",
+        examples: &[
+            "find &buffer, +256, \"needle\"",
+            "find $rsp, +4096, 0xde 0xad 0xbe 0xef",
+            "find 0x555555559000, 0x55555557a000, 1234/g",
+        ],
+    },
+    CommandInfo {
+        name: "finish",
+        category: Category::Running,
+        summary: "Run until the current function returns, and show its return value.",
+        usage: "finish",
+        examples: &["finish"],
+    },
+    CommandInfo {
+        name: "frame",
+        category: Category::Stack,
+        summary: "Select a frame by its number in the backtrace, or show the selected one.",
+        usage: "frame [number]",
+        examples: &["frame", "frame 2", "f 1"],
+    },
+    CommandInfo {
+        name: "help",
+        category: Category::Support,
+        summary: "List the commands, or describe one of them.",
+        usage: "help [command]",
+        examples: &["help", "help break"],
+    },
+    CommandInfo {
+        name: "hexdump",
+        category: Category::Data,
+        summary: "Show memory as hex bytes and ASCII, like `hexdump -C`.",
+        usage: "hexdump <address> <length>",
+        examples: &["hexdump &buffer 64", "hexdump $rsp 32"],
+    },
+    CommandInfo {
+        name: "history",
+        category: Category::Support,
+        summary: "Show the command history, or save it to the history file.",
+        usage: "history [save]",
+        examples: &["history", "history save"],
+    },
+    CommandInfo {
+        name: "ignore",
+        category: Category::Breakpoints,
+        summary: "Go past a breakpoint's next `count` hits without stopping. These are used up \
+                  before `enable once` or `enable count` counts a stop.",
+        usage: "ignore <number> <count>",
+        examples: &["ignore 2 10"],
+    },
+    CommandInfo {
+        name: "info",
+        category: Category::Status,
+        summary: "Show information about the program and the debugger's state.",
+        usage: "info <address <symbol> | aliases | all-registers | breakpoints | catch | \
+                checkpoints | display | float | frame | functions [-m] [regex] | heap | inferior | \
+                line <location> | output | record [n] | registers [names...] | scope <location> | \
+                sharedlibrary | siginfo | skip | sources | stats | symbol <addr> | trace-calls | \
+                variables [regex] | vector>",
+        examples: &[
+            "info breakpoints",
+            "info functions ^list_",
+            "info scope compute",
+            "info variables count",
+        ],
+    },
+    CommandInfo {
+        name: "interrupt",
+        category: Category::Running,
+        summary: "Stop a program started with `run &` or `continue &`.",
+        usage: "interrupt",
+        examples: &["interrupt"],
+    },
+    CommandInfo {
+        name: "jump",
+        category: Category::Running,
+        summary: "Resume the program at another location.",
+        usage: "jump <line | file:line | function | *addr>",
+        examples: &["jump 30", "jump *0x401150"],
+    },
+    CommandInfo {
+        name: "kill",
+        category: Category::Running,
+        summary: "Kill the program. Breakpoints are kept for the next run.",
+        usage: "kill",
+        examples: &["kill"],
+    },
+    CommandInfo {
+        name: "list",
+        category: Category::Data,
+        summary: "Show source lines: around a location, or the next (or, with -, previous) ones.",
+        usage: "list [- | + | line | file:line | function | *addr]",
+        examples: &["list", "list -", "list parse_args", "list list.c:40"],
+    },
+    CommandInfo {
+        name: "maintenance",
+        category: Category::Support,
+        summary: "Commands for debugging the debugger itself.",
+        usage: "maintenance <info dwarf | info line-table [file] | time on|off>",
+        examples: &["maint info line-table main.c", "maint time on"],
+    },
+    CommandInfo {
+        name: "next",
+        category: Category::Running,
+        summary: "Run to the next line, stepping over function calls.",
+        usage: "next",
+        examples: &["next"],
+    },
+    CommandInfo {
+        name: "print",
+        category: Category::Data,
+        summary: "Evaluate an expression and show its value.",
+        usage: "print[/format] <expression>",
+        examples: &["print list->head", "p/x flags", "print $rsp + 8"],
+    },
+    CommandInfo {
+        name: "printf",
+        category: Category::Data,
+        summary: "Print values with a C-style format string (%d %u %x %s %c).",
+        usage: "printf \"<format>\", <expression>...",
+        examples: &["printf \"i=%d name=%s\\n\", i, name"],
+    },
+    CommandInfo {
+        name: "profile",
+        category: Category::Running,
+        summary: "Run the program for a while, sampling where it spends its time. With no \
+                  duration, carry on with a profile a stop paused. -c splits functions by caller.",
+        usage: "profile [-c] [seconds]",
+        examples: &["profile 5", "profile -c 2.5", "profile"],
+    },
+    CommandInfo {
+        name: "ptype",
+        category: Category::Data,
+        summary: "Show the definition of a type, or of an expression's type, with its layout.",
+        usage: "ptype <type | expression>",
+        examples: &["ptype struct connection", "ptype conn", "ptype size_t"],
+    },
+    CommandInfo {
+        name: "pwd",
+        category: Category::Support,
+        summary: "Show the working directory the program is started in.",
+        usage: "pwd",
+        examples: &["pwd"],
+    },
+    CommandInfo {
+        name: "quit",
+        category: Category::Support,
+        summary: "Exit the debugger, killing the program.",
+        usage: "quit",
+        examples: &["q"],
+    },
+    CommandInfo {
+        name: "rbreak",
+        category: Category::Breakpoints,
+        summary: "Set a breakpoint on every function whose name matches a regular expression.",
+        usage: "rbreak <regex>",
+        examples: &["rbreak ^parse_", "rbreak list::.*push"],
+    },
+    CommandInfo {
+        name: "record",
+        category: Category::Running,
+        summary: "Record every instruction executed by continue (slowly), or stop recording.",
+        usage: "record [stop]",
+        examples: &["record", "record stop"],
+    },
+    CommandInfo {
+        name: "restart",
+        category: Category::Running,
+        summary: "Go back to the state saved by a checkpoint.",
+        usage: "restart <checkpoint number>",
+        examples: &["restart 1"],
+    },
+    CommandInfo {
+        name: "return",
+        category: Category::Stack,
+        summary: "Make the current function return at once, optionally with a value.",
+        usage: "return [expression]",
+        examples: &["return", "return -1"],
+    },
+    CommandInfo {
+        name: "run",
+        category: Category::Running,
+        summary: "Start the program, with arguments and redirections if given.",
+        usage: "run [args...] [< in] [> out | >> out] [2> err | 2>&1] [&]",
+        examples: &["run", "run input.txt > out.txt", "run &"],
+    },
+    CommandInfo {
+        name: "save",
+        category: Category::Breakpoints,
+        summary: "Write the breakpoints, with their enable state and ignore counts, to a file as \
+                  commands that `source` sets again.",
+        usage: "save breakpoints <file>",
+        examples: &["save breakpoints bp.deet"],
+    },
+    CommandInfo {
+        name: "set",
+        category: Category::Support,
+        summary: "Change a setting.",
+        usage: "set <args | auto-reload | capture-output | confirm | debug-file-directory | \
+                disable-randomization | environment | heap-track | inferior-tty | logging | \
+                pagination | step-into-no-debug | stop-on-solib-events | style | \
+                substitute-path <from> <to> | trace-calls | trace-syscalls> ...",
+        examples: &[
+            "set args -v input.txt",
+            "set environment DEBUG=1",
+            "set substitute-path /build/project /home/me/project",
+        ],
+    },
+    CommandInfo {
+        name: "sharedlibrary",
+        category: Category::Support,
+        summary: "Load symbols for the shared libraries whose path matches, or for all of them.",
+        usage: "sharedlibrary [regex]",
+        examples: &["sharedlibrary", "sharedlibrary libfoo"],
+    },
+    CommandInfo {
+        name: "shell",
+        category: Category::Support,
+        summary: "Run a shell command.",
+        usage: "shell <command> | !<command>",
+        examples: &["shell ls -l", "!make"],
+    },
+    CommandInfo {
+        name: "show",
+        category: Category::Status,
+        summary: "Show a setting, or the user-defined commands.",
+        usage: "show <args | auto-reload | capture-output | confirm | debug-file-directory | \
+                directories | disable-randomization | environment | heap-track | inferior-tty | \
+                logging | pagination | step-into-no-debug | stop-on-solib-events | style | \
+                substitute-path | trace-calls | trace-syscalls | user [name] | values>",
+        examples: &["show args", "show user"],
+    },
+    CommandInfo {
+        name: "skip",
+        category: Category::Running,
+        summary: "Have `step` go over calls to functions whose names match a pattern (`*` and `?` \
+                  as wildcards), or to the functions in a file, rather than into them.",
+        usage: "skip <function <pattern> | file <file> | delete [n...]>",
+        examples: &["skip function log_msg", "skip function log_*", "skip file vendor.c"],
+    },
+    CommandInfo {
+        name: "snapshot",
+        category: Category::Data,
+        summary: "Save memory to compare later, or show the rows of bytes that changed since a \
+                  snapshot was taken. Snapshots are named by their address expression.",
+        usage: "snapshot [<address> <length> | diff [name]]",
+        examples: &["snapshot buf 256", "snapshot diff", "snapshot diff buf", "snapshot"],
+    },
+    CommandInfo {
+        name: "source",
+        category: Category::Support,
+        summary: "Run the commands in a file. With -c, keep going after a failing line.",
+        usage: "source [-c] <file>",
+        examples: &["source breakpoints.deet"],
+    },
+    CommandInfo {
+        name: "start",
+        category: Category::Running,
+        summary: "Start the program and stop at the beginning of main.",
+        usage: "start [args...]",
+        examples: &["start", "start input.txt"],
+    },
+    CommandInfo {
+        name: "step",
+        category: Category::Running,
+        summary: "Run to the next line, going into the functions it calls that have line \
+                  information.",
+        usage: "step",
+        examples: &["step"],
+    },
+    CommandInfo {
+        name: "stress",
+        category: Category::Running,
+        summary: "Run the program over and over until a run crashes or stops at a breakpoint.",
+        usage: "stress <count>",
+        examples: &["stress 500"],
+    },
+    CommandInfo {
+        name: "strings",
+        category: Category::Data,
+        summary: "List the printable strings (4 or more characters) in a range or mapping.",
+        usage: "strings [-z] <address> <length> | strings [-z] <mapping>",
+        examples: &["strings heap", "strings -z &buffer 4096", "strings libc.so.6"],
+    },
+    CommandInfo {
+        name: "tui",
+        category: Category::Support,
+        summary: "Split the terminal: the source around the current line above the prompt.",
+        usage: "tui enable | tui disable",
+        examples: &["tui enable"],
+    },
+    CommandInfo {
+        name: "undisplay",
+        category: Category::Data,
+        summary: "Delete an auto-display expression, or all of them.",
+        usage: "undisplay [number]",
+        examples: &["undisplay 2", "undisplay"],
+    },
+    CommandInfo {
+        name: "unset",
+        category: Category::Support,
+        summary: "Remove an environment variable or a source path substitution rule, or all of \
+                  them. With no variable, `unset environment` starts the program with an empty \
+                  environment from the next run on.",
+        usage: "unset <environment [variable] | substitute-path [from]>",
+        examples: &[
+            "unset environment DEBUG",
+            "unset environment",
+            "unset substitute-path /build/project",
+        ],
+    },
+    CommandInfo {
+        name: "until",
+        category: Category::Running,
+        summary: "Run past the current line, or to a later line of the current function.",
+        usage: "until [line]",
+        examples: &["until", "until 57"],
+    },
+    CommandInfo {
+        name: "up",
+        category: Category::Stack,
+        summary: "Select the frame that called the selected one, or `count` frames further out.",
+        usage: "up [count]",
+        examples: &["up", "up 3"],
+    },
+    CommandInfo {
+        name: "watch",
+        category: Category::Breakpoints,
+        summary: "Stop whenever a variable changes.",
+        usage: "watch <variable>",
+        examples: &["watch count"],
+    },
+    CommandInfo {
+        name: "whatis",
+        category: Category::Data,
+        summary: "Show the declared type of an expression, or what a type name stands for.",
+        usage: "whatis <type | expression>",
+        examples: &["whatis conn", "whatis conn->buf", "whatis size_t"],
+    },
+    CommandInfo {
+        name: "x",
+        category: Category::Data,
+        summary: "Examine memory: show `count` bytes (16 by default) at an address.",
+        usage: "x[/count] <address>",
+        examples: &["x &buffer", "x/64 argv[0]"],
+    },
+];
+
+/// Looks up a command by its full name.
+pub fn command_info(name: &str) -> Option<&'static CommandInfo> {
+    COMMANDS.iter().find(|command| command.name == name)
+}
+
+/// Full names of the top-level commands, as offered by tab completion.
+pub fn command_names() -> impl Iterator<Item = &'static str> {
+    COMMANDS.iter().map(|command| command.name)
+}
+
+/// Short forms that are accepted even though other commands start with the same letters.
+pub const ABBREVIATIONS: &[(&str, &str)] = &[
+    ("b", "break"),
+    ("bt", "backtrace"),
+    ("c", "continue"),
+    ("d", "delete"),
+    ("f", "frame"),
+    ("fin", "finish"),
+    ("i", "info"),
+    ("j", "jump"),
+    ("l", "list"),
+    ("n", "next"),
+    ("p", "print"),
+    ("q", "quit"),
+    ("r", "run"),
+    ("s", "step"),
+    ("u", "until"),
+];
+
+/// What a (possibly abbreviated) command word refers to.
+pub enum Resolution {
+    Command(&'static str),
+    /// A prefix of several commands, which are listed
+    Ambiguous(Vec<&'static str>),
+    Unknown,
+}
+
+/// Resolves a command word: a full name, one of the ABBREVIATIONS, or any prefix that only one
+/// command starts with.
+pub fn resolve_name(word: &str) -> Resolution {
+    if let Some(command) = command_info(word) {
+        return Resolution::Command(command.name);
+    }
+    if let Some((_, name)) = ABBREVIATIONS.iter().find(|(abbreviation, _)| *abbreviation == word) {
+        return Resolution::Command(name);
+    }
+    let candidates: Vec<&'static str> =
+        command_names().filter(|name| name.starts_with(word)).collect();
+    match candidates.len() {
+        0 => Resolution::Unknown,
+        1 => Resolution::Command(candidates[0]),
+        _ => Resolution::Ambiguous(candidates),
+    }
+}
+
+/// The number of single-character edits (insertions, deletions and substitutions) that turn one
+/// word into the other.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    // distances[j] is the distance from the prefix of `a` seen so far to the first j chars of `b`
+    let mut distances: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = distances[0];
+        distances[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + if ca == *cb { 0 } else { 1 };
+            diagonal = distances[j + 1];
+            distances[j + 1] = substitution.min(distances[j] + 1).min(diagonal + 1);
+        }
+    }
+    distances[b.len()]
+}
+
+/// Likely intended commands for a mistyped word: the commands, plus any `extra_names` (aliases
+/// and user-defined commands), that are the fewest edits away, up to MAX_SUGGESTIONS of them.
+/// Short words get fewer edits, or none, since almost anything is a couple of edits from `x`.
+pub fn suggest_commands<'a>(
+    word: &str,
+    extra_names: impl Iterator<Item = &'a str>,
+) -> Vec<&'a str> {
+    let max_distance = (word.chars().count() / 3).min(MAX_TYPO_DISTANCE);
+    let mut scored: Vec<(usize, &'a str)> = command_names()
+        .map(|name| -> &'a str { name })
+        .chain(extra_names)
+        .map(|name| (edit_distance(word, name), name))
+        .filter(|(distance, _)| *distance > 0 && *distance <= max_distance)
+        .collect();
+    let best = match scored.iter().map(|(distance, _)| *distance).min() {
+        Some(best) => best,
+        None => return Vec::new(),
+    };
+    scored.retain(|(distance, _)| *distance == best);
+    let mut names: Vec<&'a str> = scored.into_iter().map(|(_, name)| name).collect();
+    names.sort();
+    names.dedup();
+    names.truncate(MAX_SUGGESTIONS);
+    names
+}
+
+/// How many edits `suggest_commands` allows, at most.
+const MAX_TYPO_DISTANCE: usize = 2;
+
+/// How many equally close commands `suggest_commands` lists.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Commands that take a subcommand as their first argument, with the subcommands they accept.
+pub const SUBCOMMANDS: &[(&str, &[&str])] = &[
+    ("catch", &["abort", "panic"]),
+    ("coverage", &["off", "on", "report"]),
+    ("disable", &["display"]),
+    ("enable", &["count", "display", "once"]),
+    ("history", &["save"]),
+    (
+        "info",
+        &[
+            "address",
+            "aliases",
+            "all-registers",
+            "breakpoints",
+            "catch",
+            "checkpoints",
+            "display",
+            "float",
+            "frame",
+            "functions",
+            "heap",
+            "inferior",
+            "line",
+            "output",
+            "record",
+            "registers",
+            "scope",
+            "sharedlibrary",
+            "siginfo",
+            "signal",
+            "skip",
+            "sources",
+            "stats",
+            "symbol",
+            "trace-calls",
+            "variables",
+            "vector",
+        ],
+    ),
+    ("maint", &["info", "time"]),
+    ("maintenance", &["info", "time"]),
+    ("record", &["stop"]),
+    ("save", &["breakpoints"]),
+    (
+        "set",
+        &[
+            "args",
+            "auto-reload",
+            "backtrace",
+            "capture-output",
+            "confirm",
+            "context",
+            "debug-file-directory",
+            "disable-randomization",
+            "environment",
+            "heap-track",
+            "inferior-tty",
+            "listsize",
+            "logging",
+            "pagination",
+            "print",
+            "step-into-no-debug",
+            "stop-on-solib-events",
+            "style",
+            "substitute-path",
+            "trace-calls",
+            "trace-syscalls",
+        ],
+    ),
+    (
+        "show",
+        &[
+            "args",
+            "auto-reload",
+            "backtrace",
+            "capture-output",
+            "confirm",
+            "context",
+            "debug-file-directory",
+            "directories",
+            "disable-randomization",
+            "environment",
+            "heap-track",
+            "inferior-tty",
+            "listsize",
+            "logging",
+            "pagination",
+            "print",
+            "step-into-no-debug",
+            "stop-on-solib-events",
+            "style",
+            "substitute-path",
+            "trace-calls",
+            "trace-syscalls",
+            "user",
+            "values",
+        ],
+    ),
+    ("skip", &["delete", "file", "function"]),
+    ("snapshot", &["diff"]),
+    ("tui", &["disable", "enable"]),
+    ("unset", &["environment", "substitute-path"]),
+];
+
+/// The memory `strings` scans.
+pub enum MemoryRegion {
+    /// An address expression and a length
+    Range(String, usize),
+    /// A mapping from /proc/<pid>/maps, such as `heap` or `libc.so.6`
+    Mapping(String),
+}
+
+/// What `list` shows.
+pub enum ListArgument {
+    /// `list` or `list +`: the lines after the last ones listed, or around the last stop
+    Forward,
+    /// `list -`: the lines before the last ones listed
+    Backward,
+    /// `list location`: the lines around a location
+    Around(String),
+}
+
+/// Commands (including abbreviations) whose argument is a location: a function, or a file and line.
+pub const FUNCTION_COMMANDS: &[&str] = &["advance", "b", "break", "j", "jump", "tbreak"];
+
+/// Commands whose argument may be a source file or a function, either of which is completed.
+pub const LIST_COMMANDS: &[&str] = &["l", "list"];
+
 pub enum DebuggerCommand {
     Quit,
-    Run(Vec<String>),
-    Cont,
-    Backtrace,
-    Break(String)
+    /// `run [args...]`, with any redirections taken out of the arguments
+    Run(Vec<String>, Vec<Redirection>),
+    /// `continue [N]`: with N, keeps going past the next N-1 breakpoint hits
+    Cont(Option<usize>),
+    /// Kill the inferior without leaving the debugger
+    Kill,
+    /// `run &` or `continue &`: the command, leaving the program running and the prompt usable
+    Background(Box<DebuggerCommand>),
+    /// `interrupt`: stop a program running in the background
+    Interrupt,
+    /// `file <path>`: switch to another target binary
+    File(String),
+    /// `backtrace [full] [n]`: the innermost n frames (all of them by default), and with `full`
+    /// (true) their local variables
+    Backtrace(Option<usize>, bool),
+    /// `frame [n]`: select frame n of the backtrace, or show the selected frame
+    Frame(Option<usize>),
+    /// `up [n]`: select the frame n levels further out (1 without n)
+    Up(usize),
+    /// `down [n]`: select the frame n levels further in (1 without n)
+    Down(usize),
+    /// Evaluate and show an expression
+    Print(String, Format),
+    /// `ptype <type | expression>`: show a type's definition
+    Ptype(String),
+    /// `whatis <type | expression>`: show a type's name
+    Whatis(String),
+    /// `display[/f] expr`: show an expression at every stop. With no expression, show all of
+    /// them now.
+    Display(Option<String>, Format),
+    /// Run to a line of the current function, or past the current line
+    Until(Option<usize>),
+    /// `next`: run to the next line, stepping over calls
+    Next,
+    /// `step`: run to the next line, stepping into calls
+    Step,
+    /// `skip function <pattern>` or `skip file <file>`: have `step` go over calls into them
+    Skip(SkipTarget),
+    /// `skip delete [n...]`: remove the numbered `skip` entries, or all of them
+    SkipDelete(Vec<usize>),
+    /// Save the inferior's state as a forked copy
+    Checkpoint,
+    /// Go back to a checkpoint
+    Restart(usize),
+    InfoCheckpoints,
+    /// `set logging on|off`: copy output to the logging file
+    SetLogging(bool),
+    SetLoggingFile(String),
+    /// `set logging overwrite on|off`: truncate the logging file instead of appending to it
+    SetLoggingOverwrite(bool),
+    ShowLogging,
+    /// `info stats`: run time, stops and breakpoint hits of the current run
+    InfoStats,
+    /// `record`: record every instruction executed by `continue` (slowly)
+    Record,
+    RecordStop,
+    /// `info record [n]`: show the last n recorded instructions
+    InfoRecord(Option<usize>),
+    /// `catch abort|panic [on|off]`: stop on entry to abort, or when a Rust program panics
+    Catch(Catchpoint, bool),
+    /// `info catch`: the catchpoints that are on
+    InfoCatch,
+    /// `coverage on|off`: record which lines run
+    Coverage(bool),
+    /// `coverage report [file]`: lines run per file, and optionally an lcov file of them
+    CoverageReport(Option<String>),
+    /// Call a function in the inferior: `call f(1, x)`
+    Call(String),
+    /// Pop the current frame, with an optional return value expression
+    Return(Option<String>),
+    /// Resume at another location
+    Jump(String),
+    /// Run to a location, or until the current function returns
+    Advance(String),
+    /// `finish`: run until the current function returns, and show what it returned
+    Finish,
+    /// Stop whenever a variable changes
+    Watch(String),
+    /// Remove a display by number, or all of them
+    Undisplay(Option<usize>),
+    /// `enable display n` (true) or `disable display n` (false)
+    EnableDisplay(usize, bool),
+    /// Enable breakpoints by number, in the given mode; none means all of them
+    EnableBreakpoints(Vec<usize>, EnableMode),
+    /// Disable breakpoints by number; none means all of them
+    DisableBreakpoints(Vec<usize>),
+    /// `ignore n count`
+    Ignore(usize, usize),
+    /// `condition n [expr]`: stop at breakpoint n only where expr is nonzero, or always
+    Condition(usize, Option<String>),
+    InfoDisplay,
+    /// Set a breakpoint. The flag (`-force`) skips the sanity check on raw addresses.
+    Break(String, bool),
+    /// `dprintf <location>, "format", args...`: a breakpoint that prints instead of stopping
+    Dprintf(String, String),
+    /// `rbreak <regex>`: a breakpoint on every function whose name matches
+    RBreak(String),
+    /// Delete breakpoints by number; none means all of them
+    Delete(Vec<usize>),
+    /// Delete the breakpoints at a location
+    Clear(String),
+    SetArgs(Vec<String>, Vec<Redirection>),
+    ShowArgs,
+    Start(Vec<String>, Vec<Redirection>),
+    /// `stress <count>`: rerun the program (with the stored arguments) until a run doesn't
+    /// simply exit
+    Stress(usize),
+    /// `profile [-c] [seconds]`: sample the running program for a while, or carry on with a
+    /// paused profile. With -c, the histogram is of function and caller pairs.
+    Profile(Option<f64>, bool),
+    SetEnvironment(String, String),
+    UnsetEnvironment(Option<String>),
+    ShowEnvironment,
+    SetInferiorTty(bool),
+    ShowInferiorTty,
+    /// `set capture-output on|off`: pipe the program's stdout and stderr through the debugger,
+    /// labelled with the stream, and keep them for `info output`
+    SetCaptureOutput(bool),
+    ShowCaptureOutput,
+    SetDisableRandomization(bool),
+    ShowDisableRandomization,
+    /// `set trace-syscalls on|off`: report every system call the program makes
+    SetTraceSyscalls(bool),
+    ShowTraceSyscalls,
+    /// `set trace-calls on|off`: report every call the program makes into a shared library
+    SetTraceCalls(bool),
+    ShowTraceCalls,
+    /// `info trace-calls`: how many times each traced library function was called
+    InfoTraceCalls,
+    /// `set heap-track on|off`: keep account of the blocks malloc and friends hand out
+    SetHeapTrack(bool),
+    ShowHeapTrack,
+    /// `info heap`: the live blocks, and where most of them were allocated
+    InfoHeap,
+    /// `set stop-on-solib-events on|off`: stop when a shared library is loaded or unloaded
+    SetStopOnSolibEvents(bool),
+    ShowStopOnSolibEvents,
+    /// `set step-into-no-debug on|off`: whether `step` stops in functions without line
+    /// information instead of running through them
+    SetStepIntoNoDebug(bool),
+    ShowStepIntoNoDebug,
+    /// `cd [directory]`: None goes to the home directory
+    Cd(Option<String>),
+    Pwd,
+    Shell(String),
+    /// `alias name = expansion`: make `name` short for the start of another command
+    Alias(String, String),
+    InfoAliases,
+    /// `info frame`: addresses and saved registers of the current frame
+    InfoFrame,
+    /// `info inferior`: the target, its arguments, and the state of the process
+    InfoInferior,
+    /// `info siginfo` (or `info signal`): the signal the program last stopped with, what its
+    /// si_code means, and the fault address or sending process
+    InfoSiginfo,
+    /// `info skip`: the `skip` list
+    InfoSkip,
+    /// `info registers [names...]`: the general purpose registers, or the ones named
+    InfoRegisters(Vec<String>),
+    /// `info all-registers`: every register, debug registers included while they are in use
+    InfoAllRegisters,
+    /// `info float`: the x87 floating point registers
+    InfoFloat,
+    /// `info vector`: the SSE registers
+    InfoVector,
+    /// `info address <symbol>`: where a function or global variable lives
+    InfoAddress(String),
+    /// `info symbol <addr>`: which symbol an address is in
+    InfoSymbol(String),
+    /// `info line <location>`: the address range of a line, or the line of an address
+    InfoLine(String),
+    /// `define name`: the following lines, up to `end`, become a new command
+    Define(String),
+    /// Invoke a `define`d command with arguments
+    User(String, Vec<String>),
+    /// `show user [name]`: list `define`d commands
+    ShowUser(Option<String>),
+    /// `show values`: the value history, `$1` onwards
+    ShowValues,
+    /// `help [command]`
+    Help(Option<String>),
+    /// `apropos word`: search the commands' names and summaries
+    Apropos(String),
+    /// `echo text`: print text, with C escapes such as `\n`
+    Echo(String),
+    /// `printf "format", args...`
+    Printf(String),
+    /// `x/count address` and `hexdump address length`: dump memory at the address an
+    /// expression evaluates to
+    Examine(String, usize),
+    /// `snapshot <address> <length>`: save memory at the address an expression evaluates to
+    Snapshot(String, usize),
+    /// `snapshot diff [name]`: compare memory with a snapshot, by default the last one taken
+    SnapshotDiff(Option<String>),
+    /// `snapshot`: list the snapshots taken
+    Snapshots,
+    /// `find start, end, pattern`: search memory for the pattern's bytes. The end is an
+    /// expression, or `+length`.
+    Find(String, String, Vec<u8>),
+    /// `strings [-z] region`: list the printable strings in memory. The flag (`-z`) keeps only
+    /// NUL-terminated ones.
+    Strings(MemoryRegion, bool),
+    /// Run the commands in a file. The flag says whether to keep going after a bad line.
+    Source(String, bool),
+    History,
+    HistorySave,
+    /// `save breakpoints <file>`: write commands that set the breakpoints again, as they are now
+    SaveBreakpoints(String),
+    SetStyle(bool),
+    ShowStyle,
+    /// `set pagination on|off`: whether long output pauses after each screenful
+    SetPagination(bool),
+    ShowPagination,
+    /// `set auto-reload on|off`: whether `run` reloads symbols when the target was rebuilt
+    SetAutoReload(bool),
+    ShowAutoReload,
+    /// `set confirm on|off`: whether to ask before killing the program and the like
+    SetConfirm(bool),
+    ShowConfirm,
+    /// `set print source on|off`: whether stops show the text of the source line
+    SetPrintSource(bool),
+    ShowPrintSource,
+    /// `set backtrace limit N`: the most frames `backtrace` shows (0 for no limit)
+    SetBacktraceLimit(usize),
+    ShowBacktraceLimit,
+    /// `tui enable|disable`: show a source pane above the prompt
+    Tui(bool),
+    /// `list [-|+|location]`
+    List(ListArgument),
+    /// `set listsize N`: how many lines `list` shows
+    SetListSize(usize),
+    ShowListSize,
+    /// `set context N`: show N source lines above and below the line at each stop (0 for off)
+    SetContext(usize),
+    ShowContext,
+    /// `set debug-file-directory <dir>`: where to look for separate debug files
+    SetDebugFileDirectory(String),
+    ShowDebugFileDirectory,
+    /// `set substitute-path <from> <to>`: read sources recorded under `from` from under `to`
+    SetSubstitutePath(String, String),
+    /// `directory [dir...]`: put directories at the front of the source search list, or empty it
+    Directory(Vec<String>),
+    ShowDirectories,
+    /// `unset substitute-path [from]`: remove the rule for `from`, or every rule
+    UnsetSubstitutePath(Option<String>),
+    ShowSubstitutePath,
+    /// `maint info dwarf`: dump everything read from the debug info
+    MaintInfoDwarf,
+    /// `maint info line-table [file]`: the line table rows, in address order
+    MaintInfoLineTable(Option<String>),
+    /// `maint time on|off`: report time spent on breakpoint bookkeeping at each stop
+    MaintTime(bool),
+    InfoBreakpoints,
+    /// `info functions [-m] [regex]`: list functions, optionally only those matching the regex.
+    /// The flag (`-m`) also shows mangled names.
+    InfoFunctions(Option<String>, bool),
+    /// `info variables [regex]`: list global and file-static variables, optionally only those
+    /// matching the regex
+    InfoVariables(Option<String>),
+    /// `info scope <location>`: the variables visible at a location, from the debug info
+    InfoScope(String),
+    /// `info sharedlibrary`: the loaded shared objects
+    InfoSharedLibrary,
+    /// `info output`: the output captured from the program's last run
+    InfoOutput,
+    /// `sharedlibrary [regex]`: load symbols for the matching libraries again
+    SharedLibrary(Option<String>),
+    InfoSources,
 }
 
 impl DebuggerCommand {
+    /// Whether pressing Enter on an empty line should run this command again, as for stepping
+    /// through a program. Commands with lasting effects, such as `run`, don't repeat.
+    pub fn repeats_on_empty_line(&self) -> bool {
+        match self {
+            DebuggerCommand::Cont(_)
+            | DebuggerCommand::Until(None)
+            | DebuggerCommand::Next
+            | DebuggerCommand::Step
+            | DebuggerCommand::List(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Parses a full input line. Commands that take the raw rest of the line (such as `shell`) are
+    /// handled here, before tokenizing would destroy their quoting; everything else goes through
+    /// from_tokens.
+    pub fn from_line(line: &str) -> Option<DebuggerCommand> {
+        let line = line.trim();
+        if line.starts_with('!') {
+            return Some(DebuggerCommand::Shell(line[1..].trim().to_string()));
+        }
+        let first_word = line.split_whitespace().next()?;
+        let rest = line[first_word.len()..].trim();
+        // `x/count address`: the count is attached to the command name
+        if first_word == "x" || first_word.starts_with("x/") {
+            let count = match &first_word[1..] {
+                "" => DEFAULT_EXAMINE_LENGTH,
+                count => count[1..].parse().ok()?,
+            };
+            if rest.is_empty() {
+                return None;
+            }
+            return Some(DebuggerCommand::Examine(rest.to_string(), count));
+        }
+        let name = match resolve_name(first_word) {
+            Resolution::Command(name) => name,
+            _ => first_word,
+        };
+        match name {
+            "shell" => return Some(DebuggerCommand::Shell(rest.to_string())),
+            "echo" => return Some(DebuggerCommand::Echo(rest.to_string())),
+            "printf" => return Some(DebuggerCommand::Printf(rest.to_string())),
+            "dprintf" => {
+                let idx = rest.find(',')?;
+                let location = rest[..idx].trim();
+                if location.is_empty() {
+                    return None;
+                }
+                return Some(DebuggerCommand::Dprintf(
+                    location.to_string(),
+                    rest[idx + 1..].trim().to_string(),
+                ));
+            }
+            // The expression is the rest of the line, as typed
+            "condition" => {
+                let id_end = rest.find(char::is_whitespace).unwrap_or_else(|| rest.len());
+                let id = rest[..id_end].parse().ok()?;
+                let condition = rest[id_end..].trim();
+                return Some(DebuggerCommand::Condition(
+                    id,
+                    if condition.is_empty() { None } else { Some(condition.to_string()) },
+                ));
+            }
+            "rbreak" if !rest.is_empty() => return Some(DebuggerCommand::RBreak(rest.to_string())),
+            // The address may be an expression with spaces; the length is the last word
+            "hexdump" => {
+                let idx = rest.rfind(char::is_whitespace)?;
+                let length = rest[idx..].trim().parse().ok()?;
+                return Some(DebuggerCommand::Examine(rest[..idx].trim().to_string(), length));
+            }
+            "snapshot" => {
+                if rest.is_empty() {
+                    return Some(DebuggerCommand::Snapshots);
+                }
+                if rest == "diff" || rest.starts_with("diff ") {
+                    let name = rest[4..].trim();
+                    return Some(DebuggerCommand::SnapshotDiff(if name.is_empty() {
+                        None
+                    } else {
+                        Some(name.to_string())
+                    }));
+                }
+                let idx = rest.rfind(char::is_whitespace)?;
+                let length = rest[idx..].trim().parse().ok()?;
+                return Some(DebuggerCommand::Snapshot(rest[..idx].trim().to_string(), length));
+            }
+            "find" => {
+                let mut args = rest.splitn(3, ',').map(str::trim);
+                let start = args.next().filter(|arg| !arg.is_empty())?;
+                let end = args.next().filter(|arg| !arg.is_empty())?;
+                let pattern = parse_find_pattern(args.next()?)?;
+                return Some(DebuggerCommand::Find(start.to_string(), end.to_string(), pattern));
+            }
+            "strings" => {
+                let (nul_terminated, rest) = if rest.starts_with("-z") {
+                    (true, rest[2..].trim())
+                } else {
+                    (false, rest)
+                };
+                if rest.is_empty() {
+                    return None;
+                }
+                // A single word names a mapping; otherwise the length is the last word
+                let region = match rest.rfind(char::is_whitespace) {
+                    None => MemoryRegion::Mapping(rest.to_string()),
+                    Some(idx) => MemoryRegion::Range(
+                        rest[..idx].trim().to_string(),
+                        rest[idx..].trim().parse().ok()?,
+                    ),
+                };
+                return Some(DebuggerCommand::Strings(region, nul_terminated));
+            }
+            // `alias name = expansion`
+            "alias" => {
+                let idx = rest.find('=')?;
+                let name = rest[..idx].trim();
+                let expansion = rest[idx + 1..].trim();
+                if name.is_empty() || name.contains(char::is_whitespace) || expansion.is_empty() {
+                    return None;
+                }
+                return Some(DebuggerCommand::Alias(name.to_string(), expansion.to_string()));
+            }
+            _ => {}
+        }
+        // The program's arguments: only unquoted words can be redirections or a trailing `&`
+        let first_arg = words(rest).and_then(|words| words.into_iter().next());
+        let set_args = name == "set" && first_arg.map_or(false, |word| word.is_bare("args"));
+        if name == "run" || name == "start" || set_args {
+            let mut words = words(rest)?;
+            if set_args {
+                words.remove(0);
+            }
+            let background = words.last().map_or(false, |word| word.is_bare("&"));
+            if background {
+                words.pop();
+            }
+            let (args, redirections) = split_redirections(words)?;
+            return match (name, background) {
+                ("run", false) => Some(DebuggerCommand::Run(args, redirections)),
+                ("run", true) => Some(DebuggerCommand::Background(Box::new(
+                    DebuggerCommand::Run(args, redirections),
+                ))),
+                ("start", false) => Some(DebuggerCommand::Start(args, redirections)),
+                ("set", false) => Some(DebuggerCommand::SetArgs(args, redirections)),
+                _ => None,
+            };
+        }
+        let tokens = tokenize(line)?;
+        if tokens.is_empty() {
+            return None;
+        }
+        let tokens: Vec<&str> = tokens.iter().map(|s| s.as_str()).collect();
+        DebuggerCommand::from_tokens(&tokens)
+    }
+
     pub fn from_tokens(tokens: &Vec<&str>) -> Option<DebuggerCommand> {
-        match tokens[0] {
-            "q" | "quit" => Some(DebuggerCommand::Quit),
-            "r" | "run" => {
-                let args = tokens[1..].to_vec();
-                Some(DebuggerCommand::Run(
-                    args.iter().map(|s| s.to_string()).collect(),
-                ))
-            },
-            "c" | "cont" | "continue" => Some(DebuggerCommand::Cont),
-            "bt" | "back" | "backtrace" => Some(DebuggerCommand::Backtrace),
-            "b" | "break" => {
-                tokens.get(1).map(|s| DebuggerCommand::Break(s.to_string()))
+        // `print/x` and `display/x` carry an output format after the command name
+        let (name, format) = match tokens[0].find('/') {
+            Some(idx) => {
+                let mut letters = tokens[0][idx + 1..].chars();
+                match (letters.next().and_then(Format::from_letter), letters.next()) {
+                    (Some(format), None) => (&tokens[0][..idx], format),
+                    _ => return None,
+                }
+            }
+            None => (tokens[0], Format::Natural),
+        };
+        let name = match resolve_name(name) {
+            Resolution::Command(name) => name,
+            Resolution::Ambiguous(_) | Resolution::Unknown => return None,
+        };
+        if format != Format::Natural && !["print", "display"].contains(&name) {
+            return None;
+        }
+        // `run &` and `continue &` give the prompt back while the program runs
+        if tokens.len() > 1 && tokens.last() == Some(&"&") {
+            let tokens = tokens[..tokens.len() - 1].to_vec();
+            return match DebuggerCommand::from_tokens(&tokens)? {
+                cmd @ DebuggerCommand::Cont(None) => {
+                    Some(DebuggerCommand::Background(Box::new(cmd)))
+                }
+                _ => None,
+            };
+        }
+        match name {
+            "quit" => Some(DebuggerCommand::Quit),
+            "profile" => {
+                let callers = tokens.get(1) == Some(&"-c");
+                let rest = &tokens[if callers { 2 } else { 1 }..];
+                match rest {
+                    [] => Some(DebuggerCommand::Profile(None, callers)),
+                    [seconds] => seconds
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|seconds| *seconds > 0.0 && seconds.is_finite())
+                        .map(|seconds| DebuggerCommand::Profile(Some(seconds), callers)),
+                    _ => None,
+                }
+            }
+            "stress" => tokens
+                .get(1)
+                .and_then(|count| count.parse().ok())
+                .map(DebuggerCommand::Stress),
+            "continue" => match tokens.get(1) {
+                Some(count) => count.parse().ok().map(|count| DebuggerCommand::Cont(Some(count))),
+                None => Some(DebuggerCommand::Cont(None)),
+            },
+            "backtrace" => {
+                let full = tokens.get(1) == Some(&"full");
+                let rest = if full { &tokens[2..] } else { &tokens[1..] };
+                match rest {
+                    [] => Some(DebuggerCommand::Backtrace(None, full)),
+                    [count] => count
+                        .parse()
+                        .ok()
+                        .map(|count| DebuggerCommand::Backtrace(Some(count), full)),
+                    _ => None,
+                }
+            }
+            "frame" => match tokens.get(1) {
+                Some(level) => level.parse().ok().map(|level| DebuggerCommand::Frame(Some(level))),
+                None => Some(DebuggerCommand::Frame(None)),
+            },
+            "up" => match tokens.get(1) {
+                Some(count) => count.parse().ok().map(DebuggerCommand::Up),
+                None => Some(DebuggerCommand::Up(1)),
+            },
+            "down" => match tokens.get(1) {
+                Some(count) => count.parse().ok().map(DebuggerCommand::Down),
+                None => Some(DebuggerCommand::Down(1)),
+            },
+            "ptype" if tokens.len() > 1 => Some(DebuggerCommand::Ptype(tokens[1..].join(" "))),
+            "whatis" if tokens.len() > 1 => Some(DebuggerCommand::Whatis(tokens[1..].join(" "))),
+            "print" if tokens.len() > 1 => {
+                Some(DebuggerCommand::Print(tokens[1..].join(" "), format))
+            }
+            "display" if tokens.len() > 1 => {
+                Some(DebuggerCommand::Display(Some(tokens[1..].join(" ")), format))
+            }
+            "display" => Some(DebuggerCommand::Display(None, format)),
+            "advance" if tokens.len() > 1 => Some(DebuggerCommand::Advance(tokens[1..].join(" "))),
+            "checkpoint" => Some(DebuggerCommand::Checkpoint),
+            "kill" => Some(DebuggerCommand::Kill),
+            "finish" => Some(DebuggerCommand::Finish),
+            "next" => Some(DebuggerCommand::Next),
+            "step" => Some(DebuggerCommand::Step),
+            "skip" => match tokens.get(1) {
+                Some(&"function") if tokens.len() > 2 => {
+                    Some(DebuggerCommand::Skip(SkipTarget::Function(tokens[2..].join(" "))))
+                }
+                Some(&"file") if tokens.len() == 3 => {
+                    Some(DebuggerCommand::Skip(SkipTarget::File(tokens[2].to_string())))
+                }
+                Some(&"delete") => parse_numbers(&tokens[2..]).map(DebuggerCommand::SkipDelete),
+                _ => None,
+            },
+            "interrupt" => Some(DebuggerCommand::Interrupt),
+            "list" => Some(DebuggerCommand::List(match tokens.get(1) {
+                None | Some(&"+") => ListArgument::Forward,
+                Some(&"-") => ListArgument::Backward,
+                Some(_) => ListArgument::Around(tokens[1..].join(" ")),
+            })),
+            "file" => tokens.get(1).map(|path| DebuggerCommand::File(path.to_string())),
+            "apropos" if tokens.len() > 1 => Some(DebuggerCommand::Apropos(tokens[1..].join(" "))),
+            "help" => Some(DebuggerCommand::Help(tokens.get(1).map(|s| s.to_string()))),
+            "define" if tokens.len() == 2 => Some(DebuggerCommand::Define(tokens[1].to_string())),
+            "record" => match tokens.get(1) {
+                None => Some(DebuggerCommand::Record),
+                Some(&"stop") => Some(DebuggerCommand::RecordStop),
+                Some(_) => None,
+            },
+            "catch" => {
+                let catchpoint = match tokens.get(1) {
+                    Some(&"abort") => Catchpoint::Abort,
+                    Some(&"panic") => Catchpoint::Panic,
+                    _ => return None,
+                };
+                match tokens.get(2) {
+                    Some(&"on") | None => Some(DebuggerCommand::Catch(catchpoint, true)),
+                    Some(&"off") => Some(DebuggerCommand::Catch(catchpoint, false)),
+                    Some(_) => None,
+                }
+            }
+            "sharedlibrary" => {
+                Some(DebuggerCommand::SharedLibrary(tokens.get(1).map(|s| s.to_string())))
+            }
+            "coverage" => match tokens.get(1) {
+                Some(&"on") => Some(DebuggerCommand::Coverage(true)),
+                Some(&"off") => Some(DebuggerCommand::Coverage(false)),
+                Some(&"report") => {
+                    Some(DebuggerCommand::CoverageReport(tokens.get(2).map(|s| s.to_string())))
+                }
+                _ => None,
+            },
+            "restart" => tokens
+                .get(1)
+                .and_then(|id| id.parse().ok())
+                .map(DebuggerCommand::Restart),
+            "call" if tokens.len() > 1 => Some(DebuggerCommand::Call(tokens[1..].join(" "))),
+            "return" => Some(DebuggerCommand::Return(if tokens.len() > 1 {
+                Some(tokens[1..].join(" "))
+            } else {
+                None
+            })),
+            "jump" if tokens.len() > 1 => Some(DebuggerCommand::Jump(tokens[1..].join(" "))),
+            "until" => match tokens.get(1) {
+                Some(line) => line.parse().ok().map(|line| DebuggerCommand::Until(Some(line))),
+                None => Some(DebuggerCommand::Until(None)),
+            },
+            "watch" => tokens.get(1).map(|name| DebuggerCommand::Watch(name.to_string())),
+            "delete" => parse_numbers(&tokens[1..]).map(DebuggerCommand::Delete),
+            "directory" => Some(DebuggerCommand::Directory(
+                tokens[1..]
+                    .iter()
+                    .flat_map(|arg| arg.split(':'))
+                    .filter(|dir| !dir.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            )),
+            "clear" if tokens.len() > 1 => Some(DebuggerCommand::Clear(tokens[1..].join(" "))),
+            "undisplay" => match tokens.get(1) {
+                Some(id) => id.parse().ok().map(|id| DebuggerCommand::Undisplay(Some(id))),
+                None => Some(DebuggerCommand::Undisplay(None)),
+            },
+            "enable" | "disable" => match (tokens.get(1), tokens.get(2)) {
+                (Some(&"display"), Some(id)) => id
+                    .parse()
+                    .ok()
+                    .map(|id| DebuggerCommand::EnableDisplay(id, name == "enable")),
+                (Some(&"display"), None) => None,
+                (Some(&"once"), _) if name == "enable" => parse_numbers(&tokens[2..])
+                    .map(|ids| DebuggerCommand::EnableBreakpoints(ids, EnableMode::Once)),
+                (Some(&"count"), Some(count)) if name == "enable" => {
+                    match (count.parse(), parse_numbers(&tokens[3..])) {
+                        (Ok(count), Some(ids)) if count > 0 => Some(
+                            DebuggerCommand::EnableBreakpoints(ids, EnableMode::Count(count)),
+                        ),
+                        _ => None,
+                    }
+                }
+                _ if name == "enable" => parse_numbers(&tokens[1..])
+                    .map(|ids| DebuggerCommand::EnableBreakpoints(ids, EnableMode::Keep)),
+                _ => parse_numbers(&tokens[1..]).map(DebuggerCommand::DisableBreakpoints),
+            },
+            "ignore" => match (tokens.get(1), tokens.get(2)) {
+                (Some(id), Some(count)) => match (id.parse(), count.parse()) {
+                    (Ok(id), Ok(count)) => Some(DebuggerCommand::Ignore(id, count)),
+                    _ => None,
+                },
+                _ => None,
+            },
+            // The location is the rest of the line, since C++ signatures can contain spaces
+            "break" => match tokens.get(1) {
+                Some(&"-force") if tokens.len() > 2 => {
+                    Some(DebuggerCommand::Break(tokens[2..].join(" "), true))
+                }
+                Some(&"-force") | None => None,
+                Some(_) => Some(DebuggerCommand::Break(tokens[1..].join(" "), false)),
+            },
+            "set" => match tokens.get(1) {
+                Some(&"env") | Some(&"environment") => {
+                    // Accept both `VAR=value` and `VAR value`
+                    let first = tokens.get(2)?;
+                    match first.find('=') {
+                        Some(idx) => Some(DebuggerCommand::SetEnvironment(
+                            first[..idx].to_string(),
+                            first[idx + 1..].to_string(),
+                        )),
+                        None => Some(DebuggerCommand::SetEnvironment(
+                            first.to_string(),
+                            tokens[3..].join(" "),
+                        )),
+                    }
+                }
+                Some(&"logging") => match (tokens.get(2), tokens.get(3)) {
+                    (Some(&"on"), None) => Some(DebuggerCommand::SetLogging(true)),
+                    (Some(&"off"), None) => Some(DebuggerCommand::SetLogging(false)),
+                    (Some(&"file"), Some(path)) => {
+                        Some(DebuggerCommand::SetLoggingFile(path.to_string()))
+                    }
+                    (Some(&"overwrite"), Some(&"on")) | (Some(&"overwrite"), None) => {
+                        Some(DebuggerCommand::SetLoggingOverwrite(true))
+                    }
+                    (Some(&"overwrite"), Some(&"off")) => {
+                        Some(DebuggerCommand::SetLoggingOverwrite(false))
+                    }
+                    _ => None,
+                },
+                Some(&"inferior-tty") => match tokens.get(2) {
+                    Some(&"on") => Some(DebuggerCommand::SetInferiorTty(true)),
+                    Some(&"off") => Some(DebuggerCommand::SetInferiorTty(false)),
+                    _ => None,
+                },
+                Some(&"capture-output") => match tokens.get(2) {
+                    Some(&"on") => Some(DebuggerCommand::SetCaptureOutput(true)),
+                    Some(&"off") => Some(DebuggerCommand::SetCaptureOutput(false)),
+                    _ => None,
+                },
+                Some(&"disable-randomization") => match tokens.get(2) {
+                    Some(&"on") | None => Some(DebuggerCommand::SetDisableRandomization(true)),
+                    Some(&"off") => Some(DebuggerCommand::SetDisableRandomization(false)),
+                    _ => None,
+                },
+                Some(&"auto-reload") => match tokens.get(2) {
+                    Some(&"on") | None => Some(DebuggerCommand::SetAutoReload(true)),
+                    Some(&"off") => Some(DebuggerCommand::SetAutoReload(false)),
+                    _ => None,
+                },
+                Some(&"trace-syscalls") => match tokens.get(2) {
+                    Some(&"on") | None => Some(DebuggerCommand::SetTraceSyscalls(true)),
+                    Some(&"off") => Some(DebuggerCommand::SetTraceSyscalls(false)),
+                    _ => None,
+                },
+                Some(&"trace-calls") => match tokens.get(2) {
+                    Some(&"on") | None => Some(DebuggerCommand::SetTraceCalls(true)),
+                    Some(&"off") => Some(DebuggerCommand::SetTraceCalls(false)),
+                    _ => None,
+                },
+                Some(&"heap-track") => match tokens.get(2) {
+                    Some(&"on") | None => Some(DebuggerCommand::SetHeapTrack(true)),
+                    Some(&"off") => Some(DebuggerCommand::SetHeapTrack(false)),
+                    _ => None,
+                },
+                Some(&"step-into-no-debug") => match tokens.get(2) {
+                    Some(&"on") | None => Some(DebuggerCommand::SetStepIntoNoDebug(true)),
+                    Some(&"off") => Some(DebuggerCommand::SetStepIntoNoDebug(false)),
+                    _ => None,
+                },
+                Some(&"stop-on-solib-events") => match tokens.get(2) {
+                    Some(&"on") | None => Some(DebuggerCommand::SetStopOnSolibEvents(true)),
+                    Some(&"off") => Some(DebuggerCommand::SetStopOnSolibEvents(false)),
+                    _ => None,
+                },
+                Some(&"confirm") => match tokens.get(2) {
+                    Some(&"on") | None => Some(DebuggerCommand::SetConfirm(true)),
+                    Some(&"off") => Some(DebuggerCommand::SetConfirm(false)),
+                    _ => None,
+                },
+                Some(&"backtrace") => match (tokens.get(2), tokens.get(3)) {
+                    (Some(&"limit"), Some(&"unlimited")) => {
+                        Some(DebuggerCommand::SetBacktraceLimit(0))
+                    }
+                    (Some(&"limit"), Some(limit)) => {
+                        limit.parse().ok().map(DebuggerCommand::SetBacktraceLimit)
+                    }
+                    _ => None,
+                },
+                Some(&"listsize") => tokens
+                    .get(2)
+                    .and_then(|lines| lines.parse().ok())
+                    .filter(|&lines| lines > 0)
+                    .map(DebuggerCommand::SetListSize),
+                Some(&"context") => tokens
+                    .get(2)
+                    .and_then(|lines| lines.parse().ok())
+                    .map(DebuggerCommand::SetContext),
+                Some(&"print") => match (tokens.get(2), tokens.get(3)) {
+                    (Some(&"source"), Some(&"on")) | (Some(&"source"), None) => {
+                        Some(DebuggerCommand::SetPrintSource(true))
+                    }
+                    (Some(&"source"), Some(&"off")) => Some(DebuggerCommand::SetPrintSource(false)),
+                    _ => None,
+                },
+                Some(&"debug-file-directory") => tokens
+                    .get(2)
+                    .map(|dir| DebuggerCommand::SetDebugFileDirectory(dir.to_string())),
+                Some(&"substitute-path") => match (tokens.get(2), tokens.get(3)) {
+                    (Some(from), Some(to)) if tokens.len() == 4 => Some(
+                        DebuggerCommand::SetSubstitutePath(from.to_string(), to.to_string()),
+                    ),
+                    _ => None,
+                },
+                Some(&"style") => match tokens.get(2) {
+                    Some(&"on") => Some(DebuggerCommand::SetStyle(true)),
+                    Some(&"off") => Some(DebuggerCommand::SetStyle(false)),
+                    _ => None,
+                },
+                Some(&"pagination") => match tokens.get(2) {
+                    Some(&"on") => Some(DebuggerCommand::SetPagination(true)),
+                    Some(&"off") => Some(DebuggerCommand::SetPagination(false)),
+                    _ => None,
+                },
+                _ => None,
+            },
+            "unset" => match tokens.get(1) {
+                Some(&"env") | Some(&"environment") => Some(DebuggerCommand::UnsetEnvironment(
+                    tokens.get(2).map(|s| s.to_string()),
+                )),
+                Some(&"substitute-path") => Some(DebuggerCommand::UnsetSubstitutePath(
+                    tokens.get(2).map(|s| s.to_string()),
+                )),
+                _ => None,
+            },
+            "show" => match tokens.get(1) {
+                Some(&"args") => Some(DebuggerCommand::ShowArgs),
+                Some(&"env") | Some(&"environment") => Some(DebuggerCommand::ShowEnvironment),
+                Some(&"inferior-tty") => Some(DebuggerCommand::ShowInferiorTty),
+                Some(&"capture-output") => Some(DebuggerCommand::ShowCaptureOutput),
+                Some(&"disable-randomization") => Some(DebuggerCommand::ShowDisableRandomization),
+                Some(&"trace-syscalls") => Some(DebuggerCommand::ShowTraceSyscalls),
+                Some(&"trace-calls") => Some(DebuggerCommand::ShowTraceCalls),
+                Some(&"heap-track") => Some(DebuggerCommand::ShowHeapTrack),
+                Some(&"step-into-no-debug") => Some(DebuggerCommand::ShowStepIntoNoDebug),
+                Some(&"stop-on-solib-events") => Some(DebuggerCommand::ShowStopOnSolibEvents),
+                Some(&"style") => Some(DebuggerCommand::ShowStyle),
+                Some(&"pagination") => Some(DebuggerCommand::ShowPagination),
+                Some(&"auto-reload") => Some(DebuggerCommand::ShowAutoReload),
+                Some(&"confirm") => Some(DebuggerCommand::ShowConfirm),
+                Some(&"context") => Some(DebuggerCommand::ShowContext),
+                Some(&"listsize") => Some(DebuggerCommand::ShowListSize),
+                Some(&"backtrace") => match tokens.get(2) {
+                    Some(&"limit") => Some(DebuggerCommand::ShowBacktraceLimit),
+                    _ => None,
+                },
+                Some(&"print") => match tokens.get(2) {
+                    Some(&"source") => Some(DebuggerCommand::ShowPrintSource),
+                    _ => None,
+                },
+                Some(&"debug-file-directory") => Some(DebuggerCommand::ShowDebugFileDirectory),
+                Some(&"substitute-path") => Some(DebuggerCommand::ShowSubstitutePath),
+                Some(&"directories") => Some(DebuggerCommand::ShowDirectories),
+                Some(&"logging") => Some(DebuggerCommand::ShowLogging),
+                Some(&"user") => {
+                    Some(DebuggerCommand::ShowUser(tokens.get(2).map(|s| s.to_string())))
+                }
+                Some(&"values") => Some(DebuggerCommand::ShowValues),
+                _ => None,
+            },
+            "cd" => Some(DebuggerCommand::Cd(tokens.get(1).map(|s| s.to_string()))),
+            "pwd" => Some(DebuggerCommand::Pwd),
+            "tui" => match tokens.get(1) {
+                Some(&"enable") => Some(DebuggerCommand::Tui(true)),
+                Some(&"disable") => Some(DebuggerCommand::Tui(false)),
+                _ => None,
+            },
+            "source" => match tokens.get(1) {
+                Some(&"-c") => tokens.get(2).map(|s| DebuggerCommand::Source(s.to_string(), true)),
+                Some(path) => Some(DebuggerCommand::Source(path.to_string(), false)),
+                None => None,
+            },
+            "save" => match (tokens.get(1), tokens.get(2)) {
+                (Some(&"breakpoints"), Some(path)) => {
+                    Some(DebuggerCommand::SaveBreakpoints(path.to_string()))
+                }
+                _ => None,
+            },
+            "history" => match tokens.get(1) {
+                None => Some(DebuggerCommand::History),
+                Some(&"save") => Some(DebuggerCommand::HistorySave),
+                Some(_) => None,
+            },
+            "info" => match tokens.get(1) {
+                Some(&"b") | Some(&"break") | Some(&"breakpoints") => {
+                    Some(DebuggerCommand::InfoBreakpoints)
+                }
+                Some(&"sources") => Some(DebuggerCommand::InfoSources),
+                Some(&"display") => Some(DebuggerCommand::InfoDisplay),
+                Some(&"catch") => Some(DebuggerCommand::InfoCatch),
+                Some(&"checkpoints") => Some(DebuggerCommand::InfoCheckpoints),
+                Some(&"aliases") => Some(DebuggerCommand::InfoAliases),
+                Some(&"frame") => Some(DebuggerCommand::InfoFrame),
+                Some(&"inferior") | Some(&"inferiors") => Some(DebuggerCommand::InfoInferior),
+                Some(&"address") => {
+                    tokens.get(2).map(|name| DebuggerCommand::InfoAddress(name.to_string()))
+                }
+                Some(&"symbol") => {
+                    tokens.get(2).map(|addr| DebuggerCommand::InfoSymbol(addr.to_string()))
+                }
+                Some(&"line") if tokens.len() > 2 => {
+                    Some(DebuggerCommand::InfoLine(tokens[2..].join(" ")))
+                }
+                Some(&"stats") => Some(DebuggerCommand::InfoStats),
+                Some(&"siginfo") | Some(&"signal") => Some(DebuggerCommand::InfoSiginfo),
+                Some(&"skip") => Some(DebuggerCommand::InfoSkip),
+                Some(&"float") => Some(DebuggerCommand::InfoFloat),
+                Some(&"registers") => Some(DebuggerCommand::InfoRegisters(
+                    tokens[2..].iter().map(|name| name.to_string()).collect(),
+                )),
+                Some(&"all-registers") => Some(DebuggerCommand::InfoAllRegisters),
+                Some(&"vector") => Some(DebuggerCommand::InfoVector),
+                Some(&"trace-calls") => Some(DebuggerCommand::InfoTraceCalls),
+                Some(&"heap") => Some(DebuggerCommand::InfoHeap),
+                Some(&"sharedlibrary") => Some(DebuggerCommand::InfoSharedLibrary),
+                Some(&"output") => Some(DebuggerCommand::InfoOutput),
+                Some(&"scope") if tokens.len() > 2 => {
+                    Some(DebuggerCommand::InfoScope(tokens[2..].join(" ")))
+                }
+                Some(&"record") => match tokens.get(2) {
+                    Some(count) => count.parse().ok().map(|n| DebuggerCommand::InfoRecord(Some(n))),
+                    None => Some(DebuggerCommand::InfoRecord(None)),
+                },
+                Some(&"var") | Some(&"variables") => {
+                    Some(DebuggerCommand::InfoVariables(tokens.get(2).map(|s| s.to_string())))
+                }
+                Some(&"fun") | Some(&"functions") => match tokens.get(2) {
+                    Some(&"-m") => Some(DebuggerCommand::InfoFunctions(
+                        tokens.get(3).map(|s| s.to_string()),
+                        true,
+                    )),
+                    regex => Some(DebuggerCommand::InfoFunctions(
+                        regex.map(|s| s.to_string()),
+                        false,
+                    )),
+                },
+                _ => None,
+            },
+            "maintenance" => match (tokens.get(1), tokens.get(2)) {
+                (Some(&"info"), Some(&"dwarf")) | (Some(&"info"), Some(&"symbols")) => {
+                    Some(DebuggerCommand::MaintInfoDwarf)
+                }
+                (Some(&"info"), Some(&"line-table")) => Some(DebuggerCommand::MaintInfoLineTable(
+                    tokens.get(3).map(|file| file.to_string()),
+                )),
+                (Some(&"time"), Some(&"on")) => Some(DebuggerCommand::MaintTime(true)),
+                (Some(&"time"), Some(&"off")) => Some(DebuggerCommand::MaintTime(false)),
+                _ => None,
             },
             // Default case:
             _ => None,
         }
     }
 }
+
+/// Parses what `find` searches for into bytes: hex bytes (`0xde 0xad`), a double-quoted string
+/// (with C escapes, and no terminating NUL), or an integer with an optional size suffix (`/b`,
+/// `/h`, `/w` or `/g`; without one, 4 bytes if it fits and 8 otherwise), stored little-endian.
+fn parse_find_pattern(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim();
+    if text.len() >= 2 && text.starts_with('"') && text.ends_with('"') {
+        let bytes = crate::printf::unescape(&text[1..text.len() - 1]).into_bytes();
+        return if bytes.is_empty() { None } else { Some(bytes) };
+    }
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let is_byte = |word: &&str| {
+        word.starts_with("0x") && (3..=4).contains(&word.len()) && parse_int(word).is_some()
+    };
+    if !words.is_empty() && words.iter().all(is_byte) {
+        return words.iter().map(|word| parse_int(word).map(|n| n as u8)).collect();
+    }
+    if words.len() != 1 {
+        return None;
+    }
+    let (number, size) = match text.find('/') {
+        Some(idx) => {
+            let size = match &text[idx + 1..] {
+                "b" => 1,
+                "h" => 2,
+                "w" => 4,
+                "g" => 8,
+                _ => return None,
+            };
+            (&text[..idx], Some(size))
+        }
+        None => (text, None),
+    };
+    let (negative, digits) = if number.starts_with('-') {
+        (true, &number[1..])
+    } else {
+        (false, number)
+    };
+    let magnitude = parse_int(digits)?;
+    let value = if negative {
+        (magnitude as i64).wrapping_neg() as u64
+    } else {
+        magnitude
+    };
+    let fits_in_word = if negative {
+        magnitude <= 1 << 31
+    } else {
+        magnitude <= u64::from(u32::MAX)
+    };
+    let size = size.unwrap_or(if fits_in_word { 4 } else { 8 });
+    Some(value.to_le_bytes()[..size].to_vec())
+}
+
+/// Parses a decimal or `0x` hex integer.
+fn parse_int(text: &str) -> Option<u64> {
+    if text.starts_with("0x") {
+        u64::from_str_radix(&text[2..], 16).ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+/// Parses a list of decimal numbers (breakpoint numbers, say), which may be empty.
+fn parse_numbers(tokens: &[&str]) -> Option<Vec<usize>> {
+    tokens.iter().map(|token| token.parse().ok()).collect()
+}
+
+/// Splits a command line into tokens on whitespace. Single or double quotes group characters
+/// (including whitespace) into one token, and a backslash escapes the following character. None
+/// if a quote is left open.
+pub fn tokenize(line: &str) -> Option<Vec<String>> {
+    Some(words(line)?.into_iter().map(|word| word.text).collect())
+}
+
+/// A token of a command line, with its quotes and backslashes removed.
+struct Word {
+    text: String,
+    /// How many bytes at the start of `text` were neither quoted nor escaped, and so may be
+    /// syntax, such as a redirection, rather than literal text
+    bare: usize,
+}
+
+impl Word {
+    /// Whether the word is `syntax`, unquoted.
+    fn is_bare(&self, syntax: &str) -> bool {
+        self.text == syntax && self.bare == self.text.len()
+    }
+}
+
+/// Splits a command line into words, as tokenize does, noting which parts were quoted.
+fn words(line: &str) -> Option<Vec<Word>> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_token = false;
+    // Where the first quote or backslash of the current word came
+    let mut bare: Option<usize> = None;
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => {
+                bare.get_or_insert(current.len());
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+                in_token = true;
+            }
+            (Some(_), c) => current.push(c),
+            (None, '"') | (None, '\'') => {
+                bare.get_or_insert(current.len());
+                quote = Some(c);
+                in_token = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_token {
+                    let text = std::mem::replace(&mut current, String::new());
+                    let bare = bare.take().unwrap_or_else(|| text.len());
+                    words.push(Word { text, bare });
+                    in_token = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_token = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return None;
+    }
+    if in_token {
+        let bare = bare.unwrap_or_else(|| current.len());
+        words.push(Word { text: current, bare });
+    }
+    Some(words)
+}
+
+/// Separates shell-style redirections (`< file`, `> file`, `>> file`, `2> file`, `2>&1`) from the
+/// arguments of `run`, `start` or `set args`. The operator may also be attached to the file name,
+/// as in `<input.txt`. Only an unquoted operator counts, so `run ">3"` passes `>3` to the program.
+/// None if an operator has no file name after it.
+fn split_redirections(words: Vec<Word>) -> Option<(Vec<String>, Vec<Redirection>)> {
+    let mut args = Vec::new();
+    let mut redirections = Vec::new();
+    let mut words = words.into_iter();
+    while let Some(word) = words.next() {
+        if word.is_bare("2>&1") {
+            redirections.push(Redirection::StderrToStdout);
+            continue;
+        }
+        let op = match ["2>", ">>", "<", ">"]
+            .iter()
+            .find(|op| word.text.starts_with(*op) && word.bare >= op.len())
+        {
+            Some(op) => *op,
+            None => {
+                args.push(word.text);
+                continue;
+            }
+        };
+        let path = if word.text.len() > op.len() {
+            word.text[op.len()..].to_string()
+        } else {
+            words.next()?.text
+        };
+        redirections.push(match op {
+            "<" => Redirection::Stdin(path),
+            ">>" => Redirection::AppendStdout(path),
+            "2>" => Redirection::Stderr(path),
+            _ => Redirection::Stdout(path),
+        });
+    }
+    Some((args, redirections))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_arguments(line: &str) -> Option<(Vec<String>, Vec<Redirection>)> {
+        match DebuggerCommand::from_line(line)? {
+            DebuggerCommand::Run(args, redirections) => Some((args, redirections)),
+            _ => None,
+        }
+    }
+
+    /// What every command word of one to three letters that starts a command resolves to: the
+    /// command, or the commands it is ambiguous between. A new command that changes what one of
+    /// them means has to change this table too.
+    const PREFIXES: &[(&str, &[&str])] = &[
+        ("a", &["advance", "alias", "apropos"]),
+        ("ad", &["advance"]),
+        ("adv", &["advance"]),
+        ("al", &["alias"]),
+        ("ali", &["alias"]),
+        ("ap", &["apropos"]),
+        ("apr", &["apropos"]),
+        ("b", &["break"]),
+        ("ba", &["backtrace"]),
+        ("bac", &["backtrace"]),
+        ("br", &["break"]),
+        ("bre", &["break"]),
+        ("bt", &["backtrace"]),
+        ("c", &["continue"]),
+        ("ca", &["call", "catch"]),
+        ("cal", &["call"]),
+        ("cat", &["catch"]),
+        ("cd", &["cd"]),
+        ("ch", &["checkpoint"]),
+        ("che", &["checkpoint"]),
+        ("cl", &["clear"]),
+        ("cle", &["clear"]),
+        ("co", &["condition", "continue", "coverage"]),
+        ("con", &["condition", "continue"]),
+        ("cov", &["coverage"]),
+        ("d", &["delete"]),
+        ("de", &["define", "delete"]),
+        ("def", &["define"]),
+        ("del", &["delete"]),
+        ("di", &["directory", "disable", "display"]),
+        ("dir", &["directory"]),
+        ("dis", &["disable", "display"]),
+        ("do", &["down"]),
+        ("dow", &["down"]),
+        ("dp", &["dprintf"]),
+        ("dpr", &["dprintf"]),
+        ("e", &["echo", "enable"]),
+        ("ec", &["echo"]),
+        ("ech", &["echo"]),
+        ("en", &["enable"]),
+        ("ena", &["enable"]),
+        ("f", &["frame"]),
+        ("fi", &["file", "find", "finish"]),
+        ("fil", &["file"]),
+        ("fin", &["finish"]),
+        ("fr", &["frame"]),
+        ("fra", &["frame"]),
+        ("h", &["help", "hexdump", "history"]),
+        ("he", &["help", "hexdump"]),
+        ("hel", &["help"]),
+        ("hex", &["hexdump"]),
+        ("hi", &["history"]),
+        ("his", &["history"]),
+        ("i", &["info"]),
+        ("ig", &["ignore"]),
+        ("ign", &["ignore"]),
+        ("in", &["info", "interrupt"]),
+        ("inf", &["info"]),
+        ("int", &["interrupt"]),
+        ("j", &["jump"]),
+        ("ju", &["jump"]),
+        ("jum", &["jump"]),
+        ("k", &["kill"]),
+        ("ki", &["kill"]),
+        ("kil", &["kill"]),
+        ("l", &["list"]),
+        ("li", &["list"]),
+        ("lis", &["list"]),
+        ("m", &["maintenance"]),
+        ("ma", &["maintenance"]),
+        ("mai", &["maintenance"]),
+        ("n", &["next"]),
+        ("ne", &["next"]),
+        ("nex", &["next"]),
+        ("p", &["print"]),
+        ("pr", &["print", "printf", "profile"]),
+        ("pri", &["print", "printf"]),
+        ("pro", &["profile"]),
+        ("pt", &["ptype"]),
+        ("pty", &["ptype"]),
+        ("pw", &["pwd"]),
+        ("pwd", &["pwd"]),
+        ("q", &["quit"]),
+        ("qu", &["quit"]),
+        ("qui", &["quit"]),
+        ("r", &["run"]),
+        ("rb", &["rbreak"]),
+        ("rbr", &["rbreak"]),
+        ("re", &["record", "restart", "return"]),
+        ("rec", &["record"]),
+        ("res", &["restart"]),
+        ("ret", &["return"]),
+        ("ru", &["run"]),
+        ("run", &["run"]),
+        ("s", &["step"]),
+        ("sa", &["save"]),
+        ("sav", &["save"]),
+        ("se", &["set"]),
+        ("set", &["set"]),
+        ("sh", &["sharedlibrary", "shell", "show"]),
+        ("sha", &["sharedlibrary"]),
+        ("she", &["shell"]),
+        ("sho", &["show"]),
+        ("sk", &["skip"]),
+        ("ski", &["skip"]),
+        ("sn", &["snapshot"]),
+        ("sna", &["snapshot"]),
+        ("so", &["source"]),
+        ("sou", &["source"]),
+        ("st", &["start", "step", "stress", "strings"]),
+        ("sta", &["start"]),
+        ("ste", &["step"]),
+        ("str", &["stress", "strings"]),
+        ("t", &["tui"]),
+        ("tu", &["tui"]),
+        ("tui", &["tui"]),
+        ("u", &["until"]),
+        ("un", &["undisplay", "unset", "until"]),
+        ("und", &["undisplay"]),
+        ("uns", &["unset"]),
+        ("unt", &["until"]),
+        ("up", &["up"]),
+        ("w", &["watch", "whatis"]),
+        ("wa", &["watch"]),
+        ("wat", &["watch"]),
+        ("wh", &["whatis"]),
+        ("wha", &["whatis"]),
+        ("x", &["x"]),
+    ];
+
+    #[test]
+    fn short_prefixes() {
+        for (prefix, expected) in PREFIXES {
+            let resolved = match resolve_name(prefix) {
+                Resolution::Command(name) => vec![name],
+                Resolution::Ambiguous(names) => names,
+                Resolution::Unknown => Vec::new(),
+            };
+            assert_eq!(resolved, *expected, "resolving {}", prefix);
+        }
+        for name in command_names() {
+            for len in 1..=name.len().min(3) {
+                let prefix = &name[..len];
+                let listed = PREFIXES.iter().any(|(listed, _)| *listed == prefix);
+                assert!(listed, "what {} resolves to isn't in the table", prefix);
+            }
+        }
+        for word in ["xy", "zz", "qqq", "brz"].iter() {
+            match resolve_name(word) {
+                Resolution::Unknown => {}
+                _ => panic!("{} shouldn't resolve", word),
+            }
+        }
+    }
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("break", "break"), 0);
+        assert_eq!(edit_distance("", "run"), 3);
+        assert_eq!(edit_distance("run", ""), 3);
+        assert_eq!(edit_distance("backtrce", "backtrace"), 1);
+        assert_eq!(edit_distance("backtrace", "backtrce"), 1);
+        assert_eq!(edit_distance("brake", "break"), 2);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn typo_suggestions() {
+        let none = std::iter::empty();
+        assert_eq!(suggest_commands("backtrce", none.clone()), vec!["backtrace"]);
+        assert_eq!(suggest_commands("contineu", none.clone()), vec!["continue"]);
+        assert_eq!(suggest_commands("diable", none.clone()), vec!["disable"]);
+        assert_eq!(suggest_commands("brek", none.clone()), vec!["break"]);
+        assert_eq!(suggest_commands("sho", none.clone()), vec!["show"]);
+        // Too far from anything
+        assert!(suggest_commands("hlep", none.clone()).is_empty());
+        assert!(suggest_commands("frobnicate", none.clone()).is_empty());
+        // Short words are a letter or two from too many commands to suggest any
+        for word in ["z", "qq", "zz", "xx"].iter() {
+            assert!(suggest_commands(word, none.clone()).is_empty(), "suggested for {}", word);
+        }
+        // Aliases and user-defined commands, counted once even if they are named twice
+        let extra = ["mybreak", "mybreak"];
+        assert_eq!(suggest_commands("mybrek", extra.iter().copied()), vec!["mybreak"]);
+        // Only the closest few
+        let extra = ["alpha1", "alpha2", "alpha3", "alpha4", "alphabet"];
+        let suggestions = suggest_commands("alpha", extra.iter().copied());
+        assert_eq!(suggestions, vec!["alpha1", "alpha2", "alpha3"]);
+    }
+
+    #[test]
+    fn every_command_is_described() {
+        for command in COMMANDS {
+            assert!(!command.summary.trim().is_empty(), "{} has no summary", command.name);
+            assert!(command.summary.ends_with('.'), "{}'s summary is cut short", command.name);
+            assert!(command.usage.starts_with(command.name), "{}'s usage", command.name);
+        }
+        // `maint` is listed under its abbreviation too
+        for (name, _) in SUBCOMMANDS {
+            match resolve_name(name) {
+                Resolution::Command(_) => {}
+                _ => panic!("{} has subcommands but isn't a command", name),
+            }
+        }
+        for (abbreviation, name) in ABBREVIATIONS {
+            assert!(command_info(name).is_some(), "{} stands for {}", abbreviation, name);
+        }
+        let names: Vec<&str> = command_names().collect();
+        let mut sorted = names.clone();
+        sorted.sort();
+        assert_eq!(names, sorted, "the commands aren't in alphabetical order");
+    }
+
+    #[test]
+    fn tokens() {
+        let cases: &[(&str, &[&str])] = &[
+            ("", &[]),
+            ("   ", &[]),
+            ("run a b", &["run", "a", "b"]),
+            ("  run \t a   b  ", &["run", "a", "b"]),
+            (r#"run "a b" 'c d'"#, &["run", "a b", "c d"]),
+            // Quotes of the other kind, and backslashes in single quotes, are literal
+            (r#"run "it's" 'say "hi"' 'a\b'"#, &["run", "it's", "say \"hi\"", "a\\b"]),
+            // A backslash escapes outside quotes and in double quotes
+            (r#"run a\ b "c\"d" \'"#, &["run", "a b", "c\"d", "'"]),
+            // Quoted parts join the text around them into one token
+            (r#"run x"a b"y 'c'd"#, &["run", "xa by", "cd"]),
+            // Empty quotes are an empty argument
+            (r#"run "" ''"#, &["run", "", ""]),
+            // A backslash at the very end escapes nothing
+            ("run a\\", &["run", "a"]),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(tokenize(line).unwrap(), *expected, "tokenizing {:?}", line);
+        }
+    }
+
+    #[test]
+    fn unterminated_quotes() {
+        for line in [r#"run "a b"#, "run 'a", r#"run "a\""#, r#"print "'"#].iter() {
+            assert_eq!(tokenize(line), None, "tokenizing {:?}", line);
+            assert!(DebuggerCommand::from_line(line).is_none());
+        }
+        assert!(DebuggerCommand::from_line("set args 'a").is_none());
+    }
+
+    #[test]
+    fn run_redirections() {
+        let (args, redirections) = run_arguments("run a < in > out 2>&1 b").unwrap();
+        assert_eq!(args, vec!["a", "b"]);
+        assert_eq!(
+            redirections,
+            vec![
+                Redirection::Stdin("in".to_string()),
+                Redirection::Stdout("out".to_string()),
+                Redirection::StderrToStdout,
+            ]
+        );
+        let (args, redirections) = run_arguments("run <in >>log 2>err").unwrap();
+        assert!(args.is_empty());
+        assert_eq!(
+            redirections,
+            vec![
+                Redirection::Stdin("in".to_string()),
+                Redirection::AppendStdout("log".to_string()),
+                Redirection::Stderr("err".to_string()),
+            ]
+        );
+        assert!(DebuggerCommand::from_line("run a >").is_none());
+    }
+
+    #[test]
+    fn quoted_redirections_are_arguments() {
+        let (args, redirections) = run_arguments(r#"run ">3" '<html>' \>x "2>&1" "&""#).unwrap();
+        assert_eq!(args, vec![">3", "<html>", ">x", "2>&1", "&"]);
+        assert!(redirections.is_empty());
+        // Only the operator has to be unquoted; the file name may be quoted
+        let (args, redirections) = run_arguments(r#"run >"my file" < 'in put'"#).unwrap();
+        assert!(args.is_empty());
+        assert_eq!(
+            redirections,
+            vec![
+                Redirection::Stdout("my file".to_string()),
+                Redirection::Stdin("in put".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn run_in_background() {
+        match DebuggerCommand::from_line("run x &") {
+            Some(DebuggerCommand::Background(cmd)) => match *cmd {
+                DebuggerCommand::Run(args, _) => assert_eq!(args, vec!["x"]),
+                _ => panic!("expected run"),
+            },
+            _ => panic!("expected a background run"),
+        }
+        assert!(DebuggerCommand::from_line("start &").is_none());
+    }
+
+    #[test]
+    fn set_args() {
+        match DebuggerCommand::from_line("set args 'a b' > out") {
+            Some(DebuggerCommand::SetArgs(args, redirections)) => {
+                assert_eq!(args, vec!["a b"]);
+                assert_eq!(redirections, vec![Redirection::Stdout("out".to_string())]);
+            }
+            _ => panic!("expected set args"),
+        }
+    }
+
+    #[test]
+    fn conditions() {
+        match DebuggerCommand::from_line("condition 2 n > 0 && p->next != 0") {
+            Some(DebuggerCommand::Condition(id, condition)) => {
+                assert_eq!((id, condition.as_deref()), (2, Some("n > 0 && p->next != 0")))
+            }
+            _ => panic!("expected a condition"),
+        }
+        match DebuggerCommand::from_line("cond 2") {
+            Some(DebuggerCommand::Condition(id, condition)) => {
+                assert_eq!((id, condition), (2, None))
+            }
+            _ => panic!("expected a condition to be removed"),
+        }
+        assert!(DebuggerCommand::from_line("condition").is_none());
+        assert!(DebuggerCommand::from_line("condition n > 0").is_none());
+    }
+
+    #[test]
+    fn skip_entries() {
+        match DebuggerCommand::from_line("skip function log_*") {
+            Some(DebuggerCommand::Skip(target)) => {
+                assert_eq!(target, SkipTarget::Function("log_*".to_string()))
+            }
+            _ => panic!("expected skip function"),
+        }
+        match DebuggerCommand::from_line("skip file src/vendor.c") {
+            Some(DebuggerCommand::Skip(target)) => {
+                assert_eq!(target, SkipTarget::File("src/vendor.c".to_string()))
+            }
+            _ => panic!("expected skip file"),
+        }
+        match DebuggerCommand::from_line("skip delete 2 3") {
+            Some(DebuggerCommand::SkipDelete(ids)) => assert_eq!(ids, vec![2, 3]),
+            _ => panic!("expected skip delete"),
+        }
+        assert!(DebuggerCommand::from_line("skip function").is_none());
+        assert!(DebuggerCommand::from_line("skip delete two").is_none());
+    }
+
+    #[test]
+    fn environment() {
+        match DebuggerCommand::from_line("set environment DEBUG=1") {
+            Some(DebuggerCommand::SetEnvironment(var, value)) => {
+                assert_eq!((var.as_str(), value.as_str()), ("DEBUG", "1"))
+            }
+            _ => panic!("expected set environment"),
+        }
+        match DebuggerCommand::from_line("unset env DEBUG") {
+            Some(DebuggerCommand::UnsetEnvironment(var)) => {
+                assert_eq!(var.as_deref(), Some("DEBUG"))
+            }
+            _ => panic!("expected a variable to be unset"),
+        }
+        // With no variable, the whole environment is cleared
+        match DebuggerCommand::from_line("unset environment") {
+            Some(DebuggerCommand::UnsetEnvironment(var)) => assert_eq!(var, None),
+            _ => panic!("expected the environment to be cleared"),
+        }
+    }
+
+    #[test]
+    fn cd() {
+        match DebuggerCommand::from_line("cd /tmp") {
+            Some(DebuggerCommand::Cd(dir)) => assert_eq!(dir.as_deref(), Some("/tmp")),
+            _ => panic!("expected cd"),
+        }
+        // As in gdb, for the home directory
+        match DebuggerCommand::from_line("cd") {
+            Some(DebuggerCommand::Cd(dir)) => assert_eq!(dir, None),
+            _ => panic!("expected cd with no directory"),
+        }
+    }
+}
@@ -1,18 +1,66 @@
+use crate::addr_index::{AddrCache, LazyRangeIndex};
+use crate::dwarf_expr;
 use crate::gimli_wrapper;
 use addr2line::Context;
-use object::Object;
+use object::{Object, ObjectSection};
+use std::collections::HashMap;
 use std::convert::TryInto;
+use std::path::{Path, PathBuf};
 use std::{fmt, fs};
 
+/// Where distributions install separate debug files (`set debug-file-directory`).
+pub const DEFAULT_DEBUG_FILE_DIRECTORY: &str = "/usr/lib/debug";
+
+/// The size of a PLT stub.
+pub const PLT_ENTRY_SIZE: usize = 16;
+
 #[derive(Debug)]
 pub enum Error {
     ErrorOpeningFile,
     DwarfFormatError(gimli_wrapper::Error),
 }
 
+/// How much a binary tells us about itself.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DebugInfo {
+    /// DWARF: functions, variables, types and line numbers
+    Dwarf,
+    /// Only the ELF symbol table: function names and addresses
+    Symbols,
+    /// Neither (a stripped binary)
+    Nothing,
+}
+
 pub struct DwarfData {
     files: Vec<File>,
+    types: HashMap<TypeId, Type>,
+    /// The ELF entry point
+    entry: usize,
+    /// Whether this is a position-independent executable (or shared library), whose addresses
+    /// are relative to wherever it gets loaded
+    position_independent: bool,
+    /// The sections that occupy memory when the program is loaded
+    sections: Vec<Section>,
+    /// The separate file the DWARF came from, for a stripped binary
+    debug_file: Option<PathBuf>,
+    /// Functions from the ELF symbol table, loaded only when there is no DWARF. They have no
+    /// line numbers, variables or types.
+    symbols: Vec<Function>,
+    /// Functions from the ELF symbol table, loaded alongside DWARF to name the code it doesn't
+    /// describe, such as functions written in assembly. Empty when `symbols` has them instead.
+    symbol_table: Vec<Function>,
+    /// The PLT stubs through which the program calls functions in shared libraries
+    plt_entries: Vec<PltEntry>,
     addr2line: Context<addr2line::gimli::EndianRcSlice<addr2line::gimli::RunTimeEndian>>,
+    /// Where each function's code is: (index in `files`, index in its functions), with
+    /// `files.len()` standing for `symbols`. Built on the first lookup by address.
+    function_index: LazyRangeIndex<(usize, usize)>,
+    /// Where each line table row's code is: (index in `files`, index in its line_rows). Built on
+    /// the first lookup by address.
+    line_row_index: LazyRangeIndex<(usize, usize)>,
+    /// Recent answers of get_line_from_addr and get_function_from_addr
+    line_cache: AddrCache<Option<Line>>,
+    function_cache: AddrCache<Option<String>>,
 }
 
 impl fmt::Debug for DwarfData {
@@ -29,8 +77,14 @@ impl From<gimli_wrapper::Error> for Error {
 
 impl DwarfData {
     pub fn from_file(path: &str) -> Result<DwarfData, Error> {
-        let file = fs::File::open(path).or(Err(Error::ErrorOpeningFile))?;
-        let mmap = unsafe { memmap::Mmap::map(&file).or(Err(Error::ErrorOpeningFile))? };
+        DwarfData::load(path, DEFAULT_DEBUG_FILE_DIRECTORY)
+    }
+
+    /// Loads the debug info of `path`. A binary without DWARF of its own gets it from its
+    /// separate debug file, if one can be found next to it or under `debug_file_directory`;
+    /// addresses, sections and symbols still come from the binary itself.
+    pub fn load(path: &str, debug_file_directory: &str) -> Result<DwarfData, Error> {
+        let mmap = map_file(Path::new(path))?;
         let object = object::File::parse(&*mmap)
             .or_else(|e| Err(gimli_wrapper::Error::ObjectError(e.to_string())))?;
         let endian = if object.is_little_endian() {
@@ -38,17 +92,81 @@ impl DwarfData {
         } else {
             gimli::RunTimeEndian::Big
         };
+        let (mut files, mut types) = gimli_wrapper::load_file(&object, endian)?;
+        let mut addr2line =
+            Context::new(&object).or_else(|e| Err(gimli_wrapper::Error::from(e)))?;
+        let mut debug_file = None;
+        if files.is_empty() {
+            if let Some(debug_path) = find_debug_file(path, &object, debug_file_directory) {
+                let debug_mmap = map_file(&debug_path)?;
+                let debug_object = object::File::parse(&*debug_mmap)
+                    .or_else(|e| Err(gimli_wrapper::Error::ObjectError(e.to_string())))?;
+                let (debug_files, debug_types) = gimli_wrapper::load_file(&debug_object, endian)?;
+                files = debug_files;
+                types = debug_types;
+                addr2line = Context::new(&debug_object)
+                    .or_else(|e| Err(gimli_wrapper::Error::from(e)))?;
+                debug_file = Some(debug_path);
+            }
+        }
+        let (symbols, symbol_table) = if files.is_empty() {
+            (load_symbol_table(&object), Vec::new())
+        } else {
+            (Vec::new(), load_symbol_table(&object))
+        };
+        let sections: Vec<Section> = object
+            .sections()
+            .filter(|section| section.address() != 0)
+            .map(|section| Section {
+                name: section.name().unwrap_or("").to_string(),
+                address: section.address() as usize,
+                size: section.size() as usize,
+            })
+            .collect();
+        let plt_entries = load_plt_entries(&object, &sections);
         Ok(DwarfData {
-            files: gimli_wrapper::load_file(&object, endian)?,
-            addr2line: Context::new(&object).or_else(|e| Err(gimli_wrapper::Error::from(e)))?,
+            files,
+            types,
+            entry: object.entry() as usize,
+            // e_type (at offset 16 of the ELF header) is ET_DYN for PIEs and shared libraries
+            position_independent: mmap.len() > 17 && mmap[16] == 3 && mmap[17] == 0,
+            sections,
+            debug_file,
+            symbols,
+            symbol_table,
+            plt_entries,
+            addr2line,
+            function_index: LazyRangeIndex::new(),
+            line_row_index: LazyRangeIndex::new(),
+            line_cache: AddrCache::new(),
+            function_cache: AddrCache::new(),
         })
     }
 
     #[allow(dead_code)]
     fn get_target_file(&self, file: &str) -> Option<&File> {
-        self.files.iter().find(|f| {
-            f.name == file || (!file.contains("/") && f.name.ends_with(&format!("/{}", file)))
-        })
+        self.files.iter().find(|f| path_matches(&f.name, file))
+    }
+
+    /// Returns the name of the compilation unit that defines `func_name`.
+    pub fn get_file_for_function(&self, func_name: &str) -> Option<&str> {
+        self.files
+            .iter()
+            .find(|file| {
+                file.functions
+                    .iter()
+                    .any(|func| func.address != 0 && func.matches(func_name))
+            })
+            .map(|file| file.name.as_str())
+    }
+
+    /// Returns the names of the compilation units whose line tables have code for `line_number`.
+    pub fn get_files_with_line(&self, line_number: usize) -> Vec<String> {
+        self.files
+            .iter()
+            .filter(|file| file.lines.iter().any(|line| line.number == line_number))
+            .map(|file| file.name.clone())
+            .collect()
     }
 
     #[allow(dead_code)]
@@ -66,6 +184,24 @@ impl DwarfData {
         )
     }
 
+    /// Returns the first address of `line_number` between `start` and `end` (e.g. within one
+    /// function). If that line has no code there, the next line after it that does is used.
+    pub fn get_addr_for_line_in_range(
+        &self,
+        line_number: usize,
+        start: usize,
+        end: usize,
+    ) -> Option<usize> {
+        self.files
+            .iter()
+            .flat_map(|file| file.lines.iter())
+            .filter(|line| {
+                line.number >= line_number && start <= line.address && line.address < end
+            })
+            .min_by_key(|line| (line.number, line.address))
+            .map(|line| line.address)
+    }
+
     #[allow(dead_code)]
     pub fn get_addr_for_function(&self, file: Option<&str>, func_name: &str) -> Option<usize> {
         match file {
@@ -73,22 +209,476 @@ impl DwarfData {
                 self.get_target_file(filename)?
                     .functions
                     .iter()
-                    .find(|func| func.name == func_name)?
+                    .find(|func| func.address != 0 && func.matches(func_name))?
                     .address,
             ),
-            None => {
-                for file in &self.files {
-                    if let Some(func) = file.functions.iter().find(|func| func.name == func_name) {
-                        return Some(func.address);
-                    }
+            None => Some(
+                self.all_functions()
+                    .find(|func| func.address != 0 && func.matches(func_name))?
+                    .address,
+            ),
+        }
+    }
+
+    /// Returns the address of every function `func_name` refers to (static functions of the same
+    /// name in different files, C++ overloads), or failing that of every inlined copy.
+    pub fn get_addrs_for_function(&self, func_name: &str) -> Vec<usize> {
+        let mut addrs: Vec<usize> = self
+            .all_functions()
+            .filter(|func| func.address != 0 && func.matches(func_name))
+            .map(|func| func.address)
+            .collect();
+        if addrs.is_empty() {
+            return self.get_inline_addrs_for_function(func_name);
+        }
+        addrs.sort();
+        addrs.dedup();
+        addrs
+    }
+
+    /// Returns the address of every inlined copy of `func_name`, for a function that was inlined
+    /// everywhere and has no out-of-line copy to break on. Empty otherwise.
+    pub fn get_inline_addrs_for_function(&self, func_name: &str) -> Vec<usize> {
+        if self.get_addr_for_function(None, func_name).is_some() {
+            return Vec::new();
+        }
+        let mut addrs: Vec<usize> = self
+            .all_functions()
+            .filter(|func| func.matches(func_name))
+            .flat_map(|func| func.inline_addresses.iter().cloned())
+            .collect();
+        addrs.sort();
+        addrs.dedup();
+        addrs
+    }
+
+    /// Returns the address ranges (start, end) of the code for a line, in the given file or
+    /// (when None) the first one. Adjacent ranges are merged. Empty if the line has no code.
+    pub fn get_line_ranges(&self, file: Option<&str>, line_number: usize) -> Vec<(usize, usize)> {
+        let target_file = match file {
+            Some(filename) => self.get_target_file(filename),
+            None => self.files.get(0),
+        };
+        let target_file = match target_file {
+            Some(target_file) => target_file,
+            None => return Vec::new(),
+        };
+        let mut ranges: Vec<(usize, usize)> = target_file
+            .line_rows
+            .iter()
+            .filter(|row| row.number == line_number && row.file == target_file.name)
+            .map(|row| (row.address, row.end))
+            .collect();
+        ranges.sort();
+        let mut merged: Vec<(usize, usize)> = Vec::new();
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if last.1 >= start => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        merged
+    }
+
+    /// Returns where a breakpoint on the function starting at `addr` should go: the second line
+    /// table row inside it, which is past the prologue that sets up the frame (as GDB does). That
+    /// is `addr` itself when the function has only one row, or `addr` isn't a function's start.
+    pub fn skip_prologue(&self, addr: usize) -> usize {
+        let func = match self
+            .files
+            .iter()
+            .flat_map(|file| file.functions.iter())
+            .find(|func| func.address == addr && func.address != 0)
+        {
+            Some(func) => func,
+            None => return addr,
+        };
+        let end = func.address + func.text_length;
+        self.files
+            .iter()
+            .flat_map(|file| file.line_rows.iter())
+            .filter(|row| row.is_stmt && addr < row.address && row.address < end)
+            .map(|row| row.address)
+            .min()
+            .unwrap_or(addr)
+    }
+
+    /// The name the debug info has for a source file the user named (matched like a breakpoint
+    /// location's file), such as `src/list.c` for `list.c`.
+    pub fn source_file_matching(&self, file: &str) -> Option<&str> {
+        self.files
+            .iter()
+            .flat_map(|unit| std::iter::once(&unit.name).chain(unit.source_files.iter()))
+            .find(|name| path_matches(name, file))
+            .map(String::as_str)
+    }
+
+    /// The compilation directory of a unit that uses a source file named in the debug info, which
+    /// a relative file name is taken relative to.
+    pub fn comp_dir(&self, file: &str) -> Option<&str> {
+        self.files
+            .iter()
+            .filter(|unit| unit.name == file || unit.source_files.iter().any(|f| f == file))
+            .find_map(|unit| unit.comp_dir.as_deref())
+    }
+
+    /// Returns the line table row whose range contains `addr`.
+    pub fn get_line_row_containing(&self, addr: usize) -> Option<&LineRow> {
+        let (file_idx, idx) = self.line_row_index.find_with(addr, || {
+            self.files.iter().enumerate().flat_map(|(file_idx, file)| {
+                file.line_rows
+                    .iter()
+                    .enumerate()
+                    .map(move |(idx, row)| (row.address, row.end, (file_idx, idx)))
+            })
+        })?;
+        self.files[file_idx].line_rows.get(idx)
+    }
+
+    /// Returns the line table rows of every compilation unit, in address order. Given a file,
+    /// only the rows for code from that file (matched like a breakpoint location's file).
+    pub fn line_table(&self, file: Option<&str>) -> Vec<&LineRow> {
+        let mut rows: Vec<&LineRow> = self
+            .files
+            .iter()
+            .flat_map(|unit| unit.line_rows.iter())
+            .filter(|row| file.map_or(true, |file| path_matches(&row.file, file)))
+            .collect();
+        rows.sort_by_key(|row| row.address);
+        rows
+    }
+
+    /// Iterates over the functions of every compilation unit, or those from the symbol table
+    /// when there is no DWARF.
+    fn all_functions(&self) -> impl Iterator<Item = &Function> {
+        self.files
+            .iter()
+            .flat_map(|file| file.functions.iter())
+            .chain(self.symbols.iter())
+    }
+
+    /// Returns the function whose code contains `addr`.
+    pub fn get_function_containing(&self, addr: usize) -> Option<&Function> {
+        let (file_idx, idx) = self.function_index.find_with(addr, || {
+            self.files
+                .iter()
+                .map(|file| &file.functions)
+                .chain(Some(&self.symbols))
+                .enumerate()
+                .flat_map(|(file_idx, functions)| {
+                    functions.iter().enumerate().map(move |(idx, func)| {
+                        (func.address, func.address + func.text_length, (file_idx, idx))
+                    })
+                })
+        })?;
+        match self.files.get(file_idx) {
+            Some(file) => file.functions.get(idx),
+            None => self.symbols.get(idx),
+        }
+    }
+
+    /// Iterates over the global variables of every compilation unit.
+    pub fn global_variables(&self) -> impl Iterator<Item = &Variable> {
+        self.files.iter().flat_map(|file| file.global_variables.iter())
+    }
+
+    pub fn get_type(&self, id: TypeId) -> Option<&Type> {
+        self.types.get(&id)
+    }
+
+    /// Follows typedefs and const/volatile qualifiers to the type that determines the layout.
+    pub fn resolve_type(&self, id: TypeId) -> Option<&Type> {
+        let ty = self.types.get(&id)?;
+        match ty.kind {
+            TypeKind::Typedef(Some(target)) | TypeKind::Qualified(_, Some(target)) => {
+                self.resolve_type(target)
+            }
+            _ => Some(ty),
+        }
+    }
+
+    /// Like resolve_type, but gives the id of the type it gets to.
+    pub fn resolve_type_id(&self, id: TypeId) -> TypeId {
+        match self.types.get(&id).map(|ty| &ty.kind) {
+            Some(TypeKind::Typedef(Some(target))) | Some(TypeKind::Qualified(_, Some(target))) => {
+                self.resolve_type_id(*target)
+            }
+            _ => id,
+        }
+    }
+
+    /// Looks a type up by the name C gives it: `struct node`, `union value`, `enum color`, or
+    /// the name of a typedef or base type. A struct's definition is preferred to a declaration.
+    pub fn find_type(&self, name: &str) -> Option<TypeId> {
+        let words: Vec<&str> = name.split_whitespace().collect();
+        let (tag, name) = match words.split_first() {
+            Some((&tag, rest)) if ["struct", "union", "enum"].contains(&tag) => {
+                (Some(tag), rest.join(" "))
+            }
+            _ => (None, words.join(" ")),
+        };
+        self.types
+            .iter()
+            .filter(|(_, ty)| ty.name == name)
+            .filter(|(_, ty)| match (tag, &ty.kind) {
+                (Some("struct"), TypeKind::Struct(_))
+                | (Some("union"), TypeKind::Union(_))
+                | (Some("enum"), TypeKind::Enum(_)) => true,
+                (None, TypeKind::Pointer(_)) | (None, TypeKind::Array(..)) => false,
+                (None, TypeKind::Qualified(..)) => false,
+                (None, _) => true,
+                _ => false,
+            })
+            .max_by_key(|(id, ty)| (ty.size > 0, std::cmp::Reverse(**id)))
+            .map(|(id, _)| *id)
+    }
+
+    /// Returns a pointer type to `target` from the debug info, if the program uses one.
+    pub fn pointer_type_to(&self, target: TypeId) -> Option<TypeId> {
+        self.types
+            .iter()
+            .find(|(_, ty)| match ty.kind {
+                TypeKind::Pointer(Some(pointee)) => pointee == target,
+                _ => false,
+            })
+            .map(|(id, _)| *id)
+    }
+
+    /// The size of a value of this type, in bytes.
+    pub fn type_size(&self, id: TypeId) -> usize {
+        let ty = match self.types.get(&id) {
+            Some(ty) => ty,
+            None => return 0,
+        };
+        match ty.kind {
+            TypeKind::Typedef(Some(target)) | TypeKind::Qualified(_, Some(target)) => {
+                self.type_size(target)
+            }
+            TypeKind::Array(Some(element), Some(count)) if ty.size == 0 => {
+                self.type_size(element) * count
+            }
+            TypeKind::Pointer(_) if ty.size == 0 => 8,
+            _ => ty.size,
+        }
+    }
+
+    /// Spells a type the way C would, e.g. `struct node *` or `const char [16]`.
+    pub fn type_name(&self, id: TypeId) -> String {
+        let ty = match self.types.get(&id) {
+            Some(ty) => ty,
+            None => return "<unknown type>".to_string(),
+        };
+        let name_or = |target: Option<TypeId>| match target {
+            Some(target) => self.type_name(target),
+            None => "void".to_string(),
+        };
+        match &ty.kind {
+            TypeKind::Base(_) | TypeKind::Typedef(_) => ty.name.clone(),
+            TypeKind::Pointer(target) => {
+                let target = name_or(*target);
+                if target.ends_with('*') {
+                    format!("{}*", target)
+                } else {
+                    format!("{} *", target)
                 }
-                None
             }
+            TypeKind::Struct(_) if ty.name.is_empty() => "struct {...}".to_string(),
+            TypeKind::Struct(_) => format!("struct {}", ty.name),
+            TypeKind::Union(_) if ty.name.is_empty() => "union {...}".to_string(),
+            TypeKind::Union(_) => format!("union {}", ty.name),
+            TypeKind::Enum(_) if ty.name.is_empty() => "enum {...}".to_string(),
+            TypeKind::Enum(_) => format!("enum {}", ty.name),
+            TypeKind::Array(element, count) => format!(
+                "{} [{}]",
+                name_or(*element),
+                count.map(|count| count.to_string()).unwrap_or_default()
+            ),
+            TypeKind::Qualified(qualifier, target) => format!("{} {}", qualifier, name_or(*target)),
         }
     }
 
+    /// Returns the compilation units, each with the source files its line program refers to.
+    pub fn compilation_units(&self) -> &[File] {
+        &self.files
+    }
+
+    /// Iterates over every function with code of its own, along with the file declaring it.
+    pub fn functions(&self) -> impl Iterator<Item = (&File, &Function)> {
+        self.files.iter().flat_map(|file| {
+            file.functions
+                .iter()
+                .filter(|func| func.address != 0)
+                .map(move |func| (file, func))
+        })
+    }
+
+    /// Iterates over every global and file-static variable with an address of its own, along with
+    /// the file declaring it.
+    pub fn variables(&self) -> impl Iterator<Item = (&File, &Variable)> {
+        self.files.iter().flat_map(|file| {
+            file.global_variables
+                .iter()
+                .filter(|var| match var.location {
+                    Location::Address(_) => true,
+                    _ => false,
+                })
+                .map(move |var| (file, var))
+        })
+    }
+
+    /// Returns true if `addr` falls inside the code of some function in the debug info.
+    pub fn contains_addr(&self, addr: usize) -> bool {
+        self.all_functions()
+            .any(|func| func.address <= addr && addr < func.address + func.text_length)
+    }
+
+    /// Address of the first instruction the program executes (`_start`, not `main`).
+    pub fn entry_point(&self) -> usize {
+        self.entry
+    }
+
+    /// Returns the name of the section containing `addr`, such as `.text` or `.bss`.
+    pub fn section_containing(&self, addr: usize) -> Option<&str> {
+        self.sections
+            .iter()
+            .find(|section| section.address <= addr && addr < section.address + section.size)
+            .map(|section| section.name.as_str())
+    }
+
+    /// The link-time address of the section called `name` (such as `.dynamic`), if there is one.
+    pub fn section_address(&self, name: &str) -> Option<usize> {
+        self.sections
+            .iter()
+            .find(|section| section.name == name)
+            .map(|section| section.address)
+    }
+
+    /// Returns the symbol (function, or global variable) whose storage contains `addr`, along
+    /// with the offset of `addr` into it.
+    pub fn symbol_containing(&self, addr: usize) -> Option<(String, usize)> {
+        if let Some(func) = self.get_function_containing(addr) {
+            return Some((func.display_name(), addr - func.address));
+        }
+        self.global_variables()
+            .filter_map(|var| match var.location {
+                Location::Address(start) => Some((var, start)),
+                _ => None,
+            })
+            .find(|(var, start)| {
+                *start <= addr && addr < start + self.type_size(var.type_id).max(1)
+            })
+            .map(|(var, start)| (var.name.clone(), addr - start))
+    }
+
+    /// Returns the link-time address of a global variable.
+    pub fn get_global_variable_address(&self, name: &str) -> Option<usize> {
+        self.global_variables()
+            .find_map(|var| match var.location {
+                Location::Address(addr) if var.name == name => Some(addr),
+                _ => None,
+            })
+    }
+
+    /// The PLT stubs of the functions the program calls in shared libraries, in PLT order.
+    pub fn plt_entries(&self) -> &[PltEntry] {
+        &self.plt_entries
+    }
+
+    /// Returns true if the addresses in this file are relative to its load address.
+    pub fn is_position_independent(&self) -> bool {
+        self.position_independent
+    }
+
+    /// Number of functions across all compilation units (or in the symbol table).
+    pub fn function_count(&self) -> usize {
+        self.files.iter().map(|file| file.functions.len()).sum::<usize>() + self.symbols.len()
+    }
+
+    /// The separate debug file the DWARF was loaded from, if it didn't come from the binary.
+    pub fn debug_file(&self) -> Option<&Path> {
+        self.debug_file.as_deref()
+    }
+
+    /// Whether functions and lines come from DWARF, the symbol table alone, or nowhere.
+    pub fn debug_info(&self) -> DebugInfo {
+        if !self.files.is_empty() {
+            DebugInfo::Dwarf
+        } else if !self.symbols.is_empty() {
+            DebugInfo::Symbols
+        } else {
+            DebugInfo::Nothing
+        }
+    }
+
+    /// Number of compilation units (source files).
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Returns the display names of all functions `name` refers to, sorted and deduplicated. More
+    /// than one means `name` is ambiguous, e.g. an overloaded C++ function.
+    pub fn functions_matching(&self, name: &str) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .all_functions()
+            .filter(|func| func.matches(name))
+            .map(|func| func.display_name())
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Returns the display names of all functions for which `predicate` holds, sorted and
+    /// deduplicated. Functions without code of their own (declarations) are left out.
+    pub fn function_names_where<P: Fn(&str) -> bool>(&self, predicate: P) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .all_functions()
+            .filter(|func| func.address != 0 || !func.inline_addresses.is_empty())
+            .map(|func| func.display_name())
+            .filter(|name| predicate(name))
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Returns the sorted, deduplicated names of all functions starting with `prefix`. Both the
+    /// plain and the demangled (path-qualified) names are offered.
+    pub fn functions_with_prefix(&self, prefix: &str) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .all_functions()
+            .flat_map(|func| vec![func.name.clone(), func.display_name()])
+            .filter(|name| name.starts_with(prefix))
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Returns the sorted, deduplicated names of all source files starting with `prefix`, as
+    /// breakpoint locations accept them: the compilation unit's path, and its last component.
+    pub fn files_with_prefix(&self, prefix: &str) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .files
+            .iter()
+            .flat_map(|file| {
+                let base = file.name.rsplit('/').next().unwrap_or_default();
+                vec![file.name.clone(), base.to_string()]
+            })
+            .filter(|name| name.starts_with(prefix))
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
     #[allow(dead_code)]
     pub fn get_line_from_addr(&self, curr_addr: usize) -> Option<Line> {
+        self.line_cache
+            .get_or_insert_with(curr_addr, || self.find_line(curr_addr))
+    }
+
+    fn find_line(&self, curr_addr: usize) -> Option<Line> {
         let location = self
             .addr2line
             .find_location(curr_addr.try_into().unwrap())
@@ -102,82 +692,336 @@ impl DwarfData {
 
     #[allow(dead_code)]
     pub fn get_function_from_addr(&self, curr_addr: usize) -> Option<String> {
-        let frame = self
-            .addr2line
-            .find_frames(curr_addr.try_into().unwrap())
-            .ok()?
-            .next()
-            .ok()??;
-        Some(frame.function?.raw_name().ok()?.to_string())
+        self.function_cache
+            .get_or_insert_with(curr_addr, || self.find_function_name(curr_addr))
     }
 
-    #[allow(dead_code)]
-    pub fn print(&self) {
-        for file in &self.files {
-            println!("------");
-            println!("{}", file.name);
-            println!("------");
-
-            println!("Global variables:");
-            for var in &file.global_variables {
-                println!(
-                    "  * {} ({}, located at {}, declared at line {})",
-                    var.name, var.entity_type.name, var.location, var.line_number
-                );
-            }
+    fn find_function_name(&self, curr_addr: usize) -> Option<String> {
+        if !self.symbols.is_empty() {
+            return Some(self.get_function_containing(curr_addr)?.display_name());
+        }
+        let from_dwarf = || {
+            let frame = self
+                .addr2line
+                .find_frames(curr_addr.try_into().unwrap())
+                .ok()?
+                .next()
+                .ok()??;
+            Some(demangle(&frame.function?.raw_name().ok()?))
+        };
+        from_dwarf().or_else(|| self.symbol_table_function(curr_addr))
+    }
 
-            println!("Functions:");
-            for func in &file.functions {
-                println!(
-                    "  * {} (declared on line {}, located at {:#x}, {} bytes long)",
-                    func.name, func.line_number, func.address, func.text_length
-                );
-                for var in &func.variables {
-                    println!(
-                        "    * Variable: {} ({}, located at {}, declared at line {})",
-                        var.name, var.entity_type.name, var.location, var.line_number
-                    );
-                }
+    /// The name of the symbol table's function containing `addr`, for code the DWARF doesn't
+    /// describe.
+    fn symbol_table_function(&self, addr: usize) -> Option<String> {
+        // Sorted by address: the function is the last one starting at or before addr, if any
+        let idx = match self.symbol_table.binary_search_by_key(&addr, |func| func.address) {
+            Ok(idx) => idx,
+            Err(0) => return None,
+            Err(idx) => idx - 1,
+        };
+        let func = &self.symbol_table[idx];
+        if addr < func.address + func.text_length {
+            Some(func.display_name())
+        } else {
+            None
+        }
+    }
+
+    /// Returns the chain of functions executing at `curr_addr`, innermost first: the functions
+    /// inlined there, followed by the function the code physically belongs to. Each comes with
+    /// its own source line, which for the outer ones is where the inner one was inlined.
+    pub fn get_frames_from_addr(&self, curr_addr: usize) -> Vec<(Option<String>, Option<Line>)> {
+        let mut frames = Vec::new();
+        if !self.symbols.is_empty() {
+            if let Some(func) = self.get_function_containing(curr_addr) {
+                frames.push((Some(func.display_name()), None));
+            }
+            return frames;
+        }
+        if let Ok(mut iter) = self.addr2line.find_frames(curr_addr.try_into().unwrap()) {
+            while let Ok(Some(frame)) = iter.next() {
+                let function = frame
+                    .function
+                    .and_then(|function| Some(demangle(&function.raw_name().ok()?)));
+                let line = frame.location.and_then(|location| {
+                    Some(Line {
+                        file: location.file?.to_string(),
+                        number: location.line?.try_into().unwrap(),
+                        address: curr_addr,
+                    })
+                });
+                frames.push((function, line));
+            }
+        }
+        // Code the DWARF doesn't describe may still have a name in the symbol table
+        if frames.is_empty() {
+            if let Some(name) = self.symbol_table_function(curr_addr) {
+                frames.push((Some(name), None));
             }
+        }
+        frames
+    }
+}
 
-            println!("Line numbers:");
-            for line in &file.lines {
-                println!("  * {} (at {:#x})", line.number, line.address);
+fn map_file(path: &Path) -> Result<memmap::Mmap, Error> {
+    let file = fs::File::open(path).or(Err(Error::ErrorOpeningFile))?;
+    unsafe { memmap::Mmap::map(&file).or(Err(Error::ErrorOpeningFile)) }
+}
+
+/// Finds the separate debug file of a stripped binary: by build-id, under
+/// `<debug dir>/.build-id/`, or by the name in `.gnu_debuglink`, looked for next to the binary,
+/// in its `.debug` subdirectory and under `<debug dir>` followed by the binary's directory. A
+/// debuglink candidate only counts if its CRC matches the one recorded in the binary.
+fn find_debug_file(
+    path: &str,
+    object: &object::File,
+    debug_file_directory: &str,
+) -> Option<PathBuf> {
+    let debug_dir = Path::new(debug_file_directory);
+    if let Some(note) = object.section_data_by_name(".note.gnu.build-id") {
+        if let Some(build_id) = build_id(&note).filter(|build_id| build_id.len() > 1) {
+            let hex: String = build_id.iter().map(|byte| format!("{:02x}", byte)).collect();
+            let candidate = debug_dir
+                .join(".build-id")
+                .join(&hex[..2])
+                .join(format!("{}.debug", &hex[2..]));
+            if candidate.is_file() {
+                return Some(candidate);
             }
         }
     }
+
+    // The debuglink section is the file name, NUL-terminated and padded to 4 bytes, then a CRC
+    let debuglink = object.section_data_by_name(".gnu_debuglink")?;
+    let name_end = debuglink.iter().position(|&byte| byte == 0)?;
+    let name = std::str::from_utf8(&debuglink[..name_end]).ok()?;
+    let crc_offset = (name_end + 4) & !3;
+    let crc = u32::from_le_bytes(debuglink.get(crc_offset..crc_offset + 4)?.try_into().ok()?);
+    let binary = fs::canonicalize(path).ok()?;
+    let dir = binary.parent()?;
+    let candidates = vec![
+        dir.join(name),
+        dir.join(".debug").join(name),
+        debug_dir.join(dir.strip_prefix("/").unwrap_or(dir)).join(name),
+    ];
+    candidates.into_iter().find(|candidate| {
+        *candidate != binary && fs::read(candidate).map_or(false, |data| crc32(&data) == crc)
+    })
+}
+
+/// Returns the build-id from a `.note.gnu.build-id` section.
+fn build_id(note: &[u8]) -> Option<&[u8]> {
+    let word = |offset: usize| -> Option<usize> {
+        Some(u32::from_le_bytes(note.get(offset..offset + 4)?.try_into().ok()?) as usize)
+    };
+    let (name_size, desc_size, kind) = (word(0)?, word(4)?, word(8)?);
+    // NT_GNU_BUILD_ID
+    if kind != 3 {
+        return None;
+    }
+    let desc_start = 12 + ((name_size + 3) & !3);
+    note.get(desc_start..desc_start + desc_size)
+}
+
+/// The CRC-32 (the zlib one) that `.gnu_debuglink` records for the debug file.
+fn crc32(data: &[u8]) -> u32 {
+    let mut table = [0u32; 256];
+    for (n, entry) in table.iter_mut().enumerate() {
+        let mut crc = n as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+        *entry = crc;
+    }
+    !data.iter().fold(!0u32, |crc, &byte| {
+        table[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Finds the PLT stub of each function named in `.rela.plt`. The stubs are in the same order as
+/// the relocations, 16 bytes apart: in `.plt.sec` when the binary has one (it does when built
+/// with CET), otherwise in `.plt` after its first slot, which holds the lazy binding code.
+fn load_plt_entries(object: &object::File, sections: &[Section]) -> Vec<PltEntry> {
+    // Elf64_Rela and Elf64_Sym are both 24 bytes
+    const RELA_SIZE: usize = 24;
+    const SYM_SIZE: usize = 24;
+    let start = |name: &str| {
+        sections
+            .iter()
+            .find(|section| section.name == name)
+            .map(|section| section.address)
+    };
+    let first_stub = match (start(".plt.sec"), start(".plt")) {
+        (Some(plt_sec), _) => plt_sec,
+        (None, Some(plt)) => plt + PLT_ENTRY_SIZE,
+        (None, None) => return Vec::new(),
+    };
+    let (relocations, dynsym, dynstr) = match (
+        object.section_data_by_name(".rela.plt"),
+        object.section_data_by_name(".dynsym"),
+        object.section_data_by_name(".dynstr"),
+    ) {
+        (Some(relocations), Some(dynsym), Some(dynstr)) => (relocations, dynsym, dynstr),
+        _ => return Vec::new(),
+    };
+    let read_u32 = |data: &[u8], offset: usize| -> Option<usize> {
+        Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?) as usize)
+    };
+    let symbol_name = |index: usize| -> Option<String> {
+        let name_offset = read_u32(&dynsym, index * SYM_SIZE)?;
+        let name = dynstr.get(name_offset..)?;
+        let end = name.iter().position(|&byte| byte == 0)?;
+        Some(String::from_utf8_lossy(&name[..end]).into_owned())
+    };
+    relocations
+        .chunks_exact(RELA_SIZE)
+        .enumerate()
+        .filter_map(|(slot, relocation)| {
+            // The symbol index is the top half of r_info, which follows the 8-byte r_offset
+            let symbol = read_u32(relocation, 12)?;
+            Some(PltEntry {
+                name: symbol_name(symbol).filter(|name| !name.is_empty())?,
+                address: first_stub + slot * PLT_ENTRY_SIZE,
+            })
+        })
+        .collect()
+}
+
+/// Reads the functions out of `.symtab`, or `.dynsym` for a stripped binary, as stand-ins for the
+/// DWARF ones.
+fn load_symbol_table(object: &object::File) -> Vec<Function> {
+    let mut symbols: Vec<Function> = object
+        .symbols()
+        .chain(object.dynamic_symbols())
+        .map(|(_, symbol)| symbol)
+        .filter(|symbol| {
+            symbol.kind() == object::SymbolKind::Text
+                && !symbol.is_undefined()
+                && symbol.address() != 0
+        })
+        .filter_map(|symbol| {
+            let name = symbol.name()?;
+            if name.is_empty() {
+                return None;
+            }
+            Some(Function {
+                name: name.to_string(),
+                linkage_name: Some(name.to_string()),
+                address: symbol.address() as usize,
+                text_length: symbol.size() as usize,
+                line_number: 0,
+                return_type: None,
+                variables: Vec::new(),
+                inline_addresses: Vec::new(),
+                frame_base: None,
+            })
+        })
+        .collect();
+    // The same function is usually in both tables
+    symbols.sort_by_key(|func| func.address);
+    symbols.dedup_by_key(|func| func.address);
+    symbols
+}
+
+/// Whether `path` is `file`, or (when `file` is a bare name) a file of that name in some
+/// directory.
+pub(crate) fn path_matches(path: &str, file: &str) -> bool {
+    path == file || (!file.contains('/') && path.ends_with(&format!("/{}", file)))
 }
 
-#[derive(Debug, Clone, Default)]
+/// Identifies a type: the offset of its DIE in .debug_info.
+pub type TypeId = usize;
+
+#[derive(Debug, Clone)]
 pub struct Type {
+    /// The type's own name; empty for pointers, arrays and anonymous structs
     pub name: String,
     pub size: usize,
+    pub kind: TypeKind,
 }
 
 impl Type {
-    pub fn new(name: String, size: usize) -> Self {
+    pub fn new(name: String, size: usize, kind: TypeKind) -> Self {
         Type {
             name: name,
             size: size,
+            kind: kind,
         }
     }
 }
 
+#[derive(Debug, Clone)]
+pub enum TypeKind {
+    /// An integer, floating point, boolean or character type, with its DW_ATE_* encoding
+    Base(u64),
+    /// A pointer to the target type, or to void when there is none
+    Pointer(Option<TypeId>),
+    Struct(Vec<Member>),
+    Union(Vec<Member>),
+    /// An array of the element type, with its element count if it is known
+    Array(Option<TypeId>, Option<usize>),
+    Typedef(Option<TypeId>),
+    /// A `const` or `volatile` version of the target type
+    Qualified(&'static str, Option<TypeId>),
+    /// An enum, with its enumerators' names and values in declaration order
+    Enum(Vec<(String, i64)>),
+}
+
+/// A field of a struct or union.
+#[derive(Debug, Default, Clone)]
+pub struct Member {
+    pub name: String,
+    pub type_id: TypeId,
+    /// Offset from the start of the struct, in bytes
+    pub offset: usize,
+    /// For a bit-field, its width in bits
+    pub bit_size: Option<usize>,
+    /// For a bit-field, how many bits past `offset` its lowest bit is
+    pub bit_offset: usize,
+}
+
 #[derive(Clone)]
 pub enum Location {
     Address(usize),
     FramePointerOffset(isize),
+    /// Any other location expression, still encoded, for dwarf_expr to evaluate at a stop
+    Expression(Vec<u8>),
+    /// A location list: the expression for each range [start, end) of link-time addresses. At
+    /// an address outside all of them, the variable is optimized out.
+    List(Vec<(usize, usize, Vec<u8>)>),
 }
 
 impl fmt::Display for Location {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match *self {
+        match self {
             Location::Address(addr) => write!(f, "Address({:#x})", addr),
             Location::FramePointerOffset(offset) => write!(f, "FramePointerOffset({})", offset),
+            Location::Expression(expression) => write!(f, "Expression({})", hex(expression)),
+            Location::List(ranges) => {
+                write!(f, "List(")?;
+                for (idx, (start, end, expression)) in ranges.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:#x}-{:#x}: {}", start, end, hex(expression))?;
+                }
+                write!(f, ")")
+            }
         }
     }
 }
 
+/// An encoded expression as hex bytes, for showing it in a DWARF dump.
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
 impl fmt::Debug for Location {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt::Display::fmt(self, f)
@@ -188,18 +1032,150 @@ impl fmt::Debug for Location {
 #[derive(Debug, Clone)]
 pub struct Variable {
     pub name: String,
-    pub entity_type: Type,
+    pub type_id: TypeId,
     pub location: Location,
     pub line_number: usize, // Line number in source file
+    /// The [start, end) addresses of the innermost lexical block declaring the variable, or None
+    /// if it is declared at the top of its function (or is global)
+    pub scope: Option<(usize, usize)>,
+}
+
+impl Variable {
+    /// Whether the variable is visible at `addr`, a link-time address in its function.
+    pub fn in_scope_at(&self, addr: usize) -> bool {
+        self.scope.map_or(true, |(start, end)| start <= addr && addr < end)
+    }
+
+    /// Says where the variable is at `addr`, the way `info scope` shows it.
+    pub fn describe_location(&self, addr: usize) -> String {
+        match &self.location {
+            Location::Address(addr) => format!("static storage at address {:#x}", addr),
+            Location::FramePointerOffset(offset) => {
+                format!("a variable at frame base offset {}", offset)
+            }
+            Location::Expression(expression) => dwarf_expr::describe(expression),
+            Location::List(ranges) => {
+                match ranges.iter().find(|(start, end, _)| *start <= addr && addr < *end) {
+                    Some((_, _, expression)) => dwarf_expr::describe(expression),
+                    None => "optimized out here".to_string(),
+                }
+            }
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct Function {
     pub name: String,
+    /// The symbol name (DW_AT_linkage_name), which for Rust functions is mangled
+    pub linkage_name: Option<String>,
     pub address: usize,
     pub text_length: usize,
     pub line_number: usize, // Line number in source file
+    /// None for a function that returns void
+    pub return_type: Option<TypeId>,
+    /// The DW_AT_frame_base expression, that DW_OP_fbreg offsets are from. None if the
+    /// function has none, in which case the CFA is assumed.
+    pub frame_base: Option<Vec<u8>>,
     pub variables: Vec<Variable>,
+    /// Where the function was inlined into other functions
+    pub inline_addresses: Vec<usize>,
+}
+
+impl Function {
+    /// The variables and parameters visible at `addr`, a link-time address in the function, in
+    /// the order they are declared.
+    pub fn variables_at(&self, addr: usize) -> impl Iterator<Item = &Variable> {
+        self.variables.iter().filter(move |var| var.in_scope_at(addr))
+    }
+
+    /// The variable `name` means at `addr`: of those visible there, the one declared in the
+    /// innermost block, so that a block's variable hides one of the same name outside it.
+    pub fn variable_at(&self, name: &str, addr: usize) -> Option<&Variable> {
+        self.variables_at(addr)
+            .filter(|var| var.name == name)
+            .min_by_key(|var| var.scope.map_or(usize::MAX, |(start, end)| end - start))
+    }
+
+    /// The name to show the user: the demangled linkage name (`mycrate::main`, `Foo::bar(int)`)
+    /// when there is one, otherwise the plain name.
+    pub fn display_name(&self) -> String {
+        match &self.linkage_name {
+            Some(linkage_name) => {
+                let demangled = demangle(linkage_name);
+                if &demangled != linkage_name {
+                    demangled
+                } else {
+                    self.name.clone()
+                }
+            }
+            None => self.name.clone(),
+        }
+    }
+
+    /// Returns true if `name` refers to this function: its plain name, its mangled linkage name,
+    /// the demangled Rust path with or without the trailing hash, or the demangled C++ name with
+    /// or without its parameter list.
+    pub fn matches(&self, name: &str) -> bool {
+        if self.name == name {
+            return true;
+        }
+        let linkage_name = match &self.linkage_name {
+            Some(linkage_name) => linkage_name,
+            None => return false,
+        };
+        if linkage_name == name {
+            return true;
+        }
+        if let Ok(demangled) = rustc_demangle::try_demangle(linkage_name) {
+            return demangled.to_string() == name || format!("{:#}", demangled) == name;
+        }
+        match demangle_cpp(linkage_name) {
+            Some(demangled) => demangled == name || without_parameters(&demangled) == name,
+            None => false,
+        }
+    }
+}
+
+/// Returns the readable form of a mangled symbol name: the Rust path without its hash, or the
+/// C++ name with its signature. Other names are returned unchanged.
+pub fn demangle(name: &str) -> String {
+    if let Ok(demangled) = rustc_demangle::try_demangle(name) {
+        return format!("{:#}", demangled);
+    }
+    demangle_cpp(name).unwrap_or_else(|| name.to_string())
+}
+
+/// Demangles an Itanium C++ ABI name (`_ZN3Foo3barEi` becomes `Foo::bar(int)`).
+fn demangle_cpp(name: &str) -> Option<String> {
+    if !name.starts_with("_Z") {
+        return None;
+    }
+    let symbol = cpp_demangle::Symbol::new(name.as_bytes()).ok()?;
+    Some(symbol.to_string())
+}
+
+/// Strips the parameter list (and any trailing qualifiers such as `const`) from a demangled C++
+/// name, so `Foo::bar(int) const` becomes `Foo::bar`.
+fn without_parameters(name: &str) -> &str {
+    let close = match name.rfind(')') {
+        Some(close) => close,
+        None => return name,
+    };
+    let mut depth = 0;
+    for (idx, c) in name[..=close].char_indices().rev() {
+        match c {
+            ')' => depth += 1,
+            '(' => {
+                depth -= 1;
+                if depth == 0 {
+                    return &name[..idx];
+                }
+            }
+            _ => {}
+        }
+    }
+    name
 }
 
 #[derive(Debug, Default, Clone)]
@@ -208,6 +1184,12 @@ pub struct File {
     pub global_variables: Vec<Variable>,
     pub functions: Vec<Function>,
     pub lines: Vec<Line>,
+    /// Every row of the unit's line table (headers included), with the address range it covers
+    pub line_rows: Vec<LineRow>,
+    /// Paths in the unit's line program file table, as recorded by the compiler
+    pub source_files: Vec<String>,
+    /// The directory the unit was compiled in, which relative paths are relative to
+    pub comp_dir: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -217,6 +1199,34 @@ pub struct Line {
     pub address: usize,
 }
 
+/// The stub in the PLT that calls a function in a shared library.
+#[derive(Debug, Clone)]
+pub struct PltEntry {
+    pub name: String,
+    /// Link-time address of the stub
+    pub address: usize,
+}
+
+/// A section of the executable that is loaded into memory.
+#[derive(Debug, Clone)]
+struct Section {
+    name: String,
+    address: usize,
+    size: usize,
+}
+
+/// A row of a line table: the code from `address` up to `end` belongs to this line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineRow {
+    pub file: String,
+    pub number: usize,
+    pub address: usize,
+    pub end: usize,
+    /// Whether the compiler marked this row as the start of a statement, a place a breakpoint
+    /// on the line should go
+    pub is_stmt: bool,
+}
+
 impl fmt::Display for Line {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}:{}", self.file, self.number)
@@ -224,3 +1234,97 @@ impl fmt::Display for Line {
 }
 
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(name: &str) -> DwarfData {
+        let path = format!("{}/samples/{}", env!("CARGO_MANIFEST_DIR"), name);
+        DwarfData::from_file(&path).expect("failed to load the sample's debug info")
+    }
+
+    #[test]
+    fn lookups_by_name_leave_the_address_indexes_unbuilt() {
+        let data = sample("function_calls");
+        // What break and info functions look up
+        assert!(data.get_addr_for_function(None, "func2").is_some());
+        assert!(!data.get_addrs_for_function("func3").is_empty());
+        assert!(data.get_addr_for_line(None, 11).is_some());
+        assert!(!data.get_line_ranges(None, 11).is_empty());
+        assert!(!data.function_names_where(|name| name.starts_with("func")).is_empty());
+        assert!(!data.function_index.is_built());
+        assert!(!data.line_row_index.is_built());
+        let addr = data.get_addr_for_function(None, "main").unwrap();
+        assert!(data.get_function_containing(addr).is_some());
+        assert!(data.get_line_row_containing(addr).is_some());
+        assert!(data.function_index.is_built());
+        assert!(data.line_row_index.is_built());
+    }
+
+    #[test]
+    fn indexed_lookups_match_a_scan() {
+        for name in ["function_calls", "segfault", "count", "exit"].iter() {
+            let data = sample(name);
+            let rows: Vec<&LineRow> = data
+                .files
+                .iter()
+                .flat_map(|file| file.line_rows.iter())
+                .collect();
+            let start = data.all_functions().map(|func| func.address).min().unwrap();
+            let end = rows.iter().map(|row| row.end).max().unwrap();
+            // Every address in the program's code, and a little either side of it
+            for addr in start.saturating_sub(16)..end + 16 {
+                let func = data
+                    .all_functions()
+                    .find(|func| func.address <= addr && addr < func.address + func.text_length);
+                assert_eq!(
+                    data.get_function_containing(addr).map(|func| func as *const Function),
+                    func.map(|func| func as *const Function),
+                    "function at {:#x} in {}",
+                    addr,
+                    name
+                );
+                let row = rows
+                    .iter()
+                    .find(|row| row.address <= addr && addr < row.end)
+                    .cloned();
+                assert_eq!(
+                    data.get_line_row_containing(addr).map(|row| row as *const LineRow),
+                    row.map(|row| row as *const LineRow),
+                    "line row at {:#x} in {}",
+                    addr,
+                    name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn demangled_names() {
+        let cases = [
+            // Rust, legacy mangling: the hash is dropped
+            ("_ZN4core3fmt5write17h0123456789abcdefE", "core::fmt::write"),
+            (
+                "_ZN71_$LT$Test$u20$$u2b$$u20$$u27$static$u20$as$u20$foo..Bar$LT$Test$GT$$GT$\
+                 3bar17h930b740aa94f1d3aE",
+                "<Test + 'static as foo::Bar<Test>>::bar",
+            ),
+            // Rust, v0 mangling: so are the crate disambiguators
+            ("_RNvNtCs1234_7mycrate3foo3bar", "mycrate::foo::bar"),
+            ("_RINvNtCs1234_7mycrate3foo3barmE", "mycrate::foo::bar::<u32>"),
+            // C++: with the signature
+            ("_ZN3Foo3barEi", "Foo::bar(int)"),
+            ("_ZNK3Foo3bazEv", "Foo::baz() const"),
+            ("_Z1fPKc", "f(char const*)"),
+            ("_ZN9containerC2Ev", "container::container()"),
+            ("_ZSt4cout", "std::cout"),
+            // Anything else is left alone
+            ("main", "main"),
+            ("_start", "_start"),
+            ("printf", "printf"),
+        ];
+        for &(mangled, expected) in cases.iter() {
+            assert_eq!(demangle(mangled), expected, "demangling {}", mangled);
+        }
+    }
+}
@@ -0,0 +1,564 @@
+//! DWARF location expressions: the small stack programs in the debug info that say where a
+//! variable is. Unoptimized code only needs `DW_OP_fbreg` and `DW_OP_addr`, which dwarf_data
+//! keeps as plain offsets and addresses; optimized code keeps variables in registers or computes
+//! them, and needs the rest of the common opcodes evaluated against the registers of a frame.
+
+use std::fmt;
+
+/// Where an expression says a value is.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Place {
+    /// In memory at this address
+    Memory(usize),
+    /// In this register (DWARF numbering; see `register_name`)
+    Register(u16),
+    /// Nowhere: the expression computes the value itself (DW_OP_stack_value)
+    Value(u64),
+    /// Nowhere: the value's bytes are in the expression (DW_OP_implicit_value)
+    Bytes(Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprError {
+    /// An opcode that isn't implemented, such as DW_OP_entry_value
+    Unsupported(u8),
+    /// The expression ends in the middle of an operation, or pops from an empty stack
+    Malformed,
+    /// The frame has no value for this register
+    UnknownRegister(u16),
+    /// The expression uses the frame base or the CFA, and the frame has none
+    NoFrameBase,
+    /// Reading the inferior's memory at this address failed
+    Memory(usize),
+}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExprError::Unsupported(op) => {
+                write!(f, "Unsupported DWARF expression operation {:#x}", op)
+            }
+            ExprError::Malformed => write!(f, "Malformed DWARF expression"),
+            ExprError::UnknownRegister(number) => {
+                write!(f, "The value of DWARF register {} is not known", number)
+            }
+            ExprError::NoFrameBase => write!(f, "The frame base is not known"),
+            ExprError::Memory(addr) => write!(f, "Cannot access memory at address {:#x}", addr),
+        }
+    }
+}
+
+/// What an expression is evaluated against: a frame of the stopped inferior.
+pub trait Frame {
+    /// The value of a register (DWARF numbering) in this frame
+    fn register(&self, number: u16) -> Option<u64>;
+    /// The value of the function's DW_AT_frame_base, which DW_OP_fbreg offsets are from
+    fn frame_base(&self) -> Option<u64>;
+    /// The canonical frame address, for DW_OP_call_frame_cfa
+    fn cfa(&self) -> Option<u64>;
+    fn read_memory(&self, addr: usize, len: usize) -> Option<Vec<u8>>;
+    /// What DW_OP_addr's link-time addresses are moved by where the object was loaded
+    fn load_bias(&self) -> u64;
+}
+
+/// The x86-64 register a DWARF register number stands for, by the name `inferior::register_value`
+/// knows it by. Numbers past 16 are the floating point and vector registers.
+pub fn register_name(number: u16) -> Option<&'static str> {
+    const NAMES: [&str; 17] = [
+        "rax", "rdx", "rcx", "rbx", "rsi", "rdi", "rbp", "rsp", "r8", "r9", "r10", "r11", "r12",
+        "r13", "r14", "r15", "rip",
+    ];
+    NAMES.get(number as usize).copied()
+}
+
+const DW_OP_ADDR: u8 = 0x03;
+const DW_OP_DEREF: u8 = 0x06;
+const DW_OP_CONST1U: u8 = 0x08;
+const DW_OP_CONST1S: u8 = 0x09;
+const DW_OP_CONST2U: u8 = 0x0a;
+const DW_OP_CONST2S: u8 = 0x0b;
+const DW_OP_CONST4U: u8 = 0x0c;
+const DW_OP_CONST4S: u8 = 0x0d;
+const DW_OP_CONST8U: u8 = 0x0e;
+const DW_OP_CONST8S: u8 = 0x0f;
+const DW_OP_CONSTU: u8 = 0x10;
+const DW_OP_CONSTS: u8 = 0x11;
+const DW_OP_DUP: u8 = 0x12;
+const DW_OP_DROP: u8 = 0x13;
+const DW_OP_OVER: u8 = 0x14;
+const DW_OP_PICK: u8 = 0x15;
+const DW_OP_SWAP: u8 = 0x16;
+const DW_OP_ROT: u8 = 0x17;
+const DW_OP_ABS: u8 = 0x19;
+const DW_OP_AND: u8 = 0x1a;
+const DW_OP_DIV: u8 = 0x1b;
+const DW_OP_MINUS: u8 = 0x1c;
+const DW_OP_MOD: u8 = 0x1d;
+const DW_OP_MUL: u8 = 0x1e;
+const DW_OP_NEG: u8 = 0x1f;
+const DW_OP_NOT: u8 = 0x20;
+const DW_OP_OR: u8 = 0x21;
+const DW_OP_PLUS: u8 = 0x22;
+const DW_OP_PLUS_UCONST: u8 = 0x23;
+const DW_OP_SHL: u8 = 0x24;
+const DW_OP_SHR: u8 = 0x25;
+const DW_OP_SHRA: u8 = 0x26;
+const DW_OP_XOR: u8 = 0x27;
+const DW_OP_LIT0: u8 = 0x30;
+const DW_OP_LIT31: u8 = 0x4f;
+const DW_OP_REG0: u8 = 0x50;
+const DW_OP_REG31: u8 = 0x6f;
+const DW_OP_BREG0: u8 = 0x70;
+const DW_OP_BREG31: u8 = 0x8f;
+const DW_OP_REGX: u8 = 0x90;
+const DW_OP_FBREG: u8 = 0x91;
+const DW_OP_BREGX: u8 = 0x92;
+const DW_OP_PIECE: u8 = 0x93;
+const DW_OP_DEREF_SIZE: u8 = 0x94;
+const DW_OP_NOP: u8 = 0x96;
+const DW_OP_CALL_FRAME_CFA: u8 = 0x9c;
+const DW_OP_IMPLICIT_VALUE: u8 = 0x9e;
+const DW_OP_STACK_VALUE: u8 = 0x9f;
+
+/// Says where a location expression puts a variable, for `info scope`, when it is one of the
+/// single operations compilers use for most variables. Anything else is just called complex.
+pub fn describe(expression: &[u8]) -> String {
+    let mut ops = Bytes {
+        bytes: expression,
+        pos: 0,
+    };
+    let described = match ops.next_byte() {
+        Some(DW_OP_FBREG) => {
+            ops.sleb().ok().map(|offset| format!("a variable at frame base offset {}", offset))
+        }
+        Some(op @ DW_OP_REG0..=DW_OP_REG31) => {
+            Some(format!("a variable in {}", describe_register((op - DW_OP_REG0) as u16)))
+        }
+        Some(DW_OP_REGX) => ops
+            .uleb()
+            .ok()
+            .map(|number| format!("a variable in {}", describe_register(number as u16))),
+        Some(op @ DW_OP_BREG0..=DW_OP_BREG31) => ops.sleb().ok().map(|offset| {
+            let register = describe_register((op - DW_OP_BREG0) as u16);
+            format!("a variable at offset {} from {}", offset, register)
+        }),
+        Some(DW_OP_ADDR) => {
+            ops.fixed(8).ok().map(|addr| format!("static storage at address {:#x}", addr))
+        }
+        _ => None,
+    };
+    match described {
+        Some(text) if ops.pos == expression.len() => text,
+        _ => "a variable with a complex DWARF expression locating its address".to_string(),
+    }
+}
+
+/// A register by name, such as `register $rbp`, or by number when it has no name here.
+fn describe_register(number: u16) -> String {
+    match register_name(number) {
+        Some(name) => format!("register ${}", name),
+        None => format!("DWARF register {}", number),
+    }
+}
+
+/// Runs a location expression. A value in pieces (DW_OP_piece) is taken to be wholly where its
+/// first piece is, which is right for anything that fits in one register.
+pub fn evaluate(expression: &[u8], frame: &dyn Frame) -> Result<Place, ExprError> {
+    let mut ops = Bytes {
+        bytes: expression,
+        pos: 0,
+    };
+    let mut stack: Vec<u64> = Vec::new();
+    while let Some(op) = ops.next_byte() {
+        match op {
+            DW_OP_ADDR => stack.push(ops.fixed(8)?.wrapping_add(frame.load_bias())),
+            DW_OP_DEREF => {
+                let addr = pop(&mut stack)? as usize;
+                stack.push(read(frame, addr, 8)?);
+            }
+            DW_OP_DEREF_SIZE => {
+                let size = ops.fixed(1)? as usize;
+                let addr = pop(&mut stack)? as usize;
+                stack.push(read(frame, addr, size)?);
+            }
+            DW_OP_CONST1U => stack.push(ops.fixed(1)?),
+            DW_OP_CONST1S => stack.push(ops.fixed(1)? as u8 as i8 as u64),
+            DW_OP_CONST2U => stack.push(ops.fixed(2)?),
+            DW_OP_CONST2S => stack.push(ops.fixed(2)? as u16 as i16 as u64),
+            DW_OP_CONST4U => stack.push(ops.fixed(4)?),
+            DW_OP_CONST4S => stack.push(ops.fixed(4)? as u32 as i32 as u64),
+            DW_OP_CONST8U | DW_OP_CONST8S => stack.push(ops.fixed(8)?),
+            DW_OP_CONSTU => stack.push(ops.uleb()?),
+            DW_OP_CONSTS => stack.push(ops.sleb()? as u64),
+            DW_OP_LIT0..=DW_OP_LIT31 => stack.push((op - DW_OP_LIT0) as u64),
+            DW_OP_DUP => stack.push(peek(&stack, 0)?),
+            DW_OP_DROP => {
+                pop(&mut stack)?;
+            }
+            DW_OP_OVER => stack.push(peek(&stack, 1)?),
+            DW_OP_PICK => {
+                let idx = ops.fixed(1)? as usize;
+                stack.push(peek(&stack, idx)?);
+            }
+            DW_OP_SWAP => {
+                let top = pop(&mut stack)?;
+                let second = pop(&mut stack)?;
+                stack.push(top);
+                stack.push(second);
+            }
+            DW_OP_ROT => {
+                let top = pop(&mut stack)?;
+                let second = pop(&mut stack)?;
+                let third = pop(&mut stack)?;
+                stack.push(top);
+                stack.push(third);
+                stack.push(second);
+            }
+            DW_OP_ABS => {
+                let value = pop(&mut stack)? as i64;
+                stack.push(value.wrapping_abs() as u64);
+            }
+            DW_OP_NEG => {
+                let value = pop(&mut stack)? as i64;
+                stack.push(value.wrapping_neg() as u64);
+            }
+            DW_OP_NOT => {
+                let value = pop(&mut stack)?;
+                stack.push(!value);
+            }
+            DW_OP_PLUS_UCONST => {
+                let value = pop(&mut stack)?;
+                stack.push(value.wrapping_add(ops.uleb()?));
+            }
+            DW_OP_AND | DW_OP_DIV | DW_OP_MINUS | DW_OP_MOD | DW_OP_MUL | DW_OP_OR | DW_OP_PLUS
+            | DW_OP_SHL | DW_OP_SHR | DW_OP_SHRA | DW_OP_XOR => {
+                let right = pop(&mut stack)?;
+                let left = pop(&mut stack)?;
+                stack.push(binary(op, left, right)?);
+            }
+            DW_OP_REG0..=DW_OP_REG31 => {
+                return finish(&mut ops, Place::Register((op - DW_OP_REG0) as u16));
+            }
+            DW_OP_REGX => {
+                let number = ops.uleb()? as u16;
+                return finish(&mut ops, Place::Register(number));
+            }
+            DW_OP_BREG0..=DW_OP_BREG31 => {
+                let number = (op - DW_OP_BREG0) as u16;
+                let offset = ops.sleb()?;
+                stack.push(register(frame, number)?.wrapping_add(offset as u64));
+            }
+            DW_OP_BREGX => {
+                let number = ops.uleb()? as u16;
+                let offset = ops.sleb()?;
+                stack.push(register(frame, number)?.wrapping_add(offset as u64));
+            }
+            DW_OP_FBREG => {
+                let offset = ops.sleb()?;
+                let base = frame.frame_base().ok_or(ExprError::NoFrameBase)?;
+                stack.push(base.wrapping_add(offset as u64));
+            }
+            DW_OP_CALL_FRAME_CFA => stack.push(frame.cfa().ok_or(ExprError::NoFrameBase)?),
+            DW_OP_NOP => {}
+            DW_OP_STACK_VALUE => return finish(&mut ops, Place::Value(pop(&mut stack)?)),
+            DW_OP_IMPLICIT_VALUE => {
+                let len = ops.uleb()? as usize;
+                let bytes = ops.take(len)?.to_vec();
+                return finish(&mut ops, Place::Bytes(bytes));
+            }
+            DW_OP_PIECE => {
+                // The first piece is a location on the stack; the rest are ignored
+                ops.uleb()?;
+                return Ok(Place::Memory(pop(&mut stack)? as usize));
+            }
+            _ => return Err(ExprError::Unsupported(op)),
+        }
+    }
+    Ok(Place::Memory(pop(&mut stack)? as usize))
+}
+
+/// Ends the expression at an operation that says where the value is. Only a DW_OP_piece may
+/// follow it.
+fn finish(ops: &mut Bytes<'_>, place: Place) -> Result<Place, ExprError> {
+    match ops.next_byte() {
+        None | Some(DW_OP_PIECE) => Ok(place),
+        Some(_) => Err(ExprError::Malformed),
+    }
+}
+
+fn binary(op: u8, left: u64, right: u64) -> Result<u64, ExprError> {
+    Ok(match op {
+        DW_OP_AND => left & right,
+        DW_OP_OR => left | right,
+        DW_OP_XOR => left ^ right,
+        DW_OP_PLUS => left.wrapping_add(right),
+        DW_OP_MINUS => left.wrapping_sub(right),
+        DW_OP_MUL => left.wrapping_mul(right),
+        // Division is signed, modulo unsigned, as the standard has it
+        DW_OP_DIV if right == 0 => return Err(ExprError::Malformed),
+        DW_OP_DIV => (left as i64).wrapping_div(right as i64) as u64,
+        DW_OP_MOD if right == 0 => return Err(ExprError::Malformed),
+        DW_OP_MOD => left % right,
+        DW_OP_SHL => left.checked_shl(right as u32).unwrap_or(0),
+        DW_OP_SHR => left.checked_shr(right as u32).unwrap_or(0),
+        _ => (left as i64).checked_shr(right as u32).unwrap_or(0) as u64,
+    })
+}
+
+fn pop(stack: &mut Vec<u64>) -> Result<u64, ExprError> {
+    stack.pop().ok_or(ExprError::Malformed)
+}
+
+/// The entry `depth` down from the top of the stack.
+fn peek(stack: &[u64], depth: usize) -> Result<u64, ExprError> {
+    stack
+        .len()
+        .checked_sub(depth + 1)
+        .map(|idx| stack[idx])
+        .ok_or(ExprError::Malformed)
+}
+
+fn register(frame: &dyn Frame, number: u16) -> Result<u64, ExprError> {
+    frame.register(number).ok_or(ExprError::UnknownRegister(number))
+}
+
+/// Reads a little-endian integer of `size` (at most 8) bytes.
+fn read(frame: &dyn Frame, addr: usize, size: usize) -> Result<u64, ExprError> {
+    let bytes = frame
+        .read_memory(addr, size.min(8))
+        .ok_or(ExprError::Memory(addr))?;
+    let mut word = [0u8; 8];
+    word[..bytes.len()].copy_from_slice(&bytes);
+    Ok(u64::from_le_bytes(word))
+}
+
+/// The operands of an expression, read from the front.
+struct Bytes<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Bytes<'a> {
+    fn next_byte(&mut self) -> Option<u8> {
+        let byte = *self.bytes.get(self.pos)?;
+        self.pos += 1;
+        Some(byte)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ExprError> {
+        let end = self.pos.checked_add(len).ok_or(ExprError::Malformed)?;
+        let bytes = self.bytes.get(self.pos..end).ok_or(ExprError::Malformed)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    /// A little-endian unsigned integer of `len` bytes.
+    fn fixed(&mut self, len: usize) -> Result<u64, ExprError> {
+        let mut word = [0u8; 8];
+        word[..len].copy_from_slice(self.take(len)?);
+        Ok(u64::from_le_bytes(word))
+    }
+
+    fn uleb(&mut self) -> Result<u64, ExprError> {
+        let mut value = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = self.next_byte().ok_or(ExprError::Malformed)?;
+            if shift < 64 {
+                value |= ((byte & 0x7f) as u64) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn sleb(&mut self) -> Result<i64, ExprError> {
+        let mut value = 0i64;
+        let mut shift = 0;
+        loop {
+            let byte = self.next_byte().ok_or(ExprError::Malformed)?;
+            if shift < 64 {
+                value |= ((byte & 0x7f) as i64) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    value |= -1i64 << shift;
+                }
+                return Ok(value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A frame stopped with rbp = 0x1000 and rsp = 0xff0, whose memory holds a pointer to 0x2000
+    /// at 0x1008 and 0xcafe at 0x2000.
+    struct MockFrame;
+
+    impl Frame for MockFrame {
+        fn register(&self, number: u16) -> Option<u64> {
+            match number {
+                6 => Some(0x1000),
+                7 => Some(0xff0),
+                _ => None,
+            }
+        }
+
+        fn frame_base(&self) -> Option<u64> {
+            Some(0x1010)
+        }
+
+        fn cfa(&self) -> Option<u64> {
+            Some(0x1020)
+        }
+
+        fn read_memory(&self, addr: usize, len: usize) -> Option<Vec<u8>> {
+            let (start, bytes) = match addr {
+                0x1008..=0x100f => (0x1008, 0x2000u64.to_le_bytes()),
+                0x2000..=0x2007 => (0x2000, 0xcafeu64.to_le_bytes()),
+                _ => return None,
+            };
+            bytes.get(addr - start..addr - start + len).map(<[u8]>::to_vec)
+        }
+
+        fn load_bias(&self) -> u64 {
+            0x10_0000
+        }
+    }
+
+    /// A frame with no frame base or CFA, as when the function's CFI couldn't be found.
+    struct BareFrame;
+
+    impl Frame for BareFrame {
+        fn register(&self, _number: u16) -> Option<u64> {
+            None
+        }
+
+        fn frame_base(&self) -> Option<u64> {
+            None
+        }
+
+        fn cfa(&self) -> Option<u64> {
+            None
+        }
+
+        fn read_memory(&self, _addr: usize, _len: usize) -> Option<Vec<u8>> {
+            None
+        }
+
+        fn load_bias(&self) -> u64 {
+            0
+        }
+    }
+
+    fn eval(expression: &[u8]) -> Result<Place, ExprError> {
+        evaluate(expression, &MockFrame)
+    }
+
+    #[test]
+    fn registers() {
+        // DW_OP_reg3 (rbx), DW_OP_regx 17
+        assert_eq!(eval(&[0x53]), Ok(Place::Register(3)));
+        assert_eq!(eval(&[0x90, 0x11]), Ok(Place::Register(17)));
+        // DW_OP_breg6 (rbp) -8, DW_OP_bregx 7 (rsp) +16
+        assert_eq!(eval(&[0x76, 0x78]), Ok(Place::Memory(0xff8)));
+        assert_eq!(eval(&[0x92, 0x07, 0x10]), Ok(Place::Memory(0x1000)));
+        // DW_OP_breg0 (rax), which the frame doesn't know
+        assert_eq!(eval(&[0x70, 0x00]), Err(ExprError::UnknownRegister(0)));
+    }
+
+    #[test]
+    fn frame_base_and_cfa() {
+        // DW_OP_fbreg -20
+        assert_eq!(eval(&[0x91, 0x6c]), Ok(Place::Memory(0x0ffc)));
+        // DW_OP_call_frame_cfa; DW_OP_plus_uconst 8
+        assert_eq!(eval(&[0x9c, 0x23, 0x08]), Ok(Place::Memory(0x1028)));
+        assert_eq!(evaluate(&[0x91, 0x00], &BareFrame), Err(ExprError::NoFrameBase));
+        assert_eq!(evaluate(&[0x9c], &BareFrame), Err(ExprError::NoFrameBase));
+    }
+
+    #[test]
+    fn addresses_are_moved_by_the_load_bias() {
+        let mut expression = vec![0x03];
+        expression.extend_from_slice(&0x4010u64.to_le_bytes());
+        assert_eq!(eval(&expression), Ok(Place::Memory(0x10_4010)));
+    }
+
+    #[test]
+    fn computed_values() {
+        // DW_OP_lit5; DW_OP_lit3; DW_OP_minus; DW_OP_stack_value
+        assert_eq!(eval(&[0x35, 0x33, 0x1c, 0x9f]), Ok(Place::Value(2)));
+        // DW_OP_const1s -1; DW_OP_abs; DW_OP_stack_value
+        assert_eq!(eval(&[0x09, 0xff, 0x19, 0x9f]), Ok(Place::Value(1)));
+        // DW_OP_breg6 0; DW_OP_lit4; DW_OP_shr; DW_OP_stack_value
+        assert_eq!(eval(&[0x76, 0x00, 0x34, 0x25, 0x9f]), Ok(Place::Value(0x100)));
+        // DW_OP_lit1; DW_OP_lit0; DW_OP_div
+        assert_eq!(eval(&[0x31, 0x30, 0x1b, 0x9f]), Err(ExprError::Malformed));
+    }
+
+    #[test]
+    fn implicit_values() {
+        // DW_OP_implicit_value 4 bytes
+        assert_eq!(eval(&[0x9e, 0x04, 1, 2, 3, 4]), Ok(Place::Bytes(vec![1, 2, 3, 4])));
+        // Fewer bytes than it says
+        assert_eq!(eval(&[0x9e, 0x04, 1, 2]), Err(ExprError::Malformed));
+        // A length that would overflow
+        let huge = [0x9e, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01];
+        assert_eq!(eval(&huge), Err(ExprError::Malformed));
+    }
+
+    #[test]
+    fn pieces() {
+        // DW_OP_reg0; DW_OP_piece 4; DW_OP_reg1; DW_OP_piece 4: taken to be wholly in rax
+        assert_eq!(eval(&[0x50, 0x93, 0x04, 0x51, 0x93, 0x04]), Ok(Place::Register(0)));
+        // DW_OP_fbreg 0; DW_OP_piece 8
+        assert_eq!(eval(&[0x91, 0x00, 0x93, 0x08]), Ok(Place::Memory(0x1010)));
+        // Something other than a piece after a register
+        assert_eq!(eval(&[0x50, 0x30]), Err(ExprError::Malformed));
+    }
+
+    #[test]
+    fn dereferences() {
+        // DW_OP_breg6 8; DW_OP_deref: the pointer at rbp+8
+        assert_eq!(eval(&[0x76, 0x08, 0x06]), Ok(Place::Memory(0x2000)));
+        // ... then DW_OP_deref_size 2; DW_OP_stack_value
+        assert_eq!(eval(&[0x76, 0x08, 0x06, 0x94, 0x02, 0x9f]), Ok(Place::Value(0xcafe)));
+        assert_eq!(eval(&[0x76, 0x08, 0x06, 0x94, 0x01, 0x9f]), Ok(Place::Value(0xfe)));
+        // Unreadable memory
+        assert_eq!(eval(&[0x30, 0x06]), Err(ExprError::Memory(0)));
+    }
+
+    #[test]
+    fn malformed_expressions() {
+        assert_eq!(eval(&[]), Err(ExprError::Malformed));
+        // Operands cut short
+        assert_eq!(eval(&[0x91]), Err(ExprError::Malformed));
+        assert_eq!(eval(&[0x91, 0x80]), Err(ExprError::Malformed));
+        assert_eq!(eval(&[0x03, 0x00, 0x00]), Err(ExprError::Malformed));
+        assert_eq!(eval(&[0x94]), Err(ExprError::Malformed));
+        // Popping from an empty stack
+        assert_eq!(eval(&[0x22]), Err(ExprError::Malformed));
+        assert_eq!(eval(&[0x9f]), Err(ExprError::Malformed));
+        assert_eq!(eval(&[0x30, 0x16]), Err(ExprError::Malformed));
+        // DW_OP_entry_value
+        assert_eq!(eval(&[0xa3, 0x01, 0x50, 0x9f]), Err(ExprError::Unsupported(0xa3)));
+    }
+
+    #[test]
+    fn descriptions() {
+        assert_eq!(describe(&[0x91, 0x6c]), "a variable at frame base offset -20");
+        assert_eq!(describe(&[0x53]), "a variable in register $rbx");
+        assert_eq!(describe(&[0x76, 0x78]), "a variable at offset -8 from register $rbp");
+        assert_eq!(describe(&[0x90, 0x11]), "a variable in DWARF register 17");
+        assert_eq!(
+            describe(&[0x91, 0x6c, 0x06]),
+            "a variable with a complex DWARF expression locating its address"
+        );
+    }
+}
@@ -0,0 +1,1294 @@
+//! Expressions for `print`: a small C-like grammar (variables, `$registers`, integer literals,
+//! `. -> [] *`, and `+ - * /` with parentheses), evaluated against the debug info and the memory
+//! of a stopped inferior. Looking up variables and registers and reading memory is left to a
+//! `Context`, so nothing in here knows about ptrace.
+
+use crate::dwarf_data::{DwarfData, Member, Type, TypeId, TypeKind};
+use std::convert::TryInto;
+use std::fmt;
+
+/// How deeply nested structs are expanded before being shown as `{...}`.
+const MAX_DEPTH: usize = 3;
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Variable(String),
+    Integer(i64),
+    /// `expr.member`
+    Member(Box<Expr>, String),
+    /// `expr->member`
+    PointerMember(Box<Expr>, String),
+    /// `expr[index]`
+    Index(Box<Expr>, Box<Expr>),
+    /// `*expr`
+    Deref(Box<Expr>),
+    /// `$name`
+    Register(String),
+    /// `$N` for the Nth value in the history, `$` for the last
+    History(Option<usize>),
+    /// `-expr`
+    Negate(Box<Expr>),
+    /// `!expr`: 1 if the operand is zero, otherwise 0
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /// `==`, `<` and the rest, which give 1 or 0. Pointers compare by address.
+    Compare(Comparison, Box<Expr>, Box<Expr>),
+    /// `a && b`: b is only evaluated if a is nonzero
+    And(Box<Expr>, Box<Expr>),
+    /// `a || b`: b is only evaluated if a is zero
+    Or(Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+/// How `print/f` and `display/f` show scalars. Structs and arrays apply it to each field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// According to the type: the default
+    Natural,
+    /// `/x`
+    Hex,
+    /// `/d`
+    Decimal,
+    /// `/u`
+    Unsigned,
+    /// `/o`
+    Octal,
+    /// `/t`
+    Binary,
+    /// `/c`
+    Char,
+}
+
+impl Format {
+    pub fn from_letter(letter: char) -> Option<Format> {
+        match letter {
+            'x' => Some(Format::Hex),
+            'd' => Some(Format::Decimal),
+            'u' => Some(Format::Unsigned),
+            'o' => Some(Format::Octal),
+            't' => Some(Format::Binary),
+            'c' => Some(Format::Char),
+            _ => None,
+        }
+    }
+
+    pub fn letter(self) -> Option<char> {
+        match self {
+            Format::Natural => None,
+            Format::Hex => Some('x'),
+            Format::Decimal => Some('d'),
+            Format::Unsigned => Some('u'),
+            Format::Octal => Some('o'),
+            Format::Binary => Some('t'),
+            Format::Char => Some('c'),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum EvalError {
+    /// The expression doesn't parse
+    Syntax(String),
+    UnknownVariable(String),
+    /// The type (named) has no such member
+    NoMember(String, String),
+    /// `.` or `->` applied to something that isn't a struct, or a pointer to one
+    NotAStruct(String),
+    NotAPointer(String),
+    /// `[]` or `*` applied to something that isn't an array or pointer
+    NotIndexable(String),
+    /// An index or operand that has to be an integer isn't one
+    NotAnInteger(String),
+    /// Dereferencing a `void *`
+    VoidPointer,
+    UnknownRegister(String),
+    /// `$N` past the end of the value history (`$` when it is empty)
+    NoHistory(Option<usize>),
+    DivisionByZero,
+    /// Arithmetic on operands that don't support it, e.g. adding two pointers
+    BadOperands(String, String),
+    /// Reading the inferior's memory at this address failed
+    Memory(usize),
+    /// The variable has no location at the current pc
+    OptimizedOut,
+    /// The variable's location expression couldn't be evaluated, for this reason
+    Location(String, String),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::Syntax(msg) => write!(f, "Syntax error: {}", msg),
+            EvalError::UnknownVariable(name) => {
+                write!(f, "No symbol \"{}\" in current context.", name)
+            }
+            EvalError::NoMember(type_name, member) => {
+                write!(f, "There is no member named {} in {}.", member, type_name)
+            }
+            EvalError::NotAStruct(type_name) => {
+                write!(
+                    f,
+                    "Attempt to extract a member of a value that is not a struct ({}).",
+                    type_name
+                )
+            }
+            EvalError::NotAPointer(type_name) => {
+                write!(
+                    f,
+                    "The -> operator needs a pointer to a struct, not {}.",
+                    type_name
+                )
+            }
+            EvalError::NotIndexable(type_name) => {
+                write!(
+                    f,
+                    "Cannot subscript or dereference a value of type {}.",
+                    type_name
+                )
+            }
+            EvalError::NotAnInteger(type_name) => {
+                write!(f, "Expected an integer, not a value of type {}.", type_name)
+            }
+            EvalError::VoidPointer => write!(f, "Attempt to take contents of a void pointer."),
+            EvalError::UnknownRegister(name) => write!(f, "Invalid register \"${}\".", name),
+            EvalError::NoHistory(Some(number)) => {
+                write!(f, "History has not yet reached ${}.", number)
+            }
+            EvalError::NoHistory(None) => write!(f, "History is empty."),
+            EvalError::DivisionByZero => write!(f, "Division by zero"),
+            EvalError::BadOperands(left, right) => {
+                write!(f, "Invalid operands ({} and {}).", left, right)
+            }
+            EvalError::Memory(addr) => write!(f, "Cannot access memory at address {:#x}", addr),
+            EvalError::OptimizedOut => write!(f, "<optimized out>"),
+            EvalError::Location(name, reason) => {
+                write!(f, "Cannot find where \"{}\" is: {}", name, reason)
+            }
+        }
+    }
+}
+
+/// Where a variable's value is at the current stop.
+pub enum Storage {
+    Memory(usize),
+    /// Not in memory (in a register, say): the value's bytes
+    Value(Vec<u8>),
+    /// Nowhere: the compiler didn't keep the variable at this point in the program
+    OptimizedOut,
+    /// The location expression couldn't be evaluated, for this reason
+    Unknown(String),
+}
+
+/// What an expression needs from the debugger: the variables in scope and the inferior's memory.
+pub trait Context {
+    fn debug_data(&self) -> &DwarfData;
+    /// Returns where the variable `name` is, and its type, as seen from the current stop.
+    fn variable(&self, name: &str) -> Option<(Storage, TypeId)>;
+    /// Returns the value of a register (such as `rsp`) in the current frame.
+    fn register(&self, name: &str) -> Option<u64>;
+    /// Returns the contents of a 128-bit vector register (such as `xmm0`).
+    fn vector_register(&self, name: &str) -> Option<[u8; 16]>;
+    /// Returns value `number` in the value history, or the last one for None.
+    fn history(&self, number: Option<usize>) -> Option<Value>;
+    fn read_memory(&self, addr: usize, len: usize) -> Option<Vec<u8>>;
+}
+
+/// The result of evaluating an expression: its type and contents, and where it lives in the
+/// inferior (None for values computed by the expression itself).
+#[derive(Debug, Clone)]
+pub struct Value {
+    /// None for a plain integer, such as a literal, held as 8 little-endian bytes
+    pub type_id: Option<TypeId>,
+    pub address: Option<usize>,
+    pub bytes: Vec<u8>,
+}
+
+impl Value {
+    fn integer(value: i64) -> Value {
+        Value {
+            type_id: None,
+            address: None,
+            bytes: value.to_le_bytes().to_vec(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Integer(i64),
+    Register(String),
+    History(Option<usize>),
+    Dot,
+    Arrow,
+    Star,
+    Plus,
+    Minus,
+    Slash,
+    LeftBracket,
+    RightBracket,
+    LeftParen,
+    RightParen,
+    Not,
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    And,
+    Or,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, EvalError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_alphabetic() || c == '_' || c == '$' {
+            chars.next();
+            let mut ident = if c == '$' {
+                String::new()
+            } else {
+                c.to_string()
+            };
+            while let Some(&c) = chars.peek() {
+                if !(c.is_ascii_alphanumeric() || c == '_') {
+                    break;
+                }
+                ident.push(c);
+                chars.next();
+            }
+            if c == '$' && ident.chars().all(|c| c.is_ascii_digit()) {
+                tokens.push(Token::History(ident.parse().ok()));
+            } else if c == '$' {
+                tokens.push(Token::Register(ident));
+            } else {
+                tokens.push(Token::Ident(ident));
+            }
+        } else if c.is_ascii_digit() {
+            let mut digits = String::new();
+            while let Some(&c) = chars.peek() {
+                if !c.is_ascii_alphanumeric() {
+                    break;
+                }
+                digits.push(c);
+                chars.next();
+            }
+            let value = if digits.starts_with("0x") || digits.starts_with("0X") {
+                i64::from_str_radix(&digits[2..], 16)
+            } else {
+                digits.parse()
+            };
+            let value =
+                value.map_err(|_| EvalError::Syntax(format!("invalid number \"{}\"", digits)))?;
+            tokens.push(Token::Integer(value));
+        } else {
+            chars.next();
+            tokens.push(match c {
+                '.' => Token::Dot,
+                '*' => Token::Star,
+                '+' => Token::Plus,
+                '/' => Token::Slash,
+                '[' => Token::LeftBracket,
+                ']' => Token::RightBracket,
+                '(' => Token::LeftParen,
+                ')' => Token::RightParen,
+                '-' if chars.peek() == Some(&'>') => {
+                    chars.next();
+                    Token::Arrow
+                }
+                '-' => Token::Minus,
+                '=' | '!' | '<' | '>' if chars.peek() == Some(&'=') => {
+                    chars.next();
+                    match c {
+                        '=' => Token::Equal,
+                        '!' => Token::NotEqual,
+                        '<' => Token::LessOrEqual,
+                        _ => Token::GreaterOrEqual,
+                    }
+                }
+                '!' => Token::Not,
+                '<' => Token::Less,
+                '>' => Token::Greater,
+                '&' | '|' if chars.peek() == Some(&c) => {
+                    chars.next();
+                    if c == '&' {
+                        Token::And
+                    } else {
+                        Token::Or
+                    }
+                }
+                _ => return Err(EvalError::Syntax(format!("unexpected character '{}'", c))),
+            });
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent over the tokens. Each method parses one precedence level.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token, what: &str) -> Result<(), EvalError> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            _ => Err(EvalError::Syntax(format!("expected {}", what))),
+        }
+    }
+
+    fn expression(&mut self) -> Result<Expr, EvalError> {
+        self.or()
+    }
+
+    fn or(&mut self) -> Result<Expr, EvalError> {
+        let mut expr = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, EvalError> {
+        let mut expr = self.equality()?;
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            expr = Expr::And(Box::new(expr), Box::new(self.equality()?));
+        }
+        Ok(expr)
+    }
+
+    fn equality(&mut self) -> Result<Expr, EvalError> {
+        let mut expr = self.relational()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Equal) => Comparison::Equal,
+                Some(Token::NotEqual) => Comparison::NotEqual,
+                _ => return Ok(expr),
+            };
+            self.next();
+            expr = Expr::Compare(op, Box::new(expr), Box::new(self.relational()?));
+        }
+    }
+
+    fn relational(&mut self) -> Result<Expr, EvalError> {
+        let mut expr = self.additive()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Less) => Comparison::Less,
+                Some(Token::LessOrEqual) => Comparison::LessOrEqual,
+                Some(Token::Greater) => Comparison::Greater,
+                Some(Token::GreaterOrEqual) => Comparison::GreaterOrEqual,
+                _ => return Ok(expr),
+            };
+            self.next();
+            expr = Expr::Compare(op, Box::new(expr), Box::new(self.additive()?));
+        }
+    }
+
+    fn additive(&mut self) -> Result<Expr, EvalError> {
+        let mut expr = self.multiplicative()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Plus) => BinaryOp::Add,
+                Some(Token::Minus) => BinaryOp::Subtract,
+                _ => return Ok(expr),
+            };
+            self.next();
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.multiplicative()?));
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr, EvalError> {
+        let mut expr = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Star) => BinaryOp::Multiply,
+                Some(Token::Slash) => BinaryOp::Divide,
+                _ => return Ok(expr),
+            };
+            self.next();
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, EvalError> {
+        match self.peek() {
+            Some(Token::Star) => {
+                self.next();
+                Ok(Expr::Deref(Box::new(self.unary()?)))
+            }
+            Some(Token::Minus) => {
+                self.next();
+                Ok(Expr::Negate(Box::new(self.unary()?)))
+            }
+            Some(Token::Not) => {
+                self.next();
+                Ok(Expr::Not(Box::new(self.unary()?)))
+            }
+            _ => self.postfix(),
+        }
+    }
+
+    fn postfix(&mut self) -> Result<Expr, EvalError> {
+        let mut expr = self.primary()?;
+        loop {
+            expr = match self.peek() {
+                Some(Token::Dot) | Some(Token::Arrow) => {
+                    let arrow = self.next() == Some(Token::Arrow);
+                    let member = match self.next() {
+                        Some(Token::Ident(member)) => member,
+                        _ => return Err(EvalError::Syntax("expected a member name".to_string())),
+                    };
+                    if arrow {
+                        Expr::PointerMember(Box::new(expr), member)
+                    } else {
+                        Expr::Member(Box::new(expr), member)
+                    }
+                }
+                Some(Token::LeftBracket) => {
+                    self.next();
+                    let index = self.expression()?;
+                    self.expect(Token::RightBracket, "]")?;
+                    Expr::Index(Box::new(expr), Box::new(index))
+                }
+                _ => return Ok(expr),
+            };
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, EvalError> {
+        match self.next() {
+            Some(Token::Ident(name)) => Ok(Expr::Variable(name)),
+            Some(Token::Integer(value)) => Ok(Expr::Integer(value)),
+            Some(Token::Register(name)) => Ok(Expr::Register(name)),
+            Some(Token::History(number)) => Ok(Expr::History(number)),
+            Some(Token::LeftParen) => {
+                let expr = self.expression()?;
+                self.expect(Token::RightParen, ")")?;
+                Ok(expr)
+            }
+            Some(_) => Err(EvalError::Syntax(
+                "expected a variable name or number".to_string(),
+            )),
+            None => Err(EvalError::Syntax(
+                "unexpected end of expression".to_string(),
+            )),
+        }
+    }
+}
+
+/// Parses an expression such as `list->head.value`, `*argv[1]`, `($rsp - $rbp) / 8` or
+/// `n > 0 && buf[n - 1] == 10`.
+pub fn parse(text: &str) -> Result<Expr, EvalError> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        pos: 0,
+    };
+    if parser.peek().is_none() {
+        return Err(EvalError::Syntax("empty expression".to_string()));
+    }
+    let expr = parser.expression()?;
+    if parser.peek().is_some() {
+        return Err(EvalError::Syntax(
+            "unexpected text after expression".to_string(),
+        ));
+    }
+    Ok(expr)
+}
+
+/// Evaluates a parsed expression. Problems that don't stop evaluation, such as indexing past the
+/// end of an array, are added to `warnings`.
+pub fn evaluate<C: Context>(
+    expr: &Expr,
+    ctx: &C,
+    warnings: &mut Vec<String>,
+) -> Result<Value, EvalError> {
+    let debug_data = ctx.debug_data();
+    match expr {
+        Expr::Variable(name) => {
+            let (storage, type_id) = ctx
+                .variable(name)
+                .ok_or_else(|| EvalError::UnknownVariable(name.clone()))?;
+            match storage {
+                Storage::Memory(address) => read_value(ctx, address, type_id),
+                Storage::Value(mut bytes) => {
+                    bytes.resize(debug_data.type_size(type_id), 0);
+                    Ok(Value {
+                        type_id: Some(type_id),
+                        address: None,
+                        bytes,
+                    })
+                }
+                Storage::OptimizedOut => Err(EvalError::OptimizedOut),
+                Storage::Unknown(reason) => Err(EvalError::Location(name.clone(), reason)),
+            }
+        }
+        Expr::Integer(value) => Ok(Value::integer(*value)),
+        Expr::Member(base, member) => {
+            let base = evaluate(base, ctx, warnings)?;
+            member_of(ctx, &base, member)
+        }
+        Expr::PointerMember(base, member) => {
+            let base = evaluate(base, ctx, warnings)?;
+            let target = match base.type_id.and_then(|id| debug_data.resolve_type(id)) {
+                Some(Type {
+                    kind: TypeKind::Pointer(Some(target)),
+                    ..
+                }) => *target,
+                _ => return Err(EvalError::NotAPointer(type_name(debug_data, &base))),
+            };
+            let pointee = read_value(ctx, to_integer(debug_data, &base)? as usize, target)?;
+            member_of(ctx, &pointee, member)
+        }
+        Expr::Index(base, index) => {
+            let base = evaluate(base, ctx, warnings)?;
+            let index = evaluate(index, ctx, warnings)?;
+            let index = to_integer(debug_data, &index)?;
+            element_of(ctx, &base, index, warnings)
+        }
+        Expr::Deref(base) => {
+            let base = evaluate(base, ctx, warnings)?;
+            element_of(ctx, &base, 0, warnings)
+        }
+        Expr::Register(name) => {
+            // An XMM register is taken to hold a double in its low 64 bits, when the program
+            // has a double type to show it as
+            if let Some(bytes) = ctx.vector_register(name) {
+                return Ok(Value {
+                    type_id: debug_data.find_type("double"),
+                    address: None,
+                    bytes: bytes[..8].to_vec(),
+                });
+            }
+            let value = ctx
+                .register(name)
+                .ok_or_else(|| EvalError::UnknownRegister(name.clone()))?;
+            Ok(Value::integer(value as i64))
+        }
+        Expr::History(number) => ctx.history(*number).ok_or(EvalError::NoHistory(*number)),
+        Expr::Negate(operand) => {
+            let operand = evaluate(operand, ctx, warnings)?;
+            Ok(Value::integer(
+                to_integer(debug_data, &operand)?.wrapping_neg(),
+            ))
+        }
+        Expr::Not(operand) => {
+            let operand = evaluate(operand, ctx, warnings)?;
+            Ok(Value::integer((scalar(debug_data, &operand)? == 0) as i64))
+        }
+        Expr::Binary(op, left, right) => {
+            let left = evaluate(left, ctx, warnings)?;
+            let right = evaluate(right, ctx, warnings)?;
+            binary(debug_data, *op, &left, &right)
+        }
+        Expr::Compare(op, left, right) => {
+            let left = scalar(debug_data, &evaluate(left, ctx, warnings)?)?;
+            let right = scalar(debug_data, &evaluate(right, ctx, warnings)?)?;
+            let result = match op {
+                Comparison::Equal => left == right,
+                Comparison::NotEqual => left != right,
+                Comparison::Less => left < right,
+                Comparison::LessOrEqual => left <= right,
+                Comparison::Greater => left > right,
+                Comparison::GreaterOrEqual => left >= right,
+            };
+            Ok(Value::integer(result as i64))
+        }
+        Expr::And(left, right) => {
+            let result = scalar(debug_data, &evaluate(left, ctx, warnings)?)? != 0
+                && scalar(debug_data, &evaluate(right, ctx, warnings)?)? != 0;
+            Ok(Value::integer(result as i64))
+        }
+        Expr::Or(left, right) => {
+            let result = scalar(debug_data, &evaluate(left, ctx, warnings)?)? != 0
+                || scalar(debug_data, &evaluate(right, ctx, warnings)?)? != 0;
+            Ok(Value::integer(result as i64))
+        }
+    }
+}
+
+/// An integer, or the address a pointer holds or an array starts at, to test or compare.
+fn scalar(debug_data: &DwarfData, value: &Value) -> Result<i64, EvalError> {
+    match pointee(debug_data, value) {
+        Some(_) => pointer_address(debug_data, value),
+        None => to_integer(debug_data, value),
+    }
+}
+
+/// Returns the type a pointer or array points into, if `value` is one.
+fn pointee(debug_data: &DwarfData, value: &Value) -> Option<TypeId> {
+    match &debug_data.resolve_type(value.type_id?)?.kind {
+        TypeKind::Pointer(target) => *target,
+        TypeKind::Array(element, _) if value.address.is_some() => *element,
+        _ => None,
+    }
+}
+
+/// The address held by a pointer, or where an array starts.
+fn pointer_address(debug_data: &DwarfData, value: &Value) -> Result<i64, EvalError> {
+    match debug_data
+        .resolve_type(value.type_id.unwrap_or(0))
+        .map(|ty| &ty.kind)
+    {
+        Some(TypeKind::Array(..)) => Ok(value.address.unwrap_or(0) as i64),
+        _ => to_integer(debug_data, value),
+    }
+}
+
+/// Integer arithmetic, plus C pointer arithmetic: pointer ± integer moves by whole elements, and
+/// the difference of two pointers counts elements.
+fn binary(
+    debug_data: &DwarfData,
+    op: BinaryOp,
+    left: &Value,
+    right: &Value,
+) -> Result<Value, EvalError> {
+    let bad_operands =
+        || EvalError::BadOperands(type_name(debug_data, left), type_name(debug_data, right));
+    match (op, pointee(debug_data, left), pointee(debug_data, right)) {
+        (BinaryOp::Add, Some(element), None)
+        | (BinaryOp::Subtract, Some(element), None)
+        | (BinaryOp::Add, None, Some(element)) => {
+            let (pointer, offset) = if pointee(debug_data, left).is_some() {
+                (left, right)
+            } else {
+                (right, left)
+            };
+            let size = debug_data.type_size(element).max(1) as i64;
+            let mut offset = to_integer(debug_data, offset)?.wrapping_mul(size);
+            if op == BinaryOp::Subtract {
+                offset = offset.wrapping_neg();
+            }
+            let address = pointer_address(debug_data, pointer)?.wrapping_add(offset);
+            // Keep the result typed when the debug info has a pointer type to the element
+            Ok(Value {
+                type_id: debug_data.pointer_type_to(element),
+                address: None,
+                bytes: address.to_le_bytes().to_vec(),
+            })
+        }
+        (BinaryOp::Subtract, Some(element), Some(_)) => {
+            let size = debug_data.type_size(element).max(1) as i64;
+            let difference = pointer_address(debug_data, left)?
+                .wrapping_sub(pointer_address(debug_data, right)?);
+            Ok(Value::integer(difference / size))
+        }
+        (_, None, None) => {
+            let (a, b) = (
+                to_integer(debug_data, left)?,
+                to_integer(debug_data, right)?,
+            );
+            let result = match op {
+                BinaryOp::Add => a.wrapping_add(b),
+                BinaryOp::Subtract => a.wrapping_sub(b),
+                BinaryOp::Multiply => a.wrapping_mul(b),
+                BinaryOp::Divide if b == 0 => return Err(EvalError::DivisionByZero),
+                BinaryOp::Divide => a.wrapping_div(b),
+            };
+            Ok(Value::integer(result))
+        }
+        _ => Err(bad_operands()),
+    }
+}
+
+fn read_value<C: Context>(ctx: &C, address: usize, type_id: TypeId) -> Result<Value, EvalError> {
+    let size = ctx.debug_data().type_size(type_id);
+    let bytes = ctx
+        .read_memory(address, size)
+        .ok_or(EvalError::Memory(address))?;
+    Ok(Value {
+        type_id: Some(type_id),
+        address: Some(address),
+        bytes,
+    })
+}
+
+fn type_name(debug_data: &DwarfData, value: &Value) -> String {
+    match value.type_id {
+        Some(id) => debug_data.type_name(id),
+        None => "long".to_string(),
+    }
+}
+
+/// Reads an integer, character, boolean or pointer value as a number.
+pub fn to_integer(debug_data: &DwarfData, value: &Value) -> Result<i64, EvalError> {
+    let kind = match value.type_id {
+        None => return Ok(i64::from_le_bytes(pad_word(&value.bytes))),
+        Some(id) => debug_data.resolve_type(id).map(|ty| &ty.kind),
+    };
+    match kind {
+        Some(TypeKind::Base(encoding)) if *encoding != ATE_FLOAT && value.bytes.len() <= 8 => {
+            Ok(sign_extend(*encoding, &value.bytes))
+        }
+        Some(TypeKind::Pointer(_)) => Ok(i64::from_le_bytes(pad_word(&value.bytes))),
+        Some(TypeKind::Enum(enumerators)) => Ok(enum_value(enumerators, &value.bytes)),
+        _ => Err(EvalError::NotAnInteger(type_name(debug_data, value))),
+    }
+}
+
+fn member_of<C: Context>(ctx: &C, base: &Value, name: &str) -> Result<Value, EvalError> {
+    let debug_data = ctx.debug_data();
+    let members = match base.type_id.and_then(|id| debug_data.resolve_type(id)) {
+        Some(Type {
+            kind: TypeKind::Struct(members),
+            ..
+        })
+        | Some(Type {
+            kind: TypeKind::Union(members),
+            ..
+        }) => members,
+        _ => return Err(EvalError::NotAStruct(type_name(debug_data, base))),
+    };
+    let member = members
+        .iter()
+        .find(|member| member.name == name)
+        .ok_or_else(|| EvalError::NoMember(type_name(debug_data, base), name.to_string()))?;
+    let address = base.address.map(|addr| addr + member.offset);
+    let bytes = member_bytes(debug_data, member, &base.bytes)
+        .ok_or(EvalError::Memory(address.unwrap_or(0)))?;
+    Ok(Value {
+        type_id: Some(member.type_id),
+        // A bit-field has no address of its own
+        address: address.filter(|_| member.bit_size.is_none()),
+        bytes,
+    })
+}
+
+/// The bytes of `member` within the bytes of its struct. A bit-field's bits are shifted down and
+/// widened (sign-extended if its type is signed) to the size of its type.
+fn member_bytes(debug_data: &DwarfData, member: &Member, bytes: &[u8]) -> Option<Vec<u8>> {
+    let size = debug_data.type_size(member.type_id);
+    let bit_size = match member.bit_size {
+        Some(bit_size) => bit_size.min(64),
+        None => return bytes.get(member.offset..member.offset + size).map(<[u8]>::to_vec),
+    };
+    let end = bytes.len().min(member.offset + 8);
+    let word = u64::from_le_bytes(pad_word(bytes.get(member.offset..end)?));
+    let mask = if bit_size == 64 {
+        u64::MAX
+    } else {
+        (1 << bit_size) - 1
+    };
+    let mut field = (word >> member.bit_offset) & mask;
+    let signed = match debug_data.resolve_type(member.type_id).map(|ty| &ty.kind) {
+        Some(TypeKind::Base(encoding)) => *encoding == ATE_SIGNED || *encoding == ATE_SIGNED_CHAR,
+        _ => false,
+    };
+    if signed && bit_size > 0 && field >> (bit_size - 1) & 1 == 1 {
+        field |= !mask;
+    }
+    Some(field.to_le_bytes()[..size.min(8)].to_vec())
+}
+
+/// An enum's value as a number: signed if any of its enumerators is negative.
+fn enum_value(enumerators: &[(String, i64)], bytes: &[u8]) -> i64 {
+    let encoding = if enumerators.iter().any(|(_, value)| *value < 0) {
+        ATE_SIGNED
+    } else {
+        ATE_UNSIGNED
+    };
+    sign_extend(encoding, &bytes[..bytes.len().min(8)])
+}
+
+/// Element `index` of an array, or of the memory a pointer points to.
+fn element_of<C: Context>(
+    ctx: &C,
+    base: &Value,
+    index: i64,
+    warnings: &mut Vec<String>,
+) -> Result<Value, EvalError> {
+    let debug_data = ctx.debug_data();
+    let (start, element, count) = match base.type_id.and_then(|id| debug_data.resolve_type(id)) {
+        Some(Type {
+            kind: TypeKind::Array(Some(element), count),
+            ..
+        }) => match base.address {
+            Some(address) => (address, *element, *count),
+            None => return Err(EvalError::NotIndexable(type_name(debug_data, base))),
+        },
+        Some(Type {
+            kind: TypeKind::Pointer(Some(target)),
+            ..
+        }) => (to_integer(debug_data, base)? as usize, *target, None),
+        Some(Type {
+            kind: TypeKind::Pointer(None),
+            ..
+        }) => return Err(EvalError::VoidPointer),
+        _ => return Err(EvalError::NotIndexable(type_name(debug_data, base))),
+    };
+    if let Some(count) = count {
+        if index < 0 || index as usize >= count {
+            warnings.push(format!(
+                "Index {} is outside the bounds of {} (0 to {}).",
+                index,
+                type_name(debug_data, base),
+                count as i64 - 1
+            ));
+        }
+    }
+    let size = debug_data.type_size(element) as i64;
+    let address = (start as i64).wrapping_add(index.wrapping_mul(size)) as usize;
+    read_value(ctx, address, element)
+}
+
+/// Renders a value the way `print` shows it: numbers in decimal, pointers in hex after their
+/// type (`(int *) 0x...`), enums by name, structs as `{field = value, ...}`. Computed integers
+/// (from literals, registers and arithmetic) are shown in both decimal and hex. Any other
+/// `format` than `Natural` overrides this for scalars.
+pub fn format_value<C: Context>(ctx: &C, value: &Value, format: Format) -> String {
+    match value.type_id {
+        Some(type_id) => format_bytes(ctx, type_id, &value.bytes, format, 0),
+        None if format == Format::Natural => {
+            let number = i64::from_le_bytes(pad_word(&value.bytes));
+            format!("{} ({:#x})", number, number)
+        }
+        None => format_scalar(format, &value.bytes),
+    }
+}
+
+fn format_bytes<C: Context>(
+    ctx: &C,
+    type_id: TypeId,
+    bytes: &[u8],
+    format: Format,
+    depth: usize,
+) -> String {
+    let debug_data = ctx.debug_data();
+    let ty = match debug_data.resolve_type(type_id) {
+        Some(ty) => ty,
+        None => return "<unknown type>".to_string(),
+    };
+    match &ty.kind {
+        TypeKind::Base(_) | TypeKind::Pointer(_) if format != Format::Natural => {
+            format_scalar(format, bytes)
+        }
+        TypeKind::Base(encoding) => format_base(*encoding, bytes),
+        TypeKind::Enum(enumerators) if format == Format::Natural => {
+            format_enum(enumerators, enum_value(enumerators, bytes))
+        }
+        TypeKind::Enum(_) => format_scalar(format, bytes),
+        TypeKind::Pointer(_) => {
+            let addr = u64::from_le_bytes(pad_word(bytes)) as usize;
+            // Named at the top level only, as gdb does; a struct's pointers are just addresses
+            if depth == 0 {
+                format!("({}) {:#x}", debug_data.type_name(type_id), addr)
+            } else {
+                format!("{:#x}", addr)
+            }
+        }
+        TypeKind::Struct(members) | TypeKind::Union(members) => {
+            if depth >= MAX_DEPTH {
+                return "{...}".to_string();
+            }
+            let fields: Vec<String> = members
+                .iter()
+                .map(|member| {
+                    let value = match member_bytes(debug_data, member, bytes) {
+                        Some(member_bytes) => {
+                            format_bytes(ctx, member.type_id, &member_bytes, format, depth + 1)
+                        }
+                        None => "<unavailable>".to_string(),
+                    };
+                    format!("{} = {}", member.name, value)
+                })
+                .collect();
+            format!("{{{}}}", fields.join(", "))
+        }
+        TypeKind::Array(Some(element), _) => {
+            let element_size = debug_data.type_size(*element);
+            if element_size == 0 {
+                return "{}".to_string();
+            }
+            let elements: Vec<String> = bytes
+                .chunks(element_size)
+                .map(|chunk| format_bytes(ctx, *element, chunk, format, depth + 1))
+                .collect();
+            format!("{{{}}}", elements.join(", "))
+        }
+        _ => "<unknown type>".to_string(),
+    }
+}
+
+/// An enum's value by the name of its enumerator. With none of that value, an enum whose
+/// enumerators are all single bits is taken for a set of flags, shown as `(READ | WRITE)` (with
+/// any bits left over as `unknown: 0x8`); anything else is shown as `(unknown: 7)`.
+fn format_enum(enumerators: &[(String, i64)], value: i64) -> String {
+    if let Some((name, _)) = enumerators.iter().find(|(_, known)| *known == value) {
+        return name.clone();
+    }
+    let flags = enumerators
+        .iter()
+        .all(|(_, flag)| *flag >= 0 && (*flag as u64).count_ones() <= 1);
+    if flags && value > 0 {
+        let mut rest = value as u64;
+        let mut names = Vec::new();
+        for (name, flag) in enumerators {
+            let flag = *flag as u64;
+            if flag != 0 && rest & flag == flag {
+                names.push(name.clone());
+                rest &= !flag;
+            }
+        }
+        if !names.is_empty() {
+            if rest != 0 {
+                names.push(format!("unknown: {:#x}", rest));
+            }
+            return format!("({})", names.join(" | "));
+        }
+    }
+    format!("(unknown: {})", value)
+}
+
+/// DW_ATE_* encodings of base types.
+const ATE_BOOLEAN: u64 = 0x02;
+const ATE_FLOAT: u64 = 0x04;
+const ATE_SIGNED: u64 = 0x05;
+const ATE_SIGNED_CHAR: u64 = 0x06;
+const ATE_UNSIGNED: u64 = 0x07;
+const ATE_UNSIGNED_CHAR: u64 = 0x08;
+
+fn format_base(encoding: u64, bytes: &[u8]) -> String {
+    match (encoding, bytes.len()) {
+        (ATE_BOOLEAN, _) => (bytes.iter().any(|b| *b != 0)).to_string(),
+        (ATE_FLOAT, 4) => f32::from_le_bytes(bytes.try_into().unwrap()).to_string(),
+        (ATE_FLOAT, 8) => f64::from_le_bytes(bytes.try_into().unwrap()).to_string(),
+        (ATE_SIGNED_CHAR, 1) | (ATE_UNSIGNED_CHAR, 1) => {
+            let c = bytes[0];
+            let number = if encoding == ATE_SIGNED_CHAR {
+                (c as i8).to_string()
+            } else {
+                c.to_string()
+            };
+            format!("{} {}", number, char_literal(c))
+        }
+        (ATE_SIGNED, 1..=8) | (ATE_SIGNED_CHAR, 1..=8) => sign_extend(encoding, bytes).to_string(),
+        (_, 1..=8) => u64::from_le_bytes(pad_word(bytes)).to_string(),
+        _ => format!("<{} bytes>", bytes.len()),
+    }
+}
+
+/// Shows the raw bits of a scalar of up to 8 bytes in an explicit format.
+fn format_scalar(format: Format, bytes: &[u8]) -> String {
+    let bytes = &bytes[..bytes.len().min(8)];
+    let bits = u64::from_le_bytes(pad_word(bytes));
+    match format {
+        Format::Hex => format!("{:#x}", bits),
+        Format::Octal => format!("{:#o}", bits),
+        Format::Binary => format!("{:b}", bits),
+        Format::Unsigned => bits.to_string(),
+        Format::Decimal | Format::Natural => sign_extend(ATE_SIGNED, bytes).to_string(),
+        Format::Char => format!("{} {}", bits as u8 as i8, char_literal(bits as u8)),
+    }
+}
+
+/// Widens a little-endian integer of up to 8 bytes, sign-extending it if the encoding is signed.
+fn sign_extend(encoding: u64, bytes: &[u8]) -> i64 {
+    let word = u64::from_le_bytes(pad_word(bytes));
+    if (encoding == ATE_SIGNED || encoding == ATE_SIGNED_CHAR) && !bytes.is_empty() {
+        let shift = 64 - 8 * bytes.len() as u32;
+        (word << shift) as i64 >> shift
+    } else {
+        word as i64
+    }
+}
+
+fn pad_word(bytes: &[u8]) -> [u8; 8] {
+    let mut word = [0u8; 8];
+    let len = bytes.len().min(8);
+    word[..len].copy_from_slice(&bytes[..len]);
+    word
+}
+
+/// Quotes a byte as a C character literal, e.g. `'a'` or `'\n'`.
+fn char_literal(c: u8) -> String {
+    match c {
+        b'\n' => "'\\n'".to_string(),
+        b'\t' => "'\\t'".to_string(),
+        b'\r' => "'\\r'".to_string(),
+        b'\'' => "'\\''".to_string(),
+        b'\\' => "'\\\\'".to_string(),
+        0x20..=0x7e => format!("'{}'", c as char),
+        _ => format!("'\\{:03o}'", c),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stopped with rsp = 0x1000 and rbp = 0x1040, and an int `count` of 21 in a register.
+    struct MockContext {
+        debug_data: DwarfData,
+    }
+
+    impl Context for MockContext {
+        fn debug_data(&self) -> &DwarfData {
+            &self.debug_data
+        }
+
+        fn variable(&self, name: &str) -> Option<(Storage, TypeId)> {
+            match name {
+                "count" => Some((
+                    Storage::Value(21i32.to_le_bytes().to_vec()),
+                    self.debug_data.find_type("int")?,
+                )),
+                _ => None,
+            }
+        }
+
+        fn register(&self, name: &str) -> Option<u64> {
+            match name {
+                "rsp" => Some(0x1000),
+                "rbp" => Some(0x1040),
+                _ => None,
+            }
+        }
+
+        fn vector_register(&self, _name: &str) -> Option<[u8; 16]> {
+            None
+        }
+
+        fn history(&self, _number: Option<usize>) -> Option<Value> {
+            None
+        }
+
+        fn read_memory(&self, _addr: usize, _len: usize) -> Option<Vec<u8>> {
+            None
+        }
+    }
+
+    fn context() -> MockContext {
+        let path = format!("{}/samples/function_calls", env!("CARGO_MANIFEST_DIR"));
+        MockContext {
+            debug_data: DwarfData::from_file(&path).unwrap(),
+        }
+    }
+
+    fn eval(ctx: &MockContext, text: &str) -> Result<i64, EvalError> {
+        let value = evaluate(&parse(text)?, ctx, &mut Vec::new())?;
+        to_integer(&ctx.debug_data, &value)
+    }
+
+    fn syntax_error(text: &str) -> String {
+        match parse(text) {
+            Err(EvalError::Syntax(msg)) => msg,
+            other => panic!("expected {:?} not to parse, got {:?}", text, other),
+        }
+    }
+
+    #[test]
+    fn tokens() {
+        assert_eq!(
+            tokenize("$rsp-0x10*(a->b.c[$1])/$").unwrap(),
+            vec![
+                Token::Register("rsp".to_string()),
+                Token::Minus,
+                Token::Integer(0x10),
+                Token::Star,
+                Token::LeftParen,
+                Token::Ident("a".to_string()),
+                Token::Arrow,
+                Token::Ident("b".to_string()),
+                Token::Dot,
+                Token::Ident("c".to_string()),
+                Token::LeftBracket,
+                Token::History(Some(1)),
+                Token::RightBracket,
+                Token::RightParen,
+                Token::Slash,
+                Token::History(None),
+            ]
+        );
+        assert_eq!(tokenize("  \t").unwrap(), vec![]);
+        assert_eq!(syntax_error("a @ b"), "unexpected character '@'");
+    }
+
+    #[test]
+    fn number_literals() {
+        let ctx = context();
+        assert_eq!(eval(&ctx, "42").unwrap(), 42);
+        assert_eq!(eval(&ctx, "0x2a").unwrap(), 42);
+        assert_eq!(eval(&ctx, "0X2A").unwrap(), 42);
+        assert_eq!(eval(&ctx, "0x7fffffffffffffff").unwrap(), i64::max_value());
+        assert_eq!(syntax_error("0x"), "invalid number \"0x\"");
+        assert_eq!(syntax_error("0xfg"), "invalid number \"0xfg\"");
+        assert_eq!(syntax_error("12ab"), "invalid number \"12ab\"");
+        assert_eq!(syntax_error("0x10000000000000000"), "invalid number \"0x10000000000000000\"");
+    }
+
+    #[test]
+    fn precedence() {
+        let ctx = context();
+        assert_eq!(eval(&ctx, "2 + 3 * 4").unwrap(), 14);
+        assert_eq!(eval(&ctx, "(2 + 3) * 4").unwrap(), 20);
+        assert_eq!(eval(&ctx, "2 * 3 + 4").unwrap(), 10);
+        // Left to right at the same level
+        assert_eq!(eval(&ctx, "10 - 4 - 3").unwrap(), 3);
+        assert_eq!(eval(&ctx, "100 / 5 / 2").unwrap(), 10);
+        assert_eq!(eval(&ctx, "12 / 4 * 3").unwrap(), 9);
+        // Negation binds tighter than either
+        assert_eq!(eval(&ctx, "-2 * 3").unwrap(), -6);
+        assert_eq!(eval(&ctx, "2 - -3").unwrap(), 5);
+        assert_eq!(eval(&ctx, "-(2 + 3)").unwrap(), -5);
+        assert_eq!(eval(&ctx, "((1))").unwrap(), 1);
+        // Integer division truncates toward zero, as in C
+        assert_eq!(eval(&ctx, "-7 / 2").unwrap(), -3);
+    }
+
+    #[test]
+    fn comparisons_and_logic() {
+        let ctx = context();
+        let ident = |name: &str| Token::Ident(name.to_string());
+        assert_eq!(
+            tokenize("a<=b!=c&&!d||a>=b<c>d==a").unwrap(),
+            vec![
+                ident("a"),
+                Token::LessOrEqual,
+                ident("b"),
+                Token::NotEqual,
+                ident("c"),
+                Token::And,
+                Token::Not,
+                ident("d"),
+                Token::Or,
+                ident("a"),
+                Token::GreaterOrEqual,
+                ident("b"),
+                Token::Less,
+                ident("c"),
+                Token::Greater,
+                ident("d"),
+                Token::Equal,
+                ident("a"),
+            ]
+        );
+        assert_eq!(eval(&ctx, "count == 21").unwrap(), 1);
+        assert_eq!(eval(&ctx, "count != 21").unwrap(), 0);
+        assert_eq!(eval(&ctx, "count < 21").unwrap(), 0);
+        assert_eq!(eval(&ctx, "count <= 21").unwrap(), 1);
+        assert_eq!(eval(&ctx, "-1 < 0").unwrap(), 1);
+        assert_eq!(eval(&ctx, "!count").unwrap(), 0);
+        assert_eq!(eval(&ctx, "!!count").unwrap(), 1);
+        // Arithmetic binds tighter than comparison, which binds tighter than && and ||
+        assert_eq!(eval(&ctx, "count * 2 > 40 && $rsp < $rbp").unwrap(), 1);
+        assert_eq!(eval(&ctx, "1 || 0 && 0").unwrap(), 1);
+        assert_eq!(eval(&ctx, "1 < 2 == 1").unwrap(), 1);
+        // The right side isn't evaluated once the left decides
+        assert_eq!(eval(&ctx, "count == 0 && $nope").unwrap(), 0);
+        assert_eq!(eval(&ctx, "count || $nope").unwrap(), 1);
+        assert!(eval(&ctx, "count && $nope").is_err());
+        assert_eq!(syntax_error("count & 1"), "unexpected character '&'");
+        assert_eq!(syntax_error("count = 1"), "unexpected character '='");
+    }
+
+    #[test]
+    fn registers_and_variables() {
+        let ctx = context();
+        assert_eq!(eval(&ctx, "$rsp - $rbp").unwrap(), -0x40);
+        assert_eq!(eval(&ctx, "(count * 8) + 16").unwrap(), 184);
+        match eval(&ctx, "$nope") {
+            Err(EvalError::UnknownRegister(name)) => assert_eq!(name, "nope"),
+            other => panic!("expected an unknown register, got {:?}", other),
+        }
+        match eval(&ctx, "missing + 1") {
+            Err(EvalError::UnknownVariable(name)) => assert_eq!(name, "missing"),
+            other => panic!("expected an unknown variable, got {:?}", other),
+        }
+        match eval(&ctx, "$") {
+            Err(EvalError::NoHistory(None)) => {}
+            other => panic!("expected an empty history, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn division_by_zero() {
+        let ctx = context();
+        for text in ["1 / 0", "count / (2 - 2)", "0 / 0"].iter() {
+            match eval(&ctx, text) {
+                Err(EvalError::DivisionByZero) => {}
+                other => panic!("expected {:?} to divide by zero, got {:?}", text, other),
+            }
+        }
+        // Overflow wraps rather than panicking
+        assert_eq!(eval(&ctx, "(-0x7fffffffffffffff - 1) / -1").unwrap(), i64::min_value());
+    }
+
+    fn enumerators(values: &[(&str, i64)]) -> Vec<(String, i64)> {
+        values.iter().map(|(name, value)| (name.to_string(), *value)).collect()
+    }
+
+    #[test]
+    fn enums() {
+        let states =
+            enumerators(&[("IDLE", 0), ("CONNECTING", 1), ("CONNECTED", 2), ("CLOSED", 3)]);
+        assert_eq!(format_enum(&states, 0), "IDLE");
+        assert_eq!(format_enum(&states, 2), "CONNECTED");
+        // Not a flag enum (3 is two bits), so no decomposing
+        assert_eq!(format_enum(&states, 7), "(unknown: 7)");
+        assert_eq!(format_enum(&states, -1), "(unknown: -1)");
+        // Values are signed only if an enumerator is negative
+        let bytes = (-1i32).to_le_bytes();
+        assert_eq!(enum_value(&states, &bytes), 0xffff_ffff);
+        let signed = enumerators(&[("LOW", -1), ("HIGH", 1)]);
+        assert_eq!(enum_value(&signed, &bytes), -1);
+        assert_eq!(format_enum(&signed, enum_value(&signed, &bytes)), "LOW");
+    }
+
+    #[test]
+    fn flag_enums() {
+        let flags = enumerators(&[("NONE", 0), ("READ", 1), ("WRITE", 2), ("EXEC", 4)]);
+        assert_eq!(format_enum(&flags, 0), "NONE");
+        assert_eq!(format_enum(&flags, 4), "EXEC");
+        assert_eq!(format_enum(&flags, 3), "(READ | WRITE)");
+        assert_eq!(format_enum(&flags, 7), "(READ | WRITE | EXEC)");
+        // Bits no flag stands for are shown together
+        assert_eq!(format_enum(&flags, 0x35), "(READ | EXEC | unknown: 0x30)");
+        // Without any known flag in it, the value is unknown
+        assert_eq!(format_enum(&flags, 8), "(unknown: 8)");
+        assert_eq!(format_enum(&flags, -4), "(unknown: -4)");
+    }
+
+    #[test]
+    fn syntax_errors() {
+        assert_eq!(syntax_error(""), "empty expression");
+        assert_eq!(syntax_error("(1 + 2"), "expected )");
+        assert_eq!(syntax_error("a[1"), "expected ]");
+        assert_eq!(syntax_error("1 +"), "unexpected end of expression");
+        assert_eq!(syntax_error("1 2"), "unexpected text after expression");
+        assert_eq!(syntax_error("1 + )"), "expected a variable name or number");
+        assert_eq!(syntax_error("a."), "expected a member name");
+    }
+}
@@ -0,0 +1,368 @@
+//! `--gdbserver`: instead of the prompt, a stub that a stock gdb (or an IDE) connects to over TCP
+//! with `target remote`, driving the inferior through the remote serial protocol. Enough of the
+//! protocol for breakpoints, stepping, ctrl+c, registers and memory; anything else gets the empty
+//! reply that tells the client it isn't supported.
+
+use crate::background::Watcher;
+use crate::inferior::{
+    Environment, Inferior, InferiorError, PtraceError, Redirects, Status, StopReason,
+};
+use crate::rsp::{self, Connection, Incoming};
+use nix::sys::signal::Signal;
+use std::fmt;
+use std::io;
+use std::net::TcpListener;
+use std::time::Duration;
+
+/// The largest packet we accept, in bytes, as told to the client in reply to qSupported.
+const PACKET_SIZE: usize = 0x4000;
+/// How often the connection is checked for a ctrl+c while the inferior runs.
+const INTERRUPT_POLL: Duration = Duration::from_millis(50);
+/// GDB's amd64 numbers for the registers sent along with a stop: rbp, rsp and rip.
+const EXPEDITED_REGISTERS: [u8; 3] = [6, 7, 16];
+
+#[derive(Debug)]
+pub enum ServerError {
+    /// Listening, accepting or talking to the client failed
+    Io(io::Error),
+    /// The target could not be started
+    Inferior(InferiorError),
+    /// Controlling the inferior failed
+    Ptrace(PtraceError),
+}
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServerError::Io(err) => write!(f, "Remote connection failed: {}", err),
+            ServerError::Inferior(err) => write!(f, "{}", err),
+            ServerError::Ptrace(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<io::Error> for ServerError {
+    fn from(err: io::Error) -> Self {
+        ServerError::Io(err)
+    }
+}
+
+impl From<PtraceError> for ServerError {
+    fn from(err: PtraceError) -> Self {
+        ServerError::Ptrace(err)
+    }
+}
+
+/// Starts `target` with `args`, stopped at its first instruction, and serves one client on
+/// `address`. An address without a host (`:1234`) listens on every interface, as gdbserver's
+/// does. The inferior is killed once the client goes, unless it has already ended.
+pub fn serve(address: &str, target: &str, args: &[String]) -> Result<(), ServerError> {
+    let address = if address.starts_with(':') {
+        format!("0.0.0.0{}", address)
+    } else {
+        address.to_string()
+    };
+    let cwd = std::env::current_dir()?;
+    let inferior = Inferior::new(
+        target,
+        args,
+        &Environment::default(),
+        Redirects::default(),
+        false,
+        &cwd,
+        true,
+    )
+    .map_err(ServerError::Inferior)?;
+    report!("Process {} created; pid = {}", target, inferior.pid());
+    let listener = TcpListener::bind(&address)?;
+    report!("Listening on {}", listener.local_addr()?);
+    let (stream, peer) = listener.accept()?;
+    report!("Remote debugging from host {}", peer.ip());
+
+    let rip = inferior.getregs()?.rip as usize;
+    let status = Status::Stopped(Signal::SIGTRAP, rip, StopReason::Trap);
+    let mut server = Server {
+        inferior,
+        status,
+        done: false,
+    };
+    let mut connection = Connection::new(stream)?;
+    let served = server.serve(&mut connection);
+    if server.is_alive() {
+        server.inferior.kill();
+    }
+    served
+}
+
+struct Server {
+    inferior: Inferior,
+    /// How the inferior last stopped, or how it ended
+    status: Status,
+    /// Set once the client has asked for the inferior to be killed
+    done: bool,
+}
+
+impl Server {
+    fn serve(&mut self, connection: &mut Connection) -> Result<(), ServerError> {
+        while !self.done {
+            let packet = match connection.read()? {
+                Some(Incoming::Packet(packet)) => packet,
+                // A ctrl+c while the inferior runs is picked up by `cont`; one that arrives after
+                // it has stopped has nothing left to interrupt
+                Some(Incoming::Interrupt) => continue,
+                None => break,
+            };
+            if packet == b"QStartNoAckMode" {
+                connection.write(b"OK")?;
+                connection.set_no_ack();
+                continue;
+            }
+            if packet == b"k" {
+                // Needs no reply
+                self.kill();
+                break;
+            }
+            let reply = self.handle(&packet, connection)?;
+            connection.write(reply.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// The reply to one packet. Failures the client should hear about are replied with `E01`;
+    /// only losing track of the inferior altogether is an error.
+    fn handle(
+        &mut self,
+        packet: &[u8],
+        connection: &mut Connection,
+    ) -> Result<String, ServerError> {
+        let (command, rest) = match packet.split_first() {
+            Some((&command, rest)) => (command, rest),
+            None => return Ok(String::new()),
+        };
+        if packet.starts_with(b"qSupported") {
+            return Ok(format!("PacketSize={:x};QStartNoAckMode+", PACKET_SIZE));
+        }
+        if packet.starts_with(b"vKill") {
+            self.kill();
+            return Ok("OK".to_string());
+        }
+        if command == b'?' {
+            return Ok(self.stop_reply());
+        }
+        // The rest need a live process
+        let needs_process = b"gGmMZzcsH".contains(&command);
+        if needs_process && !self.is_alive() {
+            return Ok("E01".to_string());
+        }
+        Ok(match command {
+            // There's only the one thread to select
+            b'H' => "OK".to_string(),
+            b'g' => rsp::to_hex(&registers(&self.inferior.getregs()?)),
+            b'G' => {
+                let mut regs = self.inferior.getregs()?;
+                match rsp::from_hex(rest) {
+                    Some(bytes) if set_registers(&mut regs, &bytes) => {
+                        ok_or_error(self.inferior.setregs(regs))
+                    }
+                    _ => "E01".to_string(),
+                }
+            }
+            // A reply may be shorter than asked for, so one that wouldn't fit in a packet is cut
+            // short rather than refused
+            b'm' => match parse_range(rest) {
+                Some((addr, len, _)) => {
+                    let len = len.min(PACKET_SIZE / 2);
+                    match addr.checked_add(len) {
+                        Some(_) => match self.inferior.read_bytes(addr, len) {
+                            Ok(bytes) => rsp::to_hex(&bytes),
+                            Err(_) => "E01".to_string(),
+                        },
+                        None => "E01".to_string(),
+                    }
+                }
+                None => "E01".to_string(),
+            },
+            b'M' => match parse_range(rest).and_then(|(addr, len, data)| {
+                addr.checked_add(len)?;
+                rsp::from_hex(data?)
+                    .filter(|bytes| bytes.len() == len)
+                    .map(|bytes| (addr, bytes))
+            }) {
+                Some((addr, bytes)) => ok_or_error(self.inferior.write_bytes(addr, &bytes)),
+                None => "E01".to_string(),
+            },
+            // Software breakpoints only; the kind (the instruction length) is always 1 here
+            b'Z' | b'z' if rest.starts_with(b"0,") => match parse_range(&rest[2..]) {
+                Some((addr, _, _)) if command == b'Z' => match self.inferior.arm(addr) {
+                    Ok(()) => "OK".to_string(),
+                    Err(_) => "E01".to_string(),
+                },
+                Some((addr, _, _)) => ok_or_error(self.inferior.disarm(addr)),
+                None => "E01".to_string(),
+            },
+            // Resuming at another address isn't supported
+            b'c' | b's' if !rest.is_empty() => "E01".to_string(),
+            b'c' => {
+                self.status = self.cont(connection)?;
+                self.stop_reply()
+            }
+            b's' => {
+                self.status = self.inferior.step_instruction()?;
+                self.stop_reply()
+            }
+            _ => String::new(),
+        })
+    }
+
+    /// Continues the inferior until it stops or exits. Meanwhile the connection is watched for a
+    /// ctrl+c, which stops the inferior with SIGINT.
+    fn cont(&mut self, connection: &mut Connection) -> Result<Status, ServerError> {
+        self.inferior.in_foreground(|inferior| {
+            if let Some(status) = inferior.resume()? {
+                return Ok(status);
+            }
+            let watcher = Watcher::start(inferior.pid(), None);
+            while !watcher.has_stopped() {
+                if connection.interrupted(INTERRUPT_POLL)? {
+                    inferior.interrupt()?;
+                }
+            }
+            watcher.wait();
+            Ok(inferior.wait_running()?)
+        })
+    }
+
+    /// `T` with the signal the inferior is stopped by, its thread and the registers GDB needs
+    /// first (saving it asking for them all), or `W` with its exit code, or `X` with the signal
+    /// that killed it.
+    fn stop_reply(&self) -> String {
+        match self.status {
+            Status::Stopped(signal, _, _) => {
+                let mut reply = format!("T{:02x}", gdb_signal(signal));
+                if let Ok(regs) = self.inferior.getregs() {
+                    let values = [regs.rbp, regs.rsp, regs.rip];
+                    for (number, value) in EXPEDITED_REGISTERS.iter().zip(values.iter()) {
+                        let value = rsp::to_hex(&value.to_le_bytes());
+                        reply.push_str(&format!("{:02x}:{};", number, value));
+                    }
+                }
+                reply.push_str(&format!("thread:{:x};", self.inferior.pid().as_raw()));
+                reply
+            }
+            Status::Exited(code) => format!("W{:02x}", code as u8),
+            Status::Signaled(signal) => format!("X{:02x}", gdb_signal(signal)),
+        }
+    }
+
+    fn is_alive(&self) -> bool {
+        !self.done
+            && match self.status {
+                Status::Stopped(..) => true,
+                Status::Exited(_) | Status::Signaled(_) => false,
+            }
+    }
+
+    fn kill(&mut self) {
+        if self.is_alive() {
+            self.inferior.kill();
+            self.status = Status::Signaled(Signal::SIGKILL);
+        }
+        self.done = true;
+    }
+}
+
+/// GDB's number for a signal. It numbers them as the oldest Unixes did, which agrees with Linux
+/// for the common ones but not for the rest.
+fn gdb_signal(signal: Signal) -> u8 {
+    match signal {
+        Signal::SIGBUS => 10,
+        Signal::SIGUSR1 => 30,
+        Signal::SIGUSR2 => 31,
+        Signal::SIGCHLD => 20,
+        Signal::SIGCONT => 19,
+        Signal::SIGSTOP => 17,
+        Signal::SIGTSTP => 18,
+        Signal::SIGURG => 16,
+        Signal::SIGIO => 23,
+        Signal::SIGSYS => 12,
+        Signal::SIGWINCH => 28,
+        Signal::SIGPROF => 27,
+        Signal::SIGVTALRM => 26,
+        Signal::SIGXCPU => 24,
+        Signal::SIGXFSZ => 25,
+        Signal::SIGTTIN => 21,
+        Signal::SIGTTOU => 22,
+        Signal::SIGPWR => 32,
+        Signal::SIGSTKFLT => 143,
+        other => other as u8,
+    }
+}
+
+fn ok_or_error(result: Result<(), PtraceError>) -> String {
+    match result {
+        Ok(()) => "OK".to_string(),
+        Err(_) => "E01".to_string(),
+    }
+}
+
+/// The `addr,length` of m, M, Z and z, in hex, and for M what follows the `:`.
+fn parse_range(text: &[u8]) -> Option<(usize, usize, Option<&[u8]>)> {
+    let text = std::str::from_utf8(text).ok()?;
+    let (range, data) = match text.find(':') {
+        Some(colon) => (&text[..colon], Some(text[colon + 1..].as_bytes())),
+        None => (text, None),
+    };
+    let comma = range.find(',')?;
+    let addr = usize::from_str_radix(&range[..comma], 16).ok()?;
+    let len = usize::from_str_radix(&range[comma + 1..], 16).ok()?;
+    Some((addr, len, data))
+}
+
+/// The general registers in the order of GDB's amd64 layout, without a target description: the
+/// sixteen general purpose registers and rip as 8 bytes each, then eflags and the segment
+/// registers as 4. The floating point registers that follow are left off, which GDB takes as
+/// unavailable.
+fn registers(regs: &libc::user_regs_struct) -> Vec<u8> {
+    let wide = [
+        regs.rax, regs.rbx, regs.rcx, regs.rdx, regs.rsi, regs.rdi, regs.rbp, regs.rsp, regs.r8,
+        regs.r9, regs.r10, regs.r11, regs.r12, regs.r13, regs.r14, regs.r15, regs.rip,
+    ];
+    let narrow = [regs.eflags, regs.cs, regs.ss, regs.ds, regs.es, regs.fs, regs.gs];
+    let mut bytes = Vec::with_capacity(wide.len() * 8 + narrow.len() * 4);
+    for value in wide.iter() {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    for value in narrow.iter() {
+        bytes.extend_from_slice(&(*value as u32).to_le_bytes());
+    }
+    bytes
+}
+
+/// Overwrites the registers `registers` describes with `bytes` from a G packet. Returns false if
+/// it's too short to hold them.
+fn set_registers(regs: &mut libc::user_regs_struct, bytes: &[u8]) -> bool {
+    let mut wide = [
+        &mut regs.rax, &mut regs.rbx, &mut regs.rcx, &mut regs.rdx, &mut regs.rsi, &mut regs.rdi,
+        &mut regs.rbp, &mut regs.rsp, &mut regs.r8, &mut regs.r9, &mut regs.r10, &mut regs.r11,
+        &mut regs.r12, &mut regs.r13, &mut regs.r14, &mut regs.r15, &mut regs.rip,
+    ];
+    let mut narrow = [
+        &mut regs.eflags, &mut regs.cs, &mut regs.ss, &mut regs.ds, &mut regs.es, &mut regs.fs,
+        &mut regs.gs,
+    ];
+    if bytes.len() < wide.len() * 8 + narrow.len() * 4 {
+        return false;
+    }
+    let (wide_bytes, narrow_bytes) = bytes.split_at(wide.len() * 8);
+    for (register, value) in wide.iter_mut().zip(wide_bytes.chunks(8)) {
+        let mut word = [0u8; 8];
+        word.copy_from_slice(value);
+        **register = u64::from_le_bytes(word);
+    }
+    for (register, value) in narrow.iter_mut().zip(narrow_bytes.chunks(4)) {
+        let mut word = [0u8; 4];
+        word.copy_from_slice(value);
+        **register = u32::from_le_bytes(word) as u64;
+    }
+    true
+}
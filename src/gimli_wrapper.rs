@@ -7,21 +7,31 @@
 
 use gimli;
 use gimli::{UnitOffset, UnitSectionOffset};
-use object::Object;
+use object::{Object, ObjectSection};
 use std::borrow;
 //use std::io::{BufWriter, Write};
-use crate::dwarf_data::{File, Function, Line, Location, Type, Variable};
+use crate::dwarf_data::{
+    File, Function, Line, LineRow, Location, Member, Type, TypeId, TypeKind, Variable,
+};
 use std::collections::HashMap;
 use std::convert::TryInto;
 use std::fmt::Write;
 use std::{io, path};
 
-pub fn load_file(object: &object::File, endian: gimli::RunTimeEndian) -> Result<Vec<File>, Error> {
-    // Load a section and return as `Cow<[u8]>`.
-    let load_section = |id: gimli::SectionId| -> Result<borrow::Cow<[u8]>, gimli::Error> {
-        Ok(object
-            .section_data_by_name(id.name())
-            .unwrap_or(borrow::Cow::Borrowed(&[][..])))
+/// Loads the compilation units of an object file, and the types their variables refer to.
+pub fn load_file(
+    object: &object::File,
+    endian: gimli::RunTimeEndian,
+) -> Result<(Vec<File>, HashMap<TypeId, Type>), Error> {
+    // Load a section and return as `Cow<[u8]>`. object finds `.zdebug_*` sections for us, and
+    // decompresses those and SHF_COMPRESSED ones.
+    let load_section = |id: gimli::SectionId| -> Result<borrow::Cow<[u8]>, Error> {
+        match object.section_by_name(id.name()) {
+            Some(section) => section
+                .uncompressed_data()
+                .ok_or_else(|| Error::DecompressionError(id.name().to_string())),
+            None => Ok(borrow::Cow::Borrowed(&[][..])),
+        }
     };
     // Load a supplementary section. We don't have a supplementary object file,
     // so always return an empty slice.
@@ -40,9 +50,15 @@ pub fn load_file(object: &object::File, endian: gimli::RunTimeEndian) -> Result<
     let dwarf = dwarf_cow.borrow(&borrow_section);
 
     // Define a mapping from type offsets to type structs
-    let mut offset_to_type: HashMap<usize, Type> = HashMap::new();
+    let mut offset_to_type: HashMap<TypeId, Type> = HashMap::new();
 
     let mut compilation_units: Vec<File> = Vec::new();
+    // The first compilation unit of each name, which the line rows for that source file go to
+    let mut unit_by_name: HashMap<String, usize> = HashMap::new();
+
+    // Where each subprogram DIE ended up, so that inlined copies and out-of-line instances can
+    // find the function they are an instance of: (compilation unit, function) indices
+    let mut function_offsets: HashMap<usize, (usize, usize)> = HashMap::new();
 
     // Iterate over the compilation units.
     let mut iter = dwarf.units();
@@ -51,9 +67,20 @@ pub fn load_file(object: &object::File, endian: gimli::RunTimeEndian) -> Result<
 
         // Iterate over the Debugging Information Entries (DIEs) in the unit.
         let mut depth = 0;
+        // The struct, union and array types enclosing the current DIE, with their depths
+        let mut aggregates: Vec<(isize, TypeId)> = Vec::new();
+        // The lexical blocks enclosing the current DIE, with their depths and address ranges
+        let mut blocks: Vec<(isize, Option<(usize, usize)>)> = Vec::new();
         let mut entries = unit.entries();
         while let Some((delta_depth, entry)) = entries.next_dfs()? {
             depth += delta_depth;
+            // Leave the aggregate types whose children we are done with
+            while aggregates.last().map_or(false, |&(parent_depth, _)| parent_depth >= depth) {
+                aggregates.pop();
+            }
+            while blocks.last().map_or(false, |&(parent_depth, _)| parent_depth >= depth) {
+                blocks.pop();
+            }
             // Update the offset_to_type mapping for types
             // Update the variable list for formal params/variables
             match entry.tag() {
@@ -67,42 +94,170 @@ pub fn load_file(object: &object::File, endian: gimli::RunTimeEndian) -> Result<
                     } else {
                         "<unknown>".to_string()
                     };
+                    unit_by_name
+                        .entry(name.clone())
+                        .or_insert(compilation_units.len());
                     compilation_units.push(File {
                         name,
                         global_variables: Vec::new(),
                         functions: Vec::new(),
                         lines: Vec::new(),
+                        line_rows: Vec::new(),
+                        source_files: Vec::new(),
+                        comp_dir: unit
+                            .comp_dir
+                            .as_ref()
+                            .and_then(|dir| dir.to_string_lossy().ok())
+                            .map(|dir| dir.into_owned()),
                     });
                 }
-                gimli::DW_TAG_base_type => {
-                    let name = if let Ok(Some(attr)) = entry.attr(gimli::DW_AT_name) {
-                        if let Ok(DebugValue::Str(name)) = get_attr_value(&attr, &unit, &dwarf) {
-                            name
-                        } else {
-                            "<unknown>".to_string()
+                gimli::DW_TAG_base_type
+                | gimli::DW_TAG_pointer_type
+                | gimli::DW_TAG_structure_type
+                | gimli::DW_TAG_union_type
+                | gimli::DW_TAG_enumeration_type
+                | gimli::DW_TAG_array_type
+                | gimli::DW_TAG_typedef
+                | gimli::DW_TAG_const_type
+                | gimli::DW_TAG_volatile_type => {
+                    let mut name = String::new();
+                    let mut byte_size = 0;
+                    let mut encoding = 0;
+                    let mut target = None;
+                    let mut attrs = entry.attrs();
+                    while let Some(attr) = attrs.next()? {
+                        match (attr.name(), get_attr_value(&attr, &unit, &dwarf)) {
+                            (gimli::DW_AT_name, Ok(DebugValue::Str(attr_name))) => name = attr_name,
+                            (gimli::DW_AT_byte_size, Ok(DebugValue::Uint(size))) => byte_size = size,
+                            (gimli::DW_AT_type, Ok(DebugValue::Size(offset))) => target = Some(offset),
+                            (gimli::DW_AT_encoding, _) => {
+                                encoding = match attr.value() {
+                                    gimli::AttributeValue::Encoding(ate) => ate.0.into(),
+                                    value => value.udata_value().unwrap_or(0),
+                                };
+                            }
+                            _ => {}
                         }
-                    } else {
-                        "<unknown>".to_string()
+                    }
+                    let kind = match entry.tag() {
+                        gimli::DW_TAG_base_type => TypeKind::Base(encoding),
+                        gimli::DW_TAG_pointer_type => TypeKind::Pointer(target),
+                        gimli::DW_TAG_structure_type => TypeKind::Struct(Vec::new()),
+                        gimli::DW_TAG_union_type => TypeKind::Union(Vec::new()),
+                        // The enumerators are its DW_TAG_enumerator children
+                        gimli::DW_TAG_enumeration_type => TypeKind::Enum(Vec::new()),
+                        // The element count comes from the DW_TAG_subrange_type child
+                        gimli::DW_TAG_array_type => TypeKind::Array(target, None),
+                        gimli::DW_TAG_const_type => TypeKind::Qualified("const", target),
+                        gimli::DW_TAG_volatile_type => TypeKind::Qualified("volatile", target),
+                        _ => TypeKind::Typedef(target),
                     };
-                    let byte_size = if let Ok(Some(attr)) = entry.attr(gimli::DW_AT_byte_size) {
-                        if let Ok(DebugValue::Uint(byte_size)) =
-                            get_attr_value(&attr, &unit, &dwarf)
-                        {
-                            byte_size
-                        } else {
-                            // TODO: report error?
-                            0
+                    let type_offset = die_offset(entry.offset(), &unit);
+                    offset_to_type.insert(
+                        type_offset,
+                        Type::new(name, byte_size.try_into().unwrap(), kind),
+                    );
+                    aggregates.push((depth, type_offset));
+                }
+                gimli::DW_TAG_member | gimli::DW_TAG_subrange_type => {
+                    // Both describe the aggregate type they are a child of
+                    let parent = match aggregates.last() {
+                        Some(&(parent_depth, offset)) if parent_depth == depth - 1 => offset,
+                        _ => continue,
+                    };
+                    let mut member = Member::default();
+                    let mut count = None;
+                    // A bit-field's position: DWARF 4's DW_AT_data_bit_offset, or DWARF 2's
+                    // DW_AT_bit_offset within a storage unit of DW_AT_byte_size
+                    let mut data_bit_offset = None;
+                    let mut storage_bit_offset = None;
+                    let mut storage_size = None;
+                    let mut attrs = entry.attrs();
+                    while let Some(attr) = attrs.next()? {
+                        match (attr.name(), get_attr_value(&attr, &unit, &dwarf)) {
+                            (gimli::DW_AT_name, Ok(DebugValue::Str(name))) => member.name = name,
+                            (gimli::DW_AT_type, Ok(DebugValue::Size(offset))) => {
+                                member.type_id = offset
+                            }
+                            (gimli::DW_AT_data_member_location, Ok(DebugValue::Uint(offset))) => {
+                                member.offset = offset.try_into().unwrap()
+                            }
+                            (gimli::DW_AT_bit_size, Ok(DebugValue::Uint(bits))) => {
+                                member.bit_size = Some(bits as usize)
+                            }
+                            (gimli::DW_AT_data_bit_offset, Ok(DebugValue::Uint(bits))) => {
+                                data_bit_offset = Some(bits as usize)
+                            }
+                            (gimli::DW_AT_bit_offset, Ok(DebugValue::Uint(bits))) => {
+                                storage_bit_offset = Some(bits as usize)
+                            }
+                            (gimli::DW_AT_byte_size, Ok(DebugValue::Uint(size))) => {
+                                storage_size = Some(size as usize)
+                            }
+                            (gimli::DW_AT_upper_bound, Ok(DebugValue::Uint(bound))) => {
+                                count = Some(bound as usize + 1)
+                            }
+                            (gimli::DW_AT_count, Ok(DebugValue::Uint(bound))) => {
+                                count = Some(bound as usize)
+                            }
+                            _ => {}
                         }
-                    } else {
-                        // TODO: report error?
-                        0
+                    }
+                    if let Some(bits) = data_bit_offset {
+                        member.offset = bits / 8;
+                        member.bit_offset = bits % 8;
+                    } else if let Some(bits) = storage_bit_offset {
+                        // Counted from the storage unit's most significant bit, which on a
+                        // little-endian machine is at its far end
+                        let end = member.offset * 8 + storage_size.unwrap_or(4) * 8;
+                        let bits = end.saturating_sub(bits + member.bit_size.unwrap_or(0));
+                        member.offset = bits / 8;
+                        member.bit_offset = bits % 8;
+                    }
+                    match offset_to_type.get_mut(&parent).map(|parent| &mut parent.kind) {
+                        Some(TypeKind::Struct(members)) | Some(TypeKind::Union(members)) => {
+                            members.push(member)
+                        }
+                        Some(TypeKind::Array(_, element_count)) => *element_count = count,
+                        _ => {}
+                    }
+                }
+                gimli::DW_TAG_enumerator => {
+                    let parent = match aggregates.last() {
+                        Some(&(parent_depth, offset)) if parent_depth == depth - 1 => offset,
+                        _ => continue,
                     };
-                    let type_offset = entry.offset().0;
-                    offset_to_type
-                        .insert(type_offset, Type::new(name, byte_size.try_into().unwrap()));
+                    let mut name = String::new();
+                    let mut value = 0;
+                    let mut attrs = entry.attrs();
+                    while let Some(attr) = attrs.next()? {
+                        match attr.name() {
+                            gimli::DW_AT_name => {
+                                if let Ok(DebugValue::Str(attr_name)) =
+                                    get_attr_value(&attr, &unit, &dwarf)
+                                {
+                                    name = attr_name;
+                                }
+                            }
+                            // Often DW_FORM_data1 and the like, which say nothing of the sign
+                            gimli::DW_AT_const_value => {
+                                value = match attr.value() {
+                                    gimli::AttributeValue::Sdata(value) => value,
+                                    value => value.udata_value().unwrap_or(0) as i64,
+                                };
+                            }
+                            _ => {}
+                        }
+                    }
+                    if let Some(TypeKind::Enum(enumerators)) =
+                        offset_to_type.get_mut(&parent).map(|parent| &mut parent.kind)
+                    {
+                        enumerators.push((name, value));
+                    }
                 }
                 gimli::DW_TAG_subprogram => {
                     let mut func: Function = Default::default();
+                    let mut abstract_origin = None;
                     let mut attrs = entry.attrs();
                     while let Some(attr) = attrs.next()? {
                         let val = get_attr_value(&attr, &unit, &dwarf);
@@ -113,6 +268,11 @@ pub fn load_file(object: &object::File, endian: gimli::RunTimeEndian) -> Result<
                                     func.name = name;
                                 }
                             }
+                            gimli::DW_AT_linkage_name | gimli::DW_AT_MIPS_linkage_name => {
+                                if let Ok(DebugValue::Str(name)) = val {
+                                    func.linkage_name = Some(name);
+                                }
+                            }
                             gimli::DW_AT_high_pc => {
                                 if let Ok(DebugValue::Uint(high_pc)) = val {
                                     func.text_length = high_pc.try_into().unwrap();
@@ -129,14 +289,85 @@ pub fn load_file(object: &object::File, endian: gimli::RunTimeEndian) -> Result<
                                     func.line_number = line_number.try_into().unwrap();
                                 }
                             }
+                            gimli::DW_AT_abstract_origin => {
+                                if let Ok(DebugValue::Size(offset)) = val {
+                                    abstract_origin = Some(offset);
+                                }
+                            }
+                            gimli::DW_AT_type => {
+                                if let Ok(DebugValue::Size(offset)) = val {
+                                    func.return_type = Some(offset);
+                                }
+                            }
+                            gimli::DW_AT_frame_base => func.frame_base = get_frame_base(&attr),
                             _ => {}
                         }
                     }
-                    compilation_units.last_mut().unwrap().functions.push(func);
+                    // An out-of-line instance of an inline function has no name of its own
+                    if let Some(&(cu_idx, func_idx)) =
+                        abstract_origin.and_then(|offset| function_offsets.get(&offset))
+                    {
+                        let origin = &compilation_units[cu_idx].functions[func_idx];
+                        if func.name.is_empty() {
+                            func.name = origin.name.clone();
+                            func.linkage_name = origin.linkage_name.clone();
+                            func.line_number = origin.line_number;
+                            func.return_type = origin.return_type;
+                        }
+                    }
+                    let cu_idx = compilation_units.len() - 1;
+                    function_offsets.insert(
+                        die_offset(entry.offset(), &unit),
+                        (cu_idx, compilation_units[cu_idx].functions.len()),
+                    );
+                    compilation_units[cu_idx].functions.push(func);
+                }
+                gimli::DW_TAG_inlined_subroutine => {
+                    let mut abstract_origin = None;
+                    let mut low_pc = None;
+                    let mut entry_pc = None;
+                    let mut attrs = entry.attrs();
+                    while let Some(attr) = attrs.next()? {
+                        let val = get_attr_value(&attr, &unit, &dwarf);
+                        match (attr.name(), val) {
+                            (gimli::DW_AT_abstract_origin, Ok(DebugValue::Size(offset))) => {
+                                abstract_origin = Some(offset);
+                            }
+                            (gimli::DW_AT_low_pc, Ok(DebugValue::Uint(addr))) => {
+                                low_pc = Some(addr);
+                            }
+                            (gimli::DW_AT_entry_pc, Ok(DebugValue::Uint(addr))) => {
+                                entry_pc = Some(addr);
+                            }
+                            _ => {}
+                        }
+                    }
+                    // Copies split into several ranges (DW_AT_ranges) only have an entry_pc
+                    if let (Some(&(cu_idx, func_idx)), Some(addr)) = (
+                        abstract_origin.and_then(|offset| function_offsets.get(&offset)),
+                        entry_pc.or(low_pc),
+                    ) {
+                        compilation_units[cu_idx].functions[func_idx]
+                            .inline_addresses
+                            .push(addr.try_into().unwrap());
+                    }
+                }
+                gimli::DW_TAG_lexical_block => {
+                    // A block split into several ranges is taken to cover everything between
+                    // them: a variable visible a little too widely beats one that can't be found
+                    let mut ranges = dwarf.die_ranges(&unit, entry)?;
+                    let mut hull: Option<(usize, usize)> = None;
+                    while let Some(range) = ranges.next()? {
+                        let (start, end) = (range.begin as usize, range.end as usize);
+                        hull = Some(hull.map_or((start, end), |(first, last)| {
+                            (first.min(start), last.max(end))
+                        }));
+                    }
+                    blocks.push((depth, hull));
                 }
                 gimli::DW_TAG_formal_parameter | gimli::DW_TAG_variable => {
                     let mut name = String::new();
-                    let mut entity_type: Option<Type> = None;
+                    let mut type_id: Option<TypeId> = None;
                     let mut location: Option<Location> = None;
                     let mut line_number = 0;
                     let mut attrs = entry.attrs();
@@ -151,13 +382,11 @@ pub fn load_file(object: &object::File, endian: gimli::RunTimeEndian) -> Result<
                             }
                             gimli::DW_AT_type => {
                                 if let Ok(DebugValue::Size(offset)) = val {
-                                    if let Some(dtype) = offset_to_type.get(&offset).clone() {
-                                        entity_type = Some(dtype.clone());
-                                    }
+                                    type_id = Some(offset);
                                 }
                             }
                             gimli::DW_AT_location => {
-                                if let Some(loc) = get_location(&attr, &unit) {
+                                if let Some(loc) = get_location(&attr, &unit, &dwarf) {
                                     location = Some(loc);
                                 }
                             }
@@ -169,12 +398,13 @@ pub fn load_file(object: &object::File, endian: gimli::RunTimeEndian) -> Result<
                             _ => {}
                         }
                     }
-                    if entity_type.is_some() && location.is_some() {
+                    if type_id.is_some() && location.is_some() {
                         let var = Variable {
                             name,
-                            entity_type: entity_type.unwrap(),
+                            type_id: type_id.unwrap(),
                             location: location.unwrap(),
                             line_number: line_number.try_into().unwrap(),
+                            scope: blocks.iter().rev().find_map(|&(_, range)| range),
                         };
                         if depth == 1 {
                             compilation_units
@@ -202,28 +432,44 @@ pub fn load_file(object: &object::File, endian: gimli::RunTimeEndian) -> Result<
 
         // Get line numbers
         if let Some(program) = unit.line_program.clone() {
+            // Record the line program's file table: every source file (headers included) that
+            // contributed code to this unit
+            if let Some(cu) = compilation_units.last_mut() {
+                let header = program.header();
+                for file in header.file_names() {
+                    let path = file_entry_path(file, header, &unit, &dwarf)?;
+                    let path = path.to_string_lossy().into_owned();
+                    if !cu.source_files.contains(&path) {
+                        cu.source_files.push(path);
+                    }
+                }
+            }
+
             // Iterate over the line program rows.
             let mut rows = program.rows();
+            // The row before this one in the sequence, whose range ends where this one starts
+            let mut previous: Option<LineRow> = None;
             while let Some((header, row)) = rows.next_row()? {
-                if !row.end_sequence() {
-                    // Determine the path. Real applications should cache this for performance.
-                    let mut path = path::PathBuf::new();
-                    if let Some(file) = row.file(header) {
-                        if let Some(dir) = file.directory(header) {
-                            path.push(dwarf.attr_string(&unit, dir)?.to_string_lossy().as_ref());
+                let address: usize = row.address().try_into().unwrap();
+                if let Some(mut previous_row) = previous.take() {
+                    previous_row.end = address;
+                    if previous_row.end > previous_row.address {
+                        if let Some(cu) = compilation_units.last_mut() {
+                            cu.line_rows.push(previous_row);
                         }
-                        path.push(
-                            dwarf
-                                .attr_string(&unit, file.path_name())?
-                                .to_string_lossy()
-                                .as_ref(),
-                        );
                     }
+                }
+                if !row.end_sequence() {
+                    // Determine the path. Real applications should cache this for performance.
+                    let path = match row.file(header) {
+                        Some(file) => file_entry_path(file, header, &unit, &dwarf)?,
+                        None => path::PathBuf::new(),
+                    };
 
                     // Get the File
-                    let file = compilation_units
-                        .iter_mut()
-                        .find(|f| f.name == path.as_os_str().to_str().unwrap());
+                    let file = unit_by_name
+                        .get(path.as_os_str().to_str().unwrap())
+                        .map(|&idx| &mut compilation_units[idx]);
 
                     // Determine line/column. DWARF line/column is never 0, so we use that
                     // but other applications may want to display this differently.
@@ -236,11 +482,18 @@ pub fn load_file(object: &object::File, endian: gimli::RunTimeEndian) -> Result<
                             address: row.address().try_into().unwrap(),
                         });
                     }
+                    previous = Some(LineRow {
+                        file: path.to_string_lossy().into_owned(),
+                        number: line.try_into().unwrap(),
+                        address,
+                        end: address,
+                        is_stmt: row.is_stmt(),
+                    });
                 }
             }
         }
     }
-    Ok(compilation_units)
+    Ok((compilation_units, offset_to_type))
 }
 
 #[derive(Debug, Clone)]
@@ -257,6 +510,8 @@ pub enum Error {
     GimliError(gimli::Error),
     Addr2lineError(addr2line::gimli::Error),
     ObjectError(String),
+    /// A compressed debug section (named here) that couldn't be decompressed
+    DecompressionError(String),
     IoError,
 }
 
@@ -284,6 +539,35 @@ impl From<std::fmt::Error> for Error {
     }
 }
 
+/// Builds the path of an entry in a line program's file table from its directory and name.
+fn file_entry_path<R: Reader>(
+    file: &gimli::FileEntry<R>,
+    header: &gimli::LineProgramHeader<R>,
+    unit: &gimli::Unit<R>,
+    dwarf: &gimli::Dwarf<R>,
+) -> Result<path::PathBuf, Error> {
+    let mut path = path::PathBuf::new();
+    if let Some(dir) = file.directory(header) {
+        let dir = dwarf.attr_string(unit, dir)?;
+        let dir = dir.to_string_lossy()?;
+        // DWARF 5 include directories other than the first are relative to the compilation
+        // directory
+        if !dir.starts_with('/') {
+            if let Some(ref comp_dir) = unit.comp_dir {
+                path.push(comp_dir.to_string_lossy()?.as_ref());
+            }
+        }
+        path.push(dir.as_ref());
+    }
+    path.push(
+        dwarf
+            .attr_string(unit, file.path_name())?
+            .to_string_lossy()?
+            .as_ref(),
+    );
+    Ok(path)
+}
+
 impl<'input, Endian> Reader for gimli::EndianSlice<'input, Endian> where
     Endian: gimli::Endianity + Send + Sync
 {
@@ -291,28 +575,74 @@ impl<'input, Endian> Reader for gimli::EndianSlice<'input, Endian> where
 
 trait Reader: gimli::Reader<Offset = usize> + Send + Sync {}
 
-fn get_location<R: Reader>(attr: &gimli::Attribute<R>, unit: &gimli::Unit<R>) -> Option<Location> {
-    if let gimli::AttributeValue::Exprloc(ref data) = attr.value() {
-        let encoding = unit.encoding();
-        let mut pc = data.0.clone();
-        if pc.len() > 0 {
+/// A variable's DW_AT_location. An expression that is a single DW_OP_fbreg or DW_OP_addr (the
+/// usual thing in unoptimized code) is kept as the offset or address; anything else is kept
+/// encoded, as is each entry of a location list, for dwarf_expr to evaluate at a stop.
+fn get_location<R: Reader>(
+    attr: &gimli::Attribute<R>,
+    unit: &gimli::Unit<R>,
+    dwarf: &gimli::Dwarf<R>,
+) -> Option<Location> {
+    match attr.value() {
+        gimli::AttributeValue::Exprloc(ref data) => {
+            let encoding = unit.encoding();
+            let mut pc = data.0.clone();
+            if pc.len() == 0 {
+                return None;
+            }
             if let Ok(op) = gimli::Operation::parse(&mut pc, encoding) {
-                match op {
-                    gimli::Operation::FrameOffset { offset } => {
-                        return Some(Location::FramePointerOffset(offset.try_into().unwrap()));
-                    }
-                    gimli::Operation::Address { address } => {
-                        return Some(Location::Address(address.try_into().unwrap()));
+                if pc.len() == 0 {
+                    match op {
+                        gimli::Operation::FrameOffset { offset } => {
+                            return Some(Location::FramePointerOffset(offset.try_into().unwrap()));
+                        }
+                        gimli::Operation::Address { address } => {
+                            return Some(Location::Address(address.try_into().unwrap()));
+                        }
+                        // DWARF 5's DW_OP_addrx: an index into .debug_addr
+                        gimli::Operation::AddressIndex { index } => {
+                            let address = dwarf.address(unit, index).ok()?;
+                            return Some(Location::Address(address.try_into().unwrap()));
+                        }
+                        _ => {}
                     }
-                    _ => {}
                 }
             }
+            Some(Location::Expression(data.0.to_slice().ok()?.to_vec()))
+        }
+        gimli::AttributeValue::LocationListsRef(offset) => {
+            let mut entries = dwarf.locations(unit, offset).ok()?;
+            let mut ranges = Vec::new();
+            while let Ok(Some(entry)) = entries.next() {
+                ranges.push((
+                    entry.range.begin.try_into().unwrap(),
+                    entry.range.end.try_into().unwrap(),
+                    entry.data.0.to_slice().ok()?.to_vec(),
+                ));
+            }
+            Some(Location::List(ranges))
         }
+        _ => None,
+    }
+}
+
+/// A function's DW_AT_frame_base expression, encoded.
+fn get_frame_base<R: Reader>(attr: &gimli::Attribute<R>) -> Option<Vec<u8>> {
+    match attr.value() {
+        gimli::AttributeValue::Exprloc(ref data) => Some(data.0.to_slice().ok()?.to_vec()),
+        _ => None,
     }
-    None
 }
 
 // based on dwarf_dump.rs
+/// Converts a DIE's unit-relative offset to the .debug_info offset that references to it use.
+fn die_offset<R: Reader>(offset: UnitOffset, unit: &gimli::Unit<R>) -> usize {
+    match offset.to_unit_section_offset(unit) {
+        UnitSectionOffset::DebugInfoOffset(goff) => goff.0,
+        UnitSectionOffset::DebugTypesOffset(goff) => goff.0,
+    }
+}
+
 fn get_attr_value<R: Reader>(
     attr: &gimli::Attribute<R>,
     unit: &gimli::Unit<R>,
@@ -344,6 +674,16 @@ fn get_attr_value<R: Reader>(
                 Ok(DebugValue::Str(format!("<.debug_str+0x{:08x}>", offset.0)))
             }
         }
+        // DWARF 5 strings: DW_FORM_strx (through .debug_str_offsets) and DW_FORM_line_strp
+        gimli::AttributeValue::DebugStrOffsetsIndex(_)
+        | gimli::AttributeValue::DebugLineStrRef(_) => match dwarf.attr_string(unit, value) {
+            Ok(s) => Ok(DebugValue::Str(format!("{}", s.to_string_lossy()?))),
+            Err(_) => Ok(DebugValue::NoVal),
+        },
+        // DWARF 5's DW_FORM_addrx: an index into .debug_addr
+        gimli::AttributeValue::DebugAddrIndex(index) => {
+            Ok(DebugValue::Uint(dwarf.address(unit, index)?))
+        }
         gimli::AttributeValue::Sdata(data) => Ok(DebugValue::Int(data)),
         gimli::AttributeValue::Addr(data) => Ok(DebugValue::Uint(data)),
         gimli::AttributeValue::Udata(data) => Ok(DebugValue::Uint(data)),
@@ -368,13 +708,15 @@ fn dump_file_index<R: Reader, W: Write>(
     unit: &gimli::Unit<R>,
     dwarf: &gimli::Dwarf<R>,
 ) -> Result<(), Error> {
-    if file == 0 {
-        return Ok(());
-    }
     let header = match unit.line_program {
         Some(ref program) => program.header(),
         None => return Ok(()),
     };
+    // Before DWARF 5, file numbers start at 1 and 0 means no file; from 5 on, 0 is the primary
+    // source file
+    if file == 0 && header.version() < 5 {
+        return Ok(());
+    }
     let file = match header.file(file) {
         Some(header) => header,
         None => {
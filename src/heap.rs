@@ -0,0 +1,187 @@
+//! `set heap-track`: bookkeeping of what malloc, calloc and realloc have handed out and free has
+//! taken back, fed by the breakpoints Session puts on them.
+
+use std::collections::HashMap;
+
+/// The allocator functions that are tracked.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Allocator {
+    Malloc,
+    Calloc,
+    Realloc,
+    Free,
+}
+
+impl Allocator {
+    pub const ALL: [Allocator; 4] =
+        [Allocator::Malloc, Allocator::Calloc, Allocator::Realloc, Allocator::Free];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Allocator::Malloc => "malloc",
+            Allocator::Calloc => "calloc",
+            Allocator::Realloc => "realloc",
+            Allocator::Free => "free",
+        }
+    }
+}
+
+/// A block that has been allocated and not yet freed.
+struct Allocation {
+    size: usize,
+    /// The return address of the call that allocated it
+    call_site: usize,
+}
+
+/// A call to malloc, calloc or realloc that hasn't returned yet.
+struct Unreturned {
+    return_address: usize,
+    /// The stack pointer once the call has returned, which tells a recursive caller's calls apart
+    stack: usize,
+    size: usize,
+    /// The block realloc was asked to resize
+    old: usize,
+    /// Whether the breakpoint at the return address was put there for this call, rather than
+    /// being someone else's
+    owns_breakpoint: bool,
+}
+
+/// Where the live blocks were allocated from, summed up for `info heap`.
+pub struct CallSite {
+    pub address: usize,
+    pub blocks: usize,
+    pub bytes: usize,
+}
+
+#[derive(Default)]
+pub struct HeapTracker {
+    /// The entry point of each allocator function in the current inferior
+    entries: Vec<(usize, Allocator)>,
+    live: HashMap<usize, Allocation>,
+    unreturned: Vec<Unreturned>,
+    /// Calls to malloc, calloc and realloc that returned a block, since tracking was turned on
+    pub allocations: usize,
+    /// Calls to free with a block we knew about
+    pub frees: usize,
+}
+
+impl HeapTracker {
+    /// Forgets the blocks and calls of a process that is gone. The totals are kept.
+    pub fn reset(&mut self) {
+        self.live.clear();
+        self.unreturned.clear();
+    }
+
+    pub fn set_entries(&mut self, entries: Vec<(usize, Allocator)>) {
+        self.entries = entries;
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = usize> + '_ {
+        self.entries.iter().map(|(addr, _)| *addr)
+    }
+
+    /// Which allocator function starts at `addr`, if any.
+    pub fn allocator_at(&self, addr: usize) -> Option<Allocator> {
+        self.entries
+            .iter()
+            .find(|(entry, _)| *entry == addr)
+            .map(|(_, allocator)| *allocator)
+    }
+
+    /// Whether some call in progress put the breakpoint at `return_address` there.
+    pub fn owns_breakpoint(&self, return_address: usize) -> bool {
+        self.unreturned
+            .iter()
+            .any(|call| call.return_address == return_address && call.owns_breakpoint)
+    }
+
+    /// The return addresses with a breakpoint put there by a call in progress.
+    pub fn owned_breakpoints(&self) -> impl Iterator<Item = usize> + '_ {
+        self.unreturned
+            .iter()
+            .filter(|call| call.owns_breakpoint)
+            .map(|call| call.return_address)
+    }
+
+    /// Notes a call as it is made, from its first two argument registers. free takes effect at
+    /// once; the others are finished by `returned`.
+    pub fn enter(
+        &mut self,
+        allocator: Allocator,
+        args: [u64; 2],
+        return_address: usize,
+        stack: usize,
+        owns_breakpoint: bool,
+    ) {
+        let (size, old) = match allocator {
+            Allocator::Malloc => (args[0] as usize, 0),
+            Allocator::Calloc => ((args[0] as usize).saturating_mul(args[1] as usize), 0),
+            Allocator::Realloc => (args[1] as usize, args[0] as usize),
+            Allocator::Free => {
+                if self.live.remove(&(args[0] as usize)).is_some() {
+                    self.frees += 1;
+                }
+                return;
+            }
+        };
+        self.unreturned.push(Unreturned {
+            return_address,
+            stack,
+            size,
+            old,
+            owns_breakpoint,
+        });
+    }
+
+    /// Finishes the call returning to `return_address` with the stack pointer at `stack`, if
+    /// there is one. Returns whether it owned the breakpoint at the return address, and None if
+    /// no tracked call was returning.
+    pub fn returned(&mut self, return_address: usize, stack: usize, result: u64) -> Option<bool> {
+        let idx = self
+            .unreturned
+            .iter()
+            .rposition(|call| call.return_address == return_address && call.stack == stack)?;
+        let call = self.unreturned.remove(idx);
+        let block = result as usize;
+        // realloc has freed the old block if it returned a different one, or if it was asked
+        // for 0 bytes
+        if call.old != 0 && (block != 0 || call.size == 0) {
+            self.live.remove(&call.old);
+        }
+        if block != 0 {
+            let allocation = Allocation {
+                size: call.size,
+                call_site: return_address,
+            };
+            self.live.insert(block, allocation);
+            self.allocations += 1;
+        }
+        Some(call.owns_breakpoint)
+    }
+
+    /// How many blocks are live, and their total size.
+    pub fn live(&self) -> (usize, usize) {
+        (self.live.len(), self.live.values().map(|block| block.size).sum())
+    }
+
+    /// The call sites of the live blocks, most bytes first, up to `limit` of them.
+    pub fn call_sites(&self, limit: usize) -> Vec<CallSite> {
+        let mut sites: HashMap<usize, (usize, usize)> = HashMap::new();
+        for block in self.live.values() {
+            let site = sites.entry(block.call_site).or_insert((0, 0));
+            site.0 += 1;
+            site.1 += block.size;
+        }
+        let mut sites: Vec<CallSite> = sites
+            .into_iter()
+            .map(|(address, (blocks, bytes))| CallSite {
+                address,
+                blocks,
+                bytes,
+            })
+            .collect();
+        sites.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.address.cmp(&b.address)));
+        sites.truncate(limit);
+        sites
+    }
+}
@@ -1,17 +1,212 @@
+use nix::errno::Errno;
 use nix::sys::ptrace;
 use nix::sys::signal;
+use nix::sys::uio::{process_vm_readv, IoVec, RemoteIoVec};
 use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
-use std::collections::HashMap;
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryInto;
+use std::fmt;
+use std::fs::{self, File};
+use std::path::Path;
 use std::mem::size_of;
-use nix::unistd::Pid;
+use nix::sys::termios::Termios;
+use nix::unistd::{close, getpgid, setpgid, setsid, Pid};
+use std::os::unix::io::RawFd;
 use std::os::unix::process::CommandExt;
-use std::process::{Child, Command};
-use crate::dwarf_data::DwarfData;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::time::{Duration, Instant};
+use crate::dwarf_data::Line;
+use crate::inferior_tty::InferiorTty;
+use crate::output_capture::{self, SharedOutput, Stream};
+use crate::proc_maps;
+use crate::terminal;
+
+/// Pid of the inferior we are currently blocked waiting on, or 0 if the debugger is not waiting on
+/// anything. Read by the SIGINT handler so that ctrl+c can be forwarded to the inferior.
+static RUNNING_PID: AtomicI32 = AtomicI32::new(0);
+
+/// Set by ctrl+c, for loops that run the inferior many times to stop between runs.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// SIGINT handler for the debugger process. The inferior lives in its own process group, so the
+/// terminal's ctrl+c only reaches us unless the inferior has been handed the terminal (see
+/// `terminal`); if an inferior is running, pass the interrupt along to its process group so that
+/// waitpid returns with the inferior stopped.
+pub extern "C" fn forward_sigint(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+    let pid = RUNNING_PID.load(Ordering::SeqCst);
+    if pid != 0 {
+        unsafe {
+            libc::kill(-pid, libc::SIGINT);
+        }
+    }
+}
+
+/// Whether ctrl+c was pressed since the last call.
+pub fn take_interrupt() -> bool {
+    INTERRUPTED.swap(false, Ordering::SeqCst)
+}
+
+/// Why the inferior stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// Hit the breakpoint installed at this address. The instruction pointer has already been
+    /// moved back onto the breakpoint address.
+    Breakpoint(usize),
+    /// Finished a single step requested with step_instruction.
+    Step,
+    /// Received a signal other than SIGTRAP. Carries the fault for SIGSEGV, SIGBUS, SIGFPE and
+    /// SIGILL.
+    Signal(Option<Fault>),
+    /// A hardware watchpoint in this debug register slot (0 to 3) was written to. The
+    /// instruction pointer is just past the instruction that wrote.
+    Watchpoint(usize),
+    /// Received a SIGTRAP that doesn't come from one of our breakpoints or a step.
+    Trap,
+    /// Entered or returned from a system call, while tracing them
+    Syscall,
+}
+
+/// Where and how a fault happened, from the siginfo of a SIGSEGV, SIGBUS, SIGFPE or SIGILL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fault {
+    /// The memory address that was accessed, or for SIGFPE and SIGILL the faulting instruction
+    pub address: usize,
+    /// `si_code`, which says what kind of fault it was
+    pub code: i32,
+}
+
+impl Fault {
+    /// What the fault's si_code means, e.g. "address not mapped" for SEGV_MAPERR, and "null
+    /// dereference" for that in the first page.
+    pub fn description(&self, sig: signal::Signal) -> Option<&'static str> {
+        Some(match (sig, self.code) {
+            (signal::SIGSEGV, 1) if self.address < NULL_PAGE_SIZE => "null dereference",
+            (signal::SIGSEGV, 1) => "address not mapped",
+            (signal::SIGSEGV, 2) => "invalid permissions for mapped object",
+            (signal::SIGSEGV, 3) => "failed address bound checks",
+            (signal::SIGSEGV, 4) => "failed protection key checks",
+            // SI_KERNEL: a general protection fault, such as a non-canonical address
+            (signal::SIGSEGV, 0x80) => "general protection fault",
+            (signal::SIGBUS, 1) => "invalid address alignment",
+            (signal::SIGBUS, 2) => "nonexistent physical address",
+            (signal::SIGBUS, 3) => "object-specific hardware error",
+            (signal::SIGFPE, 1) => "integer divide by zero",
+            (signal::SIGFPE, 2) => "integer overflow",
+            (signal::SIGFPE, 3) => "floating-point divide by zero",
+            (signal::SIGFPE, 4) => "floating-point overflow",
+            (signal::SIGFPE, 5) => "floating-point underflow",
+            (signal::SIGFPE, 6) => "floating-point inexact result",
+            (signal::SIGFPE, 7) => "invalid floating-point operation",
+            (signal::SIGFPE, 8) => "subscript out of range",
+            (signal::SIGILL, 1) => "illegal opcode",
+            (signal::SIGILL, 2) => "illegal operand",
+            (signal::SIGILL, 3) => "illegal addressing mode",
+            (signal::SIGILL, 4) => "illegal trap",
+            (signal::SIGILL, 5) => "privileged opcode",
+            (signal::SIGILL, 6) => "privileged register",
+            (signal::SIGILL, 7) => "coprocessor error",
+            (signal::SIGILL, 8) => "internal stack error",
+            _ => return None,
+        })
+    }
+}
+
+/// The siginfo of the signal the inferior last stopped with, for `info siginfo`.
+#[derive(Debug, Clone, Copy)]
+pub struct SignalInfo {
+    pub signal: signal::Signal,
+    pub errno: i32,
+    pub code: i32,
+    /// For SIGSEGV, SIGBUS, SIGFPE and SIGILL raised by the kernel
+    pub fault: Option<Fault>,
+    /// The pid and uid of the process that sent the signal, for one sent with kill, tgkill or
+    /// sigqueue
+    pub sender: Option<(Pid, u32)>,
+}
+
+impl SignalInfo {
+    /// The name of `code`, e.g. SEGV_MAPERR or SI_USER.
+    pub fn code_name(&self) -> Option<&'static str> {
+        Some(match (self.signal, self.code) {
+            (_, 0) => "SI_USER",
+            (_, 0x80) => "SI_KERNEL",
+            (_, -1) => "SI_QUEUE",
+            (_, -2) => "SI_TIMER",
+            (_, -3) => "SI_MESGQ",
+            (_, -4) => "SI_ASYNCIO",
+            (_, -5) => "SI_SIGIO",
+            (_, -6) => "SI_TKILL",
+            (signal::SIGSEGV, 1) => "SEGV_MAPERR",
+            (signal::SIGSEGV, 2) => "SEGV_ACCERR",
+            (signal::SIGSEGV, 3) => "SEGV_BNDERR",
+            (signal::SIGSEGV, 4) => "SEGV_PKUERR",
+            (signal::SIGBUS, 1) => "BUS_ADRALN",
+            (signal::SIGBUS, 2) => "BUS_ADRERR",
+            (signal::SIGBUS, 3) => "BUS_OBJERR",
+            (signal::SIGFPE, 1) => "FPE_INTDIV",
+            (signal::SIGFPE, 2) => "FPE_INTOVF",
+            (signal::SIGFPE, 3) => "FPE_FLTDIV",
+            (signal::SIGFPE, 4) => "FPE_FLTOVF",
+            (signal::SIGFPE, 5) => "FPE_FLTUND",
+            (signal::SIGFPE, 6) => "FPE_FLTRES",
+            (signal::SIGFPE, 7) => "FPE_FLTINV",
+            (signal::SIGFPE, 8) => "FPE_FLTSUB",
+            (signal::SIGILL, 1) => "ILL_ILLOPC",
+            (signal::SIGILL, 2) => "ILL_ILLOPN",
+            (signal::SIGILL, 3) => "ILL_ILLADR",
+            (signal::SIGILL, 4) => "ILL_ILLTRP",
+            (signal::SIGILL, 5) => "ILL_PRVOPC",
+            (signal::SIGILL, 6) => "ILL_PRVREG",
+            (signal::SIGILL, 7) => "ILL_COPROC",
+            (signal::SIGILL, 8) => "ILL_BADSTK",
+            (signal::SIGTRAP, 1) => "TRAP_BRKPT",
+            (signal::SIGTRAP, 2) => "TRAP_TRACE",
+            (signal::SIGTRAP, 3) => "TRAP_BRANCH",
+            (signal::SIGTRAP, 4) => "TRAP_HWBKPT",
+            (signal::SIGCHLD, 1) => "CLD_EXITED",
+            (signal::SIGCHLD, 2) => "CLD_KILLED",
+            (signal::SIGCHLD, 3) => "CLD_DUMPED",
+            (signal::SIGCHLD, 4) => "CLD_TRAPPED",
+            (signal::SIGCHLD, 5) => "CLD_STOPPED",
+            (signal::SIGCHLD, 6) => "CLD_CONTINUED",
+            _ => return None,
+        })
+    }
+
+    /// What `code` means: the fault's description, or who sent the signal.
+    pub fn code_description(&self) -> Option<&'static str> {
+        if let Some(fault) = self.fault {
+            return fault.description(self.signal);
+        }
+        Some(match self.code {
+            0 => "sent by kill",
+            0x80 => "sent by the kernel",
+            -1 => "sent by sigqueue",
+            -2 => "POSIX timer expired",
+            -6 => "sent by tkill or tgkill",
+            _ => return None,
+        })
+    }
+}
+
+/// Faulting addresses below this are reported as null dereferences.
+const NULL_PAGE_SIZE: usize = 4096;
+
+/// How many hardware watchpoints x86-64 has: one per debug address register, DR0 to DR3.
+pub const WATCHPOINT_SLOTS: usize = 4;
+
+/// Offset of `u_debugreg` in the kernel's `struct user`, for PTRACE_PEEKUSER/POKEUSER.
+const DEBUG_REGISTERS_OFFSET: usize = 848;
+const DR6: usize = 6;
+const DR7: usize = 7;
 
 pub enum Status {
-    /// Indicates inferior stopped. Contains the signal that stopped the process, as well as the
-    /// current instruction pointer that it is stopped at.
-    Stopped(signal::Signal, usize),
+    /// Indicates inferior stopped. Contains the signal that stopped the process, the current
+    /// instruction pointer that it is stopped at, and why it stopped.
+    Stopped(signal::Signal, usize, StopReason),
 
     /// Indicates inferior exited normally. Contains the exit status code.
     Exited(i32),
@@ -30,154 +225,1376 @@ fn child_traceme() -> Result<(), std::io::Error> {
     )))
 }
 
+/// Moves the child into its own process group, so that ctrl+c at the terminal is delivered to the
+/// debugger only (until the child is made the terminal's foreground group while it runs). Also
+/// undoes the debugger ignoring SIGTTOU, which exec would otherwise pass on.
+fn child_setpgid() -> Result<(), std::io::Error> {
+    setpgid(Pid::from_raw(0), Pid::from_raw(0)).or(Err(std::io::Error::new(
+        std::io::ErrorKind::Other,
+        "setpgid failed",
+    )))?;
+    unsafe {
+        libc::signal(libc::SIGTTOU, libc::SIG_DFL);
+    }
+    Ok(())
+}
+
+/// Turns off address space layout randomization for the child, so that addresses are the same
+/// from one run to the next. Inherited across exec.
+fn child_disable_aslr() -> Result<(), std::io::Error> {
+    // Passing 0xffffffff reads the current persona without changing it
+    let persona = unsafe { libc::personality(0xffffffff) };
+    if persona < 0
+        || unsafe { libc::personality((persona | libc::ADDR_NO_RANDOMIZE) as libc::c_ulong) } < 0
+    {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Starts a new session (and so a new process group) with the pty as its controlling terminal.
+/// Used instead of child_setpgid when the inferior runs on its own pty.
+fn child_take_tty(master: RawFd, slave: RawFd) -> Result<(), std::io::Error> {
+    setsid().or(Err(std::io::Error::new(std::io::ErrorKind::Other, "setsid failed")))?;
+    if unsafe { libc::ioctl(slave, libc::TIOCSCTTY, 0) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // The child's stdio already holds copies of the slave side
+    let _ = close(master);
+    let _ = close(slave);
+    Ok(())
+}
+
+/// Changes to make to the debugger's own environment when spawning an inferior.
+#[derive(Default)]
+pub struct Environment {
+    /// Start from an empty environment instead of inheriting the debugger's.
+    pub clear: bool,
+    /// Variables to set (Some) or remove (None).
+    pub vars: BTreeMap<String, Option<String>>,
+}
+
+/// Files to connect to the inferior's standard streams instead of the debugger's terminal.
+#[derive(Default)]
+pub struct Redirects {
+    pub stdin: Option<File>,
+    pub stdout: Option<File>,
+    pub stderr: Option<File>,
+    /// Where to capture stdout and stderr (those not redirected to files), with
+    /// `set capture-output on`
+    pub capture: Option<SharedOutput>,
+}
+
+/// Looks up a register by name (`rax`, `rip`, ...), including the aliases `pc`, `sp` and `fp`.
+pub fn register_value(regs: &libc::user_regs_struct, name: &str) -> Option<u64> {
+    Some(match name {
+        "rax" => regs.rax,
+        "rbx" => regs.rbx,
+        "rcx" => regs.rcx,
+        "rdx" => regs.rdx,
+        "rsi" => regs.rsi,
+        "rdi" => regs.rdi,
+        "rbp" | "fp" => regs.rbp,
+        "rsp" | "sp" => regs.rsp,
+        "r8" => regs.r8,
+        "r9" => regs.r9,
+        "r10" => regs.r10,
+        "r11" => regs.r11,
+        "r12" => regs.r12,
+        "r13" => regs.r13,
+        "r14" => regs.r14,
+        "r15" => regs.r15,
+        "rip" | "pc" => regs.rip,
+        "eflags" => regs.eflags,
+        "cs" => regs.cs,
+        "ss" => regs.ss,
+        "ds" => regs.ds,
+        "es" => regs.es,
+        "fs" => regs.fs,
+        "gs" => regs.gs,
+        "fs_base" => regs.fs_base,
+        "gs_base" => regs.gs_base,
+        "orig_rax" => regs.orig_rax,
+        _ => return None,
+    })
+}
+
+/// Timing and stop counts for one run of the inferior.
+#[derive(Clone, Copy, Default)]
+pub struct RunStats {
+    /// Wall-clock time from the first continue to the exit (or until now, if still running)
+    pub elapsed: Duration,
+    pub stops: usize,
+    pub breakpoint_hits: usize,
+}
+
+/// One entry in a backtrace.
+pub struct Frame {
+    pub address: usize,
+    pub function: Option<String>,
+    pub line: Option<Line>,
+    /// True for a function inlined into the next frame; it shares that frame's address
+    pub inlined: bool,
+}
+
+/// A physical frame found by walking the frame pointer chain.
+#[derive(Clone, Copy)]
+pub struct StackFrame {
+    /// Where execution is in this frame: the current pc for the innermost frame, otherwise the
+    /// return address into it
+    pub rip: usize,
+    pub rbp: usize,
+    /// The canonical frame address: the caller's rsp before the call, just above the return
+    /// address
+    pub cfa: usize,
+}
+
+/// Iterates over the frames of a stopped inferior, innermost first, by following saved rbp
+/// values. It has no idea where the stack ends, so callers stop it (at main, say); it also ends
+/// at a null frame pointer, at one that doesn't point further up the stack than the last (so a
+/// corrupt chain can't loop), or after an error reading a frame record.
+pub struct StackFrames<'a> {
+    inferior: &'a Inferior,
+    next: Option<Result<StackFrame, PtraceError>>,
+}
+
+impl<'a> Iterator for StackFrames<'a> {
+    type Item = Result<StackFrame, PtraceError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = match self.next.take()? {
+            Ok(frame) => frame,
+            Err(err) => return Some(Err(err)),
+        };
+        if frame.rbp != 0 {
+            // The saved frame pointer, followed by the return address
+            self.next = Some(self.inferior.read_bytes(frame.rbp, 16).map(|record| {
+                let rbp = u64::from_le_bytes(record[..8].try_into().unwrap()) as usize;
+                // Callers' frames are higher up; anything else means the chain is garbage from
+                // here on, so this frame is the last
+                let rbp = if rbp > frame.rbp { rbp } else { 0 };
+                StackFrame {
+                    rip: u64::from_le_bytes(record[8..].try_into().unwrap()) as usize,
+                    rbp,
+                    cfa: rbp.wrapping_add(16),
+                }
+            }));
+        }
+        Some(Ok(frame))
+    }
+}
+
+/// A ptrace (or waitpid) call on the inferior failed. Carries enough context to tell, say, a
+/// process that has already exited (ESRCH) from one we aren't allowed to trace (EPERM).
+#[derive(Debug, Clone)]
+pub struct PtraceError {
+    /// The request that failed, e.g. "PTRACE_CONT"
+    pub op: &'static str,
+    pub pid: Pid,
+    /// The inferior address the request was about, for memory reads and writes
+    pub addr: Option<usize>,
+    pub err: nix::Error,
+}
+
+impl fmt::Display for PtraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} on process {}", self.op, self.pid)?;
+        if let Some(addr) = self.addr {
+            write!(f, " at {:#x}", addr)?;
+        }
+        write!(f, " failed: {}", self.err)?;
+        match self.err {
+            nix::Error::Sys(Errno::ESRCH) | nix::Error::Sys(Errno::ECHILD) => {
+                write!(f, " (the process has exited or is not stopped)")
+            }
+            nix::Error::Sys(Errno::EPERM) => write!(
+                f,
+                " (not permitted; check /proc/sys/kernel/yama/ptrace_scope)"
+            ),
+            nix::Error::Sys(Errno::EIO) | nix::Error::Sys(Errno::EFAULT) if self.addr.is_some() => {
+                write!(f, " (the address is not mapped in the process)")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Why a breakpoint could not be inserted. The breakpoint stays pending and insertion is retried
+/// the next time the inferior is resumed.
+#[derive(Debug)]
+pub enum BreakpointError {
+    /// The address isn't in an executable mapping of the program or its libraries
+    NotInCode(usize),
+    /// Writing the int3 failed
+    Ptrace(usize, PtraceError),
+}
+
+impl fmt::Display for BreakpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BreakpointError::NotInCode(addr) => write!(
+                f,
+                "Cannot insert breakpoint at {:#x}: address not in executable code",
+                addr
+            ),
+            BreakpointError::Ptrace(addr, err) => {
+                write!(f, "Cannot insert breakpoint at {:#x}: {}", addr, err)
+            }
+        }
+    }
+}
+
+/// Reasons an inferior could not be started.
+#[derive(Debug)]
+pub enum InferiorError {
+    /// The target could not be executed
+    SpawnFailed(std::io::Error),
+    /// Waiting for the new process to stop at its first instruction failed
+    WaitFailed(nix::Error),
+    /// The process exited or was killed before it stopped for tracing
+    ExitedBeforeTrap(WaitStatus),
+    /// The process stopped with some signal other than the SIGTRAP from exec
+    UnexpectedStop(signal::Signal),
+    /// waitpid reported something other than an exit or a stop
+    UnexpectedStatus(WaitStatus),
+    /// The pseudo-terminal for `set inferior-tty on` could not be set up
+    TtyFailed(nix::Error),
+}
+
+impl fmt::Display for InferiorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InferiorError::SpawnFailed(err) if err.kind() == std::io::ErrorKind::NotFound => write!(
+                f,
+                "Could not execute the program: {}. Check the path, and the working directory set with `cd`.",
+                err
+            ),
+            InferiorError::SpawnFailed(err) => write!(
+                f,
+                "Could not execute the program: {}. Check that it is an executable file.",
+                err
+            ),
+            InferiorError::WaitFailed(err) => {
+                write!(f, "Could not wait for the new process: {}", err)
+            }
+            InferiorError::ExitedBeforeTrap(WaitStatus::Exited(_, code)) => write!(
+                f,
+                "The program exited with code {} before it could be traced. Is ptrace allowed here?",
+                code
+            ),
+            InferiorError::ExitedBeforeTrap(WaitStatus::Signaled(_, sig, _)) => write!(
+                f,
+                "The program was killed by {} before it could be traced.",
+                sig
+            ),
+            InferiorError::ExitedBeforeTrap(status) => {
+                write!(f, "The program went away before it could be traced: {:?}", status)
+            }
+            InferiorError::UnexpectedStop(sig) => write!(
+                f,
+                "The program stopped with {} instead of SIGTRAP while starting.",
+                sig
+            ),
+            InferiorError::UnexpectedStatus(status) => {
+                write!(f, "Unexpected status while starting the program: {:?}", status)
+            }
+            InferiorError::TtyFailed(err) => write!(
+                f,
+                "Could not set up a terminal for the program: {}. Try `set inferior-tty off`.",
+                err
+            ),
+        }
+    }
+}
+
 pub struct Inferior {
-    child: Child,
-    bp_to_original_byte: HashMap<usize, u8>
+    /// The process we spawned. None for a copy made with `fork`, which isn't our child.
+    child: Option<Child>,
+    pid: Pid,
+    /// The byte each installed breakpoint replaced. An address is only ever recorded once, while
+    /// it still holds its original byte; writing over an installed int3 would record 0xcc as the
+    /// "original" and corrupt the program when the breakpoint is stepped over.
+    bp_to_original_byte: HashMap<usize, u8>,
+    /// What we last wrote to each (aligned) word that holds breakpoints, i.e. the original code
+    /// with int3s patched in. Lets breakpoints be disarmed and re-armed with a single write each.
+    patched_words: HashMap<usize, u64>,
+    /// Time spent installing, removing and stepping over breakpoints since the last call to
+    /// take_breakpoint_time
+    breakpoint_time: Duration,
+    /// The pseudo-terminal the inferior runs on, if it was started with `set inferior-tty on`
+    tty: Option<InferiorTty>,
+    /// What we last wrote to DR7, which enables the watchpoints in DR0-DR3
+    debug_control: u64,
+    /// When the inferior was first continued, and when it exited. The exit is noted by `wait`, so
+    /// that no way of running the inferior can miss it
+    started: Option<Instant>,
+    finished: Cell<Option<Instant>>,
+    stops: usize,
+    breakpoint_hits: usize,
+    /// The siginfo of the last stop by a signal other than SIGTRAP, kept until the next stop
+    last_siginfo: Cell<Option<SignalInfo>>,
+    /// Whether resuming stops at every system call's entry and exit
+    trace_syscalls: bool,
+    /// The inferior's terminal settings as of its last stop, given back to it when it next runs
+    terminal_settings: Option<Termios>,
+}
+
+/// Defines `read_*` methods on Inferior that each read one little-endian integer of the given
+/// type, with read_bytes (so installed breakpoints are hidden).
+macro_rules! typed_reads {
+    ($($name:ident: $ty:ty,)*) => {
+        $(
+            pub fn $name(&self, addr: usize) -> Result<$ty, PtraceError> {
+                let bytes = self.read_bytes(addr, size_of::<$ty>())?;
+                Ok(<$ty>::from_le_bytes(bytes[..].try_into().unwrap()))
+            }
+        )*
+    };
 }
 
 impl Inferior {
-    /// Attempts to start a new inferior process. Returns Some(Inferior) if successful, or None if
-    /// an error is encountered.
-    pub fn new(target: &str, args: &Vec<String>, break_points: &mut Vec<usize>) -> Option<Inferior> {
-        // TODO: implement me!
+    /// Attempts to start a new inferior process, stopped at its first instruction.
+    pub fn new(
+        target: &str,
+        args: &[String],
+        env: &Environment,
+        redirects: Redirects,
+        use_tty: bool,
+        cwd: &Path,
+        disable_aslr: bool,
+    ) -> Result<Inferior, InferiorError> {
         let mut command = Command::new(target);
         command.args(args);
+        command.current_dir(cwd);
+        let mut tty = if use_tty { Some(InferiorTty::open().map_err(InferiorError::TtyFailed)?) } else { None };
+        // Streams that aren't redirected to a file are captured, or go to the inferior's pty if
+        // it has one
+        if let Some(file) = redirects.stdin {
+            command.stdin(file);
+        } else if let Some(tty) = &tty {
+            command.stdin(tty.slave_stdio().map_err(InferiorError::TtyFailed)?);
+        }
+        if let Some(file) = redirects.stdout {
+            command.stdout(file);
+        } else if redirects.capture.is_some() {
+            command.stdout(Stdio::piped());
+        } else if let Some(tty) = &tty {
+            command.stdout(tty.slave_stdio().map_err(InferiorError::TtyFailed)?);
+        }
+        if let Some(file) = redirects.stderr {
+            command.stderr(file);
+        } else if redirects.capture.is_some() {
+            command.stderr(Stdio::piped());
+        } else if let Some(tty) = &tty {
+            command.stderr(tty.slave_stdio().map_err(InferiorError::TtyFailed)?);
+        }
+        if env.clear {
+            command.env_clear();
+        }
+        for (var, value) in &env.vars {
+            match value {
+                Some(value) => command.env(var, value),
+                None => command.env_remove(var),
+            };
+        }
+        let tty_fds = tty.as_ref().map(|tty| (tty.master_fd(), tty.slave_fd()));
         unsafe {
-            command.pre_exec(|| {
+            command.pre_exec(move || {
+                match tty_fds {
+                    Some((master, Some(slave))) => child_take_tty(master, slave)?,
+                    _ => child_setpgid()?,
+                }
+                if disable_aslr {
+                    child_disable_aslr()?;
+                }
                 child_traceme()
             });
         }
-        let child = command.spawn().expect("spawn shit!");
+        let mut child = command.spawn().map_err(InferiorError::SpawnFailed)?;
         let child_pid = Pid::from_raw(child.id() as i32);
-        let status = waitpid(Some(child_pid), None).expect("wait shit!");
-        match status {
-            WaitStatus::Exited(_, _) => {
-                None
-            },
-            WaitStatus::Signaled(_, _, _) => {
-                None
-            },
+        match waitpid(Some(child_pid), None).map_err(InferiorError::WaitFailed)? {
+            WaitStatus::Stopped(_, signal::SIGTRAP) => {}
+            status @ WaitStatus::Exited(..) | status @ WaitStatus::Signaled(..) => {
+                return Err(InferiorError::ExitedBeforeTrap(status));
+            }
             WaitStatus::Stopped(_, signal) => {
-                match signal {
-                    signal::SIGTRAP => {
-                        let mut ret_inf = Inferior{child, bp_to_original_byte: HashMap::new()};
-                        ret_inf.install_break_points(break_points).ok()?;
-                        Some(ret_inf)
-                    },
-                    _ => None
-                }
-            },
-            WaitStatus::PtraceEvent(_, _, _) => {
-                None
-            },
-            WaitStatus::PtraceSyscall(_) => {
-                None
-            },
-            WaitStatus::Continued(_) => {
-                None
-            },
-            WaitStatus::StillAlive => {
-                None
+                let _ = child.kill();
+                let _ = waitpid(Some(child_pid), None);
+                return Err(InferiorError::UnexpectedStop(signal));
+            }
+            status => {
+                let _ = child.kill();
+                let _ = waitpid(Some(child_pid), None);
+                return Err(InferiorError::UnexpectedStatus(status));
             }
         }
+        if let Some(tty) = &mut tty {
+            tty.start_relay().map_err(InferiorError::TtyFailed)?;
+        }
+        if let Some(captured) = redirects.capture {
+            if let Some(stdout) = child.stdout.take() {
+                output_capture::relay(stdout, Stream::Stdout, captured.clone());
+            }
+            if let Some(stderr) = child.stderr.take() {
+                output_capture::relay(stderr, Stream::Stderr, captured);
+            }
+        }
+        Ok(Inferior {
+            child: Some(child),
+            pid: child_pid,
+            bp_to_original_byte: HashMap::new(),
+            patched_words: HashMap::new(),
+            breakpoint_time: Duration::default(),
+            tty,
+            debug_control: 0,
+            started: None,
+            finished: Cell::new(None),
+            stops: 0,
+            breakpoint_hits: 0,
+            last_siginfo: Cell::new(None),
+            trace_syscalls: false,
+            terminal_settings: None,
+        })
     }
 
     /// Returns the pid of this inferior.
     pub fn pid(&self) -> Pid {
-        nix::unistd::Pid::from_raw(self.child.id() as i32)
+        self.pid
+    }
+
+    /// Returns a function that wraps a nix error from `op` with this inferior's pid, for use with
+    /// map_err.
+    fn ptrace_err(&self, op: &'static str, addr: Option<usize>) -> impl Fn(nix::Error) -> PtraceError {
+        let pid = self.pid();
+        move |err| PtraceError { op, pid, addr, err }
     }
 
     /// Calls waitpid on this inferior and returns a Status to indicate the state of the process
-    /// after the waitpid call.
-    pub fn wait(&self, options: Option<WaitPidFlag>) -> Result<Status, nix::Error> {
-        Ok(match waitpid(self.pid(), options)? {
-            WaitStatus::Exited(_pid, exit_code) => Status::Exited(exit_code),
-            WaitStatus::Signaled(_pid, signal, _core_dumped) => Status::Signaled(signal),
+    /// after the waitpid call. Once it reports an exit, has_exited is true.
+    pub fn wait(&self, options: Option<WaitPidFlag>) -> Result<Status, PtraceError> {
+        RUNNING_PID.store(self.pid().as_raw(), Ordering::SeqCst);
+        if let Some(tty) = &self.tty {
+            tty.set_running(true);
+        }
+        let wait_status = waitpid(self.pid(), options);
+        if let Some(tty) = &self.tty {
+            tty.set_running(false);
+        }
+        RUNNING_PID.store(0, Ordering::SeqCst);
+        Ok(match wait_status.map_err(self.ptrace_err("waitpid", None))? {
+            WaitStatus::Exited(_pid, exit_code) => {
+                self.finished.set(Some(Instant::now()));
+                Status::Exited(exit_code)
+            }
+            WaitStatus::Signaled(_pid, signal, _core_dumped) => {
+                self.finished.set(Some(Instant::now()));
+                Status::Signaled(signal)
+            }
             WaitStatus::Stopped(_pid, signal) => {
-                let regs = ptrace::getregs(self.pid())?;
-                Status::Stopped(signal, regs.rip as usize)
+                let regs = self.getregs()?;
+                // SIGTRAP stops come with every breakpoint and single step, so their siginfo is
+                // only read if asked for
+                let info = if signal == signal::SIGTRAP { None } else { self.siginfo().ok() };
+                self.last_siginfo.set(info);
+                let reason = if signal == signal::SIGTRAP {
+                    StopReason::Trap
+                } else {
+                    StopReason::Signal(info.and_then(|info| info.fault))
+                };
+                Status::Stopped(signal, regs.rip as usize, reason)
+            }
+            WaitStatus::PtraceSyscall(_pid) => {
+                self.last_siginfo.set(None);
+                let regs = self.getregs()?;
+                Status::Stopped(signal::SIGTRAP, regs.rip as usize, StopReason::Syscall)
             }
             other => panic!("waitpid returned unexpected status: {:?}", other),
         })
     }
 
-    pub fn continue_running(&mut self, break_points: &mut Vec<usize>) -> Result<Status, nix::Error> {
-        self.install_break_points(break_points)?;
-        let inf_pid = self.pid();
-        let mut regs = ptrace::getregs(inf_pid)?;
-        let possible_bp_addr = (regs.rip - 1) as usize;
-        if let Some(origin_byte) = self.bp_to_original_byte.get(&possible_bp_addr) {
-            self.write_byte(possible_bp_addr, *origin_byte)?;
-            regs.rip -= 1;
-            ptrace::setregs(inf_pid, regs)?;
-            ptrace::step(inf_pid, None)?;
-            // println!("after step");
-            self.write_byte(possible_bp_addr, 0xcc)?;
-            // println!("after write byte");
-        }
-        ptrace::cont(inf_pid, None)?;
-        self.wait(None)
-    }
-
-    pub fn kill(&mut self) -> Vec<usize> {
-        println!("process {} being killed", self.child.id());
-        self.child.kill().expect("failed to kill process");
-        waitpid(self.pid(), None).expect("failed to reaping killed process");
-        self.bp_to_original_byte.keys().map(|k| *k).collect()
-    }
-    pub fn print_backtrace(&self, debug_data: 
-        &DwarfData) -> Result<(), nix::Error> {
-        let regs = ptrace::getregs(self.pid())?;
-        let mut rip = regs.rip;
-        let mut rbp = regs.rbp;
+    /// The siginfo of the signal the inferior is stopped with: the one captured when it stopped,
+    /// or for a SIGTRAP stop, read now.
+    pub fn last_siginfo(&self) -> Result<SignalInfo, PtraceError> {
+        match self.last_siginfo.get() {
+            Some(info) => Ok(info),
+            None => self.siginfo(),
+        }
+    }
+
+    /// Reads the siginfo of the signal the inferior is stopped with.
+    fn siginfo(&self) -> Result<SignalInfo, PtraceError> {
+        let info = ptrace::getsiginfo(self.pid())
+            .map_err(self.ptrace_err("PTRACE_GETSIGINFO", None))?;
+        let signal = signal::Signal::from_c_int(info.si_signo)
+            .map_err(self.ptrace_err("PTRACE_GETSIGINFO", None))?;
+        // A positive si_code means the kernel raised the signal; si_addr is only meaningful then
+        let fault = match signal {
+            signal::SIGSEGV | signal::SIGBUS | signal::SIGFPE | signal::SIGILL
+                if info.si_code > 0 =>
+            {
+                Some(Fault {
+                    address: unsafe { info.si_addr() } as usize,
+                    code: info.si_code,
+                })
+            }
+            _ => None,
+        };
+        let sender = match info.si_code {
+            0 | -1 | -6 => Some(unsafe { (Pid::from_raw(info.si_pid()), info.si_uid()) }),
+            _ => None,
+        };
+        Ok(SignalInfo {
+            signal,
+            errno: info.si_errno,
+            code: info.si_code,
+            fault,
+            sender,
+        })
+    }
+
+    /// Resumes the inferior (stepping over a breakpoint at the current address first) and waits for
+    /// it to stop or exit. Meanwhile it has the debugger's terminal, unless it runs on its own.
+    pub fn continue_running(&mut self) -> Result<Status, PtraceError> {
+        self.in_foreground(|inferior| match inferior.resume() {
+            Ok(Some(status)) => Ok(status),
+            Ok(None) => inferior.wait_running(),
+            Err(err) => Err(err),
+        })
+    }
+
+    /// Calls `run`, which resumes the inferior and waits for it, with the debugger's terminal
+    /// given to the inferior unless it runs on its own. The terminal is taken back afterwards.
+    pub fn in_foreground<T>(&mut self, run: impl FnOnce(&mut Inferior) -> T) -> T {
+        let foreground = match (&self.tty, getpgid(Some(self.pid))) {
+            (None, Ok(group)) => terminal::give_to(group, self.terminal_settings.as_ref()),
+            _ => None,
+        };
+        let result = run(self);
+        if let Some(foreground) = foreground {
+            self.terminal_settings = foreground.take_back();
+        }
+        result
+    }
+
+    /// Resumes the inferior (stepping over a breakpoint at the current address first) without
+    /// waiting for it. Returns the status if stepping off the breakpoint already stopped it;
+    /// otherwise `wait_running` collects the stop.
+    pub fn resume(&mut self) -> Result<Option<Status>, PtraceError> {
+        let rip = self.getregs()?.rip as usize;
+        self.started.get_or_insert_with(Instant::now);
+        // If we're sitting on a breakpoint, execute the original instruction before re-arming it
+        if self.bp_to_original_byte.contains_key(&rip) {
+            let started = Instant::now();
+            self.restore_original_byte(rip)?;
+            ptrace::step(self.pid(), None).map_err(self.ptrace_err("PTRACE_SINGLESTEP", None))?;
+            // The instruction may have been the program's last, or been hit by a signal
+            let status = self.wait(None)?;
+            if let Status::Stopped(..) = status {
+                self.restore_int3(rip)?;
+            }
+            self.breakpoint_time += started.elapsed();
+            match status {
+                Status::Stopped(signal::SIGTRAP, _, _) => {}
+                other => return Ok(Some(self.count_stop(other))),
+            }
+        }
+        if self.trace_syscalls {
+            ptrace::syscall(self.pid(), None).map_err(self.ptrace_err("PTRACE_SYSCALL", None))?;
+        } else {
+            ptrace::cont(self.pid(), None).map_err(self.ptrace_err("PTRACE_CONT", None))?;
+        }
+        Ok(None)
+    }
+
+    /// Turns stopping at system calls on or off for the next time the inferior is resumed. While
+    /// it's on, `wait_running` returns a StopReason::Syscall stop at each call's entry and exit.
+    pub fn set_syscall_tracing(&mut self, on: bool) -> Result<(), PtraceError> {
+        self.trace_syscalls = on;
+        ptrace::setoptions(self.pid, self.ptrace_options())
+            .map_err(self.ptrace_err("PTRACE_SETOPTIONS", None))
+    }
+
+    /// The ptrace options the inferior normally runs with. TRACESYSGOOD sets system call stops
+    /// apart from SIGTRAPs.
+    fn ptrace_options(&self) -> ptrace::Options {
+        if self.trace_syscalls {
+            ptrace::Options::PTRACE_O_TRACESYSGOOD
+        } else {
+            ptrace::Options::empty()
+        }
+    }
+
+    /// Waits for an inferior set going with `resume` to stop or exit.
+    pub fn wait_running(&mut self) -> Result<Status, PtraceError> {
+        let status = self.wait(None)?;
+        let status = self.classify_stop(status)?;
+        Ok(self.count_stop(status))
+    }
+
+    /// Stops the running inferior with SIGINT, as ctrl+c would. `wait_running` collects the stop.
+    pub fn interrupt(&self) -> Result<(), PtraceError> {
+        signal::kill(self.pid(), signal::SIGINT).map_err(self.ptrace_err("kill", None))
+    }
+
+    /// Stops the running inferior with SIGSTOP, for `profile` to take a sample. `wait_running`
+    /// collects the stop.
+    pub fn stop(&self) -> Result<(), PtraceError> {
+        signal::kill(self.pid(), signal::SIGSTOP).map_err(self.ptrace_err("kill", None))
+    }
+
+    /// Gets rid of a SIGSTOP from `stop` that is still pending because the inferior stopped for
+    /// something else first, so that it doesn't turn up at the next resume. The inferior is
+    /// continued just long enough for the signal to be delivered, which happens before it runs
+    /// another instruction, and the signal is then dropped. The stop's siginfo is kept.
+    pub fn discard_pending_stop(&mut self) -> Result<(), PtraceError> {
+        if !self.signal_pending(signal::SIGSTOP) {
+            return Ok(());
+        }
+        let info = self.last_siginfo.get();
+        ptrace::cont(self.pid(), None).map_err(self.ptrace_err("PTRACE_CONT", None))?;
+        self.wait(None)?;
+        self.last_siginfo.set(info);
+        Ok(())
+    }
+
+    /// Whether `sig` is waiting to be delivered to the inferior, from the pending masks in
+    /// /proc/<pid>/status (the thread's own, and the whole process's).
+    fn signal_pending(&self, sig: signal::Signal) -> bool {
+        let status = match fs::read_to_string(format!("/proc/{}/status", self.pid())) {
+            Ok(status) => status,
+            Err(_) => return false,
+        };
+        let bit = 1u64 << (sig as i32 - 1);
+        status
+            .lines()
+            .filter(|line| line.starts_with("SigPnd:") || line.starts_with("ShdPnd:"))
+            .filter_map(|line| u64::from_str_radix(line[7..].trim(), 16).ok())
+            .any(|mask| mask & bit != 0)
+    }
+
+    /// Counts a stop in the run statistics.
+    fn count_stop(&mut self, status: Status) -> Status {
+        match status {
+            Status::Stopped(_, _, StopReason::Syscall) => {}
+            Status::Stopped(_, _, reason) => {
+                self.stops += 1;
+                if let StopReason::Breakpoint(_) = reason {
+                    self.breakpoint_hits += 1;
+                }
+            }
+            Status::Exited(_) | Status::Signaled(_) => {}
+        }
+        status
+    }
+
+    /// Returns how many breakpoints are written into the process's code.
+    pub fn installed_breakpoint_count(&self) -> usize {
+        self.bp_to_original_byte.len()
+    }
+
+    /// Returns true once the process has exited or been killed by a signal.
+    pub fn has_exited(&self) -> bool {
+        self.finished.get().is_some()
+    }
+
+    /// Returns the run time and stop counts since this inferior was first continued.
+    pub fn stats(&self) -> RunStats {
+        let elapsed = match (self.started, self.finished.get()) {
+            (Some(started), Some(finished)) => finished.duration_since(started),
+            (Some(started), None) => started.elapsed(),
+            (None, _) => Duration::default(),
+        };
+        RunStats {
+            elapsed,
+            stops: self.stops,
+            breakpoint_hits: self.breakpoint_hits,
+        }
+    }
+
+    /// Executes a single instruction, stepping over a breakpoint at the current address if there
+    /// is one.
+    pub fn step_instruction(&mut self) -> Result<Status, PtraceError> {
+        let rip = self.getregs()?.rip as usize;
+        let on_breakpoint = self.bp_to_original_byte.contains_key(&rip);
+        if on_breakpoint {
+            self.restore_original_byte(rip)?;
+        }
+        ptrace::step(self.pid(), None).map_err(self.ptrace_err("PTRACE_SINGLESTEP", None))?;
+        let status = self.wait(None);
+        if on_breakpoint {
+            if let Ok(Status::Stopped(..)) = status {
+                self.restore_int3(rip)?;
+            }
+        }
+        match status? {
+            Status::Stopped(signal::SIGTRAP, addr, _) => {
+                Ok(Status::Stopped(signal::SIGTRAP, addr, StopReason::Step))
+            }
+            other => Ok(other),
+        }
+    }
+
+    /// Works out whether a SIGTRAP came from one of our breakpoints. If it did, the instruction
+    /// pointer (which is just past the int3) is moved back onto the breakpoint address.
+    fn classify_stop(&mut self, status: Status) -> Result<Status, PtraceError> {
+        match status {
+            Status::Stopped(_, _, StopReason::Syscall) => Ok(status),
+            Status::Stopped(signal::SIGTRAP, rip, _) => {
+                let bp_addr = rip - 1;
+                if self.bp_to_original_byte.contains_key(&bp_addr) {
+                    let mut regs = self.getregs()?;
+                    regs.rip = bp_addr as u64;
+                    ptrace::setregs(self.pid(), regs)
+                        .map_err(self.ptrace_err("PTRACE_SETREGS", None))?;
+                    Ok(Status::Stopped(signal::SIGTRAP, bp_addr, StopReason::Breakpoint(bp_addr)))
+                } else if let Some(slot) = self.take_triggered_watchpoint()? {
+                    Ok(Status::Stopped(
+                        signal::SIGTRAP,
+                        rip,
+                        StopReason::Watchpoint(slot),
+                    ))
+                } else {
+                    Ok(Status::Stopped(signal::SIGTRAP, rip, StopReason::Trap))
+                }
+            }
+            other => Ok(other),
+        }
+    }
+
+    /// Arms a hardware watchpoint in debug register `slot` that traps after any write to the
+    /// `len` bytes at `addr`. `len` must be 1, 2, 4 or 8, and `addr` aligned to it.
+    pub fn set_watchpoint(
+        &mut self,
+        slot: usize,
+        addr: usize,
+        len: usize,
+    ) -> Result<(), PtraceError> {
+        let len_bits: u64 = match len {
+            1 => 0b00,
+            2 => 0b01,
+            4 => 0b11,
+            _ => 0b10,
+        };
+        // Local enable bit, then "break on data writes" and the length in the slot's nibble
+        let mut control = self.debug_control & !(0b11 << (2 * slot)) & !(0xf << (16 + 4 * slot));
+        control |= 1 << (2 * slot);
+        control |= (0b01 | len_bits << 2) << (16 + 4 * slot);
+        self.poke_debug_register(slot, addr as u64)?;
+        self.poke_debug_register(DR7, control)?;
+        self.debug_control = control;
+        Ok(())
+    }
+
+    /// Disarms the watchpoint in debug register `slot`.
+    pub fn clear_watchpoint(&mut self, slot: usize) -> Result<(), PtraceError> {
+        let control = self.debug_control & !(0b11 << (2 * slot)) & !(0xf << (16 + 4 * slot));
+        self.poke_debug_register(DR7, control)?;
+        self.debug_control = control;
+        Ok(())
+    }
+
+    /// After a SIGTRAP, checks DR6 for a watchpoint that fired and returns its slot. DR6 is
+    /// sticky, so it's cleared for the next stop.
+    fn take_triggered_watchpoint(&self) -> Result<Option<usize>, PtraceError> {
+        if self.debug_control == 0 {
+            return Ok(None);
+        }
+        let status = self.peek_debug_register(DR6)?;
+        let triggered = (0..WATCHPOINT_SLOTS).find(|slot| status & (1 << slot) != 0);
+        if triggered.is_some() {
+            self.poke_debug_register(DR6, 0)?;
+        }
+        Ok(triggered)
+    }
+
+    /// DR0 to DR3 (the watched addresses), DR6 (status) and DR7 (control), with their numbers.
+    pub fn debug_registers(&self) -> Result<Vec<(usize, u64)>, PtraceError> {
+        [0, 1, 2, 3, DR6, DR7]
+            .iter()
+            .map(|&register| Ok((register, self.peek_debug_register(register)?)))
+            .collect()
+    }
+
+    fn peek_debug_register(&self, register: usize) -> Result<u64, PtraceError> {
+        let offset = DEBUG_REGISTERS_OFFSET + register * size_of::<u64>();
+        let value =
+            unsafe { libc::ptrace(libc::PTRACE_PEEKUSER, self.pid().as_raw(), offset, 0usize) };
+        Errno::result(value)
+            .map(|value| value as u64)
+            .map_err(self.ptrace_err("PTRACE_PEEKUSER", None))
+    }
+
+    fn poke_debug_register(&self, register: usize, value: u64) -> Result<(), PtraceError> {
+        let offset = DEBUG_REGISTERS_OFFSET + register * size_of::<u64>();
+        let result =
+            unsafe { libc::ptrace(libc::PTRACE_POKEUSER, self.pid().as_raw(), offset, value) };
+        Errno::result(result)
+            .map(drop)
+            .map_err(self.ptrace_err("PTRACE_POKEUSER", None))
+    }
+
+    /// Kills the process and reaps it. A process that has already exited was reaped by the wait
+    /// that saw it go, so there is nothing left to do. Nor is there for one that went without our
+    /// seeing it, such as one killed and reaped already: kill fails with ESRCH and waitpid with
+    /// ECHILD, and both are ignored.
+    pub fn kill(&mut self) {
+        if self.has_exited() {
+            return;
+        }
+        let killed = match &mut self.child {
+            Some(child) => match child.kill() {
+                Err(err) if err.raw_os_error() != Some(libc::ESRCH) => false,
+                _ => true,
+            },
+            None => match signal::kill(self.pid, signal::SIGKILL) {
+                Ok(()) | Err(nix::Error::Sys(Errno::ESRCH)) => true,
+                Err(_) => false,
+            },
+        };
+        // Any other failure can't happen to a process we trace; but if it somehow did, the process
+        // would still be there, and waiting for it would hang
+        if !killed {
+            return;
+        }
         loop {
-            let function_name = print_function_line(rip as usize, debug_data)?;
-            if function_name == "main" {
+            match waitpid(self.pid(), None) {
+                // A signal, such as the user's Ctrl+C, came in while we waited
+                Err(nix::Error::Sys(Errno::EINTR)) => continue,
+                // Reaped now, or (ECHILD) before
+                _ => break,
+            }
+        }
+    }
+
+    /// Forks the inferior by having it make a fork system call where it is stopped, and returns
+    /// the new process: stopped, traced, and with the same registers. The copy inherits the
+    /// int3s patched into the code, so it gets a copy of our breakpoint bookkeeping too. Hardware
+    /// watchpoints aren't inherited.
+    pub fn fork(&mut self) -> Result<Inferior, PtraceError> {
+        let regs = self.getregs()?;
+        let pc = regs.rip as usize;
+        // The code as it is in memory, int3s included, to put back afterwards
+        let code = self.read_bytes_by_word(pc, 2)?;
+        self.write_bytes(pc, &[0x0f, 0x05])?;
+        let mut fork_regs = regs;
+        fork_regs.rax = libc::SYS_fork as u64;
+        self.setregs(fork_regs)?;
+        let forked = self.step_fork();
+        // Put the code and registers back, whether or not the fork worked
+        self.write_bytes(pc, &code)?;
+        self.setregs(regs)?;
+        let mut copy = Inferior {
+            child: None,
+            pid: forked?,
+            bp_to_original_byte: self.bp_to_original_byte.clone(),
+            patched_words: self.patched_words.clone(),
+            breakpoint_time: Duration::default(),
+            tty: None,
+            debug_control: 0,
+            started: None,
+            finished: Cell::new(None),
+            stops: 0,
+            breakpoint_hits: 0,
+            last_siginfo: Cell::new(None),
+            trace_syscalls: false,
+            terminal_settings: self.terminal_settings.clone(),
+        };
+        copy.write_bytes(pc, &code)?;
+        copy.setregs(regs)?;
+        copy.set_syscall_tracing(self.trace_syscalls)?;
+        Ok(copy)
+    }
+
+    /// Single-steps over the fork system call set up by `fork`, with fork tracing on so that
+    /// the new process starts out traced by us. Returns its pid once it has stopped.
+    fn step_fork(&self) -> Result<Pid, PtraceError> {
+        let unexpected = self.ptrace_err("fork", None);
+        ptrace::setoptions(self.pid, self.ptrace_options() | ptrace::Options::PTRACE_O_TRACEFORK)
+            .map_err(self.ptrace_err("PTRACE_SETOPTIONS", None))?;
+        ptrace::step(self.pid, None).map_err(self.ptrace_err("PTRACE_SINGLESTEP", None))?;
+        let forked = match waitpid(self.pid, None).map_err(self.ptrace_err("waitpid", None))? {
+            WaitStatus::PtraceEvent(_, _, event) if event == libc::PTRACE_EVENT_FORK => {
+                let pid = ptrace::getevent(self.pid)
+                    .map_err(self.ptrace_err("PTRACE_GETEVENTMSG", None))?;
+                Pid::from_raw(pid as i32)
+            }
+            _ => return Err(unexpected(nix::Error::Sys(Errno::ECHILD))),
+        };
+        // Finish the system call in the parent, and wait for the new process's initial stop
+        ptrace::step(self.pid, None).map_err(self.ptrace_err("PTRACE_SINGLESTEP", None))?;
+        waitpid(self.pid, None).map_err(self.ptrace_err("waitpid", None))?;
+        waitpid(forked, None).map_err(self.ptrace_err("waitpid", None))?;
+        ptrace::setoptions(self.pid, self.ptrace_options())
+            .map_err(self.ptrace_err("PTRACE_SETOPTIONS", None))?;
+        Ok(forked)
+    }
+
+    /// Hands this inferior's pseudo-terminal (if it has one) to `other`, which shares it.
+    pub fn give_terminal(&mut self, other: &mut Inferior) {
+        other.tty = self.tty.take();
+    }
+    /// Walks the frame pointer chain from the current instruction up to main. Frames without
+    /// debug info are kept (with no function or line) and the walk goes on through them. It stops
+    /// early at a return address of 0 or one that `in_code` says isn't in any executable
+    /// mapping, which is where the chain ends or goes astray, and after `limit` frames (unless
+    /// that is 0). `symbolize` looks up the functions and source line for an address.
+    pub fn backtrace<F, G>(
+        &self,
+        symbolize: F,
+        in_code: G,
+        limit: usize,
+    ) -> Result<Vec<Frame>, PtraceError>
+    where
+        F: Fn(usize) -> Vec<(Option<String>, Option<Line>)>,
+        G: Fn(usize) -> bool,
+    {
+        let mut frames: Vec<Frame> = Vec::new();
+        for (depth, stack_frame) in self.stack_frames()?.enumerate() {
+            let rip = match stack_frame {
+                Ok(stack_frame) => stack_frame.rip,
+                // An unreadable frame record ends the chain, but not the backtrace so far
+                Err(_) if depth > 0 => break,
+                Err(err) => return Err(err),
+            };
+            if depth > 0 && (rip == 0 || !in_code(rip)) {
+                break;
+            }
+            if limit != 0 && depth == limit {
+                break;
+            }
+            // Inlined functions come first, then the function the code belongs to
+            let mut chain = symbolize(rip);
+            if chain.is_empty() {
+                chain.push((None, None));
+            }
+            let outermost = chain.len() - 1;
+            for (idx, (function, line)) in chain.into_iter().enumerate() {
+                frames.push(Frame {
+                    address: rip,
+                    function,
+                    line,
+                    inlined: idx < outermost,
+                });
+            }
+            if frames.last().unwrap().function.as_deref() == Some("main") {
                 break;
             }
-            rip = ptrace::read(self.pid(), (rbp + 8) as ptrace::AddressType)? as u64;
-            rbp = ptrace::read(self.pid(), rbp as ptrace::AddressType)? as u64;
         }
-        Ok(())
+        Ok(frames)
+    }
+
+    /// Starts a walk up the stack from the current instruction. See StackFrames.
+    pub fn stack_frames(&self) -> Result<StackFrames<'_>, PtraceError> {
+        let regs = self.getregs()?;
+        Ok(StackFrames {
+            inferior: self,
+            next: Some(Ok(StackFrame {
+                rip: regs.rip as usize,
+                rbp: regs.rbp as usize,
+                cfa: (regs.rbp as usize).wrapping_add(16),
+            })),
+        })
+    }
+    /// Returns true if a breakpoint is installed at `addr`.
+    pub fn has_break_point(&self, addr: usize) -> bool {
+        self.bp_to_original_byte.contains_key(&addr)
+    }
+
+    /// Forgets the breakpoints in [start, end) without restoring anything, for code that has been
+    /// unmapped (a library that was unloaded).
+    pub fn forget_break_points_in(&mut self, start: usize, end: usize) {
+        self.bp_to_original_byte
+            .retain(|&addr, _| addr < start || addr >= end);
+        let still_patched: Vec<usize> = self
+            .bp_to_original_byte
+            .keys()
+            .map(|&addr| align_addr_to_word(addr))
+            .collect();
+        self.patched_words
+            .retain(|aligned_addr, _| still_patched.contains(aligned_addr));
     }
-    fn write_byte(&mut self, addr: usize, val: u8) -> Result<u8, nix::Error> {
+
+    /// Restores the original byte at `addr` and forgets the breakpoint. The original byte also
+    /// goes into the word's patched copy, so that stepping over another breakpoint in the same
+    /// word doesn't write the int3 back. If the inferior is stopped on the breakpoint
+    /// (classify_stop has already moved rip back onto it), the next resume just runs the restored
+    /// instruction: there is nothing left to step over. Does nothing if `addr` isn't armed.
+    pub fn disarm(&mut self, addr: usize) -> Result<(), PtraceError> {
+        let origin_byte = match self.bp_to_original_byte.get(&addr) {
+            Some(&byte) => byte,
+            None => return Ok(()),
+        };
+        let started = Instant::now();
+        self.restore_original_byte(addr)?;
+        self.bp_to_original_byte.remove(&addr);
         let aligned_addr = align_addr_to_word(addr);
-        let byte_offset = addr - aligned_addr;
-        let word = ptrace::read(self.pid(), aligned_addr as ptrace::AddressType)? as u64;
-        let orig_byte = (word >> 8 * byte_offset) & 0xff;
-        let masked_word = word & !(0xff << 8 * byte_offset);
-        let updated_word = masked_word | ((val as u64) << 8 * byte_offset);
+        if self
+            .bp_to_original_byte
+            .keys()
+            .any(|bp_addr| align_addr_to_word(*bp_addr) == aligned_addr)
+        {
+            let word = self.patched_words[&aligned_addr];
+            self.patched_words
+                .insert(aligned_addr, set_byte(word, addr - aligned_addr, origin_byte));
+        } else {
+            self.patched_words.remove(&aligned_addr);
+        }
+        self.breakpoint_time += started.elapsed();
+        Ok(())
+    }
+
+    /// Returns (and resets) the time spent on breakpoint bookkeeping since the last call.
+    pub fn take_breakpoint_time(&mut self) -> Duration {
+        std::mem::replace(&mut self.breakpoint_time, Duration::default())
+    }
+
+    /// Reads the inferior's registers.
+    pub fn getregs(&self) -> Result<libc::user_regs_struct, PtraceError> {
+        ptrace::getregs(self.pid()).map_err(self.ptrace_err("PTRACE_GETREGS", None))
+    }
+
+    /// Reads the inferior's x87, SSE and MXCSR registers.
+    pub fn getfpregs(&self) -> Result<libc::user_fpregs_struct, PtraceError> {
+        // Plain old data, which the kernel fills in
+        let mut fpregs: libc::user_fpregs_struct = unsafe { std::mem::zeroed() };
+        let result = unsafe {
+            libc::ptrace(
+                libc::PTRACE_GETFPREGS,
+                self.pid().as_raw(),
+                0usize,
+                &mut fpregs as *mut libc::user_fpregs_struct,
+            )
+        };
+        Errno::result(result)
+            .map(|_| fpregs)
+            .map_err(self.ptrace_err("PTRACE_GETFPREGS", None))
+    }
+
+    /// Overwrites the inferior's registers.
+    pub fn setregs(&self, regs: libc::user_regs_struct) -> Result<(), PtraceError> {
+        ptrace::setregs(self.pid(), regs).map_err(self.ptrace_err("PTRACE_SETREGS", None))
+    }
+
+    /// Reads one word of the inferior's memory.
+    pub fn read_word(&self, addr: usize) -> Result<u64, PtraceError> {
+        ptrace::read(self.pid(), addr as ptrace::AddressType)
+            .map(|word| word as u64)
+            .map_err(self.ptrace_err("PTRACE_PEEKDATA", Some(addr)))
+    }
+
+    /// Reads `len` bytes of the inferior's memory starting at `addr`. Uses a single
+    /// process_vm_readv call when possible, and falls back to reading word by word with ptrace
+    /// when that syscall is unavailable or not permitted. Installed breakpoints are hidden: their
+    /// original bytes are returned rather than the int3.
+    pub fn read_bytes(&self, addr: usize, len: usize) -> Result<Vec<u8>, PtraceError> {
+        // A range that wraps past the top of the address space can't all be mapped
+        if addr.checked_add(len).is_none() {
+            let err = nix::Error::Sys(Errno::EFAULT);
+            return Err(self.ptrace_err("PTRACE_PEEKDATA", Some(addr))(err));
+        }
+        let mut bytes = vec![0u8; len];
+        let remote = [RemoteIoVec { base: addr, len }];
+        let done = process_vm_readv(self.pid(), &[IoVec::from_mut_slice(&mut bytes)], &remote);
+        let mut bytes = match done {
+            Ok(n) if n == len => bytes,
+            _ => self.read_bytes_by_word(addr, len)?,
+        };
+        for (&bp_addr, &orig_byte) in self.bp_to_original_byte.iter() {
+            if addr <= bp_addr && bp_addr < addr + len {
+                bytes[bp_addr - addr] = orig_byte;
+            }
+        }
+        Ok(bytes)
+    }
+
+    typed_reads! {
+        read_u8: u8,
+        read_u16: u16,
+        read_u32: u32,
+        read_u64: u64,
+        read_i32: i32,
+        read_i64: i64,
+    }
+
+    fn read_bytes_by_word(&self, addr: usize, len: usize) -> Result<Vec<u8>, PtraceError> {
+        let word_size = size_of::<usize>();
+        let mut bytes = Vec::with_capacity(len + 2 * word_size);
+        let start = align_addr_to_word(addr);
+        let mut word_addr = start;
+        while word_addr < addr + len {
+            bytes.extend_from_slice(&self.read_word(word_addr)?.to_le_bytes());
+            word_addr += word_size;
+        }
+        Ok(bytes[addr - start..addr - start + len].to_vec())
+    }
+
+    /// Writes one (aligned) word of the inferior's memory.
+    pub fn write_word(&mut self, aligned_addr: usize, word: u64) -> Result<(), PtraceError> {
         ptrace::write(
             self.pid(),
             aligned_addr as ptrace::AddressType,
-            updated_word as *mut std::ffi::c_void,
-        )?;
-        Ok(orig_byte as u8)
+            word as *mut std::ffi::c_void,
+        )
+        .map_err(self.ptrace_err("PTRACE_POKEDATA", Some(aligned_addr)))
     }
-    fn install_break_points(&mut self, break_points: &mut Vec<usize>) -> Result<(), nix::Error> {
-        while let Some(addr) = break_points.pop() {
-            let origin_byte = self.write_byte(addr, 0xcc)?;
-            self.bp_to_original_byte.insert(addr, origin_byte);
+
+    /// Writes `bytes` to the inferior's memory at `addr`, a word at a time. Only the words at
+    /// either end that are partly covered are read, to keep the bytes around the write. Memory
+    /// is written as is: an int3 in the range is overwritten, and the breakpoint bookkeeping
+    /// isn't updated.
+    pub fn write_bytes(&mut self, addr: usize, bytes: &[u8]) -> Result<(), PtraceError> {
+        if bytes.is_empty() {
+            return Ok(());
+        }
+        let word_size = size_of::<usize>();
+        let start = align_addr_to_word(addr);
+        let end = align_addr_to_word(addr + bytes.len() - 1) + word_size;
+        let last = end - word_size;
+        let mut words = vec![0u8; end - start];
+        // The partly covered words at either end, which may be the same word
+        if addr != start {
+            words[..word_size].copy_from_slice(&self.read_word(start)?.to_le_bytes());
+        }
+        if addr + bytes.len() != end && (last != start || addr == start) {
+            words[last - start..].copy_from_slice(&self.read_word(last)?.to_le_bytes());
+        }
+        words[addr - start..addr - start + bytes.len()].copy_from_slice(bytes);
+        for (idx, word) in words.chunks(word_size).enumerate() {
+            let word = u64::from_le_bytes(word.try_into().unwrap());
+            self.write_word(start + idx * word_size, word)?;
         }
         Ok(())
     }
-}
 
-fn print_function_line(rip: usize, debug_data: &DwarfData) -> Result<String, nix::Error> {
-    if let Some(line) = debug_data.get_line_from_addr(rip as usize) {
-        if let Some(function_name) = debug_data.get_function_from_addr(rip as usize) {
-            println!("{} ({})", function_name, line);
-            Ok(function_name)
-        } else {
-            println!("no function name");
-            Err(nix::Error::Sys(nix::errno::Errno::UnknownErrno))
+    /// Puts the original byte back at an armed breakpoint for a moment, to run the instruction
+    /// under it, leaving any other breakpoints in the same word armed.
+    fn restore_original_byte(&mut self, addr: usize) -> Result<(), PtraceError> {
+        let aligned_addr = align_addr_to_word(addr);
+        let word = self.patched_words[&aligned_addr];
+        let origin_byte = self.bp_to_original_byte[&addr];
+        self.write_word(aligned_addr, set_byte(word, addr - aligned_addr, origin_byte))
+    }
+
+    /// Writes the int3 back after restore_original_byte.
+    fn restore_int3(&mut self, addr: usize) -> Result<(), PtraceError> {
+        let aligned_addr = align_addr_to_word(addr);
+        let word = self.patched_words[&aligned_addr];
+        self.write_word(aligned_addr, word)
+    }
+
+    /// Patches an int3 into `addr`, unless it is armed already.
+    pub fn arm(&mut self, addr: usize) -> Result<(), BreakpointError> {
+        match self.arm_all(&[addr]).pop() {
+            Some(err) => Err(err),
+            None => Ok(()),
         }
-    } else {
-        println!("no line number");
-        Err(nix::Error::Sys(nix::errno::Errno::UnknownErrno))
     }
+
+    /// Patches an int3 into every address in `addrs` that doesn't already have one. Addresses
+    /// outside executable code are left alone. Breakpoints that share a word are armed with one
+    /// read and one write. A failure doesn't stop the remaining breakpoints from being armed; all
+    /// failures are returned.
+    pub fn arm_all(&mut self, addrs: &[usize]) -> Vec<BreakpointError> {
+        let mut errors = Vec::new();
+        let new: Vec<usize> = addrs
+            .iter()
+            .copied()
+            .filter(|addr| !self.bp_to_original_byte.contains_key(addr))
+            .collect();
+        if new.is_empty() {
+            return errors;
+        }
+        let started = Instant::now();
+        let code_ranges = self.code_ranges();
+        let mut by_word: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for addr in new {
+            if let Some(ranges) = &code_ranges {
+                if !ranges.iter().any(|(start, end)| *start <= addr && addr < *end) {
+                    errors.push(BreakpointError::NotInCode(addr));
+                    continue;
+                }
+            }
+            let addrs = by_word.entry(align_addr_to_word(addr)).or_default();
+            if !addrs.contains(&addr) {
+                addrs.push(addr);
+            }
+        }
+        for (aligned_addr, addrs) in by_word {
+            let word = match self.patched_words.get(&aligned_addr) {
+                Some(&word) => Ok(word),
+                None => self.read_word(aligned_addr),
+            };
+            let mut patched = match word {
+                Ok(word) => word,
+                Err(err) => {
+                    errors.extend(
+                        addrs
+                            .iter()
+                            .map(|&addr| BreakpointError::Ptrace(addr, err.clone())),
+                    );
+                    continue;
+                }
+            };
+            let mut origin_bytes = Vec::new();
+            for &addr in &addrs {
+                let offset = addr - aligned_addr;
+                origin_bytes.push((addr, (patched >> (8 * offset)) as u8));
+                patched = set_byte(patched, offset, 0xcc);
+            }
+            match self.write_word(aligned_addr, patched) {
+                Ok(()) => {
+                    self.patched_words.insert(aligned_addr, patched);
+                    self.bp_to_original_byte.extend(origin_bytes);
+                }
+                Err(err) => {
+                    errors.extend(
+                        addrs
+                            .iter()
+                            .map(|&addr| BreakpointError::Ptrace(addr, err.clone())),
+                    );
+                }
+            }
+        }
+        self.breakpoint_time += started.elapsed();
+        errors
+    }
+
+    /// Returns the address ranges of the inferior's executable mappings (the program and its
+    /// shared libraries), from /proc/<pid>/maps. Returns None if they can't be read, in which case
+    /// callers should skip the check rather than refuse every address.
+    fn code_ranges(&self) -> Option<Vec<(usize, usize)>> {
+        let mappings = proc_maps::read(self.pid()).ok()?;
+        Some(
+            mappings
+                .iter()
+                .filter(|m| m.is_executable() && m.path.is_some())
+                .map(|m| (m.start, m.end))
+                .collect(),
+        )
+    }
+}
+
+/// Returns `word` with the byte at `offset` (counting from the least significant byte) replaced.
+fn set_byte(word: u64, offset: usize, val: u8) -> u64 {
+    (word & !(0xff << (8 * offset))) | ((val as u64) << (8 * offset))
 }
 
 fn align_addr_to_word(addr: usize) -> usize {
     addr & (-(size_of::<usize>() as isize) as usize)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A sample program, stopped at its first instruction.
+    fn inferior() -> Inferior {
+        let target = format!("{}/samples/count", env!("CARGO_MANIFEST_DIR"));
+        let cwd = Path::new(env!("CARGO_MANIFEST_DIR"));
+        Inferior::new(&target, &[], &Environment::default(), Redirects::default(), false, cwd, true)
+            .unwrap()
+    }
+
+    #[test]
+    fn write_bytes_keeps_the_bytes_around_it() {
+        let mut inferior = inferior();
+        // The words above the stack pointer (argc and argv) are mapped and writable
+        let base = align_addr_to_word(inferior.getregs().unwrap().rsp as usize);
+        let len = 4 * size_of::<usize>();
+        let cases = [
+            // (offset from a word boundary, length)
+            (3, 2),  // starts and ends in the middle of one word
+            (5, 14), // starts mid-word, spans a whole word and ends mid-word
+            (8, 8),  // exactly one word
+            (8, 3),  // starts on a boundary, ends mid-word
+            (13, 3), // starts mid-word, ends on a boundary
+            (7, 1),  // the last byte of a word
+            (0, 32), // several whole words
+        ];
+        for (idx, &(offset, count)) in cases.iter().enumerate() {
+            let before = inferior.read_bytes(base, len).unwrap();
+            let patch: Vec<u8> = (0..count).map(|byte| (idx * 0x20 + byte) as u8).collect();
+            inferior.write_bytes(base + offset, &patch).unwrap();
+            let mut expected = before.clone();
+            expected[offset..offset + count].copy_from_slice(&patch);
+            let after = inferior.read_bytes_by_word(base, len).unwrap();
+            assert_eq!(after, expected, "writing {} bytes at offset {}", count, offset);
+        }
+        inferior.kill();
+    }
+
+    #[test]
+    fn bulk_reads_match_word_by_word_reads() {
+        let mut inferior = inferior();
+        let regs = inferior.getregs().unwrap();
+        // Unaligned ranges of the program's text and of the stack on either side of rsp
+        let ranges = [
+            (regs.rip as usize, 64),
+            (regs.rip as usize + 3, 101),
+            (regs.rsp as usize - 5, 37),
+            (regs.rsp as usize, 256),
+        ];
+        for &(addr, len) in ranges.iter() {
+            let bulk = inferior.read_bytes(addr, len).unwrap();
+            assert_eq!(bulk.len(), len);
+            assert_eq!(bulk, inferior.read_bytes_by_word(addr, len).unwrap(), "at {:#x}", addr);
+        }
+        inferior.kill();
+    }
+
+    #[test]
+    fn reading_unmapped_memory_is_an_error() {
+        let mut inferior = inferior();
+        let stack = proc_maps::read(inferior.pid())
+            .unwrap()
+            .into_iter()
+            .find(|mapping| mapping.name == "[stack]")
+            .unwrap();
+        // Nothing at all mapped, a range running off the end of the stack, and one wrapping
+        // around the top of the address space
+        let ranges = [(0, 16), (stack.end - 8, 16), (usize::MAX - 7, 16)];
+        for &(addr, len) in ranges.iter() {
+            match inferior.read_bytes(addr, len) {
+                Err(err) => assert!(err.addr.is_some(), "{}", err),
+                Ok(_) => panic!("read {} bytes at {:#x}", len, addr),
+            }
+        }
+        assert_eq!(inferior.read_bytes(stack.end - 8, 8).unwrap().len(), 8);
+        inferior.kill();
+    }
+
+    #[test]
+    fn killing_a_process_that_has_gone() {
+        let mut inferior = inferior();
+        inferior.kill();
+        // Killed and reaped already
+        inferior.kill();
+        // Killed and reaped by someone else
+        let mut inferior = self::inferior();
+        signal::kill(inferior.pid(), signal::SIGKILL).unwrap();
+        waitpid(inferior.pid(), None).unwrap();
+        inferior.kill();
+    }
+
+    #[test]
+    fn reads_show_the_original_bytes_under_breakpoints() {
+        let mut inferior = inferior();
+        let rip = inferior.getregs().unwrap().rip as usize;
+        let original = inferior.read_bytes(rip, 24).unwrap();
+        let breakpoints = [rip + 1, rip + 2, rip + 9, rip + 23];
+        assert!(inferior.arm_all(&breakpoints).is_empty());
+        // The int3s are in memory...
+        for &addr in breakpoints.iter() {
+            let word = inferior.read_word(align_addr_to_word(addr)).unwrap();
+            assert_eq!(word.to_le_bytes()[addr - align_addr_to_word(addr)], 0xcc);
+        }
+        // ...but no read shows them, whether it covers, starts or ends at a breakpoint
+        assert_eq!(inferior.read_bytes(rip, 24).unwrap(), original);
+        assert_eq!(inferior.read_bytes(rip + 2, 8).unwrap(), &original[2..10]);
+        assert_eq!(inferior.read_bytes(rip + 5, 5).unwrap(), &original[5..10]);
+        assert_eq!(inferior.read_bytes(rip + 23, 1).unwrap(), &original[23..]);
+        assert_eq!(inferior.read_u8(rip + 9).unwrap(), original[9]);
+        let word = u16::from_le_bytes([original[1], original[2]]);
+        assert_eq!(inferior.read_u16(rip + 1).unwrap(), word);
+        inferior.kill();
+    }
+}
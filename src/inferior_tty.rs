@@ -0,0 +1,103 @@
+//! Runs the inferior on its own pseudo-terminal. Whatever the inferior writes to the pty is relayed
+//! to the debugger's stdout with a prefix, but only while the inferior is running, so that its
+//! output never interleaves with the (deet) prompt.
+
+use nix::pty::openpty;
+use nix::unistd::{close, dup};
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::unix::io::{FromRawFd, RawFd};
+use std::process::Stdio;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+const PREFIX: &str = "[inferior] ";
+
+pub struct InferiorTty {
+    master: RawFd,
+    /// Our copy of the slave side. Closed once the child has been spawned with its own copies.
+    slave: Option<RawFd>,
+    /// Whether the inferior is running, i.e. whether the relay thread may print.
+    running: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl InferiorTty {
+    pub fn open() -> Result<InferiorTty, nix::Error> {
+        let pty = openpty(None, None)?;
+        Ok(InferiorTty {
+            master: pty.master,
+            slave: Some(pty.slave),
+            running: Arc::new((Mutex::new(false), Condvar::new())),
+        })
+    }
+
+    pub fn master_fd(&self) -> RawFd {
+        self.master
+    }
+
+    pub fn slave_fd(&self) -> Option<RawFd> {
+        self.slave
+    }
+
+    /// Returns a fresh handle to the slave side, for use as one of the child's standard streams.
+    pub fn slave_stdio(&self) -> Result<Stdio, nix::Error> {
+        let slave = self.slave.ok_or(nix::Error::Sys(nix::errno::Errno::EBADF))?;
+        let fd = dup(slave)?;
+        Ok(unsafe { Stdio::from_raw_fd(fd) })
+    }
+
+    /// Closes our copy of the slave side (the child holds its own) and starts the thread that
+    /// copies the inferior's output to our stdout.
+    pub fn start_relay(&mut self) -> Result<(), nix::Error> {
+        if let Some(slave) = self.slave.take() {
+            close(slave)?;
+        }
+        let mut master = unsafe { File::from_raw_fd(dup(self.master)?) };
+        let running = self.running.clone();
+        thread::spawn(move || {
+            let mut buf = [0u8; 4096];
+            let mut at_line_start = true;
+            // Reads fail with EIO once the inferior has exited and no slave handles remain
+            while let Ok(n) = master.read(&mut buf) {
+                if n == 0 {
+                    break;
+                }
+                let (lock, cvar) = &*running;
+                let mut running = lock.lock().unwrap();
+                while !*running {
+                    running = cvar.wait(running).unwrap();
+                }
+                let stdout = io::stdout();
+                let mut out = stdout.lock();
+                for &byte in &buf[..n] {
+                    if at_line_start {
+                        let _ = out.write_all(PREFIX.as_bytes());
+                    }
+                    let _ = out.write_all(&[byte]);
+                    at_line_start = byte == b'\n';
+                }
+                let _ = out.flush();
+            }
+        });
+        Ok(())
+    }
+
+    /// Lets the relay thread print (when the inferior is resumed) or holds its output back (when
+    /// the inferior stops).
+    pub fn set_running(&self, running: bool) {
+        let (lock, cvar) = &*self.running;
+        *lock.lock().unwrap() = running;
+        cvar.notify_all();
+    }
+}
+
+impl Drop for InferiorTty {
+    fn drop(&mut self) {
+        // Let the relay thread flush anything it is holding so that it can exit
+        self.set_running(true);
+        if let Some(slave) = self.slave.take() {
+            let _ = close(slave);
+        }
+        let _ = close(self.master);
+    }
+}
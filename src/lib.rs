@@ -0,0 +1,41 @@
+//! deet: a small debugger for x86-64 Linux programs. `session::Session` is the programmatic API;
+//! `debugger::Debugger` is the interactive REPL built on top of it.
+
+/// Prints a line of free-form debugger output: plain text normally, or a `message` event in JSON
+/// mode.
+macro_rules! report {
+    ($($arg:tt)*) => {
+        $crate::output::emit(&$crate::output::Event::Message(&format!($($arg)*)))
+    };
+}
+
+mod addr_index;
+mod background;
+pub mod breakpoint;
+mod completer;
+mod coverage;
+pub mod dap;
+pub mod debugger;
+mod debugger_command;
+pub mod dwarf_data;
+mod dwarf_expr;
+pub mod expr;
+pub mod gdbserver;
+mod gimli_wrapper;
+mod heap;
+pub mod inferior;
+mod inferior_tty;
+mod json;
+pub mod output;
+pub mod output_capture;
+mod printf;
+mod proc_maps;
+mod registers;
+mod rsp;
+pub mod session;
+pub mod shared_library;
+pub mod skip;
+mod source_cache;
+mod syscalls;
+mod terminal;
+mod tui;
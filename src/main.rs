@@ -1,24 +1,107 @@
-mod debugger;
-mod debugger_command;
-mod inferior;
-mod dwarf_data;
-mod gimli_wrapper;
-
-use crate::debugger::Debugger;
-use nix::sys::signal::{signal, SigHandler, Signal};
+use deet::debugger::{Debugger, Options, SymbolsBanner};
+use deet::session::Session;
+use deet::{dap, gdbserver, inferior, output};
+use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
 use std::env;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        println!("Usage: {} <target program>", args[0]);
-        std::process::exit(1);
+    let mut options = Options::default();
+    let mut target = None;
+    let mut no_color = false;
+    let mut gdbserver = None;
+    let mut adapter = false;
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--nx" | "-nx" | "-n" => options.skip_init_files = true,
+            "--batch" | "-batch" => options.batch = true,
+            "--no-color" => no_color = true,
+            "--quiet" | "-q" => options.symbols_banner = SymbolsBanner::Quiet,
+            "--verbose-symbols" => options.symbols_banner = SymbolsBanner::Full,
+            "--interpreter=json" => options.json = true,
+            "--tui" | "-tui" => options.tui = true,
+            "--ex" | "-ex" => match iter.next() {
+                Some(command) => options.commands.push(command.clone()),
+                None => usage(&args[0]),
+            },
+            "--dap" => adapter = true,
+            "--gdbserver" => match iter.next() {
+                Some(address) => gdbserver = Some(address.clone()),
+                None => usage(&args[0]),
+            },
+            "--history-file" => match iter.next() {
+                Some(path) => options.history_file = Some(path.clone()),
+                None => usage(&args[0]),
+            },
+            // Everything after the target is for the target, not for us
+            "--args" | "-args" => match iter.next() {
+                Some(program) if target.is_none() => {
+                    target = Some(program.clone());
+                    options.args = iter.by_ref().cloned().collect();
+                }
+                _ => usage(&args[0]),
+            },
+            _ if target.is_none() && !arg.starts_with('-') => target = Some(arg.clone()),
+            _ => usage(&args[0]),
+        }
     }
-    let target = &args[1];
+    let target = match target {
+        Some(target) => target,
+        None => usage(&args[0]),
+    };
+
+    output::init(no_color);
+    output::set_json_mode(options.json);
 
-    // Disable handling of ctrl+c in this process (so that ctrl+c only gets delivered to child
-    // processes)
-    unsafe { signal(Signal::SIGINT, SigHandler::SigIgn) }.expect("Error disabling SIGINT handling");
+    // The client does the debugging, so there's no prompt
+    if let Some(address) = gdbserver {
+        if let Err(err) = gdbserver::serve(&address, &target, &options.args) {
+            println!("{}: {}", target, err);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if adapter {
+        let mut session = match Session::new(&target) {
+            Ok(session) => session,
+            Err(err) => {
+                eprintln!("{}: {}", target, err);
+                std::process::exit(1);
+            }
+        };
+        session.args = options.args;
+        if let Err(err) = dap::serve(session) {
+            eprintln!("{}: {}", target, err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Forward ctrl+c to the inferior while it is running. At the prompt, rustyline puts the
+    // terminal in raw mode and reports ctrl+c itself, so the handler never fires there.
+    let sigint_action = SigAction::new(
+        SigHandler::Handler(inferior::forward_sigint),
+        SaFlags::SA_RESTART,
+        SigSet::empty(),
+    );
+    unsafe { sigaction(Signal::SIGINT, &sigint_action) }.expect("Error installing SIGINT handler");
+    // The inferior is the terminal's foreground group while it runs; taking the terminal back
+    // from a background group would stop us with SIGTTOU
+    let ignore = SigAction::new(SigHandler::SigIgn, SaFlags::empty(), SigSet::empty());
+    unsafe { sigaction(Signal::SIGTTOU, &ignore) }.expect("Error ignoring SIGTTOU");
+
+    let mut debugger = match Debugger::new(&target, options) {
+        Ok(debugger) => debugger,
+        Err(err) => {
+            println!("{}: {}", target, err);
+            std::process::exit(1);
+        }
+    };
+    std::process::exit(debugger.run());
+}
 
-    Debugger::new(target).run();
+fn usage(program: &str) -> ! {
+    println!("Usage: {} [--nx] [--batch] [--quiet | --verbose-symbols] [--no-color] [--interpreter=json] [--tui] [--history-file <path>] [--gdbserver [<host>]:<port> | --dap] [-ex <command>]... (<target program> | --args <target program> [<argument>...])", program);
+    std::process::exit(1);
 }
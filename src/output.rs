@@ -0,0 +1,2785 @@
+//! Formatting helpers for user-facing output. When styling is on (stdout is a terminal and the
+//! user hasn't disabled it), function names, source locations, addresses and signals are colored.
+
+use crate::coverage::FileCoverage;
+use crate::dwarf_data::{
+    DebugInfo, DwarfData, File as CompilationUnit, Line, LineRow, Location, Member, Type, TypeId,
+    TypeKind,
+};
+use crate::inferior::{Fault, Frame, RunStats, SignalInfo, StackFrame, StopReason};
+use crate::output_capture::Stream;
+use crate::registers;
+use crate::breakpoint::EnableMode;
+use crate::session::{Assertion, Catchpoint, LibraryCall, SolibEvent};
+use crate::syscalls::SyscallTrace;
+use crate::terminal;
+use nix::sys::signal::Signal;
+use nix::unistd::isatty;
+use std::cell::RefCell;
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static STYLE_ENABLED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    /// The transcript file for `set logging on`, which gets a copy of everything emitted
+    static LOG_FILE: RefCell<Option<File>> = RefCell::new(None);
+    /// How far the pager has got through the current command's output
+    static PAGER: RefCell<Pager> = RefCell::new(Pager::default());
+}
+
+static PAGINATION: AtomicBool = AtomicBool::new(true);
+
+#[derive(Default)]
+struct Pager {
+    /// Terminal rows written since the last pause
+    rows: usize,
+    /// Set when the user answered `q`: the rest of the command's output is dropped
+    quit: bool,
+}
+
+/// Turns styling on if stdout is a terminal, unless `--no-color` was given.
+pub fn init(no_color: bool) {
+    let is_tty = isatty(libc::STDOUT_FILENO).unwrap_or(false);
+    set_style(!no_color && is_tty);
+}
+
+/// Handles `set style on|off`.
+pub fn set_style(enabled: bool) {
+    STYLE_ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+pub fn style_enabled() -> bool {
+    STYLE_ENABLED.load(Ordering::SeqCst)
+}
+
+/// Handles `set pagination on|off`.
+pub fn set_pagination(enabled: bool) {
+    PAGINATION.store(enabled, Ordering::SeqCst);
+}
+
+pub fn pagination_enabled() -> bool {
+    PAGINATION.load(Ordering::SeqCst)
+}
+
+/// Starts the pager afresh, for the output of the next command.
+pub fn start_paging() {
+    PAGER.with(|pager| *pager.borrow_mut() = Pager::default());
+}
+
+/// The terminal's rows and columns if output is to be paged: pagination is on, output isn't
+/// JSON, and both stdin and stdout are terminals.
+fn page_size() -> Option<(usize, usize)> {
+    if !pagination_enabled() || json_mode() || !isatty(libc::STDIN_FILENO).unwrap_or(false) {
+        return None;
+    }
+    terminal::size()
+}
+
+/// Prints `text`, pausing each time it has filled the screen until the user presses return, or
+/// answers `q` to drop the rest of the command's output.
+fn print_paged(text: &str) {
+    let (rows, columns) = match page_size() {
+        Some((rows, columns)) => (rows, columns.max(1)),
+        None => {
+            println!("{}", text);
+            return;
+        }
+    };
+    PAGER.with(|pager| {
+        let mut pager = pager.borrow_mut();
+        for line in text.split('\n') {
+            // A long line wraps onto several rows; the last row is kept for the pause prompt
+            let height = (unstyled(line).chars().count().max(1) + columns - 1) / columns;
+            if pager.rows > 0 && pager.rows + height > rows - 1 {
+                print!("--Type <RET> for more, q to quit--");
+                let _ = io::stdout().flush();
+                let mut answer = String::new();
+                match io::stdin().read_line(&mut answer) {
+                    Ok(0) | Err(_) => println!(),
+                    Ok(_) => {}
+                }
+                if answer.is_empty() || answer.trim() == "q" {
+                    pager.quit = true;
+                    return;
+                }
+                pager.rows = 0;
+            }
+            println!("{}", line);
+            pager.rows += height;
+        }
+    });
+}
+
+/// Starts (Some) or stops (None) copying output to a transcript file.
+pub fn set_log_file(file: Option<File>) {
+    LOG_FILE.with(|log| *log.borrow_mut() = file);
+}
+
+/// Appends a line to the transcript, if logging is on, without the color escapes.
+fn log(text: &str) {
+    LOG_FILE.with(|log| {
+        if let Some(file) = log.borrow_mut().as_mut() {
+            // A failing transcript shouldn't get in the way of the session itself
+            let _ = writeln!(file, "{}", unstyled(text));
+        }
+    });
+}
+
+/// Removes the escape sequences added by `paint`.
+fn unstyled(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip to the end of the sequence, e.g. `\x1b[1;31m`
+            for c in &mut chars {
+                if c == 'm' {
+                    break;
+                }
+            }
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
+
+fn paint(color: &str, text: &str) -> String {
+    if style_enabled() {
+        format!("\x1b[{}m{}\x1b[0m", color, text)
+    } else {
+        text.to_string()
+    }
+}
+
+pub fn function(name: &str) -> String {
+    paint("33", name)
+}
+
+/// Styles a source location such as `samples/count.c:5`.
+pub fn location(location: &str) -> String {
+    paint("32", location)
+}
+
+pub fn address(addr: usize) -> String {
+    paint("34", &format!("{:#x}", addr))
+}
+
+pub fn signal(sig: Signal) -> String {
+    paint("1;31", &format!("{}", sig))
+}
+
+/// Renders the "where" part of a stop message or backtrace frame: `func at file:line` when the
+/// address has debug info, the function and address when only its name is known, otherwise just
+/// the address.
+pub fn code_location(addr: usize, function_name: Option<&str>, line: Option<&str>) -> String {
+    match (function_name, line) {
+        (Some(name), Some(line)) => format!("{} at {}", function(name), location(line)),
+        (Some(name), None) => format!("{} ({}, no line info)", function(name), address(addr)),
+        _ => address(addr),
+    }
+}
+
+/// One frame of a backtrace, numbered `level`.
+fn frame_line(level: usize, frame: &Frame) -> String {
+    let place = match &frame.function {
+        Some(_) => code_location(
+            frame.address,
+            frame.function.as_deref(),
+            frame.line.as_ref().map(|line| line.to_string()).as_deref(),
+        ),
+        // No symbol covers the address; the walk went on through it anyway
+        None => "?? ()".to_string(),
+    };
+    format!(
+        "#{:<2} {} in {}{}",
+        level,
+        address(frame.address),
+        place,
+        if frame.inlined { " (inlined)" } else { "" }
+    )
+}
+
+/// What a signal is called in stop messages, as in "SIGSEGV, Segmentation fault".
+fn signal_name(sig: Signal) -> Option<&'static str> {
+    match sig {
+        Signal::SIGSEGV => Some("Segmentation fault"),
+        Signal::SIGBUS => Some("Bus error"),
+        Signal::SIGFPE => Some("Arithmetic exception"),
+        Signal::SIGILL => Some("Illegal instruction"),
+        Signal::SIGTRAP => Some("Trace/breakpoint trap"),
+        Signal::SIGABRT => Some("Aborted"),
+        Signal::SIGINT => Some("Interrupt"),
+        _ => None,
+    }
+}
+
+/// "Program received signal SIGSEGV, Segmentation fault."
+fn received_signal(sig: Signal) -> String {
+    match signal_name(sig) {
+        Some(name) => format!("Program received signal {}, {}.", signal(sig), name),
+        None => format!("Program received signal {}.", signal(sig)),
+    }
+}
+
+static JSON_MODE: AtomicBool = AtomicBool::new(false);
+
+/// Switches between human-readable text and one JSON object per line (`--interpreter=json`).
+pub fn set_json_mode(enabled: bool) {
+    JSON_MODE.store(enabled, Ordering::SeqCst);
+}
+
+pub fn json_mode() -> bool {
+    JSON_MODE.load(Ordering::SeqCst)
+}
+
+/// One breakpoint in `info breakpoints`.
+#[derive(Debug, PartialEq)]
+pub struct BreakpointInfo {
+    pub id: usize,
+    /// The location as the user gave it
+    pub location: String,
+    pub enabled: bool,
+    pub mode: EnableMode,
+    /// The expression that must be nonzero for a hit to stop the program
+    pub condition: Option<String>,
+    /// Hits still to go past without stopping
+    pub ignore_count: usize,
+    pub hits: usize,
+    /// What a `dprintf` breakpoint prints: its format string and arguments
+    pub dprintf: Option<String>,
+    /// Where the breakpoint is; empty while it is pending
+    pub sites: Vec<BreakpointSite>,
+}
+
+/// One of the addresses a breakpoint is set at.
+#[derive(Debug, PartialEq)]
+pub struct BreakpointSite {
+    pub address: usize,
+    pub function: Option<String>,
+    pub line: Option<Line>,
+}
+
+/// One row of `info functions`.
+pub struct FunctionInfo {
+    /// The demangled name
+    pub name: String,
+    /// The mangled name, when asked for and different from `name`
+    pub linkage_name: Option<String>,
+    pub address: usize,
+    /// The file declaring the function
+    pub file: String,
+}
+
+/// One row of `info variables`.
+pub struct VariableInfo {
+    pub name: String,
+    /// The variable declared the way C would, e.g. `char buffer[64]`
+    pub declaration: String,
+    pub address: usize,
+    /// The file declaring the variable
+    pub file: String,
+}
+
+/// One frame of `backtrace full`, with its local variables. None when they couldn't be looked
+/// up, such as in a function without debug info.
+pub struct FrameLocals {
+    pub frame: Frame,
+    /// Each variable's name and value (or why it has none)
+    pub locals: Option<Vec<(String, String)>>,
+}
+
+/// The source files of one compilation unit, for `info sources`.
+pub struct SourcesInfo {
+    pub compilation_unit: String,
+    /// Each path as recorded in the line table, and whether it exists on disk
+    pub files: Vec<(String, bool)>,
+}
+
+/// A variable or parameter listed by `info scope`.
+pub struct ScopeVariable {
+    pub name: String,
+    /// Where it is kept at the location, such as "a variable in register $rbx"
+    pub location: String,
+    /// The size of its type in bytes
+    pub size: usize,
+}
+
+/// One loaded object in `info sharedlibrary`.
+pub struct SharedLibraryInfo {
+    pub path: String,
+    pub base: usize,
+    /// The start of its first executable mapping and the end of its last
+    pub text: Option<(usize, usize)>,
+    pub symbols: DebugInfo,
+}
+
+/// One checkpoint in `info checkpoints`.
+pub struct CheckpointInfo {
+    pub id: usize,
+    pub pid: i32,
+    pub address: usize,
+    pub function: Option<String>,
+    pub line: Option<Line>,
+}
+
+/// `info heap`: the blocks live according to heap tracking, and where most of them come from.
+pub struct HeapInfo {
+    pub live_blocks: usize,
+    pub live_bytes: usize,
+    pub allocations: usize,
+    pub frees: usize,
+    pub sites: Vec<HeapSiteInfo>,
+}
+
+/// A call site of malloc and friends, with the live blocks allocated there.
+pub struct HeapSiteInfo {
+    pub address: usize,
+    pub blocks: usize,
+    pub bytes: usize,
+    pub function: Option<String>,
+    pub line: Option<Line>,
+}
+
+/// The selected frame in `info frame`.
+pub struct FrameInfo {
+    pub level: usize,
+    pub frame: StackFrame,
+    pub function: Option<String>,
+    pub line: Option<Line>,
+    /// The calling frame, where the return address points
+    pub caller: Option<StackFrame>,
+    pub caller_function: Option<String>,
+    pub caller_line: Option<Line>,
+}
+
+/// What the debugger is controlling, for `info inferior`.
+pub struct InferiorInfo {
+    pub target: String,
+    /// The arguments the program is (or will be) run with
+    pub args: Vec<String>,
+    pub process: ProcessState,
+    /// Breakpoints currently written into the process's code
+    pub installed_breakpoints: usize,
+}
+
+/// The process part of `info inferior`.
+pub enum ProcessState {
+    /// There is no process
+    None,
+    Stopped {
+        pid: i32,
+        address: usize,
+        function: Option<String>,
+        line: Option<Line>,
+    },
+    Exited {
+        pid: i32,
+        code: i32,
+    },
+    Signaled {
+        pid: i32,
+        signal: Signal,
+    },
+}
+
+/// One executed instruction in `info record`.
+pub struct RecordedInstruction {
+    pub address: usize,
+    pub function: Option<String>,
+    pub line: Option<Line>,
+}
+
+/// One alias in `info aliases`.
+pub struct AliasInfo<'a> {
+    pub name: &'a str,
+    pub expansion: &'a str,
+}
+
+/// One expression in `info display`.
+pub struct DisplayInfo {
+    pub id: usize,
+    pub expression: String,
+    /// The `/f` format letter, if one was given
+    pub format: Option<char>,
+    pub enabled: bool,
+}
+
+/// Something the debugger tells the user about.
+pub enum Event<'a> {
+    Stopped {
+        signal: Signal,
+        address: usize,
+        reason: StopReason,
+        /// Id of the user breakpoint that was hit
+        breakpoint: Option<usize>,
+        function: Option<&'a str>,
+        line: Option<&'a Line>,
+    },
+    /// A stop at a catchpoint, with the assertion that failed if that's why abort was called
+    Caught {
+        catchpoint: Catchpoint,
+        address: usize,
+        function: Option<&'a str>,
+        line: Option<&'a Line>,
+        assertion: Option<&'a Assertion>,
+    },
+    /// The text of a source line, shown after a stop
+    SourceLine {
+        number: usize,
+        text: &'a str,
+    },
+    /// `list`: lines of a source file
+    SourceLines {
+        file: &'a str,
+        lines: &'a [(usize, String)],
+    },
+    /// `set context`: the source lines around a stop, `current` being the one stopped at
+    SourceContext {
+        lines: &'a [(usize, String)],
+        current: usize,
+    },
+    Exited(i32),
+    Signaled(Signal),
+    /// After an exit, and for `info stats`
+    RunStats(RunStats),
+    BreakpointSet {
+        id: usize,
+        /// Which of the breakpoint's locations this is (from 1), when it has several
+        sub_location: Option<usize>,
+        address: usize,
+        function: Option<&'a str>,
+        line: Option<&'a Line>,
+    },
+    WatchpointSet {
+        id: usize,
+        expression: &'a str,
+    },
+    /// A watched variable changed
+    WatchpointChanged {
+        id: usize,
+        expression: &'a str,
+        old: &'a str,
+        new: &'a str,
+    },
+    /// A watchpoint on a local variable was deleted because its frame returned
+    WatchpointScope(usize),
+    /// A breakpoint whose location doesn't resolve yet
+    BreakpointPending {
+        id: usize,
+        location: &'a str,
+    },
+    /// `clear`, with the breakpoints it deleted
+    BreakpointsDeleted(&'a [usize]),
+    /// `info breakpoints`
+    Breakpoints(&'a [BreakpointInfo]),
+    /// `info functions`
+    Functions(&'a [FunctionInfo]),
+    /// `info variables`
+    Variables(&'a [VariableInfo]),
+    /// `info sources`
+    Sources(&'a [SourcesInfo]),
+    /// `info scope`: the variables visible at `location`, which is in `function`
+    Scope {
+        location: &'a str,
+        function: &'a str,
+        variables: &'a [ScopeVariable],
+    },
+    Backtrace(&'a [Frame]),
+    /// `backtrace full`
+    BacktraceFull(&'a [FrameLocals]),
+    /// The frame `frame`, `up` or `down` selected
+    Frame {
+        level: usize,
+        frame: &'a Frame,
+    },
+    /// The result of `print`
+    Value {
+        expression: &'a str,
+        value: &'a str,
+    },
+    /// What the function `finish` ran out of returned, and its number in the value history
+    ReturnValue {
+        history: usize,
+        value: &'a str,
+    },
+    /// `info display`
+    Displays(&'a [DisplayInfo]),
+    /// One command in `show user`
+    UserCommand {
+        name: &'a str,
+        body: &'a [String],
+    },
+    /// `info aliases`
+    Aliases(&'a [AliasInfo<'a>]),
+    /// `info frame`
+    FrameInfo(&'a FrameInfo),
+    /// `info inferior`
+    InferiorInfo(&'a InferiorInfo),
+    /// `info siginfo`
+    SignalInfo(&'a SignalInfo),
+    /// `info registers`: names and values
+    Registers(&'a [(String, u64)]),
+    /// `info float`: the x87 registers and their control and status
+    FloatRegisters(&'a libc::user_fpregs_struct),
+    /// `info vector`: the XMM registers and MXCSR
+    VectorRegisters(&'a libc::user_fpregs_struct),
+    /// A system call the program made, while `set trace-syscalls` is on
+    Syscall(&'a SyscallTrace),
+    /// A call the program made into a shared library, while `set trace-calls` is on
+    LibraryCall(&'a LibraryCall),
+    /// `info trace-calls`: each library function called, with how many times
+    CallCounts(&'a [(String, usize)]),
+    /// `info heap`
+    Heap(&'a HeapInfo),
+    /// A shared library loaded or unloaded while the program ran
+    Solib(&'a SolibEvent),
+    /// `info catch`: the catchpoints that are on, with how many places each has a breakpoint
+    Catchpoints(&'a [(Catchpoint, usize)]),
+    /// `coverage report`: executed and total lines of each file
+    Coverage(&'a [FileCoverage]),
+    /// The histogram `profile` ends with: sample counts by function (or function and caller),
+    /// most first
+    Profile {
+        samples: usize,
+        rows: &'a [(String, usize)],
+    },
+    /// `info address`
+    SymbolAddress {
+        name: &'a str,
+        is_function: bool,
+        address: usize,
+        runtime_address: Option<usize>,
+    },
+    /// `info symbol`: `offset` bytes into `symbol`
+    SymbolAt {
+        address: usize,
+        symbol: &'a str,
+        offset: usize,
+        section: Option<&'a str>,
+    },
+    /// `info line`: the code of a line. `address` is the address asked about, when that isn't
+    /// the start of the line.
+    LineRange {
+        file: &'a str,
+        line: usize,
+        start: usize,
+        end: usize,
+        address: Option<usize>,
+    },
+    /// `ptype` (which sets `expand` to spell the type out) and `whatis`. None is the type of a
+    /// computed number.
+    Type {
+        data: &'a DwarfData,
+        type_id: Option<TypeId>,
+        expand: bool,
+    },
+    /// `maint info dwarf`: everything read from the debug info for one compilation unit
+    DwarfUnit {
+        data: &'a DwarfData,
+        unit: &'a CompilationUnit,
+    },
+    /// `maint info line-table`
+    LineTable(&'a [&'a LineRow]),
+    /// `x` and `hexdump`: the bytes at `address`
+    Memory {
+        address: usize,
+        bytes: &'a [u8],
+    },
+    /// `snapshot diff`: memory at `address` when the snapshot was taken, and now
+    MemoryDiff {
+        address: usize,
+        old: &'a [u8],
+        new: &'a [u8],
+    },
+    /// `find`: one match, in the mapping named `mapping` (empty for an anonymous one)
+    MemoryMatch {
+        address: usize,
+        mapping: &'a str,
+    },
+    /// `strings`: printable text found at `address`
+    MemoryString {
+        address: usize,
+        text: &'a str,
+    },
+    /// `info checkpoints`
+    Checkpoints(&'a [CheckpointInfo]),
+    /// `info sharedlibrary`, in load order
+    SharedLibraries(&'a [SharedLibraryInfo]),
+    /// A line the program wrote, with `set capture-output on`
+    InferiorOutput {
+        stream: Stream,
+        text: &'a str,
+    },
+    /// `info record`: the last instructions executed, oldest first, numbered from `first`
+    Record {
+        first: usize,
+        instructions: &'a [RecordedInstruction],
+    },
+    /// One auto-display expression, shown at a stop. The value is an error message if the
+    /// expression couldn't be evaluated.
+    Display {
+        id: usize,
+        expression: &'a str,
+        /// The `/f` format letter, if one was given
+        format: Option<char>,
+        value: Result<&'a str, &'a str>,
+    },
+    Message(&'a str),
+}
+
+/// Prints an event in the current output mode, through the pager.
+pub fn emit(event: &Event) {
+    if PAGER.with(|pager| pager.borrow().quit) {
+        return;
+    }
+    let text = if json_mode() {
+        render_json(event)
+    } else {
+        render_text(event)
+    };
+    print_paged(&text);
+    log(&text);
+}
+
+pub fn render_text(event: &Event) -> String {
+    match event {
+        Event::Stopped {
+            signal: sig,
+            address: addr,
+            reason,
+            breakpoint,
+            function: func,
+            line,
+        } => {
+            let place = code_location(*addr, *func, line.map(|line| line.to_string()).as_deref());
+            match (reason, breakpoint) {
+                (StopReason::Breakpoint(_), Some(id)) => format!("Breakpoint {}, {}", id, place),
+                (StopReason::Breakpoint(_), None) => format!("Temporary breakpoint, {}", place),
+                (StopReason::Step, _)
+                | (StopReason::Watchpoint(_), _)
+                | (StopReason::Syscall, _) => format!("{} in {}", address(*addr), place),
+                (StopReason::Signal(Some(fault)), _) => {
+                    let mut text = format!("Fault address {}", address(fault.address));
+                    if let Some(description) = fault.description(*sig) {
+                        text.push_str(&format!(" ({})", description));
+                    }
+                    format!(
+                        "{}\n{}\n{} in {}",
+                        received_signal(*sig),
+                        text,
+                        address(*addr),
+                        place
+                    )
+                }
+                // A SIGTRAP that isn't one of our breakpoints, steps or watchpoints: the program
+                // ran an int3 of its own or raised SIGTRAP
+                (StopReason::Trap, _) | (StopReason::Signal(None), _) => {
+                    format!("{}\n{} in {}", received_signal(*sig), address(*addr), place)
+                }
+            }
+        }
+        Event::Caught {
+            catchpoint,
+            address: addr,
+            function: func,
+            line,
+            assertion,
+        } => {
+            let place = code_location(*addr, *func, line.map(|line| line.to_string()).as_deref());
+            let mut text = format!("Caught {}, {}", catchpoint.name(), place);
+            if let Some(assertion) = assertion {
+                text.push_str(&format!(
+                    "\nAssertion `{}' failed in {} at {}.",
+                    assertion.expression,
+                    function(&assertion.function),
+                    location(&format!("{}:{}", assertion.file, assertion.line))
+                ));
+            }
+            text
+        }
+        Event::WatchpointSet { id, expression } => {
+            format!("Hardware watchpoint {}: {}", id, expression)
+        }
+        Event::WatchpointChanged {
+            id,
+            expression,
+            old,
+            new,
+        } => format!(
+            "\nHardware watchpoint {}: {}\n\nOld value = {}\nNew value = {}",
+            id, expression, old, new
+        ),
+        Event::WatchpointScope(id) => format!(
+            "\nWatchpoint {} deleted because the program has left the block in which its expression is valid.",
+            id
+        ),
+        Event::SourceLine { number, text } => format!("{}\t{}", number, text),
+        Event::SourceLines { lines, .. } => lines
+            .iter()
+            .map(|(number, text)| format!("{}\t{}", number, text))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Event::SourceContext { lines, current } => {
+            let width = lines.last().map_or(1, |(number, _)| number.to_string().len());
+            lines
+                .iter()
+                .map(|(number, text)| {
+                    let marker = if number == current {
+                        paint("1;32", "=>")
+                    } else {
+                        "  ".to_string()
+                    };
+                    format!("{} {:>width$}\t{}", marker, number, text, width = width)
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        Event::Exited(code) => format!("Program exited with code {}", code),
+        Event::Signaled(sig) => format!("Program terminated by signal {}", signal(*sig)),
+        Event::RunStats(stats) => format!(
+            "run time: {:.2}s, stops: {}, breakpoint hits: {}",
+            stats.elapsed.as_secs_f64(),
+            stats.stops,
+            stats.breakpoint_hits
+        ),
+        Event::BreakpointSet {
+            id,
+            sub_location,
+            address: addr,
+            function: func,
+            line,
+        } => format!(
+            "Breakpoint {} at {}: {}",
+            match sub_location {
+                Some(sub) => format!("{}.{}", id, sub),
+                None => id.to_string(),
+            },
+            address(*addr),
+            code_location(*addr, *func, line.map(|line| line.to_string()).as_deref())
+        ),
+        Event::BreakpointPending { id, location } => {
+            format!("Breakpoint {} ({}) pending.", id, location)
+        }
+        Event::BreakpointsDeleted(ids) => format!(
+            "Deleted breakpoint{} {}",
+            if ids.len() == 1 { "" } else { "s" },
+            ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(" ")
+        ),
+        Event::Breakpoints(breakpoints) if breakpoints.is_empty() => "No breakpoints.".to_string(),
+        Event::Breakpoints(breakpoints) => {
+            let mut lines = vec![format!(
+                "{:<7} {:<10} {:<3} {:<18} {}",
+                "Num", "Type", "Enb", "Address", "What"
+            )];
+            let site_row = |num: String, kind: &str, enabled: &str, site: &BreakpointSite| {
+                // Pad by hand: the address may contain color escapes
+                let padding = 18usize.saturating_sub(format!("{:#x}", site.address).len());
+                format!(
+                    "{:<7} {:<10} {:<3} {}{} {}",
+                    num,
+                    kind,
+                    enabled,
+                    address(site.address),
+                    " ".repeat(padding),
+                    code_location(
+                        site.address,
+                        site.function.as_deref(),
+                        site.line.as_ref().map(|line| line.to_string()).as_deref(),
+                    )
+                )
+            };
+            for bp in breakpoints.iter() {
+                let enabled = if bp.enabled { "y" } else { "n" };
+                let kind = if bp.dprintf.is_some() { "dprintf" } else { "breakpoint" };
+                match bp.sites.len() {
+                    0 => lines.push(format!(
+                        "{:<7} {:<10} {:<3} {:<18} {}",
+                        bp.id, kind, enabled, "<pending>", bp.location
+                    )),
+                    1 => lines.push(site_row(bp.id.to_string(), kind, enabled, &bp.sites[0])),
+                    _ => {
+                        lines.push(format!(
+                            "{:<7} {:<10} {:<3} {:<18} {}",
+                            bp.id, kind, enabled, "<MULTIPLE>", bp.location
+                        ));
+                        for (idx, site) in bp.sites.iter().enumerate() {
+                            let num = format!("{}.{}", bp.id, idx + 1);
+                            lines.push(site_row(num, "", "", site));
+                        }
+                    }
+                }
+                if let Some(condition) = &bp.condition {
+                    lines.push(format!("        stop only if {}", condition));
+                }
+                if let Some(args) = &bp.dprintf {
+                    lines.push(format!("        printf {}", args));
+                }
+                if bp.hits > 0 {
+                    lines.push(format!(
+                        "        breakpoint already hit {} time{}",
+                        bp.hits,
+                        if bp.hits == 1 { "" } else { "s" }
+                    ));
+                }
+                if bp.ignore_count > 0 {
+                    lines.push(format!(
+                        "        will ignore the next {} crossing{}",
+                        bp.ignore_count,
+                        if bp.ignore_count == 1 { "" } else { "s" }
+                    ));
+                }
+                match bp.mode {
+                    EnableMode::Keep => {}
+                    EnableMode::Once => {
+                        lines.push("        disables itself after the next stop".to_string())
+                    }
+                    EnableMode::Count(count) => lines.push(format!(
+                        "        disables itself after {} more stop{}",
+                        count,
+                        if count == 1 { "" } else { "s" }
+                    )),
+                }
+            }
+            lines.join("\n")
+        }
+        Event::Functions(functions) if functions.is_empty() => "No functions.".to_string(),
+        Event::Functions(functions) => functions
+            .iter()
+            .map(|func| {
+                let mut line = format!(
+                    "{} {} in {}",
+                    address(func.address),
+                    function(&func.name),
+                    location(&func.file)
+                );
+                if let Some(linkage_name) = &func.linkage_name {
+                    if linkage_name != &func.name {
+                        line.push_str(&format!(" [{}]", linkage_name));
+                    }
+                }
+                line
+            })
+            .collect::<Vec<String>>()
+            .join("\n"),
+        Event::Variables(variables) if variables.is_empty() => "No variables.".to_string(),
+        Event::Variables(variables) => variables
+            .iter()
+            .map(|var| {
+                format!("{} {} in {}", address(var.address), var.declaration, location(&var.file))
+            })
+            .collect::<Vec<String>>()
+            .join("\n"),
+        Event::Scope {
+            location,
+            function,
+            variables,
+        } if variables.is_empty() => format!(
+            "Scope for {}:\nSymbol table for {} contains no locals or arguments.",
+            location, function
+        ),
+        Event::Scope {
+            location,
+            variables,
+            ..
+        } => {
+            let mut lines = vec![format!("Scope for {}:", location)];
+            for var in variables.iter() {
+                lines.push(format!(
+                    "Symbol {} is {}, length {}.",
+                    var.name, var.location, var.size
+                ));
+            }
+            lines.join("\n")
+        }
+        Event::Sources(units) => units
+            .iter()
+            .map(|unit| {
+                let mut lines = vec![format!("{}:", location(&unit.compilation_unit))];
+                for (path, exists) in unit.files.iter() {
+                    if *exists {
+                        lines.push(format!("    {}", path));
+                    } else {
+                        lines.push(format!("    {} (not found)", path));
+                    }
+                }
+                lines.join("\n")
+            })
+            .collect::<Vec<String>>()
+            .join("\n\n"),
+        Event::Backtrace(frames) => frames
+            .iter()
+            .enumerate()
+            .map(|(idx, frame)| frame_line(idx, frame))
+            .collect::<Vec<String>>()
+            .join("\n"),
+        Event::BacktraceFull(frames) => {
+            let mut lines = Vec::new();
+            for (idx, frame) in frames.iter().enumerate() {
+                lines.push(frame_line(idx, &frame.frame));
+                match &frame.locals {
+                    None => lines.push("        (no locals available)".to_string()),
+                    Some(locals) if locals.is_empty() => {
+                        lines.push("        No locals.".to_string())
+                    }
+                    Some(locals) => lines.extend(
+                        locals
+                            .iter()
+                            .map(|(name, value)| format!("        {} = {}", name, value)),
+                    ),
+                }
+            }
+            lines.join("\n")
+        }
+        Event::Frame { level, frame } => frame_line(*level, frame),
+        Event::Value { expression, value } => format!("{} = {}", expression, value),
+        Event::ReturnValue { history, value } => {
+            format!("Value returned is ${} = {}", history, value)
+        }
+        Event::Syscall(trace) => format!(
+            "{}({}) = {}",
+            function(&trace.name),
+            trace.arguments.join(", "),
+            trace.result.as_deref().unwrap_or("?")
+        ),
+        Event::LibraryCall(call) => {
+            format!("{}({})", function(&call.name), call.arguments.join(", "))
+        }
+        Event::CallCounts(counts) if counts.is_empty() => {
+            "No library calls have been traced yet.".to_string()
+        }
+        Event::CallCounts(counts) => {
+            let mut lines = vec![format!("{:>8}  Function", "Calls")];
+            lines.extend(
+                counts
+                    .iter()
+                    .map(|(name, count)| format!("{:>8}  {}", count, function(name))),
+            );
+            lines.join("\n")
+        }
+        Event::Solib(SolibEvent::Loaded(path)) => {
+            format!("Loaded {}", location(&path.display().to_string()))
+        }
+        Event::Solib(SolibEvent::Unloaded(path)) => {
+            format!("Unloaded {}", location(&path.display().to_string()))
+        }
+        Event::Catchpoints(catchpoints) if catchpoints.is_empty() => {
+            "No catchpoints.".to_string()
+        }
+        Event::Catchpoints(catchpoints) => catchpoints
+            .iter()
+            .map(|(catchpoint, locations)| match locations {
+                0 => format!("catch {} (pending)", catchpoint.name()),
+                1 => format!("catch {} (1 location)", catchpoint.name()),
+                n => format!("catch {} ({} locations)", catchpoint.name(), n),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Event::Coverage(files) => {
+            let mut lines = vec![format!("{:>8} {:>8} {:>7}  File", "Executed", "Lines", "%")];
+            let row = |executed: usize, total: usize, name: &str| {
+                let percent = 100.0 * executed as f64 / total.max(1) as f64;
+                format!("{:>8} {:>8} {:>6.1}%  {}", executed, total, percent, name)
+            };
+            for file in files.iter() {
+                lines.push(row(file.executed, file.total, &file.file));
+            }
+            let executed = files.iter().map(|file| file.executed).sum();
+            let total = files.iter().map(|file| file.total).sum();
+            lines.push(row(executed, total, "Total"));
+            lines.join("\n")
+        }
+        Event::Profile { samples: 0, .. } => "No samples were taken.".to_string(),
+        Event::Profile { samples, rows } => {
+            let mut lines = vec![
+                format!("{} sample{}:", samples, if *samples == 1 { "" } else { "s" }),
+                format!("{:>8} {:>7}  Function", "Samples", "%"),
+            ];
+            for (name, count) in rows.iter() {
+                lines.push(format!(
+                    "{:>8} {:>6.1}%  {}",
+                    count,
+                    100.0 * *count as f64 / *samples as f64,
+                    function(name)
+                ));
+            }
+            lines.join("\n")
+        }
+        Event::Heap(heap) => {
+            let mut lines = vec![format!(
+                "{} live block{}, {} byte{} ({} allocation{} and {} free{} since tracking began).",
+                heap.live_blocks,
+                if heap.live_blocks == 1 { "" } else { "s" },
+                heap.live_bytes,
+                if heap.live_bytes == 1 { "" } else { "s" },
+                heap.allocations,
+                if heap.allocations == 1 { "" } else { "s" },
+                heap.frees,
+                if heap.frees == 1 { "" } else { "s" }
+            )];
+            if !heap.sites.is_empty() {
+                lines.push("Top call sites:".to_string());
+            }
+            for site in &heap.sites {
+                lines.push(format!(
+                    "{:>10} bytes in {} block{} from {}",
+                    site.bytes,
+                    site.blocks,
+                    if site.blocks == 1 { "" } else { "s" },
+                    code_location(
+                        site.address,
+                        site.function.as_deref(),
+                        site.line.as_ref().map(|line| line.to_string()).as_deref()
+                    )
+                ));
+            }
+            lines.join("\n")
+        }
+        Event::Record { instructions, .. } if instructions.is_empty() => {
+            "No instructions have been recorded.".to_string()
+        }
+        Event::Record {
+            first,
+            instructions,
+        } => instructions
+            .iter()
+            .enumerate()
+            .map(|(idx, instruction)| {
+                format!(
+                    "{:<7} {} in {}",
+                    first + idx,
+                    address(instruction.address),
+                    code_location(
+                        instruction.address,
+                        instruction.function.as_deref(),
+                        instruction.line.as_ref().map(|line| line.to_string()).as_deref()
+                    )
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n"),
+        Event::Checkpoints(checkpoints) if checkpoints.is_empty() => {
+            "No checkpoints.".to_string()
+        }
+        Event::SharedLibraries(libraries) if libraries.is_empty() => {
+            "No shared libraries loaded at this time.".to_string()
+        }
+        Event::InferiorOutput { stream, text } => match stream {
+            Stream::Stdout => format!("[out] {}", text),
+            Stream::Stderr => format!("[err] {}", text),
+        },
+        Event::SharedLibraries(libraries) => {
+            let mut lines = vec![format!(
+                "{:<18} {:<18} {:<18} {:<12} Shared Object Library",
+                "Base", "From", "To", "Syms Read"
+            )];
+            for library in libraries.iter() {
+                let (from, to) = match library.text {
+                    Some((from, to)) => (format!("{:#x}", from), format!("{:#x}", to)),
+                    None => (String::new(), String::new()),
+                };
+                lines.push(format!(
+                    "{:<18} {:<18} {:<18} {:<12} {}",
+                    format!("{:#x}", library.base),
+                    from,
+                    to,
+                    match library.symbols {
+                        DebugInfo::Dwarf => "Yes",
+                        DebugInfo::Symbols => "Symbols only",
+                        DebugInfo::Nothing => "No",
+                    },
+                    location(&library.path)
+                ));
+            }
+            lines.join("\n")
+        }
+        Event::Checkpoints(checkpoints) => checkpoints
+            .iter()
+            .map(|checkpoint| {
+                format!(
+                    "{:<3} process {} at {}",
+                    checkpoint.id,
+                    checkpoint.pid,
+                    code_location(
+                        checkpoint.address,
+                        checkpoint.function.as_deref(),
+                        checkpoint.line.as_ref().map(|line| line.to_string()).as_deref()
+                    )
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n"),
+        Event::SymbolAddress {
+            name,
+            is_function,
+            address: addr,
+            runtime_address,
+        } => {
+            let mut text = format!(
+                "Symbol \"{}\" is {} at address {}",
+                name,
+                if *is_function { "a function" } else { "static storage" },
+                address(*addr)
+            );
+            if let Some(runtime_address) = runtime_address {
+                text.push_str(&format!(" ({} in the running process)", address(*runtime_address)));
+            }
+            text.push('.');
+            text
+        }
+        Event::SymbolAt {
+            symbol,
+            offset,
+            section,
+            ..
+        } => {
+            let mut text = if *offset == 0 {
+                symbol.to_string()
+            } else {
+                format!("{} + {:#x}", symbol, offset)
+            };
+            if let Some(section) = section {
+                text.push_str(&format!(" in section {}", section));
+            }
+            text
+        }
+        Event::LineRange {
+            file,
+            line,
+            start,
+            end,
+            address: None,
+        } => format!(
+            "Line {} of \"{}\" starts at address {} and ends at {}.",
+            line,
+            location(file),
+            address(*start),
+            address(*end)
+        ),
+        Event::LineRange {
+            file,
+            line,
+            start,
+            end,
+            address: Some(addr),
+        } => format!(
+            "Address {} is in the middle of line {} of \"{}\", which starts at {} and ends at {}.",
+            address(*addr),
+            line,
+            location(file),
+            address(*start),
+            address(*end)
+        ),
+        Event::Type {
+            data,
+            type_id,
+            expand,
+        } => match type_id {
+            Some(type_id) if *expand => type_definition(data, *type_id),
+            Some(type_id) => format!("type = {}", data.type_name(*type_id)),
+            None => format!("type = {}", COMPUTED_TYPE),
+        },
+        Event::DwarfUnit { data, unit } => {
+            let mut lines = vec![location(&unit.name), "Global variables:".to_string()];
+            for var in &unit.global_variables {
+                lines.push(format!(
+                    "  * {} ({}, located at {}, declared at line {})",
+                    var.name,
+                    data.type_name(var.type_id),
+                    var.location,
+                    var.line_number
+                ));
+            }
+            lines.push("Functions:".to_string());
+            for func in &unit.functions {
+                lines.push(format!(
+                    "  * {} (declared on line {}, located at {}, {} bytes long)",
+                    function(&func.display_name()),
+                    func.line_number,
+                    address(func.address),
+                    func.text_length
+                ));
+                for var in &func.variables {
+                    lines.push(format!(
+                        "    * Variable: {} ({}, located at {}, declared at line {})",
+                        var.name,
+                        data.type_name(var.type_id),
+                        var.location,
+                        var.line_number
+                    ));
+                }
+            }
+            lines.push("Line numbers:".to_string());
+            for line in &unit.lines {
+                lines.push(format!("  * {} (at {})", line.number, address(line.address)));
+            }
+            lines.join("\n")
+        }
+        Event::LineTable(rows) => {
+            if rows.is_empty() {
+                return "No line table rows.".to_string();
+            }
+            let mut lines =
+                vec![format!("{:<18} {:<18} {:<5} {}", "Address", "End", "Stmt", "Line")];
+            for row in rows.iter() {
+                lines.push(format!(
+                    "{:<18} {:<18} {:<5} {}:{}",
+                    format!("{:#x}", row.address),
+                    format!("{:#x}", row.end),
+                    if row.is_stmt { "Y" } else { "" },
+                    location(&row.file),
+                    row.number
+                ));
+            }
+            lines.join("\n")
+        }
+        Event::Memory { address, bytes } => hexdump(*address, bytes).join("\n"),
+        Event::MemoryDiff { address, old, new } => {
+            let lines = memory_diff(*address, old, new);
+            if lines.is_empty() {
+                "No bytes changed.".to_string()
+            } else {
+                lines.join("\n")
+            }
+        }
+        Event::MemoryMatch { address: addr, mapping } => {
+            if mapping.is_empty() {
+                format!("{} (anonymous mapping)", address(*addr))
+            } else {
+                format!("{} in {}", address(*addr), mapping)
+            }
+        }
+        Event::MemoryString { address: addr, text } => format!("{}  {}", address(*addr), text),
+        Event::Registers(values) => values
+            .iter()
+            .map(|(name, value)| {
+                let natural = if name == "eflags" {
+                    format!("[ {} ]", registers::eflags_names(*value).join(" "))
+                } else if registers::ADDRESS_REGISTERS.contains(&name.as_str())
+                    || name.starts_with("dr")
+                {
+                    address(*value as usize)
+                } else {
+                    (*value as i64).to_string()
+                };
+                format!("{:<15}{:<19}{}", name, format!("{:#x}", value), natural)
+            })
+            .collect::<Vec<String>>()
+            .join("\n"),
+        Event::FloatRegisters(fpregs) => {
+            let mut lines: Vec<String> = (0..registers::X87_REGISTERS)
+                .map(|n| {
+                    let bytes = registers::st(fpregs, n);
+                    if registers::st_empty(fpregs, n) {
+                        format!("st{:<4} Empty  {}", n, registers::hex(&bytes))
+                    } else {
+                        format!(
+                            "st{:<4} Valid  {}  {}",
+                            n,
+                            registers::hex(&bytes),
+                            registers::x87_to_f64(&bytes)
+                        )
+                    }
+                })
+                .collect();
+            lines.push(format!("fctrl  {:#06x}", fpregs.cwd));
+            lines.push(format!("fstat  {:#06x}", fpregs.swd));
+            lines.push(format!("ftag   {:#06x}", fpregs.ftw));
+            lines.push(format!("fop    {:#06x}", fpregs.fop));
+            lines.push(format!("fip    {}", address(fpregs.rip as usize)));
+            lines.push(format!("fdp    {}", address(fpregs.rdp as usize)));
+            lines.push(format!("mxcsr  {:#010x}", fpregs.mxcsr));
+            lines.join("\n")
+        }
+        Event::VectorRegisters(fpregs) => {
+            let mut lines: Vec<String> = (0..registers::XMM_REGISTERS)
+                .map(|n| {
+                    let bytes = registers::xmm(fpregs, n);
+                    let (doubles, floats) = vector_lanes(&bytes);
+                    let join = |lanes: Vec<String>| lanes.join(", ");
+                    format!(
+                        "xmm{:<3} {{v2_double = {{{}}}, v4_float = {{{}}}, uint128 = {}}}",
+                        n,
+                        join(doubles.iter().map(f64::to_string).collect()),
+                        join(floats.iter().map(f32::to_string).collect()),
+                        registers::hex(&bytes)
+                    )
+                })
+                .collect();
+            lines.push(format!("mxcsr  {:#010x}", fpregs.mxcsr));
+            lines.join("\n")
+        }
+        Event::SignalInfo(info) => {
+            let mut lines = vec![
+                format!("signal: {} ({})", signal(info.signal), info.signal as i32),
+                format!("errno: {}", info.errno),
+            ];
+            let meaning: Vec<&str> = info
+                .code_name()
+                .into_iter()
+                .chain(info.code_description())
+                .collect();
+            lines.push(if meaning.is_empty() {
+                format!("code: {}", info.code)
+            } else {
+                format!("code: {} ({})", info.code, meaning.join(": "))
+            });
+            if let Some(fault) = info.fault {
+                lines.push(format!("address: {}", address(fault.address)));
+            }
+            if let Some((pid, uid)) = info.sender {
+                lines.push(format!("sent by: pid {}, uid {}", pid, uid));
+            }
+            lines.join("\n")
+        }
+        Event::InferiorInfo(info) => {
+            let mut lines = vec![format!("Target: {}", location(&info.target))];
+            if info.args.is_empty() {
+                lines.push("Arguments: none".to_string());
+            } else {
+                lines.push(format!("Arguments: {}", info.args.join(" ")));
+            }
+            lines.push(match &info.process {
+                ProcessState::None => "No process.".to_string(),
+                ProcessState::Stopped {
+                    pid,
+                    address: addr,
+                    function,
+                    line,
+                } => format!(
+                    "Process {}, stopped at {} in {}",
+                    pid,
+                    address(*addr),
+                    code_location(
+                        *addr,
+                        function.as_deref(),
+                        line.as_ref().map(|line| line.to_string()).as_deref()
+                    )
+                ),
+                ProcessState::Exited { pid, code } => {
+                    format!("Process {} has exited with code {}.", pid, code)
+                }
+                ProcessState::Signaled { pid, signal: sig } => {
+                    format!("Process {} was terminated by signal {}.", pid, signal(*sig))
+                }
+            });
+            lines.push(format!("Breakpoints installed: {}", info.installed_breakpoints));
+            lines.join("\n")
+        }
+        Event::FrameInfo(info) => {
+            let place = |addr, function: &Option<String>, line: &Option<Line>| {
+                code_location(
+                    addr,
+                    function.as_deref(),
+                    line.as_ref().map(|line| line.to_string()).as_deref(),
+                )
+            };
+            let mut lines = vec![
+                format!("Stack level {}, frame at {}:", info.level, address(info.frame.cfa)),
+                format!(
+                    " rip = {} in {}",
+                    address(info.frame.rip),
+                    place(info.frame.rip, &info.function, &info.line)
+                ),
+            ];
+            match &info.caller {
+                Some(caller) => {
+                    lines.push(format!(
+                        " saved rip = {}, in {}",
+                        address(caller.rip),
+                        place(caller.rip, &info.caller_function, &info.caller_line)
+                    ));
+                    lines.push(format!(" called by frame at {}", address(caller.cfa)));
+                    lines.push(format!(
+                        " rbp = {}, saved rbp = {}",
+                        address(info.frame.rbp),
+                        address(caller.rbp)
+                    ));
+                }
+                None => lines.push(format!(
+                    " rbp = {} (the caller's frame can not be read)",
+                    address(info.frame.rbp)
+                )),
+            }
+            lines.join("\n")
+        }
+        Event::UserCommand { name, body } => {
+            let mut lines = vec![format!("User command \"{}\":", name)];
+            lines.extend(body.iter().map(|line| format!("  {}", line)));
+            lines.join("\n")
+        }
+        Event::Aliases(aliases) if aliases.is_empty() => "There are no aliases.".to_string(),
+        Event::Aliases(aliases) => aliases
+            .iter()
+            .map(|alias| format!("{} = {}", alias.name, alias.expansion))
+            .collect::<Vec<String>>()
+            .join("\n"),
+        Event::Displays(displays) if displays.is_empty() => {
+            "There are no auto-display expressions now.".to_string()
+        }
+        Event::Displays(displays) => {
+            let mut lines = vec![
+                "Auto-display expressions now in effect:".to_string(),
+                "Num Enb Expression".to_string(),
+            ];
+            for display in displays.iter() {
+                let format = display
+                    .format
+                    .map_or(String::new(), |letter| format!("/{} ", letter));
+                lines.push(format!(
+                    "{:<3} {:<3} {}{}",
+                    format!("{}:", display.id),
+                    if display.enabled { "y" } else { "n" },
+                    format,
+                    display.expression
+                ));
+            }
+            lines.join("\n")
+        }
+        Event::Display {
+            id,
+            expression,
+            format,
+            value,
+        } => match value {
+            Ok(value) => match format {
+                Some(letter) => format!("{}: /{} {} = {}", id, letter, expression, value),
+                None => format!("{}: {} = {}", id, expression, value),
+            },
+            Err(err) => format!("display {}: cannot evaluate: {}", id, err),
+        },
+        Event::Message(text) => text.to_string(),
+    }
+}
+
+pub fn render_json(event: &Event) -> String {
+    match event {
+        Event::Stopped {
+            signal,
+            address,
+            reason,
+            breakpoint,
+            function,
+            line,
+        } => format!(
+            "{{\"event\":\"stopped\",\"reason\":\"{}\",\"breakpoint\":{},\"signal\":{},\"address\":{},\"fault\":{},{}}}",
+            match reason {
+                StopReason::Breakpoint(_) => "breakpoint",
+                StopReason::Step => "step",
+                StopReason::Watchpoint(_) => "watchpoint",
+                StopReason::Signal(_) => "signal",
+                StopReason::Trap => "trap",
+                StopReason::Syscall => "syscall",
+            },
+            breakpoint
+                .map(|id| id.to_string())
+                .unwrap_or_else(|| "null".to_string()),
+            json_string(&format!("{}", signal)),
+            address,
+            match reason {
+                StopReason::Signal(Some(fault)) => json_fault(*signal, fault),
+                _ => "null".to_string(),
+            },
+            json_code_location(*function, *line)
+        ),
+        Event::Caught {
+            catchpoint,
+            address,
+            function,
+            line,
+            assertion,
+        } => format!(
+            "{{\"event\":\"caught\",\"catchpoint\":\"{}\",\"address\":{},{},\"assertion\":{}}}",
+            catchpoint.name(),
+            address,
+            json_code_location(*function, *line),
+            assertion.map_or_else(
+                || "null".to_string(),
+                |assertion| format!(
+                    "{{\"expression\":{},\"file\":{},\"line\":{},\"function\":{}}}",
+                    json_string(&assertion.expression),
+                    json_string(&assertion.file),
+                    assertion.line,
+                    json_string(&assertion.function)
+                )
+            )
+        ),
+        Event::WatchpointSet { id, expression } => format!(
+            "{{\"event\":\"watchpoint-set\",\"id\":{},\"expression\":{}}}",
+            id,
+            json_string(expression)
+        ),
+        Event::WatchpointChanged {
+            id,
+            expression,
+            old,
+            new,
+        } => format!(
+            "{{\"event\":\"watchpoint-changed\",\"id\":{},\"expression\":{},\"old\":{},\"new\":{}}}",
+            id,
+            json_string(expression),
+            json_string(old),
+            json_string(new)
+        ),
+        Event::WatchpointScope(id) => {
+            format!("{{\"event\":\"watchpoint-scope\",\"id\":{}}}", id)
+        }
+        Event::SourceLine { number, text } => format!(
+            "{{\"event\":\"source-line\",\"line\":{},\"text\":{}}}",
+            number,
+            json_string(text)
+        ),
+        Event::SourceLines { file, lines } => {
+            let lines: Vec<String> = lines
+                .iter()
+                .map(|(number, text)| {
+                    format!("{{\"line\":{},\"text\":{}}}", number, json_string(text))
+                })
+                .collect();
+            format!(
+                "{{\"event\":\"source-lines\",\"file\":{},\"lines\":[{}]}}",
+                json_string(file),
+                lines.join(",")
+            )
+        }
+        Event::SourceContext { lines, current } => {
+            let lines: Vec<String> = lines
+                .iter()
+                .map(|(number, text)| {
+                    format!("{{\"line\":{},\"text\":{}}}", number, json_string(text))
+                })
+                .collect();
+            format!(
+                "{{\"event\":\"source-context\",\"current\":{},\"lines\":[{}]}}",
+                current,
+                lines.join(",")
+            )
+        }
+        Event::Exited(code) => format!("{{\"event\":\"exited\",\"code\":{}}}", code),
+        Event::RunStats(stats) => format!(
+            "{{\"event\":\"run-stats\",\"seconds\":{:.3},\"stops\":{},\"breakpoint_hits\":{}}}",
+            stats.elapsed.as_secs_f64(),
+            stats.stops,
+            stats.breakpoint_hits
+        ),
+        Event::Signaled(signal) => format!(
+            "{{\"event\":\"signaled\",\"signal\":{}}}",
+            json_string(&format!("{}", signal))
+        ),
+        Event::BreakpointSet {
+            id,
+            sub_location,
+            address,
+            function,
+            line,
+        } => format!(
+            "{{\"event\":\"breakpoint-set\",\"id\":{},\"sub_location\":{},\"address\":{},{}}}",
+            id,
+            sub_location
+                .map(|sub| sub.to_string())
+                .unwrap_or_else(|| "null".to_string()),
+            address,
+            json_code_location(*function, *line)
+        ),
+        Event::BreakpointPending { id, location } => format!(
+            "{{\"event\":\"breakpoint-pending\",\"id\":{},\"location\":{}}}",
+            id,
+            json_string(location)
+        ),
+        Event::BreakpointsDeleted(ids) => format!(
+            "{{\"event\":\"breakpoints-deleted\",\"ids\":[{}]}}",
+            ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(",")
+        ),
+        Event::Breakpoints(breakpoints) => {
+            let breakpoints: Vec<String> = breakpoints
+                .iter()
+                .map(|bp| {
+                    let sites: Vec<String> = bp
+                        .sites
+                        .iter()
+                        .map(|site| {
+                            format!(
+                                "{{\"address\":{},{}}}",
+                                site.address,
+                                json_code_location(site.function.as_deref(), site.line.as_ref())
+                            )
+                        })
+                        .collect();
+                    let (mode, remaining) = match bp.mode {
+                        EnableMode::Keep => ("keep", "null".to_string()),
+                        EnableMode::Once => ("once", "1".to_string()),
+                        EnableMode::Count(count) => ("count", count.to_string()),
+                    };
+                    format!(
+                        "{{\"id\":{},\"type\":\"{}\",\"dprintf\":{},\"location\":{},\
+                         \"enabled\":{},\"mode\":\"{}\",\"remaining\":{},\"condition\":{},\
+                         \"ignore_count\":{},\"hits\":{},\"locations\":[{}]}}",
+                        bp.id,
+                        if bp.dprintf.is_some() { "dprintf" } else { "breakpoint" },
+                        bp.dprintf.as_deref().map_or("null".to_string(), json_string),
+                        json_string(&bp.location),
+                        bp.enabled,
+                        mode,
+                        remaining,
+                        bp.condition.as_deref().map_or("null".to_string(), json_string),
+                        bp.ignore_count,
+                        bp.hits,
+                        sites.join(",")
+                    )
+                })
+                .collect();
+            format!(
+                "{{\"event\":\"breakpoints\",\"breakpoints\":[{}]}}",
+                breakpoints.join(",")
+            )
+        }
+        Event::Functions(functions) => {
+            let functions: Vec<String> = functions
+                .iter()
+                .map(|func| {
+                    format!(
+                        "{{\"name\":{},\"linkage_name\":{},\"address\":{},\"file\":{}}}",
+                        json_string(&func.name),
+                        func.linkage_name
+                            .as_deref()
+                            .map(json_string)
+                            .unwrap_or_else(|| "null".to_string()),
+                        func.address,
+                        json_string(&func.file)
+                    )
+                })
+                .collect();
+            format!(
+                "{{\"event\":\"functions\",\"functions\":[{}]}}",
+                functions.join(",")
+            )
+        }
+        Event::Variables(variables) => {
+            let variables: Vec<String> = variables
+                .iter()
+                .map(|var| {
+                    format!(
+                        "{{\"name\":{},\"declaration\":{},\"address\":{},\"file\":{}}}",
+                        json_string(&var.name),
+                        json_string(&var.declaration),
+                        var.address,
+                        json_string(&var.file)
+                    )
+                })
+                .collect();
+            format!(
+                "{{\"event\":\"variables\",\"variables\":[{}]}}",
+                variables.join(",")
+            )
+        }
+        Event::Scope {
+            location,
+            function,
+            variables,
+        } => {
+            let variables: Vec<String> = variables
+                .iter()
+                .map(|var| {
+                    format!(
+                        "{{\"name\":{},\"location\":{},\"size\":{}}}",
+                        json_string(&var.name),
+                        json_string(&var.location),
+                        var.size
+                    )
+                })
+                .collect();
+            format!(
+                "{{\"event\":\"scope\",\"location\":{},\"function\":{},\"variables\":[{}]}}",
+                json_string(location),
+                json_string(function),
+                variables.join(",")
+            )
+        }
+        Event::Sources(units) => {
+            let units: Vec<String> = units
+                .iter()
+                .map(|unit| {
+                    let files: Vec<String> = unit
+                        .files
+                        .iter()
+                        .map(|(path, exists)| {
+                            format!("{{\"path\":{},\"exists\":{}}}", json_string(path), exists)
+                        })
+                        .collect();
+                    format!(
+                        "{{\"compilation_unit\":{},\"files\":[{}]}}",
+                        json_string(&unit.compilation_unit),
+                        files.join(",")
+                    )
+                })
+                .collect();
+            format!("{{\"event\":\"sources\",\"units\":[{}]}}", units.join(","))
+        }
+        Event::Backtrace(frames) => {
+            let frames: Vec<String> = frames
+                .iter()
+                .map(|frame| {
+                    format!(
+                        "{{\"address\":{},\"inlined\":{},{}}}",
+                        frame.address,
+                        frame.inlined,
+                        json_code_location(frame.function.as_deref(), frame.line.as_ref())
+                    )
+                })
+                .collect();
+            format!("{{\"event\":\"backtrace\",\"frames\":[{}]}}", frames.join(","))
+        }
+        Event::BacktraceFull(frames) => {
+            let frames: Vec<String> = frames
+                .iter()
+                .map(|FrameLocals { frame, locals }| {
+                    let locals = match locals {
+                        Some(locals) => {
+                            let locals: Vec<String> = locals
+                                .iter()
+                                .map(|(name, value)| {
+                                    format!(
+                                        "{{\"name\":{},\"value\":{}}}",
+                                        json_string(name),
+                                        json_string(value)
+                                    )
+                                })
+                                .collect();
+                            format!("[{}]", locals.join(","))
+                        }
+                        None => "null".to_string(),
+                    };
+                    format!(
+                        "{{\"address\":{},\"inlined\":{},{},\"locals\":{}}}",
+                        frame.address,
+                        frame.inlined,
+                        json_code_location(frame.function.as_deref(), frame.line.as_ref()),
+                        locals
+                    )
+                })
+                .collect();
+            format!("{{\"event\":\"backtrace\",\"frames\":[{}]}}", frames.join(","))
+        }
+        Event::Frame { level, frame } => format!(
+            "{{\"event\":\"frame\",\"level\":{},\"address\":{},\"inlined\":{},{}}}",
+            level,
+            frame.address,
+            frame.inlined,
+            json_code_location(frame.function.as_deref(), frame.line.as_ref())
+        ),
+        Event::Value { expression, value } => format!(
+            "{{\"event\":\"value\",\"expression\":{},\"value\":{}}}",
+            json_string(expression),
+            json_string(value)
+        ),
+        Event::ReturnValue { history, value } => format!(
+            "{{\"event\":\"return_value\",\"history\":{},\"value\":{}}}",
+            history,
+            json_string(value)
+        ),
+        Event::Syscall(trace) => {
+            let arguments: Vec<String> =
+                trace.arguments.iter().map(|arg| json_string(arg)).collect();
+            format!(
+                "{{\"event\":\"syscall\",\"number\":{},\"name\":{},\"arguments\":[{}],\
+                 \"result\":{}}}",
+                trace.number,
+                json_string(&trace.name),
+                arguments.join(","),
+                trace.result.as_deref().map_or("null".to_string(), json_string)
+            )
+        }
+        Event::LibraryCall(call) => {
+            let arguments: Vec<String> =
+                call.arguments.iter().map(|arg| json_string(arg)).collect();
+            format!(
+                "{{\"event\":\"library_call\",\"name\":{},\"arguments\":[{}]}}",
+                json_string(&call.name),
+                arguments.join(",")
+            )
+        }
+        Event::CallCounts(counts) => {
+            let counts: Vec<String> = counts
+                .iter()
+                .map(|(name, count)| {
+                    format!("{{\"name\":{},\"calls\":{}}}", json_string(name), count)
+                })
+                .collect();
+            format!("{{\"event\":\"call_counts\",\"functions\":[{}]}}", counts.join(","))
+        }
+        Event::Solib(event) => {
+            let (action, path) = match event {
+                SolibEvent::Loaded(path) => ("loaded", path),
+                SolibEvent::Unloaded(path) => ("unloaded", path),
+            };
+            format!(
+                "{{\"event\":\"solib\",\"action\":\"{}\",\"path\":{}}}",
+                action,
+                json_string(&path.display().to_string())
+            )
+        }
+        Event::Catchpoints(catchpoints) => {
+            let catchpoints: Vec<String> = catchpoints
+                .iter()
+                .map(|(catchpoint, locations)| {
+                    format!(
+                        "{{\"catchpoint\":\"{}\",\"locations\":{}}}",
+                        catchpoint.name(),
+                        locations
+                    )
+                })
+                .collect();
+            format!("{{\"event\":\"catchpoints\",\"catchpoints\":[{}]}}", catchpoints.join(","))
+        }
+        Event::Coverage(files) => {
+            let files: Vec<String> = files
+                .iter()
+                .map(|file| {
+                    format!(
+                        "{{\"file\":{},\"executed\":{},\"lines\":{}}}",
+                        json_string(&file.file),
+                        file.executed,
+                        file.total
+                    )
+                })
+                .collect();
+            format!("{{\"event\":\"coverage\",\"files\":[{}]}}", files.join(","))
+        }
+        Event::Profile { samples, rows } => {
+            let rows: Vec<String> = rows
+                .iter()
+                .map(|(name, count)| {
+                    format!("{{\"function\":{},\"samples\":{}}}", json_string(name), count)
+                })
+                .collect();
+            format!(
+                "{{\"event\":\"profile\",\"samples\":{},\"functions\":[{}]}}",
+                samples,
+                rows.join(",")
+            )
+        }
+        Event::Heap(heap) => {
+            let sites: Vec<String> = heap
+                .sites
+                .iter()
+                .map(|site| {
+                    format!(
+                        "{{\"address\":{},\"blocks\":{},\"bytes\":{},{}}}",
+                        site.address,
+                        site.blocks,
+                        site.bytes,
+                        json_code_location(site.function.as_deref(), site.line.as_ref())
+                    )
+                })
+                .collect();
+            format!(
+                "{{\"event\":\"heap\",\"live_blocks\":{},\"live_bytes\":{},\
+                 \"allocations\":{},\"frees\":{},\"sites\":[{}]}}",
+                heap.live_blocks,
+                heap.live_bytes,
+                heap.allocations,
+                heap.frees,
+                sites.join(",")
+            )
+        }
+        Event::Record {
+            first,
+            instructions,
+        } => {
+            let instructions: Vec<String> = instructions
+                .iter()
+                .enumerate()
+                .map(|(idx, instruction)| {
+                    format!(
+                        "{{\"number\":{},\"address\":{},{}}}",
+                        first + idx,
+                        instruction.address,
+                        json_code_location(
+                            instruction.function.as_deref(),
+                            instruction.line.as_ref()
+                        )
+                    )
+                })
+                .collect();
+            format!(
+                "{{\"event\":\"record\",\"instructions\":[{}]}}",
+                instructions.join(",")
+            )
+        }
+        Event::InferiorOutput { stream, text } => format!(
+            "{{\"event\":\"output\",\"stream\":\"{}\",\"text\":{}}}",
+            stream.name(),
+            json_string(text)
+        ),
+        Event::SharedLibraries(libraries) => {
+            let libraries: Vec<String> = libraries
+                .iter()
+                .map(|library| {
+                    let (from, to) = match library.text {
+                        Some((from, to)) => (from.to_string(), to.to_string()),
+                        None => ("null".to_string(), "null".to_string()),
+                    };
+                    format!(
+                        "{{\"path\":{},\"base\":{},\"from\":{},\"to\":{},\"symbols\":\"{}\"}}",
+                        json_string(&library.path),
+                        library.base,
+                        from,
+                        to,
+                        match library.symbols {
+                            DebugInfo::Dwarf => "dwarf",
+                            DebugInfo::Symbols => "symbols",
+                            DebugInfo::Nothing => "none",
+                        }
+                    )
+                })
+                .collect();
+            format!(
+                "{{\"event\":\"shared_libraries\",\"libraries\":[{}]}}",
+                libraries.join(",")
+            )
+        }
+        Event::Checkpoints(checkpoints) => {
+            let checkpoints: Vec<String> = checkpoints
+                .iter()
+                .map(|checkpoint| {
+                    format!(
+                        "{{\"id\":{},\"pid\":{},\"address\":{},{}}}",
+                        checkpoint.id,
+                        checkpoint.pid,
+                        checkpoint.address,
+                        json_code_location(
+                            checkpoint.function.as_deref(),
+                            checkpoint.line.as_ref()
+                        )
+                    )
+                })
+                .collect();
+            format!(
+                "{{\"event\":\"checkpoints\",\"checkpoints\":[{}]}}",
+                checkpoints.join(",")
+            )
+        }
+        Event::SymbolAddress {
+            name,
+            is_function,
+            address,
+            runtime_address,
+        } => format!(
+            "{{\"event\":\"symbol-address\",\"name\":{},\"function\":{},\"address\":{},\"runtime_address\":{}}}",
+            json_string(name),
+            is_function,
+            address,
+            runtime_address.map_or("null".to_string(), |addr| addr.to_string())
+        ),
+        Event::SymbolAt {
+            address,
+            symbol,
+            offset,
+            section,
+        } => format!(
+            "{{\"event\":\"symbol-at\",\"address\":{},\"symbol\":{},\"offset\":{},\"section\":{}}}",
+            address,
+            json_string(symbol),
+            offset,
+            section.map_or("null".to_string(), json_string)
+        ),
+        Event::LineRange {
+            file,
+            line,
+            start,
+            end,
+            address,
+        } => format!(
+            "{{\"event\":\"line-range\",\"file\":{},\"line\":{},\"start\":{},\"end\":{},\"address\":{}}}",
+            json_string(file),
+            line,
+            start,
+            end,
+            address.map_or("null".to_string(), |addr| addr.to_string())
+        ),
+        Event::Type {
+            data,
+            type_id,
+            expand,
+        } => {
+            let type_id = match type_id {
+                Some(type_id) => *type_id,
+                None => {
+                    return format!(
+                        "{{\"event\":\"type\",\"type\":{},\"size\":8}}",
+                        json_string(COMPUTED_TYPE)
+                    )
+                }
+            };
+            let mut fields = format!(
+                "\"type\":{},\"size\":{}",
+                json_string(&data.type_name(type_id)),
+                data.type_size(type_id)
+            );
+            let ty = data.get_type(pointee(data, type_id).0);
+            match ty.map(|ty| &ty.kind) {
+                Some(TypeKind::Struct(members)) | Some(TypeKind::Union(members)) if *expand => {
+                    let members: Vec<String> = members
+                        .iter()
+                        .map(|member| {
+                            format!(
+                                "{{\"name\":{},\"type\":{},\"offset\":{},\"size\":{},\
+                                 \"bit_size\":{},\"bit_offset\":{}}}",
+                                json_string(&member.name),
+                                json_string(&data.type_name(member.type_id)),
+                                member.offset,
+                                data.type_size(member.type_id),
+                                member
+                                    .bit_size
+                                    .map_or("null".to_string(), |bits| bits.to_string()),
+                                member.bit_offset
+                            )
+                        })
+                        .collect();
+                    fields.push_str(&format!(",\"members\":[{}]", members.join(",")));
+                }
+                Some(TypeKind::Enum(enumerators)) if *expand => {
+                    let enumerators: Vec<String> = enumerators
+                        .iter()
+                        .map(|(name, value)| {
+                            format!("{{\"name\":{},\"value\":{}}}", json_string(name), value)
+                        })
+                        .collect();
+                    fields.push_str(&format!(",\"enumerators\":[{}]", enumerators.join(",")));
+                }
+                _ => {}
+            }
+            format!("{{\"event\":\"type\",{}}}", fields)
+        }
+        Event::DwarfUnit { data, unit } => {
+            let json_variable = |var: &crate::dwarf_data::Variable| {
+                let location = match var.location {
+                    Location::Address(addr) => format!("{{\"address\":{}}}", addr),
+                    Location::FramePointerOffset(offset) => {
+                        format!("{{\"frame_offset\":{}}}", offset)
+                    }
+                    Location::Expression(_) | Location::List(_) => {
+                        format!("{{\"expression\":{}}}", json_string(&var.location.to_string()))
+                    }
+                };
+                format!(
+                    "{{\"name\":{},\"type\":{},\"location\":{},\"line\":{}}}",
+                    json_string(&var.name),
+                    json_string(&data.type_name(var.type_id)),
+                    location,
+                    var.line_number
+                )
+            };
+            let globals: Vec<String> = unit.global_variables.iter().map(json_variable).collect();
+            let functions: Vec<String> = unit
+                .functions
+                .iter()
+                .map(|func| {
+                    let variables: Vec<String> = func.variables.iter().map(json_variable).collect();
+                    format!(
+                        "{{\"name\":{},\"line\":{},\"address\":{},\"length\":{},\"variables\":[{}]}}",
+                        json_string(&func.display_name()),
+                        func.line_number,
+                        func.address,
+                        func.text_length,
+                        variables.join(",")
+                    )
+                })
+                .collect();
+            let lines: Vec<String> = unit
+                .lines
+                .iter()
+                .map(|line| format!("{{\"line\":{},\"address\":{}}}", line.number, line.address))
+                .collect();
+            format!(
+                "{{\"event\":\"dwarf-unit\",\"name\":{},\"global_variables\":[{}],\"functions\":[{}],\"lines\":[{}]}}",
+                json_string(&unit.name),
+                globals.join(","),
+                functions.join(","),
+                lines.join(",")
+            )
+        }
+        Event::LineTable(rows) => {
+            let rows: Vec<String> = rows
+                .iter()
+                .map(|row| {
+                    format!(
+                        "{{\"address\":{},\"end\":{},\"file\":{},\"line\":{},\"is_stmt\":{}}}",
+                        row.address,
+                        row.end,
+                        json_string(&row.file),
+                        row.number,
+                        row.is_stmt
+                    )
+                })
+                .collect();
+            format!("{{\"event\":\"line-table\",\"rows\":[{}]}}", rows.join(","))
+        }
+        Event::Memory { address, bytes } => {
+            let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+            format!("{{\"event\":\"memory\",\"address\":{},\"bytes\":\"{}\"}}", address, hex)
+        }
+        Event::MemoryDiff { address, old, new } => {
+            let changes: Vec<String> = old
+                .iter()
+                .zip(new.iter())
+                .enumerate()
+                .filter(|(_, (old, new))| old != new)
+                .map(|(offset, (old, new))| {
+                    format!("{{\"offset\":{},\"old\":{},\"new\":{}}}", offset, old, new)
+                })
+                .collect();
+            format!(
+                "{{\"event\":\"memory-diff\",\"address\":{},\"changes\":[{}]}}",
+                address,
+                changes.join(",")
+            )
+        }
+        Event::MemoryMatch { address, mapping } => format!(
+            "{{\"event\":\"memory-match\",\"address\":{},\"mapping\":{}}}",
+            address,
+            if mapping.is_empty() {
+                "null".to_string()
+            } else {
+                json_string(mapping)
+            }
+        ),
+        Event::MemoryString { address, text } => format!(
+            "{{\"event\":\"memory-string\",\"address\":{},\"text\":{}}}",
+            address,
+            json_string(text)
+        ),
+        Event::Registers(values) => {
+            let values: Vec<String> = values
+                .iter()
+                .map(|(name, value)| {
+                    let flags = if name == "eflags" {
+                        let names: Vec<String> = registers::eflags_names(*value)
+                            .into_iter()
+                            .map(json_string)
+                            .collect();
+                        format!(",\"flags\":[{}]", names.join(","))
+                    } else {
+                        String::new()
+                    };
+                    format!("{{\"name\":{},\"value\":{}{}}}", json_string(name), value, flags)
+                })
+                .collect();
+            format!("{{\"event\":\"registers\",\"registers\":[{}]}}", values.join(","))
+        }
+        Event::FloatRegisters(fpregs) => {
+            let stack: Vec<String> = (0..registers::X87_REGISTERS)
+                .map(|n| {
+                    let bytes = registers::st(fpregs, n);
+                    let empty = registers::st_empty(fpregs, n);
+                    format!(
+                        "{{\"name\":\"st{}\",\"empty\":{},\"raw\":{},\"value\":{}}}",
+                        n,
+                        empty,
+                        json_string(&registers::hex(&bytes)),
+                        if empty {
+                            "null".to_string()
+                        } else {
+                            json_float(registers::x87_to_f64(&bytes))
+                        }
+                    )
+                })
+                .collect();
+            format!(
+                "{{\"event\":\"float-registers\",\"registers\":[{}],\"fctrl\":{},\"fstat\":{},\
+                 \"ftag\":{},\"fop\":{},\"fip\":{},\"fdp\":{},\"mxcsr\":{}}}",
+                stack.join(","),
+                fpregs.cwd,
+                fpregs.swd,
+                fpregs.ftw,
+                fpregs.fop,
+                fpregs.rip,
+                fpregs.rdp,
+                fpregs.mxcsr
+            )
+        }
+        Event::VectorRegisters(fpregs) => {
+            let vectors: Vec<String> = (0..registers::XMM_REGISTERS)
+                .map(|n| {
+                    let bytes = registers::xmm(fpregs, n);
+                    let (doubles, floats) = vector_lanes(&bytes);
+                    let doubles: Vec<String> =
+                        doubles.iter().map(|lane| json_float(*lane)).collect();
+                    let floats: Vec<String> =
+                        floats.iter().map(|lane| json_float(*lane as f64)).collect();
+                    format!(
+                        "{{\"name\":\"xmm{}\",\"raw\":{},\"v2_double\":[{}],\"v4_float\":[{}]}}",
+                        n,
+                        json_string(&registers::hex(&bytes)),
+                        doubles.join(","),
+                        floats.join(",")
+                    )
+                })
+                .collect();
+            format!(
+                "{{\"event\":\"vector-registers\",\"registers\":[{}],\"mxcsr\":{}}}",
+                vectors.join(","),
+                fpregs.mxcsr
+            )
+        }
+        Event::SignalInfo(info) => format!(
+            "{{\"event\":\"siginfo\",\"signal\":{},\"number\":{},\"errno\":{},\"code\":{},\
+             \"code_name\":{},\"fault\":{},\"sender\":{}}}",
+            json_string(&info.signal.to_string()),
+            info.signal as i32,
+            info.errno,
+            info.code,
+            info.code_name().map_or("null".to_string(), json_string),
+            info.fault
+                .map_or("null".to_string(), |fault| json_fault(info.signal, &fault)),
+            info.sender.map_or("null".to_string(), |(pid, uid)| format!(
+                "{{\"pid\":{},\"uid\":{}}}",
+                pid, uid
+            ))
+        ),
+        Event::InferiorInfo(info) => {
+            let args: Vec<String> = info.args.iter().map(|arg| json_string(arg)).collect();
+            let process = match &info.process {
+                ProcessState::None => "null".to_string(),
+                ProcessState::Stopped {
+                    pid,
+                    address,
+                    function,
+                    line,
+                } => format!(
+                    "{{\"pid\":{},\"state\":\"stopped\",\"address\":{},{}}}",
+                    pid,
+                    address,
+                    json_code_location(function.as_deref(), line.as_ref())
+                ),
+                ProcessState::Exited { pid, code } => format!(
+                    "{{\"pid\":{},\"state\":\"exited\",\"code\":{}}}",
+                    pid, code
+                ),
+                ProcessState::Signaled { pid, signal } => format!(
+                    "{{\"pid\":{},\"state\":\"signaled\",\"signal\":{}}}",
+                    pid,
+                    json_string(&format!("{}", signal))
+                ),
+            };
+            format!(
+                "{{\"event\":\"inferior\",\"target\":{},\"args\":[{}],\"process\":{},\"installed_breakpoints\":{}}}",
+                json_string(&info.target),
+                args.join(","),
+                process,
+                info.installed_breakpoints
+            )
+        }
+        Event::FrameInfo(info) => {
+            let caller = match &info.caller {
+                Some(caller) => format!(
+                    "{{\"address\":{},\"cfa\":{},\"rbp\":{},{}}}",
+                    caller.rip,
+                    caller.cfa,
+                    caller.rbp,
+                    json_code_location(info.caller_function.as_deref(), info.caller_line.as_ref())
+                ),
+                None => "null".to_string(),
+            };
+            format!(
+                "{{\"event\":\"frame-info\",\"level\":{},\"address\":{},\"cfa\":{},\"rbp\":{},{},\"caller\":{}}}",
+                info.level,
+                info.frame.rip,
+                info.frame.cfa,
+                info.frame.rbp,
+                json_code_location(info.function.as_deref(), info.line.as_ref()),
+                caller
+            )
+        }
+        Event::UserCommand { name, body } => {
+            let body: Vec<String> = body.iter().map(|line| json_string(line)).collect();
+            format!(
+                "{{\"event\":\"user-command\",\"name\":{},\"body\":[{}]}}",
+                json_string(name),
+                body.join(",")
+            )
+        }
+        Event::Aliases(aliases) => {
+            let aliases: Vec<String> = aliases
+                .iter()
+                .map(|alias| {
+                    format!(
+                        "{{\"name\":{},\"expansion\":{}}}",
+                        json_string(alias.name),
+                        json_string(alias.expansion)
+                    )
+                })
+                .collect();
+            format!("{{\"event\":\"aliases\",\"aliases\":[{}]}}", aliases.join(","))
+        }
+        Event::Displays(displays) => {
+            let displays: Vec<String> = displays
+                .iter()
+                .map(|display| {
+                    format!(
+                        "{{\"id\":{},\"expression\":{},\"format\":{},\"enabled\":{}}}",
+                        display.id,
+                        json_string(&display.expression),
+                        json_format(display.format),
+                        display.enabled
+                    )
+                })
+                .collect();
+            format!("{{\"event\":\"displays\",\"displays\":[{}]}}", displays.join(","))
+        }
+        Event::Display {
+            id,
+            expression,
+            format,
+            value,
+        } => format!(
+            "{{\"event\":\"display\",\"id\":{},\"expression\":{},\"format\":{},{}}}",
+            id,
+            json_string(expression),
+            json_format(*format),
+            match value {
+                Ok(value) => format!("\"value\":{}", json_string(value)),
+                Err(err) => format!("\"error\":{}", json_string(err)),
+            }
+        ),
+        Event::Message(text) => format!("{{\"event\":\"message\",\"text\":{}}}", json_string(text)),
+    }
+}
+
+/// What `ptype` calls the type of a number computed from literals, registers or arithmetic.
+const COMPUTED_TYPE: &str = "long";
+
+/// The type a chain of pointers (if `type_id` is one) ends at, with typedefs seen through, and
+/// how many pointers there were.
+fn pointee(data: &DwarfData, type_id: TypeId) -> (TypeId, usize) {
+    let mut id = data.resolve_type_id(type_id);
+    let mut pointers = 0;
+    while let Some(TypeKind::Pointer(Some(target))) = data.get_type(id).map(|ty| &ty.kind) {
+        id = data.resolve_type_id(*target);
+        pointers += 1;
+    }
+    (id, pointers)
+}
+
+/// `ptype`'s rendering of a type. A struct, union or enum, or a pointer to one, is spelled out:
+/// each member with its offset and size, and a member that is itself a struct or union spelled
+/// out one level further. Anything else is just named, with its typedefs seen through.
+fn type_definition(data: &DwarfData, type_id: TypeId) -> String {
+    let resolved = data.resolve_type_id(type_id);
+    let (id, pointers) = pointee(data, type_id);
+    let ty = match data.get_type(id) {
+        Some(ty) => ty,
+        None => return format!("type = {}", data.type_name(resolved)),
+    };
+    let pointers = if pointers == 0 {
+        String::new()
+    } else {
+        format!(" {}", "*".repeat(pointers))
+    };
+    // Each line, with the comment to show beside it
+    let mut rows: Vec<(String, String)> = Vec::new();
+    match &ty.kind {
+        TypeKind::Struct(members) | TypeKind::Union(members) => {
+            rows.push((format!("type = {} {{", aggregate_header(ty)), String::new()));
+            member_rows(data, members, 0, 1, true, &mut rows);
+            rows.push((format!("}}{}", pointers), format!("/* size {} */", data.type_size(id))));
+        }
+        TypeKind::Enum(enumerators) => {
+            let enumerators: Vec<String> = enumerators
+                .iter()
+                .map(|(name, value)| format!("{} = {}", name, value))
+                .collect();
+            let text = format!("{} {{{}}}", aggregate_header(ty), enumerators.join(", "));
+            rows.push((format!("type = {}{}", text, pointers), String::new()));
+        }
+        _ => return format!("type = {}", data.type_name(resolved)),
+    }
+    let width = rows
+        .iter()
+        .filter(|(_, comment)| !comment.is_empty())
+        .map(|(text, _)| text.len())
+        .max()
+        .unwrap_or(0);
+    rows.iter()
+        .map(|(text, comment)| {
+            if comment.is_empty() {
+                text.clone()
+            } else {
+                format!("{:<width$}  {}", text, comment, width = width)
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// `struct name`, `union name` or `enum name`, or just the keyword for an anonymous one.
+fn aggregate_header(ty: &Type) -> String {
+    let keyword = match ty.kind {
+        TypeKind::Union(_) => "union",
+        TypeKind::Enum(_) => "enum",
+        _ => "struct",
+    };
+    if ty.name.is_empty() {
+        keyword.to_string()
+    } else {
+        format!("{} {}", keyword, ty.name)
+    }
+}
+
+/// The lines for the members of a struct or union `base` bytes into the outermost one, indented
+/// `indent` levels. Offsets are from the start of the outermost struct.
+fn member_rows(
+    data: &DwarfData,
+    members: &[Member],
+    base: usize,
+    indent: usize,
+    expand: bool,
+    rows: &mut Vec<(String, String)>,
+) {
+    let pad = "    ".repeat(indent);
+    for member in members {
+        let offset = base + member.offset;
+        let comment = match member.bit_size {
+            Some(bits) => format!(
+                "/* offset {}, bit {}, {} bit{} */",
+                offset,
+                member.bit_offset,
+                bits,
+                if bits == 1 { "" } else { "s" }
+            ),
+            None => format!("/* offset {}, size {} */", offset, data.type_size(member.type_id)),
+        };
+        let nested = data.get_type(data.resolve_type_id(member.type_id));
+        match nested.map(|ty| (ty, &ty.kind)) {
+            Some((ty, TypeKind::Struct(nested))) | Some((ty, TypeKind::Union(nested)))
+                if expand =>
+            {
+                rows.push((format!("{}{} {{", pad, aggregate_header(ty)), String::new()));
+                member_rows(data, nested, offset, indent + 1, false, rows);
+                let name = if member.name.is_empty() {
+                    String::new()
+                } else {
+                    format!(" {}", member.name)
+                };
+                rows.push((format!("{}}}{};", pad, name), comment));
+            }
+            _ => {
+                let declaration = declaration(data, member.type_id, &member.name);
+                let declaration = match member.bit_size {
+                    Some(bits) => format!("{} : {}", declaration, bits),
+                    None => declaration,
+                };
+                rows.push((format!("{}{};", pad, declaration), comment));
+            }
+        }
+    }
+}
+
+/// Declares `name` as having the type `type_id`, the way C would: `char *name`, `int name[4]`.
+pub fn declaration(data: &DwarfData, type_id: TypeId, name: &str) -> String {
+    if let Some(TypeKind::Array(Some(element), count)) = data.get_type(type_id).map(|ty| &ty.kind) {
+        let count = count.map(|count| count.to_string()).unwrap_or_default();
+        return declaration(data, *element, &format!("{}[{}]", name, count));
+    }
+    let type_name = data.type_name(type_id);
+    if name.is_empty() {
+        type_name
+    } else if type_name.ends_with('*') {
+        format!("{}{}", type_name, name)
+    } else {
+        format!("{} {}", type_name, name)
+    }
+}
+
+/// A number for JSON, which has no way to write infinity or NaN; those are null.
+fn json_float(value: f64) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+/// A 128-bit register read as two doubles and as four floats, lowest lane first.
+fn vector_lanes(bytes: &[u8; 16]) -> ([f64; 2], [f32; 4]) {
+    let mut doubles = [0f64; 2];
+    for (lane, chunk) in doubles.iter_mut().zip(bytes.chunks(8)) {
+        *lane = f64::from_le_bytes(chunk.try_into().unwrap());
+    }
+    let mut floats = [0f32; 4];
+    for (lane, chunk) in floats.iter_mut().zip(bytes.chunks(4)) {
+        *lane = f32::from_le_bytes(chunk.try_into().unwrap());
+    }
+    (doubles, floats)
+}
+
+fn json_format(format: Option<char>) -> String {
+    format.map_or("null".to_string(), |letter| json_string(&letter.to_string()))
+}
+
+fn json_code_location(function: Option<&str>, line: Option<&Line>) -> String {
+    format!(
+        "\"function\":{},\"file\":{},\"line\":{}",
+        function.map(json_string).unwrap_or_else(|| "null".to_string()),
+        line.map(|line| json_string(&line.file))
+            .unwrap_or_else(|| "null".to_string()),
+        line.map(|line| line.number.to_string())
+            .unwrap_or_else(|| "null".to_string())
+    )
+}
+
+/// Bytes per row in `hexdump`.
+const HEXDUMP_WIDTH: usize = 16;
+
+/// Renders memory the way `hexdump -C` does: 16 bytes per row after the address, in two groups
+/// of eight, then the bytes as ASCII with anything unprintable as a dot. A run of rows identical
+/// to the one before is shown as a single `*`.
+pub fn hexdump(start: usize, bytes: &[u8]) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut previous: Option<&[u8]> = None;
+    let mut collapsed = false;
+    for (idx, row) in bytes.chunks(HEXDUMP_WIDTH).enumerate() {
+        if previous == Some(row) {
+            if !collapsed {
+                lines.push("*".to_string());
+                collapsed = true;
+            }
+            continue;
+        }
+        previous = Some(row);
+        collapsed = false;
+        let mut hex = String::new();
+        for column in 0..HEXDUMP_WIDTH {
+            if column == HEXDUMP_WIDTH / 2 {
+                hex.push(' ');
+            }
+            match row.get(column) {
+                Some(byte) => hex.push_str(&format!("{:02x} ", byte)),
+                None => hex.push_str("   "),
+            }
+        }
+        let ascii: String = row
+            .iter()
+            .map(|&byte| if byte == b' ' || byte.is_ascii_graphic() { byte as char } else { '.' })
+            .collect();
+        lines.push(format!("{:012x}  {} |{}|", start + idx * HEXDUMP_WIDTH, hex, ascii));
+    }
+    // Like hexdump, say where the data ends when that's hidden in a `*`
+    if collapsed {
+        lines.push(format!("{:012x}", start + bytes.len()));
+    }
+    lines
+}
+
+/// Renders the rows of `hexdump` where `old` and `new` differ, as a pair of lines each: the offset
+/// and address with the old bytes, then the new bytes with the unchanged ones shown as `..`.
+/// Empty if nothing changed.
+pub fn memory_diff(start: usize, old: &[u8], new: &[u8]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (idx, (old_row, new_row)) in old
+        .chunks(HEXDUMP_WIDTH)
+        .zip(new.chunks(HEXDUMP_WIDTH))
+        .enumerate()
+    {
+        if old_row == new_row {
+            continue;
+        }
+        let mut old_hex = String::new();
+        let mut new_hex = String::new();
+        for (column, (old_byte, new_byte)) in old_row.iter().zip(new_row.iter()).enumerate() {
+            if column == HEXDUMP_WIDTH / 2 {
+                old_hex.push(' ');
+                new_hex.push(' ');
+            }
+            old_hex.push_str(&format!(" {:02x}", old_byte));
+            if old_byte == new_byte {
+                new_hex.push_str(" ..");
+            } else {
+                new_hex.push_str(&format!(" {:02x}", new_byte));
+            }
+        }
+        let offset = idx * HEXDUMP_WIDTH;
+        lines.push(format!("+{:<#6x} {:012x}  -{}", offset, start + offset, old_hex));
+        lines.push(format!("{:20}  +{}", "", new_hex));
+    }
+    lines
+}
+
+/// A fault's address, si_code and what the code means, for stop and siginfo events.
+fn json_fault(sig: Signal, fault: &Fault) -> String {
+    format!(
+        "{{\"address\":{},\"code\":{},\"description\":{}}}",
+        fault.address,
+        fault.code,
+        fault.description(sig).map_or("null".to_string(), json_string)
+    )
+}
+
+/// Quotes and escapes a string for JSON.
+pub fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_plain_text_with_style_off() {
+        let line = Line {
+            file: "samples/function_calls.c".to_string(),
+            number: 17,
+            address: 0x401136,
+        };
+        let frames = [
+            Frame {
+                address: 0x401136,
+                function: Some("func1".to_string()),
+                line: Some(line.clone()),
+                inlined: false,
+            },
+            Frame {
+                address: 0x4011a2,
+                function: None,
+                line: None,
+                inlined: false,
+            },
+        ];
+        let at_line = &line;
+        let stop = move |reason, signal| Event::Stopped {
+            signal,
+            address: 0x401136,
+            reason,
+            breakpoint: Some(1),
+            function: Some("func1"),
+            line: Some(at_line),
+        };
+        let cases = [
+            (
+                Event::BreakpointSet {
+                    id: 1,
+                    sub_location: None,
+                    address: 0x401136,
+                    function: Some("func1"),
+                    line: Some(&line),
+                },
+                "Breakpoint 1 at 0x401136: func1 at samples/function_calls.c:17",
+            ),
+            (
+                stop(StopReason::Breakpoint(0x401136), Signal::SIGTRAP),
+                "Breakpoint 1, func1 at samples/function_calls.c:17",
+            ),
+            (
+                stop(StopReason::Signal(None), Signal::SIGSEGV),
+                "Program received signal SIGSEGV, Segmentation fault.\n\
+                 0x401136 in func1 at samples/function_calls.c:17",
+            ),
+            (
+                Event::Frame {
+                    level: 0,
+                    frame: &frames[0],
+                },
+                "#0  0x401136 in func1 at samples/function_calls.c:17",
+            ),
+            (
+                Event::Backtrace(&frames),
+                "#0  0x401136 in func1 at samples/function_calls.c:17\n#1  0x4011a2 in ?? ()",
+            ),
+        ];
+        set_style(false);
+        for (event, expected) in cases.iter() {
+            assert_eq!(render_text(event), *expected);
+        }
+        // The same text, colored, with styling on
+        set_style(true);
+        let styled: Vec<String> = cases.iter().map(|(event, _)| render_text(event)).collect();
+        set_style(false);
+        for (text, (_, expected)) in styled.iter().zip(cases.iter()) {
+            assert!(text.contains('\x1b'), "{:?}", text);
+            assert_eq!(unstyled(text), *expected);
+        }
+    }
+
+    #[test]
+    fn breakpoint_stops_carry_their_id() {
+        let line = Line {
+            file: "list.c".to_string(),
+            number: 42,
+            address: 0x401136,
+        };
+        let stop = |reason, breakpoint, function, line| Event::Stopped {
+            signal: Signal::SIGTRAP,
+            address: 0x401136,
+            reason,
+            breakpoint,
+            function,
+            line,
+        };
+        let hit = stop(StopReason::Breakpoint(0x401136), Some(3), Some("compute_sum"), Some(&line));
+        // `unstyled` because another test turns styling on for a moment
+        assert_eq!(unstyled(&render_text(&hit)), "Breakpoint 3, compute_sum at list.c:42");
+        assert!(render_json(&hit).contains("\"reason\":\"breakpoint\",\"breakpoint\":3,"));
+        // Set by address, somewhere no symbol covers: just the address
+        let by_address = stop(StopReason::Breakpoint(0x401136), Some(4), None, None);
+        assert_eq!(unstyled(&render_text(&by_address)), "Breakpoint 4, 0x401136");
+        // A trap that no breakpoint of the user's accounts for isn't worded as one
+        let trap = stop(StopReason::Trap, None, None, None);
+        assert!(!unstyled(&render_text(&trap)).contains("Breakpoint"));
+        assert!(render_json(&trap).contains("\"reason\":\"trap\",\"breakpoint\":null,"));
+    }
+
+    #[test]
+    fn hexdump_rows_show_hex_then_ascii() {
+        let bytes = b"Hello, world!\n\x00\x01ABC";
+        assert_eq!(
+            hexdump(0x401000, bytes),
+            [
+                "000000401000  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0a 00 01  \
+                 |Hello, world!...|",
+                // The last row is padded to keep the ASCII column in line
+                &format!("000000401010  41 42 43 {:40} |ABC|", ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn hexdump_collapses_repeated_rows() {
+        let zeros = "00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00";
+        let mut bytes = vec![0u8; 40];
+        bytes.extend_from_slice(b"end");
+        assert_eq!(
+            hexdump(0x7ffc0, &bytes),
+            [
+                format!("00000007ffc0  {}  |................|", zeros),
+                "*".to_string(),
+                format!("00000007ffe0  00 00 00 00 00 00 00 00  65 6e 64 {:15} |........end|", ""),
+            ]
+        );
+        // Ending in repeated rows, the end address is given after the `*`
+        assert_eq!(
+            hexdump(0x10, &[0u8; 32]),
+            [
+                format!("000000000010  {}  |................|", zeros),
+                "*".to_string(),
+                "000000000030".to_string(),
+            ]
+        );
+    }
+}
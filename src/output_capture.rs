@@ -0,0 +1,81 @@
+//! `set capture-output on`: the inferior's stdout and stderr go to pipes instead of the terminal.
+//! A thread per pipe shows each line labelled with the stream it came from (or as an event in
+//! JSON mode) and keeps it for `info output`.
+
+use crate::output::{self, Event};
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// The most bytes of output kept for `info output`. Older lines are dropped (and counted) to make
+/// room for new ones.
+const CAPTURE_LIMIT: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    pub fn name(self) -> &'static str {
+        match self {
+            Stream::Stdout => "stdout",
+            Stream::Stderr => "stderr",
+        }
+    }
+}
+
+/// The output of one run, as far as it fits in CAPTURE_LIMIT.
+#[derive(Default)]
+pub struct CapturedOutput {
+    /// The lines kept, oldest first, without their newlines
+    pub lines: VecDeque<(Stream, String)>,
+    bytes: usize,
+    /// How many lines were dropped to stay under the limit
+    pub dropped: usize,
+}
+
+impl CapturedOutput {
+    fn push(&mut self, stream: Stream, line: String) {
+        self.bytes += line.len();
+        self.lines.push_back((stream, line));
+        while self.bytes > CAPTURE_LIMIT {
+            match self.lines.pop_front() {
+                Some((_, old)) => {
+                    self.bytes -= old.len();
+                    self.dropped += 1;
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+/// Captured output, shared between the relay threads and the session.
+pub type SharedOutput = Arc<Mutex<CapturedOutput>>;
+
+/// Starts a thread that shows and keeps each line read from `pipe` until it is closed (when the
+/// inferior exits). A last line without a newline is kept too.
+pub fn relay<R: Read + Send + 'static>(pipe: R, stream: Stream, captured: SharedOutput) {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(pipe);
+        let mut buf = Vec::new();
+        while let Ok(n) = reader.read_until(b'\n', &mut buf) {
+            if n == 0 {
+                break;
+            }
+            if buf.last() == Some(&b'\n') {
+                buf.pop();
+            }
+            let line = String::from_utf8_lossy(&buf).into_owned();
+            output::emit(&Event::InferiorOutput {
+                stream,
+                text: &line,
+            });
+            captured.lock().unwrap().push(stream, line);
+            buf.clear();
+        }
+    });
+}
@@ -0,0 +1,245 @@
+//! The text handling behind `echo` and `printf`: backslash escapes, and C-style format strings
+//! with `%d %u %x %s %c %%` conversions.
+
+use std::fmt;
+
+#[derive(Debug)]
+pub enum PrintfError {
+    /// The arguments don't start with a double-quoted format string
+    MissingFormat,
+    /// The format string has no closing quote
+    UnterminatedFormat,
+    /// Something other than a comma after the format string
+    ExpectedComma,
+    /// A `%` conversion we don't support
+    BadConversion(String),
+    /// The format string wants a different number of arguments than were given
+    ArgumentCount { expected: usize, given: usize },
+}
+
+impl fmt::Display for PrintfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrintfError::MissingFormat => {
+                write!(f, "Bad format string: expected \"format\", args...")
+            }
+            PrintfError::UnterminatedFormat => {
+                write!(f, "Bad format string: missing closing quote.")
+            }
+            PrintfError::ExpectedComma => write!(f, "Expected a comma after the format string."),
+            PrintfError::BadConversion(conversion) => write!(
+                f,
+                "Unsupported conversion \"{}\" in format string (use %d %u %x %s %c or %%).",
+                conversion
+            ),
+            PrintfError::ArgumentCount { expected, given } => write!(
+                f,
+                "The format string needs {} argument{}, but {} {} given.",
+                expected,
+                if *expected == 1 { "" } else { "s" },
+                given,
+                if *given == 1 { "was" } else { "were" }
+            ),
+        }
+    }
+}
+
+/// A value for one conversion: `%s` takes text (already read from the inferior), the others a
+/// number.
+pub enum Argument {
+    Integer(i64),
+    Text(String),
+}
+
+enum Piece {
+    Text(String),
+    /// The conversion letter, one of `d u x s c`
+    Conversion(char),
+}
+
+/// A parsed format string.
+pub struct Template {
+    pieces: Vec<Piece>,
+}
+
+impl Template {
+    /// Parses a format string whose escapes have already been expanded. Length modifiers (`%ld`,
+    /// `%lx`, ...) are accepted and ignored, since all integers are 64 bits here.
+    pub fn parse(format: &str) -> Result<Template, PrintfError> {
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        let mut chars = format.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                text.push(c);
+                continue;
+            }
+            let mut conversion = String::from("%");
+            loop {
+                match chars.next() {
+                    Some('%') if conversion.len() == 1 => {
+                        text.push('%');
+                        break;
+                    }
+                    Some(modifier @ 'l') | Some(modifier @ 'h') | Some(modifier @ 'z') => {
+                        conversion.push(modifier)
+                    }
+                    Some(letter @ 'd') | Some(letter @ 'i') | Some(letter @ 'u')
+                    | Some(letter @ 'x') | Some(letter @ 's') | Some(letter @ 'c') => {
+                        if !text.is_empty() {
+                            pieces.push(Piece::Text(std::mem::replace(&mut text, String::new())));
+                        }
+                        pieces.push(Piece::Conversion(if letter == 'i' { 'd' } else { letter }));
+                        break;
+                    }
+                    Some(other) => {
+                        conversion.push(other);
+                        return Err(PrintfError::BadConversion(conversion));
+                    }
+                    None => return Err(PrintfError::BadConversion(conversion)),
+                }
+            }
+        }
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+        Ok(Template { pieces })
+    }
+
+    /// The conversion letters that take an argument, in order.
+    pub fn conversions(&self) -> Vec<char> {
+        self.pieces
+            .iter()
+            .filter_map(|piece| match piece {
+                Piece::Conversion(letter) => Some(*letter),
+                Piece::Text(_) => None,
+            })
+            .collect()
+    }
+
+    /// Fills in the conversions. `args` must match `conversions` in number and kind; a
+    /// mismatched kind is rendered as best it can be.
+    pub fn render(&self, args: &[Argument]) -> String {
+        let mut output = String::new();
+        let mut args = args.iter();
+        for piece in &self.pieces {
+            match piece {
+                Piece::Text(text) => output.push_str(text),
+                Piece::Conversion(letter) => {
+                    if let Some(arg) = args.next() {
+                        output.push_str(&convert(*letter, arg));
+                    }
+                }
+            }
+        }
+        output
+    }
+}
+
+fn convert(letter: char, arg: &Argument) -> String {
+    match (letter, arg) {
+        ('d', Argument::Integer(n)) => n.to_string(),
+        ('u', Argument::Integer(n)) => (*n as u64).to_string(),
+        ('x', Argument::Integer(n)) => format!("{:x}", n),
+        ('c', Argument::Integer(n)) => (*n as u8 as char).to_string(),
+        (_, Argument::Text(text)) => text.clone(),
+        (_, Argument::Integer(n)) => n.to_string(),
+    }
+}
+
+/// Parses the arguments of `printf` or `dprintf` into the format string's template and the
+/// argument expressions, one for each conversion.
+pub fn parse_arguments(line: &str) -> Result<(Template, Vec<String>), PrintfError> {
+    let (format, expressions) = split_arguments(line)?;
+    let template = Template::parse(&format)?;
+    let expected = template.conversions().len();
+    if expected != expressions.len() {
+        return Err(PrintfError::ArgumentCount {
+            expected,
+            given: expressions.len(),
+        });
+    }
+    Ok((template, expressions))
+}
+
+/// Splits the arguments of `printf`, `"format", expr, expr...`, into the format string (with its
+/// escapes expanded) and the argument expressions. Commas inside parentheses, brackets or
+/// quotes don't separate arguments, so `printf "%d\n", f(a, b)` works.
+pub fn split_arguments(line: &str) -> Result<(String, Vec<String>), PrintfError> {
+    let line = line.trim();
+    if !line.starts_with('"') {
+        return Err(PrintfError::MissingFormat);
+    }
+    // Find the closing quote, skipping escaped ones
+    let mut end = None;
+    let mut escaped = false;
+    for (idx, c) in line.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => {
+                end = Some(idx);
+                break;
+            }
+            _ => {}
+        }
+    }
+    let end = end.ok_or(PrintfError::UnterminatedFormat)?;
+    let format = unescape(&line[1..end]);
+    let rest = line[end + 1..].trim();
+    if rest.is_empty() {
+        return Ok((format, Vec::new()));
+    }
+    if !rest.starts_with(',') {
+        return Err(PrintfError::ExpectedComma);
+    }
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+    let mut quote = None;
+    for c in rest[1..].chars() {
+        match c {
+            '"' | '\'' if quote.is_none() => quote = Some(c),
+            _ if Some(c) == quote => quote = None,
+            '(' | '[' if quote.is_none() => depth += 1,
+            ')' | ']' if quote.is_none() => depth = depth.saturating_sub(1),
+            ',' if quote.is_none() && depth == 0 => {
+                args.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    args.push(current.trim().to_string());
+    Ok((format, args))
+}
+
+/// Expands the C escapes `\n \t \r \a \e \" \\` (and `\` before anything else, which is kept
+/// as-is).
+pub fn unescape(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            output.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => output.push('\n'),
+            Some('t') => output.push('\t'),
+            Some('r') => output.push('\r'),
+            Some('a') => output.push('\x07'),
+            Some('e') => output.push('\x1b'),
+            Some('"') => output.push('"'),
+            Some('\\') => output.push('\\'),
+            Some(other) => {
+                output.push('\\');
+                output.push(other);
+            }
+            None => output.push('\\'),
+        }
+    }
+    output
+}
@@ -0,0 +1,71 @@
+//! Parsing of /proc/<pid>/maps, which lists the inferior's memory mappings.
+
+use nix::unistd::Pid;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// One line of /proc/<pid>/maps.
+#[derive(Debug, Clone)]
+pub struct Mapping {
+    pub start: usize,
+    pub end: usize,
+    /// Permissions, such as "r-xp"
+    pub perms: String,
+    /// Offset of the mapping in the file
+    pub offset: usize,
+    /// The mapped file. None for anonymous mappings and for pseudo-paths like [stack].
+    pub path: Option<PathBuf>,
+    /// The last column as listed: a file path, a pseudo-path like [heap], or empty for anonymous
+    /// mappings
+    pub name: String,
+}
+
+impl Mapping {
+    pub fn is_executable(&self) -> bool {
+        self.perms.contains('x')
+    }
+
+    pub fn is_readable(&self) -> bool {
+        self.perms.starts_with('r')
+    }
+
+    pub fn contains(&self, addr: usize) -> bool {
+        self.start <= addr && addr < self.end
+    }
+}
+
+/// Reads the current mappings of a process.
+pub fn read(pid: Pid) -> io::Result<Vec<Mapping>> {
+    let maps = fs::read_to_string(format!("/proc/{}/maps", pid))?;
+    Ok(maps.lines().filter_map(parse_line).collect())
+}
+
+/// Parses a line like "00400000-00401000 r-xp 00000000 08:01 1234   /path/to/binary".
+fn parse_line(line: &str) -> Option<Mapping> {
+    let mut fields = line.split_whitespace();
+    let mut bounds = fields.next()?.split('-');
+    let start = usize::from_str_radix(bounds.next()?, 16).ok()?;
+    let end = usize::from_str_radix(bounds.next()?, 16).ok()?;
+    let perms = fields.next()?.to_string();
+    let offset = usize::from_str_radix(fields.next()?, 16).ok()?;
+    // Skip the device and inode; the rest of the line (which may contain spaces) is the path
+    let path = fields
+        .skip(2)
+        .collect::<Vec<&str>>()
+        .join(" ");
+    let name = path.clone();
+    let path = if path.starts_with('/') {
+        Some(PathBuf::from(path))
+    } else {
+        None
+    };
+    Some(Mapping {
+        start,
+        end,
+        perms,
+        offset,
+        path,
+        name,
+    })
+}
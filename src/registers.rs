@@ -0,0 +1,129 @@
+//! Making sense of the floating point and vector registers, as PTRACE_GETFPREGS returns them in
+//! the FXSAVE layout: the eight x87 registers in stack order, ten bytes of each used out of 16,
+//! then the sixteen 128-bit XMM registers.
+
+/// How many x87 registers there are, st0 to st7.
+pub const X87_REGISTERS: usize = 8;
+/// How many XMM registers x86-64 has, xmm0 to xmm15.
+pub const XMM_REGISTERS: usize = 16;
+
+/// The registers `info registers` lists, in the order it lists them.
+pub const GENERAL_REGISTERS: &[&str] = &[
+    "rax", "rbx", "rcx", "rdx", "rsi", "rdi", "rbp", "rsp", "r8", "r9", "r10", "r11", "r12", "r13",
+    "r14", "r15", "rip", "eflags", "cs", "ss", "ds", "es", "fs", "gs", "fs_base", "gs_base",
+];
+
+/// The registers that hold addresses, which are shown in hex rather than decimal.
+pub const ADDRESS_REGISTERS: &[&str] = &["rip", "rsp", "rbp", "fs_base", "gs_base"];
+
+/// The eflags bits with names, by bit number.
+const EFLAGS: &[(u32, &str)] = &[
+    (0, "CF"),
+    (2, "PF"),
+    (4, "AF"),
+    (6, "ZF"),
+    (7, "SF"),
+    (8, "TF"),
+    (9, "IF"),
+    (10, "DF"),
+    (11, "OF"),
+    (14, "NT"),
+    (16, "RF"),
+    (17, "VM"),
+    (18, "AC"),
+    (19, "VIF"),
+    (20, "VIP"),
+    (21, "ID"),
+];
+
+/// The names of the flags set in an eflags value, lowest bit first.
+pub fn eflags_names(eflags: u64) -> Vec<&'static str> {
+    EFLAGS
+        .iter()
+        .filter(|(bit, _)| eflags & (1 << bit) != 0)
+        .map(|(_, name)| *name)
+        .collect()
+}
+
+/// The 80 bits of x87 register `st<n>`, little-endian.
+pub fn st(fpregs: &libc::user_fpregs_struct, n: usize) -> [u8; 10] {
+    let mut slot = [0u8; 16];
+    for (chunk, word) in slot.chunks_mut(4).zip(&fpregs.st_space[n * 4..n * 4 + 4]) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    let mut bytes = [0u8; 10];
+    bytes.copy_from_slice(&slot[..10]);
+    bytes
+}
+
+/// Whether `st<n>` holds nothing. FXSAVE keeps one tag bit per physical register, set when it's
+/// in use; st0 is the physical register the top-of-stack field of the status word points at.
+pub fn st_empty(fpregs: &libc::user_fpregs_struct, n: usize) -> bool {
+    let top = (fpregs.swd >> 11) as usize & 7;
+    let physical = (top + n) % X87_REGISTERS;
+    fpregs.ftw & (1 << physical) == 0
+}
+
+/// An 80-bit extended precision number as the nearest f64: values too big or too small for an
+/// f64 become infinity or zero.
+pub fn x87_to_f64(bytes: &[u8; 10]) -> f64 {
+    let mut mantissa_bytes = [0u8; 8];
+    mantissa_bytes.copy_from_slice(&bytes[..8]);
+    let mantissa = u64::from_le_bytes(mantissa_bytes);
+    let sign = if bytes[9] & 0x80 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bytes[9] as i32 & 0x7f) << 8) | bytes[8] as i32;
+    let magnitude = match exponent {
+        0 if mantissa == 0 => 0.0,
+        // Infinity has only the explicit integer bit set; anything else is a NaN
+        0x7fff if mantissa << 1 == 0 => std::f64::INFINITY,
+        0x7fff => std::f64::NAN,
+        // Denormals have an exponent of 1 - 16383, like the smallest normal numbers
+        _ => mantissa as f64 * 2f64.powi(exponent.max(1) - 16383 - 63),
+    };
+    sign * magnitude
+}
+
+/// The 128 bits of `xmm<n>`, little-endian.
+pub fn xmm(fpregs: &libc::user_fpregs_struct, n: usize) -> [u8; 16] {
+    let mut bytes = [0u8; 16];
+    for (chunk, word) in bytes.chunks_mut(4).zip(&fpregs.xmm_space[n * 4..n * 4 + 4]) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    bytes
+}
+
+/// The number of an XMM register named like `xmm3`.
+pub fn xmm_number(name: &str) -> Option<usize> {
+    let number: usize = name.strip_prefix("xmm")?.parse().ok()?;
+    if number < XMM_REGISTERS {
+        Some(number)
+    } else {
+        None
+    }
+}
+
+/// A register's bytes as one hex number, most significant digit first.
+pub fn hex(bytes: &[u8]) -> String {
+    let digits: String = bytes.iter().rev().map(|byte| format!("{:02x}", byte)).collect();
+    format!("0x{}", digits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eflags_decoding() {
+        assert!(eflags_names(0).is_empty());
+        // What a program usually runs with: IF, plus the always-set reserved bit 1
+        assert_eq!(eflags_names(0x202), vec!["IF"]);
+        // After comparing equal values
+        assert_eq!(eflags_names(0x246), vec!["PF", "ZF", "IF"]);
+        assert_eq!(eflags_names(0x893), vec!["CF", "AF", "SF", "OF"]);
+        assert_eq!(eflags_names((1 << 21) | (1 << 16) | (1 << 8)), vec!["TF", "RF", "ID"]);
+        // Bits without a name (reserved ones, the I/O privilege level, the upper half) are skipped
+        assert!(eflags_names(0x2 | 0x3000 | 0x8000 | 0xffff_ffff_0000_0000).is_empty());
+        let all: u64 = EFLAGS.iter().map(|(bit, _)| 1u64 << bit).sum();
+        assert_eq!(eflags_names(all).len(), EFLAGS.len());
+    }
+}
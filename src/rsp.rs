@@ -0,0 +1,305 @@
+//! The framing of the GDB remote serial protocol, for `--gdbserver`: `$data#cs` packets with a
+//! two-digit checksum, each acknowledged with `+` (or `-` to ask for it again) until the client
+//! turns acknowledgements off with QStartNoAckMode.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::os::unix::io::AsRawFd;
+use std::thread;
+use std::time::Duration;
+
+/// The byte a client sends outside of any packet to interrupt the running program (ctrl+c).
+const INTERRUPT: u8 = 0x03;
+/// Escapes the byte after it, which is xored with ESCAPE_XOR.
+const ESCAPE: u8 = b'}';
+const ESCAPE_XOR: u8 = 0x20;
+/// How many times a packet is sent again after the client asks for it with `-`.
+const RETRANSMIT_LIMIT: usize = 10;
+
+/// What came in from the client.
+pub enum Incoming {
+    /// A packet whose checksum matched, unescaped
+    Packet(Vec<u8>),
+    /// A ctrl+c
+    Interrupt,
+}
+
+pub struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    /// Whether the client has turned acknowledgements off
+    no_ack: bool,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> io::Result<Connection> {
+        stream.set_nodelay(true)?;
+        Ok(Connection {
+            writer: stream.try_clone()?,
+            reader: BufReader::new(stream),
+            no_ack: false,
+        })
+    }
+
+    /// Stops sending and expecting acknowledgements, once the reply to QStartNoAckMode has gone.
+    pub fn set_no_ack(&mut self) {
+        self.no_ack = true;
+    }
+
+    /// Reads the next packet or interrupt, asking again for any packet that arrives damaged.
+    /// Stray acknowledgements are skipped. None once the client has closed the connection.
+    pub fn read(&mut self) -> io::Result<Option<Incoming>> {
+        loop {
+            match self.read_byte()? {
+                None => return Ok(None),
+                Some(INTERRUPT) => return Ok(Some(Incoming::Interrupt)),
+                Some(b'$') => {}
+                Some(_) => continue,
+            }
+            let mut data = Vec::new();
+            loop {
+                match self.read_byte()? {
+                    None => return Ok(None),
+                    Some(b'#') => break,
+                    Some(byte) => data.push(byte),
+                }
+            }
+            let mut digits = [0u8; 2];
+            self.reader.read_exact(&mut digits)?;
+            let sent = std::str::from_utf8(&digits)
+                .ok()
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok());
+            if sent == Some(checksum(&data)) {
+                if !self.no_ack {
+                    self.writer.write_all(b"+")?;
+                }
+                return Ok(Some(Incoming::Packet(unescape(&data))));
+            }
+            if !self.no_ack {
+                self.writer.write_all(b"-")?;
+            }
+        }
+    }
+
+    /// Sends a packet, escaping what needs it, and waits for the client to acknowledge it.
+    pub fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        let escaped = escape(data);
+        let mut packet = Vec::with_capacity(escaped.len() + 4);
+        packet.push(b'$');
+        packet.extend_from_slice(&escaped);
+        packet.extend_from_slice(format!("#{:02x}", checksum(&escaped)).as_bytes());
+        for _ in 0..=RETRANSMIT_LIMIT {
+            self.writer.write_all(&packet)?;
+            if self.no_ack {
+                return Ok(());
+            }
+            loop {
+                match self.read_byte()? {
+                    Some(b'+') => return Ok(()),
+                    Some(b'-') => break,
+                    Some(_) => continue,
+                    None => return Err(io::ErrorKind::UnexpectedEof.into()),
+                }
+            }
+        }
+        Err(io::Error::new(io::ErrorKind::Other, "the client kept rejecting a packet"))
+    }
+
+    /// Waits up to `timeout` for a ctrl+c, for while the program runs. A packet that arrives
+    /// instead is left for `read`; stray acknowledgements are skipped. An error if the client has
+    /// closed the connection.
+    pub fn interrupted(&mut self, timeout: Duration) -> io::Result<bool> {
+        if self.reader.buffer().is_empty() {
+            let mut fd = libc::pollfd {
+                fd: self.reader.get_ref().as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let ready = unsafe { libc::poll(&mut fd, 1, timeout.as_millis() as libc::c_int) };
+            if ready < 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    return Ok(false);
+                }
+                return Err(err);
+            }
+            if ready == 0 {
+                return Ok(false);
+            }
+            if self.reader.fill_buf()?.is_empty() {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+        }
+        loop {
+            match self.reader.buffer().first() {
+                Some(&INTERRUPT) => {
+                    self.reader.consume(1);
+                    return Ok(true);
+                }
+                Some(b'$') => {
+                    // Nothing to do with it until the program stops, so don't spin on it
+                    thread::sleep(timeout);
+                    return Ok(false);
+                }
+                Some(_) => self.reader.consume(1),
+                None => return Ok(false),
+            }
+        }
+    }
+
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0u8; 1];
+        match self.reader.read(&mut byte)? {
+            0 => Ok(None),
+            _ => Ok(Some(byte[0])),
+        }
+    }
+}
+
+/// The sum of the bytes between `$` and `#`, modulo 256.
+fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte))
+}
+
+/// Escapes the bytes that would otherwise end the packet or be taken for run-length encoding.
+fn escape(data: &[u8]) -> Vec<u8> {
+    let mut escaped = Vec::with_capacity(data.len());
+    for &byte in data {
+        match byte {
+            b'$' | b'#' | b'}' | b'*' => escaped.extend_from_slice(&[ESCAPE, byte ^ ESCAPE_XOR]),
+            _ => escaped.push(byte),
+        }
+    }
+    escaped
+}
+
+fn unescape(data: &[u8]) -> Vec<u8> {
+    let mut unescaped = Vec::with_capacity(data.len());
+    let mut bytes = data.iter();
+    while let Some(&byte) = bytes.next() {
+        match byte {
+            ESCAPE => match bytes.next() {
+                Some(&escaped) => unescaped.push(escaped ^ ESCAPE_XOR),
+                None => break,
+            },
+            _ => unescaped.push(byte),
+        }
+    }
+    unescaped
+}
+
+/// Bytes as two lowercase hex digits each, the way memory and registers are sent.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// The bytes of a string of hex digit pairs. None if there's an odd digit out or a non-digit.
+pub fn from_hex(text: &[u8]) -> Option<Vec<u8>> {
+    if text.len() % 2 != 0 {
+        return None;
+    }
+    // Digit by digit: from_str_radix would also take a sign
+    let digit = |byte: u8| (byte as char).to_digit(16).map(|digit| digit as u8);
+    text.chunks(2)
+        .map(|pair| Some((digit(pair[0])? << 4) | digit(pair[1])?))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    /// A connection and the client's end of it.
+    fn connected() -> (Connection, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        (Connection::new(stream).unwrap(), client)
+    }
+
+    fn read_exactly(client: &mut TcpStream, len: usize) -> Vec<u8> {
+        let mut bytes = vec![0u8; len];
+        client.read_exact(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(checksum(b""), 0);
+        assert_eq!(checksum(b"OK"), 0x9a);
+        assert_eq!(checksum(b"qSupported"), 0x37);
+        // Wraps rather than overflowing
+        assert_eq!(checksum(&[0xff, 0x02]), 0x01);
+    }
+
+    #[test]
+    fn escaping() {
+        assert_eq!(escape(b"abc"), b"abc");
+        assert_eq!(escape(b"$#}*"), b"}\x04}\x03}]}\x0a");
+        for data in [&b"plain"[..], b"a$b#c}d*e", b"}}", b""].iter() {
+            assert_eq!(unescape(&escape(data)), *data);
+        }
+        // A trailing escape with nothing after it is dropped
+        assert_eq!(unescape(b"ab}"), b"ab");
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(to_hex(&[0x00, 0xab, 0x7f]), "00ab7f");
+        assert_eq!(from_hex(b"00ab7f"), Some(vec![0x00, 0xab, 0x7f]));
+        assert_eq!(from_hex(b"00AB"), Some(vec![0x00, 0xab]));
+        assert_eq!(from_hex(b""), Some(vec![]));
+        assert_eq!(from_hex(b"abc"), None);
+        assert_eq!(from_hex(b"zz"), None);
+        assert_eq!(from_hex(b"+1"), None);
+    }
+
+    #[test]
+    fn damaged_packets_are_asked_for_again() {
+        let (mut connection, mut client) = connected();
+        client.write_all(b"$OK#00$OK#9a").unwrap();
+        match connection.read().unwrap() {
+            Some(Incoming::Packet(packet)) => assert_eq!(packet, b"OK"),
+            _ => panic!("expected a packet"),
+        }
+        assert_eq!(read_exactly(&mut client, 2), b"-+");
+    }
+
+    #[test]
+    fn rejected_packets_are_sent_again() {
+        let (mut connection, mut client) = connected();
+        client.write_all(b"--+").unwrap();
+        connection.write(b"OK").unwrap();
+        assert_eq!(read_exactly(&mut client, 18), b"$OK#9a$OK#9a$OK#9a");
+    }
+
+    #[test]
+    fn gives_up_on_a_packet_rejected_too_often() {
+        let (mut connection, mut client) = connected();
+        client.write_all(&[b'-'; RETRANSMIT_LIMIT + 1]).unwrap();
+        assert!(connection.write(b"OK").is_err());
+    }
+
+    #[test]
+    fn interrupts_while_running() {
+        let (mut connection, mut client) = connected();
+        let timeout = Duration::from_millis(10);
+        assert!(!connection.interrupted(timeout).unwrap());
+        client.write_all(b"+\x03$g#67").unwrap();
+        let mut interrupted = false;
+        for _ in 0..100 {
+            if connection.interrupted(timeout).unwrap() {
+                interrupted = true;
+                break;
+            }
+        }
+        assert!(interrupted);
+        // The packet after it is still there to read
+        assert!(!connection.interrupted(timeout).unwrap());
+        match connection.read().unwrap() {
+            Some(Incoming::Packet(packet)) => assert_eq!(packet, b"g"),
+            _ => panic!("expected a packet"),
+        }
+    }
+}
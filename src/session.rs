@@ -0,0 +1,4062 @@
+//! Programmatic control of a debugging session: the target's debug info, its breakpoints, and the
+//! running inferior. Nothing in here reads from the terminal or prints; the REPL in debugger.rs is
+//! a thin layer on top of this, and other tools can drive a Session directly.
+
+use crate::dwarf_data::{
+    self, DebugInfo, DwarfData, Error as DwarfError, Function, Line, LineRow, PltEntry, TypeId,
+    TypeKind, Variable,
+};
+use crate::dwarf_expr::{self, ExprError, Frame as _, Place};
+use crate::expr::{self, EvalError, Format, Storage, Value};
+use crate::inferior::{
+    self, BreakpointError, Environment, Frame, Inferior, InferiorError, PtraceError, Redirects,
+    RunStats, SignalInfo, StackFrame, Status, StopReason,
+};
+use crate::breakpoint::{Breakpoint, BreakpointId, BreakpointTable, EnableMode};
+use crate::coverage::Coverage;
+use crate::gimli_wrapper;
+use crate::heap::{Allocator, HeapTracker};
+use crate::output_capture::{SharedOutput, Stream};
+use crate::printf::{self, Argument, PrintfError};
+use crate::proc_maps;
+use crate::registers;
+use crate::shared_library::{LibraryChanges, LoadedLibraries, SharedLibrary};
+use crate::skip::SkipList;
+use crate::source_cache::{self, PathSubstitutions, SourceCache};
+use crate::syscalls::{self, SyscallTrace};
+use nix::sys::signal::Signal;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::TryInto;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// A shell-style redirection of one of the inferior's standard streams, from `run` or `set args`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Redirection {
+    /// `< file`
+    Stdin(String),
+    /// `> file`
+    Stdout(String),
+    /// `>> file`
+    AppendStdout(String),
+    /// `2> file`
+    Stderr(String),
+    /// `2>&1`: standard error goes wherever standard output goes at this point
+    StderrToStdout,
+}
+
+impl fmt::Display for Redirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Redirection::Stdin(path) => write!(f, "< {}", path),
+            Redirection::Stdout(path) => write!(f, "> {}", path),
+            Redirection::AppendStdout(path) => write!(f, ">> {}", path),
+            Redirection::Stderr(path) => write!(f, "2> {}", path),
+            Redirection::StderrToStdout => write!(f, "2>&1"),
+        }
+    }
+}
+
+/// Where to put a breakpoint.
+#[derive(Debug, Clone)]
+pub enum Location {
+    Address(usize),
+    /// A line, in the given file or (when None) in the file containing `main`
+    Line(Option<String>, usize),
+    /// A function, past its prologue
+    Function(String),
+    /// `*function`: the function's first instruction, before the prologue has set up its frame
+    FunctionEntry(String),
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Location::Address(addr) => write!(f, "*{:#x}", addr),
+            Location::Line(Some(file), line_number) => write!(f, "{}:{}", file, line_number),
+            Location::Line(None, line_number) => write!(f, "{}", line_number),
+            Location::Function(name) => write!(f, "{}", name),
+            Location::FunctionEntry(name) => write!(f, "*{}", name),
+        }
+    }
+}
+
+/// Where a function or global variable lives, for `info address`.
+pub struct SymbolAddress {
+    pub is_function: bool,
+    /// The address in the executable
+    pub address: usize,
+    /// Where that is in the running process, when it differs (a PIE)
+    pub runtime_address: Option<usize>,
+}
+
+/// What `find` found in a range of memory.
+#[derive(Debug, Default)]
+pub struct MemorySearch {
+    /// Each match, with the name of the mapping it is in (a path, a pseudo-path like [heap], or
+    /// empty for an anonymous mapping)
+    pub matches: Vec<(usize, String)>,
+    /// Parts of the range that aren't mapped or couldn't be read, as (start, end)
+    pub skipped: Vec<(usize, usize)>,
+    /// Whether the search stopped at the match limit before reaching the end
+    pub truncated: bool,
+}
+
+/// A piece of a range of memory, as `scan_memory` goes through it.
+enum Chunk<'a> {
+    /// Bytes read at `addr`, in the mapping named `mapping`
+    Data {
+        addr: usize,
+        bytes: &'a [u8],
+        mapping: &'a str,
+    },
+    /// Memory that isn't mapped or couldn't be read, which breaks up the data around it
+    Gap,
+}
+
+/// What became of the breakpoints when the target was replaced with another binary (`file`).
+#[derive(Debug, Default)]
+pub struct TargetReload {
+    /// Breakpoints on functions or lines that the new binary has
+    pub resolved: Vec<BreakpointId>,
+    /// Breakpoints on functions or lines that it doesn't; they are pending now
+    pub pending: Vec<BreakpointId>,
+    /// Breakpoints on raw addresses, which probably mean something else in the new binary
+    pub stale: Vec<BreakpointId>,
+}
+
+/// A hardware watchpoint on a variable, reporting every change to its value.
+#[derive(Debug, Clone)]
+pub struct Watchpoint {
+    /// Numbered from 1 in the order watchpoints were set
+    pub id: usize,
+    /// The variable name the user gave
+    pub expression: String,
+    pub address: usize,
+    pub type_id: TypeId,
+    /// The debug register slots in use, with the address and length each one covers. A variable
+    /// that isn't a single aligned 1, 2, 4 or 8 bytes needs several.
+    slots: Vec<(usize, usize, usize)>,
+    /// The variable's contents as of the last stop
+    value: Vec<u8>,
+    /// Set for a local variable: the frame it lives in
+    scope: Option<WatchScope>,
+}
+
+/// The frame a watched local variable belongs to. The frame is gone once the stack pointer is
+/// back at (or above) its canonical frame address.
+#[derive(Debug, Clone, Copy)]
+struct WatchScope {
+    frame_base: usize,
+    /// Where the frame's function returns to; a breakpoint there catches the frame going away
+    return_address: usize,
+}
+
+/// Something a watchpoint reports at a stop.
+#[derive(Debug, Clone)]
+pub enum WatchpointEvent {
+    /// The watched variable changed. The values are formatted according to its type.
+    Changed {
+        id: usize,
+        expression: String,
+        old: String,
+        new: String,
+    },
+    /// The variable's frame returned, so the watchpoint was deleted
+    OutOfScope(usize),
+}
+
+/// Which of its two stopping conditions ended an `advance`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdvanceStop {
+    /// The location was reached
+    Reached,
+    /// The function `advance` started in returned first
+    Returned,
+}
+
+/// The commands that step a line at a time, which differ in where they stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineStep {
+    /// At a later address of another line, stepping over calls
+    Until,
+    /// At any other line, stepping over calls
+    Next,
+    /// At any other line, stepping into calls to functions with line information
+    Step,
+}
+
+/// What the function `finish` ran out of returned.
+#[derive(Debug)]
+pub enum ReturnValue {
+    /// A void function, or one whose return type isn't known
+    Void,
+    /// rax, as the function's return type
+    Value(Value),
+}
+
+/// The samples `profile` has taken. A profile paused by a stop is kept, so that `profile` can
+/// carry on with it.
+#[derive(Debug, Default)]
+pub struct Profile {
+    /// Samples by the function the program was in ("??" outside any known function)
+    pub functions: HashMap<String, usize>,
+    /// Samples by function and the function that called it
+    pub callers: HashMap<(String, String), usize>,
+    pub samples: usize,
+    /// How much of the time asked for is left
+    pub remaining: Duration,
+}
+
+/// How `profile` ended.
+#[derive(Debug)]
+pub enum ProfileOutcome {
+    /// The time ran out
+    Finished(Profile),
+    /// The program stopped at a breakpoint or with a signal first. `profile` without a duration
+    /// carries on for the time that is left.
+    Paused(StopEvent, Duration),
+    /// The program exited or was killed first, with the samples taken until then
+    Ended(StopEvent, Profile),
+}
+
+/// How a function called with `call` ended.
+#[derive(Debug)]
+pub enum CallOutcome {
+    /// The function returned. Holds its return value formatted by the return type, or None
+    /// for a void function.
+    Returned(Option<String>),
+    /// The inferior stopped (at a breakpoint, say) or exited before the function returned. If
+    /// it's stopped, continuing finishes the call and puts the inferior back where it was.
+    Stopped(StopEvent),
+}
+
+/// A call the program made through its PLT, while `set trace-calls` is on. The arguments are
+/// the first three, decoded as strings where they point at text.
+pub struct LibraryCall {
+    pub name: String,
+    pub arguments: Vec<String>,
+}
+
+/// The arguments of a failed `assert()`, read from the call to `__assert_fail` that precedes
+/// the abort.
+pub struct Assertion {
+    pub expression: String,
+    pub file: String,
+    pub line: u32,
+    pub function: String,
+}
+
+/// A shared library coming or going, seen at the dynamic linker's breakpoint.
+pub enum SolibEvent {
+    Loaded(PathBuf),
+    Unloaded(PathBuf),
+}
+
+/// What `catch` stops the program at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Catchpoint {
+    /// A call to abort, before the SIGABRT it raises. On by default.
+    Abort,
+    /// A Rust panic, before the stack is unwound
+    Panic,
+}
+
+impl Catchpoint {
+    pub fn name(self) -> &'static str {
+        match self {
+            Catchpoint::Abort => "abort",
+            Catchpoint::Panic => "panic",
+        }
+    }
+}
+
+/// A stop at a catchpoint.
+pub struct Caught {
+    pub catchpoint: Catchpoint,
+    /// The assertion that failed, if it was `__assert_fail` that called abort
+    pub assertion: Option<Assertion>,
+}
+
+/// A forked copy of the inferior, kept stopped so that `restart` can go back to it.
+pub struct Checkpoint {
+    /// Numbered from 1 in the order checkpoints were taken
+    pub id: usize,
+    inferior: Inferior,
+    /// Where the inferior was stopped when the checkpoint was taken
+    pub address: usize,
+    pub function: Option<String>,
+    pub line: Option<Line>,
+}
+
+impl Checkpoint {
+    pub fn pid(&self) -> nix::unistd::Pid {
+        self.inferior.pid()
+    }
+}
+
+/// A function call made with `call`, with what's needed to undo it once it returns.
+struct PendingCall {
+    /// The registers as they were before the call
+    regs: libc::user_regs_struct,
+    /// The stack word the return address was written over, and its old contents
+    stack_word: (usize, u64),
+    /// Where the called function returns to: a breakpoint at the program's entry point, which
+    /// has long since run
+    trampoline: usize,
+    return_type: Option<TypeId>,
+}
+
+/// What happened when the inferior was resumed.
+#[derive(Debug, Clone)]
+pub enum StopEvent {
+    /// The inferior stopped. The function and source line are filled in when the stop address has
+    /// debug info. `breakpoint` is set when the stop was a user breakpoint (as opposed to the
+    /// temporary one placed by `start`).
+    Stopped {
+        signal: Signal,
+        address: usize,
+        reason: StopReason,
+        breakpoint: Option<BreakpointId>,
+        function: Option<String>,
+        line: Option<Line>,
+    },
+    Exited(i32),
+    Signaled(Signal),
+}
+
+#[derive(Debug)]
+pub enum SessionError {
+    /// The target's debug info could not be loaded
+    Symbols(DwarfError),
+    /// The command needs a running inferior
+    NoProcess,
+    /// A location did not resolve to an address
+    UnknownLocation(Location),
+    /// There is already a breakpoint at this address
+    DuplicateBreakpoint(BreakpointId, usize),
+    /// No breakpoint has this number (it was never set, or has been deleted)
+    NoBreakpoint(usize),
+    /// `clear` found no breakpoint at the location
+    NoBreakpointAt(Location),
+    /// A line number without a file name isn't in the file containing `main` but is in several
+    /// others
+    AmbiguousLine(usize, Vec<String>),
+    /// A raw breakpoint address is not inside any function the debug info knows about
+    InvalidAddress(usize),
+    /// The working directory set with `cd` no longer exists
+    MissingWorkingDirectory(PathBuf),
+    /// A `<`, `>` or `2>` redirection in the arguments could not be set up
+    Redirect(String),
+    /// The inferior could not be started
+    Inferior(InferiorError),
+    /// A ptrace or waitpid call on the inferior failed
+    Ptrace(PtraceError),
+    /// An expression could not be evaluated
+    Eval(EvalError),
+    /// All the debug registers are in use by other watchpoints
+    TooManyWatchpoints,
+    /// A watchpoint on a variable that's held in a register or computed, not kept in memory
+    NotInMemory(String),
+    /// The inferior is stopped somewhere without line number information
+    NoLineInfo,
+    /// `until` to a line with no code in the current function
+    LineNotInFunction(usize),
+    /// `return` needs to unwind a frame that has no debug info
+    NoFrameInfo,
+    /// `return` in main, which has no caller to return to
+    ReturnFromMain,
+    /// `finish` in main
+    FinishFromMain,
+    /// `frame` with a number past the outermost frame
+    NoFrame(usize),
+    /// `up` from the outermost frame
+    OutermostFrame,
+    /// `down` from the innermost frame
+    InnermostFrame,
+    /// `call` while an earlier call hasn't returned yet
+    CallInProgress,
+    /// `call` with more arguments than fit in registers
+    TooManyArguments(usize),
+    /// `restart` with a number that isn't a checkpoint
+    NoCheckpoint(usize),
+    /// `profile` without a duration, when there is no paused profile to carry on with
+    NoProfile,
+    /// `continue &` while recording, which single-steps the inferior from the debugger
+    RecordingInBackground,
+    /// `continue &` while tracing system calls or library calls, each of which stops the
+    /// inferior for the debugger to report. Carries the setting that is on.
+    TracingInBackground(&'static str),
+    /// `continue &` while recording line coverage, whose breakpoints the debugger has to take
+    /// out as they are hit
+    CoverageInBackground,
+    /// `set trace-calls on` for a program that makes no calls through a PLT
+    NoPltEntries,
+    /// `coverage on` for a program without a line table
+    NoLineTable,
+    /// `catch panic` for a program without Rust's panic machinery
+    NoPanicFunction,
+    /// `printf` or `dprintf` arguments that don't fit the format string
+    Printf(PrintfError),
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::Symbols(DwarfError::ErrorOpeningFile) => write!(f, "Could not open file"),
+            SessionError::Symbols(DwarfError::DwarfFormatError(
+                gimli_wrapper::Error::DecompressionError(section),
+            )) => write!(f, "Could not decompress the debug section {}.", section),
+            SessionError::Symbols(DwarfError::DwarfFormatError(err)) => {
+                write!(f, "Could not load debugging symbols: {:?}", err)
+            }
+            SessionError::NoProcess => write!(f, "The program is not being run."),
+            SessionError::UnknownLocation(location) => write!(f, "No location matches \"{}\".", location),
+            SessionError::DuplicateBreakpoint(id, addr) => {
+                write!(f, "Note: breakpoint {} already set at {:#x}.", id.0, addr)
+            }
+            SessionError::NoBreakpoint(id) => write!(f, "No breakpoint number {}.", id),
+            SessionError::NoBreakpointAt(location) => write!(f, "No breakpoint at {}.", location),
+            SessionError::AmbiguousLine(line_number, files) => write!(
+                f,
+                "Line {} is in several files ({}). Use file:line to pick one.",
+                line_number,
+                files.join(", ")
+            ),
+            SessionError::InvalidAddress(addr) => write!(
+                f,
+                "Warning: {:#x} is not inside any known function. Use \"break -force *{:#x}\" to set it anyway.",
+                addr, addr
+            ),
+            SessionError::MissingWorkingDirectory(path) => {
+                write!(f, "Working directory {} no longer exists", path.display())
+            }
+            SessionError::Redirect(err) => write!(f, "{}", err),
+            SessionError::Inferior(err) => write!(f, "Error starting subprocess: {}", err),
+            SessionError::Ptrace(err) => write!(f, "{}", err),
+            SessionError::Eval(err) => write!(f, "{}", err),
+            SessionError::Printf(err) => write!(f, "{}", err),
+            SessionError::TooManyWatchpoints => write!(
+                f,
+                "Hardware watchpoints used exceeds limit ({} debug registers).",
+                inferior::WATCHPOINT_SLOTS
+            ),
+            SessionError::NotInMemory(name) => {
+                write!(f, "Cannot watch \"{}\": it isn't kept in memory here.", name)
+            }
+            SessionError::NoLineInfo => {
+                write!(f, "No line number information for the current location.")
+            }
+            SessionError::LineNotInFunction(line_number) => {
+                write!(f, "Line {} is not in the current function.", line_number)
+            }
+            SessionError::NoFrameInfo => {
+                write!(f, "Can not unwind the current frame: its function has no debug info.")
+            }
+            SessionError::ReturnFromMain => write!(f, "Can not force a return from main."),
+            SessionError::FinishFromMain => {
+                write!(f, "\"finish\" not meaningful in the outermost frame (main).")
+            }
+            SessionError::CallInProgress => write!(
+                f,
+                "The program is stopped in a function called with `call`. Continue to finish it first."
+            ),
+            SessionError::NoCheckpoint(id) => write!(f, "No checkpoint number {}.", id),
+            SessionError::NoProfile => {
+                write!(f, "No profile to carry on with. Use \"profile <seconds>\".")
+            }
+            SessionError::RecordingInBackground => {
+                write!(f, "Can not run in the background while recording. Use \"record stop\".")
+            }
+            SessionError::TracingInBackground(setting) => write!(
+                f,
+                "Can not run in the background while tracing. Use \"set {} off\".",
+                setting
+            ),
+            SessionError::CoverageInBackground => write!(
+                f,
+                "Can not run in the background while recording coverage. Use \"coverage off\"."
+            ),
+            SessionError::NoPltEntries => {
+                write!(f, "The program makes no calls through a PLT, so there is nothing to trace.")
+            }
+            SessionError::NoPanicFunction => write!(
+                f,
+                "The program has no {} to catch panics at.",
+                PANIC_FUNCTIONS.join(" or ")
+            ),
+            SessionError::NoLineTable => {
+                write!(f, "The program has no line number information to record coverage of.")
+            }
+            SessionError::NoFrame(level) => write!(f, "No frame at level {}.", level),
+            SessionError::OutermostFrame => {
+                write!(f, "Initial frame selected; you cannot go up.")
+            }
+            SessionError::InnermostFrame => {
+                write!(f, "Bottom (innermost) frame selected; you cannot go down.")
+            }
+            SessionError::TooManyArguments(count) => write!(
+                f,
+                "Can not pass {} arguments: at most {} are supported.",
+                count,
+                ARGUMENT_REGISTERS
+            ),
+        }
+    }
+}
+
+impl From<PtraceError> for SessionError {
+    fn from(err: PtraceError) -> Self {
+        SessionError::Ptrace(err)
+    }
+}
+
+impl From<EvalError> for SessionError {
+    fn from(err: EvalError) -> Self {
+        SessionError::Eval(err)
+    }
+}
+
+impl From<PrintfError> for SessionError {
+    fn from(err: PrintfError) -> Self {
+        SessionError::Printf(err)
+    }
+}
+
+pub struct Session {
+    pub(crate) target: String,
+    pub(crate) debug_data: Rc<DwarfData>,
+    pub(crate) inferior: Option<Inferior>,
+    /// Breakpoints set by the user
+    breakpoints: BreakpointTable,
+    /// Breakpoints that could not be inserted the last time the inferior was resumed
+    breakpoint_errors: Vec<BreakpointError>,
+    /// Pending breakpoints that resolved since the last call to take_resolved_breakpoints
+    resolved_breakpoints: Vec<BreakpointId>,
+    watchpoints: Vec<Watchpoint>,
+    next_watchpoint_id: usize,
+    /// A `call` that stopped before its function returned
+    pending_call: Option<PendingCall>,
+    checkpoints: Vec<Checkpoint>,
+    next_checkpoint_id: usize,
+    /// The value history: `$1`, `$2` and so on, from `print` and `finish`
+    values: Vec<Value>,
+    /// The frame variables are looked up in, numbered as `backtrace` numbers them. Back to 0,
+    /// the innermost, whenever the inferior stops.
+    selected_frame: usize,
+    /// How many physical frames (not counting inlined ones) are inside the selected frame
+    selected_depth: usize,
+    /// While `record` is on: the addresses of the most recently executed instructions, oldest
+    /// first, up to RECORD_LIMIT of them
+    recording: Option<VecDeque<usize>>,
+    /// Watchpoint changes and deletions since the last call to take_watchpoint_events
+    watchpoint_events: Vec<WatchpointEvent>,
+    /// Shared libraries loaded in the inferior, as of its last stop
+    libraries: LoadedLibraries,
+    /// How far the target was moved from its link-time addresses when it was loaded. Always 0 for
+    /// non-PIE executables. Added to addresses from the debug info, subtracted from runtime
+    /// addresses before looking them up.
+    load_bias: usize,
+    /// Arguments passed to the inferior by the most recent `run` or `set args`, as they are
+    pub args: Vec<String>,
+    /// Where the inferior's standard streams go instead of the debugger's, in order
+    pub redirections: Vec<Redirection>,
+    /// Changes to the environment the inferior is started with
+    pub env: Environment,
+    /// Whether to run the inferior on its own pseudo-terminal
+    pub use_tty: bool,
+    /// Whether to capture the inferior's stdout and stderr (`set capture-output`)
+    pub capture_output: bool,
+    /// What was captured from the current or last run
+    captured_output: Option<SharedOutput>,
+    /// Working directory the inferior is started in
+    pub cwd: PathBuf,
+    /// Whether to start the inferior with address space randomization turned off
+    pub disable_randomization: bool,
+    /// The most frames `backtrace` shows (`set backtrace limit`); 0 for no limit
+    pub backtrace_limit: usize,
+    /// Where to look for separate debug files (`set debug-file-directory`)
+    debug_file_directory: String,
+    /// The target's modification time and size when its debug info was loaded, to notice when
+    /// it gets rebuilt
+    target_stamp: Option<(SystemTime, u64)>,
+    /// Source files read to show lines at stops
+    sources: SourceCache,
+    /// `set substitute-path` rules, applied to source paths from the debug info before reading
+    pub substitute_paths: PathSubstitutions,
+    /// `directory` search list, for sources that aren't where the debug info says
+    pub source_directories: Vec<PathBuf>,
+    /// Told about each system call the inferior makes, while `set trace-syscalls` is on
+    syscall_tracer: Option<Box<dyn FnMut(&SyscallTrace)>>,
+    /// Told about each call the program makes through its PLT, while `set trace-calls` is on
+    call_tracer: Option<Box<dyn FnMut(&LibraryCall)>>,
+    /// Given the text of each `dprintf` hit, or the error that kept it from being formatted
+    dprintf_printer: Option<Box<dyn FnMut(BreakpointId, Result<String, SessionError>)>>,
+    /// How many times each library function has been called since `set trace-calls on`
+    call_counts: HashMap<String, usize>,
+    /// The account of heap blocks, while `set heap-track` is on
+    heap: Option<HeapTracker>,
+    /// The lines run so far, while `coverage` is on
+    coverage: Option<Coverage>,
+    /// What `catch` stops at
+    catchpoints: Vec<Catchpoint>,
+    /// Where the catchpoints' functions start, in the target and its loaded libraries
+    catch_entries: Vec<(usize, Catchpoint)>,
+    /// Where __assert_fail starts, while catching abort
+    assert_entries: Vec<usize>,
+    /// The assertion whose failure is on its way to abort
+    assertion: Option<Assertion>,
+    /// The catchpoint the inferior last stopped at, until the debugger takes it
+    caught: Option<Caught>,
+    /// Whether to stop when a shared library is loaded or unloaded (`set stop-on-solib-events`)
+    pub stop_on_solib_events: bool,
+    /// Told about each library loaded or unloaded while the program runs
+    solib_listener: Option<Box<dyn FnMut(&SolibEvent)>>,
+    /// The dynamic linker's r_debug, once it has filled it in, and the address it calls whenever
+    /// the list of loaded objects changes (r_brk, which is _dl_debug_state)
+    r_debug: Option<(usize, usize)>,
+    /// Whether the last stop was for a library loaded or unloaded, until the debugger takes it
+    solib_stop: bool,
+    /// A profile paused by a stop
+    profile: Option<Profile>,
+    /// Whether `step` stops in functions without line information, to go through them an
+    /// instruction at a time, instead of running until they return (`set step-into-no-debug`)
+    pub step_into_no_debug: bool,
+    /// Functions and files `step` goes over calls into, as `next` would (`skip`)
+    pub skips: SkipList,
+    /// The functions without line information that `step` has run through
+    no_line_functions: HashSet<String>,
+    /// Those run through for the first time since the last call to take_no_line_functions
+    new_no_line_functions: Vec<String>,
+}
+
+impl Session {
+    /// Loads the target's debug info. Nothing is started until `run`.
+    pub fn new(target: &str) -> Result<Session, SessionError> {
+        let debug_data = DwarfData::from_file(target).map_err(SessionError::Symbols)?;
+        Ok(Session {
+            target: target.to_string(),
+            debug_data: Rc::new(debug_data),
+            inferior: None,
+            breakpoints: BreakpointTable::default(),
+            breakpoint_errors: vec![],
+            resolved_breakpoints: vec![],
+            watchpoints: vec![],
+            next_watchpoint_id: 1,
+            pending_call: None,
+            checkpoints: vec![],
+            values: Vec::new(),
+            selected_frame: 0,
+            selected_depth: 0,
+            next_checkpoint_id: 1,
+            recording: None,
+            watchpoint_events: vec![],
+            libraries: LoadedLibraries::default(),
+            load_bias: 0,
+            args: vec![],
+            redirections: vec![],
+            env: Environment::default(),
+            use_tty: false,
+            capture_output: false,
+            captured_output: None,
+            cwd: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+            disable_randomization: true,
+            backtrace_limit: DEFAULT_BACKTRACE_LIMIT,
+            debug_file_directory: dwarf_data::DEFAULT_DEBUG_FILE_DIRECTORY.to_string(),
+            target_stamp: file_stamp(target),
+            sources: SourceCache::default(),
+            substitute_paths: PathSubstitutions::default(),
+            source_directories: vec![],
+            syscall_tracer: None,
+            call_tracer: None,
+            dprintf_printer: None,
+            call_counts: HashMap::new(),
+            heap: None,
+            coverage: None,
+            catchpoints: vec![Catchpoint::Abort],
+            catch_entries: Vec::new(),
+            assert_entries: Vec::new(),
+            assertion: None,
+            caught: None,
+            stop_on_solib_events: false,
+            solib_listener: None,
+            r_debug: None,
+            solib_stop: false,
+            profile: None,
+            step_into_no_debug: false,
+            skips: SkipList::default(),
+            no_line_functions: HashSet::new(),
+            new_no_line_functions: Vec::new(),
+        })
+    }
+
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn debug_data(&self) -> &Rc<DwarfData> {
+        &self.debug_data
+    }
+
+    pub fn debug_file_directory(&self) -> &str {
+        &self.debug_file_directory
+    }
+
+    /// Changes where separate debug files are looked for. If the target has no DWARF yet, its
+    /// debug info is loaded again in case the new directory has it.
+    pub fn set_debug_file_directory(&mut self, dir: &str) -> Result<(), SessionError> {
+        self.debug_file_directory = dir.to_string();
+        if self.debug_data.debug_info() != dwarf_data::DebugInfo::Dwarf {
+            let debug_data =
+                DwarfData::load(&self.target, dir).map_err(SessionError::Symbols)?;
+            self.debug_data = Rc::new(debug_data);
+        }
+        Ok(())
+    }
+
+    /// Where to read a source file named in the debug info from, after `set substitute-path` and
+    /// the `directory` search. The source cache is keyed on this path, so changes to either take
+    /// effect on the next read.
+    fn source_path(&self, file: &str) -> PathBuf {
+        source_cache::find_source(
+            file,
+            self.debug_data.comp_dir(file),
+            &self.substitute_paths,
+            &self.source_directories,
+            Path::exists,
+        )
+    }
+
+    /// The text of a line of a source file named in the debug info, if the file can be read and
+    /// has that many lines. Files are read once and cached.
+    pub fn source_line(&mut self, file: &str, number: usize) -> Option<String> {
+        let path = self.source_path(file);
+        self.sources.line(&path, number).map(str::to_string)
+    }
+
+    /// How many lines a source file named in the debug info has, or None if it can't be read.
+    pub fn source_line_count(&mut self, file: &str) -> Option<usize> {
+        let path = self.source_path(file);
+        self.sources.lines(&path).map(|lines| lines.len())
+    }
+
+    /// Lines `first` to `last` (inclusive, counting from 1) of a source file named in the debug
+    /// info, with their numbers, cut short at the end of the file. None if the file can't be read
+    /// or ends before `first`.
+    pub fn source_lines(
+        &mut self,
+        file: &str,
+        first: usize,
+        last: usize,
+    ) -> Option<Vec<(usize, String)>> {
+        let path = self.source_path(file);
+        let lines = self.sources.lines(&path)?;
+        let first = first.max(1);
+        if first > lines.len() {
+            return None;
+        }
+        let last = last.min(lines.len());
+        Some(
+            (first..=last)
+                .map(|number| (number, lines[number - 1].clone()))
+                .collect(),
+        )
+    }
+
+    /// Returns true if there is an inferior process.
+    pub fn is_running(&self) -> bool {
+        self.inferior.is_some()
+    }
+
+    /// Returns true if there is an inferior process that hasn't exited yet.
+    pub fn is_alive(&self) -> bool {
+        self.inferior.as_ref().map_or(false, |inferior| !inferior.has_exited())
+    }
+
+    /// Turns a location into an address using the debug info of the target and then of its
+    /// loaded shared libraries. When the location matches several places, returns the first.
+    pub fn resolve(&self, location: &Location) -> Option<usize> {
+        self.resolve_all(location).into_iter().next()
+    }
+
+    /// Returns every address a location refers to: a function name can match functions in
+    /// several files (or libraries), overloads, or the inlined copies of a function with no
+    /// out-of-line copy.
+    pub fn resolve_all(&self, location: &Location) -> Vec<usize> {
+        match location {
+            Location::Address(addr) => vec![*addr],
+            Location::Line(file, line_number) => self
+                .debug_data
+                .get_addr_for_line(
+                    file.as_deref().or_else(|| self.default_source_file()),
+                    *line_number,
+                )
+                .map(|addr| addr + self.load_bias)
+                .into_iter()
+                .collect(),
+            Location::Function(name) => self
+                .function_entries(name)
+                .into_iter()
+                .map(|addr| self.skip_prologue(addr))
+                .collect(),
+            Location::FunctionEntry(name) => self.function_entries(name),
+        }
+    }
+
+    /// The entry address of every function `name` refers to, in the program and the loaded
+    /// shared libraries.
+    fn function_entries(&self, name: &str) -> Vec<usize> {
+        let mut addrs: Vec<usize> = self
+            .debug_data
+            .get_addrs_for_function(name)
+            .into_iter()
+            .map(|addr| addr + self.load_bias)
+            .collect();
+        for lib in self.libraries.iter() {
+            addrs.extend(lib.get_addrs_for_function(name));
+        }
+        addrs
+    }
+
+    /// Where a breakpoint on the function entered at `addr` goes: past its prologue, when the
+    /// line table says where that ends.
+    fn skip_prologue(&self, addr: usize) -> usize {
+        if let Some(lib) = self.library_at(addr) {
+            return lib.skip_prologue(addr);
+        }
+        match addr.checked_sub(self.load_bias) {
+            Some(addr) => self.debug_data.skip_prologue(addr) + self.load_bias,
+            None => addr,
+        }
+    }
+
+    /// `info address`: where the function or global variable `name` lives. Works without a
+    /// running process.
+    pub fn symbol_address(&self, name: &str) -> Option<SymbolAddress> {
+        let (is_function, address) = match self.debug_data.get_addrs_for_function(name).first() {
+            Some(addr) => (true, *addr),
+            None => (false, self.debug_data.get_global_variable_address(name)?),
+        };
+        let runtime_address = if self.load_bias != 0 {
+            Some(address + self.load_bias)
+        } else {
+            None
+        };
+        Some(SymbolAddress {
+            is_function,
+            address,
+            runtime_address,
+        })
+    }
+
+    /// `info symbol`: the symbol whose code or data contains `addr` (a runtime address, or a
+    /// link-time one without a running process), the offset into it, and the section.
+    pub fn symbol_at(&self, addr: usize) -> Option<(String, usize, Option<String>)> {
+        let addr = addr.checked_sub(self.load_bias)?;
+        let (name, offset) = self.debug_data.symbol_containing(addr)?;
+        let section = self.debug_data.section_containing(addr).map(str::to_string);
+        Some((name, offset, section))
+    }
+
+    /// Returns the (runtime) address ranges of the code for a line, in the given file or the
+    /// default one. See DwarfData::get_line_ranges.
+    pub fn line_ranges(&self, file: Option<&str>, line_number: usize) -> Vec<(usize, usize)> {
+        self.debug_data
+            .get_line_ranges(file.or_else(|| self.default_source_file()), line_number)
+            .into_iter()
+            .map(|(start, end)| (start + self.load_bias, end + self.load_bias))
+            .collect()
+    }
+
+    /// Returns the line table row (with runtime addresses) whose code contains `addr`.
+    pub fn line_row_at(&self, addr: usize) -> Option<LineRow> {
+        let row = self
+            .debug_data
+            .get_line_row_containing(addr.wrapping_sub(self.load_bias))?;
+        Some(LineRow {
+            address: row.address + self.load_bias,
+            end: row.end + self.load_bias,
+            ..row.clone()
+        })
+    }
+
+    /// Returns the full names of every function `name` refers to, in the target and its loaded
+    /// shared libraries. More than one means the name is ambiguous, e.g. an overloaded C++
+    /// function; each of the returned names resolves to just one of them.
+    pub fn functions_matching(&self, name: &str) -> Vec<String> {
+        let mut names = self.debug_data.functions_matching(name);
+        for lib in self.libraries.iter() {
+            if let Some(debug_data) = &lib.debug_data {
+                names.extend(debug_data.functions_matching(name));
+            }
+        }
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Returns the names of all functions for which `predicate` holds, in the target and its
+    /// loaded shared libraries.
+    pub fn function_names_where<P: Fn(&str) -> bool>(&self, predicate: P) -> Vec<String> {
+        let mut names = self.debug_data.function_names_where(&predicate);
+        for lib in self.libraries.iter() {
+            if let Some(debug_data) = &lib.debug_data {
+                names.extend(debug_data.function_names_where(&predicate));
+            }
+        }
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Returns every function in the target and its loaded shared libraries, with the name of the
+    /// file declaring it and its address in the inferior.
+    pub fn functions(&self) -> Vec<(&str, &Function, usize)> {
+        let mut functions: Vec<(&str, &Function, usize)> = self
+            .debug_data
+            .functions()
+            .map(|(file, func)| (file.name.as_str(), func, func.address + self.load_bias))
+            .collect();
+        for lib in self.libraries.iter() {
+            if let Some(debug_data) = &lib.debug_data {
+                functions.extend(
+                    debug_data
+                        .functions()
+                        .map(|(file, func)| (file.name.as_str(), func, func.address + lib.base)),
+                );
+            }
+        }
+        functions
+    }
+
+    /// Returns every global and file-static variable in the target and its loaded shared
+    /// libraries, with the debug info describing its type, the name of the file declaring it, and
+    /// its address in the inferior.
+    pub fn global_variables(&self) -> Vec<(&DwarfData, &str, &Variable, usize)> {
+        let mut objects: Vec<(&DwarfData, usize)> = vec![(&*self.debug_data, self.load_bias)];
+        for lib in self.libraries.iter() {
+            if let Some(debug_data) = &lib.debug_data {
+                objects.push((&**debug_data, lib.base));
+            }
+        }
+        let mut variables = Vec::new();
+        for (debug_data, base) in objects {
+            for (file, var) in debug_data.variables() {
+                if let dwarf_data::Location::Address(addr) = var.location {
+                    variables.push((debug_data, file.name.as_str(), var, addr + base));
+                }
+            }
+        }
+        variables
+    }
+
+    /// The file a line number without a file name refers to: the one containing `main`.
+    pub fn default_source_file(&self) -> Option<&str> {
+        self.debug_data.get_file_for_function("main")
+    }
+
+    /// Returns the name of the function containing `addr`, in the target or a shared library.
+    pub fn function_at(&self, addr: usize) -> Option<String> {
+        match self.library_at(addr) {
+            Some(lib) => lib.get_function_from_addr(addr),
+            None => self
+                .debug_data
+                .get_function_from_addr(addr.checked_sub(self.load_bias)?),
+        }
+    }
+
+    /// Returns the functions executing at `addr`, innermost first: any functions inlined there,
+    /// then the function the code belongs to.
+    pub fn frames_at(&self, addr: usize) -> Vec<(Option<String>, Option<Line>)> {
+        match self.library_at(addr) {
+            Some(lib) => lib.get_frames_from_addr(addr),
+            None => {
+                let offset = match addr.checked_sub(self.load_bias) {
+                    Some(offset) => offset,
+                    None => return Vec::new(),
+                };
+                let mut frames = self.debug_data.get_frames_from_addr(offset);
+                for (_, line) in frames.iter_mut() {
+                    if let Some(line) = line {
+                        line.address = addr;
+                    }
+                }
+                frames
+            }
+        }
+    }
+
+    /// Returns the source line for `addr`, in the target or a shared library.
+    pub fn line_at(&self, addr: usize) -> Option<Line> {
+        match self.library_at(addr) {
+            Some(lib) => lib.get_line_from_addr(addr),
+            None => {
+                let mut line = self
+                    .debug_data
+                    .get_line_from_addr(addr.checked_sub(self.load_bias)?)?;
+                line.address = addr;
+                Some(line)
+            }
+        }
+    }
+
+    /// Returns the shared libraries loaded in the inferior.
+    pub fn shared_libraries(&self) -> &[SharedLibrary] {
+        self.libraries.all()
+    }
+
+    /// `sharedlibrary`: loads the debug info of the loaded libraries whose path satisfies
+    /// `predicate` again, this time looking for separate debug files as well. Pending breakpoints
+    /// are resolved again. Returns each library's path and what was found for it.
+    pub fn load_library_symbols<P: Fn(&Path) -> bool>(
+        &mut self,
+        predicate: P,
+    ) -> Vec<(PathBuf, DebugInfo)> {
+        let debug_file_directory = &self.debug_file_directory;
+        let loaded: Vec<(PathBuf, DebugInfo)> = self
+            .libraries
+            .matching_mut(predicate)
+            .map(|lib| (lib.path.clone(), lib.reload_symbols(debug_file_directory)))
+            .collect();
+        if !loaded.is_empty() {
+            self.resolve_pending_breakpoints();
+        }
+        loaded
+    }
+
+    fn library_at(&self, addr: usize) -> Option<&SharedLibrary> {
+        self.libraries.containing(addr)
+    }
+
+    /// Adds a breakpoint. It is armed the next time the inferior is started or resumed. Raw
+    /// addresses must fall inside a known function unless `force` is set. A function or line that
+    /// doesn't resolve yet becomes a pending breakpoint, which is resolved when the symbols change.
+    /// A line without a file name is looked up in the file containing `main`, or else in the only
+    /// file that has it; the breakpoint remembers the file it was resolved in.
+    pub fn set_breakpoint(
+        &mut self,
+        location: Location,
+        force: bool,
+    ) -> Result<BreakpointId, SessionError> {
+        let location = match location {
+            Location::Line(None, line_number) => {
+                Location::Line(Some(self.file_for_line(line_number)?), line_number)
+            }
+            location => location,
+        };
+        let addrs = self.resolve_all(&location);
+        if let Some(&addr) = addrs.first() {
+            if let Some(id) = self.breakpoint_at(addr) {
+                if addrs.iter().all(|addr| self.breakpoint_at(*addr).is_some()) {
+                    return Err(SessionError::DuplicateBreakpoint(id, addr));
+                }
+            }
+            if let Location::Address(_) = location {
+                let in_code = addr
+                    .checked_sub(self.load_bias)
+                    .map_or(false, |addr| self.debug_data.contains_addr(addr))
+                    || self.library_at(addr).is_some();
+                if addr == 0 || (!force && !in_code) {
+                    return Err(SessionError::InvalidAddress(addr));
+                }
+            }
+        }
+        Ok(self.breakpoints.add(location, addrs))
+    }
+
+    /// `dprintf`: sets a breakpoint that prints `args` (a `printf` format string and arguments)
+    /// each time it is hit, through the printer given to set_dprintf_printer, and lets the
+    /// program carry on.
+    pub fn set_dprintf(
+        &mut self,
+        location: Location,
+        args: &str,
+    ) -> Result<BreakpointId, SessionError> {
+        printf::parse_arguments(args)?;
+        let id = self.set_breakpoint(location, false)?;
+        if let Some(bp) = self.breakpoints.get_mut(id) {
+            bp.dprintf = Some(args.to_string());
+        }
+        Ok(id)
+    }
+
+    /// Sets what `dprintf` breakpoints print with.
+    pub fn set_dprintf_printer(
+        &mut self,
+        printer: Option<Box<dyn FnMut(BreakpointId, Result<String, SessionError>)>>,
+    ) {
+        self.dprintf_printer = printer;
+    }
+
+    /// Sets a breakpoint on some of the addresses a location resolves to, the ones the user
+    /// picked when it matched several functions. The breakpoint keeps the location as given, so
+    /// it goes back to all of them if the target is reloaded.
+    pub fn set_breakpoint_sites(
+        &mut self,
+        location: Location,
+        addrs: Vec<usize>,
+    ) -> Result<BreakpointId, SessionError> {
+        if let Some(&addr) = addrs.first() {
+            if let Some(id) = self.breakpoint_at(addr) {
+                if addrs.iter().all(|addr| self.breakpoint_at(*addr).is_some()) {
+                    return Err(SessionError::DuplicateBreakpoint(id, addr));
+                }
+            }
+        }
+        Ok(self.breakpoints.add(location, addrs))
+    }
+
+    /// Picks the file for a line number given without one.
+    fn file_for_line(&self, line_number: usize) -> Result<String, SessionError> {
+        let files = self.debug_data.get_files_with_line(line_number);
+        if let Some(default) = self.default_source_file() {
+            if files.iter().any(|file| file == default) || files.is_empty() {
+                return Ok(default.to_string());
+            }
+        }
+        match files.len() {
+            0 => Err(SessionError::UnknownLocation(Location::Line(None, line_number))),
+            1 => Ok(files[0].clone()),
+            _ => Err(SessionError::AmbiguousLine(line_number, files)),
+        }
+    }
+
+    /// Returns all breakpoints, in id order.
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        self.breakpoints.all()
+    }
+
+    /// The id of the breakpoint set most recently, even if it has since been deleted.
+    pub fn last_breakpoint_id(&self) -> Option<BreakpointId> {
+        self.breakpoints.last_id()
+    }
+
+    /// Returns the breakpoint with this id, unless it has been deleted.
+    pub fn breakpoint(&self, id: BreakpointId) -> Option<&Breakpoint> {
+        self.breakpoints.get(id)
+    }
+
+    /// Returns the breakpoint set at `addr`, if there is one.
+    pub fn breakpoint_at(&self, addr: usize) -> Option<BreakpointId> {
+        self.breakpoints.at(addr).map(|bp| bp.id)
+    }
+
+    /// Returns the addresses of a breakpoint: empty if it doesn't exist or is pending.
+    pub fn breakpoint_addresses(&self, id: BreakpointId) -> &[usize] {
+        match self.breakpoint(id) {
+            Some(bp) => &bp.addresses,
+            None => &[],
+        }
+    }
+
+    /// Deletes a breakpoint, taking it out of the inferior if there is one.
+    pub fn delete_breakpoint(&mut self, id: BreakpointId) -> Result<(), SessionError> {
+        if self.breakpoint(id).is_none() {
+            return Err(SessionError::NoBreakpoint(id.0));
+        }
+        self.delete_breakpoints(&[id])
+    }
+
+    /// Enables a breakpoint (writing it into the inferior, if there is one) in the given mode.
+    pub fn enable_breakpoint(
+        &mut self,
+        id: BreakpointId,
+        mode: EnableMode,
+    ) -> Result<(), SessionError> {
+        let bp = self
+            .breakpoints
+            .get_mut(id)
+            .ok_or(SessionError::NoBreakpoint(id.0))?;
+        bp.enabled = true;
+        bp.mode = mode;
+        if self.is_alive() {
+            self.arm_breakpoints()?;
+        }
+        Ok(())
+    }
+
+    /// Disables a breakpoint, taking it out of the inferior but keeping it for `enable`.
+    pub fn disable_breakpoint(&mut self, id: BreakpointId) -> Result<(), SessionError> {
+        let bp = self
+            .breakpoints
+            .get_mut(id)
+            .ok_or(SessionError::NoBreakpoint(id.0))?;
+        bp.enabled = false;
+        let addrs = bp.addresses.clone();
+        self.remove_unwanted_breakpoints(addrs)
+    }
+
+    /// `ignore`: makes a breakpoint let the program go past its next `count` hits.
+    pub fn set_ignore_count(&mut self, id: BreakpointId, count: usize) -> Result<(), SessionError> {
+        let bp = self
+            .breakpoints
+            .get_mut(id)
+            .ok_or(SessionError::NoBreakpoint(id.0))?;
+        bp.ignore_count = count;
+        Ok(())
+    }
+
+    /// `condition`: makes a breakpoint stop the program only where `condition` is nonzero, or
+    /// (None) wherever it is hit. The condition isn't checked until the breakpoint is hit.
+    pub fn set_condition(
+        &mut self,
+        id: BreakpointId,
+        condition: Option<String>,
+    ) -> Result<(), SessionError> {
+        let bp = self
+            .breakpoints
+            .get_mut(id)
+            .ok_or(SessionError::NoBreakpoint(id.0))?;
+        bp.condition = condition;
+        Ok(())
+    }
+
+    /// Counts a hit of the enabled breakpoint at `addr`, if there is one. A hit where the
+    /// breakpoint's condition is zero doesn't count, and returns false: the program should keep
+    /// going. The ignore count is used up next, returning false while it lasts. Otherwise the
+    /// hit stops the program, which uses up one stop of `enable once` or `enable count`; the
+    /// breakpoint is disabled when they run out (it is still the one reported as hit).
+    fn count_breakpoint_hit(&mut self, addr: usize) -> Result<bool, SessionError> {
+        let (id, condition) = match self.breakpoints.enabled_at(addr) {
+            Some(bp) => (bp.id, bp.condition.clone()),
+            None => return Ok(true),
+        };
+        if let Some(condition) = condition {
+            // A condition that can't be evaluated stops the program, as a true one would
+            self.deselect_frame();
+            if let Ok(false) = self.condition_holds(&condition) {
+                return Ok(false);
+            }
+        }
+        let bp = match self.breakpoints.get_mut(id) {
+            Some(bp) => bp,
+            None => return Ok(true),
+        };
+        bp.hits += 1;
+        if bp.ignore_count > 0 {
+            bp.ignore_count -= 1;
+            return Ok(false);
+        }
+        let disable = match bp.mode {
+            EnableMode::Keep => false,
+            EnableMode::Once | EnableMode::Count(1) => true,
+            EnableMode::Count(count) => {
+                bp.mode = EnableMode::Count(count - 1);
+                false
+            }
+        };
+        if disable {
+            bp.enabled = false;
+            bp.mode = EnableMode::Keep;
+            let addrs = bp.addresses.clone();
+            self.remove_unwanted_breakpoints(addrs)?;
+        }
+        Ok(true)
+    }
+
+    /// Whether a breakpoint condition is nonzero in the innermost frame.
+    fn condition_holds(&self, condition: &str) -> Result<bool, SessionError> {
+        let (value, _) = self.evaluate(condition)?;
+        Ok(expr::to_integer(&self.debug_data, &value)? != 0)
+    }
+
+    /// Prints the line of the `dprintf` breakpoint hit at `addr`, if that's what it is. Returns
+    /// true if so: the program goes on without stopping, stepping over the breakpoint as it does
+    /// for the others. A line whose arguments can't be evaluated is reported as an error instead.
+    fn print_dprintf(&mut self, addr: usize) -> bool {
+        let (id, args) = match self
+            .breakpoints
+            .at(addr)
+            .and_then(|bp| bp.dprintf.as_ref().map(|args| (bp.id, args.clone())))
+        {
+            Some(found) => found,
+            None => return false,
+        };
+        let text = self.format_printf(&args);
+        if let Some(printer) = self.dprintf_printer.as_mut() {
+            printer(id, text);
+        }
+        true
+    }
+
+    /// Expands the arguments of `printf` or `dprintf`: evaluates each expression for its
+    /// conversion, reading the string a pointer points to for `%s`.
+    pub fn format_printf(&self, args: &str) -> Result<String, SessionError> {
+        let (template, expressions) = printf::parse_arguments(args)?;
+        let mut values = Vec::new();
+        for (conversion, expression) in template.conversions().iter().zip(&expressions) {
+            let (value, _) = self.evaluate(expression)?;
+            let number = expr::to_integer(&self.debug_data, &value)?;
+            values.push(if *conversion == 's' {
+                Argument::Text(self.read_c_string(number as usize)?)
+            } else {
+                Argument::Integer(number)
+            });
+        }
+        Ok(template.render(&values))
+    }
+
+    /// Deletes every breakpoint, returning their ids.
+    pub fn delete_all_breakpoints(&mut self) -> Result<Vec<BreakpointId>, SessionError> {
+        let ids: Vec<BreakpointId> = self.breakpoints.all().iter().map(|bp| bp.id).collect();
+        self.delete_breakpoints(&ids)?;
+        Ok(ids)
+    }
+
+    /// `clear`: deletes the breakpoints at a location, that is those with an address the location
+    /// resolves to, and pending ones set on the same location. Returns their ids.
+    pub fn clear_breakpoints(
+        &mut self,
+        location: Location,
+    ) -> Result<Vec<BreakpointId>, SessionError> {
+        let location = match location {
+            Location::Line(None, line_number) => {
+                Location::Line(Some(self.file_for_line(line_number)?), line_number)
+            }
+            location => location,
+        };
+        let addrs = self.resolve_all(&location);
+        let name = location.to_string();
+        let ids: Vec<BreakpointId> = self
+            .breakpoints
+            .all()
+            .iter()
+            .filter(|bp| {
+                bp.addresses.iter().any(|addr| addrs.contains(addr))
+                    || (bp.addresses.is_empty() && bp.location.to_string() == name)
+            })
+            .map(|bp| bp.id)
+            .collect();
+        if ids.is_empty() {
+            return Err(SessionError::NoBreakpointAt(location));
+        }
+        self.delete_breakpoints(&ids)?;
+        Ok(ids)
+    }
+
+    /// Forgets the breakpoints and restores the original bytes at their addresses, except where
+    /// the debugger still needs a breakpoint of its own (or another user breakpoint shares it).
+    fn delete_breakpoints(&mut self, ids: &[BreakpointId]) -> Result<(), SessionError> {
+        let addrs = self.breakpoints.remove(ids);
+        self.resolved_breakpoints.retain(|id| !ids.contains(id));
+        self.remove_unwanted_breakpoints(addrs)
+    }
+
+    /// Restores the original bytes at those of `addrs` that no longer need a breakpoint, after
+    /// user breakpoints there were deleted or disabled.
+    fn remove_unwanted_breakpoints(&mut self, addrs: Vec<usize>) -> Result<(), SessionError> {
+        if !self.is_alive() {
+            return Ok(());
+        }
+        let wanted = self.wanted_breakpoints();
+        let inferior = live_mut(&mut self.inferior)?;
+        // This includes a breakpoint the inferior is stopped on: once its byte is back, the next
+        // resume runs the instruction instead of stepping over (and re-arming) the breakpoint
+        for addr in addrs {
+            if !wanted.contains(&addr) {
+                inferior.disarm(addr)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Tries again to resolve every pending breakpoint. Called whenever the symbols may have
+    /// changed; the ones that resolve are reported by take_resolved_breakpoints.
+    pub fn resolve_pending_breakpoints(&mut self) {
+        for idx in 0..self.breakpoints.all().len() {
+            let bp = &self.breakpoints.all()[idx];
+            if !bp.addresses.is_empty() {
+                continue;
+            }
+            let addrs = self.resolve_all(&bp.location);
+            if addrs.is_empty() {
+                continue;
+            }
+            let bp = &mut self.breakpoints.all_mut()[idx];
+            bp.addresses = addrs;
+            self.resolved_breakpoints.push(bp.id);
+        }
+    }
+
+    /// Returns (and forgets) the pending breakpoints that have been resolved since the last call.
+    pub fn take_resolved_breakpoints(&mut self) -> Vec<BreakpointId> {
+        std::mem::replace(&mut self.resolved_breakpoints, Vec::new())
+    }
+
+    /// Sets a hardware watchpoint on a variable of the current frame (or a global), which stops
+    /// the inferior whenever the variable's value changes. A watchpoint on a local variable is
+    /// deleted when its frame returns.
+    pub fn set_watchpoint(&mut self, name: &str) -> Result<&Watchpoint, SessionError> {
+        let regs = self.frame_registers()?;
+        let (storage, type_id, function) = self
+            .find_variable(name, &regs)
+            .ok_or_else(|| EvalError::UnknownVariable(name.to_string()))?;
+        let address = match storage {
+            Storage::Memory(address) => address,
+            _ => return Err(SessionError::NotInMemory(name.to_string())),
+        };
+        let len = self.debug_data.type_size(type_id).max(1);
+        let ranges = watch_ranges(address, len);
+        let used: Vec<usize> = self
+            .watchpoints
+            .iter()
+            .flat_map(|watch| watch.slots.iter().map(|(slot, _, _)| *slot))
+            .collect();
+        let free: Vec<usize> = (0..inferior::WATCHPOINT_SLOTS)
+            .filter(|slot| !used.contains(slot))
+            .collect();
+        if ranges.len() > free.len() {
+            return Err(SessionError::TooManyWatchpoints);
+        }
+        let scope = match function {
+            Some(function_addr) => {
+                let frame_base = self.frame_base(function_addr, &regs);
+                Some(WatchScope {
+                    frame_base,
+                    return_address: self.read_u64(frame_base - 8)? as usize,
+                })
+            }
+            None => None,
+        };
+        let value = self.read_memory(address, len)?;
+        let slots: Vec<(usize, usize, usize)> = free
+            .into_iter()
+            .zip(ranges)
+            .map(|(slot, (addr, len))| (slot, addr, len))
+            .collect();
+        let inferior = live_mut(&mut self.inferior)?;
+        for &(slot, addr, len) in &slots {
+            inferior.set_watchpoint(slot, addr, len)?;
+        }
+        self.watchpoints.push(Watchpoint {
+            id: self.next_watchpoint_id,
+            expression: name.to_string(),
+            address,
+            type_id,
+            slots,
+            value,
+            scope,
+        });
+        self.next_watchpoint_id += 1;
+        Ok(self.watchpoints.last().unwrap())
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    /// Returns (and forgets) what watchpoints have reported since the last call.
+    pub fn take_watchpoint_events(&mut self) -> Vec<WatchpointEvent> {
+        std::mem::replace(&mut self.watchpoint_events, Vec::new())
+    }
+
+    /// Looks at a stop for watchpoints: records changed values, and deletes watchpoints whose
+    /// frame has returned. Returns None when the stop was only for our own bookkeeping (a watched
+    /// value was rewritten unchanged, or a recursive call of a watched frame returned) and the
+    /// inferior should just keep going.
+    fn check_watchpoints(&mut self, status: Status) -> Result<Option<Status>, SessionError> {
+        if self.watchpoints.is_empty() {
+            return Ok(Some(status));
+        }
+        let (sig, addr, reason) = match status {
+            Status::Stopped(sig, addr, reason) => (sig, addr, reason),
+            // Watchpoints on globals survive to the next run
+            status => {
+                self.drop_local_watchpoints();
+                return Ok(Some(status));
+            }
+        };
+        let rsp = self.registers()?.rsp as usize;
+        let mut keep_going = false;
+        if let StopReason::Watchpoint(slot) = reason {
+            let idx = self
+                .watchpoints
+                .iter()
+                .position(|watch| watch.slots.iter().any(|(s, _, _)| *s == slot));
+            if let Some(idx) = idx {
+                let watch = &self.watchpoints[idx];
+                let value = self.read_memory(watch.address, watch.value.len())?;
+                if value == watch.value {
+                    keep_going = true;
+                } else {
+                    let event = WatchpointEvent::Changed {
+                        id: watch.id,
+                        expression: watch.expression.clone(),
+                        old: self.format_bytes(watch.type_id, &watch.value),
+                        new: self.format_bytes(watch.type_id, &value),
+                    };
+                    self.watchpoint_events.push(event);
+                    self.watchpoints[idx].value = value;
+                }
+            }
+        }
+        // The breakpoints at return addresses are ours unless the user has one there too
+        let scope_trap = match reason {
+            StopReason::Breakpoint(bp_addr) if self.breakpoint_at(bp_addr).is_none() => self
+                .watchpoints
+                .iter()
+                .any(|watch| watch.scope.map(|scope| scope.return_address) == Some(bp_addr)),
+            _ => false,
+        };
+        let (gone, kept): (Vec<Watchpoint>, Vec<Watchpoint>) = self
+            .watchpoints
+            .drain(..)
+            .partition(|watch| watch.scope.map_or(false, |scope| rsp >= scope.frame_base));
+        self.watchpoints = kept;
+        let inferior = live_mut(&mut self.inferior)?;
+        for watch in &gone {
+            for &(slot, _, _) in &watch.slots {
+                inferior.clear_watchpoint(slot)?;
+            }
+            self.watchpoint_events
+                .push(WatchpointEvent::OutOfScope(watch.id));
+        }
+        // Take out return address breakpoints nobody needs any more
+        for scope in gone.iter().filter_map(|watch| watch.scope) {
+            let addr = scope.return_address;
+            let user_breakpoint = self.breakpoints.at(addr).is_some();
+            let still_watching = self
+                .watchpoints
+                .iter()
+                .any(|watch| watch.scope.map(|scope| scope.return_address) == Some(addr));
+            if !user_breakpoint && !still_watching {
+                inferior.disarm(addr)?;
+            }
+        }
+        match gone.first() {
+            Some(watch) if scope_trap => {
+                let slot = watch.slots[0].0;
+                Ok(Some(Status::Stopped(
+                    sig,
+                    addr,
+                    StopReason::Watchpoint(slot),
+                )))
+            }
+            None if scope_trap || keep_going => Ok(None),
+            _ => Ok(Some(status)),
+        }
+    }
+
+    /// Deletes the watchpoints on local variables once the inferior is gone.
+    fn drop_local_watchpoints(&mut self) {
+        let (gone, kept): (Vec<Watchpoint>, Vec<Watchpoint>) = self
+            .watchpoints
+            .drain(..)
+            .partition(|watch| watch.scope.is_some());
+        self.watchpoint_events.extend(
+            gone.iter()
+                .map(|watch| WatchpointEvent::OutOfScope(watch.id)),
+        );
+        self.watchpoints = kept;
+    }
+
+    /// Shows raw bytes of the given type the way `print` would.
+    fn format_bytes(&self, type_id: TypeId, bytes: &[u8]) -> String {
+        let value = Value {
+            type_id: Some(type_id),
+            address: None,
+            bytes: bytes.to_vec(),
+        };
+        expr::format_value(self, &value, Format::Natural)
+    }
+
+    /// Starts the target from the beginning (killing any current inferior) and runs it until it
+    /// stops or exits. Non-empty `args` replace the stored argument list.
+    pub fn run(&mut self, args: Vec<String>) -> Result<StopEvent, SessionError> {
+        if !args.is_empty() {
+            self.args = args;
+        }
+        self.spawn()?;
+        if let Some(status) = self.load_libraries()? {
+            return Ok(self.finish_stop(status));
+        }
+        self.cont()
+    }
+
+    /// Like `run`, but stops at the top of main using a temporary breakpoint (unless the user
+    /// already has a breakpoint there).
+    pub fn start(&mut self, args: Vec<String>) -> Result<StopEvent, SessionError> {
+        let main = Location::Function("main".to_string());
+        if self.resolve(&main).is_none() {
+            return Err(SessionError::UnknownLocation(main));
+        }
+        if !args.is_empty() {
+            self.args = args;
+        }
+        self.spawn()?;
+        if let Some(status) = self.load_libraries()? {
+            return Ok(self.finish_stop(status));
+        }
+        // Resolved again now that the load bias is known
+        let main_addr = self
+            .resolve(&main)
+            .ok_or(SessionError::UnknownLocation(main))?;
+        let status = self.run_to(main_addr)?;
+        Ok(self.finish_stop(status))
+    }
+
+    /// Resumes the inferior with a temporary breakpoint at `addr` (unless the user already has a
+    /// breakpoint there). The temporary breakpoint is gone again once this returns.
+    fn run_to(&mut self, addr: usize) -> Result<Status, SessionError> {
+        let temporary = self.breakpoint_at(addr).is_none();
+        self.arm_breakpoints()?;
+        let inferior = live_mut(&mut self.inferior)?;
+        if temporary {
+            if let Err(err) = inferior.arm(addr) {
+                self.breakpoint_errors.push(err);
+            }
+        }
+        // Coverage's breakpoint may already be there, and the stop at it is ours as well
+        let bias = self.load_bias;
+        if let Some(coverage) = self.coverage.as_mut() {
+            coverage.hold(addr.checked_sub(bias));
+        }
+        let status = self.resume();
+        if let Some(coverage) = self.coverage.as_mut() {
+            coverage.hold(None);
+        }
+        let status = status?;
+        if temporary {
+            if let Status::Stopped(..) = status {
+                let inferior = live_mut(&mut self.inferior)?;
+                inferior.disarm(addr)?;
+            }
+        }
+        Ok(status)
+    }
+
+    /// Continues the inferior until it stops somewhere the user should hear about. Stops that
+    /// watchpoints don't need to report are skipped over, as are system call stops once the
+    /// tracer has been told about them.
+    fn resume(&mut self) -> Result<Status, SessionError> {
+        loop {
+            let inferior = live_mut(&mut self.inferior)?;
+            let status = inferior.continue_running()?;
+            if let Some(status) = self.filter_stop(status)? {
+                return Ok(status);
+            }
+        }
+    }
+
+    /// Deals with the stops that are only for the debugger's own bookkeeping: system calls and
+    /// library calls being traced, heap tracking, line coverage, failed assertions (which are
+    /// reported once they get to abort), libraries loaded and unloaded, and watchpoints that
+    /// have nothing to report.
+    /// Returns None when the inferior should just keep going.
+    fn filter_stop(&mut self, status: Status) -> Result<Option<Status>, SessionError> {
+        match status {
+            Status::Stopped(_, _, StopReason::Syscall) => {
+                self.trace_syscall()?;
+                return Ok(None);
+            }
+            Status::Stopped(_, _, StopReason::Breakpoint(addr)) => {
+                if self.trace_call(addr)?
+                    || self.track_heap(addr)?
+                    || self.track_coverage(addr)?
+                    || self.catch_assertion(addr)?
+                    || self.solib_event(addr)?
+                {
+                    return Ok(None);
+                }
+                if !self.count_breakpoint_hit(addr)? || self.print_dprintf(addr) {
+                    return Ok(None);
+                }
+                if let Some(&(_, catchpoint)) =
+                    self.catch_entries.iter().find(|(entry, _)| *entry == addr)
+                {
+                    let assertion = match catchpoint {
+                        Catchpoint::Abort => self.assertion.take(),
+                        Catchpoint::Panic => None,
+                    };
+                    self.caught = Some(Caught {
+                        catchpoint,
+                        assertion,
+                    });
+                }
+            }
+            _ => {}
+        }
+        self.check_watchpoints(status)
+    }
+
+    /// `profile`: lets the inferior run for `duration`, stopping it every PROFILE_INTERVAL to
+    /// note which function it is in and which function called that one. A stop for anything
+    /// else (a breakpoint, say) pauses the profile; without a duration, the paused profile is
+    /// carried on with.
+    pub fn profile(&mut self, duration: Option<Duration>) -> Result<ProfileOutcome, SessionError> {
+        if !self.is_alive() {
+            return Err(SessionError::NoProcess);
+        }
+        if let Some(duration) = duration {
+            self.profile = Some(Profile {
+                remaining: duration,
+                ..Profile::default()
+            });
+        }
+        let remaining = self.profile.as_ref().ok_or(SessionError::NoProfile)?.remaining;
+        let deadline = Instant::now() + remaining;
+        self.arm_breakpoints()?;
+        loop {
+            let status = self.run_until_sample(deadline)?;
+            if let Status::Stopped(Signal::SIGSTOP, addr, _) = status {
+                self.take_sample(addr)?;
+                if Instant::now() >= deadline {
+                    let profile = self.profile.take().ok_or(SessionError::NoProfile)?;
+                    return Ok(ProfileOutcome::Finished(profile));
+                }
+                continue;
+            }
+            if let Status::Stopped(..) = status {
+                let inferior = live_mut(&mut self.inferior)?;
+                inferior.discard_pending_stop()?;
+            }
+            let status = match self.filter_stop(status)? {
+                Some(status) => status,
+                None => continue,
+            };
+            let stop = self.finish_stop(status);
+            if let StopEvent::Stopped { .. } = stop {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if let Some(profile) = self.profile.as_mut() {
+                    profile.remaining = remaining;
+                }
+                return Ok(ProfileOutcome::Paused(stop, remaining));
+            }
+            let profile = self.profile.take().ok_or(SessionError::NoProfile)?;
+            return Ok(ProfileOutcome::Ended(stop, profile));
+        }
+    }
+
+    /// Resumes the inferior, and after PROFILE_INTERVAL (or at the deadline, if that's sooner)
+    /// stops it with SIGSTOP. Returns the stop, which is some other one if the inferior stopped
+    /// by itself in the meantime.
+    fn run_until_sample(&mut self, deadline: Instant) -> Result<Status, SessionError> {
+        let inferior = live_mut(&mut self.inferior)?;
+        if let Some(status) = inferior.resume()? {
+            return Ok(status);
+        }
+        thread::sleep(PROFILE_INTERVAL.min(deadline.saturating_duration_since(Instant::now())));
+        inferior.stop()?;
+        Ok(inferior.wait_running()?)
+    }
+
+    /// Counts a sample taken with the inferior stopped at `addr`. The caller is found through
+    /// the return address above the saved frame pointer, so it's a guess in code without frame
+    /// pointers.
+    fn take_sample(&mut self, addr: usize) -> Result<(), SessionError> {
+        let rbp = self.registers()?.rbp as usize;
+        let unknown = || "??".to_string();
+        let function = self.function_at(addr).unwrap_or_else(unknown);
+        let caller = self
+            .read_u64(rbp.wrapping_add(8))
+            .ok()
+            .and_then(|return_address| self.function_at(return_address as usize))
+            .unwrap_or_else(unknown);
+        if let Some(profile) = self.profile.as_mut() {
+            profile.samples += 1;
+            *profile.functions.entry(function.clone()).or_insert(0) += 1;
+            *profile.callers.entry((function, caller)).or_insert(0) += 1;
+        }
+        Ok(())
+    }
+
+    /// `set trace-syscalls`: with a tracer, the inferior stops at every system call, and the
+    /// tracer is told about each one as it returns (or as it is made, for exit and exit_group).
+    /// Checkpoints are switched too, so that restarting one keeps the setting.
+    pub fn set_syscall_tracer(
+        &mut self,
+        tracer: Option<Box<dyn FnMut(&SyscallTrace)>>,
+    ) -> Result<(), SessionError> {
+        let on = tracer.is_some();
+        self.syscall_tracer = tracer;
+        if let Ok(inferior) = live_mut(&mut self.inferior) {
+            inferior.set_syscall_tracing(on)?;
+        }
+        for checkpoint in self.checkpoints.iter_mut() {
+            checkpoint.inferior.set_syscall_tracing(on)?;
+        }
+        Ok(())
+    }
+
+    pub fn is_tracing_syscalls(&self) -> bool {
+        self.syscall_tracer.is_some()
+    }
+
+    /// Tells the tracer about the system call the inferior is stopped at. x86-64 sets rax to
+    /// -ENOSYS on entry, which is how entry stops are told from exit stops; the argument
+    /// registers are preserved across the call, so both kinds of stop can decode them.
+    fn trace_syscall(&mut self) -> Result<(), SessionError> {
+        let inferior = live(&self.inferior)?;
+        let regs = inferior.getregs()?;
+        let number = regs.orig_rax;
+        let entering = regs.rax as i64 == -(libc::ENOSYS as i64);
+        // Calls that return are reported on the way out, when their result is known
+        if entering != syscalls::never_returns(number) {
+            return Ok(());
+        }
+        let args = [regs.rdi, regs.rsi, regs.rdx, regs.r10, regs.r8, regs.r9];
+        let result = if entering { None } else { Some(regs.rax) };
+        let trace = syscalls::describe(number, &args, result, |addr, len| {
+            inferior.read_bytes(addr, len).ok()
+        });
+        if let Some(tracer) = self.syscall_tracer.as_mut() {
+            tracer(&trace);
+        }
+        Ok(())
+    }
+
+    /// `set trace-calls`: with a tracer, a breakpoint of our own goes on every PLT stub (the next
+    /// time the inferior is resumed), and the tracer is told about each call through one. Without,
+    /// the stubs' code is put back, in the inferior and in its checkpoints. Returns how many
+    /// functions are traced.
+    pub fn set_call_tracer(
+        &mut self,
+        tracer: Option<Box<dyn FnMut(&LibraryCall)>>,
+    ) -> Result<usize, SessionError> {
+        if tracer.is_some() {
+            let count = self.debug_data.plt_entries().len();
+            if count == 0 {
+                return Err(SessionError::NoPltEntries);
+            }
+            self.call_tracer = tracer;
+            self.call_counts.clear();
+            return Ok(count);
+        }
+        if self.call_tracer.take().is_some() {
+            let stubs: Vec<usize> = self
+                .plt_stubs()
+                .into_iter()
+                .filter(|&addr| self.breakpoint_at(addr).is_none())
+                .collect();
+            self.remove_internal_breakpoints(&stubs)?;
+        }
+        Ok(0)
+    }
+
+    pub fn is_tracing_calls(&self) -> bool {
+        self.call_tracer.is_some()
+    }
+
+    /// `info trace-calls`: each library function called since tracing was turned on, with how
+    /// many times, most called first.
+    pub fn call_counts(&self) -> Vec<(String, usize)> {
+        let mut counts: Vec<(String, usize)> = self
+            .call_counts
+            .iter()
+            .map(|(name, &count)| (name.clone(), count))
+            .collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts
+    }
+
+    /// The runtime addresses of the PLT stubs.
+    fn plt_stubs(&self) -> Vec<usize> {
+        self.debug_data
+            .plt_entries()
+            .iter()
+            .map(|entry| entry.address + self.load_bias)
+            .collect()
+    }
+
+    /// Tells the call tracer about a call through the PLT stub at `addr`, if that's what the
+    /// breakpoint there is. Returns whether the stop was only for tracing and the inferior
+    /// should carry on, which it shouldn't if the user has a breakpoint there too.
+    fn trace_call(&mut self, addr: usize) -> Result<bool, SessionError> {
+        if self.call_tracer.is_none() {
+            return Ok(false);
+        }
+        let name = match addr.checked_sub(self.load_bias).and_then(|addr| {
+            self.debug_data.plt_entries().iter().find(|entry| entry.address == addr)
+        }) {
+            Some(entry) => entry.name.clone(),
+            None => return Ok(false),
+        };
+        let regs = self.registers()?;
+        let arguments = [regs.rdi, regs.rsi, regs.rdx]
+            .iter()
+            .map(|&value| self.call_argument(value))
+            .collect();
+        *self.call_counts.entry(name.clone()).or_insert(0) += 1;
+        if let Some(tracer) = self.call_tracer.as_mut() {
+            tracer(&LibraryCall { name, arguments });
+        }
+        Ok(self.breakpoint_at(addr).is_none())
+    }
+
+    /// An argument of a traced call, as best it can be guessed without the function's prototype:
+    /// the text it points to if that's a printable string, otherwise the number.
+    fn call_argument(&self, value: u64) -> String {
+        if value >= 0x10000 {
+            if let Ok(text) = self.read_c_string(value as usize) {
+                let printable = text.chars().all(|c| !c.is_control() || c == '\n' || c == '\t');
+                if !text.is_empty() && printable {
+                    let shown: String = text.chars().take(CALL_STRING_LIMIT).collect();
+                    let cut = if shown.len() < text.len() { "..." } else { "" };
+                    return format!("{:?}{}", shown, cut);
+                }
+            }
+        }
+        if (-0x10000..0x10000).contains(&(value as i64)) {
+            (value as i64).to_string()
+        } else {
+            format!("{:#x}", value)
+        }
+    }
+
+    /// `set heap-track`: on, breakpoints of our own go on malloc, calloc, realloc and free (the
+    /// next time the inferior is resumed) to keep account of the blocks they hand out. Off,
+    /// they are taken out again, from the inferior and its checkpoints, and the account is
+    /// forgotten.
+    pub fn set_heap_tracking(&mut self, on: bool) -> Result<(), SessionError> {
+        if on {
+            if self.heap.is_none() {
+                self.heap = Some(HeapTracker::default());
+            }
+            return Ok(());
+        }
+        let heap = match self.heap.take() {
+            Some(heap) => heap,
+            None => return Ok(()),
+        };
+        let addresses: Vec<usize> = heap
+            .entries()
+            .chain(heap.owned_breakpoints())
+            .filter(|&addr| self.breakpoint_at(addr).is_none())
+            .collect();
+        self.remove_internal_breakpoints(&addresses)
+    }
+
+    /// The heap account, while `set heap-track` is on.
+    pub fn heap_tracker(&self) -> Option<&HeapTracker> {
+        self.heap.as_ref()
+    }
+
+    /// The entry points of the allocator functions in the target and its loaded libraries,
+    /// which heap tracking puts breakpoints on. Empty while it is off.
+    fn heap_entries(&mut self) -> Vec<usize> {
+        if self.heap.is_none() {
+            return Vec::new();
+        }
+        let entries: Vec<(usize, Allocator)> = Allocator::ALL
+            .iter()
+            .flat_map(|&allocator| {
+                self.function_entries(allocator.name())
+                    .into_iter()
+                    .map(move |addr| (addr, allocator))
+            })
+            .collect();
+        let addresses = entries.iter().map(|(addr, _)| *addr).collect();
+        if let Some(heap) = self.heap.as_mut() {
+            heap.set_entries(entries);
+        }
+        addresses
+    }
+
+    /// Keeps the heap account at a breakpoint of heap tracking's own: on entry to an allocator
+    /// function, when a breakpoint goes on the return address to catch the result, or at that
+    /// return address. Returns whether the stop was only for the account and the inferior should
+    /// carry on.
+    fn track_heap(&mut self, addr: usize) -> Result<bool, SessionError> {
+        let allocator = match &self.heap {
+            Some(heap) => heap.allocator_at(addr),
+            None => return Ok(false),
+        };
+        let regs = self.registers()?;
+        let user_breakpoint = self.breakpoint_at(addr).is_some();
+        let return_address = match allocator {
+            Some(_) => self.read_u64(regs.rsp as usize)? as usize,
+            None => 0,
+        };
+        let inferior = live_mut(&mut self.inferior)?;
+        let heap = match self.heap.as_mut() {
+            Some(heap) => heap,
+            None => return Ok(false),
+        };
+        if let Some(allocator) = allocator {
+            // A breakpoint already at the return address is someone else's (a `next` stepping
+            // over the call, say), unless another call in progress put it there
+            let owns_breakpoint = allocator != Allocator::Free
+                && (heap.owns_breakpoint(return_address)
+                    || !inferior.has_break_point(return_address));
+            if owns_breakpoint {
+                if let Err(err) = inferior.arm(return_address) {
+                    self.breakpoint_errors.push(err);
+                }
+            }
+            let stack = regs.rsp as usize + 8;
+            heap.enter(allocator, [regs.rdi, regs.rsi], return_address, stack, owns_breakpoint);
+            return Ok(!user_breakpoint);
+        }
+        match heap.returned(addr, regs.rsp as usize, regs.rax) {
+            Some(owned) => {
+                if owned && !heap.owns_breakpoint(addr) && !user_breakpoint {
+                    inferior.disarm(addr)?;
+                }
+                Ok(owned && !user_breakpoint)
+            }
+            None => Ok(heap.owns_breakpoint(addr) && !user_breakpoint),
+        }
+    }
+
+    /// `coverage on`: a breakpoint of our own goes on the first address of every statement in the
+    /// program's line table (the next time the inferior is resumed, all in one batch), and comes
+    /// out the first time it is hit, so that the line runs at full speed from then on. Turning
+    /// it on again starts over. Returns how many lines there are.
+    pub fn start_coverage(&mut self) -> Result<usize, SessionError> {
+        self.stop_coverage()?;
+        let coverage = Coverage::new(
+            self.debug_data
+                .line_table(None)
+                .into_iter()
+                .filter(|row| row.is_stmt && row.number != 0)
+                .map(|row| (row.file.as_str(), row.number, row.address)),
+        );
+        let lines = coverage.report().iter().map(|file| file.total).sum();
+        if lines == 0 {
+            return Err(SessionError::NoLineTable);
+        }
+        self.coverage = Some(coverage);
+        Ok(lines)
+    }
+
+    /// `coverage off`: takes out the breakpoints of the lines that haven't run, from the inferior
+    /// and its checkpoints, and forgets the coverage.
+    pub fn stop_coverage(&mut self) -> Result<(), SessionError> {
+        let coverage = match self.coverage.take() {
+            Some(coverage) => coverage,
+            None => return Ok(()),
+        };
+        let addresses: Vec<usize> = coverage
+            .pending()
+            .map(|addr| addr + self.load_bias)
+            .filter(|&addr| self.breakpoint_at(addr).is_none())
+            .collect();
+        self.remove_internal_breakpoints(&addresses)
+    }
+
+    /// The lines run so far, while `coverage` is on.
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    /// Counts the line that a statement starting at `addr` belongs to as run, if coverage was
+    /// still waiting for it, and takes out the line's breakpoints (except where the user has
+    /// one). Returns whether coverage was waiting for it.
+    fn cover(&mut self, addr: usize) -> Result<bool, SessionError> {
+        let bias = self.load_bias;
+        let hit = match (self.coverage.as_mut(), addr.checked_sub(bias)) {
+            (Some(coverage), Some(addr)) if coverage.is_pending(addr) => coverage.hit(addr),
+            _ => return Ok(false),
+        };
+        let addresses: Vec<usize> = hit
+            .into_iter()
+            .map(|addr| addr + bias)
+            .filter(|&addr| self.breakpoint_at(addr).is_none())
+            .collect();
+        self.remove_internal_breakpoints(&addresses)?;
+        Ok(true)
+    }
+
+    /// Records coverage at a breakpoint. Returns whether the stop was only coverage's and the
+    /// inferior should carry on, which it shouldn't if the user has a breakpoint there or
+    /// `run_to` is running to it.
+    fn track_coverage(&mut self, addr: usize) -> Result<bool, SessionError> {
+        if !self.cover(addr)? {
+            return Ok(false);
+        }
+        let held = match (&self.coverage, addr.checked_sub(self.load_bias)) {
+            (Some(coverage), Some(addr)) => coverage.is_held(addr),
+            _ => false,
+        };
+        Ok(!held && self.breakpoint_at(addr).is_none())
+    }
+
+    /// Takes breakpoints of the debugger's own out of the inferior and its checkpoints.
+    fn remove_internal_breakpoints(&mut self, addresses: &[usize]) -> Result<(), SessionError> {
+        let checkpoints = self
+            .checkpoints
+            .iter_mut()
+            .map(|checkpoint| &mut checkpoint.inferior);
+        for inferior in live_mut(&mut self.inferior).into_iter().chain(checkpoints) {
+            for &addr in addresses {
+                inferior.disarm(addr)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// `catch abort` and `catch panic`: on, a breakpoint of our own goes on the catchpoint's
+    /// function (the next time the inferior is resumed), so that the program stops with the
+    /// interesting frames still on the stack: before abort raises SIGABRT (with one on
+    /// __assert_fail to pick up the message of a failed assertion on the way), or before a
+    /// panic unwinds. Off, they are taken out again.
+    pub fn set_catchpoint(&mut self, catchpoint: Catchpoint, on: bool) -> Result<(), SessionError> {
+        if on {
+            if catchpoint == Catchpoint::Panic && self.panic_function().is_none() {
+                return Err(SessionError::NoPanicFunction);
+            }
+            if !self.catchpoints.contains(&catchpoint) {
+                self.catchpoints.push(catchpoint);
+            }
+            return Ok(());
+        }
+        self.catchpoints.retain(|&other| other != catchpoint);
+        let mut entries: Vec<usize> = self
+            .catch_entries
+            .iter()
+            .filter(|(_, other)| *other == catchpoint)
+            .map(|(addr, _)| *addr)
+            .collect();
+        self.catch_entries.retain(|(_, other)| *other != catchpoint);
+        if catchpoint == Catchpoint::Abort {
+            entries.append(&mut self.assert_entries);
+        }
+        entries.retain(|&addr| self.breakpoint_at(addr).is_none());
+        self.remove_internal_breakpoints(&entries)
+    }
+
+    /// `info catch`: the catchpoints that are on, with how many places each has a breakpoint
+    /// (which is only known once the inferior has been resumed).
+    pub fn catchpoints(&self) -> Vec<(Catchpoint, usize)> {
+        self.catchpoints
+            .iter()
+            .map(|&catchpoint| {
+                let count = self
+                    .catch_entries
+                    .iter()
+                    .filter(|(_, other)| *other == catchpoint)
+                    .count();
+                (catchpoint, count)
+            })
+            .collect()
+    }
+
+    /// The catchpoint the inferior is stopped at, if the last stop was one.
+    pub fn take_caught(&mut self) -> Option<Caught> {
+        self.caught.take()
+    }
+
+    /// Whether the target's symbols look like a Rust program's, for suggesting `catch panic`.
+    pub fn looks_like_rust(&self) -> bool {
+        RUST_MARKERS
+            .iter()
+            .any(|name| !self.debug_data.get_addrs_for_function(name).is_empty())
+    }
+
+    /// The function `catch panic` stops at: the first of PANIC_FUNCTIONS the target has.
+    fn panic_function(&self) -> Option<&'static str> {
+        PANIC_FUNCTIONS
+            .iter()
+            .copied()
+            .find(|name| !self.debug_data.get_addrs_for_function(name).is_empty())
+    }
+
+    /// The entry points of the functions the catchpoints that are on stop at, and of
+    /// __assert_fail while catching abort.
+    fn catch_entries(&mut self) -> Vec<usize> {
+        let mut entries = Vec::new();
+        self.assert_entries.clear();
+        for &catchpoint in &self.catchpoints {
+            let name = match catchpoint {
+                Catchpoint::Abort => {
+                    self.assert_entries = self.function_entries("__assert_fail");
+                    Some("abort")
+                }
+                Catchpoint::Panic => self.panic_function(),
+            };
+            for addr in name.map_or_else(Vec::new, |name| self.function_entries(name)) {
+                entries.push((addr, catchpoint));
+            }
+        }
+        self.catch_entries = entries;
+        self.catch_entries
+            .iter()
+            .map(|(addr, _)| *addr)
+            .chain(self.assert_entries.iter().copied())
+            .collect()
+    }
+
+    /// Reads the arguments of a call to __assert_fail at a breakpoint on its entry, keeping them
+    /// for when it calls abort. Returns whether the stop was only for that and the inferior
+    /// should carry on.
+    fn catch_assertion(&mut self, addr: usize) -> Result<bool, SessionError> {
+        if !self.assert_entries.contains(&addr) {
+            return Ok(false);
+        }
+        // __assert_fail(assertion, file, line, function)
+        let regs = self.registers()?;
+        let text = |arg: u64| self.read_c_string(arg as usize).unwrap_or_default();
+        self.assertion = Some(Assertion {
+            expression: text(regs.rdi),
+            file: text(regs.rsi),
+            line: regs.rdx as u32,
+            function: text(regs.rcx),
+        });
+        Ok(self.breakpoint_at(addr).is_none())
+    }
+
+    /// Called right after spawning. The dynamic linker hasn't loaded any libraries yet, so if
+    /// there are pending breakpoints (which might be in a library), or the heap is tracked or
+    /// abort caught (the allocator and abort are in libc), the inferior is run to the program's
+    /// entry point, by which time every library it links against is loaded. Returns the status
+    /// if the inferior stopped somewhere else first.
+    fn load_libraries(&mut self) -> Result<Option<Status>, SessionError> {
+        if self.heap.is_none()
+            && self.catchpoints.is_empty()
+            && self.breakpoints.all().iter().all(|bp| !bp.addresses.is_empty())
+        {
+            self.refresh_libraries();
+            return Ok(None);
+        }
+        let entry = self.debug_data.entry_point() + self.load_bias;
+        let status = self.run_to(entry)?;
+        match status {
+            Status::Stopped(_, _, StopReason::Breakpoint(addr))
+                if addr == entry && self.breakpoint_at(entry).is_none() =>
+            {
+                self.refresh_libraries();
+                Ok(None)
+            }
+            status => Ok(Some(status)),
+        }
+    }
+
+    /// Rereads the inferior's mappings to pick up libraries loaded or unloaded since the last
+    /// stop. Pending breakpoints are resolved again if a new library appeared; breakpoints in a
+    /// library that has gone become pending again. Returns what changed.
+    fn refresh_libraries(&mut self) -> LibraryChanges {
+        let pid = match &self.inferior {
+            Some(inferior) => inferior.pid(),
+            None => return LibraryChanges::default(),
+        };
+        let mappings = match proc_maps::read(pid) {
+            Ok(mappings) => mappings,
+            Err(_) => return LibraryChanges::default(),
+        };
+        let exe = fs::read_link(format!("/proc/{}/exe", pid)).ok();
+        let changes = self.libraries.refresh(&mappings, exe.as_ref());
+        for lib in &changes.unloaded {
+            self.unresolve_breakpoints_in(lib);
+        }
+        if !changes.loaded.is_empty() {
+            self.resolve_pending_breakpoints();
+        }
+        changes
+    }
+
+    /// Sets the function told about each shared library loaded or unloaded while the program
+    /// runs.
+    pub fn set_solib_listener(&mut self, listener: Option<Box<dyn FnMut(&SolibEvent)>>) {
+        self.solib_listener = listener;
+    }
+
+    /// Whether the last stop was for `set stop-on-solib-events`.
+    pub fn take_solib_stop(&mut self) -> bool {
+        std::mem::replace(&mut self.solib_stop, false)
+    }
+
+    /// Finds the dynamic linker's r_debug through the DT_DEBUG entry of the program's dynamic
+    /// section, which the dynamic linker fills in as it starts, and with it r_brk. None for a
+    /// static program, or until the dynamic linker has got that far.
+    fn find_r_debug(&self) -> Option<(usize, usize)> {
+        let dynamic = self.debug_data.section_address(".dynamic")? + self.load_bias;
+        for entry in (dynamic..).step_by(16).take(DYNAMIC_ENTRY_LIMIT) {
+            match self.read_u64(entry).ok()? {
+                DT_NULL => return None,
+                DT_DEBUG => {
+                    let r_debug = self.read_u64(entry + 8).ok()? as usize;
+                    if r_debug == 0 {
+                        return None;
+                    }
+                    let r_brk = self.read_u64(r_debug + R_BRK_OFFSET).ok()? as usize;
+                    return if r_brk == 0 { None } else { Some((r_debug, r_brk)) };
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// At the dynamic linker's breakpoint, once the list of loaded objects is consistent again
+    /// (it stops there before and after changing it): rescans the libraries, which loads the new
+    /// ones' symbols and resolves pending breakpoints against them, and tells the listener what
+    /// changed. Returns whether the stop was only for that and the inferior should carry on,
+    /// which it shouldn't if `set stop-on-solib-events` is on and something changed.
+    fn solib_event(&mut self, addr: usize) -> Result<bool, SessionError> {
+        let r_debug = match self.r_debug {
+            Some((r_debug, r_brk)) if r_brk == addr => r_debug,
+            _ => return Ok(false),
+        };
+        let user_breakpoint = self.breakpoint_at(addr).is_some();
+        // r_state, an int
+        if self.read_u64(r_debug + R_STATE_OFFSET)? as u32 != RT_CONSISTENT {
+            return Ok(!user_breakpoint);
+        }
+        let changes = self.refresh_libraries();
+        if let Some(listener) = self.solib_listener.as_mut() {
+            for path in &changes.loaded {
+                listener(&SolibEvent::Loaded(path.clone()));
+            }
+            for lib in &changes.unloaded {
+                listener(&SolibEvent::Unloaded(lib.path.clone()));
+            }
+        }
+        self.solib_stop = self.stop_on_solib_events && !changes.is_empty();
+        Ok(!self.solib_stop && !user_breakpoint)
+    }
+
+    /// Takes the addresses in an unloaded library's code out of the breakpoints, which become
+    /// pending if that was all of them (except those on raw addresses, which are left alone).
+    /// The inferior forgets its breakpoints there, since the code they patched is gone.
+    fn unresolve_breakpoints_in(&mut self, lib: &SharedLibrary) {
+        for bp in self.breakpoints.all_mut() {
+            if let Location::Address(_) = bp.location {
+                continue;
+            }
+            bp.addresses.retain(|&addr| !lib.contains(addr));
+        }
+        if let Some(inferior) = self.inferior.as_mut() {
+            for &(start, end) in &lib.code {
+                inferior.forget_break_points_in(start, end);
+            }
+        }
+    }
+
+    /// Updates the loaded libraries after the inferior stopped, and describes the stop.
+    fn finish_stop(&mut self, status: Status) -> StopEvent {
+        self.deselect_frame();
+        match status {
+            Status::Stopped(..) => {
+                self.refresh_libraries();
+            }
+            _ => self.libraries.clear(),
+        }
+        self.stop_event(status)
+    }
+
+    /// `record`: from now on, `cont` single-steps the inferior and remembers the address of every
+    /// instruction it executes (the most recent RECORD_LIMIT of them).
+    pub fn start_recording(&mut self) {
+        if self.recording.is_none() {
+            self.recording = Some(VecDeque::new());
+        }
+    }
+
+    /// `record stop`: goes back to running at full speed, and forgets the recording.
+    pub fn stop_recording(&mut self) {
+        self.recording = None;
+    }
+
+    /// The recorded instruction addresses, oldest first, or None if `record` is off.
+    pub fn recorded_instructions(&self) -> Option<&VecDeque<usize>> {
+        self.recording.as_ref()
+    }
+
+    /// Like `resume`, but one instruction at a time, recording each address. Stops at user
+    /// breakpoints (int3s are stepped over rather than hit when single-stepping, so they're
+    /// checked for by address), signals and exits.
+    fn resume_recording(&mut self) -> Result<Status, SessionError> {
+        loop {
+            let pc = self.registers()?.rip as usize;
+            if let Some(recording) = self.recording.as_mut() {
+                if recording.len() == RECORD_LIMIT {
+                    recording.pop_front();
+                }
+                recording.push_back(pc);
+            }
+            let inferior = live_mut(&mut self.inferior)?;
+            match inferior.step_instruction()? {
+                Status::Stopped(sig, addr, StopReason::Step) => {
+                    self.cover(addr)?;
+                    if self.breakpoint_at(addr).is_some() {
+                        return Ok(Status::Stopped(sig, addr, StopReason::Breakpoint(addr)));
+                    }
+                }
+                status => return Ok(status),
+            }
+        }
+    }
+
+    /// Resumes the inferior until it stops or exits. If that finishes a `call` that had
+    /// stopped, the inferior is put back where it was before the call.
+    pub fn cont(&mut self) -> Result<StopEvent, SessionError> {
+        self.arm_breakpoints()?;
+        let mut status = if self.recording.is_some() {
+            self.resume_recording()?
+        } else {
+            self.resume()?
+        };
+        if self.finish_call(&status)?.is_some() {
+            let regs = self.registers()?;
+            status = Status::Stopped(Signal::SIGTRAP, regs.rip as usize, StopReason::Step);
+        }
+        Ok(self.finish_stop(status))
+    }
+
+    /// `run &`: like `run`, but once the program is started it is left running. Returns the stop
+    /// if it stopped before it could get going; otherwise `wait_background` collects the stop.
+    pub fn run_background(&mut self, args: Vec<String>) -> Result<Option<StopEvent>, SessionError> {
+        if self.recording.is_some() {
+            return Err(SessionError::RecordingInBackground);
+        }
+        if self.syscall_tracer.is_some() {
+            return Err(SessionError::TracingInBackground("trace-syscalls"));
+        }
+        if self.call_tracer.is_some() {
+            return Err(SessionError::TracingInBackground("trace-calls"));
+        }
+        if self.heap.is_some() {
+            return Err(SessionError::TracingInBackground("heap-track"));
+        }
+        if self.coverage.is_some() {
+            return Err(SessionError::CoverageInBackground);
+        }
+        if !args.is_empty() {
+            self.args = args;
+        }
+        self.spawn()?;
+        if let Some(status) = self.load_libraries()? {
+            return Ok(Some(self.finish_stop(status)));
+        }
+        self.cont_background()
+    }
+
+    /// `continue &`: resumes the inferior without waiting for it to stop. Returns the stop if it
+    /// stopped before it could get going; otherwise `wait_background` collects the stop.
+    pub fn cont_background(&mut self) -> Result<Option<StopEvent>, SessionError> {
+        if self.recording.is_some() {
+            return Err(SessionError::RecordingInBackground);
+        }
+        if self.syscall_tracer.is_some() {
+            return Err(SessionError::TracingInBackground("trace-syscalls"));
+        }
+        if self.call_tracer.is_some() {
+            return Err(SessionError::TracingInBackground("trace-calls"));
+        }
+        if self.heap.is_some() {
+            return Err(SessionError::TracingInBackground("heap-track"));
+        }
+        if self.coverage.is_some() {
+            return Err(SessionError::CoverageInBackground);
+        }
+        self.arm_breakpoints()?;
+        let inferior = live_mut(&mut self.inferior)?;
+        match inferior.resume()? {
+            Some(status) => self.background_stop(status),
+            None => Ok(None),
+        }
+    }
+
+    /// Collects the stop of an inferior left running by `cont_background`, once it has stopped.
+    /// None if the stop was only watchpoint bookkeeping and the inferior was set going again.
+    pub fn wait_background(&mut self) -> Result<Option<StopEvent>, SessionError> {
+        let inferior = live_mut(&mut self.inferior)?;
+        let status = inferior.wait_running()?;
+        self.background_stop(status)
+    }
+
+    /// `interrupt`: stops an inferior left running by `cont_background` with SIGINT.
+    /// `wait_background` collects the stop.
+    pub fn interrupt(&self) -> Result<(), SessionError> {
+        let inferior = live(&self.inferior)?;
+        Ok(inferior.interrupt()?)
+    }
+
+    fn background_stop(&mut self, status: Status) -> Result<Option<StopEvent>, SessionError> {
+        match self.filter_stop(status)? {
+            Some(status) => Ok(Some(self.finish_stop(status))),
+            None => self.cont_background(),
+        }
+    }
+
+    /// `checkpoint`: saves the state of the inferior as a forked copy of it, which `restart` can
+    /// go back to.
+    pub fn checkpoint(&mut self) -> Result<&Checkpoint, SessionError> {
+        let inferior = live_mut(&mut self.inferior)?;
+        let copy = inferior.fork()?;
+        let address = copy.getregs()?.rip as usize;
+        self.checkpoints.push(Checkpoint {
+            id: self.next_checkpoint_id,
+            inferior: copy,
+            address,
+            function: self.function_at(address),
+            line: self.line_at(address),
+        });
+        self.next_checkpoint_id += 1;
+        Ok(self.checkpoints.last().unwrap())
+    }
+
+    pub fn checkpoints(&self) -> &[Checkpoint] {
+        &self.checkpoints
+    }
+
+    /// `restart`: goes back to a checkpoint, killing the current inferior. The checkpoint is
+    /// forked again and the copy runs, so the same checkpoint can be restarted any number of
+    /// times.
+    pub fn restart(&mut self, id: usize) -> Result<StopEvent, SessionError> {
+        let checkpoint = self
+            .checkpoints
+            .iter_mut()
+            .find(|checkpoint| checkpoint.id == id)
+            .ok_or(SessionError::NoCheckpoint(id))?;
+        let mut inferior = checkpoint.inferior.fork()?;
+        let address = checkpoint.address;
+        if let Some(mut current) = self.inferior.take() {
+            current.give_terminal(&mut inferior);
+            current.kill();
+        }
+        self.inferior = Some(inferior);
+        self.pending_call = None;
+        if let Some(heap) = self.heap.as_mut() {
+            heap.reset();
+        }
+        self.relocate_watchpoints(self.load_bias)?;
+        Ok(self.finish_stop(Status::Stopped(
+            Signal::SIGTRAP,
+            address,
+            StopReason::Step,
+        )))
+    }
+
+    /// `call`: runs a function of the inferior with integer or pointer arguments (evaluated as
+    /// expressions), following the System V AMD64 calling convention. The function returns to a
+    /// breakpoint at the program's entry point, after which the registers and stack are put
+    /// back as they were.
+    pub fn call_function(
+        &mut self,
+        name: &str,
+        args: &[String],
+    ) -> Result<CallOutcome, SessionError> {
+        if self.pending_call.is_some() {
+            return Err(SessionError::CallInProgress);
+        }
+        // Call the function at its entry, so that its prologue sets up the frame
+        let location = Location::FunctionEntry(name.to_string());
+        let address = self
+            .resolve(&location)
+            .ok_or(SessionError::UnknownLocation(location))?;
+        let return_type = self
+            .debug_data
+            .functions()
+            .find(|(_, func)| func.matches(name))
+            .and_then(|(_, func)| func.return_type);
+        if args.len() > ARGUMENT_REGISTERS {
+            return Err(SessionError::TooManyArguments(args.len()));
+        }
+        let mut values = Vec::new();
+        for arg in args {
+            let (value, _) = self.evaluate(arg)?;
+            values.push(expr::to_integer(&self.debug_data, &value)? as u64);
+        }
+        let saved = self.registers()?;
+        let trampoline = self.debug_data.entry_point() + self.load_bias;
+        // Leave the red zone below the stack pointer alone, and align the stack as for a call
+        let sp = ((saved.rsp as usize - 256) & !0xf) - 8;
+        let mut regs = saved;
+        let mut registers = [
+            &mut regs.rdi,
+            &mut regs.rsi,
+            &mut regs.rdx,
+            &mut regs.rcx,
+            &mut regs.r8,
+            &mut regs.r9,
+        ];
+        for (register, value) in registers.iter_mut().zip(values) {
+            **register = value;
+        }
+        regs.rip = address as u64;
+        regs.rsp = sp as u64;
+        // No vector registers used by varargs, and no system call to restart
+        regs.rax = 0;
+        regs.orig_rax = u64::MAX;
+        let temporary = self.breakpoint_at(trampoline).is_none();
+        self.arm_breakpoints()?;
+        let inferior = live_mut(&mut self.inferior)?;
+        let old_word = inferior.read_word(sp)?;
+        inferior.write_word(sp, trampoline as u64)?;
+        if temporary {
+            self.breakpoint_errors
+                .extend(inferior.arm(trampoline).err());
+        }
+        inferior.setregs(regs)?;
+        self.pending_call = Some(PendingCall {
+            regs: saved,
+            stack_word: (sp, old_word),
+            trampoline,
+            return_type,
+        });
+        let status = self.resume()?;
+        match self.finish_call(&status)? {
+            Some((return_type, rax)) => Ok(CallOutcome::Returned(
+                return_type.map(|type_id| {
+                    let size = self.debug_data.type_size(type_id).min(8);
+                    self.format_bytes(type_id, &rax.to_le_bytes()[..size])
+                }),
+            )),
+            None => Ok(CallOutcome::Stopped(self.finish_stop(status))),
+        }
+    }
+
+    /// If `status` is the called function of a pending `call` returning, restores the registers
+    /// and stack from before the call and returns the function's return type and rax.
+    fn finish_call(
+        &mut self,
+        status: &Status,
+    ) -> Result<Option<(Option<TypeId>, u64)>, SessionError> {
+        let call = match self.pending_call.take() {
+            Some(call) => call,
+            None => return Ok(None),
+        };
+        match *status {
+            Status::Stopped(_, addr, StopReason::Breakpoint(_)) if addr == call.trampoline => {}
+            Status::Stopped(..) => {
+                self.pending_call = Some(call);
+                return Ok(None);
+            }
+            _ => return Ok(None),
+        }
+        let user_breakpoint = self.breakpoint_at(call.trampoline).is_some();
+        let inferior = live_mut(&mut self.inferior)?;
+        let rax = inferior.getregs()?.rax;
+        inferior.setregs(call.regs)?;
+        inferior.write_word(call.stack_word.0, call.stack_word.1)?;
+        if !user_breakpoint {
+            inferior.disarm(call.trampoline)?;
+        }
+        Ok(Some((call.return_type, rax)))
+    }
+
+    /// `until`: with a line, runs until that line of the current function is reached in the
+    /// current frame (a recursive call reaching it doesn't count). Without one, runs until a line
+    /// after the current one, which unlike single-stepping lines never goes back to the top of a
+    /// loop.
+    pub fn until(&mut self, line_number: Option<usize>) -> Result<StopEvent, SessionError> {
+        let regs = self.registers()?;
+        let pc = regs.rip as usize;
+        let (start, end) = self
+            .function_bounds(pc)
+            .ok_or(SessionError::NoLineInfo)?;
+        let frame_base = self.frame_base(start, &regs);
+        let status = match line_number {
+            Some(line_number) => {
+                let addr = self
+                    .debug_data
+                    .get_addr_for_line_in_range(
+                        line_number,
+                        start - self.load_bias,
+                        end - self.load_bias,
+                    )
+                    .ok_or(SessionError::LineNotInFunction(line_number))?
+                    + self.load_bias;
+                loop {
+                    let status = self.run_to(addr)?;
+                    match status {
+                        Status::Stopped(_, stop_addr, StopReason::Breakpoint(_))
+                            if stop_addr == addr && self.breakpoint_at(addr).is_none() =>
+                        {
+                            let regs = self.registers()?;
+                            if self.frame_base(start, &regs) >= frame_base {
+                                break status;
+                            }
+                        }
+                        status => break status,
+                    }
+                }
+            }
+            None => {
+                let line = self.line_at(pc).ok_or(SessionError::NoLineInfo)?;
+                self.step_past_line(pc, line.number, (start, end), frame_base, LineStep::Until)?
+            }
+        };
+        Ok(self.finish_stop(status))
+    }
+
+    /// Whether `addr` is in the function the inferior is stopped in.
+    pub fn in_current_function(&self, addr: usize) -> Result<bool, SessionError> {
+        let pc = self.registers()?.rip as usize;
+        Ok(match self.function_bounds(pc) {
+            Some((start, end)) => start <= addr && addr < end,
+            None => false,
+        })
+    }
+
+    /// `jump`: resumes the inferior at `addr` instead of where it stopped. A breakpoint at `addr`
+    /// is stepped over rather than reported, as when continuing from one.
+    pub fn jump(&mut self, addr: usize) -> Result<StopEvent, SessionError> {
+        let inferior = live(&self.inferior)?;
+        let mut regs = inferior.getregs()?;
+        regs.rip = addr as u64;
+        inferior.setregs(regs)?;
+        self.cont()
+    }
+
+    /// `return`: pops the innermost frame without running the rest of its function, leaving the
+    /// inferior stopped at the return address in the caller. `value` (an expression) is put in
+    /// rax as the return value. Returns the caller's frame (and any functions inlined at the
+    /// return address).
+    pub fn return_from_frame(&mut self, value: Option<&str>) -> Result<Vec<Frame>, SessionError> {
+        let mut regs = self.registers()?;
+        let pc = regs.rip as usize;
+        let (start, _) = self.function_bounds(pc).ok_or(SessionError::NoFrameInfo)?;
+        if self.function_at(pc).as_deref() == Some("main") {
+            return Err(SessionError::ReturnFromMain);
+        }
+        let value = match value {
+            Some(expression) => {
+                let (value, _) = self.evaluate(expression)?;
+                Some(expr::to_integer(&self.debug_data, &value)?)
+            }
+            None => None,
+        };
+        let frame_base = self.frame_base(start, &regs);
+        // Return address, and below it the caller's rbp once the prologue has pushed it
+        let saved = self.read_memory(frame_base - 16, 16)?;
+        if regs.rsp as usize + 8 != frame_base {
+            regs.rbp = u64::from_le_bytes(saved[..8].try_into().unwrap());
+        }
+        regs.rip = u64::from_le_bytes(saved[8..].try_into().unwrap());
+        regs.rsp = frame_base as u64;
+        if let Some(value) = value {
+            regs.rax = value as u64;
+        }
+        let inferior = live(&self.inferior)?;
+        inferior.setregs(regs)?;
+        self.deselect_frame();
+        let mut frames = self.backtrace()?;
+        let caller = frames
+            .iter()
+            .position(|frame| !frame.inlined)
+            .unwrap_or(frames.len() - 1);
+        frames.truncate(caller + 1);
+        Ok(frames)
+    }
+
+    /// `finish`: runs until the current function returns to its caller. Also returns what it
+    /// returned, unless the inferior stopped for some other reason (such as a user breakpoint)
+    /// first.
+    pub fn finish(&mut self) -> Result<(StopEvent, Option<ReturnValue>), SessionError> {
+        let regs = self.registers()?;
+        let pc = regs.rip as usize;
+        let func = pc
+            .checked_sub(self.load_bias)
+            .and_then(|addr| self.debug_data.get_function_containing(addr))
+            .ok_or(SessionError::NoFrameInfo)?;
+        if func.matches("main") {
+            return Err(SessionError::FinishFromMain);
+        }
+        let return_type = func.return_type;
+        let frame_base = self.frame_base(func.address + self.load_bias, &regs);
+        let return_address = self.read_u64(frame_base - 8)? as usize;
+        let (status, returned) = loop {
+            let status = self.run_to(return_address)?;
+            match status {
+                Status::Stopped(_, addr, StopReason::Breakpoint(_)) if addr == return_address => {
+                    // A deeper recursive call returning doesn't count
+                    if self.registers()?.rsp as usize >= frame_base {
+                        break (status, true);
+                    }
+                    if self.breakpoint_at(addr).is_some() {
+                        break (status, false);
+                    }
+                }
+                status => break (status, false),
+            }
+        };
+        let value = if returned {
+            let rax = self.registers()?.rax;
+            Some(match return_type {
+                Some(type_id) => {
+                    let size = self.debug_data.type_size(type_id).min(8);
+                    ReturnValue::Value(Value {
+                        type_id: Some(type_id),
+                        address: None,
+                        bytes: rax.to_le_bytes()[..size].to_vec(),
+                    })
+                }
+                None => ReturnValue::Void,
+            })
+        } else {
+            None
+        };
+        Ok((self.finish_stop(status), value))
+    }
+
+    /// Adds a value to the value history, and returns its number (`$N`).
+    pub fn record_value(&mut self, value: Value) -> usize {
+        self.values.push(value);
+        self.values.len()
+    }
+
+    /// The value history, `$1` first.
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    /// `advance`: runs until `location` is reached or the current function returns, whichever
+    /// comes first. Also says which it was, unless the inferior stopped for some other reason
+    /// (such as a user breakpoint) first.
+    pub fn advance(
+        &mut self,
+        location: &Location,
+    ) -> Result<(StopEvent, Option<AdvanceStop>), SessionError> {
+        let targets = self.resolve_all(location);
+        if targets.is_empty() {
+            return Err(SessionError::UnknownLocation(location.clone()));
+        }
+        let regs = self.registers()?;
+        // Where the current frame returns to, and the stack pointer once it has
+        let caller = match self.function_bounds(regs.rip as usize) {
+            Some((start, _)) => {
+                let frame_base = self.frame_base(start, &regs);
+                let return_address = self.read_u64(frame_base - 8)?;
+                Some((return_address as usize, frame_base))
+            }
+            None => None,
+        };
+        let temporary: Vec<usize> = targets
+            .iter()
+            .copied()
+            .chain(caller.map(|(return_address, _)| return_address))
+            .filter(|&addr| self.breakpoint_at(addr).is_none())
+            .collect();
+        self.arm_breakpoints()?;
+        let inferior = live_mut(&mut self.inferior)?;
+        self.breakpoint_errors
+            .extend(inferior.arm_all(&temporary));
+        let (status, outcome) = loop {
+            let status = self.resume()?;
+            let addr = match status {
+                Status::Stopped(_, addr, StopReason::Breakpoint(_)) => addr,
+                status => break (status, None),
+            };
+            if targets.contains(&addr) {
+                break (status, Some(AdvanceStop::Reached));
+            }
+            match caller {
+                Some((return_address, frame_base)) if addr == return_address => {
+                    // A deeper recursive call returning doesn't count
+                    if self.registers()?.rsp as usize >= frame_base {
+                        break (status, Some(AdvanceStop::Returned));
+                    }
+                    if self.breakpoint_at(addr).is_some() {
+                        break (status, None);
+                    }
+                }
+                _ => break (status, None),
+            }
+        };
+        if let (Status::Stopped(..), Some(inferior)) = (&status, self.inferior.as_mut()) {
+            for addr in temporary {
+                inferior.disarm(addr)?;
+            }
+        }
+        Ok((self.finish_stop(status), outcome))
+    }
+
+    /// `step`: runs to the start of another line, going into the functions called on the way
+    /// that have line information (stopping past their prologues) and through those that don't.
+    /// From code without line information, runs until its function returns and steps on from
+    /// there. With `step_into_no_debug`, stops in such code an instruction at a time instead.
+    pub fn step(&mut self) -> Result<StopEvent, SessionError> {
+        self.step_line(LineStep::Step)
+    }
+
+    /// `next`: runs to the start of another line, or until the current function returns,
+    /// stepping over calls. From code without line information, runs until its function returns
+    /// and steps on from there.
+    pub fn next(&mut self) -> Result<StopEvent, SessionError> {
+        self.step_line(LineStep::Next)
+    }
+
+    /// Returns (and forgets) the functions without line information that `step` has run
+    /// through for the first time since the last call, for the debugger to mention once each.
+    pub fn take_no_line_functions(&mut self) -> Vec<String> {
+        std::mem::replace(&mut self.new_no_line_functions, Vec::new())
+    }
+
+    fn step_line(&mut self, mode: LineStep) -> Result<StopEvent, SessionError> {
+        let regs = self.registers()?;
+        let pc = regs.rip as usize;
+        let line = self.line_at(pc);
+        if line.is_none() && mode == LineStep::Step && self.step_into_no_debug {
+            return self.step_instruction();
+        }
+        let status = match (line, self.function_bounds(pc)) {
+            (Some(line), Some((start, end))) => {
+                let frame_base = self.frame_base(start, &regs);
+                self.step_past_line(pc, line.number, (start, end), frame_base, mode)?
+            }
+            _ => {
+                if mode == LineStep::Step {
+                    self.note_no_line_function(pc);
+                }
+                self.step_out_of_function(mode)?
+            }
+        };
+        Ok(self.finish_stop(status))
+    }
+
+    /// Runs until the function the inferior is stopped in, which has no line information,
+    /// returns, then steps on to the start of a line in the caller (the return address is in the
+    /// middle of one). The return address is found as `finish` finds it, from where the function
+    /// starts by the symbol table; in a PLT stub it's still on top of the stack.
+    fn step_out_of_function(&mut self, mode: LineStep) -> Result<Status, SessionError> {
+        let regs = self.registers()?;
+        let pc = regs.rip as usize;
+        let frame_base = if self.plt_entry_at(pc).is_some() {
+            regs.rsp as usize + 8
+        } else {
+            let start = self.function_start(pc).ok_or(SessionError::NoFrameInfo)?;
+            self.frame_base(start, &regs)
+        };
+        let return_address = self.read_u64(frame_base - 8)? as usize;
+        let status = loop {
+            let status = self.run_to(return_address)?;
+            match status {
+                Status::Stopped(_, addr, StopReason::Breakpoint(_))
+                    if addr == return_address && self.breakpoint_at(addr).is_none() =>
+                {
+                    // A deeper recursive call returning doesn't count
+                    if self.registers()?.rsp as usize >= frame_base {
+                        break status;
+                    }
+                }
+                status => return Ok(status),
+            }
+        };
+        let regs = self.registers()?;
+        match (self.line_at(return_address), self.function_bounds(return_address)) {
+            (Some(line), Some((start, end))) => {
+                let frame_base = self.frame_base(start, &regs);
+                self.step_past_line(return_address, line.number, (start, end), frame_base, mode)
+            }
+            _ => Ok(match status {
+                Status::Stopped(sig, addr, _) => Status::Stopped(sig, addr, StopReason::Step),
+                status => status,
+            }),
+        }
+    }
+
+    /// For `step`, having just stepped into a call to `addr`: stops in the called function, past
+    /// its prologue, if it has line information (or at its first instruction if it hasn't and
+    /// `step_into_no_debug` is on). None to step over the call instead, as for functions without
+    /// line information and those the `skip` list matches.
+    fn step_into_call(
+        &mut self,
+        addr: usize,
+        sig: Signal,
+    ) -> Result<Option<Status>, SessionError> {
+        let line = match self.line_at(addr) {
+            Some(line) => line,
+            None => {
+                if self.step_into_no_debug {
+                    return Ok(Some(Status::Stopped(sig, addr, StopReason::Step)));
+                }
+                self.note_no_line_function(addr);
+                return Ok(None);
+            }
+        };
+        if self.skips.matches(self.function_at(addr).as_deref(), Some(&line.file)) {
+            return Ok(None);
+        }
+        let target = match self.library_at(addr) {
+            Some(lib) => lib.skip_prologue(addr),
+            None => match addr.checked_sub(self.load_bias) {
+                Some(link_addr) => self.debug_data.skip_prologue(link_addr) + self.load_bias,
+                None => addr,
+            },
+        };
+        if target == addr {
+            return Ok(Some(Status::Stopped(sig, addr, StopReason::Step)));
+        }
+        Ok(Some(match self.run_to(target)? {
+            Status::Stopped(sig, stop_addr, StopReason::Breakpoint(_))
+                if stop_addr == target && self.breakpoint_at(target).is_none() =>
+            {
+                Status::Stopped(sig, target, StopReason::Step)
+            }
+            status => status,
+        }))
+    }
+
+    /// Where the function containing `addr` starts, by the debug info or symbol table of the
+    /// program or library it's in.
+    fn function_start(&self, addr: usize) -> Option<usize> {
+        let (debug_data, base) = match self.library_at(addr) {
+            Some(lib) => (lib.debug_data.as_ref()?, lib.base),
+            None => (&self.debug_data, self.load_bias),
+        };
+        let func = debug_data.get_function_containing(addr.checked_sub(base)?)?;
+        Some(func.address + base)
+    }
+
+    /// The PLT stub `addr` is in, if it's in one.
+    fn plt_entry_at(&self, addr: usize) -> Option<&PltEntry> {
+        let addr = addr.checked_sub(self.load_bias)?;
+        let end = |entry: &PltEntry| entry.address + dwarf_data::PLT_ENTRY_SIZE;
+        self.debug_data
+            .plt_entries()
+            .iter()
+            .find(|entry| entry.address <= addr && addr < end(entry))
+    }
+
+    /// Remembers that `step` went through the function at `addr` for want of line information.
+    /// A PLT stub goes by the library function it calls.
+    fn note_no_line_function(&mut self, addr: usize) {
+        let name = match self.plt_entry_at(addr) {
+            Some(entry) => entry.name.clone(),
+            None => self
+                .function_at(addr)
+                .unwrap_or_else(|| format!("{:#x}", addr)),
+        };
+        if self.no_line_functions.insert(name.clone()) {
+            self.new_no_line_functions.push(name);
+        }
+    }
+
+    /// Steps instructions from `pc` until reaching an address that belongs to another line (for
+    /// `until`, only a later one), stepping over calls, except that `step` goes into those to
+    /// functions with line information. Stops early when the function returns or a breakpoint is
+    /// reached.
+    fn step_past_line(
+        &mut self,
+        pc: usize,
+        line_number: usize,
+        (start, end): (usize, usize),
+        frame_base: usize,
+        mode: LineStep,
+    ) -> Result<Status, SessionError> {
+        self.arm_breakpoints()?;
+        loop {
+            let rsp_before = self.registers()?.rsp as usize;
+            let inferior = live_mut(&mut self.inferior)?;
+            let mut status = inferior.step_instruction()?;
+            if let Status::Exited(_) | Status::Signaled(_) = status {
+                return Ok(status);
+            }
+            let mut regs = self.registers()?;
+            let mut addr = regs.rip as usize;
+            if (addr < start || addr >= end) && (regs.rsp as usize) < rsp_before {
+                if let (LineStep::Step, Status::Stopped(sig, _, _)) = (mode, &status) {
+                    if let Some(status) = self.step_into_call(addr, *sig)? {
+                        return Ok(status);
+                    }
+                }
+                // A call: run until it returns to this frame
+                let return_address = self.read_u64(regs.rsp as usize)? as usize;
+                loop {
+                    status = self.run_to(return_address)?;
+                    match status {
+                        Status::Stopped(_, stop_addr, StopReason::Breakpoint(_))
+                            if stop_addr == return_address
+                                && self.breakpoint_at(stop_addr).is_none() =>
+                        {
+                            regs = self.registers()?;
+                            if regs.rsp as usize > rsp_before {
+                                break;
+                            }
+                        }
+                        status => return Ok(status),
+                    }
+                }
+                addr = return_address;
+            }
+            if let Status::Stopped(sig, _, _) = status {
+                if addr < start || addr >= end || regs.rsp as usize >= frame_base {
+                    // Returned from the function
+                    return Ok(Status::Stopped(sig, addr, StopReason::Step));
+                }
+                self.cover(addr)?;
+                if self.breakpoint_at(addr).is_some() {
+                    return Ok(Status::Stopped(sig, addr, StopReason::Breakpoint(addr)));
+                }
+                let new_line = self.line_at(addr).map(|line| line.number);
+                let onwards = mode != LineStep::Until || addr > pc;
+                if onwards && new_line.map_or(false, |number| number != line_number) {
+                    return Ok(Status::Stopped(sig, addr, StopReason::Step));
+                }
+            } else {
+                return Ok(status);
+            }
+        }
+    }
+
+    /// The start and end (runtime) addresses of the program's function containing `addr`.
+    fn function_bounds(&self, addr: usize) -> Option<(usize, usize)> {
+        let func = self
+            .debug_data
+            .get_function_containing(addr.checked_sub(self.load_bias)?)?;
+        let start = func.address + self.load_bias;
+        Some((start, start + func.text_length))
+    }
+
+    /// Executes a single machine instruction.
+    pub fn step_instruction(&mut self) -> Result<StopEvent, SessionError> {
+        self.arm_breakpoints()?;
+        let inferior = live_mut(&mut self.inferior)?;
+        let status = inferior.step_instruction()?;
+        if let Status::Stopped(_, addr, StopReason::Step) = status {
+            self.cover(addr)?;
+        }
+        Ok(self.finish_stop(status))
+    }
+
+    /// Returns (and forgets) the breakpoints that could not be inserted the last time the
+    /// inferior was resumed. They stay set and are retried on the next resume.
+    pub fn take_breakpoint_errors(&mut self) -> Vec<BreakpointError> {
+        std::mem::replace(&mut self.breakpoint_errors, Vec::new())
+    }
+
+    /// Returns the run time and stop counts of the current (or last) run, if there is one.
+    pub fn run_stats(&self) -> Option<RunStats> {
+        self.inferior.as_ref().map(Inferior::stats)
+    }
+
+    /// Returns (and resets) the time spent on breakpoint bookkeeping since the last call.
+    pub fn take_breakpoint_time(&mut self) -> Duration {
+        match self.inferior.as_mut() {
+            Some(inferior) => inferior.take_breakpoint_time(),
+            None => Duration::default(),
+        }
+    }
+
+    /// Arms any breakpoints that aren't in the inferior yet, remembering the ones that fail.
+    fn arm_breakpoints(&mut self) -> Result<(), SessionError> {
+        let addresses = self.wanted_breakpoints();
+        let inferior = live_mut(&mut self.inferior)?;
+        self.breakpoint_errors = inferior.arm_all(&addresses);
+        Ok(())
+    }
+
+    /// The addresses that should have a breakpoint in the inferior: the user's enabled
+    /// breakpoints, the ones that catch watched frames returning, and those the debugger keeps for
+    /// itself: on the PLT stubs and the allocator functions while tracing library calls or the
+    /// heap, on the lines coverage hasn't seen run yet, on abort and __assert_fail while catching
+    /// aborts, and on the dynamic linker's r_brk to see libraries come and go.
+    fn wanted_breakpoints(&mut self) -> Vec<usize> {
+        let stubs = if self.call_tracer.is_some() {
+            self.plt_stubs()
+        } else {
+            Vec::new()
+        };
+        let heap_entries = self.heap_entries();
+        let catch_entries = self.catch_entries();
+        if self.r_debug.is_none() {
+            self.r_debug = self.find_r_debug();
+        }
+        let r_brk = self.r_debug.map(|(_, r_brk)| r_brk);
+        let bias = self.load_bias;
+        let uncovered: Vec<usize> = match &self.coverage {
+            Some(coverage) => coverage.pending().map(|addr| addr + bias).collect(),
+            None => Vec::new(),
+        };
+        self.breakpoints
+            .armed_addresses()
+            .chain(
+                self.watchpoints
+                    .iter()
+                    .filter_map(|watch| watch.scope.map(|scope| scope.return_address)),
+            )
+            .chain(stubs)
+            .chain(heap_entries)
+            .chain(uncovered)
+            .chain(catch_entries)
+            .chain(r_brk)
+            .collect()
+    }
+
+    /// Returns true if the target file was modified since its debug info was loaded.
+    pub fn target_changed(&self) -> bool {
+        file_stamp(&self.target) != self.target_stamp
+    }
+
+    /// Replaces the target with another binary, killing the inferior if there is one. Breakpoints
+    /// are kept: those on functions and lines are resolved again in the new binary, and those on
+    /// raw addresses are left alone. If the new binary can't be loaded, nothing changes.
+    pub fn load_target(&mut self, target: &str) -> Result<TargetReload, SessionError> {
+        let debug_data = DwarfData::load(target, &self.debug_file_directory)
+            .map_err(SessionError::Symbols)?;
+        self.kill();
+        self.target = target.to_string();
+        self.target_stamp = file_stamp(target);
+        self.debug_data = Rc::new(debug_data);
+        self.load_bias = 0;
+        self.libraries.clear();
+        self.sources.clear();
+        // Coverage starts over with the new binary's lines, or goes off if it has none
+        if self.coverage.take().is_some() {
+            let _ = self.start_coverage();
+        }
+        let mut reload = TargetReload::default();
+        for idx in 0..self.breakpoints.all().len() {
+            let bp = &self.breakpoints.all()[idx];
+            if let Location::Address(_) = bp.location {
+                reload.stale.push(bp.id);
+                continue;
+            }
+            let addrs = self.resolve_all(&bp.location);
+            if addrs.is_empty() {
+                reload.pending.push(bp.id);
+            } else {
+                reload.resolved.push(bp.id);
+            }
+            self.breakpoints.all_mut()[idx].addresses = addrs;
+        }
+        Ok(reload)
+    }
+
+    /// Kills the inferior, if there is one, and any checkpoints.
+    pub fn kill(&mut self) {
+        self.pending_call = None;
+        for mut checkpoint in self.checkpoints.drain(..) {
+            checkpoint.inferior.kill();
+        }
+        if let Some(mut inferior) = self.inferior.take() {
+            inferior.kill();
+        }
+    }
+
+    /// The frames from the current instruction up to main, or to where the frame pointer chain
+    /// leaves the program's code, at most `backtrace_limit` of them.
+    pub fn backtrace(&self) -> Result<Vec<Frame>, SessionError> {
+        let inferior = live(&self.inferior)?;
+        let code: Vec<(usize, usize)> = proc_maps::read(inferior.pid())
+            .unwrap_or_default()
+            .iter()
+            .filter(|m| m.is_executable())
+            .map(|m| (m.start, m.end))
+            .collect();
+        // Without the mappings, any address may be code
+        let in_code = |addr: usize| {
+            code.is_empty() || code.iter().any(|&(start, end)| start <= addr && addr < end)
+        };
+        Ok(inferior.backtrace(|addr| self.frames_at(addr), in_code, self.backtrace_limit)?)
+    }
+
+    /// The siginfo of the signal the inferior last stopped with.
+    pub fn siginfo(&self) -> Result<SignalInfo, SessionError> {
+        let inferior = live(&self.inferior)?;
+        Ok(inferior.last_siginfo()?)
+    }
+
+    /// The selected stack frame, and its caller's if the frame pointer chain can be followed
+    /// that far, for `info frame`.
+    pub fn current_frame(&self) -> Result<(StackFrame, Option<StackFrame>), SessionError> {
+        let inferior = live(&self.inferior)?;
+        let mut frames = inferior.stack_frames()?.skip(self.selected_depth);
+        let current = frames
+            .next()
+            .ok_or(SessionError::NoFrame(self.selected_frame))??;
+        let caller = frames.next().and_then(Result::ok);
+        Ok((current, caller))
+    }
+
+    /// The number of the selected frame, as `backtrace` numbers them.
+    pub fn selected_frame(&self) -> usize {
+        self.selected_frame
+    }
+
+    /// Selects frame `level` of the backtrace, for `print` and the rest to look variables up
+    /// in, and returns it.
+    pub fn select_frame(&mut self, level: usize) -> Result<Frame, SessionError> {
+        let frames = self.backtrace()?;
+        if level >= frames.len() {
+            return Err(SessionError::NoFrame(level));
+        }
+        self.selected_depth = frames[..level].iter().filter(|frame| !frame.inlined).count();
+        self.selected_frame = level;
+        Ok(frames.into_iter().nth(level).unwrap())
+    }
+
+    /// `up`: selects the frame `count` levels further out, or the outermost if there aren't
+    /// that many.
+    pub fn frame_up(&mut self, count: usize) -> Result<Frame, SessionError> {
+        let outermost = self.backtrace()?.len().saturating_sub(1);
+        if self.selected_frame >= outermost {
+            return Err(SessionError::OutermostFrame);
+        }
+        self.select_frame((self.selected_frame + count).min(outermost))
+    }
+
+    /// `down`: selects the frame `count` levels further in, or the innermost if there aren't
+    /// that many.
+    pub fn frame_down(&mut self, count: usize) -> Result<Frame, SessionError> {
+        if self.selected_frame == 0 {
+            return Err(SessionError::InnermostFrame);
+        }
+        self.select_frame(self.selected_frame.saturating_sub(count))
+    }
+
+    fn deselect_frame(&mut self) {
+        self.selected_frame = 0;
+        self.selected_depth = 0;
+    }
+
+    /// The registers as they were in the selected frame. Walking the frame pointer chain only
+    /// recovers rip, rbp and rsp; the rest are left as the innermost frame has them.
+    fn frame_registers(&self) -> Result<libc::user_regs_struct, SessionError> {
+        let mut regs = self.registers()?;
+        if self.selected_depth == 0 {
+            return Ok(regs);
+        }
+        let inferior = live(&self.inferior)?;
+        let mut callee_cfa = None;
+        for (depth, stack_frame) in inferior.stack_frames()?.enumerate() {
+            let stack_frame = stack_frame?;
+            if depth == self.selected_depth {
+                regs.rip = stack_frame.rip as u64;
+                regs.rbp = stack_frame.rbp as u64;
+                if let Some(cfa) = callee_cfa {
+                    regs.rsp = cfa as u64;
+                }
+                return Ok(regs);
+            }
+            callee_cfa = Some(stack_frame.cfa);
+        }
+        Err(SessionError::NoFrame(self.selected_frame))
+    }
+
+    /// Reads `len` bytes of the inferior's memory starting at `addr`.
+    pub fn read_memory(&self, addr: usize, len: usize) -> Result<Vec<u8>, SessionError> {
+        let inferior = live(&self.inferior)?;
+        Ok(inferior.read_bytes(addr, len)?)
+    }
+
+    /// Searches [start, end) for `pattern`. Stops after `limit` matches.
+    pub fn find_in_memory(
+        &self,
+        start: usize,
+        end: usize,
+        pattern: &[u8],
+        limit: usize,
+    ) -> Result<MemorySearch, SessionError> {
+        let mut matches = Vec::new();
+        let mut truncated = false;
+        if pattern.is_empty() {
+            return Ok(MemorySearch::default());
+        }
+        // The end of the previous chunk, kept so that matches spanning two chunks are found
+        let mut carry: Vec<u8> = Vec::new();
+        let skipped = self.scan_memory(start, end, |chunk| {
+            let (addr, bytes, mapping) = match chunk {
+                Chunk::Data {
+                    addr,
+                    bytes,
+                    mapping,
+                } => (addr, bytes, mapping),
+                Chunk::Gap => {
+                    carry.clear();
+                    return true;
+                }
+            };
+            let base = addr - carry.len();
+            let mut window = std::mem::replace(&mut carry, Vec::new());
+            window.extend_from_slice(bytes);
+            for (offset, candidate) in window.windows(pattern.len()).enumerate() {
+                if candidate == pattern {
+                    matches.push((base + offset, mapping.to_string()));
+                    if matches.len() == limit {
+                        truncated = base + offset + pattern.len() < end;
+                        return false;
+                    }
+                }
+            }
+            let keep = (pattern.len() - 1).min(window.len());
+            carry = window.split_off(window.len() - keep);
+            true
+        })?;
+        Ok(MemorySearch {
+            matches,
+            skipped,
+            truncated,
+        })
+    }
+
+    /// Finds the runs of at least `min_length` printable ASCII characters in [start, end),
+    /// passing each to `found` with its address as soon as it ends. With `nul_terminated`, only
+    /// runs followed by a NUL byte count. Returns the parts of the range that couldn't be read.
+    pub fn strings_in_memory<F: FnMut(usize, &str)>(
+        &self,
+        start: usize,
+        end: usize,
+        min_length: usize,
+        nul_terminated: bool,
+        mut found: F,
+    ) -> Result<Vec<(usize, usize)>, SessionError> {
+        let mut run = String::new();
+        let mut run_start = start;
+        let mut finish = |run: &mut String, run_start: usize, terminated: bool| {
+            if run.len() >= min_length && (terminated || !nul_terminated) {
+                found(run_start, run);
+            }
+            run.clear();
+        };
+        let skipped = self.scan_memory(start, end, |chunk| {
+            let (addr, bytes) = match chunk {
+                Chunk::Data { addr, bytes, .. } => (addr, bytes),
+                Chunk::Gap => {
+                    finish(&mut run, run_start, false);
+                    return true;
+                }
+            };
+            for (offset, &byte) in bytes.iter().enumerate() {
+                if byte == b'\t' || (0x20..0x7f).contains(&byte) {
+                    if run.is_empty() {
+                        run_start = addr + offset;
+                    }
+                    run.push(byte as char);
+                } else {
+                    finish(&mut run, run_start, byte == 0);
+                }
+            }
+            true
+        })?;
+        finish(&mut run, run_start, false);
+        Ok(skipped)
+    }
+
+    /// The range spanned by the mappings with the given name: a path, the file name at the end
+    /// of one, or a pseudo-path like [heap] (the brackets may be left out, as in `heap`).
+    pub fn mapping_range(&self, name: &str) -> Result<Option<(usize, usize)>, SessionError> {
+        let inferior = live(&self.inferior)?;
+        let mappings = proc_maps::read(inferior.pid()).unwrap_or_default();
+        let pseudo_path = format!("[{}]", name);
+        let matching: Vec<_> = mappings
+            .iter()
+            .filter(|m| {
+                m.name == name
+                    || m.name == pseudo_path
+                    || m.path.as_ref().and_then(|path| path.file_name()) == Some(name.as_ref())
+            })
+            .collect();
+        let start = matching.iter().map(|m| m.start).min();
+        let end = matching.iter().map(|m| m.end).max();
+        Ok(start.and_then(|start| end.map(|end| (start, end))))
+    }
+
+    /// Reads the readable parts of [start, end) a page at a time, so that one unreadable page
+    /// doesn't end a scan, and passes each chunk to `visit`, which returns false to stop early.
+    /// Returns the parts of the range that aren't mapped or couldn't be read.
+    fn scan_memory<F: FnMut(Chunk) -> bool>(
+        &self,
+        start: usize,
+        end: usize,
+        mut visit: F,
+    ) -> Result<Vec<(usize, usize)>, SessionError> {
+        let inferior = live(&self.inferior)?;
+        let mappings = proc_maps::read(inferior.pid()).unwrap_or_default();
+        let mut skipped: Vec<(usize, usize)> = Vec::new();
+        let mut skip = |from: usize, to: usize| match skipped.last_mut() {
+            Some(last) if last.1 == from => last.1 = to,
+            _ => skipped.push((from, to)),
+        };
+        let mut addr = start;
+        while addr < end {
+            let mapping = match mappings.iter().find(|m| m.contains(addr) && m.is_readable()) {
+                Some(mapping) => mapping,
+                None => {
+                    // Skip ahead to the next readable mapping
+                    let next = mappings
+                        .iter()
+                        .filter(|m| m.start > addr && m.is_readable())
+                        .map(|m| m.start)
+                        .min()
+                        .unwrap_or(end)
+                        .min(end);
+                    skip(addr, next);
+                    addr = next;
+                    if !visit(Chunk::Gap) {
+                        break;
+                    }
+                    continue;
+                }
+            };
+            let len = (SCAN_CHUNK_SIZE - addr % SCAN_CHUNK_SIZE)
+                .min(mapping.end - addr)
+                .min(end - addr);
+            let keep_going = match inferior.read_bytes(addr, len) {
+                Ok(bytes) => visit(Chunk::Data {
+                    addr,
+                    bytes: &bytes,
+                    mapping: &mapping.name,
+                }),
+                Err(_) => {
+                    skip(addr, addr + len);
+                    visit(Chunk::Gap)
+                }
+            };
+            if !keep_going {
+                break;
+            }
+            addr += len;
+        }
+        Ok(skipped)
+    }
+
+    /// Reads one (little-endian) word of the inferior's memory, such as a saved return address.
+    pub fn read_u64(&self, addr: usize) -> Result<u64, SessionError> {
+        let inferior = live(&self.inferior)?;
+        Ok(inferior.read_u64(addr)?)
+    }
+
+    /// Reads a NUL-terminated string from the inferior, up to MAX_STRING_LENGTH bytes. Bytes
+    /// that aren't UTF-8 are replaced.
+    pub fn read_c_string(&self, addr: usize) -> Result<String, SessionError> {
+        let mut bytes = Vec::new();
+        let mut chunk_addr = addr;
+        while bytes.len() < MAX_STRING_LENGTH {
+            // Read up to the next 64-byte boundary, so a read never crosses into an unmapped page
+            let len = 64 - chunk_addr % 64;
+            let chunk = self.read_memory(chunk_addr, len)?;
+            match chunk.iter().position(|&b| b == 0) {
+                Some(end) => {
+                    bytes.extend_from_slice(&chunk[..end]);
+                    break;
+                }
+                None => bytes.extend_from_slice(&chunk),
+            }
+            chunk_addr += len;
+        }
+        bytes.truncate(MAX_STRING_LENGTH);
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// Evaluates an expression such as `list->head.value` in the selected frame. Also returns
+    /// warnings about questionable but valid expressions, such as an index past the end of an
+    /// array.
+    pub fn evaluate(&self, expression: &str) -> Result<(Value, Vec<String>), SessionError> {
+        if !self.is_alive() {
+            return Err(SessionError::NoProcess);
+        }
+        let expr = expr::parse(expression)?;
+        let mut warnings = Vec::new();
+        let value = expr::evaluate(&expr, self, &mut warnings)?;
+        Ok((value, warnings))
+    }
+
+    /// The type `ptype` describes: the one called `text` (`struct node`, a typedef, `int`), or
+    /// else the type of `text` as an expression. A global's type is found without a process.
+    /// None for a computed number, which has no type in the debug info.
+    pub fn type_of(&self, text: &str) -> Result<Option<TypeId>, SessionError> {
+        if let Some(type_id) = self.debug_data.find_type(text) {
+            return Ok(Some(type_id));
+        }
+        match self.evaluate(text) {
+            Ok((value, _)) => Ok(value.type_id),
+            Err(SessionError::NoProcess) => self
+                .debug_data
+                .global_variables()
+                .find(|var| var.name == text.trim())
+                .map(|var| Some(var.type_id))
+                .ok_or_else(|| EvalError::UnknownVariable(text.trim().to_string()).into()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// The type `whatis` names: as for `ptype`, except that a typedef's name is taken one step
+    /// further, to what it stands for.
+    pub fn whatis(&self, text: &str) -> Result<Option<TypeId>, SessionError> {
+        if let Some(type_id) = self.debug_data.find_type(text) {
+            if let Some(TypeKind::Typedef(Some(target))) =
+                self.debug_data.get_type(type_id).map(|ty| &ty.kind)
+            {
+                return Ok(Some(*target));
+            }
+        }
+        self.type_of(text)
+    }
+
+    /// The names of the variables of the selected frame's function, parameters included, in the
+    /// order they are declared. Empty without debug info for the function.
+    pub fn local_variables(&self) -> Result<Vec<String>, SessionError> {
+        let pc = self.frame_registers()?.rip as usize;
+        Ok(pc
+            .checked_sub(self.load_bias)
+            .and_then(|pc| self.debug_data.get_function_containing(pc))
+            .map(|func| func.variables_at(pc).map(|var| var.name.clone()).collect())
+            .unwrap_or_default())
+    }
+
+    /// `info scope`: the function containing `addr` and the variables and parameters visible
+    /// there, each with where it is kept at that address, from the debug info alone. None if
+    /// `addr` isn't in a function of the target.
+    pub fn variables_in_scope(
+        &self,
+        addr: usize,
+    ) -> Option<(&Function, Vec<(&Variable, String)>)> {
+        let addr = addr.checked_sub(self.load_bias)?;
+        let func = self.debug_data.get_function_containing(addr)?;
+        let variables = func
+            .variables_at(addr)
+            .map(|var| (var, var.describe_location(addr)))
+            .collect();
+        Some((func, variables))
+    }
+
+    /// Renders a value from `evaluate` the way `print` shows it.
+    pub fn format_value(&self, value: &Value, format: Format) -> String {
+        expr::format_value(self, value, format)
+    }
+
+    /// The canonical frame address of the innermost frame, which is what DW_OP_fbreg offsets are
+    /// relative to in gcc and clang output. Frame pointers are assumed, except on the first
+    /// instructions of a function, before `push %rbp; mov %rsp,%rbp` have run.
+    fn frame_base(&self, function_addr: usize, regs: &libc::user_regs_struct) -> usize {
+        let (rip, rsp, rbp) = (regs.rip as usize, regs.rsp as usize, regs.rbp as usize);
+        let prologue = self.read_memory(function_addr, 8).unwrap_or_default();
+        // Skip an endbr64
+        let push_addr = if prologue.starts_with(&[0xf3, 0x0f, 0x1e, 0xfa]) {
+            function_addr + 4
+        } else {
+            function_addr
+        };
+        if rip <= push_addr {
+            rsp + 8
+        } else if rip == push_addr + 1 && prologue.get(push_addr - function_addr) == Some(&0x55) {
+            rsp + 16
+        } else {
+            rbp + 16
+        }
+    }
+
+    /// Looks `name` up among the variables of the function `regs` are stopped in, then
+    /// among the globals. Returns where the variable is and its type, and for a variable that
+    /// lives in the function's frame, the (runtime) address of its function.
+    fn find_variable(
+        &self,
+        name: &str,
+        regs: &libc::user_regs_struct,
+    ) -> Option<(Storage, TypeId, Option<usize>)> {
+        let pc = (regs.rip as usize).checked_sub(self.load_bias)?;
+        if let Some(func) = self.debug_data.get_function_containing(pc) {
+            if let Some(var) = func.variable_at(name, pc) {
+                let function = match var.location {
+                    dwarf_data::Location::Address(_) => None,
+                    _ => Some(func.address + self.load_bias),
+                };
+                let storage = self.variable_storage(&var.location, Some(func), regs);
+                return Some((storage, var.type_id, function));
+            }
+        }
+        let var = self
+            .debug_data
+            .global_variables()
+            .find(|var| var.name == name)?;
+        Some((self.variable_storage(&var.location, None, regs), var.type_id, None))
+    }
+
+    /// Where a variable of `func` (None for a global) with `location` is at the current stop.
+    /// A location list with no entry for the pc means the variable has been optimized out there.
+    fn variable_storage(
+        &self,
+        location: &dwarf_data::Location,
+        func: Option<&Function>,
+        regs: &libc::user_regs_struct,
+    ) -> Storage {
+        let frame = ExprFrame::new(self, func, regs);
+        let pc = (regs.rip as usize).wrapping_sub(self.load_bias);
+        let expression = match location {
+            dwarf_data::Location::Address(addr) => return Storage::Memory(addr + self.load_bias),
+            dwarf_data::Location::FramePointerOffset(offset) => {
+                return match frame.frame_base {
+                    Some(base) => Storage::Memory((base as isize + offset) as usize),
+                    None => Storage::Unknown(ExprError::NoFrameBase.to_string()),
+                }
+            }
+            dwarf_data::Location::Expression(expression) => expression,
+            dwarf_data::Location::List(ranges) => {
+                match ranges
+                    .iter()
+                    .find(|(start, end, _)| *start <= pc && pc < *end)
+                {
+                    Some((_, _, expression)) => expression,
+                    None => return Storage::OptimizedOut,
+                }
+            }
+        };
+        match dwarf_expr::evaluate(expression, &frame) {
+            Ok(Place::Memory(addr)) => Storage::Memory(addr),
+            Ok(Place::Register(number)) => match frame.register(number) {
+                Some(value) => Storage::Value(value.to_le_bytes().to_vec()),
+                None => Storage::Unknown(ExprError::UnknownRegister(number).to_string()),
+            },
+            Ok(Place::Value(value)) => Storage::Value(value.to_le_bytes().to_vec()),
+            Ok(Place::Bytes(bytes)) => Storage::Value(bytes),
+            Err(err) => Storage::Unknown(err.to_string()),
+        }
+    }
+
+    pub fn registers(&self) -> Result<libc::user_regs_struct, SessionError> {
+        let inferior = live(&self.inferior)?;
+        Ok(inferior.getregs()?)
+    }
+
+    /// The general purpose registers of the selected frame that `info registers` lists, or the
+    /// ones in `names` (with or without a `$`), with their values.
+    pub fn register_values(&self, names: &[String]) -> Result<Vec<(String, u64)>, SessionError> {
+        let regs = self.frame_registers()?;
+        if names.is_empty() {
+            return Ok(registers::GENERAL_REGISTERS
+                .iter()
+                .filter_map(|&name| {
+                    inferior::register_value(&regs, name).map(|value| (name.to_string(), value))
+                })
+                .collect());
+        }
+        names
+            .iter()
+            .map(|name| {
+                let name = name.trim_start_matches('$');
+                inferior::register_value(&regs, name)
+                    .map(|value| (name.to_string(), value))
+                    .ok_or_else(|| EvalError::UnknownRegister(name.to_string()).into())
+            })
+            .collect()
+    }
+
+    /// The debug registers, named dr0 to dr7, while any hardware watchpoint is set.
+    pub fn debug_registers(&self) -> Result<Vec<(String, u64)>, SessionError> {
+        if self.watchpoints.is_empty() {
+            return Ok(Vec::new());
+        }
+        let inferior = live(&self.inferior)?;
+        Ok(inferior
+            .debug_registers()?
+            .into_iter()
+            .map(|(number, value)| (format!("dr{}", number), value))
+            .collect())
+    }
+
+    /// The x87 and SSE registers, for `info float` and `info vector`.
+    pub fn float_registers(&self) -> Result<libc::user_fpregs_struct, SessionError> {
+        let inferior = live(&self.inferior)?;
+        Ok(inferior.getfpregs()?)
+    }
+
+    /// The output captured from the current (or last) run: its lines, oldest first, and how many
+    /// older lines were dropped to make room. None if no run has had its output captured.
+    pub fn captured_output(&self) -> Option<(Vec<(Stream, String)>, usize)> {
+        let captured = self.captured_output.as_ref()?.lock().unwrap();
+        Some((captured.lines.iter().cloned().collect(), captured.dropped))
+    }
+
+    /// Kills the current inferior (if any) and spawns a fresh one with the stored arguments.
+    fn spawn(&mut self) -> Result<(), SessionError> {
+        self.kill();
+        self.libraries.clear();
+        self.deselect_frame();
+        if !self.cwd.is_dir() {
+            return Err(SessionError::MissingWorkingDirectory(self.cwd.clone()));
+        }
+        let mut redirects =
+            open_redirections(&self.redirections, &self.cwd).map_err(SessionError::Redirect)?;
+        if self.capture_output {
+            let captured = SharedOutput::default();
+            redirects.capture = Some(captured.clone());
+            self.captured_output = Some(captured);
+        }
+        let mut inferior = Inferior::new(
+            &self.target,
+            &self.args,
+            &self.env,
+            redirects,
+            self.use_tty,
+            &self.cwd,
+            self.disable_randomization,
+        )
+        .map_err(SessionError::Inferior)?;
+        if self.syscall_tracer.is_some() {
+            inferior.set_syscall_tracing(true)?;
+        }
+        if let Some(heap) = self.heap.as_mut() {
+            heap.reset();
+        }
+        self.assertion = None;
+        self.caught = None;
+        self.r_debug = None;
+        let old_bias = self.load_bias;
+        self.load_bias = if self.debug_data.is_position_independent() {
+            executable_base(inferior.pid()).unwrap_or(0)
+        } else {
+            0
+        };
+        self.inferior = Some(inferior);
+        self.relocate_breakpoints();
+        self.relocate_watchpoints(old_bias)?;
+        Ok(())
+    }
+
+    /// Carries watchpoints over to a new inferior. Watchpoints on globals are armed again
+    /// (moved by the change in load bias); those on locals are deleted, since their frame is
+    /// gone.
+    fn relocate_watchpoints(&mut self, old_bias: usize) -> Result<(), SessionError> {
+        self.drop_local_watchpoints();
+        let load_bias = self.load_bias;
+        let inferior = live_mut(&mut self.inferior)?;
+        for watch in self.watchpoints.iter_mut() {
+            watch.address = watch.address - old_bias + load_bias;
+            for (slot, addr, len) in watch.slots.iter_mut() {
+                *addr = *addr - old_bias + load_bias;
+                inferior.set_watchpoint(*slot, *addr, *len)?;
+            }
+            watch.value = inferior.read_bytes(watch.address, watch.value.len())?;
+        }
+        Ok(())
+    }
+
+    /// Resolves every function and line breakpoint again for a new inferior, whose load bias may
+    /// differ from the last one. Breakpoints in libraries become pending until the libraries are
+    /// loaded. Raw addresses are left as they are.
+    fn relocate_breakpoints(&mut self) {
+        for idx in 0..self.breakpoints.all().len() {
+            let bp = &self.breakpoints.all()[idx];
+            if let Location::Address(_) = bp.location {
+                continue;
+            }
+            let addrs = self.resolve_all(&bp.location);
+            if bp.addresses.is_empty() && !addrs.is_empty() {
+                self.resolved_breakpoints.push(bp.id);
+            }
+            self.breakpoints.all_mut()[idx].addresses = addrs;
+        }
+    }
+
+    /// Adds symbol information to a wait status.
+    fn stop_event(&self, status: Status) -> StopEvent {
+        match status {
+            Status::Exited(code) => StopEvent::Exited(code),
+            Status::Signaled(sig) => StopEvent::Signaled(sig),
+            Status::Stopped(sig, addr, reason) => StopEvent::Stopped {
+                signal: sig,
+                address: addr,
+                reason,
+                breakpoint: match reason {
+                    StopReason::Breakpoint(bp_addr) => self.breakpoint_at(bp_addr),
+                    _ => None,
+                },
+                function: self.function_at(addr),
+                line: self.line_at(addr),
+            },
+        }
+    }
+}
+
+/// The inferior, unless there is none or it has exited. One that has exited is kept for its run
+/// statistics, but there is no process left to control.
+fn live(inferior: &Option<Inferior>) -> Result<&Inferior, SessionError> {
+    inferior
+        .as_ref()
+        .filter(|inferior| !inferior.has_exited())
+        .ok_or(SessionError::NoProcess)
+}
+
+fn live_mut(inferior: &mut Option<Inferior>) -> Result<&mut Inferior, SessionError> {
+    inferior
+        .as_mut()
+        .filter(|inferior| !inferior.has_exited())
+        .ok_or(SessionError::NoProcess)
+}
+
+/// A file's modification time and size, or None if it can't be read.
+fn file_stamp(path: &str) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// The longest string `read_c_string` returns.
+const MAX_STRING_LENGTH: usize = 4096;
+
+/// How much memory `find` and `strings` read at a time: a page, the unit in which memory is
+/// readable or not.
+const SCAN_CHUNK_SIZE: usize = 4096;
+
+/// How many frames `backtrace` shows until `set backtrace limit` changes it.
+const DEFAULT_BACKTRACE_LIMIT: usize = 1000;
+
+/// How many instruction addresses `record` keeps (8 bytes each).
+const RECORD_LIMIT: usize = 1 << 18;
+
+/// How many integer arguments `call` can pass: those that go in rdi, rsi, rdx, rcx, r8 and r9.
+const ARGUMENT_REGISTERS: usize = 6;
+
+/// How long `profile` lets the inferior run between samples.
+const PROFILE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How many characters of a string argument `set trace-calls` shows.
+const CALL_STRING_LIMIT: usize = 32;
+
+/// Where `catch panic` stops, the first of them the program has. rust_panic is called once the
+/// panic hook has printed its message and before unwinding begins; panic_fmt is earlier, but is
+/// there in builds where rust_panic has been inlined away.
+const PANIC_FUNCTIONS: &[&str] =
+    &["rust_panic", "std::panicking::rust_panic", "core::panicking::panic_fmt"];
+
+/// The most entries of the program's dynamic section looked through for DT_DEBUG.
+const DYNAMIC_ENTRY_LIMIT: usize = 64;
+
+/// Dynamic section tags: the end of the section, and the entry the dynamic linker points at its
+/// r_debug.
+const DT_NULL: u64 = 0;
+const DT_DEBUG: u64 = 21;
+
+/// Offsets into struct r_debug of r_brk and r_state, and the r_state that means the list of
+/// loaded objects isn't being changed.
+const R_BRK_OFFSET: usize = 16;
+const R_STATE_OFFSET: usize = 24;
+const RT_CONSISTENT: u32 = 0;
+
+/// Functions only a Rust program has, whose presence suggests `catch panic`.
+const RUST_MARKERS: &[&str] = &["rust_begin_unwind", "rust_panic", "__rust_start_panic"];
+
+/// Splits `len` bytes at `addr` into the naturally aligned 1, 2, 4 and 8 byte pieces that debug
+/// registers can watch.
+fn watch_ranges(mut addr: usize, len: usize) -> Vec<(usize, usize)> {
+    let end = addr + len;
+    let mut ranges = Vec::new();
+    while addr < end {
+        let size = [8, 4, 2, 1]
+            .iter()
+            .copied()
+            .find(|size| addr % size == 0 && addr + size <= end)
+            .unwrap();
+        ranges.push((addr, size));
+        addr += size;
+    }
+    ranges
+}
+
+/// Returns the address the program's own binary is mapped at (the start of its mapping at file
+/// offset 0), which is the load bias for a PIE.
+fn executable_base(pid: nix::unistd::Pid) -> Option<usize> {
+    let exe = fs::read_link(format!("/proc/{}/exe", pid)).ok()?;
+    proc_maps::read(pid)
+        .ok()?
+        .iter()
+        .find(|m| m.offset == 0 && m.path.as_ref() == Some(&exe))
+        .map(|m| m.start)
+}
+
+/// Opens the files named by `redirections`, resolving relative paths against the inferior's
+/// working directory.
+fn open_redirections(redirections: &[Redirection], cwd: &Path) -> Result<Redirects, String> {
+    let mut redirects = Redirects::default();
+    for redirection in redirections {
+        let (path, opened) = match redirection {
+            Redirection::Stdin(path) => (path, File::open(cwd.join(path))),
+            Redirection::Stdout(path) | Redirection::Stderr(path) => {
+                (path, File::create(cwd.join(path)))
+            }
+            Redirection::AppendStdout(path) => {
+                (path, OpenOptions::new().append(true).create(true).open(cwd.join(path)))
+            }
+            Redirection::StderrToStdout => {
+                // Not redirected (yet), standard output is the debugger's, and so is stderr
+                redirects.stderr = match &redirects.stdout {
+                    Some(file) => Some(file.try_clone().map_err(|err| format!("2>&1: {}", err))?),
+                    None => None,
+                };
+                continue;
+            }
+        };
+        let file = opened.map_err(|err| format!("{}: {}", path, err))?;
+        match redirection {
+            Redirection::Stdin(_) => redirects.stdin = Some(file),
+            Redirection::Stderr(_) => redirects.stderr = Some(file),
+            _ => redirects.stdout = Some(file),
+        }
+    }
+    Ok(redirects)
+}
+
+impl expr::Context for Session {
+    fn debug_data(&self) -> &DwarfData {
+        &self.debug_data
+    }
+
+    fn variable(&self, name: &str) -> Option<(Storage, TypeId)> {
+        let regs = self.frame_registers().ok()?;
+        self.find_variable(name, &regs)
+            .map(|(storage, type_id, _)| (storage, type_id))
+    }
+
+    fn register(&self, name: &str) -> Option<u64> {
+        inferior::register_value(&self.frame_registers().ok()?, name)
+    }
+
+    fn vector_register(&self, name: &str) -> Option<[u8; 16]> {
+        let number = registers::xmm_number(name)?;
+        Some(registers::xmm(&self.float_registers().ok()?, number))
+    }
+
+    fn read_memory(&self, addr: usize, len: usize) -> Option<Vec<u8>> {
+        Session::read_memory(self, addr, len).ok()
+    }
+
+    fn history(&self, number: Option<usize>) -> Option<Value> {
+        match number.filter(|&number| number > 0) {
+            Some(number) => self.values.get(number - 1).cloned(),
+            None => self.values.last().cloned(),
+        }
+    }
+}
+
+/// The innermost frame, as location expressions see it. The frame base is the function's
+/// DW_AT_frame_base, evaluated once, or the CFA for a function without one.
+struct ExprFrame<'a> {
+    session: &'a Session,
+    regs: &'a libc::user_regs_struct,
+    cfa: Option<u64>,
+    frame_base: Option<u64>,
+}
+
+impl<'a> ExprFrame<'a> {
+    fn new(
+        session: &'a Session,
+        func: Option<&Function>,
+        regs: &'a libc::user_regs_struct,
+    ) -> ExprFrame<'a> {
+        let cfa =
+            func.map(|func| session.frame_base(func.address + session.load_bias, regs) as u64);
+        let mut frame = ExprFrame {
+            session,
+            regs,
+            cfa,
+            frame_base: cfa,
+        };
+        if let Some(expression) = func.and_then(|func| func.frame_base.as_ref()) {
+            frame.frame_base = match dwarf_expr::evaluate(expression, &frame) {
+                Ok(Place::Memory(addr)) => Some(addr as u64),
+                Ok(Place::Register(number)) => frame.register(number),
+                Ok(Place::Value(value)) => Some(value),
+                _ => None,
+            };
+        }
+        frame
+    }
+}
+
+impl<'a> dwarf_expr::Frame for ExprFrame<'a> {
+    fn register(&self, number: u16) -> Option<u64> {
+        inferior::register_value(self.regs, dwarf_expr::register_name(number)?)
+    }
+
+    fn frame_base(&self) -> Option<u64> {
+        self.frame_base
+    }
+
+    fn cfa(&self) -> Option<u64> {
+        self.cfa
+    }
+
+    fn read_memory(&self, addr: usize, len: usize) -> Option<Vec<u8>> {
+        self.session.read_memory(addr, len).ok()
+    }
+
+    fn load_bias(&self) -> u64 {
+        self.session.load_bias as u64
+    }
+}
@@ -0,0 +1,212 @@
+//! Shared libraries loaded into the inferior, with their debug info. Addresses in a library's
+//! DWARF are relative to the address it was loaded at, so every lookup applies the library's base.
+
+use crate::dwarf_data::{DebugInfo, DwarfData, Line};
+use crate::proc_maps::Mapping;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+pub struct SharedLibrary {
+    pub path: PathBuf,
+    /// Where the start of the file is mapped; added to addresses from the library's debug info
+    pub base: usize,
+    /// Address ranges of the library's executable mappings
+    pub code: Vec<(usize, usize)>,
+    /// None if the library has no debug info we can read
+    pub debug_data: Option<Rc<DwarfData>>,
+}
+
+impl SharedLibrary {
+    /// Builds a library from its entries in the process's mappings and loads its debug info.
+    fn load(path: PathBuf, mappings: &[&Mapping]) -> Option<SharedLibrary> {
+        let base = mappings.iter().find(|m| m.offset == 0)?.start;
+        let code = mappings
+            .iter()
+            .filter(|m| m.is_executable())
+            .map(|m| (m.start, m.end))
+            .collect();
+        let debug_data = DwarfData::from_file(&path.to_string_lossy()).ok().map(Rc::new);
+        Some(SharedLibrary {
+            path,
+            base,
+            code,
+            debug_data,
+        })
+    }
+
+    /// Loads the library's debug info again, looking in `debug_file_directory` for a separate
+    /// debug file too. Returns what was found.
+    pub fn reload_symbols(&mut self, debug_file_directory: &str) -> DebugInfo {
+        self.debug_data = DwarfData::load(&self.path.to_string_lossy(), debug_file_directory)
+            .ok()
+            .map(Rc::new);
+        self.debug_info()
+    }
+
+    /// How much debug info the library has.
+    pub fn debug_info(&self) -> DebugInfo {
+        self.debug_data
+            .as_ref()
+            .map_or(DebugInfo::Nothing, |debug_data| debug_data.debug_info())
+    }
+
+    /// The start of the library's first executable mapping and the end of its last.
+    pub fn text_range(&self) -> Option<(usize, usize)> {
+        let start = self.code.iter().map(|(start, _)| *start).min()?;
+        let end = self.code.iter().map(|(_, end)| *end).max()?;
+        Some((start, end))
+    }
+
+    /// Returns true if `addr` is in this library's code.
+    pub fn contains(&self, addr: usize) -> bool {
+        self.code.iter().any(|(start, end)| *start <= addr && addr < *end)
+    }
+
+    pub fn get_function_from_addr(&self, addr: usize) -> Option<String> {
+        self.debug_data
+            .as_ref()?
+            .get_function_from_addr(addr.checked_sub(self.base)?)
+    }
+
+    pub fn get_addrs_for_function(&self, name: &str) -> Vec<usize> {
+        match &self.debug_data {
+            Some(debug_data) => debug_data
+                .get_addrs_for_function(name)
+                .into_iter()
+                .map(|offset| self.base + offset)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// The address past the prologue of the function starting at `addr`, as
+    /// DwarfData::skip_prologue.
+    pub fn skip_prologue(&self, addr: usize) -> usize {
+        match (&self.debug_data, addr.checked_sub(self.base)) {
+            (Some(debug_data), Some(offset)) => self.base + debug_data.skip_prologue(offset),
+            _ => addr,
+        }
+    }
+
+    pub fn get_frames_from_addr(&self, addr: usize) -> Vec<(Option<String>, Option<Line>)> {
+        let (debug_data, offset) = match (&self.debug_data, addr.checked_sub(self.base)) {
+            (Some(debug_data), Some(offset)) => (debug_data, offset),
+            _ => return Vec::new(),
+        };
+        let mut frames = debug_data.get_frames_from_addr(offset);
+        for (_, line) in frames.iter_mut() {
+            if let Some(line) = line {
+                line.address = addr;
+            }
+        }
+        frames
+    }
+
+    pub fn get_line_from_addr(&self, addr: usize) -> Option<Line> {
+        let mut line = self
+            .debug_data
+            .as_ref()?
+            .get_line_from_addr(addr.checked_sub(self.base)?)?;
+        line.address = addr;
+        Some(line)
+    }
+}
+
+/// What changed in a rescan of the libraries.
+#[derive(Default)]
+pub struct LibraryChanges {
+    /// The paths of the libraries loaded for the first time
+    pub loaded: Vec<PathBuf>,
+    /// The libraries no longer mapped
+    pub unloaded: Vec<SharedLibrary>,
+}
+
+impl LibraryChanges {
+    pub fn is_empty(&self) -> bool {
+        self.loaded.is_empty() && self.unloaded.is_empty()
+    }
+}
+
+/// The shared libraries loaded into a process, in the order they were first seen, kept up to
+/// date from its mappings.
+#[derive(Default)]
+pub struct LoadedLibraries {
+    libraries: Vec<SharedLibrary>,
+}
+
+impl LoadedLibraries {
+    /// Rescans the process's mappings for every mapped file with executable code, other than the
+    /// program itself. Libraries still mapped at the same base are kept where they were, rather
+    /// than reloaded; new ones are loaded and go at the end. Returns the libraries loaded and
+    /// unloaded since the last rescan.
+    pub fn refresh(&mut self, mappings: &[Mapping], exe: Option<&PathBuf>) -> LibraryChanges {
+        let mut paths: Vec<&PathBuf> = Vec::new();
+        for mapping in mappings {
+            if let Some(path) = &mapping.path {
+                if mapping.is_executable() && Some(path) != exe && !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+        }
+        let mut kept = vec![false; self.libraries.len()];
+        let mut loaded = Vec::new();
+        for path in paths {
+            let entries: Vec<&Mapping> = mappings
+                .iter()
+                .filter(|m| m.path.as_ref() == Some(path))
+                .collect();
+            let base = entries.iter().find(|m| m.offset == 0).map(|m| m.start);
+            if let Some(idx) = self
+                .libraries
+                .iter()
+                .position(|lib| &lib.path == path && Some(lib.base) == base)
+            {
+                kept[idx] = true;
+                continue;
+            }
+            if let Some(library) = SharedLibrary::load(path.clone(), &entries) {
+                loaded.push(library);
+            }
+        }
+        let mut changes = LibraryChanges::default();
+        for (lib, kept) in std::mem::replace(&mut self.libraries, Vec::new())
+            .into_iter()
+            .zip(kept)
+        {
+            if kept {
+                self.libraries.push(lib);
+            } else {
+                changes.unloaded.push(lib);
+            }
+        }
+        changes.loaded = loaded.iter().map(|lib| lib.path.clone()).collect();
+        self.libraries.extend(loaded);
+        changes
+    }
+
+    /// Forgets the libraries of a process that is gone.
+    pub fn clear(&mut self) {
+        self.libraries.clear();
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, SharedLibrary> {
+        self.libraries.iter()
+    }
+
+    pub fn all(&self) -> &[SharedLibrary] {
+        &self.libraries
+    }
+
+    /// The library whose code contains `addr`.
+    pub fn containing(&self, addr: usize) -> Option<&SharedLibrary> {
+        self.libraries.iter().find(|lib| lib.contains(addr))
+    }
+
+    /// The libraries whose path satisfies `predicate`, to load symbols for.
+    pub fn matching_mut<P: Fn(&Path) -> bool>(
+        &mut self,
+        predicate: P,
+    ) -> impl Iterator<Item = &mut SharedLibrary> {
+        self.libraries.iter_mut().filter(move |lib| predicate(&lib.path))
+    }
+}
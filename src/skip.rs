@@ -0,0 +1,139 @@
+//! `skip`: functions and files that `step` goes over rather than into, such as logging helpers
+//! called from everywhere or code vendored from elsewhere.
+
+use crate::dwarf_data;
+
+/// What a `skip` entry keeps `step` out of.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SkipTarget {
+    /// Functions whose names match a pattern, in which `*` matches any run of characters and `?`
+    /// any one character
+    Function(String),
+    /// The functions in a source file, named by its path or (a bare name) in any directory
+    File(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skip {
+    pub id: usize,
+    pub target: SkipTarget,
+}
+
+/// The `skip` list: functions and files that `step` steps over calls into, as `next` would.
+#[derive(Default)]
+pub struct SkipList {
+    skips: Vec<Skip>,
+    last_id: usize,
+}
+
+impl SkipList {
+    /// Adds an entry, returning its number.
+    pub fn add(&mut self, target: SkipTarget) -> usize {
+        self.last_id += 1;
+        self.skips.push(Skip { id: self.last_id, target });
+        self.last_id
+    }
+
+    /// Removes entry `id`. Returns false if there was none.
+    pub fn delete(&mut self, id: usize) -> bool {
+        let count = self.skips.len();
+        self.skips.retain(|skip| skip.id != id);
+        self.skips.len() != count
+    }
+
+    pub fn clear(&mut self) {
+        self.skips.clear();
+    }
+
+    pub fn skips(&self) -> &[Skip] {
+        &self.skips
+    }
+
+    /// Whether `step` should step over a call into `function`, whose code is in `file`.
+    pub fn matches(&self, function: Option<&str>, file: Option<&str>) -> bool {
+        self.skips.iter().any(|skip| match (&skip.target, function, file) {
+            (SkipTarget::Function(pattern), Some(function), _) => glob_matches(pattern, function),
+            (SkipTarget::File(name), _, Some(file)) => dwarf_data::path_matches(file, name),
+            _ => false,
+        })
+    }
+}
+
+/// Whether `text` matches `pattern`, in which `*` matches any run of characters and `?` any one.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    // Where the last `*` was, and the text position it's now taken to match up to, to backtrack
+    // to when the rest of the pattern fails to match
+    let mut star: Option<(usize, usize)> = None;
+    let (mut p, mut t) = (0, 0);
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(&'*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, t));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs_match_whole_names() {
+        assert!(glob_matches("log_msg", "log_msg"));
+        assert!(!glob_matches("log", "log_msg"));
+        assert!(!glob_matches("log_msg", "log"));
+        assert!(glob_matches("log_*", "log_msg"));
+        assert!(glob_matches("log_*", "log_"));
+        assert!(glob_matches("*_msg", "log_msg"));
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("l?g_m*e", "log_message"));
+        assert!(!glob_matches("l?g", "lg"));
+        assert!(glob_matches("*a*b", "xaxab"));
+        assert!(!glob_matches("*a*b", "xaxba"));
+        assert!(glob_matches("std::*::push_back", "std::vector<int>::push_back"));
+    }
+
+    #[test]
+    fn entries_match_by_function_or_file() {
+        let mut skips = SkipList::default();
+        assert!(!skips.matches(Some("log_msg"), Some("/src/vendor.c")));
+        skips.add(SkipTarget::Function("log_*".to_string()));
+        skips.add(SkipTarget::File("vendor.c".to_string()));
+        assert!(skips.matches(Some("log_msg"), Some("/src/main.c")));
+        assert!(skips.matches(Some("parse"), Some("/src/vendor.c")));
+        assert!(skips.matches(None, Some("vendor.c")));
+        assert!(!skips.matches(Some("parse"), Some("/src/my_vendor.c")));
+        assert!(!skips.matches(Some("main"), None));
+    }
+
+    #[test]
+    fn deleted_numbers_are_not_reused() {
+        let mut skips = SkipList::default();
+        assert_eq!(skips.add(SkipTarget::Function("a".to_string())), 1);
+        assert_eq!(skips.add(SkipTarget::Function("b".to_string())), 2);
+        assert!(skips.delete(2));
+        assert!(!skips.delete(2));
+        assert_eq!(skips.add(SkipTarget::File("c.c".to_string())), 3);
+        let ids: Vec<usize> = skips.skips().iter().map(|skip| skip.id).collect();
+        assert_eq!(ids, vec![1, 3]);
+        skips.clear();
+        assert!(skips.skips().is_empty());
+    }
+}
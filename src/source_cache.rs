@@ -0,0 +1,292 @@
+//! The contents of source files, read once and kept for showing lines at stops, and the rules
+//! that say where to find them.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Default)]
+pub struct SourceCache {
+    /// The lines of each file read so far, or None if it couldn't be read
+    files: HashMap<PathBuf, Option<Vec<String>>>,
+}
+
+impl SourceCache {
+    /// The lines of a file, reading it the first time it is asked for. None if it can't be read.
+    pub fn lines(&mut self, path: &Path) -> Option<&[String]> {
+        self.files
+            .entry(path.to_path_buf())
+            .or_insert_with(|| {
+                let bytes = fs::read(path).ok()?;
+                Some(
+                    String::from_utf8_lossy(&bytes)
+                        .lines()
+                        .map(str::to_string)
+                        .collect(),
+                )
+            })
+            .as_deref()
+    }
+
+    /// Line `number` (counting from 1) of a file, if the file can be read and is that long.
+    pub fn line(&mut self, path: &Path, number: usize) -> Option<&str> {
+        let lines = self.lines(path)?;
+        lines.get(number.checked_sub(1)?).map(String::as_str)
+    }
+
+    /// Forgets every file, so they are read again (after the program was rebuilt, say).
+    pub fn clear(&mut self) {
+        self.files.clear();
+    }
+}
+
+/// `set substitute-path` rules, for sources built somewhere other than where they are now: a path
+/// recorded in the debug info that starts with a rule's `from` directory is read from under its
+/// `to` directory instead.
+#[derive(Default)]
+pub struct PathSubstitutions {
+    /// (from, to), in the order they were set
+    rules: Vec<(PathBuf, PathBuf)>,
+}
+
+impl PathSubstitutions {
+    /// Adds a rule, or changes where an existing rule for `from` points.
+    pub fn set(&mut self, from: &str, to: &str) {
+        let (from, to) = (PathBuf::from(from), PathBuf::from(to));
+        match self.rules.iter_mut().find(|(existing, _)| *existing == from) {
+            Some(rule) => rule.1 = to,
+            None => self.rules.push((from, to)),
+        }
+    }
+
+    /// Removes the rule for `from`. Returns false if there was none.
+    pub fn remove(&mut self, from: &str) -> bool {
+        let count = self.rules.len();
+        self.rules.retain(|(existing, _)| existing != Path::new(from));
+        self.rules.len() != count
+    }
+
+    pub fn clear(&mut self) {
+        self.rules.clear();
+    }
+
+    pub fn rules(&self) -> &[(PathBuf, PathBuf)] {
+        &self.rules
+    }
+
+    /// Where to read `path` from. The rule with the longest `from` that is a prefix of the path
+    /// (whole components only, so `/build/a` doesn't match `/build/abc`) applies; of rules as long
+    /// as each other, the first one set. A path no rule matches is left as it is.
+    pub fn apply(&self, path: &Path) -> PathBuf {
+        let mut best: Option<(usize, &Path, &Path)> = None;
+        for (from, to) in &self.rules {
+            if let Ok(rest) = path.strip_prefix(from) {
+                let length = from.components().count();
+                if best.map_or(true, |(best_length, _, _)| length > best_length) {
+                    best = Some((length, to, rest));
+                }
+            }
+        }
+        match best {
+            Some((_, to, rest)) if rest.as_os_str().is_empty() => to.to_path_buf(),
+            Some((_, to, rest)) => to.join(rest),
+            None => path.to_path_buf(),
+        }
+    }
+}
+
+/// Where to read a source file named in the debug info from. The recorded path (taken relative to
+/// the compilation directory if it isn't absolute) is tried first; if it doesn't exist, the file
+/// is looked for in each of `directories` (`directory`), then the compilation directory, then the
+/// current directory, first by its recorded relative path and then by its base name. Substitution
+/// rules apply to every candidate. `exists` says whether a path is there, which keeps this free of
+/// the file system. If nothing is found, the recorded path is returned so that errors name it.
+pub fn find_source<F: Fn(&Path) -> bool>(
+    file: &str,
+    comp_dir: Option<&str>,
+    substitutions: &PathSubstitutions,
+    directories: &[PathBuf],
+    exists: F,
+) -> PathBuf {
+    let file = Path::new(file);
+    let recorded = match comp_dir {
+        Some(dir) if file.is_relative() => Path::new(dir).join(file),
+        _ => file.to_path_buf(),
+    };
+    let recorded = substitutions.apply(&recorded);
+    if exists(&recorded) {
+        return recorded;
+    }
+    let mut names = Vec::new();
+    if file.is_relative() {
+        names.push(file);
+    }
+    if let Some(base) = file.file_name() {
+        names.push(Path::new(base));
+    }
+    let search = directories
+        .iter()
+        .map(PathBuf::as_path)
+        .chain(comp_dir.map(Path::new))
+        .chain(std::iter::once(Path::new("")));
+    for dir in search {
+        for name in &names {
+            let candidate = substitutions.apply(&dir.join(name));
+            if exists(&candidate) {
+                return candidate;
+            }
+        }
+    }
+    recorded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn substitutions(rules: &[(&str, &str)]) -> PathSubstitutions {
+        let mut substitutions = PathSubstitutions::default();
+        for (from, to) in rules {
+            substitutions.set(from, to);
+        }
+        substitutions
+    }
+
+    #[test]
+    fn substitution_rules() {
+        let rules = substitutions(&[("/build", "/src"), ("/build/lib", "/lib"), ("/a", "/b")]);
+        assert_eq!(rules.apply(Path::new("/build/main.c")), Path::new("/src/main.c"));
+        // The longest match wins, whatever the order the rules were set in
+        assert_eq!(rules.apply(Path::new("/build/lib/x.c")), Path::new("/lib/x.c"));
+        // Whole components only
+        assert_eq!(rules.apply(Path::new("/abc/x.c")), Path::new("/abc/x.c"));
+        assert_eq!(rules.apply(Path::new("/a")), Path::new("/b"));
+        assert_eq!(rules.apply(Path::new("rel/x.c")), Path::new("rel/x.c"));
+        // Setting a directory again, however it's spelled, changes its rule
+        let mut rules = substitutions(&[("/build", "/one"), ("/build/", "/two")]);
+        assert_eq!(rules.rules().len(), 1);
+        assert_eq!(rules.apply(Path::new("/build/x.c")), Path::new("/two/x.c"));
+        assert!(rules.remove("/build"));
+        assert!(!rules.remove("/build"));
+        assert_eq!(rules.apply(Path::new("/build/x.c")), Path::new("/build/x.c"));
+    }
+
+    #[test]
+    fn finding_sources() {
+        struct Case {
+            file: &'static str,
+            comp_dir: Option<&'static str>,
+            rules: &'static [(&'static str, &'static str)],
+            directories: &'static [&'static str],
+            existing: &'static [&'static str],
+            expected: &'static str,
+        }
+        let cases = [
+            // Where the debug info says
+            Case {
+                file: "/build/src/a.c",
+                comp_dir: Some("/build"),
+                rules: &[],
+                directories: &["/dir"],
+                existing: &["/build/src/a.c", "/dir/a.c"],
+                expected: "/build/src/a.c",
+            },
+            Case {
+                file: "src/a.c",
+                comp_dir: Some("/build"),
+                rules: &[],
+                directories: &["/dir"],
+                existing: &["/build/src/a.c", "/dir/src/a.c"],
+                expected: "/build/src/a.c",
+            },
+            // Moved: the directories in order, by relative path and then by base name
+            Case {
+                file: "src/a.c",
+                comp_dir: Some("/build"),
+                rules: &[],
+                directories: &["/one", "/two"],
+                existing: &["/one/a.c", "/two/src/a.c"],
+                expected: "/one/a.c",
+            },
+            Case {
+                file: "src/a.c",
+                comp_dir: Some("/build"),
+                rules: &[],
+                directories: &["/one"],
+                existing: &["/one/a.c", "/one/src/a.c"],
+                expected: "/one/src/a.c",
+            },
+            // An absolute path is only looked for by its base name
+            Case {
+                file: "/old/src/a.c",
+                comp_dir: Some("/old"),
+                rules: &[],
+                directories: &["/new"],
+                existing: &["/new/old/src/a.c", "/new/a.c"],
+                expected: "/new/a.c",
+            },
+            // Then the compilation directory, then the current directory
+            Case {
+                file: "src/a.c",
+                comp_dir: Some("/build"),
+                rules: &[],
+                directories: &["/dir"],
+                existing: &["/build/a.c", "a.c"],
+                expected: "/build/a.c",
+            },
+            Case {
+                file: "src/a.c",
+                comp_dir: Some("/build"),
+                rules: &[],
+                directories: &[],
+                existing: &["src/a.c", "a.c"],
+                expected: "src/a.c",
+            },
+            Case {
+                file: "a.c",
+                comp_dir: None,
+                rules: &[],
+                directories: &[],
+                existing: &["a.c"],
+                expected: "a.c",
+            },
+            // Substitutions apply to the recorded path and to every candidate
+            Case {
+                file: "src/a.c",
+                comp_dir: Some("/build"),
+                rules: &[("/build", "/home/me/project")],
+                directories: &[],
+                existing: &["/home/me/project/src/a.c"],
+                expected: "/home/me/project/src/a.c",
+            },
+            Case {
+                file: "/build/src/a.c",
+                comp_dir: Some("/build"),
+                rules: &[("/mnt", "/media")],
+                directories: &["/mnt/src"],
+                existing: &["/media/src/a.c"],
+                expected: "/media/src/a.c",
+            },
+            // Nowhere: the recorded path, substituted, for errors to name
+            Case {
+                file: "src/a.c",
+                comp_dir: Some("/build"),
+                rules: &[("/build", "/moved")],
+                directories: &["/dir"],
+                existing: &[],
+                expected: "/moved/src/a.c",
+            },
+        ];
+        for (idx, case) in cases.iter().enumerate() {
+            let directories: Vec<PathBuf> = case.directories.iter().map(PathBuf::from).collect();
+            let found = find_source(
+                case.file,
+                case.comp_dir,
+                &substitutions(case.rules),
+                &directories,
+                |path| case.existing.iter().any(|existing| Path::new(existing) == path),
+            );
+            assert_eq!(found, Path::new(case.expected), "case {} ({})", idx, case.file);
+        }
+    }
+}
@@ -0,0 +1,324 @@
+//! `set trace-syscalls`: names for x86-64 system call numbers, and decoding of the arguments
+//! and results of the common ones, strace-style.
+
+use nix::errno::Errno;
+
+/// How to show one argument of a system call.
+#[derive(Clone, Copy)]
+enum Arg {
+    /// A signed number
+    Int,
+    /// An address, or a number best read in hex
+    Hex,
+    /// File permission bits, in octal
+    Mode,
+    /// A file descriptor, with AT_FDCWD by name
+    Fd,
+    /// A NUL-terminated path
+    Path,
+    /// Data the program hands to the kernel, as long as the argument at this index says
+    Input(usize),
+    /// A buffer the kernel fills in, as long as the result says
+    Output,
+    /// The flags of open and openat
+    OpenFlags,
+    /// The protection of mmap and mprotect
+    Prot,
+    /// The flags of mmap
+    MapFlags,
+}
+
+struct Syscall {
+    number: u64,
+    name: &'static str,
+    args: &'static [Arg],
+    /// Whether the result is an address (shown in hex) rather than a count or a descriptor
+    returns_address: bool,
+}
+
+/// Builds the table from lines like `9 => mmap(Hex, Int, ...) -> address;`.
+macro_rules! syscalls {
+    (@returns address) => { true };
+    (@returns) => { false };
+    ($($number:literal => $name:ident($($arg:expr),*) $(-> $ret:ident)?;)*) => {
+        &[$(Syscall {
+            number: $number,
+            name: stringify!($name),
+            args: &[$($arg),*],
+            returns_address: syscalls!(@returns $($ret)?),
+        },)*]
+    };
+}
+
+use Arg::*;
+
+/// The system calls we know by name. Anything else is shown by number with six raw arguments.
+const SYSCALLS: &[Syscall] = syscalls! {
+    0 => read(Fd, Output, Int);
+    1 => write(Fd, Input(2), Int);
+    2 => open(Path, OpenFlags, Mode);
+    3 => close(Fd);
+    4 => stat(Path, Hex);
+    5 => fstat(Fd, Hex);
+    6 => lstat(Path, Hex);
+    7 => poll(Hex, Int, Int);
+    8 => lseek(Fd, Int, Int);
+    9 => mmap(Hex, Int, Prot, MapFlags, Fd, Hex) -> address;
+    10 => mprotect(Hex, Int, Prot);
+    11 => munmap(Hex, Int);
+    12 => brk(Hex) -> address;
+    13 => rt_sigaction(Int, Hex, Hex, Int);
+    14 => rt_sigprocmask(Int, Hex, Hex, Int);
+    16 => ioctl(Fd, Hex, Hex);
+    17 => pread64(Fd, Output, Int, Int);
+    18 => pwrite64(Fd, Input(2), Int, Int);
+    20 => writev(Fd, Hex, Int);
+    21 => access(Path, Int);
+    22 => pipe(Hex);
+    23 => select(Int, Hex, Hex, Hex, Hex);
+    24 => sched_yield();
+    32 => dup(Fd);
+    33 => dup2(Fd, Fd);
+    35 => nanosleep(Hex, Hex);
+    39 => getpid();
+    41 => socket(Int, Int, Int);
+    42 => connect(Fd, Hex, Int);
+    56 => clone(Hex, Hex, Hex, Hex, Hex);
+    57 => fork();
+    59 => execve(Path, Hex, Hex);
+    60 => exit(Int);
+    61 => wait4(Int, Hex, Int, Hex);
+    62 => kill(Int, Int);
+    72 => fcntl(Fd, Int, Hex);
+    79 => getcwd(Hex, Int);
+    80 => chdir(Path);
+    82 => rename(Path, Path);
+    83 => mkdir(Path, Mode);
+    84 => rmdir(Path);
+    87 => unlink(Path);
+    89 => readlink(Path, Hex, Int);
+    96 => gettimeofday(Hex, Hex);
+    102 => getuid();
+    158 => arch_prctl(Hex, Hex);
+    186 => gettid();
+    202 => futex(Hex, Int, Int, Hex, Hex, Int);
+    217 => getdents64(Fd, Hex, Int);
+    218 => set_tid_address(Hex);
+    228 => clock_gettime(Int, Hex);
+    230 => clock_nanosleep(Int, Int, Hex, Hex);
+    231 => exit_group(Int);
+    257 => openat(Fd, Path, OpenFlags, Mode);
+    262 => newfstatat(Fd, Path, Hex, Hex);
+    273 => set_robust_list(Hex, Int);
+    293 => pipe2(Hex, Hex);
+    302 => prlimit64(Int, Int, Hex, Hex);
+    318 => getrandom(Hex, Int, Hex);
+    332 => statx(Fd, Path, Hex, Hex, Hex);
+    334 => rseq(Hex, Int, Hex, Hex);
+};
+
+/// How many bytes of a buffer are shown before it is cut off with `...`.
+const BUFFER_LIMIT: usize = 32;
+/// The longest path read from the inferior.
+const PATH_LIMIT: usize = 4096;
+
+/// One traced system call, ready to show.
+pub struct SyscallTrace {
+    pub number: u64,
+    pub name: String,
+    pub arguments: Vec<String>,
+    /// The result, decoded; None for a call that doesn't return (exit and exit_group), which is
+    /// reported as it is made
+    pub result: Option<String>,
+}
+
+/// Whether the call never returns to the program, so has to be reported on entry.
+pub fn never_returns(number: u64) -> bool {
+    number == libc::SYS_exit as u64 || number == libc::SYS_exit_group as u64
+}
+
+/// Decodes a system call from its number, its six argument registers and its result (None on
+/// entry). `read` reads `len` bytes of the inferior's memory at an address.
+pub fn describe<R>(number: u64, args: &[u64; 6], result: Option<u64>, read: R) -> SyscallTrace
+where
+    R: Fn(usize, usize) -> Option<Vec<u8>>,
+{
+    let syscall = SYSCALLS.iter().find(|syscall| syscall.number == number);
+    let (name, arguments) = match syscall {
+        Some(syscall) => (
+            syscall.name.to_string(),
+            syscall
+                .args
+                .iter()
+                .zip(args.iter())
+                .map(|(kind, &value)| argument(*kind, value, args, result, &read))
+                .collect(),
+        ),
+        None => (
+            format!("syscall_{}", number),
+            args.iter().map(|value| format!("{:#x}", value)).collect(),
+        ),
+    };
+    let returns_address = syscall.map_or(false, |syscall| syscall.returns_address);
+    SyscallTrace {
+        number,
+        name,
+        arguments,
+        result: result.map(|result| result_text(result, returns_address)),
+    }
+}
+
+fn argument<R>(kind: Arg, value: u64, args: &[u64; 6], result: Option<u64>, read: &R) -> String
+where
+    R: Fn(usize, usize) -> Option<Vec<u8>>,
+{
+    match kind {
+        Int => (value as i64).to_string(),
+        Hex => format!("{:#x}", value),
+        Mode => format!("{:#o}", value),
+        Fd if value as i32 == libc::AT_FDCWD => "AT_FDCWD".to_string(),
+        Fd => (value as i32).to_string(),
+        Path if value == 0 => "NULL".to_string(),
+        Path => match read_path(value as usize, read) {
+            Some(path) => quote(&path, false),
+            None => format!("{:#x}", value),
+        },
+        Input(len) => buffer(value, args[len] as usize, read),
+        Output => match result {
+            Some(result) if (result as i64) >= 0 => buffer(value, result as usize, read),
+            _ => format!("{:#x}", value),
+        },
+        OpenFlags => open_flags(value),
+        Prot => flag_names(value, PROT_FLAGS, "PROT_NONE"),
+        MapFlags => flag_names(value, MAP_FLAGS, "0"),
+    }
+}
+
+/// The contents of a buffer, quoted, up to BUFFER_LIMIT bytes of it. Just the address if the
+/// memory can't be read.
+fn buffer<R>(addr: u64, len: usize, read: &R) -> String
+where
+    R: Fn(usize, usize) -> Option<Vec<u8>>,
+{
+    match read(addr as usize, len.min(BUFFER_LIMIT)) {
+        Some(bytes) => quote(&bytes, len > BUFFER_LIMIT),
+        None => format!("{:#x}", addr),
+    }
+}
+
+/// Reads a NUL-terminated string a word at a time, so that it can end just before an unmapped
+/// page.
+fn read_path<R>(addr: usize, read: &R) -> Option<Vec<u8>>
+where
+    R: Fn(usize, usize) -> Option<Vec<u8>>,
+{
+    let mut path = Vec::new();
+    while path.len() < PATH_LIMIT {
+        let word = read(addr + path.len(), 8)?;
+        match word.iter().position(|&byte| byte == 0) {
+            Some(end) => {
+                path.extend_from_slice(&word[..end]);
+                return Some(path);
+            }
+            None => path.extend_from_slice(&word),
+        }
+    }
+    Some(path)
+}
+
+/// Bytes as a C string literal, with `...` after it if it was cut short.
+fn quote(bytes: &[u8], truncated: bool) -> String {
+    let mut text = String::from("\"");
+    for &byte in bytes {
+        match byte {
+            b'\n' => text.push_str("\\n"),
+            b'\t' => text.push_str("\\t"),
+            b'\r' => text.push_str("\\r"),
+            b'"' => text.push_str("\\\""),
+            b'\\' => text.push_str("\\\\"),
+            0x20..=0x7e => text.push(byte as char),
+            _ => text.push_str(&format!("\\x{:02x}", byte)),
+        }
+    }
+    text.push('"');
+    if truncated {
+        text.push_str("...");
+    }
+    text
+}
+
+/// A result as the number it is, or for an error, -1 and the errno's name and description.
+fn result_text(result: u64, returns_address: bool) -> String {
+    let value = result as i64;
+    if (-4095..0).contains(&value) {
+        let errno = Errno::from_i32(-value as i32);
+        format!("-1 {:?} ({})", errno, errno.desc())
+    } else if returns_address {
+        format!("{:#x}", result)
+    } else {
+        value.to_string()
+    }
+}
+
+const OPEN_FLAGS: &[(i32, &str)] = &[
+    (libc::O_CREAT, "O_CREAT"),
+    (libc::O_EXCL, "O_EXCL"),
+    (libc::O_NOCTTY, "O_NOCTTY"),
+    (libc::O_TRUNC, "O_TRUNC"),
+    (libc::O_APPEND, "O_APPEND"),
+    (libc::O_NONBLOCK, "O_NONBLOCK"),
+    (libc::O_DIRECTORY, "O_DIRECTORY"),
+    (libc::O_NOFOLLOW, "O_NOFOLLOW"),
+    (libc::O_CLOEXEC, "O_CLOEXEC"),
+];
+
+const PROT_FLAGS: &[(i32, &str)] = &[
+    (libc::PROT_READ, "PROT_READ"),
+    (libc::PROT_WRITE, "PROT_WRITE"),
+    (libc::PROT_EXEC, "PROT_EXEC"),
+];
+
+const MAP_FLAGS: &[(i32, &str)] = &[
+    (libc::MAP_SHARED, "MAP_SHARED"),
+    (libc::MAP_PRIVATE, "MAP_PRIVATE"),
+    (libc::MAP_FIXED, "MAP_FIXED"),
+    (libc::MAP_ANONYMOUS, "MAP_ANONYMOUS"),
+    (libc::MAP_DENYWRITE, "MAP_DENYWRITE"),
+    (libc::MAP_NORESERVE, "MAP_NORESERVE"),
+];
+
+/// The access mode (which isn't a bit of its own) and then the other flags.
+fn open_flags(value: u64) -> String {
+    let mode = match value as i32 & libc::O_ACCMODE {
+        libc::O_WRONLY => "O_WRONLY",
+        libc::O_RDWR => "O_RDWR",
+        _ => "O_RDONLY",
+    };
+    let rest = flag_names(value & !(libc::O_ACCMODE as u64), OPEN_FLAGS, "");
+    if rest.is_empty() {
+        mode.to_string()
+    } else {
+        format!("{}|{}", mode, rest)
+    }
+}
+
+/// Flags joined with `|`, any bits without a name in hex at the end, or `none` if no bits are
+/// set.
+fn flag_names(value: u64, names: &[(i32, &str)], none: &str) -> String {
+    let mut parts = Vec::new();
+    let mut rest = value;
+    for &(bit, name) in names {
+        if rest & bit as u64 != 0 {
+            parts.push(name.to_string());
+            rest &= !(bit as u64);
+        }
+    }
+    if rest != 0 {
+        parts.push(format!("{:#x}", rest));
+    }
+    if parts.is_empty() {
+        none.to_string()
+    } else {
+        parts.join("|")
+    }
+}
@@ -0,0 +1,66 @@
+//! Sharing the debugger's terminal with the inferior. The inferior runs in its own process group,
+//! so that ctrl+c at the prompt reaches only the debugger; but then it may only read from the
+//! terminal while its group is the terminal's foreground group. So the terminal is handed to it
+//! for as long as it runs, and taken back, with the debugger's own settings, when it stops.
+
+use nix::sys::termios::{tcgetattr, tcsetattr, SetArg, Termios};
+use nix::unistd::{getpgrp, isatty, tcgetpgrp, tcsetpgrp, Pid};
+use std::os::unix::io::RawFd;
+
+/// The terminal we share: the debugger's stdin.
+const TERMINAL: RawFd = libc::STDIN_FILENO;
+
+/// The terminal's size in rows and columns, or None if stdout isn't a terminal.
+pub fn size() -> Option<(usize, usize)> {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } < 0
+        || size.ws_row == 0
+    {
+        return None;
+    }
+    Some((size.ws_row as usize, size.ws_col as usize))
+}
+
+/// The terminal while the inferior has it: what to put back when it stops.
+pub struct Foreground {
+    debugger_group: Pid,
+    debugger_settings: Option<Termios>,
+}
+
+/// Makes `group` the terminal's foreground process group, first applying `settings` (the
+/// inferior's, from when it last had the terminal). Returns None, leaving the terminal alone, if
+/// stdin isn't a terminal or the debugger itself isn't in the foreground (it was started with
+/// `&`, say).
+pub fn give_to(group: Pid, settings: Option<&Termios>) -> Option<Foreground> {
+    if !isatty(TERMINAL).unwrap_or(false) {
+        return None;
+    }
+    let debugger_group = tcgetpgrp(TERMINAL).ok()?;
+    if debugger_group != getpgrp() {
+        return None;
+    }
+    let debugger_settings = tcgetattr(TERMINAL).ok();
+    if let Some(settings) = settings {
+        let _ = tcsetattr(TERMINAL, SetArg::TCSADRAIN, settings);
+    }
+    tcsetpgrp(TERMINAL, group).ok()?;
+    Some(Foreground {
+        debugger_group,
+        debugger_settings,
+    })
+}
+
+impl Foreground {
+    /// Takes the terminal back for the debugger and restores its settings, which the inferior
+    /// may have changed (a REPL turning off echo, say). Returns the inferior's settings, to give
+    /// back to it when it next runs. The debugger ignores SIGTTOU, which would otherwise stop it
+    /// for changing the terminal from a background group.
+    pub fn take_back(self) -> Option<Termios> {
+        let inferior_settings = tcgetattr(TERMINAL).ok();
+        let _ = tcsetpgrp(TERMINAL, self.debugger_group);
+        if let Some(settings) = &self.debugger_settings {
+            let _ = tcsetattr(TERMINAL, SetArg::TCSADRAIN, settings);
+        }
+        inferior_settings
+    }
+}
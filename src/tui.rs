@@ -0,0 +1,118 @@
+//! `tui enable`: a source pane across the top of the terminal, drawn with ANSI escapes, above a
+//! scrolling region where the prompt and command output carry on as before.
+
+use crate::terminal;
+use std::io::{self, Write};
+
+/// The fewest terminal rows the split layout is attempted in.
+pub const MIN_ROWS: usize = 12;
+
+/// What the source pane shows.
+pub struct SourceView<'a> {
+    /// Shown in the pane's bottom border
+    pub file: &'a str,
+    /// The lines in view, with their numbers
+    pub lines: &'a [(usize, String)],
+    /// The line the program is stopped at
+    pub current: usize,
+    /// Lines with a breakpoint on them
+    pub breakpoints: &'a [usize],
+}
+
+pub struct Tui {
+    /// The terminal's size, in rows and columns, when the layout was last set up
+    size: (usize, usize),
+}
+
+impl Tui {
+    /// Splits the terminal. None if stdout isn't a terminal or is too small to split.
+    pub fn enable() -> Option<Tui> {
+        let size = terminal::size().filter(|&(rows, _)| rows >= MIN_ROWS)?;
+        let tui = Tui { size };
+        tui.set_up_layout();
+        Some(tui)
+    }
+
+    /// Gives the whole terminal back to ordinary scrolling output.
+    pub fn disable(self) {
+        print!("\x1b[r\x1b[2J\x1b[H");
+        let _ = io::stdout().flush();
+    }
+
+    /// How many source lines fit in the pane.
+    pub fn pane_height(&self) -> usize {
+        self.size.0 / 2 - 1
+    }
+
+    /// Redraws the source pane, first laying the terminal out again if it was resized. With no
+    /// view, the pane says there is no source to show.
+    pub fn draw(&mut self, view: Option<&SourceView>) {
+        if let Some(size) = terminal::size().filter(|&(rows, _)| rows >= MIN_ROWS) {
+            if size != self.size {
+                self.size = size;
+                self.set_up_layout();
+            }
+        }
+        let (height, width) = (self.pane_height(), self.size.1);
+        // Save the cursor, which is somewhere in the command area
+        let mut screen = String::from("\x1b7");
+        for row in 0..height {
+            screen.push_str(&format!("\x1b[{};1H\x1b[2K", row + 1));
+            match view {
+                Some(view) => {
+                    if let Some((number, text)) = view.lines.get(row) {
+                        screen.push_str(&render_line(view, *number, text, width));
+                    }
+                }
+                None if row == height / 2 => {
+                    screen.push_str(&truncate("[ No source available ]", width))
+                }
+                None => {}
+            }
+        }
+        let title = view.map_or("", |view| view.file);
+        let border = truncate(&format!("-- {} ", title), width);
+        screen.push_str(&format!(
+            "\x1b[{};1H\x1b[2K\x1b[7m{:<width$}\x1b[0m",
+            height + 1,
+            border,
+            width = width
+        ));
+        screen.push_str("\x1b8");
+        print!("{}", screen);
+        let _ = io::stdout().flush();
+    }
+
+    /// Clears the screen and confines scrolling to the rows below the pane and its border, with
+    /// the cursor on the last row.
+    fn set_up_layout(&self) {
+        let rows = self.size.0;
+        print!("\x1b[2J\x1b[{};{}r\x1b[{};1H", self.pane_height() + 2, rows, rows);
+        let _ = io::stdout().flush();
+    }
+}
+
+/// One row of the source pane: a gutter marking breakpoints (`B`) and the current line (`>`),
+/// the line number, and the text, with the current line in reverse video.
+fn render_line(view: &SourceView, number: usize, text: &str, width: usize) -> String {
+    let breakpoint = if view.breakpoints.contains(&number) { 'B' } else { ' ' };
+    let current = number == view.current;
+    let row = format!(
+        "{}{} {:>4} {}",
+        breakpoint,
+        if current { '>' } else { ' ' },
+        number,
+        text.replace('\t', "    ")
+    );
+    let row = truncate(&row, width);
+    if current {
+        format!("\x1b[7m{:<width$}\x1b[0m", row, width = width)
+    } else {
+        row
+    }
+}
+
+/// Cuts text down to `width` characters, so that a long line can't wrap into the next row.
+fn truncate(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
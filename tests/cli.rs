@@ -0,0 +1,16 @@
+//! Runs the deet binary itself, as a script or an editor would.
+
+use std::process::Command;
+
+#[test]
+fn batch_run_passes_args_and_exits_with_the_programs_status() {
+    let target = format!("{}/samples/args", env!("CARGO_MANIFEST_DIR"));
+    let output = Command::new(env!("CARGO_BIN_EXE_deet"))
+        .args(&["--nx", "--batch", "-ex", "run", "--args", &target, "a", "b c"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("argv[1] = a\nargv[2] = b c\n"), "{}", stdout);
+    assert!(stdout.contains("Program exited with code 3"), "{}", stdout);
+    assert_eq!(output.status.code(), Some(3));
+}
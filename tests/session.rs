@@ -0,0 +1,567 @@
+//! Drives the programs in samples/ (built by `make`, and checked in) through the Session API.
+
+use deet::breakpoint::EnableMode;
+use deet::dwarf_data::DebugInfo;
+use deet::output::{render_text, Event};
+use deet::session::{Location, Session, StopEvent};
+use deet::skip::SkipTarget;
+
+/// Opens a Session on one of the samples.
+fn session(sample: &str) -> Session {
+    // As in main.rs: taking the terminal back from the inferior's process group raises SIGTTOU
+    unsafe {
+        libc::signal(libc::SIGTTOU, libc::SIG_IGN);
+    }
+    let path = format!("{}/samples/{}", env!("CARGO_MANIFEST_DIR"), sample);
+    Session::new(&path).expect("failed to load the sample's debug info")
+}
+
+fn function(name: &str) -> Location {
+    Location::Function(name.to_string())
+}
+
+fn stopped_at(stop: StopEvent) -> (Option<String>, Option<usize>) {
+    match stop {
+        StopEvent::Stopped { function, line, .. } => (function, line.map(|line| line.number)),
+        other => panic!("expected a stop, got {:?}", other),
+    }
+}
+
+fn at(function: &str, line: usize) -> (Option<String>, Option<usize>) {
+    (Some(function.to_string()), Some(line))
+}
+
+/// What `ptype` shows for an expression or type name.
+fn ptype(session: &Session, text: &str) -> String {
+    render_text(&Event::Type {
+        data: session.debug_data(),
+        type_id: session.type_of(text).unwrap(),
+        expand: true,
+    })
+}
+
+/// What `whatis` shows for an expression or type name.
+fn whatis(session: &Session, text: &str) -> String {
+    render_text(&Event::Type {
+        data: session.debug_data(),
+        type_id: session.whatis(text).unwrap(),
+        expand: false,
+    })
+}
+
+#[test]
+fn stops_at_a_breakpoint_and_runs_to_exit() {
+    let mut session = session("function_calls");
+    let id = session.set_breakpoint(function("func2"), false).unwrap();
+    match session.run(Vec::new()).unwrap() {
+        StopEvent::Stopped {
+            breakpoint,
+            function,
+            line,
+            ..
+        } => {
+            assert_eq!(breakpoint, Some(id));
+            assert_eq!(function.as_deref(), Some("func2"));
+            assert_eq!(line.map(|line| line.number), Some(10));
+        }
+        other => panic!("expected a stop in func2, got {:?}", other),
+    }
+    match session.cont().unwrap() {
+        StopEvent::Exited(0) => {}
+        other => panic!("expected the program to exit, got {:?}", other),
+    }
+}
+
+/// Checks that a session whose program has exited refuses the commands that need a process,
+/// rather than driving the reaped child.
+fn assert_no_process(session: &mut Session) {
+    assert!(!session.is_alive());
+    let not_running = |result: Result<(), deet::session::SessionError>| match result {
+        Err(deet::session::SessionError::NoProcess) => {}
+        other => panic!("expected NoProcess, got {:?}", other),
+    };
+    not_running(session.cont().map(drop));
+    not_running(session.backtrace().map(drop));
+    not_running(session.evaluate("global").map(drop));
+    not_running(session.registers().map(drop));
+    not_running(session.step_instruction().map(drop));
+    not_running(session.finish().map(drop));
+    // Killing what has already gone does nothing, more than once
+    session.kill();
+    session.kill();
+}
+
+#[test]
+fn commands_after_the_program_exits() {
+    let mut session = session("function_calls");
+    match session.run(Vec::new()).unwrap() {
+        StopEvent::Exited(0) => {}
+        other => panic!("expected the program to exit, got {:?}", other),
+    }
+    assert!(session.run_stats().is_some());
+    assert_no_process(&mut session);
+    // The program can be run again afterwards
+    session.set_breakpoint(function("func3"), false).unwrap();
+    match session.run(Vec::new()).unwrap() {
+        StopEvent::Stopped { function, .. } => assert_eq!(function.as_deref(), Some("func3")),
+        other => panic!("expected a stop in func3, got {:?}", other),
+    }
+    session.kill();
+}
+
+#[test]
+fn commands_after_recording_to_exit() {
+    let mut session = session("count");
+    session.set_breakpoint(function("main"), false).unwrap();
+    match session.run(Vec::new()).unwrap() {
+        StopEvent::Stopped { .. } => {}
+        other => panic!("expected a stop in main, got {:?}", other),
+    }
+    // Recording single-steps the rest of the program, exit included
+    session.start_recording();
+    match session.cont().unwrap() {
+        StopEvent::Exited(0) => {}
+        other => panic!("expected the program to exit, got {:?}", other),
+    }
+    assert_no_process(&mut session);
+}
+
+#[test]
+fn ignore_count_is_used_up_before_enable_count() {
+    let mut session = session("function_calls");
+    // func3 is called twice
+    let id = session.set_breakpoint(function("func3"), false).unwrap();
+    session.set_ignore_count(id, 1).unwrap();
+    session.enable_breakpoint(id, EnableMode::Count(1)).unwrap();
+    match session.run(Vec::new()).unwrap() {
+        StopEvent::Stopped { breakpoint, .. } => assert_eq!(breakpoint, Some(id)),
+        other => panic!("expected a stop in func3, got {:?}", other),
+    }
+    let bp = session.breakpoint(id).unwrap();
+    assert_eq!(bp.hits, 2);
+    assert!(!bp.enabled);
+    match session.cont().unwrap() {
+        StopEvent::Exited(0) => {}
+        other => panic!("expected the program to exit, got {:?}", other),
+    }
+}
+
+#[test]
+fn duplicate_breakpoints_leave_the_program_intact() {
+    let mut session = session("function_calls");
+    let id = session.set_breakpoint(function("func2"), false).unwrap();
+    match session.set_breakpoint(function("func2"), false) {
+        Err(deet::session::SessionError::DuplicateBreakpoint(existing, _)) => {
+            assert_eq!(existing, id)
+        }
+        other => panic!("expected a duplicate breakpoint, got {:?}", other),
+    }
+    // Run twice, the second time after a kill, so the breakpoint is installed again over the
+    // addresses it was taken out of
+    for _ in 0..2 {
+        match session.run(Vec::new()).unwrap() {
+            StopEvent::Stopped { breakpoint, .. } => assert_eq!(breakpoint, Some(id)),
+            other => panic!("expected a stop in func2, got {:?}", other),
+        }
+        session.kill();
+    }
+    session.run(Vec::new()).unwrap();
+    match session.cont().unwrap() {
+        StopEvent::Exited(0) => {}
+        other => panic!("expected the program to exit, got {:?}", other),
+    }
+}
+
+#[test]
+fn breakpoints_are_armed_again_on_restart() {
+    let mut session = session("function_calls");
+    let id = session.set_breakpoint(function("func2"), false).unwrap();
+    // Restarting while stopped at the breakpoint, without a kill in between
+    for hits in 1..=3 {
+        assert_eq!(stopped_at(session.run(Vec::new()).unwrap()), at("func2", 10));
+        assert_eq!(session.breakpoint(id).unwrap().hits, hits);
+    }
+    match session.cont().unwrap() {
+        StopEvent::Exited(0) => {}
+        other => panic!("expected the program to exit, got {:?}", other),
+    }
+    // And after the program has exited
+    assert_eq!(stopped_at(session.run(Vec::new()).unwrap()), at("func2", 10));
+    session.kill();
+}
+
+#[test]
+fn breakpoints_set_while_stopped_fire_after_a_restart() {
+    let mut session = session("function_calls");
+    let first = session.set_breakpoint(function("func1"), false).unwrap();
+    assert_eq!(stopped_at(session.run(Vec::new()).unwrap()), at("func1", 17));
+    // Set at the prompt, while the first inferior is stopped
+    let second = session.set_breakpoint(function("func3"), false).unwrap();
+    let stopped_by = |stop: StopEvent| match stop {
+        StopEvent::Stopped { breakpoint, .. } => breakpoint,
+        other => panic!("expected a breakpoint stop, got {:?}", other),
+    };
+    assert_eq!(stopped_by(session.run(Vec::new()).unwrap()), Some(first));
+    // func3 is called from func2 and then from func1
+    assert_eq!(stopped_by(session.cont().unwrap()), Some(second));
+    assert_eq!(stopped_by(session.cont().unwrap()), Some(second));
+    match session.cont().unwrap() {
+        StopEvent::Exited(0) => {}
+        other => panic!("expected the program to exit, got {:?}", other),
+    }
+    assert_eq!(session.breakpoint(first).unwrap().hits, 2);
+    assert_eq!(session.breakpoint(second).unwrap().hits, 2);
+}
+
+#[test]
+fn conditions_decide_which_hits_stop() {
+    let mut session = session("function_calls");
+    let id = session.set_breakpoint(function("func1"), false).unwrap();
+    session.set_condition(id, Some("a == 41".to_string())).unwrap();
+    match session.run(Vec::new()).unwrap() {
+        StopEvent::Exited(0) => {}
+        other => panic!("expected the program to exit, got {:?}", other),
+    }
+    // A hit where the condition is false doesn't count
+    assert_eq!(session.breakpoint(id).unwrap().hits, 0);
+    session.set_condition(id, Some("a == 42 && global == 5".to_string())).unwrap();
+    assert_eq!(stopped_at(session.run(Vec::new()).unwrap()), at("func1", 17));
+    assert_eq!(session.breakpoint(id).unwrap().hits, 1);
+    // Without a condition, every hit stops again
+    session.set_condition(id, None).unwrap();
+    assert_eq!(stopped_at(session.run(Vec::new()).unwrap()), at("func1", 17));
+    session.kill();
+    assert!(session.set_condition(deet::breakpoint::BreakpointId(9), None).is_err());
+}
+
+#[test]
+fn first_stop_after_run_is_the_users_breakpoint() {
+    let mut session = session("function_calls");
+    let func2 = session.set_breakpoint(function("func2"), false).unwrap();
+    let addr = session.breakpoint_addresses(func2)[0];
+    session.delete_breakpoint(func2).unwrap();
+    // Set by raw address, and numbered after the one deleted; the exec trap that starts the
+    // program isn't reported at all
+    let id = session.set_breakpoint(Location::Address(addr), false).unwrap();
+    assert_ne!(id, func2);
+    match session.run(Vec::new()).unwrap() {
+        StopEvent::Stopped {
+            breakpoint,
+            address,
+            ..
+        } => {
+            assert_eq!(breakpoint, Some(id));
+            assert_eq!(address, addr);
+        }
+        other => panic!("expected a stop at the breakpoint, got {:?}", other),
+    }
+    session.kill();
+}
+
+#[test]
+fn breakpoint_stops_report_the_breakpoint_line() {
+    let mut session = session("function_calls");
+    // Line 11 starts right after the call that ends line 10, so the stop address has to be the
+    // breakpoint's own rather than one byte either side of it
+    let id = session.set_breakpoint(Location::Line(None, 11), false).unwrap();
+    let addr = session.breakpoint_addresses(id)[0];
+    match session.run(Vec::new()).unwrap() {
+        StopEvent::Stopped {
+            breakpoint,
+            address,
+            line,
+            ..
+        } => {
+            assert_eq!(breakpoint, Some(id));
+            assert_eq!(address, addr);
+            assert_eq!(line.map(|line| line.number), Some(11));
+        }
+        other => panic!("expected a stop at line 11, got {:?}", other),
+    }
+    // The kernel leaves rip past the int3; the program itself is wound back to the breakpoint,
+    // so continuing runs line 11 from its start
+    assert_eq!(session.registers().unwrap().rip as usize, addr);
+    match session.cont().unwrap() {
+        StopEvent::Exited(0) => {}
+        other => panic!("expected the program to exit, got {:?}", other),
+    }
+}
+
+#[test]
+fn breakpoint_on_the_exiting_instruction() {
+    let mut session = session("exit");
+    session.set_breakpoint(function("main"), false).unwrap();
+    match session.run(Vec::new()).unwrap() {
+        StopEvent::Stopped { .. } => {}
+        other => panic!("expected a stop in main, got {:?}", other),
+    }
+    // main's exit syscall, the last instruction the program runs
+    let (start, end) = session.line_ranges(None, 4)[0];
+    let code = session.read_memory(start, end - start).unwrap();
+    let offset = code
+        .windows(2)
+        .position(|bytes| bytes == [0x0f, 0x05])
+        .expect("no syscall on line 4");
+    let id = session
+        .set_breakpoint(Location::Address(start + offset), false)
+        .unwrap();
+    match session.cont().unwrap() {
+        StopEvent::Stopped { breakpoint, .. } => assert_eq!(breakpoint, Some(id)),
+        other => panic!("expected a stop at the syscall, got {:?}", other),
+    }
+    // Stepping over the breakpoint exits
+    match session.cont().unwrap() {
+        StopEvent::Exited(0) => {}
+        other => panic!("expected the program to exit, got {:?}", other),
+    }
+    assert!(!session.is_alive());
+}
+
+#[test]
+fn deleting_the_breakpoint_stopped_at() {
+    let mut session = session("function_calls");
+    // func3 is called twice: the second call would stop again if the breakpoint were re-armed
+    let id = session.set_breakpoint(function("func3"), false).unwrap();
+    match session.run(Vec::new()).unwrap() {
+        StopEvent::Stopped { breakpoint, .. } => assert_eq!(breakpoint, Some(id)),
+        other => panic!("expected a stop in func3, got {:?}", other),
+    }
+    session.delete_breakpoint(id).unwrap();
+    match session.cont().unwrap() {
+        StopEvent::Exited(0) => {}
+        other => panic!("expected the program to exit, got {:?}", other),
+    }
+}
+
+/// The function and line a stop is in.
+#[test]
+fn step_goes_into_calls_and_next_goes_over_them() {
+    let mut session = session("function_calls");
+    session.set_breakpoint(function("func1"), false).unwrap();
+    assert_eq!(stopped_at(session.run(Vec::new()).unwrap()), at("func1", 17));
+    // printf has no line information, so step runs through it
+    assert_eq!(stopped_at(session.step().unwrap()), at("func1", 18));
+    assert_eq!(session.take_no_line_functions(), vec!["printf".to_string()]);
+    // Into func2, past its prologue
+    assert_eq!(stopped_at(session.step().unwrap()), at("func2", 10));
+    assert_eq!(stopped_at(session.next().unwrap()), at("func2", 11));
+    assert_eq!(stopped_at(session.next().unwrap()), at("func2", 12));
+    assert_eq!(stopped_at(session.next().unwrap()), at("func2", 13));
+    assert_eq!(stopped_at(session.step().unwrap()), at("func3", 6));
+    // printf is only mentioned the first time
+    assert_eq!(stopped_at(session.step().unwrap()), at("func3", 7));
+    assert!(session.take_no_line_functions().is_empty());
+    // Out of func3, back to the line that called it
+    assert_eq!(stopped_at(session.next().unwrap()), at("func2", 13));
+    session.kill();
+}
+
+#[test]
+fn step_goes_over_skipped_functions_and_files() {
+    let mut session = session("function_calls");
+    session.set_breakpoint(function("func1"), false).unwrap();
+    session.run(Vec::new()).unwrap();
+    assert_eq!(stopped_at(session.next().unwrap()), at("func1", 18));
+    session.skips.add(SkipTarget::Function("func?".to_string()));
+    assert_eq!(stopped_at(session.step().unwrap()), at("func1", 19));
+    assert!(session.skips.delete(1));
+    session.skips.add(SkipTarget::File("function_calls.c".to_string()));
+    assert_eq!(stopped_at(session.step().unwrap()), at("func1", 20));
+    session.kill();
+}
+
+#[test]
+fn step_into_code_without_line_information() {
+    let mut session = session("function_calls");
+    session.set_breakpoint(function("func1"), false).unwrap();
+    session.run(Vec::new()).unwrap();
+    // With step-into-no-debug, step stops in printf's PLT stub
+    session.step_into_no_debug = true;
+    assert_eq!(stopped_at(session.step().unwrap()).1, None);
+    assert!(session.take_no_line_functions().is_empty());
+    // Without it, step runs until printf returns, and on to func1's next line
+    session.step_into_no_debug = false;
+    assert_eq!(stopped_at(session.step().unwrap()), at("func1", 18));
+    assert_eq!(session.take_no_line_functions(), vec!["printf".to_string()]);
+    session.kill();
+}
+
+#[test]
+fn backtrace_through_recursive_calls() {
+    let mut session = session("recursion");
+    // Only the innermost call, factorial(1), returns from line 5
+    session.set_breakpoint(Location::Line(None, 5), false).unwrap();
+    assert_eq!(stopped_at(session.run(Vec::new()).unwrap()), at("factorial", 5));
+    let frames = session.backtrace().unwrap();
+    let functions: Vec<&str> = frames
+        .iter()
+        .map(|frame| frame.function.as_deref().unwrap())
+        .collect();
+    assert_eq!(functions, ["factorial", "factorial", "factorial", "factorial", "main"]);
+    let lines: Vec<usize> = frames
+        .iter()
+        .map(|frame| frame.line.as_ref().unwrap().number)
+        .collect();
+    assert_eq!(lines, [5, 7, 7, 7, 11]);
+    session.kill();
+}
+
+#[test]
+fn ptype_spells_out_the_types_of_globals() {
+    let session = session("types");
+    let point = "type = struct point {\n    \
+                 int x;  /* offset 0, size 4 */\n    \
+                 int y;  /* offset 4, size 4 */\n\
+                 }           /* size 8 */";
+    assert_eq!(ptype(&session, "origin"), point);
+    // A pointer to a struct is spelled out too, and a typedef is seen through
+    assert_eq!(ptype(&session, "cursor"), point.replace("}   ", "} * "));
+    assert_eq!(ptype(&session, "corner"), point);
+    assert_eq!(ptype(&session, "point_t"), point);
+    assert_eq!(ptype(&session, "scores"), "type = int [4]");
+    assert_eq!(ptype(&session, "elapsed"), "type = unsigned int");
+    let type_id = session.type_of("cursor").unwrap().unwrap();
+    assert_eq!(session.debug_data().type_name(type_id), "struct point *");
+}
+
+#[test]
+fn whatis_peels_one_typedef_and_does_not_expand() {
+    let session = session("types");
+    // An expression's declared type, as written
+    assert_eq!(whatis(&session, "elapsed"), "type = ticks_t");
+    assert_eq!(whatis(&session, "corner"), "type = point_t");
+    assert_eq!(whatis(&session, "origin"), "type = struct point");
+    assert_eq!(whatis(&session, "cursor"), "type = struct point *");
+    // A typedef name, one level down
+    assert_eq!(whatis(&session, "ticks_t"), "type = counter_t");
+    assert_eq!(whatis(&session, "counter_t"), "type = unsigned int");
+    assert_eq!(whatis(&session, "point_t"), "type = struct point");
+    // where ptype goes all the way and spells structs out
+    assert_eq!(ptype(&session, "ticks_t"), "type = unsigned int");
+    assert!(ptype(&session, "point_t").starts_with("type = struct point {\n"));
+}
+
+#[test]
+fn libc_is_listed_once_loaded() {
+    let mut session = session("function_calls");
+    session.set_breakpoint(function("main"), false).unwrap();
+    session.run(Vec::new()).unwrap();
+    let libc = session
+        .shared_libraries()
+        .iter()
+        .find(|library| {
+            let name = library.path.file_name().unwrap().to_string_lossy();
+            name.starts_with("libc.so") || name.starts_with("libc-")
+        })
+        .expect("libc isn't among the shared libraries");
+    assert!(!libc.code.is_empty());
+    for &(start, end) in &libc.code {
+        assert!(libc.base <= start && start < end);
+    }
+    session.kill();
+}
+
+#[test]
+fn breakpoints_on_main_with_and_without_pie() {
+    for &sample in ["function_calls", "pie"].iter() {
+        let mut session = session(sample);
+        let id = session.set_breakpoint(function("main"), false).unwrap();
+        // Until the program is loaded, a PIE's addresses are as linked
+        let resolved = session.breakpoint_addresses(id).to_vec();
+        assert_eq!(resolved.len(), 1, "{}", sample);
+        match session.run(Vec::new()).unwrap() {
+            StopEvent::Stopped {
+                breakpoint,
+                function,
+                ..
+            } => {
+                assert_eq!(breakpoint, Some(id), "{}", sample);
+                assert_eq!(function.as_deref(), Some("main"), "{}", sample);
+            }
+            other => panic!("{}: expected a stop at main, got {:?}", sample, other),
+        }
+        // then move to where it was loaded
+        let loaded = session.breakpoint_addresses(id);
+        assert_eq!(loaded.len(), 1, "{}", sample);
+        if sample == "pie" {
+            assert!(loaded[0] > resolved[0]);
+        } else {
+            assert_eq!(loaded, &resolved[..]);
+        }
+        session.kill();
+    }
+}
+
+#[test]
+fn dwarf_4_and_5_builds_agree() {
+    // Where each function's breakpoint goes, and a line in func2, as function, line and address
+    let lookups = |sample: &str| {
+        let session = session(sample);
+        let mut locations: Vec<Location> = ["main", "func1", "func2", "func3"]
+            .iter()
+            .map(|name| function(name))
+            .collect();
+        locations.push(Location::Line(None, 12));
+        locations
+            .iter()
+            .map(|location| {
+                let addr = session.resolve(location).unwrap();
+                let line = session.line_at(addr).unwrap();
+                assert!(line.file.ends_with("function_calls.c"), "{}", line.file);
+                (session.function_at(addr).unwrap(), line.number, addr)
+            })
+            .collect::<Vec<_>>()
+    };
+    let dwarf4 = lookups("function_calls_dwarf4");
+    let found: Vec<(&str, usize)> = dwarf4
+        .iter()
+        .map(|(function, line, _)| (function.as_str(), *line))
+        .collect();
+    assert_eq!(
+        found,
+        [("main", 24), ("func1", 17), ("func2", 10), ("func3", 6), ("func2", 12)]
+    );
+    assert_eq!(lookups("function_calls_dwarf5"), dwarf4);
+}
+
+#[test]
+fn compressed_debug_sections_are_read() {
+    let mut session = session("function_calls_gz");
+    assert_eq!(session.debug_data().debug_info(), DebugInfo::Dwarf);
+    let globals: Vec<&str> = session
+        .global_variables()
+        .iter()
+        .map(|(_, _, var, _)| var.name.as_str())
+        .collect();
+    assert_eq!(globals, ["global"]);
+    session.set_breakpoint(function("func2"), false).unwrap();
+    assert_eq!(stopped_at(session.run(Vec::new()).unwrap()), at("func2", 10));
+    session.kill();
+}
+
+#[test]
+fn fault_in_a_function_without_line_information() {
+    let mut session = session("no_line_info");
+    // store_zero is written in assembly: it's in the symbol table, but not the DWARF
+    match session.run(Vec::new()).unwrap() {
+        StopEvent::Stopped {
+            signal,
+            function,
+            line,
+            ..
+        } => {
+            assert_eq!(signal, nix::sys::signal::Signal::SIGSEGV);
+            assert_eq!(function.as_deref(), Some("store_zero"));
+            assert!(line.is_none());
+        }
+        other => panic!("expected a fault, got {:?}", other),
+    }
+    let frames: Vec<(Option<String>, Option<usize>)> = session
+        .backtrace()
+        .unwrap()
+        .into_iter()
+        .map(|frame| (frame.function, frame.line.map(|line| line.number)))
+        .collect();
+    assert_eq!(frames, [(Some("store_zero".to_string()), None), at("main", 19)]);
+    session.kill();
+}